@@ -501,6 +501,9 @@ mod tests {
             rpc_retry_backoff: 200,
             rpc_retry_cu: 1000,
             log_json: false,
+            admin_addr: None,
+            admin_token: None,
+            metrics_addr: None,
         }
     }
 
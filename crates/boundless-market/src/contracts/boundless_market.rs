@@ -24,7 +24,7 @@ use alloy::{
     network::Ethereum,
     primitives::{utils::format_ether, Address, Bytes, B256, U256},
     providers::{PendingTransactionBuilder, PendingTransactionError, Provider},
-    rpc::types::{Log, TransactionReceipt},
+    rpc::types::{Log, TransactionReceipt, TransactionRequest},
     signers::Signer,
 };
 
@@ -397,6 +397,19 @@ impl<P: Provider> BoundlessMarketService<P> {
         client_sig: impl Into<Bytes>,
         priority_gas: Option<u64>,
     ) -> Result<u64, MarketError> {
+        let receipt = self.lock_request_with_receipt(request, client_sig, priority_gas).await?;
+        Ok(receipt.block_number.context("TXN Receipt missing block number")?)
+    }
+
+    /// Lock the request to the prover, returning the receipt of the lock transaction.
+    ///
+    /// See [BoundlessMarketService::lock_request] for more details.
+    pub async fn lock_request_with_receipt(
+        &self,
+        request: &ProofRequest,
+        client_sig: impl Into<Bytes>,
+        priority_gas: Option<u64>,
+    ) -> Result<TransactionReceipt, MarketError> {
         tracing::trace!("Calling requestIsLocked({:x})", request.id);
         let is_locked_in: bool =
             self.instance.requestIsLocked(request.id).call().await.context("call failed")?;
@@ -444,7 +457,21 @@ impl<P: Provider> BoundlessMarketService<P> {
 
         self.check_stake_balance().await?;
 
-        Ok(receipt.block_number.context("TXN Receipt missing block number")?)
+        Ok(receipt)
+    }
+
+    /// Build the transaction sent by [BoundlessMarketService::lock_request], without sending it.
+    ///
+    /// Useful to callers that manage the nonce and fees of their transactions themselves.
+    pub fn lock_request_transaction(
+        &self,
+        request: &ProofRequest,
+        client_sig: impl Into<Bytes>,
+    ) -> TransactionRequest {
+        self.instance
+            .lockRequest(request.clone(), client_sig.into())
+            .from(self.caller)
+            .into_transaction_request()
     }
 
     /// Lock the request to the prover, giving them exclusive rights to be paid to
@@ -622,6 +649,122 @@ impl<P: Provider> BoundlessMarketService<P> {
         }
     }
 
+    /// Submits a `FulfillmentTx`, returning the receipt of the confirmed transaction.
+    ///
+    /// Sends the transaction built by [BoundlessMarketService::fulfillment_transaction]. See
+    /// [BoundlessMarketService::fulfill] for more details.
+    pub async fn fulfill_with_receipt(
+        &self,
+        tx: FulfillmentTx,
+    ) -> Result<TransactionReceipt, MarketError> {
+        let request = self.fulfillment_transaction(tx);
+        tracing::trace!("Sending fulfillment {request:?}");
+        let pending_tx = self
+            .instance
+            .provider()
+            .send_transaction(request)
+            .await
+            .map_err(|err| TxnErr::from(alloy::contract::Error::TransportError(err)))?;
+        tracing::debug!("Broadcasting tx {}", pending_tx.tx_hash());
+
+        let receipt = self.get_receipt_with_retry(pending_tx).await?;
+
+        tracing::info!("Submitted fulfillment {}", receipt.transaction_hash);
+
+        Ok(receipt)
+    }
+
+    /// Build the transaction sent by [BoundlessMarketService::fulfill], without sending it.
+    ///
+    /// Useful to callers that manage the nonce and fees of their transactions themselves.
+    pub fn fulfillment_transaction(&self, tx: FulfillmentTx) -> TransactionRequest {
+        let FulfillmentTx { root, unlocked_requests, fulfillments, assessor_receipt, withdraw } =
+            tx;
+        let (requests, client_sigs): (Vec<_>, Vec<_>) =
+            unlocked_requests.into_iter().map(|ur| (ur.request, ur.client_sig)).unzip();
+        let price = !requests.is_empty();
+        let instance = &self.instance;
+
+        let request = match root {
+            None => match (price, withdraw) {
+                (false, false) => {
+                    instance.fulfill(fulfillments, assessor_receipt).into_transaction_request()
+                }
+                (false, true) => instance
+                    .fulfillAndWithdraw(fulfillments, assessor_receipt)
+                    .into_transaction_request(),
+                (true, false) => instance
+                    .priceAndFulfill(requests, client_sigs, fulfillments, assessor_receipt)
+                    .into_transaction_request(),
+                (true, true) => instance
+                    .priceAndFulfillAndWithdraw(
+                        requests,
+                        client_sigs,
+                        fulfillments,
+                        assessor_receipt,
+                    )
+                    .into_transaction_request(),
+            },
+            Some(root) => match (price, withdraw) {
+                (false, false) => instance
+                    .submitRootAndFulfill(
+                        root.verifier_address,
+                        root.root,
+                        root.seal,
+                        fulfillments,
+                        assessor_receipt,
+                    )
+                    .into_transaction_request(),
+                (false, true) => instance
+                    .submitRootAndFulfillAndWithdraw(
+                        root.verifier_address,
+                        root.root,
+                        root.seal,
+                        fulfillments,
+                        assessor_receipt,
+                    )
+                    .into_transaction_request(),
+                (true, false) => instance
+                    .submitRootAndPriceAndFulfill(
+                        root.verifier_address,
+                        root.root,
+                        root.seal,
+                        requests,
+                        client_sigs,
+                        fulfillments,
+                        assessor_receipt,
+                    )
+                    .into_transaction_request(),
+                (true, true) => instance
+                    .submitRootAndPriceAndFulfillAndWithdraw(
+                        root.verifier_address,
+                        root.root,
+                        root.seal,
+                        requests,
+                        client_sigs,
+                        fulfillments,
+                        assessor_receipt,
+                    )
+                    .into_transaction_request(),
+            },
+        };
+        request.from(self.caller)
+    }
+
+    /// Simulate the transaction sent by [BoundlessMarketService::fulfill] with `eth_call`.
+    ///
+    /// Returns the decoded error if the fulfillment would revert.
+    pub async fn simulate_fulfill(&self, tx: FulfillmentTx) -> Result<(), MarketError> {
+        let request = self.fulfillment_transaction(tx);
+        tracing::trace!("Simulating fulfillment {request:?}");
+        self.instance
+            .provider()
+            .call(request)
+            .await
+            .map_err(|err| TxnErr::from(alloy::contract::Error::TransportError(err)))?;
+        Ok(())
+    }
+
     /// Fulfill a batch of requests by delivering the proof for each application.
     ///
     /// See [BoundlessMarketService::fulfill] for more details.
@@ -1404,6 +1547,20 @@ impl<P: Provider> BoundlessMarketService<P> {
         let decimals = contract.decimals().call().await.context("Failed to get token decimals")?;
         Ok(decimals)
     }
+
+    /// Returns the balance of the stake token held by the given account, outside of the market.
+    pub async fn stake_token_balance_of(
+        &self,
+        account: impl Into<Address>,
+    ) -> Result<U256, MarketError> {
+        let account = account.into();
+        let address = self.stake_token_address().await?;
+        let contract = IERC20::new(address, self.instance.provider());
+        tracing::trace!("Calling balanceOf({account}) on the stake token");
+        let balance =
+            contract.balanceOf(account).call().await.context("Failed to get token balance")?;
+        Ok(balance)
+    }
 }
 
 impl Offer {
@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use alloy_primitives::{B256, U256};
 use bytemuck::Pod;
 use risc0_zkvm::serde::to_vec;
 use risc0_zkvm::ExecutorEnv;
@@ -88,6 +89,32 @@ pub struct GuestEnv {
     /// be read. If the guest uses `env::read`, this should be encoded using the default RISC Zero
     /// codec. [GuestEnvBuilder::write] will encode the data given using the default codec.
     pub stdin: Vec<u8>,
+    /// Receipts the guest relies on through composition, e.g. `env::verify`.
+    ///
+    /// The prover resolves each of these to a receipt and adds it as an assumption when executing
+    /// and proving the guest.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assumptions: Vec<AssumptionSource>,
+}
+
+/// Location of a receipt to be added as an assumption when executing and proving a guest.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(test, derive(PartialEq, Eq))]
+#[non_exhaustive]
+pub enum AssumptionSource {
+    /// The fulfillment of a prior request on the Boundless Market.
+    ///
+    /// The request must have been fulfilled with an unaggregated proof (e.g. by requiring a
+    /// Groth16 proof), as receipts for claims included in an aggregated set cannot be used as
+    /// assumptions by the zkVM.
+    Fulfillment {
+        /// ID of the fulfilled request.
+        request_id: U256,
+        /// Image ID of the program that was proven for the request.
+        image_id: B256,
+    },
+    /// URL of a bincode encoded [risc0_zkvm::Receipt].
+    Url(String),
 }
 
 impl GuestEnv {
@@ -102,7 +129,7 @@ impl GuestEnv {
             return Err(Error::EmptyEncodedInput);
         }
         match Version::try_from(bytes[0])? {
-            Version::V0 => Ok(Self { stdin: bytes[1..].to_vec() }),
+            Version::V0 => Ok(Self::from_stdin(&bytes[1..])),
             Version::V1 => Ok(rmp_serde::from_read(&bytes[1..])?),
         }
     }
@@ -118,7 +145,7 @@ impl GuestEnv {
 
     /// Create a [GuestEnv] with `stdin` set to the contents of the given `bytes`.
     pub fn from_stdin(bytes: impl Into<Vec<u8>>) -> Self {
-        GuestEnv { stdin: bytes.into() }
+        GuestEnv { stdin: bytes.into(), assumptions: Vec::new() }
     }
 }
 
@@ -127,8 +154,10 @@ impl TryFrom<GuestEnv> for ExecutorEnv<'_> {
 
     /// Create an [ExecutorEnv], which can be used for execution and proving through the
     /// [risc0_zkvm] [Prover][risc0_zkvm::Prover] and [Executor][risc0_zkvm::Executor] traits, from
-    /// the given [GuestEnv].
+    /// the given [GuestEnv]. Fails if the [GuestEnv] has assumptions, which must be resolved to
+    /// receipts by the prover.
     fn try_from(env: GuestEnv) -> Result<Self, Self::Error> {
+        anyhow::ensure!(env.assumptions.is_empty(), "guest env assumptions must be resolved");
         ExecutorEnv::builder().write_slice(&env.stdin).build()
     }
 }
@@ -150,17 +179,21 @@ pub struct GuestEnvBuilder {
     ///
     /// See [GuestEnv::stdin]
     pub stdin: Vec<u8>,
+    /// Receipts the guest relies on through composition.
+    ///
+    /// See [GuestEnv::assumptions]
+    pub assumptions: Vec<AssumptionSource>,
 }
 
 impl GuestEnvBuilder {
     /// Create a new input builder.
     pub fn new() -> Self {
-        Self { stdin: Vec::new() }
+        Self { stdin: Vec::new(), assumptions: Vec::new() }
     }
 
     /// Build the [GuestEnv] for inclusion in a proof request.
     pub fn build_env(self) -> GuestEnv {
-        GuestEnv { stdin: self.stdin }
+        GuestEnv { stdin: self.stdin, assumptions: self.assumptions }
     }
 
     /// Build the and encode [GuestEnv] for inclusion in a proof request.
@@ -236,6 +269,15 @@ impl GuestEnvBuilder {
         input.extend_from_slice(payload);
         Self { stdin: input, ..self }
     }
+
+    /// Add an assumption, to be resolved by the prover.
+    ///
+    /// The guest can then verify the claim of the referenced receipt with `env::verify`.
+    pub fn with_assumption(self, assumption: AssumptionSource) -> Self {
+        let mut assumptions = self.assumptions;
+        assumptions.push(assumption);
+        Self { assumptions, ..self }
+    }
 }
 
 #[cfg(test)]
@@ -272,4 +314,24 @@ mod tests {
         assert_eq!(env, decoded_env);
         Ok(())
     }
+
+    #[test]
+    fn test_encode_decode_env_with_assumptions() -> Result<(), Error> {
+        let env = GuestEnv::builder()
+            .write_slice(&[1u8, 2, 3])
+            .with_assumption(AssumptionSource::Fulfillment {
+                request_id: U256::from(1),
+                image_id: B256::repeat_byte(0x41),
+            })
+            .with_assumption(AssumptionSource::Url("https://example.com/receipt".into()))
+            .build_env();
+
+        let decoded_env = GuestEnv::decode(&env.encode()?)?;
+        assert_eq!(env, decoded_env);
+
+        // Envs without assumptions keep the same encoding.
+        let env = GuestEnv::builder().write_slice(&[1u8, 2, 3]).build_env();
+        assert!(!String::from_utf8_lossy(&env.encode()?).contains("assumptions"));
+        Ok(())
+    }
 }
@@ -22,15 +22,63 @@ use alloy::{
     rpc::types::TransactionRequest,
     transports::{RpcError, TransportResult},
 };
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::sync::Arc;
 use tokio::sync::{Mutex, Semaphore};
 
+/// Nonces reserved by senders of transactions, by account.
+///
+/// Shared by a [NonceProvider] and the other senders of the same accounts on the same chain,
+/// e.g. a sender which replaces its transactions with an explicit nonce, so that a nonce reserved
+/// by one sender is skipped by the others.
+#[derive(Clone, Debug, Default)]
+pub struct ReservedNonces(Arc<std::sync::Mutex<HashMap<Address, BTreeSet<u64>>>>);
+
+impl ReservedNonces {
+    /// Reserve the lowest nonce of the account, starting from its pending nonce, that is not
+    /// reserved yet.
+    pub fn reserve(&self, account: Address, pending_nonce: u64) -> u64 {
+        let mut reserved = self.0.lock().unwrap();
+        let nonces = reserved.entry(account).or_default();
+        let mut nonce = pending_nonce;
+        while !nonces.insert(nonce) {
+            nonce += 1;
+        }
+        nonce
+    }
+
+    /// Release a nonce reserved with [ReservedNonces::reserve].
+    pub fn release(&self, account: Address, nonce: u64) {
+        if let Some(nonces) = self.0.lock().unwrap().get_mut(&account) {
+            nonces.remove(&nonce);
+        }
+    }
+
+    /// Returns whether any nonce of the account is reserved.
+    pub fn is_reserved(&self, account: Address) -> bool {
+        self.0.lock().unwrap().get(&account).is_some_and(|nonces| !nonces.is_empty())
+    }
+}
+
+/// Reservation of a nonce, released once the transaction using it is sent.
+struct Reservation<'a> {
+    reserved: &'a ReservedNonces,
+    account: Address,
+    nonce: u64,
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        self.reserved.release(self.account, self.nonce);
+    }
+}
+
 /// A provider that manages nonces per account using semaphores.
 ///
 /// This provider exists to avoid nonce collisions when submitting transactions concurrently.
 /// It does so by holding a semaphore permit between fetching the pending nonce of the signer until
-/// the transaction is sent.
+/// the transaction is sent. Nonces reserved by other senders through
+/// [NonceProvider::reserved_nonces] are skipped.
 #[derive(Clone, Debug)]
 pub struct NonceProvider<F, P>
 where
@@ -40,6 +88,7 @@ where
     inner: Arc<FillProvider<F, P, Ethereum>>,
     wallet: EthereumWallet,
     account_semaphores: Arc<Mutex<HashMap<Address, Arc<Semaphore>>>>,
+    reserved_nonces: ReservedNonces,
 }
 
 impl<F, P> NonceProvider<F, P>
@@ -53,9 +102,15 @@ where
             inner: Arc::new(inner),
             wallet,
             account_semaphores: Arc::new(Mutex::new(HashMap::new())),
+            reserved_nonces: ReservedNonces::default(),
         }
     }
 
+    /// Nonces skipped by this provider, to be reserved by other senders of its accounts.
+    pub fn reserved_nonces(&self) -> ReservedNonces {
+        self.reserved_nonces.clone()
+    }
+
     /// Get or create a semaphore for the given account address.
     async fn get_account_semaphore(&self, address: Address) -> Arc<Semaphore> {
         let mut semaphores = self.account_semaphores.lock().await;
@@ -88,16 +143,23 @@ where
         let semaphore = self.get_account_semaphore(from_address).await;
         let _permit = semaphore.acquire().await.unwrap();
 
-        // Fetch the pending nonce if not already set
-        if request.nonce.is_none() {
-            let pending_nonce = self.inner.get_transaction_count(from_address).pending().await?;
-            request.nonce = Some(pending_nonce);
-            tracing::trace!(
-                "NonceProvider::send_with_nonce_management - set nonce {} for address: {}",
-                pending_nonce,
-                from_address
-            );
-        }
+        // Fetch the pending nonce if not already set, skipping the nonces reserved by other
+        // senders. The nonce is reserved until the transaction is sent.
+        let _reservation = match request.nonce {
+            Some(_) => None,
+            None => {
+                let pending_nonce =
+                    self.inner.get_transaction_count(from_address).pending().await?;
+                let nonce = self.reserved_nonces.reserve(from_address, pending_nonce);
+                request.nonce = Some(nonce);
+                tracing::trace!(
+                    "NonceProvider::send_with_nonce_management - set nonce {} for address: {}",
+                    nonce,
+                    from_address
+                );
+                Some(Reservation { reserved: &self.reserved_nonces, account: from_address, nonce })
+            }
+        };
 
         let tx = self.inner.fill(request).await?;
 
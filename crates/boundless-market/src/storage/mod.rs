@@ -146,7 +146,8 @@ pub struct StorageProviderConfig {
     /// - For 's3', the following options are required:
     ///   --s3-access-key, --s3-secret-key, --s3-bucket, --s3-url, --aws-region
     /// - For 'pinata', the following option is required:
-    ///   --pinata-jwt (optionally, you can specify --pinata-api-url, --ipfs-gateway-url)
+    ///   --pinata-jwt (optionally, you can specify --pinata-api-url, --ipfs-gateway-url,
+    ///   --pinata-ipfs-urls)
     /// - For 'file', no additional options are required (optionally, you can specify --file-path)    
     #[arg(long, env, value_enum, default_value = "none", default_value_ifs = [
         ("s3_access_key", ArgPredicate::IsPresent, "s3"),
@@ -195,6 +196,10 @@ pub struct StorageProviderConfig {
     #[arg(long, env, requires("pinata_jwt"))]
     #[builder(setter(strip_option), default)]
     pub ipfs_gateway_url: Option<Url>,
+    /// Return `ipfs://` URLs for Pinata uploads instead of URLs on the IPFS gateway
+    #[arg(long, env, requires("pinata_jwt"))]
+    #[builder(default)]
+    pub pinata_ipfs_urls: bool,
 
     // **File Storage Provider Options**
     /// Path for file storage provider
@@ -222,6 +227,7 @@ impl StorageProviderConfig {
             pinata_jwt: None,
             pinata_api_url: None,
             ipfs_gateway_url: None,
+            pinata_ipfs_urls: false,
             file_path: None,
         }
     }
@@ -256,7 +262,8 @@ impl StorageProvider for StandardStorageProvider {
 ///
 /// If the environment variable `RISC0_DEV_MODE` is set, a temporary file storage provider is used.
 /// Otherwise, the following environment variables are checked in order:
-/// - `PINATA_JWT`, `PINATA_API_URL`, `IPFS_GATEWAY_URL`: Pinata storage provider;
+/// - `PINATA_JWT`, `PINATA_API_URL`, `IPFS_GATEWAY_URL`, `PINATA_IPFS_URLS`: Pinata storage
+///   provider;
 /// - `S3_ACCESS`, `S3_SECRET`, `S3_BUCKET`, `S3_URL`, `AWS_REGION`: S3 storage provider.
 pub fn storage_provider_from_env() -> Result<StandardStorageProvider, StandardStorageProviderError>
 {
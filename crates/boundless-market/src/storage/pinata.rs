@@ -33,6 +33,7 @@ pub struct PinataStorageProvider {
     pinata_jwt: String,
     pinata_api_url: Url,
     ipfs_gateway_url: Url,
+    ipfs_urls: bool,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -88,10 +89,26 @@ impl PinataStorageProvider {
             Err(e) => return Err(e.into()),
         };
         let gateway_url = Url::parse(&gateway_url_str)?;
+        // Parsed like the `--pinata-ipfs-urls` flag when set from the environment, so that
+        // e.g. `PINATA_IPFS_URLS=false` disables it.
+        let ipfs_urls = match std::env::var("PINATA_IPFS_URLS") {
+            Ok(value) => !matches!(
+                value.trim().to_lowercase().as_str(),
+                "" | "0" | "false" | "no" | "off" | "n" | "f"
+            ),
+            Err(VarError::NotPresent) => false,
+            Err(e) => return Err(e.into()),
+        };
 
         let client = reqwest::Client::new();
 
-        Ok(Self { pinata_jwt: jwt, pinata_api_url: api_url, ipfs_gateway_url: gateway_url, client })
+        Ok(Self {
+            pinata_jwt: jwt,
+            pinata_api_url: api_url,
+            ipfs_gateway_url: gateway_url,
+            ipfs_urls,
+            client,
+        })
     }
 
     /// Creates a new Pinata storage provider from the given parts.
@@ -104,7 +121,20 @@ impl PinataStorageProvider {
         let gateway_url = Url::parse(&gateway_url)?;
         let client = reqwest::Client::new();
 
-        Ok(Self { pinata_jwt: jwt, pinata_api_url: api_url, ipfs_gateway_url: gateway_url, client })
+        Ok(Self {
+            pinata_jwt: jwt,
+            pinata_api_url: api_url,
+            ipfs_gateway_url: gateway_url,
+            ipfs_urls: false,
+            client,
+        })
+    }
+
+    /// Sets whether uploads return `ipfs://` URLs instead of URLs on the IPFS gateway.
+    ///
+    /// `ipfs://` URLs do not depend on a single gateway, but require provers to support them.
+    pub fn with_ipfs_urls(self, ipfs_urls: bool) -> Self {
+        Self { ipfs_urls, ..self }
     }
 
     /// Creates a new Pinata storage provider from the given configuration.
@@ -122,6 +152,7 @@ impl PinataStorageProvider {
                 .ipfs_gateway_url
                 .clone()
                 .unwrap_or(Url::parse(DEFAULT_GATEWAY_URL)?),
+            ipfs_urls: config.pinata_ipfs_urls,
             client: reqwest::Client::new(),
         })
     }
@@ -170,7 +201,11 @@ impl PinataStorageProvider {
             .as_str()
             .ok_or(anyhow!("response from Pinata contains an invalid IPFS hash"))?;
 
-        let data_url = self.ipfs_gateway_url.join(&format!("ipfs/{ipfs_hash}"))?;
+        let data_url = if self.ipfs_urls {
+            Url::parse(&format!("ipfs://{ipfs_hash}"))?
+        } else {
+            self.ipfs_gateway_url.join(&format!("ipfs/{ipfs_hash}"))?
+        };
         Ok(data_url)
     }
 }
@@ -52,8 +52,8 @@ use risc0_zkvm::{
 pub use guest_assessor::{ASSESSOR_GUEST_ELF, ASSESSOR_GUEST_ID, ASSESSOR_GUEST_PATH};
 pub use guest_set_builder::{SET_BUILDER_ELF, SET_BUILDER_ID, SET_BUILDER_PATH};
 pub use guest_util::{
-    ECHO_ELF, ECHO_ID, ECHO_PATH, IDENTITY_ELF, IDENTITY_ID, IDENTITY_PATH, LOOP_ELF, LOOP_ID,
-    LOOP_PATH,
+    COMPOSE_ELF, COMPOSE_ID, COMPOSE_PATH, ECHO_ELF, ECHO_ID, ECHO_PATH, IDENTITY_ELF, IDENTITY_ID,
+    IDENTITY_PATH, LOOP_ELF, LOOP_ID, LOOP_PATH,
 };
 
 /// Re-export of the boundless_market crate, which can be used to avoid dependency issues when
@@ -84,6 +84,28 @@ pub async fn deploy_verifier_router<P: Provider>(
     Ok(*instance.address())
 }
 
+/// Route the seals with the given selector to a verifier.
+pub async fn add_verifier_route<P: Provider>(
+    deployer_provider: P,
+    verifier_router: Address,
+    selector: [u8; 4],
+    verifier: Address,
+) -> Result<()> {
+    let router_instance = RiscZeroVerifierRouter::RiscZeroVerifierRouterInstance::new(
+        verifier_router,
+        deployer_provider,
+    );
+    router_instance
+        .addVerifier(selector.into(), verifier)
+        .send()
+        .await
+        .context("failed to send addVerifier")?
+        .watch()
+        .await
+        .context("failed to confirm addVerifier")?;
+    Ok(())
+}
+
 pub async fn deploy_groth16_verifier<P: Provider>(
     deployer_provider: P,
     control_root: B256,
@@ -237,23 +259,13 @@ pub async fn deploy_contracts(
     let set_verifier =
         deploy_set_verifier(&deployer_provider, verifier, set_builder_id, set_builder_url).await?;
 
-    let router_instance = RiscZeroVerifierRouter::RiscZeroVerifierRouterInstance::new(
-        verifier_router,
-        deployer_provider.clone(),
-    );
-
-    let call = &router_instance
-        .addVerifier(groth16_selector.into(), verifier)
-        .from(deployer_signer.address());
-    let _ = call.send().await?;
+    add_verifier_route(&deployer_provider, verifier_router, groth16_selector, verifier).await?;
 
     let verifier_parameters_digest =
         SetInclusionReceiptVerifierParameters { image_id: set_builder_id }.digest();
     let set_verifier_selector: [u8; 4] = verifier_parameters_digest.as_bytes()[..4].try_into()?;
-    let call = &router_instance
-        .addVerifier(set_verifier_selector.into(), set_verifier)
-        .from(deployer_signer.address());
-    let _ = call.send().await?;
+    add_verifier_route(&deployer_provider, verifier_router, set_verifier_selector, set_verifier)
+        .await?;
 
     let hit_points = deploy_hit_points(deployer_address, &deployer_provider).await?;
     let boundless_market = deploy_boundless_market(
@@ -0,0 +1,12 @@
+-- Batches created before the broker served multiple chains belong to its only chain, which is
+-- taken from the batch's first order or else from any stored order. Without any stored order a
+-- batch cannot hold orders either, so those batches are dropped.
+UPDATE batches
+SET data = json_set(data, '$.chain_id', COALESCE(
+    (SELECT orders.data->>'chain_id' FROM orders WHERE orders.id = batches.data->'orders'->>0),
+    (SELECT orders.data->>'chain_id' FROM orders LIMIT 1)
+))
+WHERE data->>'chain_id' IS NULL
+    AND EXISTS (SELECT 1 FROM orders);
+
+DELETE FROM batches WHERE data->>'chain_id' IS NULL;
@@ -0,0 +1,7 @@
+CREATE TABLE shadow_decisions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    order_id TEXT NOT NULL,
+    data JSONB,
+    created_at INTEGER NOT NULL
+);
+CREATE INDEX shadow_decisions_created_at ON shadow_decisions (created_at);
@@ -0,0 +1,7 @@
+CREATE TABLE lock_bids (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    order_id TEXT NOT NULL,
+    data JSONB,
+    created_at INTEGER NOT NULL
+);
+CREATE INDEX lock_bids_created_at ON lock_bids (created_at);
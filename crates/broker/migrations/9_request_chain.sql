@@ -0,0 +1,51 @@
+-- Key the locked and fulfilled requests by chain, as request IDs are only unique per market.
+-- Rows recorded before the broker served multiple chains belong to its only chain, which is
+-- taken from the matching order or else from any stored order. Rows whose chain is unknown are
+-- dropped, the market monitor records them again on the next lock or fulfillment event.
+CREATE TABLE fulfilled_requests_new (
+    chain_id INTEGER NOT NULL,
+    id TEXT NOT NULL,
+    block_number INTEGER,
+    PRIMARY KEY (chain_id, id)
+);
+
+INSERT INTO fulfilled_requests_new (chain_id, id, block_number)
+SELECT chain_id, id, block_number FROM (
+    SELECT
+        COALESCE(
+            (SELECT data->>'chain_id' FROM orders WHERE orders.id LIKE fulfilled_requests.id || '-%' LIMIT 1),
+            (SELECT data->>'chain_id' FROM orders LIMIT 1)
+        ) AS chain_id,
+        id,
+        block_number
+    FROM fulfilled_requests
+)
+WHERE chain_id IS NOT NULL;
+
+DROP TABLE fulfilled_requests;
+ALTER TABLE fulfilled_requests_new RENAME TO fulfilled_requests;
+
+CREATE TABLE locked_requests_new (
+    chain_id INTEGER NOT NULL,
+    id TEXT NOT NULL,
+    locker TEXT,
+    block_number INTEGER,
+    PRIMARY KEY (chain_id, id)
+);
+
+INSERT INTO locked_requests_new (chain_id, id, locker, block_number)
+SELECT chain_id, id, locker, block_number FROM (
+    SELECT
+        COALESCE(
+            (SELECT data->>'chain_id' FROM orders WHERE orders.id LIKE locked_requests.id || '-%' LIMIT 1),
+            (SELECT data->>'chain_id' FROM orders LIMIT 1)
+        ) AS chain_id,
+        id,
+        locker,
+        block_number
+    FROM locked_requests
+)
+WHERE chain_id IS NOT NULL;
+
+DROP TABLE locked_requests;
+ALTER TABLE locked_requests_new RENAME TO locked_requests;
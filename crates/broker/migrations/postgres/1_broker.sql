@@ -0,0 +1,14 @@
+CREATE TABLE orders (
+    id TEXT PRIMARY KEY,
+    data JSONB
+);
+
+CREATE TABLE batches (
+    id BIGSERIAL PRIMARY KEY,
+    data JSONB
+);
+
+CREATE TABLE last_block (
+    id INTEGER PRIMARY KEY,
+    block TEXT
+);
@@ -0,0 +1,7 @@
+CREATE TABLE shadow_decisions (
+    id BIGSERIAL PRIMARY KEY,
+    order_id TEXT NOT NULL,
+    data JSONB,
+    created_at BIGINT NOT NULL
+);
+CREATE INDEX shadow_decisions_created_at ON shadow_decisions (created_at);
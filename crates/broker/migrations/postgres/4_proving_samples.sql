@@ -0,0 +1,7 @@
+CREATE TABLE proving_samples (
+    id BIGSERIAL PRIMARY KEY,
+    order_id TEXT NOT NULL,
+    data JSONB,
+    created_at BIGINT NOT NULL
+);
+CREATE INDEX proving_samples_created_at ON proving_samples (created_at);
@@ -0,0 +1,7 @@
+CREATE TABLE lock_bids (
+    id BIGSERIAL PRIMARY KEY,
+    order_id TEXT NOT NULL,
+    data JSONB,
+    created_at BIGINT NOT NULL
+);
+CREATE INDEX lock_bids_created_at ON lock_bids (created_at);
@@ -0,0 +1,8 @@
+CREATE TABLE preflight_cache (
+    image_id TEXT NOT NULL,
+    input_digest TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+    data JSONB,
+    PRIMARY KEY (image_id, input_digest)
+);
+CREATE INDEX preflight_cache_created_at ON preflight_cache (created_at);
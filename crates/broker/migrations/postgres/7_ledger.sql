@@ -0,0 +1,11 @@
+CREATE TABLE ledger (
+    id BIGSERIAL PRIMARY KEY,
+    order_id TEXT NOT NULL,
+    chain_id BIGINT NOT NULL,
+    kind TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+    data JSONB
+);
+CREATE INDEX ledger_order_id ON ledger (order_id);
+CREATE INDEX ledger_chain_id ON ledger (chain_id);
+CREATE INDEX ledger_created_at ON ledger (created_at);
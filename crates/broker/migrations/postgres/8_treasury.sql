@@ -0,0 +1,9 @@
+CREATE TABLE treasury_actions (
+    id BIGSERIAL PRIMARY KEY,
+    chain_id BIGINT NOT NULL,
+    kind TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+    pending BOOLEAN NOT NULL DEFAULT FALSE,
+    data JSONB
+);
+CREATE INDEX treasury_actions_created_at ON treasury_actions (created_at);
@@ -0,0 +1,27 @@
+-- Key the locked and fulfilled requests by chain, as request IDs are only unique per market.
+-- Rows recorded before the broker served multiple chains belong to its only chain, which is
+-- taken from the matching order or else from any stored order. Rows whose chain is unknown are
+-- dropped, the market monitor records them again on the next lock or fulfillment event.
+ALTER TABLE fulfilled_requests ADD COLUMN chain_id BIGINT;
+
+UPDATE fulfilled_requests SET chain_id = COALESCE(
+    (SELECT (data->>'chain_id')::BIGINT FROM orders WHERE orders.id LIKE fulfilled_requests.id || '-%' LIMIT 1),
+    (SELECT (data->>'chain_id')::BIGINT FROM orders LIMIT 1)
+);
+
+DELETE FROM fulfilled_requests WHERE chain_id IS NULL;
+ALTER TABLE fulfilled_requests ALTER COLUMN chain_id SET NOT NULL;
+ALTER TABLE fulfilled_requests DROP CONSTRAINT fulfilled_requests_pkey;
+ALTER TABLE fulfilled_requests ADD PRIMARY KEY (chain_id, id);
+
+ALTER TABLE locked_requests ADD COLUMN chain_id BIGINT;
+
+UPDATE locked_requests SET chain_id = COALESCE(
+    (SELECT (data->>'chain_id')::BIGINT FROM orders WHERE orders.id LIKE locked_requests.id || '-%' LIMIT 1),
+    (SELECT (data->>'chain_id')::BIGINT FROM orders LIMIT 1)
+);
+
+DELETE FROM locked_requests WHERE chain_id IS NULL;
+ALTER TABLE locked_requests ALTER COLUMN chain_id SET NOT NULL;
+ALTER TABLE locked_requests DROP CONSTRAINT locked_requests_pkey;
+ALTER TABLE locked_requests ADD PRIMARY KEY (chain_id, id);
@@ -0,0 +1,826 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Local admin HTTP API for a running broker.
+//!
+//! Read endpoints expose the committed orders, batches, chain state and balances. The per-chain
+//! endpoints take a `chain_id` query parameter, defaulting to the first chain served. Write
+//! endpoints require the configured admin token to be sent as a bearer token.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
+
+use alloy::{
+    network::Ethereum,
+    primitives::{Address, U256},
+    providers::{Provider, WalletProvider},
+};
+use anyhow::Context;
+use axum::{
+    extract::{Path, Query, Request, State},
+    http::{header::AUTHORIZATION, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use boundless_market::contracts::boundless_market::BoundlessMarketService;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    chain_monitor::ChainMonitorService,
+    db::{DbError, DbObj},
+    errors::{impl_coded_debug, CodedError},
+    order_monitor::SharedCapacity,
+    shadow::ShadowReport,
+    task::{RetryRes, RetryTask, SupervisorErr},
+    Batch, Order, SkipReason,
+};
+
+const COMMITTED_ORDERS_PATH: &str = "/api/v1/orders/committed";
+const SKIP_ORDER_PATH: &str = "/api/v1/orders/{order_id}/skip";
+const SKIPPED_ORDERS_PATH: &str = "/api/v1/orders/skipped";
+const CURRENT_BATCH_PATH: &str = "/api/v1/batches/current";
+const BATCH_PATH: &str = "/api/v1/batches/{batch_id}";
+const FLUSH_BATCH_PATH: &str = "/api/v1/batches/flush";
+const CHAIN_PATH: &str = "/api/v1/chain";
+const BALANCES_PATH: &str = "/api/v1/balances";
+const PICKING_PATH: &str = "/api/v1/picking";
+const PAUSE_PICKING_PATH: &str = "/api/v1/picking/pause";
+const RESUME_PICKING_PATH: &str = "/api/v1/picking/resume";
+const SHADOW_REPORT_PATH: &str = "/api/v1/shadow/report";
+const DRAIN_PATH: &str = "/api/v1/drain";
+const START_DRAIN_PATH: &str = "/api/v1/drain/start";
+
+/// How long an order marked to be skipped is remembered if the broker never sees it
+const SKIPPED_ORDER_TTL: std::time::Duration = std::time::Duration::from_secs(24 * 60 * 60);
+
+/// Runtime controls shared between the admin API and the broker services.
+#[derive(Clone, Default)]
+pub(crate) struct BrokerControl {
+    picking_paused: Arc<AtomicBool>,
+    /// Orders marked to be skipped, by the time they were marked
+    skipped_orders: Arc<Mutex<HashMap<String, Instant>>>,
+    flush_batch: Arc<AtomicBool>,
+    /// Cancelled once the broker starts draining, before shutting down
+    drain: CancellationToken,
+    /// Number of order monitor iterations which may still lock or commit to orders
+    commits_in_flight: Arc<AtomicUsize>,
+    /// Proving capacity shared by the order monitors of all chains
+    capacity: SharedCapacity,
+    /// Run without sending lock or fulfillment transactions, see [crate::shadow]
+    shadow: bool,
+}
+
+/// Held while the order monitor may lock or commit to orders, see [BrokerControl::start_commit]
+pub(crate) struct CommitGuard(Arc<AtomicUsize>);
+
+impl Drop for CommitGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl BrokerControl {
+    pub(crate) fn new(shadow: bool) -> Self {
+        Self { shadow, ..Default::default() }
+    }
+
+    pub(crate) fn is_shadow(&self) -> bool {
+        self.shadow
+    }
+
+    pub(crate) fn set_picking_paused(&self, paused: bool) {
+        self.picking_paused.store(paused, Ordering::SeqCst);
+    }
+
+    pub(crate) fn is_picking_paused(&self) -> bool {
+        self.picking_paused.load(Ordering::SeqCst)
+    }
+
+    /// Mark an order ID to be skipped by the order picker and order monitor.
+    ///
+    /// Marks older than [SKIPPED_ORDER_TTL] are dropped, so that orders which are never seen by
+    /// the broker are not remembered forever.
+    pub(crate) fn skip_order(&self, order_id: &str) {
+        let now = Instant::now();
+        let mut skipped = self.skipped_orders.lock().unwrap();
+        skipped.retain(|_, marked_at| now.duration_since(*marked_at) < SKIPPED_ORDER_TTL);
+        skipped.insert(order_id.to_string(), now);
+    }
+
+    /// Returns whether the order was marked to be skipped, removing the mark as the caller skips
+    /// the order.
+    pub(crate) fn take_skipped_order(&self, order_id: &str) -> bool {
+        self.skipped_orders.lock().unwrap().remove(order_id).is_some()
+    }
+
+    /// Request the aggregator to finalize the current batch on its next iteration.
+    pub(crate) fn request_batch_flush(&self) {
+        self.flush_batch.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns true, and clears the request, if a batch flush was requested.
+    pub(crate) fn take_batch_flush(&self) -> bool {
+        self.flush_batch.swap(false, Ordering::SeqCst)
+    }
+
+    /// Stop picking and committing to new orders, ahead of shutting down the broker.
+    pub(crate) fn start_drain(&self) {
+        self.drain.cancel();
+    }
+
+    pub(crate) fn is_draining(&self) -> bool {
+        self.drain.is_cancelled()
+    }
+
+    /// Resolves once the broker starts draining.
+    pub(crate) async fn draining(&self) {
+        self.drain.cancelled().await
+    }
+
+    /// Returns a guard to hold while locking or committing to orders, or `None` if the broker is
+    /// draining.
+    ///
+    /// The count is raised before checking for a drain, so once [Self::commits_in_flight] reads
+    /// zero while draining, no order can be committed to anymore.
+    pub(crate) fn start_commit(&self) -> Option<CommitGuard> {
+        self.commits_in_flight.fetch_add(1, Ordering::SeqCst);
+        let guard = CommitGuard(self.commits_in_flight.clone());
+        if self.is_draining() {
+            return None;
+        }
+        Some(guard)
+    }
+
+    pub(crate) fn commits_in_flight(&self) -> usize {
+        self.commits_in_flight.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn capacity(&self) -> &SharedCapacity {
+        &self.capacity
+    }
+}
+
+#[derive(Error)]
+pub enum AdminErr {
+    #[error("{code} Failed to bind admin API to {0}: {1}", code = self.code())]
+    BindErr(SocketAddr, std::io::Error),
+
+    #[error("{code} Admin API server error: {0}", code = self.code())]
+    ServerErr(std::io::Error),
+}
+
+impl_coded_debug!(AdminErr);
+
+impl CodedError for AdminErr {
+    fn code(&self) -> &str {
+        match self {
+            AdminErr::BindErr(..) => "[B-ADM-001]",
+            AdminErr::ServerErr(_) => "[B-ADM-500]",
+        }
+    }
+}
+
+/// Error returned by the admin API handlers
+#[derive(Error, Debug)]
+enum ApiError {
+    #[error("unauthorized")]
+    Unauthorized,
+
+    #[error("not found: {0}")]
+    NotFound(String),
+
+    #[error("internal error")]
+    InternalErr(anyhow::Error),
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(err: anyhow::Error) -> Self {
+        Self::InternalErr(err)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ErrMsg {
+    r#type: String,
+    msg: String,
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (code, type_str) = match self {
+            Self::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized"),
+            Self::NotFound(_) => (StatusCode::NOT_FOUND, "NotFound"),
+            Self::InternalErr(_) => (StatusCode::INTERNAL_SERVER_ERROR, "InternalErr"),
+        };
+        tracing::warn!("admin api error, code {code}: {self:?}");
+
+        (code, Json(ErrMsg { r#type: type_str.into(), msg: self.to_string() })).into_response()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct BatchRes {
+    batch_id: usize,
+    batch: Batch,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ChainRes {
+    chain_id: u64,
+    block_number: u64,
+    block_timestamp: u64,
+    gas_price: u128,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct BalancesRes {
+    chain_id: u64,
+    address: Address,
+    /// Gas token balance of the signer account
+    gas_balance: U256,
+    /// Stake token balance deposited to the market
+    stake_balance: U256,
+    /// Gas token balance deposited to the market
+    market_balance: U256,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct PickingRes {
+    paused: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct DrainRes {
+    draining: bool,
+    /// Order monitor iterations which may still lock or commit to orders
+    commits_in_flight: usize,
+    /// Orders committed to, which are yet to be submitted
+    committed_orders: usize,
+}
+
+/// Default window of the skipped orders counts, in seconds
+const DEFAULT_SKIP_WINDOW_SECS: u32 = 3600;
+
+#[derive(Deserialize, Debug)]
+struct SkippedOrdersQuery {
+    /// Length of the window ending now, in seconds
+    window_secs: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct SkippedOrdersRes {
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    /// Number of orders skipped in the window, by reason
+    counts: BTreeMap<SkipReason, u64>,
+}
+
+/// Default window of the shadow report, in seconds
+const DEFAULT_SHADOW_WINDOW_SECS: u32 = 24 * 60 * 60;
+
+/// Max number of decisions, the most recent in the window, the shadow report is built from
+const MAX_SHADOW_REPORT_DECISIONS: usize = 10_000;
+
+#[derive(Deserialize, Debug)]
+struct ShadowReportQuery {
+    /// Length of the window ending now, in seconds
+    window_secs: Option<u32>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChainQuery {
+    /// Chain to report on, the first chain served by the broker if unset
+    chain_id: Option<u64>,
+}
+
+/// A chain served by the broker, as reported by the admin API
+struct AdminChain<P> {
+    provider: Arc<P>,
+    chain_monitor: Arc<ChainMonitorService<P>>,
+    market: BoundlessMarketService<Arc<P>>,
+}
+
+struct AdminState<P> {
+    db: DbObj,
+    /// Chains by chain ID, along with the chain reported when no chain ID is requested
+    chains: BTreeMap<u64, AdminChain<P>>,
+    default_chain_id: u64,
+    control: BrokerControl,
+    admin_token: Option<String>,
+}
+
+impl<P> AdminState<P> {
+    fn chain(&self, query: &ChainQuery) -> Result<(u64, &AdminChain<P>), ApiError> {
+        let chain_id = query.chain_id.unwrap_or(self.default_chain_id);
+        let chain = self
+            .chains
+            .get(&chain_id)
+            .ok_or_else(|| ApiError::NotFound(format!("chain {chain_id}")))?;
+        Ok((chain_id, chain))
+    }
+}
+
+#[derive(Clone)]
+pub struct AdminService<P> {
+    addr: SocketAddr,
+    state: Arc<AdminState<P>>,
+}
+
+impl<P> AdminService<P>
+where
+    P: Provider<Ethereum> + WalletProvider + 'static + Clone,
+{
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        addr: SocketAddr,
+        admin_token: Option<String>,
+        db: DbObj,
+        provider: Arc<P>,
+        chain_id: u64,
+        chain_monitor: Arc<ChainMonitorService<P>>,
+        market_addr: Address,
+        control: BrokerControl,
+    ) -> Self {
+        if admin_token.is_none() {
+            tracing::warn!("No admin token configured, admin API write endpoints are disabled");
+        }
+        let state = Arc::new(AdminState {
+            db,
+            chains: BTreeMap::new(),
+            default_chain_id: chain_id,
+            control,
+            admin_token,
+        });
+        Self { addr, state }.with_chain(chain_id, provider, chain_monitor, market_addr)
+    }
+
+    /// Report on an additional chain, selected with the `chain_id` query parameter
+    pub(crate) fn with_chain(
+        mut self,
+        chain_id: u64,
+        provider: Arc<P>,
+        chain_monitor: Arc<ChainMonitorService<P>>,
+        market_addr: Address,
+    ) -> Self {
+        let market = BoundlessMarketService::new(
+            market_addr,
+            provider.clone(),
+            provider.default_signer_address(),
+        );
+        let state = Arc::get_mut(&mut self.state)
+            .expect("Chains are added before the admin service is served");
+        state.chains.insert(chain_id, AdminChain { provider, chain_monitor, market });
+        self
+    }
+
+    fn app(&self) -> Router {
+        let writes = Router::new()
+            .route(PAUSE_PICKING_PATH, post(pause_picking))
+            .route(RESUME_PICKING_PATH, post(resume_picking))
+            .route(SKIP_ORDER_PATH, post(skip_order))
+            .route(FLUSH_BATCH_PATH, post(flush_batch))
+            .route(START_DRAIN_PATH, post(start_drain))
+            .route_layer(middleware::from_fn_with_state(self.state.clone(), require_token));
+
+        Router::new()
+            .route(COMMITTED_ORDERS_PATH, get(committed_orders))
+            .route(SKIPPED_ORDERS_PATH, get(skipped_orders))
+            .route(CURRENT_BATCH_PATH, get(current_batch))
+            .route(BATCH_PATH, get(batch))
+            .route(CHAIN_PATH, get(chain))
+            .route(BALANCES_PATH, get(balances))
+            .route(PICKING_PATH, get(picking))
+            .route(SHADOW_REPORT_PATH, get(shadow_report))
+            .route(DRAIN_PATH, get(drain))
+            .merge(writes)
+            .with_state(self.state.clone())
+    }
+}
+
+impl<P> RetryTask for AdminService<P>
+where
+    P: Provider<Ethereum> + WalletProvider + 'static + Clone,
+{
+    type Error = AdminErr;
+    fn spawn(&self, cancel_token: CancellationToken) -> RetryRes<Self::Error> {
+        let service = self.clone();
+
+        Box::pin(async move {
+            let listener = tokio::net::TcpListener::bind(service.addr)
+                .await
+                .map_err(|err| SupervisorErr::Fault(AdminErr::BindErr(service.addr, err)))?;
+            tracing::info!("Admin API listening on: {}", service.addr);
+
+            axum::serve(listener, service.app())
+                .with_graceful_shutdown(async move { cancel_token.cancelled().await })
+                .await
+                .map_err(|err| SupervisorErr::Recover(AdminErr::ServerErr(err)))?;
+
+            tracing::debug!("Admin API shut down");
+            Ok(())
+        })
+    }
+}
+
+/// Compare two tokens without short circuiting on the first mismatched byte
+fn tokens_match(expected: &str, provided: &str) -> bool {
+    expected.len() == provided.len()
+        && expected.bytes().zip(provided.bytes()).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+async fn require_token<P>(
+    State(state): State<Arc<AdminState<P>>>,
+    req: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let Some(expected) = state.admin_token.as_deref() else {
+        return Err(ApiError::Unauthorized);
+    };
+    let authorized = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|val| val.to_str().ok())
+        .and_then(|val| val.strip_prefix("Bearer "))
+        .is_some_and(|provided| tokens_match(expected, provided));
+
+    if !authorized {
+        return Err(ApiError::Unauthorized);
+    }
+    Ok(next.run(req).await)
+}
+
+/// Returns all orders the broker has committed to prove
+async fn committed_orders<P>(
+    State(state): State<Arc<AdminState<P>>>,
+) -> Result<Json<Vec<Order>>, ApiError> {
+    let orders = state.db.get_committed_orders().await.context("Failed to query DB")?;
+    Ok(Json(orders))
+}
+
+/// Returns the number of orders skipped in a recent window, by reason
+async fn skipped_orders<P>(
+    State(state): State<Arc<AdminState<P>>>,
+    Query(query): Query<SkippedOrdersQuery>,
+) -> Result<Json<SkippedOrdersRes>, ApiError> {
+    let to = Utc::now();
+    let window_secs = query.window_secs.unwrap_or(DEFAULT_SKIP_WINDOW_SECS);
+    let from = to - Duration::seconds(window_secs.into());
+    let counts = state.db.get_skip_counts(from, to).await.context("Failed to query DB")?;
+    Ok(Json(SkippedOrdersRes { from, to, counts }))
+}
+
+/// Returns the batch of a chain currently being aggregated
+async fn current_batch<P>(
+    State(state): State<Arc<AdminState<P>>>,
+    Query(query): Query<ChainQuery>,
+) -> Result<Json<BatchRes>, ApiError> {
+    let (chain_id, _) = state.chain(&query)?;
+    let batch_id =
+        state.db.get_current_batch(chain_id).await.context("Failed to get current batch")?;
+    let batch = state.db.get_batch(batch_id).await.context("Failed to get batch")?;
+    Ok(Json(BatchRes { batch_id, batch }))
+}
+
+/// Returns a batch by ID
+async fn batch<P>(
+    State(state): State<Arc<AdminState<P>>>,
+    Path(batch_id): Path<usize>,
+) -> Result<Json<BatchRes>, ApiError> {
+    match state.db.get_batch(batch_id).await {
+        Ok(batch) => Ok(Json(BatchRes { batch_id, batch })),
+        Err(DbError::BatchNotFound(id)) => Err(ApiError::NotFound(format!("batch {id}"))),
+        Err(err) => Err(ApiError::InternalErr(err.into())),
+    }
+}
+
+/// Returns the current head and gas price of a chain
+async fn chain<P>(
+    State(state): State<Arc<AdminState<P>>>,
+    Query(query): Query<ChainQuery>,
+) -> Result<Json<ChainRes>, ApiError>
+where
+    P: Provider<Ethereum>,
+{
+    let (chain_id, chain) = state.chain(&query)?;
+    let head = chain.chain_monitor.current_chain_head().await?;
+    let gas_price = chain.chain_monitor.current_gas_price().await?;
+    Ok(Json(ChainRes {
+        chain_id,
+        block_number: head.block_number,
+        block_timestamp: head.block_timestamp,
+        gas_price,
+    }))
+}
+
+/// Returns the balances of the broker signer account on a chain
+async fn balances<P>(
+    State(state): State<Arc<AdminState<P>>>,
+    Query(query): Query<ChainQuery>,
+) -> Result<Json<BalancesRes>, ApiError>
+where
+    P: Provider<Ethereum> + WalletProvider,
+{
+    let (chain_id, chain) = state.chain(&query)?;
+    let address = chain.provider.default_signer_address();
+    let gas_balance =
+        chain.provider.get_balance(address).await.context("Failed to get gas balance")?;
+    let stake_balance =
+        chain.market.balance_of_stake(address).await.context("Failed to get stake balance")?;
+    let market_balance =
+        chain.market.balance_of(address).await.context("Failed to get market balance")?;
+    Ok(Json(BalancesRes { chain_id, address, gas_balance, stake_balance, market_balance }))
+}
+
+/// Returns whether order picking is paused
+async fn picking<P>(State(state): State<Arc<AdminState<P>>>) -> Json<PickingRes> {
+    Json(PickingRes { paused: state.control.is_picking_paused() })
+}
+
+/// Returns the progress of draining the broker
+async fn drain<P>(State(state): State<Arc<AdminState<P>>>) -> Result<Json<DrainRes>, ApiError> {
+    let committed_orders = state.db.get_committed_orders().await.context("Failed to query DB")?;
+    Ok(Json(DrainRes {
+        draining: state.control.is_draining(),
+        commits_in_flight: state.control.commits_in_flight(),
+        committed_orders: committed_orders.len(),
+    }))
+}
+
+/// Returns the report of the decisions made in shadow mode within the window ending now
+async fn shadow_report<P>(
+    State(state): State<Arc<AdminState<P>>>,
+    Query(query): Query<ShadowReportQuery>,
+) -> Result<Json<ShadowReport>, ApiError> {
+    if !state.control.is_shadow() {
+        return Err(ApiError::NotFound("shadow report, broker is not in shadow mode".into()));
+    }
+    let window_secs = query.window_secs.unwrap_or(DEFAULT_SHADOW_WINDOW_SECS);
+    let from = Utc::now() - Duration::seconds(window_secs.into());
+    let decisions = state
+        .db
+        .get_shadow_decisions(from, MAX_SHADOW_REPORT_DECISIONS)
+        .await
+        .context("Failed to get shadow decisions")?;
+    Ok(Json(ShadowReport::new(decisions)))
+}
+
+async fn pause_picking<P>(State(state): State<Arc<AdminState<P>>>) -> Json<PickingRes> {
+    tracing::info!("Order picking paused via admin API");
+    state.control.set_picking_paused(true);
+    Json(PickingRes { paused: true })
+}
+
+async fn resume_picking<P>(State(state): State<Arc<AdminState<P>>>) -> Json<PickingRes> {
+    tracing::info!("Order picking resumed via admin API");
+    state.control.set_picking_paused(false);
+    Json(PickingRes { paused: false })
+}
+
+/// Skip an order that has not yet been locked or started proving
+async fn skip_order<P>(
+    State(state): State<Arc<AdminState<P>>>,
+    Path(order_id): Path<String>,
+) -> StatusCode {
+    tracing::info!("Order {order_id} marked to be skipped via admin API");
+    state.control.skip_order(&order_id);
+    StatusCode::ACCEPTED
+}
+
+/// Finalize the current aggregation batch on the next aggregator iteration
+async fn flush_batch<P>(State(state): State<Arc<AdminState<P>>>) -> StatusCode {
+    tracing::info!("Batch flush requested via admin API");
+    state.control.request_batch_flush();
+    StatusCode::ACCEPTED
+}
+
+/// Drain the broker, which shuts down once all committed orders are submitted
+async fn start_drain<P>(State(state): State<Arc<AdminState<P>>>) -> StatusCode {
+    tracing::info!("Drain requested via admin API");
+    state.control.start_drain();
+    StatusCode::ACCEPTED
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::SqliteDb;
+    use alloy::{
+        network::EthereumWallet, node_bindings::Anvil, providers::ProviderBuilder,
+        signers::local::PrivateKeySigner,
+    };
+    use reqwest::Client;
+
+    const TOKEN: &str = "test-token";
+
+    async fn spawn_admin() -> (String, BrokerControl, alloy::node_bindings::AnvilInstance) {
+        let anvil = Anvil::new().spawn();
+        let signer: PrivateKeySigner = anvil.keys()[0].clone().into();
+        let provider = Arc::new(
+            ProviderBuilder::new()
+                .wallet(EthereumWallet::from(signer))
+                .connect(&anvil.endpoint())
+                .await
+                .unwrap(),
+        );
+        let db: DbObj = Arc::new(SqliteDb::new("sqlite::memory:").await.unwrap());
+        let chain_monitor = Arc::new(ChainMonitorService::new(provider.clone()).await.unwrap());
+        tokio::spawn(chain_monitor.spawn(Default::default()));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let control = BrokerControl::default();
+        // The same node reported as a second chain
+        let service = AdminService::new(
+            addr,
+            Some(TOKEN.into()),
+            db,
+            provider.clone(),
+            anvil.chain_id(),
+            chain_monitor.clone(),
+            Address::ZERO,
+            control.clone(),
+        )
+        .with_chain(anvil.chain_id() + 1, provider, chain_monitor, Address::ZERO);
+        let app = service.app();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        (format!("http://{addr}"), control, anvil)
+    }
+
+    #[tokio::test]
+    async fn read_endpoints() {
+        let (url, _control, anvil) = spawn_admin().await;
+        let client = Client::new();
+
+        let res = client.get(format!("{url}{COMMITTED_ORDERS_PATH}")).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let orders: Vec<serde_json::Value> = res.json().await.unwrap();
+        assert!(orders.is_empty());
+
+        let res =
+            client.get(format!("{url}{SKIPPED_ORDERS_PATH}?window_secs=60")).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let skipped: SkippedOrdersRes = res.json().await.unwrap();
+        assert!(skipped.counts.is_empty());
+        assert_eq!((skipped.to - skipped.from).num_seconds(), 60);
+
+        let res = client.get(format!("{url}{CURRENT_BATCH_PATH}")).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let batch: serde_json::Value = res.json().await.unwrap();
+        let batch_id = batch["batch_id"].as_u64().unwrap();
+
+        let res = client.get(format!("{url}/api/v1/batches/{batch_id}")).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res =
+            client.get(format!("{url}/api/v1/batches/{}", batch_id + 10)).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let res = client.get(format!("{url}{CHAIN_PATH}")).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let chain: ChainRes = res.json().await.unwrap();
+        assert_eq!(chain.chain_id, anvil.chain_id());
+        assert!(chain.gas_price > 0);
+
+        // Each chain has its own current batch
+        let other_chain_id = anvil.chain_id() + 1;
+        let res = client
+            .get(format!("{url}{CURRENT_BATCH_PATH}?chain_id={other_chain_id}"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let other_batch: serde_json::Value = res.json().await.unwrap();
+        assert_ne!(other_batch["batch_id"].as_u64().unwrap(), batch_id);
+
+        let res = client
+            .get(format!("{url}{CHAIN_PATH}?chain_id={other_chain_id}"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let chain: ChainRes = res.json().await.unwrap();
+        assert_eq!(chain.chain_id, other_chain_id);
+
+        let res = client
+            .get(format!("{url}{CHAIN_PATH}?chain_id={}", other_chain_id + 1))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let res = client.get(format!("{url}{DRAIN_PATH}")).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let drain: DrainRes = res.json().await.unwrap();
+        assert!(!drain.draining);
+        assert_eq!(drain.committed_orders, 0);
+
+        // Only served in shadow mode
+        let res = client.get(format!("{url}{SHADOW_REPORT_PATH}")).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn write_endpoints_require_token() {
+        let (url, control, _anvil) = spawn_admin().await;
+        let client = Client::new();
+
+        let res = client.post(format!("{url}{PAUSE_PICKING_PATH}")).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let res = client
+            .post(format!("{url}{PAUSE_PICKING_PATH}"))
+            .bearer_auth("wrong-token")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert!(!control.is_picking_paused());
+
+        let res = client
+            .post(format!("{url}{PAUSE_PICKING_PATH}"))
+            .bearer_auth(TOKEN)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(control.is_picking_paused());
+
+        let res = client
+            .post(format!("{url}{RESUME_PICKING_PATH}"))
+            .bearer_auth(TOKEN)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(!control.is_picking_paused());
+
+        let res = client
+            .post(format!("{url}/api/v1/orders/0x1-abc-LockAndFulfill/skip"))
+            .bearer_auth(TOKEN)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        assert!(control.take_skipped_order("0x1-abc-LockAndFulfill"));
+        assert!(!control.take_skipped_order("0x1-abc-LockAndFulfill"));
+
+        let res = client
+            .post(format!("{url}{FLUSH_BATCH_PATH}"))
+            .bearer_auth(TOKEN)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        assert!(control.take_batch_flush());
+        assert!(!control.take_batch_flush());
+
+        let res = client.post(format!("{url}{START_DRAIN_PATH}")).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert!(!control.is_draining());
+        let res = client
+            .post(format!("{url}{START_DRAIN_PATH}"))
+            .bearer_auth(TOKEN)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        assert!(control.is_draining());
+    }
+
+    #[test]
+    fn commits_stop_when_draining() {
+        let control = BrokerControl::default();
+        let guard = control.start_commit().unwrap();
+        assert_eq!(control.commits_in_flight(), 1);
+
+        control.start_drain();
+        assert!(control.start_commit().is_none());
+        assert_eq!(control.commits_in_flight(), 1);
+
+        drop(guard);
+        assert_eq!(control.commits_in_flight(), 0);
+    }
+}
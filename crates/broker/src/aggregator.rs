@@ -20,18 +20,19 @@ use chrono::Utc;
 use risc0_aggregation::GuestState;
 use risc0_zkvm::{
     sha::{Digest, Digestible},
-    ReceiptClaim,
+    Assumptions, ReceiptClaim,
 };
 
 use crate::{
+    admin::BrokerControl,
     config::ConfigLock,
     db::{AggregationOrder, DbObj},
     errors::CodedError,
     futures_retry::retry,
     impl_coded_debug, now_timestamp,
-    provers::{self, ProverObj},
+    provers::{self, ProverError, ProverObj},
     task::{RetryRes, RetryTask, SupervisorErr},
-    AggregationState, Batch, BatchStatus,
+    AggregationState, Batch, BatchStatus, OrderStatus,
 };
 use thiserror::Error;
 use tokio_util::sync::CancellationToken;
@@ -55,6 +56,17 @@ impl CodedError for AggregatorErr {
     }
 }
 
+/// Pending orders whose proofs can be added to the current batch
+#[derive(Default)]
+struct PendingProofs {
+    proofs: Vec<AggregationOrder>,
+    /// Claims of the proofs to add to the set, in the same order as `proofs`
+    claims: Vec<ReceiptClaim>,
+    groth16_proofs: Vec<AggregationOrder>,
+    /// Combined size of the journals of all pending orders
+    journal_size: usize,
+}
+
 #[derive(Clone)]
 pub struct AggregatorService {
     db: DbObj,
@@ -65,6 +77,7 @@ pub struct AggregatorService {
     market_addr: Address,
     prover_addr: Address,
     chain_id: u64,
+    control: BrokerControl,
 }
 
 impl AggregatorService {
@@ -78,6 +91,7 @@ impl AggregatorService {
         prover_addr: Address,
         config: ConfigLock,
         prover: ProverObj,
+        control: BrokerControl,
     ) -> Result<Self> {
         Ok(Self {
             db,
@@ -88,32 +102,57 @@ impl AggregatorService {
             market_addr,
             prover_addr,
             chain_id,
+            control,
         })
     }
 
+    /// Get the claim of a proof to add to the set, or the reason the proof cannot be aggregated
+    ///
+    /// Errors are returned only for failures to reach the prover, which may be transient.
+    async fn get_claim(&self, proof_id: &str) -> Result<Result<ReceiptClaim, &'static str>> {
+        let receipt = not_found_as_none(self.prover.get_receipt(proof_id).await)
+            .with_context(|| format!("Failed to get proof receipt for {proof_id}"))?;
+        let Some(receipt) = receipt else {
+            return Ok(Err("Proof receipt missing"));
+        };
+        let Ok(Ok(claim)) = receipt.claim().map(|claim| claim.value()) else {
+            return Ok(Err("Proof receipt claim missing or pruned"));
+        };
+        // Composed proofs must have their assumptions resolved by the prover, otherwise the
+        // claim would not match the unconditional claim verified for the request onchain.
+        if !is_unconditional(&claim) {
+            return Ok(Err("Proof receipt has unresolved assumptions"));
+        }
+
+        Ok(Ok(claim))
+    }
+
+    /// Get the journal of a proof, or the reason the proof cannot be aggregated
+    async fn get_journal(&self, proof_id: &str) -> Result<Result<Vec<u8>, &'static str>> {
+        let journal = not_found_as_none(self.prover.get_journal(proof_id).await)
+            .with_context(|| format!("Failed to get journal for {proof_id}"))?;
+
+        Ok(journal.ok_or("Proof journal missing"))
+    }
+
+    /// Mark an order whose proof cannot be aggregated as failed
+    async fn fail_order(&self, order_id: &str, reason: &'static str) {
+        tracing::warn!(
+            "[B-AGG-601] Order {order_id} cannot be aggregated: {reason}, marking as failed"
+        );
+
+        if let Err(err) = self.db.set_order_failure(order_id, reason).await {
+            tracing::error!("Failed to set order {order_id} as failed during aggregation: {err}");
+        }
+    }
+
     async fn prove_set_builder(
         &self,
         aggregation_state: Option<&AggregationState>,
         proofs: &[String],
+        claims: Vec<ReceiptClaim>,
         finalize: bool,
     ) -> Result<AggregationState> {
-        // TODO(#268): Handle failure to get an individual order.
-        let mut claims = Vec::<ReceiptClaim>::with_capacity(proofs.len());
-        for proof_id in proofs {
-            let receipt = self
-                .prover
-                .get_receipt(proof_id)
-                .await
-                .with_context(|| format!("Failed to get proof receipt for {proof_id}"))?
-                .with_context(|| format!("Proof receipt not found for {proof_id}"))?;
-            let claim = receipt
-                .claim()
-                .with_context(|| format!("Receipt for {proof_id} missing claim"))?
-                .value()
-                .with_context(|| format!("Receipt for {proof_id} claims pruned"))?;
-            claims.push(claim);
-        }
-
         let input = aggregation_state
             .map_or(GuestState::initial(self.set_builder_guest_id), |s| s.guest_state.clone())
             .into_input(claims.clone(), finalize)
@@ -182,90 +221,120 @@ impl AggregatorService {
     }
 
     async fn prove_assessor(&self, order_ids: &[String]) -> Result<String> {
-        let mut fills = vec![];
-        let mut assumptions = vec![];
+        prove_assessor(
+            &self.db,
+            &self.prover,
+            self.assessor_guest_id,
+            self.market_addr,
+            self.chain_id,
+            self.prover_addr,
+            order_ids,
+        )
+        .await
+    }
 
-        for order_id in order_ids {
+    /// Check the orders already included in a batch, returning the combined size of their
+    /// journals.
+    ///
+    /// Orders whose journals can no longer be retrieved are marked as failed. As their claims are
+    /// already part of the set builder state, the batch is then reset and its remaining orders
+    /// are returned to the pending orders, so the batch is rebuilt from a clean [GuestState]. In
+    /// that case, `None` is returned.
+    async fn check_batch_orders(
+        &self,
+        batch_id: usize,
+        batch: &Batch,
+    ) -> Result<Option<usize>, AggregatorErr> {
+        let mut journal_size = 0;
+        let mut failed_orders = vec![];
+        for order_id in &batch.orders {
             let order = self
                 .db
                 .get_order(order_id)
                 .await
-                .with_context(|| format!("Failed to get DB order ID {order_id}"))?
-                .with_context(|| format!("order ID {order_id} missing from DB"))?;
-
-            let proof_id = order
-                .proof_id
-                .with_context(|| format!("Missing proof_id for order: {order_id}"))?;
-
-            assumptions.push(proof_id.clone());
+                .with_context(|| format!("Failed to get order {order_id}"))?
+                .with_context(|| format!("Order {order_id} missing from DB"))?;
 
-            let journal = self
-                .prover
-                .get_journal(&proof_id)
-                .await
-                .with_context(|| format!("Failed to get {proof_id} journal"))?
-                .with_context(|| format!("{proof_id} journal missing"))?;
-
-            fills.push(Fulfillment {
-                request: order.request.clone(),
-                signature: order.client_sig.clone().to_vec(),
-                journal,
-            })
+            let journal = match order.proof_id {
+                Some(proof_id) => self.get_journal(&proof_id).await?,
+                None => Err("Proof ID missing"),
+            };
+            match journal {
+                Ok(journal) => journal_size += journal.len(),
+                Err(reason) => {
+                    self.fail_order(order_id, reason).await;
+                    failed_orders.push(order_id);
+                }
+            }
         }
 
-        let order_count = fills.len();
-        let input = AssessorInput {
-            fills,
-            domain: eip712_domain(self.market_addr, self.chain_id),
-            prover_address: self.prover_addr,
-        };
-        let stdin = GuestEnv::builder().write_frame(&input.encode()).stdin;
-
-        let input_id =
-            self.prover.upload_input(stdin).await.context("Failed to upload assessor input")?;
-
-        let proof_res = self
-            .prover
-            .prove_and_monitor_stark(&self.assessor_guest_id.to_string(), &input_id, assumptions)
-            .await
-            .context("Failed to prove assesor stark")?;
-
-        tracing::debug!(
-            "Assessor proof completed, proof id: {} count: {} cycles: {} time: {}",
-            proof_res.id,
-            order_count,
-            proof_res.stats.total_cycles,
-            proof_res.elapsed_time
-        );
-
-        Ok(proof_res.id)
-    }
+        if failed_orders.is_empty() {
+            return Ok(Some(journal_size));
+        }
 
-    /// Get the sum of the size of the journals for proofs in a batch
-    async fn get_combined_journal_size(&self, order_ids: &[String]) -> Result<usize> {
-        let mut journal_size = 0;
-        for order_id in order_ids {
+        let mut requeued_orders = vec![];
+        for order_id in batch.orders.iter().filter(|id| !failed_orders.contains(id)) {
             let order = self
                 .db
                 .get_order(order_id)
                 .await
                 .with_context(|| format!("Failed to get order {order_id}"))?
                 .with_context(|| format!("Order {order_id} missing from DB"))?;
+            let status = match order.is_groth16() {
+                false => OrderStatus::PendingAgg,
+                true => OrderStatus::SkipAggregation,
+            };
+            requeued_orders.push((order_id.clone(), status));
+        }
 
-            let proof_id =
-                order.proof_id.with_context(|| format!("Missing proof_id for order {order_id}"))?;
+        tracing::warn!(
+            "Rebuilding batch {batch_id} without orders {:x?}, requeuing orders {:x?}",
+            failed_orders,
+            requeued_orders.iter().map(|(order_id, _)| order_id).collect::<Vec<_>>()
+        );
+        self.db
+            .reset_batch(batch_id, &requeued_orders)
+            .await
+            .with_context(|| format!("Failed to reset batch {batch_id}"))?;
 
-            let journal = self
-                .prover
-                .get_journal(&proof_id)
-                .await
-                .with_context(|| format!("Failed to get journal for {proof_id}"))?
-                .with_context(|| format!("Journal for {proof_id} missing"))?;
+        Ok(None)
+    }
 
-            journal_size += journal.len();
+    /// Check the proofs of pending orders, marking the orders whose proofs are missing or invalid
+    /// as failed, so they do not fail the aggregation of the rest of the batch
+    async fn check_pending_proofs(
+        &self,
+        new_proofs: Vec<AggregationOrder>,
+        new_groth16_proofs: Vec<AggregationOrder>,
+    ) -> Result<PendingProofs, AggregatorErr> {
+        let mut pending = PendingProofs::default();
+
+        for order in new_proofs {
+            let checked = match self.get_claim(&order.proof_id).await? {
+                Ok(claim) => self.get_journal(&order.proof_id).await?.map(|j| (claim, j)),
+                Err(reason) => Err(reason),
+            };
+            match checked {
+                Ok((claim, journal)) => {
+                    pending.journal_size += journal.len();
+                    pending.claims.push(claim);
+                    pending.proofs.push(order);
+                }
+                Err(reason) => self.fail_order(&order.order_id, reason).await,
+            }
         }
 
-        Ok(journal_size)
+        for order in new_groth16_proofs {
+            match self.get_journal(&order.proof_id).await? {
+                Ok(journal) => {
+                    pending.journal_size += journal.len();
+                    pending.groth16_proofs.push(order);
+                }
+                Err(reason) => self.fail_order(&order.order_id, reason).await,
+            }
+        }
+
+        Ok(pending)
     }
 
     /// Check if we should finalize the batch
@@ -276,6 +345,7 @@ impl AggregatorService {
         batch_id: usize,
         batch: &Batch,
         pending_orders: &[AggregationOrder],
+        journal_size: usize,
     ) -> Result<bool> {
         let (
             conf_batch_size,
@@ -316,13 +386,30 @@ impl AggregatorService {
             (batch_size, _) => batch_size,
         };
 
+        let flush_requested = self.control.take_batch_flush();
+
         // Skip finalization checks if we have nothing in this batch
         let is_initial_state =
             batch.aggregation_state.as_ref().map(|s| s.guest_state.is_initial()).unwrap_or(true);
         if is_initial_state && pending_orders.is_empty() {
+            if flush_requested {
+                tracing::info!("Ignoring flush request for batch {batch_id}, batch is empty");
+            }
             return Ok(false);
         }
 
+        if flush_requested {
+            tracing::info!("Finalizing batch {batch_id}: flush requested");
+            return Ok(true);
+        }
+
+        // No more orders will join the batch once the broker drains, so waiting for a target
+        // only holds the orders back from being submitted before shutdown.
+        if self.control.is_draining() {
+            tracing::info!("Finalizing batch {batch_id}: broker is draining");
+            return Ok(true);
+        }
+
         // Finalize the batch whenever it exceeds a target size.
         // Add any pending jobs into the batch along with the finalization run.
         let batch_size = batch.orders.len() + pending_orders.len();
@@ -344,10 +431,6 @@ impl AggregatorService {
         }
 
         // Finalize the batch if the journal size is already above the max
-        let batch_journal_size = self.get_combined_journal_size(&batch.orders).await?;
-        let pending_order_ids: Vec<_> = pending_orders.iter().map(|o| o.order_id.clone()).collect();
-        let pending_journal_size = self.get_combined_journal_size(&pending_order_ids).await?;
-        let journal_size = batch_journal_size + pending_journal_size;
         if journal_size >= conf_max_journal_bytes {
             tracing::debug!(
                 "Finalizing batch {batch_id}: journal size target hit {} >= {}",
@@ -459,10 +542,16 @@ impl AggregatorService {
         let current_time = crate::now_timestamp();
 
         // Get both types of proofs
-        let new_proofs =
-            self.db.get_aggregation_proofs().await.context("Failed to get aggregation proofs")?;
-        let groth16_proofs =
-            self.db.get_groth16_proofs().await.context("Failed to get groth16 proofs")?;
+        let new_proofs = self
+            .db
+            .get_aggregation_proofs(self.chain_id)
+            .await
+            .context("Failed to get aggregation proofs")?;
+        let groth16_proofs = self
+            .db
+            .get_groth16_proofs(self.chain_id)
+            .await
+            .context("Failed to get groth16 proofs")?;
 
         // Filter expired orders from both lists
         let valid_new_proofs = self.filter_expired_orders(new_proofs, current_time).await?;
@@ -475,10 +564,16 @@ impl AggregatorService {
         &self,
         batch_id: usize,
         batch: &Batch,
-        new_proofs: &[AggregationOrder],
-        new_groth16_proofs: &[AggregationOrder],
+        pending: PendingProofs,
         finalize: bool,
     ) -> Result<String> {
+        let PendingProofs {
+            proofs: new_proofs,
+            mut claims,
+            groth16_proofs: new_groth16_proofs,
+            ..
+        } = pending;
+
         let all_orders: Vec<String> = batch
             .orders
             .iter()
@@ -505,6 +600,11 @@ impl AggregatorService {
                 assessor_proof_id
             );
 
+            let claim = self.get_claim(&assessor_proof_id).await?.map_err(|reason| {
+                anyhow::anyhow!("Invalid assessor proof {assessor_proof_id}: {reason}")
+            })?;
+            claims.push(claim);
+
             Some(assessor_proof_id)
         } else {
             None
@@ -523,7 +623,7 @@ impl AggregatorService {
             proof_ids
         );
         let aggregation_state = self
-            .prove_set_builder(batch.aggregation_state.as_ref(), &proof_ids, finalize)
+            .prove_set_builder(batch.aggregation_state.as_ref(), &proof_ids, claims, finalize)
             .await
             .context("Failed to prove set builder for batch {batch_id}")?;
 
@@ -549,13 +649,28 @@ impl AggregatorService {
     async fn aggregate(&self) -> Result<(), AggregatorErr> {
         // Get the current batch. This aggregator service works on one batch at a time, including
         // any proofs ready for aggregation into the current batch.
-        let batch_id = self.db.get_current_batch().await.context("Failed to get current batch")?;
-        let batch = self.db.get_batch(batch_id).await.context("Failed to get batch")?;
+        let batch_id = self
+            .db
+            .get_current_batch(self.chain_id)
+            .await
+            .context("Failed to get current batch")?;
+        let mut batch = self.db.get_batch(batch_id).await.context("Failed to get batch")?;
 
         let (aggregation_proof_id, compress) = match batch.status {
             BatchStatus::Aggregating => {
+                // Check the orders of the batch before taking the pending proofs, so the orders of a
+                // batch that has to be rebuilt are aggregated again right away.
+                let batch_journal_size = match self.check_batch_orders(batch_id, &batch).await? {
+                    Some(journal_size) => journal_size,
+                    None => {
+                        batch = self.db.get_batch(batch_id).await.context("Failed to get batch")?;
+                        0
+                    }
+                };
+
                 // Get and filter all pending proofs
                 let (new_proofs, new_groth16_proofs) = self.get_filtered_pending_proofs().await?;
+                let pending = self.check_pending_proofs(new_proofs, new_groth16_proofs).await?;
 
                 // Finalize the current batch before adding any new orders if the finalization conditions
                 // are already met.
@@ -563,19 +678,19 @@ impl AggregatorService {
                     .check_finalize(
                         batch_id,
                         &batch,
-                        &[new_proofs.clone(), new_groth16_proofs.clone()].concat(),
+                        &[pending.proofs.clone(), pending.groth16_proofs.clone()].concat(),
+                        batch_journal_size + pending.journal_size,
                     )
                     .await?;
 
                 // If we don't need to finalize, and there are no new proofs, there is no work to do.
-                if !finalize && new_proofs.is_empty() {
+                if !finalize && pending.proofs.is_empty() {
                     tracing::trace!("No aggregation work to do for batch {batch_id}");
                     return Ok(());
                 }
 
-                let aggregation_proof_id = self
-                    .aggregate_proofs(batch_id, &batch, &new_proofs, &new_groth16_proofs, finalize)
-                    .await?;
+                let aggregation_proof_id =
+                    self.aggregate_proofs(batch_id, &batch, pending, finalize).await?;
                 (aggregation_proof_id, finalize)
             }
             BatchStatus::PendingCompression => {
@@ -632,6 +747,70 @@ impl AggregatorService {
     }
 }
 
+/// Prove the assessor over the fulfillments of the given orders, in order, returning the proof ID
+pub(crate) async fn prove_assessor(
+    db: &DbObj,
+    prover: &ProverObj,
+    assessor_guest_id: Digest,
+    market_addr: Address,
+    chain_id: u64,
+    prover_addr: Address,
+    order_ids: &[String],
+) -> Result<String> {
+    let mut fills = vec![];
+    let mut assumptions = vec![];
+
+    for order_id in order_ids {
+        let order = db
+            .get_order(order_id)
+            .await
+            .with_context(|| format!("Failed to get DB order ID {order_id}"))?
+            .with_context(|| format!("order ID {order_id} missing from DB"))?;
+
+        let proof_id =
+            order.proof_id.with_context(|| format!("Missing proof_id for order: {order_id}"))?;
+
+        assumptions.push(proof_id.clone());
+
+        let journal = prover
+            .get_journal(&proof_id)
+            .await
+            .with_context(|| format!("Failed to get {proof_id} journal"))?
+            .with_context(|| format!("{proof_id} journal missing"))?;
+
+        fills.push(Fulfillment {
+            request: order.request.clone(),
+            signature: order.client_sig.clone().to_vec(),
+            journal,
+        })
+    }
+
+    let order_count = fills.len();
+    let input = AssessorInput {
+        fills,
+        domain: eip712_domain(market_addr, chain_id),
+        prover_address: prover_addr,
+    };
+    let stdin = GuestEnv::builder().write_frame(&input.encode()).stdin;
+
+    let input_id = prover.upload_input(stdin).await.context("Failed to upload assessor input")?;
+
+    let proof_res = prover
+        .prove_and_monitor_stark(&assessor_guest_id.to_string(), &input_id, assumptions)
+        .await
+        .context("Failed to prove assesor stark")?;
+
+    tracing::debug!(
+        "Assessor proof completed, proof id: {} count: {} cycles: {} time: {}",
+        proof_res.id,
+        order_count,
+        proof_res.stats.total_cycles,
+        proof_res.elapsed_time
+    );
+
+    Ok(proof_res.id)
+}
+
 impl RetryTask for AggregatorService {
     type Error = AggregatorErr;
     fn spawn(&self, cancel_token: CancellationToken) -> RetryRes<Self::Error> {
@@ -664,6 +843,23 @@ impl RetryTask for AggregatorService {
     }
 }
 
+/// Treat proofs reported as not found by the prover like missing proofs
+fn not_found_as_none<T>(res: Result<Option<T>, ProverError>) -> Result<Option<T>, ProverError> {
+    match res {
+        Err(ProverError::NotFound(_)) => Ok(None),
+        res => res,
+    }
+}
+
+/// Whether the claim has no unresolved assumptions
+fn is_unconditional(claim: &ReceiptClaim) -> bool {
+    match claim.output.as_value() {
+        Ok(Some(output)) => output.assumptions.digest() == Assumptions::default().digest(),
+        Ok(None) => true,
+        Err(_) => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
@@ -673,7 +869,7 @@ mod tests {
         chain_monitor::ChainMonitorService,
         db::SqliteDb,
         now_timestamp,
-        provers::{encode_input, DefaultProver, Prover},
+        provers::{encode_input, DefaultProver, ProofResult, Prover},
         BatchStatus, FulfillmentType, Order, OrderStatus,
     };
     use alloy::{
@@ -683,6 +879,7 @@ mod tests {
         providers::{ext::AnvilApi, Provider, ProviderBuilder},
         signers::local::PrivateKeySigner,
     };
+    use async_trait::async_trait;
     use boundless_market::contracts::{
         Offer, Predicate, PredicateType, ProofRequest, RequestId, RequestInput, RequestInputType,
         Requirements,
@@ -690,8 +887,181 @@ mod tests {
     use boundless_market_test_utils::{
         ASSESSOR_GUEST_ELF, ASSESSOR_GUEST_ID, ECHO_ELF, ECHO_ID, SET_BUILDER_ELF, SET_BUILDER_ID,
     };
+    use risc0_zkvm::{Assumption, MaybePruned, Receipt};
+    use std::{collections::HashSet, sync::Mutex};
     use tracing_test::traced_test;
 
+    /// Prover that reports the receipts and journals of faulted proofs as missing
+    #[derive(Default)]
+    struct FaultyProver {
+        inner: DefaultProver,
+        missing_receipts: Mutex<HashSet<String>>,
+        missing_journals: Mutex<HashSet<String>>,
+    }
+
+    #[async_trait]
+    impl Prover for FaultyProver {
+        async fn has_image(&self, image_id: &str) -> Result<bool, ProverError> {
+            self.inner.has_image(image_id).await
+        }
+        async fn upload_input(&self, input: Vec<u8>) -> Result<String, ProverError> {
+            self.inner.upload_input(input).await
+        }
+        async fn upload_receipt(&self, receipt: Vec<u8>) -> Result<String, ProverError> {
+            self.inner.upload_receipt(receipt).await
+        }
+        async fn upload_image(&self, image_id: &str, image: Vec<u8>) -> Result<(), ProverError> {
+            self.inner.upload_image(image_id, image).await
+        }
+        async fn preflight(
+            &self,
+            image_id: &str,
+            input_id: &str,
+            assumptions: Vec<String>,
+            executor_limit: Option<u64>,
+            order_id: &str,
+        ) -> Result<ProofResult, ProverError> {
+            self.inner.preflight(image_id, input_id, assumptions, executor_limit, order_id).await
+        }
+        async fn cancel_preflight(&self, order_id: &str) -> Result<(), ProverError> {
+            self.inner.cancel_preflight(order_id).await
+        }
+        async fn prove_stark(
+            &self,
+            image_id: &str,
+            input_id: &str,
+            assumptions: Vec<String>,
+        ) -> Result<String, ProverError> {
+            self.inner.prove_stark(image_id, input_id, assumptions).await
+        }
+        async fn wait_for_stark(&self, proof_id: &str) -> Result<ProofResult, ProverError> {
+            self.inner.wait_for_stark(proof_id).await
+        }
+        async fn cancel_stark(&self, proof_id: &str) -> Result<(), ProverError> {
+            self.inner.cancel_stark(proof_id).await
+        }
+        async fn get_receipt(&self, proof_id: &str) -> Result<Option<Receipt>, ProverError> {
+            if self.missing_receipts.lock().unwrap().contains(proof_id) {
+                return Ok(None);
+            }
+            self.inner.get_receipt(proof_id).await
+        }
+        async fn get_preflight_journal(
+            &self,
+            proof_id: &str,
+        ) -> Result<Option<Vec<u8>>, ProverError> {
+            self.inner.get_preflight_journal(proof_id).await
+        }
+        async fn get_journal(&self, proof_id: &str) -> Result<Option<Vec<u8>>, ProverError> {
+            if self.missing_journals.lock().unwrap().contains(proof_id) {
+                return Err(ProverError::NotFound(proof_id.to_string()));
+            }
+            self.inner.get_journal(proof_id).await
+        }
+        async fn compress(&self, proof_id: &str) -> Result<String, ProverError> {
+            self.inner.compress(proof_id).await
+        }
+        async fn get_compressed_receipt(
+            &self,
+            proof_id: &str,
+        ) -> Result<Option<Vec<u8>>, ProverError> {
+            self.inner.get_compressed_receipt(proof_id).await
+        }
+    }
+
+    /// Set up an aggregator over a [FaultyProver], returning the prover and the ID and input ID
+    /// of the echo guest image proven by the orders.
+    async fn faulty_aggregator(
+        db: DbObj,
+        config: ConfigLock,
+    ) -> (AggregatorService, Arc<FaultyProver>, String, String) {
+        let prover = Arc::new(FaultyProver::default());
+        let image_id = Digest::from(ECHO_ID).to_string();
+        prover.upload_image(&image_id, ECHO_ELF.to_vec()).await.unwrap();
+        let input_id = prover
+            .upload_input(encode_input(&vec![0x41, 0x41, 0x41, 0x41]).unwrap())
+            .await
+            .unwrap();
+
+        let set_builder_id = Digest::from(SET_BUILDER_ID);
+        prover.upload_image(&set_builder_id.to_string(), SET_BUILDER_ELF.to_vec()).await.unwrap();
+        let assessor_id = Digest::from(ASSESSOR_GUEST_ID);
+        prover.upload_image(&assessor_id.to_string(), ASSESSOR_GUEST_ELF.to_vec()).await.unwrap();
+        let aggregator = AggregatorService::new(
+            db,
+            1,
+            set_builder_id,
+            assessor_id,
+            Address::ZERO,
+            Address::ZERO,
+            config,
+            prover.clone(),
+            BrokerControl::default(),
+        )
+        .await
+        .unwrap();
+
+        (aggregator, prover, image_id, input_id)
+    }
+
+    /// Prove the echo guest and add an order for the proof, pending aggregation
+    async fn add_proven_order(
+        db: &DbObj,
+        prover: &FaultyProver,
+        image_id: &str,
+        input_id: &str,
+        idx: u32,
+    ) -> (Order, String) {
+        let proof_res = prover.prove_and_monitor_stark(image_id, input_id, vec![]).await.unwrap();
+
+        let customer_signer = PrivateKeySigner::random();
+        let request = ProofRequest::new(
+            RequestId::new(customer_signer.address(), idx),
+            Requirements::new(
+                Digest::from(ECHO_ID),
+                Predicate { predicateType: PredicateType::PrefixMatch, data: Default::default() },
+            ),
+            "http://risczero.com/image",
+            RequestInput { inputType: RequestInputType::Inline, data: Default::default() },
+            Offer {
+                minPrice: U256::from(2),
+                maxPrice: U256::from(4),
+                biddingStart: now_timestamp(),
+                timeout: 1200,
+                lockTimeout: 1200,
+                rampUpPeriod: 1,
+                lockStake: U256::from(10),
+            },
+        );
+        let client_sig =
+            request.sign_request(&customer_signer, Address::ZERO, 1).await.unwrap().as_bytes();
+
+        let order = Order {
+            status: OrderStatus::PendingAgg,
+            updated_at: Utc::now(),
+            target_timestamp: None,
+            image_id: Some(image_id.to_string()),
+            input_id: Some(input_id.to_string()),
+            assumption_ids: vec![],
+            proof_id: Some(proof_res.id.clone()),
+            compressed_proof_id: None,
+            expire_timestamp: Some(request.expires_at()),
+            client_sig: client_sig.into(),
+            lock_price: Some(U256::from(2)),
+            fulfillment_type: FulfillmentType::LockAndFulfill,
+            error_msg: None,
+            skip_reason: None,
+            request,
+            boundless_market_address: Address::ZERO,
+            chain_id: 1,
+            total_cycles: None,
+            proving_started_at: None,
+        };
+        db.add_order(&order).await.unwrap();
+
+        (order, proof_res.id)
+    }
+
     #[tokio::test]
     #[traced_test]
     async fn aggregate_order_one_shot() {
@@ -743,6 +1113,7 @@ mod tests {
             prover_addr,
             config,
             prover,
+            BrokerControl::default(),
         )
         .await
         .unwrap();
@@ -783,6 +1154,7 @@ mod tests {
             request: order_request,
             image_id: Some(image_id_str.clone()),
             input_id: Some(input_id.clone()),
+            assumption_ids: vec![],
             proof_id: Some(proof_res_1.id),
             compressed_proof_id: None,
             expire_timestamp: Some(now_timestamp() + 100),
@@ -790,6 +1162,7 @@ mod tests {
             lock_price: Some(U256::from(min_price)),
             fulfillment_type: FulfillmentType::LockAndFulfill,
             error_msg: None,
+            skip_reason: None,
             boundless_market_address: Address::ZERO,
             chain_id,
             total_cycles: None,
@@ -830,6 +1203,7 @@ mod tests {
             request: order_request,
             image_id: Some(image_id_str),
             input_id: Some(input_id),
+            assumption_ids: vec![],
             proof_id: Some(proof_res_2.id),
             compressed_proof_id: None,
             expire_timestamp: Some(now_timestamp() + 100),
@@ -837,6 +1211,7 @@ mod tests {
             lock_price: Some(U256::from(min_price)),
             fulfillment_type: FulfillmentType::LockAndFulfill,
             error_msg: None,
+            skip_reason: None,
             boundless_market_address: Address::ZERO,
             chain_id,
             total_cycles: None,
@@ -849,7 +1224,7 @@ mod tests {
         let db_order = db.get_order(&order.id()).await.unwrap().unwrap();
         assert_eq!(db_order.status, OrderStatus::PendingSubmission);
 
-        let (_batch_id, batch) = db.get_complete_batch().await.unwrap().unwrap();
+        let (_batch_id, batch) = db.get_complete_batch(chain_id).await.unwrap().unwrap();
         assert!(!batch.orders.is_empty());
         assert_eq!(batch.status, BatchStatus::PendingSubmission);
     }
@@ -904,6 +1279,7 @@ mod tests {
             prover_addr,
             config,
             prover,
+            BrokerControl::default(),
         )
         .await
         .unwrap();
@@ -944,6 +1320,7 @@ mod tests {
             target_timestamp: None,
             image_id: Some(image_id_str.clone()),
             input_id: Some(input_id.clone()),
+            assumption_ids: vec![],
             proof_id: Some(proof_res_1.id),
             compressed_proof_id: None,
             expire_timestamp: Some(order_request.expires_at()),
@@ -951,6 +1328,7 @@ mod tests {
             lock_price: Some(U256::from(min_price)),
             fulfillment_type: FulfillmentType::LockAndFulfill,
             error_msg: None,
+            skip_reason: None,
             request: order_request,
             boundless_market_address: Address::ZERO,
             chain_id,
@@ -965,10 +1343,10 @@ mod tests {
         let db_order = db.get_order(&order.id()).await.unwrap().unwrap();
         assert_eq!(db_order.status, OrderStatus::PendingSubmission);
 
-        let option_batch = db.get_complete_batch().await.unwrap();
+        let option_batch = db.get_complete_batch(chain_id).await.unwrap();
         assert!(option_batch.is_none());
 
-        let aggregating_batch_id = db.get_current_batch().await.unwrap();
+        let aggregating_batch_id = db.get_current_batch(chain_id).await.unwrap();
         let aggregating_batch = db.get_batch(aggregating_batch_id).await.unwrap();
         assert_eq!(aggregating_batch.orders, vec![order.id()]);
         assert!(aggregating_batch.aggregation_state.is_some());
@@ -1006,6 +1384,7 @@ mod tests {
             target_timestamp: None,
             image_id: Some(image_id_str),
             input_id: Some(input_id),
+            assumption_ids: vec![],
             proof_id: Some(proof_res_2.id),
             compressed_proof_id: None,
             expire_timestamp: Some(order_request.expires_at()),
@@ -1013,6 +1392,7 @@ mod tests {
             lock_price: Some(U256::from(min_price)),
             fulfillment_type: FulfillmentType::LockAndFulfill,
             error_msg: None,
+            skip_reason: None,
             request: order_request,
             boundless_market_address: Address::ZERO,
             chain_id,
@@ -1026,7 +1406,7 @@ mod tests {
         let db_order = db.get_order(&order.id()).await.unwrap().unwrap();
         assert_eq!(db_order.status, OrderStatus::PendingSubmission);
 
-        let (_batch_id, batch) = db.get_complete_batch().await.unwrap().unwrap();
+        let (_batch_id, batch) = db.get_complete_batch(chain_id).await.unwrap().unwrap();
         assert!(!batch.orders.is_empty());
         assert_eq!(batch.status, BatchStatus::PendingSubmission);
     }
@@ -1078,6 +1458,7 @@ mod tests {
             prover_addr,
             config,
             prover,
+            BrokerControl::default(),
         )
         .await
         .unwrap();
@@ -1118,6 +1499,7 @@ mod tests {
             request: order_request,
             image_id: Some(image_id_str.clone()),
             input_id: Some(input_id.clone()),
+            assumption_ids: vec![],
             proof_id: Some(proof_res.id),
             compressed_proof_id: None,
             expire_timestamp: Some(now_timestamp() + 100),
@@ -1125,6 +1507,7 @@ mod tests {
             lock_price: Some(U256::from(min_price)),
             fulfillment_type: FulfillmentType::LockAndFulfill,
             error_msg: None,
+            skip_reason: None,
             boundless_market_address: Address::ZERO,
             chain_id,
             total_cycles: None,
@@ -1137,11 +1520,35 @@ mod tests {
         let db_order = db.get_order(&order.id()).await.unwrap().unwrap();
         assert_eq!(db_order.status, OrderStatus::PendingSubmission);
 
-        let (_batch_id, batch) = db.get_complete_batch().await.unwrap().unwrap();
+        let (_batch_id, batch) = db.get_complete_batch(chain_id).await.unwrap().unwrap();
         assert!(!batch.orders.is_empty());
         assert_eq!(batch.status, BatchStatus::PendingSubmission);
     }
 
+    #[tokio::test]
+    #[traced_test]
+    async fn drain_finalize() {
+        let db: DbObj = Arc::new(SqliteDb::new("sqlite::memory:").await.unwrap());
+        let config = ConfigLock::default();
+        config.load_write().unwrap().batcher.min_batch_size = Some(2);
+        let (aggregator, prover, image_id, input_id) = faulty_aggregator(db.clone(), config).await;
+        let (order, _) = add_proven_order(&db, &prover, &image_id, &input_id, 0).await;
+
+        // Below the min batch size, the batch stays open until the broker drains
+        aggregator.aggregate().await.unwrap();
+        assert!(db.get_complete_batch(order.chain_id).await.unwrap().is_none());
+
+        aggregator.control.start_drain();
+        aggregator.aggregate().await.unwrap();
+
+        let db_order = db.get_order(&order.id()).await.unwrap().unwrap();
+        assert_eq!(db_order.status, OrderStatus::PendingSubmission);
+
+        let (_batch_id, batch) = db.get_complete_batch(order.chain_id).await.unwrap().unwrap();
+        assert_eq!(batch.orders, vec![order.id()]);
+        assert_eq!(batch.status, BatchStatus::PendingSubmission);
+    }
+
     #[tokio::test]
     #[traced_test]
     async fn deadline_finalize() {
@@ -1192,6 +1599,7 @@ mod tests {
             signer.address(),
             config.clone(),
             prover,
+            BrokerControl::default(),
         )
         .await
         .unwrap();
@@ -1232,6 +1640,7 @@ mod tests {
             request: order_request,
             image_id: Some(image_id_str.clone()),
             input_id: Some(input_id.clone()),
+            assumption_ids: vec![],
             proof_id: Some(proof_res.id),
             compressed_proof_id: None,
             expire_timestamp: Some(now_timestamp() + 100),
@@ -1239,6 +1648,7 @@ mod tests {
             lock_price: Some(U256::from(min_price)),
             fulfillment_type: FulfillmentType::LockAndFulfill,
             error_msg: None,
+            skip_reason: None,
             boundless_market_address: Address::ZERO,
             chain_id,
             total_cycles: None,
@@ -1253,7 +1663,7 @@ mod tests {
         let db_order = db.get_order(&order.id()).await.unwrap().unwrap();
         assert_eq!(db_order.status, OrderStatus::PendingSubmission);
 
-        let (_batch_id, batch) = db.get_complete_batch().await.unwrap().unwrap();
+        let (_batch_id, batch) = db.get_complete_batch(chain_id).await.unwrap().unwrap();
         assert!(!batch.orders.is_empty());
         assert_eq!(batch.status, BatchStatus::PendingSubmission);
         assert!(logs_contain("getting close to deadline"));
@@ -1314,6 +1724,7 @@ mod tests {
             signer.address(),
             config.clone(),
             prover,
+            BrokerControl::default(),
         )
         .await
         .unwrap();
@@ -1354,6 +1765,7 @@ mod tests {
             request: order_request.clone(),
             image_id: Some(image_id_str.clone()),
             input_id: Some(input_id.clone()),
+            assumption_ids: vec![],
             proof_id: Some(proof_res.clone().id),
             compressed_proof_id: None,
             expire_timestamp: Some(now_timestamp() + 1000),
@@ -1361,6 +1773,7 @@ mod tests {
             lock_price: Some(U256::from(min_price)),
             fulfillment_type: FulfillmentType::LockAndFulfill,
             error_msg: None,
+            skip_reason: None,
             boundless_market_address: Address::ZERO,
             chain_id,
             total_cycles: None,
@@ -1372,7 +1785,7 @@ mod tests {
         aggregator.aggregate().await.unwrap();
         assert!(logs_contain("journal size below limit 20 < 30"));
 
-        let batch_res = db.get_complete_batch().await.unwrap();
+        let batch_res = db.get_complete_batch(chain_id).await.unwrap();
         assert!(batch_res.is_none());
 
         // Add another order, this should cross the journal limit threshold and
@@ -1393,6 +1806,7 @@ mod tests {
             request: order_request_2,
             image_id: Some(image_id_str.clone()),
             input_id: Some(input_id.clone()),
+            assumption_ids: vec![],
             proof_id: Some(proof_res.id),
             compressed_proof_id: None,
             expire_timestamp: Some(now_timestamp() + 1000),
@@ -1400,6 +1814,7 @@ mod tests {
             lock_price: Some(U256::from(min_price)),
             fulfillment_type: FulfillmentType::LockAndFulfill,
             error_msg: None,
+            skip_reason: None,
             boundless_market_address: Address::ZERO,
             chain_id,
             total_cycles: None,
@@ -1410,7 +1825,7 @@ mod tests {
         aggregator.aggregate().await.unwrap();
         assert!(logs_contain("journal size target hit 40 >= 30"));
 
-        let (_, batch) = db.get_complete_batch().await.unwrap().unwrap();
+        let (_, batch) = db.get_complete_batch(chain_id).await.unwrap().unwrap();
         assert_eq!(batch.orders.len(), 2);
         assert_eq!(batch.status, BatchStatus::PendingSubmission);
     }
@@ -1431,6 +1846,7 @@ mod tests {
             Address::ZERO,
             config,
             prover,
+            BrokerControl::default(),
         )
         .await
         .unwrap();
@@ -1465,6 +1881,7 @@ mod tests {
             ),
             image_id: None,
             input_id: None,
+            assumption_ids: vec![],
             proof_id: None,
             compressed_proof_id: None,
             expire_timestamp: Some(current_time - 100),
@@ -1472,6 +1889,7 @@ mod tests {
             lock_price: Some(U256::from(1)),
             fulfillment_type: FulfillmentType::LockAndFulfill,
             error_msg: None,
+            skip_reason: None,
             boundless_market_address: Address::ZERO,
             chain_id: 1,
             total_cycles: None,
@@ -1506,6 +1924,7 @@ mod tests {
             ),
             image_id: None,
             input_id: None,
+            assumption_ids: vec![],
             proof_id: None,
             compressed_proof_id: None,
             expire_timestamp: Some(current_time + 100),
@@ -1513,6 +1932,7 @@ mod tests {
             lock_price: Some(U256::from(1)),
             fulfillment_type: FulfillmentType::LockAndFulfill,
             error_msg: None,
+            skip_reason: None,
             boundless_market_address: Address::ZERO,
             chain_id: 1,
             total_cycles: None,
@@ -1554,4 +1974,87 @@ mod tests {
         assert_eq!(db_valid_order.status, OrderStatus::PendingAgg);
         assert!(db_valid_order.error_msg.is_none());
     }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn aggregate_without_missing_receipt() {
+        let db: DbObj = Arc::new(SqliteDb::new("sqlite::memory:").await.unwrap());
+        let config = ConfigLock::default();
+        config.load_write().unwrap().batcher.min_batch_size = Some(3);
+        let (aggregator, prover, image_id, input_id) = faulty_aggregator(db.clone(), config).await;
+
+        let (bad_order, bad_proof_id) =
+            add_proven_order(&db, &prover, &image_id, &input_id, 0).await;
+        let (good_order, _) = add_proven_order(&db, &prover, &image_id, &input_id, 1).await;
+        prover.missing_receipts.lock().unwrap().insert(bad_proof_id);
+
+        aggregator.aggregate().await.unwrap();
+
+        let db_order = db.get_order(&bad_order.id()).await.unwrap().unwrap();
+        assert_eq!(db_order.status, OrderStatus::Failed);
+        assert_eq!(db_order.error_msg, Some("Proof receipt missing".to_string()));
+        let db_order = db.get_order(&good_order.id()).await.unwrap().unwrap();
+        assert_eq!(db_order.status, OrderStatus::PendingSubmission);
+
+        let batch = db.get_batch(db.get_current_batch(1).await.unwrap()).await.unwrap();
+        assert_eq!(batch.orders, vec![good_order.id()]);
+        assert_eq!(batch.aggregation_state.unwrap().claim_digests.len(), 1);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn rebuild_batch_without_missing_journal() {
+        let db: DbObj = Arc::new(SqliteDb::new("sqlite::memory:").await.unwrap());
+        let config = ConfigLock::default();
+        config.load_write().unwrap().batcher.min_batch_size = Some(4);
+        let (aggregator, prover, image_id, input_id) = faulty_aggregator(db.clone(), config).await;
+
+        let (bad_order, bad_proof_id) =
+            add_proven_order(&db, &prover, &image_id, &input_id, 0).await;
+        let (order_1, _) = add_proven_order(&db, &prover, &image_id, &input_id, 1).await;
+        aggregator.aggregate().await.unwrap();
+
+        let batch_id = db.get_current_batch(1).await.unwrap();
+        let batch = db.get_batch(batch_id).await.unwrap();
+        assert_eq!(batch.orders.len(), 2);
+
+        // The journal of an order already aggregated into the batch goes missing
+        prover.missing_journals.lock().unwrap().insert(bad_proof_id);
+        let (order_2, _) = add_proven_order(&db, &prover, &image_id, &input_id, 2).await;
+        aggregator.aggregate().await.unwrap();
+        assert!(logs_contain("Rebuilding batch"));
+
+        let db_order = db.get_order(&bad_order.id()).await.unwrap().unwrap();
+        assert_eq!(db_order.status, OrderStatus::Failed);
+        assert_eq!(db_order.error_msg, Some("Proof journal missing".to_string()));
+
+        // The batch is rebuilt from a clean state with the remaining orders
+        let batch = db.get_batch(batch_id).await.unwrap();
+        let mut orders = batch.orders.clone();
+        orders.sort();
+        let mut expected = vec![order_1.id(), order_2.id()];
+        expected.sort();
+        assert_eq!(orders, expected);
+        assert_eq!(batch.aggregation_state.unwrap().claim_digests.len(), 2);
+        for order in [order_1, order_2] {
+            let db_order = db.get_order(&order.id()).await.unwrap().unwrap();
+            assert_eq!(db_order.status, OrderStatus::PendingSubmission);
+        }
+    }
+
+    #[test]
+    fn unconditional_claims() {
+        let mut claim = ReceiptClaim::ok(Digest::from(ECHO_ID), vec![0x41]);
+        assert!(is_unconditional(&claim));
+
+        let MaybePruned::Value(Some(output)) = &mut claim.output else {
+            panic!("claim output should be populated");
+        };
+        output.assumptions =
+            MaybePruned::Value(Assumptions(vec![MaybePruned::Value(Assumption {
+                claim: Digest::ZERO,
+                control_root: Digest::ZERO,
+            })]));
+        assert!(!is_unconditional(&claim));
+    }
 }
@@ -0,0 +1,267 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+use alloy::primitives::{aliases::U96, Address, Bytes, FixedBytes, B256, U256};
+use anyhow::{bail, Context, Result};
+use boundless_market::contracts::{
+    Callback, Offer, Predicate, PredicateType, ProofRequest, RequestInput, RequestInputType,
+    Requirements,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{postgres::PgPool, Row};
+
+/// A market event, as exported from the indexer
+///
+/// One event is stored per line of a JSONL history export.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "event")]
+pub enum HistoryEvent {
+    RequestSubmitted {
+        request: ProofRequest,
+        #[serde(default)]
+        client_sig: Bytes,
+        timestamp: u64,
+        /// Cycle count of the request, if known
+        ///
+        /// Requests without a cycle count are proven with the default of the simulated prover.
+        #[serde(default)]
+        cycles: Option<u64>,
+    },
+    RequestLocked {
+        request_id: U256,
+        prover: Address,
+        timestamp: u64,
+    },
+    RequestFulfilled {
+        request_id: U256,
+        timestamp: u64,
+    },
+}
+
+/// A historical request, along with what happened to it on chain
+#[derive(Clone, Debug)]
+pub struct HistoricalRequest {
+    pub request: ProofRequest,
+    pub client_sig: Bytes,
+    pub submitted_at: u64,
+    pub cycles: Option<u64>,
+    /// Prover that locked the request, and when
+    pub locked: Option<(Address, u64)>,
+    pub fulfilled_at: Option<u64>,
+}
+
+/// Requests to replay, ordered by submission time
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    pub requests: Vec<HistoricalRequest>,
+}
+
+impl History {
+    /// Build the history from a sequence of market events
+    ///
+    /// Lock and fulfillment events for requests that were never submitted are ignored, as are
+    /// all but the first lock and fulfillment of each request.
+    pub fn from_events(events: impl IntoIterator<Item = HistoryEvent>) -> Self {
+        let mut requests: Vec<HistoricalRequest> = Vec::new();
+        let mut locks = HashMap::new();
+        let mut fulfillments = HashMap::new();
+        for event in events {
+            match event {
+                HistoryEvent::RequestSubmitted { request, client_sig, timestamp, cycles } => {
+                    requests.push(HistoricalRequest {
+                        request,
+                        client_sig,
+                        submitted_at: timestamp,
+                        cycles,
+                        locked: None,
+                        fulfilled_at: None,
+                    });
+                }
+                HistoryEvent::RequestLocked { request_id, prover, timestamp } => {
+                    locks
+                        .entry(request_id)
+                        .and_modify(|lock: &mut (Address, u64)| {
+                            if timestamp < lock.1 {
+                                *lock = (prover, timestamp);
+                            }
+                        })
+                        .or_insert((prover, timestamp));
+                }
+                HistoryEvent::RequestFulfilled { request_id, timestamp } => {
+                    fulfillments
+                        .entry(request_id)
+                        .and_modify(|fulfilled_at: &mut u64| {
+                            *fulfilled_at = (*fulfilled_at).min(timestamp)
+                        })
+                        .or_insert(timestamp);
+                }
+            }
+        }
+
+        for request in requests.iter_mut() {
+            request.locked = locks.get(&request.request.id).copied();
+            request.fulfilled_at = fulfillments.get(&request.request.id).copied();
+        }
+        requests.sort_by_key(|request| request.submitted_at);
+        Self { requests }
+    }
+
+    /// Load the history from a JSONL export of [HistoryEvent]s
+    pub async fn from_jsonl(path: &Path) -> Result<Self> {
+        let data = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read history {}", path.display()))?;
+        let events = data
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(idx, line)| {
+                serde_json::from_str(line)
+                    .with_context(|| format!("Failed to parse history event on line {}", idx + 1))
+            })
+            .collect::<Result<Vec<HistoryEvent>>>()?;
+        Ok(Self::from_events(events))
+    }
+
+    /// Load the requests submitted in `[from, to)` from the indexer database
+    ///
+    /// The indexer does not record image URLs, client signatures or cycle counts, so these are
+    /// left empty.
+    pub async fn from_indexer(db_url: &str, from: u64, to: u64) -> Result<Self> {
+        let pool = PgPool::connect(db_url).await.context("Failed to connect to indexer DB")?;
+
+        // Requests are indexed when submitted onchain, or when first locked if submitted
+        // offchain, so offchain requests are taken as submitted when bidding starts
+        let rows = sqlx::query(
+            "SELECT * FROM (
+                SELECT p.request_id, p.image_id, p.predicate_type, p.predicate_data,
+                    p.callback_address, p.callback_gas_limit, p.selector, p.input_type,
+                    p.input_data, p.min_price, p.max_price, p.lock_stake, p.bidding_start,
+                    p.expires_at, p.lock_end, p.ramp_up_period,
+                    COALESCE(s.block_timestamp, p.bidding_start) AS submitted_at
+                FROM proof_requests p
+                LEFT JOIN request_submitted_events s ON s.request_digest = p.request_digest
+            ) requests
+            WHERE submitted_at >= $1 AND submitted_at < $2
+            ORDER BY submitted_at",
+        )
+        .bind(from as i64)
+        .bind(to as i64)
+        .fetch_all(&pool)
+        .await
+        .context("Failed to query proof requests")?;
+        let mut events = rows
+            .iter()
+            .map(|row| {
+                let bidding_start = row.get::<i64, _>("bidding_start") as u64;
+                let callback_address = row.try_get::<Option<&str>, _>("callback_address")?;
+                let callback_gas_limit = row.try_get::<Option<&str>, _>("callback_gas_limit")?;
+                let request = ProofRequest {
+                    id: parse_hex_u256(row.get("request_id"))?,
+                    requirements: Requirements {
+                        imageId: B256::from_str(row.get("image_id"))?,
+                        callback: Callback {
+                            addr: callback_address
+                                .map(Address::from_str)
+                                .transpose()?
+                                .unwrap_or_default(),
+                            gasLimit: callback_gas_limit
+                                .map(U96::from_str)
+                                .transpose()?
+                                .unwrap_or_default(),
+                        },
+                        predicate: Predicate {
+                            predicateType: match row.get::<&str, _>("predicate_type") {
+                                "DigestMatch" => PredicateType::DigestMatch,
+                                "PrefixMatch" => PredicateType::PrefixMatch,
+                                other => bail!("Unknown predicate type {other}"),
+                            },
+                            data: Bytes::from_str(row.get("predicate_data"))?,
+                        },
+                        selector: FixedBytes::from_str(row.get("selector"))?,
+                    },
+                    imageUrl: String::new(),
+                    input: RequestInput {
+                        inputType: match row.get::<&str, _>("input_type") {
+                            "Inline" => RequestInputType::Inline,
+                            "Url" => RequestInputType::Url,
+                            other => bail!("Unknown input type {other}"),
+                        },
+                        data: Bytes::from_str(row.get("input_data"))?,
+                    },
+                    offer: Offer {
+                        minPrice: U256::from_str(row.get("min_price"))?,
+                        maxPrice: U256::from_str(row.get("max_price"))?,
+                        biddingStart: bidding_start,
+                        rampUpPeriod: row.get::<i64, _>("ramp_up_period") as u32,
+                        lockTimeout: (row.get::<i64, _>("lock_end") as u64)
+                            .saturating_sub(bidding_start)
+                            as u32,
+                        timeout: (row.get::<i64, _>("expires_at") as u64)
+                            .saturating_sub(bidding_start) as u32,
+                        lockStake: U256::from_str(row.get("lock_stake"))?,
+                    },
+                };
+                Ok(HistoryEvent::RequestSubmitted {
+                    request,
+                    client_sig: Bytes::new(),
+                    timestamp: row.get::<i64, _>("submitted_at") as u64,
+                    cycles: None,
+                })
+            })
+            .collect::<Result<Vec<_>>>()
+            .context("Failed to decode proof request")?;
+
+        let rows = sqlx::query(
+            "SELECT request_id, prover_address, block_timestamp FROM request_locked_events
+            WHERE block_timestamp >= $1",
+        )
+        .bind(from as i64)
+        .fetch_all(&pool)
+        .await
+        .context("Failed to query lock events")?;
+        for row in rows.iter() {
+            events.push(HistoryEvent::RequestLocked {
+                request_id: parse_hex_u256(row.get("request_id"))?,
+                prover: Address::from_str(row.get("prover_address"))?,
+                timestamp: row.get::<i64, _>("block_timestamp") as u64,
+            });
+        }
+
+        let rows = sqlx::query(
+            "SELECT request_id, block_timestamp FROM request_fulfilled_events
+            WHERE block_timestamp >= $1",
+        )
+        .bind(from as i64)
+        .fetch_all(&pool)
+        .await
+        .context("Failed to query fulfillment events")?;
+        for row in rows.iter() {
+            events.push(HistoryEvent::RequestFulfilled {
+                request_id: parse_hex_u256(row.get("request_id"))?,
+                timestamp: row.get::<i64, _>("block_timestamp") as u64,
+            });
+        }
+
+        Ok(Self::from_events(events))
+    }
+}
+
+/// Parse a U256 stored by the indexer as hex, without a `0x` prefix
+fn parse_hex_u256(value: &str) -> Result<U256> {
+    U256::from_str_radix(value.trim_start_matches("0x"), 16)
+        .with_context(|| format!("Invalid hex value {value}"))
+}
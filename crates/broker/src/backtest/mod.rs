@@ -0,0 +1,712 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Backtesting of broker configs against market history.
+//!
+//! Replays historical requests through a [PricingStrategy](crate::pricing::PricingStrategy) and
+//! the order prioritization used by the broker, proving accepted orders on a simulated prover.
+//! Orders compete against the locks and fulfillments of other provers recorded in the history,
+//! so the [BacktestReport] reflects which orders a config would have won, and what they would
+//! have earned.
+//!
+//! The simulation advances in fixed ticks. Preflight is not run: the cycle count of each request
+//! is taken from the history, or from the default of the [SimProverConf].
+
+mod history;
+
+pub use history::{HistoricalRequest, History, HistoryEvent};
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+};
+
+use alloy::primitives::{Address, U256};
+use anyhow::{Context, Result};
+use boundless_market::selector::SupportedSelectors;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::ConfigLock,
+    pricing::{PreflightDecision, PricingContext, PricingDecision, PricingStrategyObj},
+    prioritization::sort_orders_by_priority_and_mode,
+    provers::{ExecutorResp, ProofResult},
+    rules::{self, RuleOutcome},
+    stake_price::StakeTokenPrice,
+    utils, FulfillmentType, OrderRequest, SkipReason,
+};
+
+/// Throughput model of the simulated prover
+#[derive(Clone, Debug)]
+pub struct SimProverConf {
+    /// Proving throughput of each worker, in kHz
+    pub prove_khz: u64,
+    /// Number of proofs proven in parallel
+    pub workers: usize,
+    /// Fixed time spent on each proof, in seconds, eg. to fetch inputs and compress the receipt
+    pub overhead_secs: u64,
+    /// Time from a proof completing to its fulfillment landing on chain, in seconds
+    pub fulfill_delay_secs: u64,
+    /// Cycle count of requests without one in the history
+    pub default_cycles: u64,
+}
+
+impl SimProverConf {
+    fn proving_secs(&self, cycles: u64) -> u64 {
+        self.overhead_secs + cycles.div_ceil(self.prove_khz.max(1).saturating_mul(1_000))
+    }
+}
+
+/// Parameters of a backtest, beyond the broker config under test
+#[derive(Clone, Debug)]
+pub struct BacktestConf {
+    pub prover: SimProverConf,
+    /// Address of the prover being simulated
+    ///
+    /// Historical locks by this address are ignored, so that a config can be compared against
+    /// the decisions this prover actually made.
+    pub prover_address: Option<Address>,
+    pub boundless_market_address: Address,
+    pub chain_id: u64,
+    /// Gas price used for all orders, in wei
+    pub gas_price: u128,
+    /// Gas token balance at the start of the backtest, in wei
+    pub gas_balance: U256,
+    /// Stake token balance at the start of the backtest
+    pub stake_balance: U256,
+    pub stake_token_decimals: u8,
+    pub stake_token_price: Option<StakeTokenPrice>,
+    /// Length of a simulation step, in seconds
+    pub tick_secs: u64,
+}
+
+/// Outcome of a backtest
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BacktestReport {
+    /// Number of historical requests replayed
+    pub requests: usize,
+    /// Number of orders priced
+    pub orders_priced: usize,
+    /// Number of orders accepted by pricing
+    pub orders_accepted: usize,
+    /// Number of orders fulfilled for a reward
+    pub orders_won: usize,
+    /// Number of orders committed to, but locked or fulfilled first by another prover
+    pub orders_lost: usize,
+    /// Number of locked orders that would not have been fulfilled before the lock expired
+    pub orders_slashed: usize,
+    /// Number of orders not fulfilled, by reason
+    pub skip_reasons: BTreeMap<SkipReason, usize>,
+    /// Revenue of orders paid in native token, in wei
+    pub revenue: U256,
+    /// Revenue of orders with an expired lock, in stake token
+    pub stake_revenue: U256,
+    /// Gas spent to lock and fulfill orders, in wei
+    pub gas_spent: U256,
+    /// Stake lost to orders not fulfilled before their lock expired
+    pub slashed_stake: U256,
+    /// Largest amount of stake locked at one time
+    pub peak_locked_stake: U256,
+}
+
+impl BacktestReport {
+    fn skip(&mut self, reason: SkipReason) {
+        *self.skip_reasons.entry(reason).or_default() += 1;
+    }
+
+    fn lose(&mut self, reason: SkipReason) {
+        self.orders_lost += 1;
+        self.skip(reason);
+    }
+}
+
+/// An order accepted by pricing, along with the history of its request
+struct Committed {
+    order: Arc<OrderRequest>,
+    idx: usize,
+    cycles: u64,
+    order_gas_cost: U256,
+}
+
+impl AsRef<OrderRequest> for Committed {
+    fn as_ref(&self) -> &OrderRequest {
+        &self.order
+    }
+}
+
+/// Stake locked by the simulated prover
+struct Lock {
+    stake: U256,
+    /// Time at which the stake is released, or lost if slashed
+    until: u64,
+    slashed: bool,
+}
+
+/// Replays a [History] against a broker config
+pub struct Backtest {
+    config: ConfigLock,
+    conf: BacktestConf,
+    strategy: PricingStrategyObj,
+    supported_selectors: SupportedSelectors,
+}
+
+impl Backtest {
+    pub fn new(config: ConfigLock, conf: BacktestConf, strategy: PricingStrategyObj) -> Self {
+        Self { config, conf, strategy, supported_selectors: SupportedSelectors::default() }
+    }
+
+    /// Run the backtest over the given history
+    pub async fn run(&self, history: &History) -> Result<BacktestReport> {
+        let mut sim = Simulation::new(self, history);
+        let Some(first) = history.requests.first() else {
+            return Ok(sim.report);
+        };
+
+        let mut now = first.submitted_at;
+        while !sim.is_done() {
+            sim.observe(now)?;
+            sim.price_orders(now).await?;
+            sim.commit_orders(now)?;
+            now += self.conf.tick_secs.max(1);
+        }
+
+        Ok(sim.report)
+    }
+
+    fn is_us(&self, prover: Address) -> bool {
+        self.conf.prover_address == Some(prover)
+    }
+}
+
+/// Outcome of pricing an order in the simulation
+enum Priced {
+    Accept { target_timestamp_secs: u64, cycles: u64, order_gas_cost: U256 },
+    Skip(SkipReason),
+    Defer(u64),
+}
+
+/// State of a running backtest
+struct Simulation<'a> {
+    backtest: &'a Backtest,
+    history: &'a History,
+    report: BacktestReport,
+    /// Index of the next request to be submitted
+    next_request: usize,
+    /// Indexes of requests locked by other provers, ordered by lock time
+    other_locks: Vec<usize>,
+    next_lock: usize,
+    idx_by_order: HashMap<String, usize>,
+    pending: Vec<Box<OrderRequest>>,
+    deferred: Vec<(u64, Box<OrderRequest>)>,
+    committed: Vec<Committed>,
+    locked_by_us: HashSet<U256>,
+    locks: Vec<Lock>,
+    /// Time at which each worker of the simulated prover is next idle
+    workers: Vec<u64>,
+    /// Completion times of the proofs committed to
+    proofs: Vec<u64>,
+}
+
+impl<'a> Simulation<'a> {
+    fn new(backtest: &'a Backtest, history: &'a History) -> Self {
+        let mut other_locks: Vec<usize> = history
+            .requests
+            .iter()
+            .enumerate()
+            .filter(|(_, request)| {
+                request.locked.is_some_and(|(prover, _)| !backtest.is_us(prover))
+            })
+            .map(|(idx, _)| idx)
+            .collect();
+        other_locks.sort_by_key(|idx| history.requests[*idx].locked.map(|(_, at)| at));
+
+        Self {
+            backtest,
+            history,
+            report: BacktestReport { requests: history.requests.len(), ..Default::default() },
+            next_request: 0,
+            other_locks,
+            next_lock: 0,
+            idx_by_order: HashMap::new(),
+            pending: Vec::new(),
+            deferred: Vec::new(),
+            committed: Vec::new(),
+            locked_by_us: HashSet::new(),
+            locks: Vec::new(),
+            workers: vec![0; backtest.conf.prover.workers.max(1)],
+            proofs: Vec::new(),
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.next_request >= self.history.requests.len()
+            && self.next_lock >= self.other_locks.len()
+            && self.pending.is_empty()
+            && self.deferred.is_empty()
+            && self.committed.is_empty()
+    }
+
+    /// Lock of the request by another prover, if it happened at or before `now`
+    fn locked_by_other(&self, idx: usize, now: u64) -> Option<u64> {
+        self.history.requests[idx]
+            .locked
+            .filter(|(prover, at)| !self.backtest.is_us(*prover) && *at <= now)
+            .map(|(_, at)| at)
+    }
+
+    fn add_order(&mut self, idx: usize, fulfillment_type: FulfillmentType) {
+        let request = &self.history.requests[idx];
+        let order = Box::new(OrderRequest::new(
+            request.request.clone(),
+            request.client_sig.clone(),
+            fulfillment_type,
+            self.backtest.conf.boundless_market_address,
+            self.backtest.conf.chain_id,
+        ));
+        self.idx_by_order.insert(order.id(), idx);
+        self.pending.push(order);
+    }
+
+    /// Add the orders submitted, and the lock expired orders created, up to `now`
+    fn observe(&mut self, now: u64) -> Result<()> {
+        let history = self.history;
+        while let Some(request) = history.requests.get(self.next_request) {
+            if request.submitted_at > now {
+                break;
+            }
+            let fulfillment_type = FulfillmentType::for_unlocked_request(
+                &request.request,
+                &self.backtest.config,
+                self.backtest.conf.stake_token_decimals,
+            )?;
+            self.add_order(self.next_request, fulfillment_type);
+            self.next_request += 1;
+        }
+
+        while let Some(&idx) = self.other_locks.get(self.next_lock) {
+            if self.locked_by_other(idx, now).is_none() {
+                break;
+            }
+            self.next_lock += 1;
+            let request = &history.requests[idx].request;
+            // Requests we locked could not have been locked by another prover
+            if idx < self.next_request
+                && !self.locked_by_us.contains(&request.id)
+                && request.lock_expires_at() < request.expires_at()
+            {
+                self.add_order(idx, FulfillmentType::FulfillAfterLockExpire);
+            }
+        }
+
+        let (ready, deferred) =
+            std::mem::take(&mut self.deferred).into_iter().partition(|(at, _)| *at <= now);
+        self.deferred = deferred;
+        self.pending.extend(ready.into_iter().map(|(_, order)| order));
+        Ok(())
+    }
+
+    fn available_stake(&self, now: u64) -> U256 {
+        let (locked, slashed) = self.locks.iter().fold((U256::ZERO, U256::ZERO), |acc, lock| {
+            if lock.slashed && lock.until <= now {
+                (acc.0, acc.1 + lock.stake)
+            } else if lock.until > now {
+                (acc.0 + lock.stake, acc.1)
+            } else {
+                acc
+            }
+        });
+        self.backtest.conf.stake_balance.saturating_sub(locked).saturating_sub(slashed)
+    }
+
+    fn locked_stake(&self, now: u64) -> U256 {
+        self.locks.iter().filter(|lock| lock.until > now).map(|lock| lock.stake).sum()
+    }
+
+    /// Price up to `max_concurrent_preflights` pending orders, as the order picker would
+    async fn price_orders(&mut self, now: u64) -> Result<()> {
+        let (capacity, priority_mode, priority_addresses) = {
+            let config = self.backtest.config.lock_all().context("Failed to read config")?;
+            (
+                config.market.max_concurrent_preflights as usize,
+                config.market.order_pricing_priority,
+                config.market.priority_requestor_addresses.clone(),
+            )
+        };
+
+        sort_orders_by_priority_and_mode(
+            &mut self.pending,
+            priority_addresses.as_deref(),
+            priority_mode.into(),
+        );
+        let take_count = capacity.min(self.pending.len());
+        let orders: Vec<_> = self.pending.drain(..take_count).collect();
+
+        for mut order in orders {
+            let idx = self.idx_by_order[&order.id()];
+            self.report.orders_priced += 1;
+            match self.price_order(&mut order, idx, now).await? {
+                Priced::Accept { target_timestamp_secs, cycles, order_gas_cost } => {
+                    self.report.orders_accepted += 1;
+                    order.target_timestamp = Some(target_timestamp_secs);
+                    order.total_cycles = Some(cycles);
+                    self.committed.push(Committed {
+                        order: Arc::from(order),
+                        idx,
+                        cycles,
+                        order_gas_cost,
+                    });
+                }
+                Priced::Skip(reason) => self.report.skip(reason),
+                Priced::Defer(retry_at) => {
+                    if retry_at >= order.expiration() {
+                        self.report.skip(SkipReason::Expired);
+                    } else {
+                        self.deferred.push((retry_at, order));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn price_order(&self, order: &mut OrderRequest, idx: usize, now: u64) -> Result<Priced> {
+        let history = self.history;
+        let request = &history.requests[idx];
+        let backtest = self.backtest;
+        let lock_expired = order.fulfillment_type == FulfillmentType::FulfillAfterLockExpire;
+
+        if request.fulfilled_at.is_some_and(|at| at <= now) {
+            return Ok(Priced::Skip(SkipReason::AlreadyFulfilled));
+        }
+        if !lock_expired && self.locked_by_other(idx, now).is_some() {
+            return Ok(Priced::Skip(SkipReason::AlreadyLocked));
+        }
+        if !backtest.supported_selectors.is_supported(order.request.requirements.selector) {
+            return Ok(Priced::Skip(SkipReason::UnsupportedSelector));
+        }
+        if order.expiration() <= now {
+            return Ok(Priced::Skip(SkipReason::Expired));
+        }
+
+        let rules = {
+            let config = backtest.config.lock_all().context("Failed to read config")?;
+            rules::evaluate(&config.market.rules, order, backtest.conf.stake_token_decimals)?
+        };
+        let rule_overrides = match rules {
+            RuleOutcome::Deny { .. } => return Ok(Priced::Skip(SkipReason::Denylisted)),
+            RuleOutcome::Price(overrides) => overrides,
+        };
+        order.priority_boost = rule_overrides.priority_boost;
+
+        let order_gas = if order.fulfillment_type == FulfillmentType::LockAndFulfill {
+            utils::estimate_gas_to_lock(&backtest.config, order).await?
+                + utils::estimate_gas_to_fulfill(
+                    &backtest.config,
+                    &backtest.supported_selectors,
+                    &order.request,
+                )
+                .await?
+        } else {
+            utils::estimate_gas_to_fulfill(
+                &backtest.config,
+                &backtest.supported_selectors,
+                &order.request,
+            )
+            .await?
+        };
+        let order_gas_cost = U256::from(backtest.conf.gas_price) * U256::from(order_gas);
+
+        let ctx = PricingContext {
+            config: backtest.config.clone(),
+            now,
+            gas_price: backtest.conf.gas_price,
+            order_gas_cost,
+            available_gas: backtest.conf.gas_balance.saturating_sub(self.report.gas_spent),
+            available_stake: self.available_stake(now),
+            stake_token_decimals: backtest.conf.stake_token_decimals,
+            stake_token_price: if lock_expired { backtest.conf.stake_token_price } else { None },
+            prove_khz: Some(backtest.conf.prover.prove_khz),
+            rules: rule_overrides,
+        };
+
+        let exec_limit_cycles = match backtest.strategy.preflight_limit(order, &ctx).await? {
+            PreflightDecision::Preflight { exec_limit_cycles } => exec_limit_cycles,
+            PreflightDecision::Skip { reason } => return Ok(Priced::Skip(reason)),
+            PreflightDecision::Defer { retry_at } => return Ok(Priced::Defer(retry_at)),
+        };
+
+        let cycles = request.cycles.unwrap_or(backtest.conf.prover.default_cycles);
+        if cycles > exec_limit_cycles {
+            return Ok(Priced::Skip(SkipReason::SessionLimit));
+        }
+
+        let proof_res = ProofResult {
+            id: String::new(),
+            stats: ExecutorResp {
+                segments: 0,
+                user_cycles: cycles,
+                total_cycles: cycles,
+                assumption_count: 0,
+            },
+            elapsed_time: 0.0,
+        };
+        Ok(match backtest.strategy.price(order, &proof_res, &[], &ctx).await? {
+            PricingDecision::Accept { target_timestamp_secs } => {
+                Priced::Accept { target_timestamp_secs, cycles, order_gas_cost }
+            }
+            PricingDecision::Skip { reason } => Priced::Skip(reason),
+            PricingDecision::Defer { retry_at } => Priced::Defer(retry_at),
+        })
+    }
+
+    /// Commit to the priced orders whose target time has been reached, as the order monitor
+    /// would, and prove them on the simulated prover
+    fn commit_orders(&mut self, now: u64) -> Result<()> {
+        let (max_concurrent_proofs, min_deadline, priority_mode, priority_addresses) = {
+            let config = self.backtest.config.lock_all().context("Failed to read config")?;
+            (
+                config.market.max_concurrent_proofs,
+                config.market.min_deadline,
+                config.market.order_commitment_priority,
+                config.market.priority_requestor_addresses.clone(),
+            )
+        };
+
+        let history = self.history;
+        let mut candidates = Vec::new();
+        for committed in std::mem::take(&mut self.committed) {
+            let order = &committed.order;
+            let request = &history.requests[committed.idx];
+            let lock_expired = order.fulfillment_type == FulfillmentType::FulfillAfterLockExpire;
+            let deadline = if order.fulfillment_type == FulfillmentType::LockAndFulfill {
+                order.request.expires_at()
+            } else {
+                order.expiration()
+            };
+
+            if request.fulfilled_at.is_some_and(|at| at <= now) {
+                self.report.lose(SkipReason::AlreadyFulfilled);
+            } else if !lock_expired && self.locked_by_other(committed.idx, now).is_some() {
+                self.report.lose(SkipReason::AlreadyLocked);
+            } else if order.fulfillment_type == FulfillmentType::LockAndFulfill
+                && order.request.lock_expires_at() < now
+            {
+                self.report.skip(SkipReason::LockExpired);
+            } else if deadline < now || deadline.saturating_sub(now) < min_deadline {
+                self.report.skip(SkipReason::Expired);
+            } else if order.target_timestamp.is_some_and(|target| target <= now) {
+                candidates.push(committed);
+            } else {
+                self.committed.push(committed);
+            }
+        }
+
+        sort_orders_by_priority_and_mode(
+            &mut candidates,
+            priority_addresses.as_deref(),
+            priority_mode.into(),
+        );
+
+        self.proofs.retain(|done| *done > now);
+        let capacity = match max_concurrent_proofs {
+            Some(max) => (max as usize).saturating_sub(self.proofs.len()),
+            None => usize::MAX,
+        };
+        let deferred = candidates.split_off(capacity.min(candidates.len()));
+        self.committed.extend(deferred);
+
+        for committed in candidates {
+            self.prove(committed, now)?;
+        }
+        self.report.peak_locked_stake = self.report.peak_locked_stake.max(self.locked_stake(now));
+        Ok(())
+    }
+
+    /// Lock the order if required, then prove and fulfill it on the earliest free worker
+    fn prove(&mut self, committed: Committed, now: u64) -> Result<()> {
+        let Committed { order, idx, cycles, order_gas_cost } = committed;
+        let backtest = self.backtest;
+        let request = &self.history.requests[idx];
+        let prover = &backtest.conf.prover;
+
+        let worker = self.workers.iter_mut().min().context("Simulated prover has no workers")?;
+        let proved_at = (*worker).max(now) + prover.proving_secs(cycles);
+        *worker = proved_at;
+        self.proofs.push(proved_at);
+        let fulfilled_at = proved_at + prover.fulfill_delay_secs;
+        self.report.gas_spent += order_gas_cost;
+
+        // A fulfillment only earns a reward if it lands before any other prover's
+        let beaten = |at: Option<u64>| at.is_some_and(|at| at < fulfilled_at);
+        match order.fulfillment_type {
+            FulfillmentType::LockAndFulfill => {
+                let lock_price =
+                    order.request.offer.price_at(now).context("Failed to calculate lock price")?;
+                let stake = U256::from(order.request.offer.lockStake);
+                let lock_expires_at = order.request.lock_expires_at();
+                self.locked_by_us.insert(order.request.id);
+                if fulfilled_at > lock_expires_at {
+                    self.report.orders_slashed += 1;
+                    self.report.slashed_stake += stake;
+                    self.locks.push(Lock { stake, until: lock_expires_at, slashed: true });
+                } else {
+                    self.report.orders_won += 1;
+                    self.report.revenue += lock_price;
+                    self.locks.push(Lock { stake, until: fulfilled_at, slashed: false });
+                }
+            }
+            FulfillmentType::FulfillWithoutLocking => {
+                let locked_at = request.locked.filter(|(p, _)| !backtest.is_us(*p));
+                if beaten(request.fulfilled_at) {
+                    self.report.lose(SkipReason::AlreadyFulfilled);
+                } else if beaten(locked_at.map(|(_, at)| at)) {
+                    self.report.lose(SkipReason::AlreadyLocked);
+                } else if fulfilled_at > order.expiration() {
+                    self.report.skip(SkipReason::InsufficientCapacity);
+                } else {
+                    self.report.orders_won += 1;
+                    self.report.revenue += order
+                        .request
+                        .offer
+                        .price_at(fulfilled_at)
+                        .context("Failed to calculate fulfillment price")?;
+                }
+            }
+            FulfillmentType::FulfillAfterLockExpire => {
+                if beaten(request.fulfilled_at) {
+                    self.report.lose(SkipReason::AlreadyFulfilled);
+                } else if fulfilled_at > order.expiration() {
+                    self.report.skip(SkipReason::InsufficientCapacity);
+                } else {
+                    self.report.orders_won += 1;
+                    self.report.stake_revenue +=
+                        order.request.offer.stake_reward_if_locked_and_not_fulfilled();
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pricing::DefaultPricingStrategy;
+    use alloy::primitives::utils::parse_ether;
+    use boundless_market::contracts::{
+        Offer, Predicate, PredicateType, ProofRequest, RequestId, RequestInput, Requirements,
+    };
+    use risc0_zkvm::sha::Digest;
+
+    const START: u64 = 1_000_000;
+
+    fn request(index: u32) -> ProofRequest {
+        ProofRequest::new(
+            RequestId::new(Address::ZERO, index),
+            Requirements::new(
+                Digest::ZERO,
+                Predicate { predicateType: PredicateType::PrefixMatch, data: Default::default() },
+            ),
+            "",
+            RequestInput::builder().build_inline().unwrap(),
+            Offer {
+                minPrice: parse_ether("0.02").unwrap(),
+                maxPrice: parse_ether("0.04").unwrap(),
+                biddingStart: START,
+                timeout: 1200,
+                lockTimeout: 600,
+                rampUpPeriod: 1,
+                lockStake: U256::from(10),
+            },
+        )
+    }
+
+    fn conf(prove_khz: u64) -> BacktestConf {
+        BacktestConf {
+            prover: SimProverConf {
+                prove_khz,
+                workers: 1,
+                overhead_secs: 0,
+                fulfill_delay_secs: 10,
+                default_cycles: 100_000_000,
+            },
+            prover_address: None,
+            boundless_market_address: Address::ZERO,
+            chain_id: 1,
+            gas_price: 1,
+            gas_balance: parse_ether("10").unwrap(),
+            stake_balance: U256::from(100),
+            stake_token_decimals: 18,
+            stake_token_price: None,
+            tick_secs: 1,
+        }
+    }
+
+    fn history() -> History {
+        let other = Address::repeat_byte(1);
+        History::from_events([
+            HistoryEvent::RequestSubmitted {
+                request: request(1),
+                client_sig: Default::default(),
+                timestamp: START,
+                cycles: None,
+            },
+            HistoryEvent::RequestSubmitted {
+                request: request(2),
+                client_sig: Default::default(),
+                timestamp: START,
+                cycles: None,
+            },
+            // Request 2 is locked by another prover before the backtested prover can price it
+            HistoryEvent::RequestLocked {
+                request_id: request(2).id,
+                prover: other,
+                timestamp: START,
+            },
+            HistoryEvent::RequestFulfilled { request_id: request(2).id, timestamp: START + 100 },
+        ])
+    }
+
+    async fn run(prove_khz: u64) -> BacktestReport {
+        let config = ConfigLock::default();
+        config.load_write().unwrap().market.mcycle_price = "0.0000001".into();
+        Backtest::new(config, conf(prove_khz), Arc::new(DefaultPricingStrategy))
+            .run(&history())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn backtest_wins_unlocked_orders() {
+        let report = run(1_000).await;
+        assert_eq!(report.requests, 2);
+        assert_eq!(report.orders_won, 1);
+        assert_eq!(report.orders_slashed, 0);
+        assert_eq!(report.skip_reasons[&SkipReason::AlreadyLocked], 1);
+        // Locked as soon as it was priced, at the start of bidding
+        assert_eq!(report.revenue, parse_ether("0.02").unwrap());
+        assert!(report.gas_spent > U256::ZERO);
+        assert_eq!(report.peak_locked_stake, U256::from(10));
+    }
+
+    #[tokio::test]
+    async fn backtest_slashes_late_proofs() {
+        // 100M cycles at 100 kHz takes 1000s, past the 600s lock timeout
+        let report = run(100).await;
+        assert_eq!(report.orders_won, 0);
+        assert_eq!(report.orders_slashed, 1);
+        assert_eq!(report.slashed_stake, U256::from(10));
+        assert_eq!(report.revenue, U256::ZERO);
+    }
+}
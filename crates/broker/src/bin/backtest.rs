@@ -0,0 +1,220 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Replays market history through the pricing of a `broker.toml`, and reports the orders it
+//! would have won.
+
+use std::{path::PathBuf, sync::Arc};
+
+use alloy::{
+    primitives::{
+        utils::{format_ether, format_units, parse_ether, parse_units},
+        Address, U256,
+    },
+    providers::ProviderBuilder,
+};
+use anyhow::{bail, Context, Result};
+use broker::{
+    backtest::{Backtest, BacktestConf, History, SimProverConf},
+    config::{ConfigLock, StakePriceOracleConf},
+    pricing::PricingStrategies,
+    stake_price::{self, FixedStakePrice, StakePriceOracle, StakeTokenPrice},
+    Config,
+};
+use clap::Parser;
+use url::Url;
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Broker config to backtest
+    #[clap(short, long, default_value = "broker.toml")]
+    config_file: PathBuf,
+
+    /// JSONL export of market events to replay
+    #[clap(long, conflicts_with = "indexer_db_url")]
+    history: Option<PathBuf>,
+
+    /// Indexer database to load market events from
+    #[clap(long, env)]
+    indexer_db_url: Option<String>,
+
+    /// Start of the range of requests to load from the indexer, as a UNIX timestamp
+    #[clap(long, default_value_t = 0)]
+    from: u64,
+
+    /// End of the range of requests to load from the indexer, as a UNIX timestamp
+    #[clap(long, default_value_t = u64::MAX >> 1)]
+    to: u64,
+
+    /// Proving throughput of each simulated prover worker, in kHz
+    ///
+    /// Defaults to `market.peak_prove_khz` of the config.
+    #[clap(long)]
+    prove_khz: Option<u64>,
+
+    /// Number of proofs the simulated prover runs in parallel
+    #[clap(long, default_value_t = 1)]
+    workers: usize,
+
+    /// Fixed time spent on each proof, in seconds
+    #[clap(long, default_value_t = 30)]
+    proof_overhead_secs: u64,
+
+    /// Time from a proof completing to its fulfillment landing on chain, in seconds
+    #[clap(long, default_value_t = 60)]
+    fulfill_delay_secs: u64,
+
+    /// Cycle count of requests without one in the history, in mcycles
+    #[clap(long, default_value_t = 100)]
+    default_mcycles: u64,
+
+    /// Address of the prover being simulated, whose historical locks are ignored
+    #[clap(long)]
+    prover_address: Option<Address>,
+
+    /// Gas price used for all orders, in gwei
+    #[clap(long, default_value = "0.1")]
+    gas_price: String,
+
+    /// Gas token balance at the start of the backtest, in ether
+    #[clap(long, default_value = "1")]
+    gas_balance: String,
+
+    /// Stake token balance at the start of the backtest
+    #[clap(long, default_value = "100")]
+    stake_balance: String,
+
+    /// Decimals of the stake token
+    #[clap(long, default_value_t = 18)]
+    stake_token_decimals: u8,
+
+    /// RPC URL to read the stake token price from, when `market.stake_price_oracle` is not fixed
+    #[clap(long, env)]
+    rpc_url: Option<Url>,
+
+    /// Address of the stake token, when `market.stake_price_oracle` is a Uniswap V2 pool
+    #[clap(long)]
+    stake_token: Option<Address>,
+
+    /// Length of a simulation step, in seconds
+    #[clap(long, default_value_t = 2)]
+    tick_secs: u64,
+
+    /// Print the report as JSON
+    #[clap(long, default_value_t = false)]
+    json: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .init();
+
+    let config = Config::load(&args.config_file).await?;
+    let prove_khz = match args.prove_khz.or(config.market.peak_prove_khz) {
+        Some(prove_khz) => prove_khz,
+        None => bail!("--prove-khz is required when market.peak_prove_khz is not set"),
+    };
+
+    let pricing_strategy =
+        PricingStrategies::default().get(&config.market.pricing_strategy).with_context(|| {
+            format!("Unknown pricing strategy configured: {}", config.market.pricing_strategy)
+        })?;
+    let stake_token_price = match &config.market.stake_price_oracle {
+        Some(conf) => Some(stake_token_price(&args, conf).await?),
+        None => None,
+    };
+
+    let history = match (&args.history, &args.indexer_db_url) {
+        (Some(path), _) => History::from_jsonl(path).await?,
+        (None, Some(db_url)) => History::from_indexer(db_url, args.from, args.to).await?,
+        (None, None) => bail!("One of --history or --indexer-db-url is required"),
+    };
+
+    let conf = BacktestConf {
+        prover: SimProverConf {
+            prove_khz,
+            workers: args.workers,
+            overhead_secs: args.proof_overhead_secs,
+            fulfill_delay_secs: args.fulfill_delay_secs,
+            default_cycles: args.default_mcycles.saturating_mul(1_000_000),
+        },
+        prover_address: args.prover_address,
+        boundless_market_address: Address::ZERO,
+        chain_id: 0,
+        gas_price: U256::from(
+            parse_units(&args.gas_price, "gwei").context("Failed to parse gas price")?,
+        )
+        .try_into()
+        .context("Gas price too large")?,
+        gas_balance: parse_ether(&args.gas_balance).context("Failed to parse gas balance")?,
+        stake_balance: parse_units(&args.stake_balance, args.stake_token_decimals)
+            .context("Failed to parse stake balance")?
+            .into(),
+        stake_token_decimals: args.stake_token_decimals,
+        stake_token_price,
+        tick_secs: args.tick_secs,
+    };
+
+    let backtest = Backtest::new(ConfigLock::from_config(config), conf, pricing_strategy);
+    let report = backtest.run(&history).await?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    let stake = |amount: U256| format_units(amount, args.stake_token_decimals).unwrap_or_default();
+    println!("Requests replayed:   {}", report.requests);
+    println!("Orders priced:       {}", report.orders_priced);
+    println!("Orders accepted:     {}", report.orders_accepted);
+    println!("Orders won:          {}", report.orders_won);
+    println!("Orders lost:         {}", report.orders_lost);
+    println!("Orders slashed:      {}", report.orders_slashed);
+    println!("Revenue:             {} ETH", format_ether(report.revenue));
+    println!("Stake revenue:       {}", stake(report.stake_revenue));
+    println!("Gas spent:           {} ETH", format_ether(report.gas_spent));
+    println!("Slashed stake:       {}", stake(report.slashed_stake));
+    println!("Peak locked stake:   {}", stake(report.peak_locked_stake));
+    println!("Skipped orders:");
+    for (reason, count) in report.skip_reasons.iter() {
+        println!("  {reason}: {count}");
+    }
+
+    Ok(())
+}
+
+/// Read the stake token price from the configured oracle, used for the whole backtest
+async fn stake_token_price(args: &Args, conf: &StakePriceOracleConf) -> Result<StakeTokenPrice> {
+    if let StakePriceOracleConf::Fixed { price } = conf {
+        return FixedStakePrice::new(price, args.stake_token_decimals)?.price().await;
+    }
+
+    let Some(rpc_url) = args.rpc_url.clone() else {
+        bail!("--rpc-url is required to read the stake token price from the configured oracle");
+    };
+    let stake_token = match (conf, args.stake_token) {
+        (StakePriceOracleConf::UniswapV2 { .. }, None) => {
+            bail!("--stake-token is required to read the stake token price from a Uniswap V2 pool")
+        }
+        (_, stake_token) => stake_token.unwrap_or_default(),
+    };
+    let provider = Arc::new(ProviderBuilder::new().connect_http(rpc_url));
+    let oracle =
+        stake_price::from_config(conf, provider, stake_token, args.stake_token_decimals).await?;
+    oracle.price().await.context("Failed to read the stake token price")
+}
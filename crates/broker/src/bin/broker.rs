@@ -13,8 +13,11 @@
 // limitations under the License.
 
 use alloy::{
+    network::Ethereum,
     primitives::utils::parse_ether,
-    providers::{fillers::ChainIdFiller, network::EthereumWallet, ProviderBuilder, WalletProvider},
+    providers::{
+        fillers::ChainIdFiller, network::EthereumWallet, Provider, ProviderBuilder, WalletProvider,
+    },
     rpc::client::RpcClient,
     transports::layers::RetryBackoffLayer,
 };
@@ -23,11 +26,12 @@ use boundless_market::{
     balance_alerts_layer::{BalanceAlertConfig, BalanceAlertLayer},
     contracts::boundless_market::BoundlessMarketService,
     dynamic_gas_filler::DynamicGasFiller,
-    nonce_layer::NonceProvider,
+    nonce_layer::{NonceProvider, ReservedNonces},
 };
 use broker::{Args, Broker, Config, CustomRetryPolicy};
 use clap::Parser;
 use tracing_subscriber::fmt::format::FmtSpan;
+use url::Url;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -48,6 +52,44 @@ async fn main() -> Result<()> {
             .init();
     }
 
+    let (provider, reserved_nonces) = build_provider(&args, &config, args.rpc_url.clone())?;
+    let mut broker =
+        Broker::new(args.clone(), provider.clone()).await?.with_reserved_nonces(reserved_nonces);
+    for chain in &config.chains {
+        let rpc_url = Url::parse(&chain.rpc_url).context("Failed to parse chain RPC URL")?;
+        let (chain_provider, reserved_nonces) = build_provider(&args, &config, rpc_url)?;
+        broker =
+            broker.with_chain(chain_provider, chain).await?.with_reserved_nonces(reserved_nonces);
+    }
+
+    // One-off deposit at startup. Set `treasury.stake_target` to keep the stake topped up.
+    if let Some(deposit_amount) = args.deposit_amount.as_ref() {
+        let boundless_market = BoundlessMarketService::new(
+            broker.deployment().boundless_market_address,
+            provider.clone(),
+            provider.default_signer_address(),
+        );
+
+        tracing::info!("pre-depositing {deposit_amount} stake tokens into the market contract");
+        boundless_market
+            .deposit_stake_with_permit(*deposit_amount, &args.private_key)
+            .await
+            .context("Failed to deposit to market")?;
+    }
+
+    // Await broker shutdown before returning from main
+    broker.start_service().await.context("Broker service failed")?;
+
+    Ok(())
+}
+
+/// Build the provider used to send transactions on the chain of `rpc_url`, along with the nonces
+/// its nonce source skips because the transaction manager reserved them
+fn build_provider(
+    args: &Args,
+    config: &Config,
+    rpc_url: Url,
+) -> Result<(impl Provider<Ethereum> + WalletProvider + Clone + 'static, ReservedNonces)> {
     let wallet = EthereumWallet::from(args.private_key.clone());
 
     let retry_layer = RetryBackoffLayer::new_with_policy(
@@ -56,18 +98,20 @@ async fn main() -> Result<()> {
         args.rpc_retry_cu,
         CustomRetryPolicy,
     );
-    let client = RpcClient::builder().layer(retry_layer).http(args.rpc_url.clone());
+    let client = RpcClient::builder().layer(retry_layer).http(rpc_url);
     let balance_alerts_layer = BalanceAlertLayer::new(BalanceAlertConfig {
         watch_address: wallet.default_signer().address(),
         warn_threshold: config
             .market
             .balance_warn_threshold
-            .map(|s| parse_ether(&s))
+            .as_deref()
+            .map(parse_ether)
             .transpose()?,
         error_threshold: config
             .market
             .balance_error_threshold
-            .map(|s| parse_ether(&s))
+            .as_deref()
+            .map(parse_ether)
             .transpose()?,
     });
 
@@ -85,26 +129,7 @@ async fn main() -> Result<()> {
         .layer(balance_alerts_layer)
         .connect_client(client);
 
-    let provider = NonceProvider::new(base_provider, wallet.clone());
-    let broker = Broker::new(args.clone(), provider.clone()).await?;
-
-    // TODO: Move this code somewhere else / monitor our balanceOf and top it up as needed
-    if let Some(deposit_amount) = args.deposit_amount.as_ref() {
-        let boundless_market = BoundlessMarketService::new(
-            broker.deployment().boundless_market_address,
-            provider.clone(),
-            provider.default_signer_address(),
-        );
-
-        tracing::info!("pre-depositing {deposit_amount} stake tokens into the market contract");
-        boundless_market
-            .deposit_stake_with_permit(*deposit_amount, &args.private_key)
-            .await
-            .context("Failed to deposit to market")?;
-    }
-
-    // Await broker shutdown before returning from main
-    broker.start_service().await.context("Broker service failed")?;
-
-    Ok(())
+    let provider = NonceProvider::new(base_provider, wallet);
+    let reserved_nonces = provider.reserved_nonces();
+    Ok((provider, reserved_nonces))
 }
@@ -0,0 +1,121 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Summarizes the profit and loss ledger recorded by a broker, by day, chain, requestor or image
+//! ID.
+
+use alloy::primitives::{
+    utils::{format_ether, format_units},
+    I256, U256,
+};
+use anyhow::{Context, Result};
+use broker::ledger::{self, GroupBy, LedgerSummary};
+use chrono::{DateTime, Duration, Utc};
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Database connection url of the broker, either sqlite or postgres
+    #[clap(short = 's', long, env)]
+    db_url: String,
+
+    /// Only report the ledger entries of this chain
+    #[clap(long)]
+    chain_id: Option<u64>,
+
+    /// Key the ledger entries are grouped by
+    #[clap(long, value_enum, default_value = "day")]
+    group_by: GroupBy,
+
+    /// Start of the reported window, as a UNIX timestamp
+    ///
+    /// Defaults to 30 days before the end of the window.
+    #[clap(long)]
+    from: Option<i64>,
+
+    /// End of the reported window, as a UNIX timestamp
+    ///
+    /// Defaults to now.
+    #[clap(long)]
+    to: Option<i64>,
+
+    /// Decimals of the stake token
+    #[clap(long, default_value_t = 6)]
+    stake_token_decimals: u8,
+
+    /// Print the report as JSON
+    #[clap(long, default_value_t = false)]
+    json: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .init();
+
+    let to = match args.to {
+        Some(to) => DateTime::from_timestamp(to, 0).context("Invalid --to timestamp")?,
+        None => Utc::now(),
+    };
+    let from = match args.from {
+        Some(from) => DateTime::from_timestamp(from, 0).context("Invalid --from timestamp")?,
+        None => to - Duration::days(30),
+    };
+
+    let report = ledger::load_report(&args.db_url, from, to, args.chain_id, args.group_by).await?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    let stake = |amount: U256| format_units(amount, args.stake_token_decimals).unwrap_or_default();
+    let signed_stake = |amount: I256| {
+        let sign = if amount.is_negative() { "-" } else { "" };
+        format!("{sign}{}", stake(amount.unsigned_abs()))
+    };
+    let signed_ether = |amount: I256| {
+        let sign = if amount.is_negative() { "-" } else { "" };
+        format!("{sign}{}", format_ether(amount.unsigned_abs()))
+    };
+    let print_summary = |summary: &LedgerSummary| {
+        println!("  Orders:          {}", summary.orders);
+        println!("  Payments:        {} ETH", format_ether(summary.payments));
+        println!("  Gas spent:       {} ETH", format_ether(summary.gas));
+        println!("  Profit:          {} ETH", signed_ether(summary.profit()));
+        println!("  Stake locked:    {}", stake(summary.stake_locked));
+        println!("  Stake returned:  {}", stake(summary.stake_returned));
+        println!("  Stake rewards:   {}", stake(summary.stake_rewards));
+        println!("  Stake slashed:   {}", stake(summary.stake_slashed));
+        println!("  Stake profit:    {}", signed_stake(summary.stake_profit()));
+    };
+
+    match report.chain_id {
+        Some(chain_id) => {
+            println!("Ledger of chain {chain_id} from {} to {}", report.from, report.to)
+        }
+        None => println!("Ledger from {} to {}", report.from, report.to),
+    }
+    for (key, summary) in report.groups.iter() {
+        println!("{key}:");
+        print_summary(summary);
+    }
+    println!("Total:");
+    print_summary(&report.total);
+
+    Ok(())
+}
@@ -0,0 +1,378 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Content-addressed store of downloaded images and inputs.
+//!
+//! When `market.cache_dir` is set, the contents fetched from content-addressed request URLs, and
+//! the verified programs of requests, are stored in the cache directory under the SHA-256 digest
+//! of the contents, in `blobs/`. Blobs are found through references in `refs/`, named by the
+//! digest of the key they were stored under: the URL they were fetched from, or the image ID of a
+//! program. Referencing programs by image ID allows images uploaded to the prover before a restart
+//! to be restored without fetching them again.
+//!
+//! Blobs are verified against their digest when read, and the store is bounded to
+//! `market.max_cache_size` bytes by evicting the least recently used blobs. The modification time
+//! of a blob is used as its last access time. The sizes and access times of the blobs are
+//! scanned from the directory on first use, and tracked in memory from then on.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock},
+    time::SystemTime,
+};
+
+use risc0_zkvm::sha::{Impl as ShaImpl, Sha256};
+use tokio::sync::{Mutex, MutexGuard, OnceCell};
+
+use crate::config::ConfigLock;
+
+const BLOBS_DIR: &str = "blobs";
+const REFS_DIR: &str = "refs";
+
+/// Key referencing the contents fetched from a URL
+pub(crate) fn uri_key(uri: &str) -> String {
+    format!("uri:{uri}")
+}
+
+/// Key referencing the program with the given image ID
+pub(crate) fn image_key(image_id: &str) -> String {
+    format!("image:{image_id}")
+}
+
+/// Index of the blobs in a store directory, loaded from the directory on first use
+type SharedIndex = Arc<OnceCell<Mutex<BlobIndex>>>;
+
+/// Indexes of the store directories from the config, shared by the stores over each directory
+static INDEXES: LazyLock<std::sync::Mutex<HashMap<PathBuf, SharedIndex>>> =
+    LazyLock::new(Default::default);
+
+/// Last access times and sizes of the blobs in a store, by digest
+#[derive(Debug, Default)]
+struct BlobIndex {
+    blobs: HashMap<String, (SystemTime, u64)>,
+    total_size: u64,
+}
+
+impl BlobIndex {
+    fn insert(&mut self, digest: String, accessed_at: SystemTime, size: u64) {
+        if let Some((_, prev_size)) = self.blobs.insert(digest, (accessed_at, size)) {
+            self.total_size -= prev_size;
+        }
+        self.total_size += size;
+    }
+
+    fn remove(&mut self, digest: &str) {
+        if let Some((_, size)) = self.blobs.remove(digest) {
+            self.total_size -= size;
+        }
+    }
+
+    fn touch(&mut self, digest: &str, accessed_at: SystemTime) {
+        if let Some(entry) = self.blobs.get_mut(digest) {
+            entry.0 = accessed_at;
+        }
+    }
+
+    /// Digest of the least recently used blob
+    fn least_recently_used(&self) -> Option<String> {
+        self.blobs
+            .iter()
+            .min_by_key(|(_, (accessed_at, _))| *accessed_at)
+            .map(|(digest, _)| digest.clone())
+    }
+}
+
+/// Size-bounded, content-addressed blob store on the local filesystem
+#[derive(Clone, Debug)]
+pub(crate) struct BlobStore {
+    dir: PathBuf,
+    max_size: u64,
+    index: SharedIndex,
+}
+
+impl BlobStore {
+    pub(crate) fn new(dir: impl Into<PathBuf>, max_size: u64) -> Self {
+        Self { dir: dir.into(), max_size, index: SharedIndex::default() }
+    }
+
+    /// Blob store in `market.cache_dir`, if set
+    pub(crate) fn from_config(config: &ConfigLock) -> Option<Self> {
+        let config = match config.lock_all() {
+            Ok(config) => config,
+            Err(err) => {
+                tracing::error!("Failed to read config: {err:?}");
+                return None;
+            }
+        };
+        let dir = config.market.cache_dir.clone()?;
+        let index = INDEXES.lock().unwrap().entry(dir.clone()).or_default().clone();
+        Some(Self { dir, max_size: config.market.max_cache_size, index })
+    }
+
+    /// Lock the index of the blobs, scanning the blobs directory on first use
+    async fn index(&self) -> std::io::Result<MutexGuard<'_, BlobIndex>> {
+        let index =
+            self.index.get_or_try_init(|| async { self.scan().await.map(Mutex::new) }).await?;
+        Ok(index.lock().await)
+    }
+
+    async fn scan(&self) -> std::io::Result<BlobIndex> {
+        let mut index = BlobIndex::default();
+        let mut entries = match tokio::fs::read_dir(self.dir.join(BLOBS_DIR)).await {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(index),
+            Err(err) => return Err(err),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !metadata.is_file() || name.starts_with('.') {
+                continue;
+            }
+            index.insert(name, metadata.modified()?, metadata.len());
+        }
+        Ok(index)
+    }
+
+    fn blob_path(&self, digest: &str) -> PathBuf {
+        self.dir.join(BLOBS_DIR).join(digest)
+    }
+
+    fn ref_path(&self, key: &str) -> PathBuf {
+        self.dir.join(REFS_DIR).join(ShaImpl::hash_bytes(key.as_bytes()).to_string())
+    }
+
+    /// Get the blob referenced by the key
+    ///
+    /// Missing blobs, and blobs not matching their digest, are treated as a miss, as are
+    /// filesystem errors, which are logged.
+    pub(crate) async fn get(&self, key: &str) -> Option<Vec<u8>> {
+        match self.try_get(key).await {
+            Ok(data) => data,
+            Err(err) => {
+                tracing::warn!("Failed to read {key} from cache: {err:?}");
+                None
+            }
+        }
+    }
+
+    async fn try_get(&self, key: &str) -> std::io::Result<Option<Vec<u8>>> {
+        let ref_path = self.ref_path(key);
+        let Some(digest) = read_optional(&ref_path).await? else {
+            return Ok(None);
+        };
+        let Some(digest) = String::from_utf8(digest)
+            .ok()
+            .filter(|digest| digest.len() == 64 && digest.bytes().all(|b| b.is_ascii_hexdigit()))
+        else {
+            tracing::warn!("Removing invalid cache reference of {key}");
+            remove_optional(&ref_path).await?;
+            return Ok(None);
+        };
+
+        let blob_path = self.blob_path(&digest);
+        let Some(data) = read_optional(&blob_path).await? else {
+            // The blob was evicted
+            remove_optional(&ref_path).await?;
+            return Ok(None);
+        };
+        if ShaImpl::hash_bytes(&data).to_string() != digest {
+            tracing::warn!(
+                "Removing cached blob {digest} of {key}, which does not match its digest"
+            );
+            let mut index = self.index().await?;
+            remove_optional(&blob_path).await?;
+            index.remove(&digest);
+            remove_optional(&ref_path).await?;
+            return Ok(None);
+        }
+
+        let accessed_at = touch(&blob_path).await?;
+        self.index().await?.touch(&digest, accessed_at);
+        Ok(Some(data))
+    }
+
+    /// Store a blob, referenced by the key, evicting the least recently used blobs if the store
+    /// exceeds its maximum size
+    ///
+    /// Blobs larger than the maximum size are not stored. Filesystem errors are logged.
+    pub(crate) async fn put(&self, key: &str, data: &[u8]) {
+        if data.len() as u64 > self.max_size {
+            tracing::debug!("Not caching {key}, as it exceeds the max cache size");
+            return;
+        }
+        if let Err(err) = self.try_put(key, data).await {
+            tracing::warn!("Failed to write {key} to cache: {err:?}");
+        }
+    }
+
+    async fn try_put(&self, key: &str, data: &[u8]) -> std::io::Result<()> {
+        let digest = ShaImpl::hash_bytes(data).to_string();
+        let blob_path = self.blob_path(&digest);
+        // Writes are serialized by the index lock, so the index matches the blobs directory
+        let mut index = self.index().await?;
+        let accessed_at = if tokio::fs::try_exists(&blob_path).await? {
+            touch(&blob_path).await?
+        } else {
+            write_atomic(&blob_path, data).await?;
+            SystemTime::now()
+        };
+        index.insert(digest.clone(), accessed_at, data.len() as u64);
+        write_atomic(&self.ref_path(key), digest.as_bytes()).await?;
+
+        self.evict(&mut index).await
+    }
+
+    /// Remove the reference of the key, if any
+    pub(crate) async fn remove(&self, key: &str) {
+        if let Err(err) = remove_optional(&self.ref_path(key)).await {
+            tracing::warn!("Failed to remove {key} from cache: {err:?}");
+        }
+    }
+
+    /// Evict the least recently used blobs until the store is within its maximum size
+    ///
+    /// References to evicted blobs are removed when next read.
+    async fn evict(&self, index: &mut BlobIndex) -> std::io::Result<()> {
+        while index.total_size > self.max_size {
+            let Some(digest) = index.least_recently_used() else {
+                break;
+            };
+            tracing::debug!("Evicting blob {digest} from cache");
+            remove_optional(&self.blob_path(&digest)).await?;
+            index.remove(&digest);
+        }
+        Ok(())
+    }
+}
+
+async fn read_optional(path: &Path) -> std::io::Result<Option<Vec<u8>>> {
+    match tokio::fs::read(path).await {
+        Ok(data) => Ok(Some(data)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+async fn remove_optional(path: &Path) -> std::io::Result<()> {
+    match tokio::fs::remove_file(path).await {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// Write a file through a temporary file, so concurrent readers never see partial contents
+async fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let dir = path.parent().expect("cache paths have a parent");
+    tokio::fs::create_dir_all(dir).await?;
+    let tmp_path = dir.join(format!(".{:016x}.tmp", rand::random::<u64>()));
+    tokio::fs::write(&tmp_path, data).await?;
+    if let Err(err) = tokio::fs::rename(&tmp_path, path).await {
+        remove_optional(&tmp_path).await?;
+        return Err(err);
+    }
+    Ok(())
+}
+
+/// Mark a blob as accessed now, returning the access time
+async fn touch(path: &Path) -> std::io::Result<SystemTime> {
+    let file = tokio::fs::File::options().append(true).open(path).await?.into_std().await;
+    let accessed_at = SystemTime::now();
+    tokio::task::spawn_blocking(move || file.set_modified(accessed_at)).await??;
+    Ok(accessed_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn get_put() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = BlobStore::new(dir.path(), 1024);
+
+        assert_eq!(store.get(&uri_key("https://example.com/input")).await, None);
+        store.put(&uri_key("https://example.com/input"), b"input").await;
+        store.put(&image_key("image"), b"input").await;
+        assert_eq!(store.get(&uri_key("https://example.com/input")).await.unwrap(), b"input");
+        assert_eq!(store.get(&image_key("image")).await.unwrap(), b"input");
+
+        // Both keys reference the same blob
+        let blobs = std::fs::read_dir(dir.path().join(BLOBS_DIR)).unwrap().count();
+        assert_eq!(blobs, 1);
+
+        store.remove(&image_key("image")).await;
+        assert_eq!(store.get(&image_key("image")).await, None);
+        assert!(store.get(&uri_key("https://example.com/input")).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn integrity() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = BlobStore::new(dir.path(), 1024);
+
+        store.put("key", b"data").await;
+        let blob_path = store.blob_path(&ShaImpl::hash_bytes(b"data").to_string());
+        std::fs::write(&blob_path, b"corrupted").unwrap();
+
+        assert_eq!(store.get("key").await, None);
+        assert!(!blob_path.exists());
+    }
+
+    #[tokio::test]
+    async fn evict_least_recently_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = BlobStore::new(dir.path(), 20);
+
+        store.put("a", &[1; 8]).await;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        store.put("b", &[2; 8]).await;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        // Reading a makes b the least recently used blob
+        assert!(store.get("a").await.is_some());
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        store.put("c", &[3; 8]).await;
+
+        assert!(store.get("a").await.is_some());
+        assert_eq!(store.get("b").await, None);
+        assert!(store.get("c").await.is_some());
+
+        // Blobs larger than the store are not cached
+        store.put("d", &[4; 21]).await;
+        assert_eq!(store.get("d").await, None);
+        assert!(store.get("c").await.is_some());
+    }
+
+    #[tokio::test]
+    async fn index_scanned_from_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = BlobStore::new(dir.path(), 20);
+        store.put("a", &[1; 8]).await;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        store.put("b", &[2; 8]).await;
+
+        // A store over the same directory, as after a restart, evicts the blobs written before
+        let store = BlobStore::new(dir.path(), 20);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        store.put("c", &[3; 8]).await;
+
+        assert_eq!(store.get("a").await, None);
+        assert!(store.get("b").await.is_some());
+        assert!(store.get("c").await.is_some());
+        let blobs = std::fs::read_dir(dir.path().join(BLOBS_DIR)).unwrap().count();
+        assert_eq!(blobs, 2);
+    }
+}
@@ -14,7 +14,8 @@
 
 use alloy_chains::NamedChain;
 use std::{
-    sync::Arc,
+    collections::VecDeque,
+    sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
 use tokio::sync::{watch, Notify, RwLock};
@@ -49,6 +50,9 @@ impl CodedError for ChainMonitorErr {
     }
 }
 
+/// Number of observed blocks to keep the base fee of
+const BASE_FEE_HISTORY: usize = 32;
+
 #[derive(Clone, Debug, Copy)]
 pub(crate) struct ChainHead {
     pub block_number: u64,
@@ -62,6 +66,8 @@ pub struct ChainMonitorService<P> {
     update_notifier: Arc<Notify>,
     next_update: Arc<RwLock<Instant>>,
     head_update: watch::Sender<ChainHead>,
+    /// Base fees of the most recently observed blocks, oldest first, as (block number, base fee)
+    base_fees: Arc<Mutex<VecDeque<(u64, u128)>>>,
 }
 
 impl<P: Provider> ChainMonitorService<P> {
@@ -75,6 +81,7 @@ impl<P: Provider> ChainMonitorService<P> {
             update_notifier: Arc::new(Notify::new()),
             next_update: Arc::new(RwLock::new(Instant::now())),
             head_update,
+            base_fees: Arc::new(Mutex::new(VecDeque::with_capacity(BASE_FEE_HISTORY))),
         })
     }
 
@@ -108,6 +115,24 @@ impl<P: Provider> ChainMonitorService<P> {
             Ok(*self.gas_price.borrow())
         }
     }
+
+    /// Returns the base fees of the most recently observed blocks, oldest first.
+    ///
+    /// Blocks are observed as the chain head is updated, so this does not include every block.
+    pub(crate) fn recent_base_fees(&self) -> Vec<u128> {
+        self.base_fees.lock().unwrap().iter().map(|(_, base_fee)| *base_fee).collect()
+    }
+
+    fn record_base_fee(&self, block_number: u64, base_fee: u128) {
+        let mut base_fees = self.base_fees.lock().unwrap();
+        if base_fees.back().is_some_and(|(last_block, _)| *last_block >= block_number) {
+            return;
+        }
+        base_fees.push_back((block_number, base_fee));
+        while base_fees.len() > BASE_FEE_HISTORY {
+            base_fees.pop_front();
+        }
+    }
 }
 
 impl<P> RetryTask for ChainMonitorService<P>
@@ -160,6 +185,9 @@ where
                             block_timestamp: block.header.timestamp,
                         };
                         let _ = self_clone.head_update.send_replace(head);
+                        if let Some(base_fee) = block.header.base_fee_per_gas {
+                            self_clone.record_base_fee(head.block_number, base_fee.into());
+                        }
 
                         let gas_price = gas_price_res
                             .context("failed to get gas price")
@@ -226,5 +254,10 @@ mod tests {
 
         let block = chain_monitor.current_block_number().await.unwrap();
         assert_eq!(block, NUM_BLOCKS);
+
+        // The base fee of each observed head is recorded once
+        assert_eq!(chain_monitor.recent_base_fees().len(), 2);
+        chain_monitor.current_block_number().await.unwrap();
+        assert_eq!(chain_monitor.recent_base_fees().len(), 2);
     }
 }
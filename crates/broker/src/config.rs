@@ -18,7 +18,7 @@ use std::{
     sync::{Arc, RwLock},
 };
 
-use alloy::primitives::Address;
+use alloy::primitives::{Address, FixedBytes, B256};
 use anyhow::{Context, Result};
 use notify::{EventKind, Watcher};
 use serde::{Deserialize, Serialize};
@@ -77,6 +77,87 @@ mod defaults {
     pub const fn max_concurrent_preflights() -> u32 {
         4
     }
+
+    pub const fn max_preflight_secs() -> u64 {
+        // 5 minutes
+        5 * 60
+    }
+
+    pub const fn fulfill_without_locking_premium_bps() -> u64 {
+        // 20% over mcycle_price
+        2_000
+    }
+
+    pub const fn stake_price_max_age_secs() -> u64 {
+        // 1 day, the heartbeat of most Chainlink feeds
+        24 * 60 * 60
+    }
+
+    pub const fn lock_bid_outbid_bps() -> u64 {
+        // 10% over the highest competing priority fee
+        1_000
+    }
+
+    pub const fn lock_bid_lookback_blocks() -> u64 {
+        10
+    }
+
+    pub const fn txn_fee_bump_bps() -> u64 {
+        // Nodes require replacements to raise fees by at least 10%
+        1_250
+    }
+
+    pub const fn backend_retry_secs() -> u64 {
+        60
+    }
+
+    pub const fn preflight_cache_max_age_secs() -> u64 {
+        // 7 days
+        7 * 24 * 60 * 60
+    }
+
+    pub const fn preflight_cache_max_entries() -> u64 {
+        100_000
+    }
+
+    pub const fn history_max_age_secs() -> u64 {
+        // 30 days
+        30 * 24 * 60 * 60
+    }
+
+    pub const fn history_max_entries() -> u64 {
+        100_000
+    }
+
+    pub const fn prove_khz_window() -> usize {
+        100
+    }
+
+    pub const fn prove_khz_min_samples() -> usize {
+        5
+    }
+
+    pub const fn max_cache_size() -> u64 {
+        // 10 GB
+        10_000_000_000
+    }
+
+    pub const fn treasury_interval_secs() -> u64 {
+        // 10 minutes
+        10 * 60
+    }
+
+    pub const fn stake_deposit_permit() -> bool {
+        true
+    }
+
+    pub fn ipfs_gateways() -> Vec<String> {
+        vec!["https://ipfs.io".to_string()]
+    }
+
+    pub fn pricing_strategy() -> String {
+        crate::pricing::DEFAULT_PRICING_STRATEGY.to_string()
+    }
 }
 
 /// Order pricing priority mode for determining which orders to price first
@@ -103,7 +184,7 @@ impl Default for OrderPricingPriority {
 pub enum OrderCommitmentPriority {
     /// Process orders in random order to distribute competition among provers
     Random,
-    /// Process orders by shortest expiry first (lock expiry for lock-and-fulfill orders, request expiry for others)
+    /// Process orders by shortest expiry first (request expiry for orders with an expired lock, lock expiry for others)
     ShortestExpiry,
 }
 
@@ -113,6 +194,92 @@ impl Default for OrderCommitmentPriority {
     }
 }
 
+/// Source of the stake token price, in native token, used to price orders with an expired lock
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StakePriceOracleConf {
+    /// Fixed price, denominated in native token (e.g. ETH) per whole stake token
+    Fixed { price: String },
+    /// Spot price from a Uniswap V2 style pool of the stake token and the wrapped native token
+    UniswapV2 { pair: Address },
+    /// Price from a Chainlink style aggregator reporting the stake token price in native token
+    Chainlink {
+        aggregator: Address,
+        /// Max seconds since the last aggregator update before the price is considered stale
+        #[serde(default = "defaults::stake_price_max_age_secs")]
+        max_age_secs: u64,
+    },
+}
+
+/// Input type matched by an [OrderRule]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleInputType {
+    /// Input provided inline in the request
+    Inline,
+    /// Input fetched from a URL
+    Url,
+}
+
+/// Action applied to orders matching an [OrderRule]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum RuleAction {
+    /// Skip the order
+    Deny,
+    /// Accept the order regardless of its price, provided there is enough gas and stake
+    AlwaysAccept,
+    /// Price the order with a custom mcycle price, in place of `mcycle_price` and, if set,
+    /// `mcycle_price_stake_token`
+    McyclePrice {
+        mcycle_price: String,
+        #[serde(default)]
+        mcycle_price_stake_token: Option<String>,
+    },
+    /// Limit the order to a custom max mcycles, in place of `max_mcycle_limit`
+    MaxMcycleLimit { max_mcycle_limit: u64 },
+    /// Commit to the order ahead of other orders, as for `priority_requestor_addresses`
+    PriorityBoost,
+}
+
+/// Rule matching orders on their request, and the action to apply to matching orders
+///
+/// An order matches a rule if it matches every field set on the rule, and matches a field set to
+/// a list if it matches any entry of the list. A rule with no fields set matches every order.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct OrderRule {
+    /// Optional name of the rule, used in logs
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Requestor (client) addresses
+    #[serde(default)]
+    pub requestors: Option<Vec<Address>>,
+    /// Image IDs
+    #[serde(default)]
+    pub image_ids: Option<Vec<B256>>,
+    /// Hosts of the image URL
+    #[serde(default)]
+    pub image_url_hosts: Option<Vec<String>>,
+    /// Input type, either "inline" or "url"
+    #[serde(default)]
+    pub input_type: Option<RuleInputType>,
+    /// Selectors of the proof requirements
+    #[serde(default)]
+    pub selectors: Option<Vec<FixedBytes<4>>>,
+    /// Whether the request has a callback
+    #[serde(default)]
+    pub callback: Option<bool>,
+    /// Min lock stake, denominated in the Boundless staking token
+    #[serde(default)]
+    pub min_lock_stake: Option<String>,
+    /// Max lock stake, denominated in the Boundless staking token
+    #[serde(default)]
+    pub max_lock_stake: Option<String>,
+    /// Action applied to matching orders
+    #[serde(flatten)]
+    pub action: RuleAction,
+}
+
 /// All configuration related to markets mechanics
 #[derive(Debug, Deserialize, Serialize)]
 #[non_exhaustive]
@@ -128,6 +295,11 @@ pub struct MarketConf {
     /// Similar to the mcycle_price option above. This is used to determine the minimum price to accept an
     /// order when paid in staking tokens, as is the case for orders with an expired lock.
     pub mcycle_price_stake_token: String,
+    /// Optional source of the stake token price, in native token.
+    ///
+    /// Used to account for the gas cost of fulfilling orders with an expired lock, which are paid
+    /// in stake tokens. If not set, gas costs are ignored when pricing those orders.
+    pub stake_price_oracle: Option<StakePriceOracleConf>,
     /// Assumption price (in native token)
     ///
     /// DEPRECATED
@@ -154,6 +326,21 @@ pub struct MarketConf {
     /// Used to estimate proving capacity and accept only as much work as the prover can handle. Estimates
     /// can be derived from benchmarking using Bento CLI or from data based on fulfilling market orders.
     pub peak_prove_khz: Option<u64>,
+    /// Calibrate the proving throughput from completed proofs
+    ///
+    /// When enabled, the throughput measured over recent proofs replaces `peak_prove_khz` in
+    /// pricing and capacity checks. Pricing uses the throughput of a single proof, measured per
+    /// image ID once enough proofs of that image have completed. Capacity checks use the cycles
+    /// proven per second of wall-clock time, across concurrent proofs. `peak_prove_khz`, if set,
+    /// is used until enough proofs have completed, and caps the measured throughput.
+    #[serde(default)]
+    pub calibrate_prove_khz: bool,
+    /// Number of most recent proofs the calibrated proving throughput is measured over
+    #[serde(default = "defaults::prove_khz_window")]
+    pub prove_khz_window: usize,
+    /// Minimum number of completed proofs before the calibrated proving throughput is used
+    #[serde(default = "defaults::prove_khz_min_samples")]
+    pub prove_khz_min_samples: usize,
     /// Min seconds left before the deadline to consider bidding on a request.
     ///
     /// If there is not enough time left before the deadline, the prover may not be able to complete
@@ -165,6 +352,19 @@ pub struct MarketConf {
     ///
     /// Requests that require a higher stake than this will not be considered.
     pub max_stake: String,
+    /// Optional max lock stake, denominated in the Boundless staking token, of requests to fulfill
+    /// without locking.
+    ///
+    /// Requests requiring a stake at or below this amount are not locked. Instead they are proven
+    /// and then priced and fulfilled in a single transaction, so no stake is put up. Any other
+    /// prover may lock or fulfill such a request first, in which case the broker is not paid for
+    /// its work. If not set, all requests are locked before proving.
+    pub fulfill_without_locking_max_stake: Option<String>,
+    /// Premium over `mcycle_price`, in basis points, required to fulfill a request without locking.
+    ///
+    /// Compensates for the risk of another prover locking or fulfilling the request first.
+    #[serde(default = "defaults::fulfill_without_locking_premium_bps")]
+    pub fulfill_without_locking_premium_bps: u64,
     /// Optional allow list for customer address.
     ///
     /// If enabled, all requests from clients not in the allow list are skipped.
@@ -179,10 +379,44 @@ pub struct MarketConf {
     /// for increasing the priority if competing with multiple provers during the
     /// same block
     pub lockin_priority_gas: Option<u64>,
+    /// Max share of the expected profit of an order, in basis points, to spend on the priority fee
+    /// of its lock transaction.
+    ///
+    /// When set, the priority fee of each lock transaction is bid to outbid the lock transactions
+    /// of other provers in recent blocks, with `lockin_priority_gas` as the minimum. Every bid and
+    /// its outcome is recorded in the DB.
+    pub lock_bid_max_profit_bps: Option<u64>,
+    /// Margin, in basis points, by which to outbid the highest competing priority fee
+    #[serde(default = "defaults::lock_bid_outbid_bps")]
+    pub lock_bid_outbid_bps: u64,
+    /// Number of recent blocks to observe competing lock transactions in
+    #[serde(default = "defaults::lock_bid_lookback_blocks")]
+    pub lock_bid_lookback_blocks: u64,
+    /// Number of blocks a lock or fulfillment transaction may stay pending before it is
+    /// re-broadcast with higher fees
+    ///
+    /// When set, these transactions are sent with a tracked nonce and replaced while stuck, with
+    /// fees raised by `txn_fee_bump_bps` as long as the total fee stays within the revenue of the
+    /// order or batch. Transactions that are no longer profitable are cancelled with a zero-value
+    /// transfer to self. When unset, transactions are sent once and left to `txn_timeout`.
+    pub txn_bump_blocks: Option<u64>,
+    /// Fee increase, in basis points, of each re-broadcast of a stuck transaction
+    ///
+    /// Values below 1000 (10%) are raised to 1000, the minimum increase nodes accept for a
+    /// replacement.
+    #[serde(default = "defaults::txn_fee_bump_bps")]
+    pub txn_fee_bump_bps: u64,
     /// Max input / image file size allowed for downloading from request URLs.
     pub max_file_size: usize,
     /// Max retries for fetching input / image contents from URLs
     pub max_fetch_retries: Option<u8>,
+    /// IPFS gateways used to fetch `ipfs://` URLs, tried in order
+    ///
+    /// Content is verified against its CID, so untrusted public gateways can be used.
+    #[serde(default = "defaults::ipfs_gateways")]
+    pub ipfs_gateways: Vec<String>,
+    /// Optional Kubo HTTP RPC API used to fetch `ipfs://` URLs, tried before the gateways
+    pub ipfs_api_url: Option<String>,
     /// Gas estimate for lockin call
     ///
     /// Used for estimating the gas costs associated with an order during pricing. If not set a
@@ -229,13 +463,26 @@ pub struct MarketConf {
     pub max_concurrent_proofs: Option<u32>,
     /// Optional cache directory for storing downloaded images and inputs
     ///
-    /// If not set, files will be re-downloaded every time
+    /// Images and inputs are stored by the digest of their contents. Programs are restored from
+    /// the cache by image ID, and inputs only from content-addressed (IPFS) URLs, as the contents
+    /// of other URLs may change. If not set, files will be re-downloaded every time
     pub cache_dir: Option<PathBuf>,
+    /// Max size of the cache directory, in bytes
+    ///
+    /// The least recently used images and inputs are evicted when the cache exceeds this size.
+    #[serde(default = "defaults::max_cache_size")]
+    pub max_cache_size: u64,
     /// Maximum number of orders to concurrently work on pricing
     ///
     /// Used to limit pricing tasks spawned to prevent overwhelming the system
     #[serde(default = "defaults::max_concurrent_preflights")]
     pub max_concurrent_preflights: u32,
+    /// Max wall-clock seconds to spend on the preflight of a single order
+    ///
+    /// The timeout applied to an order is the smaller of this value and the time remaining until
+    /// the order's deadline, less `min_deadline`. Orders whose preflight times out are skipped.
+    #[serde(default = "defaults::max_preflight_secs")]
+    pub max_preflight_secs: u64,
     /// Order pricing priority mode
     ///
     /// Determines how orders are prioritized for pricing. Options:
@@ -251,6 +498,20 @@ pub struct MarketConf {
     /// - "shortest_expiry": Process orders by shortest expiry first (lock expiry for lock-and-fulfill orders, request expiry for others)
     #[serde(default, alias = "expired_order_fulfillment_priority")]
     pub order_commitment_priority: OrderCommitmentPriority,
+    /// Pricing strategy
+    ///
+    /// Name of the pricing strategy used to decide which orders to lock and prove. Custom
+    /// strategies can be registered on the broker; "default" uses the built-in pricing based on
+    /// `mcycle_price` and `mcycle_price_stake_token`.
+    #[serde(default = "defaults::pricing_strategy")]
+    pub pricing_strategy: String,
+    /// Rules matching orders and the action to apply to them
+    ///
+    /// Rules are evaluated in order before preflight. The first matching rule setting an mcycle
+    /// price or max mcycle limit takes effect. Orders matching a deny rule are skipped, unless an
+    /// earlier always-accept rule matches them.
+    #[serde(default)]
+    pub rules: Vec<OrderRule>,
 }
 
 impl Default for MarketConf {
@@ -260,19 +521,32 @@ impl Default for MarketConf {
         Self {
             mcycle_price: "0.00001".to_string(),
             mcycle_price_stake_token: "0.001".to_string(),
+            stake_price_oracle: None,
             assumption_price: None,
             max_mcycle_limit: None,
             priority_requestor_addresses: None,
             max_journal_bytes: defaults::max_journal_bytes(), // 10 KB
             peak_prove_khz: None,
+            calibrate_prove_khz: false,
+            prove_khz_window: defaults::prove_khz_window(),
+            prove_khz_min_samples: defaults::prove_khz_min_samples(),
             min_deadline: 120, // 2 mins
             lookback_blocks: 100,
             max_stake: "0.1".to_string(),
+            fulfill_without_locking_max_stake: None,
+            fulfill_without_locking_premium_bps: defaults::fulfill_without_locking_premium_bps(),
             allow_client_addresses: None,
             deny_requestor_addresses: None,
             lockin_priority_gas: None,
+            lock_bid_max_profit_bps: None,
+            lock_bid_outbid_bps: defaults::lock_bid_outbid_bps(),
+            lock_bid_lookback_blocks: defaults::lock_bid_lookback_blocks(),
+            txn_bump_blocks: None,
+            txn_fee_bump_bps: defaults::txn_fee_bump_bps(),
             max_file_size: 50_000_000,
             max_fetch_retries: Some(2),
+            ipfs_gateways: defaults::ipfs_gateways(),
+            ipfs_api_url: None,
             lockin_gas_estimate: defaults::lockin_gas_estimate(),
             fulfill_gas_estimate: defaults::fulfill_gas_estimate(),
             groth16_verify_gas_estimate: defaults::groth16_verify_gas_estimate(),
@@ -283,9 +557,13 @@ impl Default for MarketConf {
             stake_balance_error_threshold: None,
             max_concurrent_proofs: None,
             cache_dir: None,
+            max_cache_size: defaults::max_cache_size(),
             max_concurrent_preflights: defaults::max_concurrent_preflights(),
+            max_preflight_secs: defaults::max_preflight_secs(),
             order_pricing_priority: OrderPricingPriority::default(),
             order_commitment_priority: OrderCommitmentPriority::default(),
+            pricing_strategy: defaults::pricing_strategy(),
+            rules: Vec::new(),
         }
     }
 }
@@ -342,6 +620,28 @@ pub struct ProverConf {
     /// If not set, it defaults to 30 seconds.
     #[serde(default = "defaults::reaper_grace_period_secs")]
     pub reaper_grace_period_secs: u32,
+    /// Seconds to keep a cached preflight outcome before the reaper deletes it
+    #[serde(default = "defaults::preflight_cache_max_age_secs")]
+    pub preflight_cache_max_age_secs: u64,
+    /// Max number of cached preflight outcomes, beyond which the reaper deletes the oldest
+    #[serde(default = "defaults::preflight_cache_max_entries")]
+    pub preflight_cache_max_entries: u64,
+    /// Seconds to keep the shadow decisions, proving samples and lock bids before the reaper
+    /// deletes them
+    #[serde(default = "defaults::history_max_age_secs")]
+    pub history_max_age_secs: u64,
+    /// Max number of shadow decisions, proving samples and lock bids each, beyond which the
+    /// reaper deletes the oldest
+    #[serde(default = "defaults::history_max_entries")]
+    pub history_max_entries: u64,
+    /// Prover backends to route preflight, proving and compression across
+    ///
+    /// When set, these replace the single Bento or Bonsai backend given on the command line.
+    #[serde(default)]
+    pub backends: Vec<ProverBackendConf>,
+    /// Seconds to stop routing work to a prover backend after it fails a request
+    #[serde(default = "defaults::backend_retry_secs")]
+    pub backend_retry_secs: u64,
 }
 
 impl Default for ProverConf {
@@ -359,10 +659,60 @@ impl Default for ProverConf {
             max_critical_task_retries: None,
             reaper_interval_secs: defaults::reaper_interval_secs(),
             reaper_grace_period_secs: defaults::reaper_grace_period_secs(),
+            preflight_cache_max_age_secs: defaults::preflight_cache_max_age_secs(),
+            preflight_cache_max_entries: defaults::preflight_cache_max_entries(),
+            history_max_age_secs: defaults::history_max_age_secs(),
+            history_max_entries: defaults::history_max_entries(),
+            backends: Vec::new(),
+            backend_retry_secs: defaults::backend_retry_secs(),
         }
     }
 }
 
+/// A Bento or Bonsai prover backend
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct ProverBackendConf {
+    /// Unique name of the backend, recorded with each proof it holds
+    pub name: String,
+    /// API URL of the backend
+    pub api_url: String,
+    /// API key, only required for Bonsai
+    pub api_key: Option<String>,
+    /// Estimated peak proving performance of the backend, in kHz
+    ///
+    /// Used to weigh how much work is routed to the backend. Backends without it are weighted as
+    /// 1 kHz.
+    pub peak_prove_khz: Option<u64>,
+}
+
+/// An additional chain and Boundless market deployment served by the broker
+///
+/// Each chain runs its own market monitors, order monitor, aggregator and submitter, sharing the
+/// order picker, prover backends and capacity limits of the broker.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct ChainConf {
+    /// RPC URL of the chain
+    pub rpc_url: String,
+    /// Address of the BoundlessMarket contract
+    ///
+    /// Defaults to the Boundless deployment of the chain.
+    pub boundless_market_address: Option<Address>,
+    /// Address of the RiscZeroSetVerifier contract
+    ///
+    /// Defaults to the Boundless deployment of the chain.
+    pub set_verifier_address: Option<Address>,
+    /// URL of the offchain order stream
+    ///
+    /// Defaults to the Boundless deployment of the chain.
+    pub order_stream_url: Option<String>,
+    /// Source of the stake token price on this chain, see `market.stake_price_oracle`
+    pub stake_price_oracle: Option<StakePriceOracleConf>,
+    /// Treasury of this chain, see `treasury`
+    ///
+    /// If not set, the treasury takes no action on this chain.
+    pub treasury: Option<TreasuryConf>,
+}
+
 /// All configuration related to batching / aggregation
 #[derive(Debug, Deserialize, Serialize)]
 pub struct BatcherConfig {
@@ -419,6 +769,61 @@ impl Default for BatcherConfig {
     }
 }
 
+/// Automatic management of the stake and balances of the broker on a chain
+///
+/// Every action taken is recorded in the DB, and the amounts moved by each kind of action are
+/// capped per rolling day by the `max_daily_*` limits. Actions without a limit are uncapped.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct TreasuryConf {
+    /// Seconds between two checks of the balances
+    #[serde(default = "defaults::treasury_interval_secs")]
+    pub interval_secs: u64,
+    /// Optional stake balance (in stake tokens) to keep deposited in the market
+    ///
+    /// When the deposited stake drops below this, stake tokens held by the broker wallet are
+    /// deposited to restore it.
+    pub stake_target: Option<String>,
+    /// Whether to deposit stake with an EIP-2612 permit
+    ///
+    /// If false, the market must already be approved to spend the stake tokens of the broker.
+    #[serde(default = "defaults::stake_deposit_permit")]
+    pub stake_deposit_permit: bool,
+    /// Optional max stake (in stake tokens) deposited per day
+    pub max_daily_stake_deposit: Option<String>,
+    /// Optional address market earnings are swept to
+    pub sweep_address: Option<Address>,
+    /// Market balance (in native token) above which earnings are swept to `sweep_address`
+    pub sweep_threshold: Option<String>,
+    /// Market balance (in native token) left after a sweep, defaults to 0
+    pub sweep_reserve: Option<String>,
+    /// Optional max amount (in native token) swept per day
+    pub max_daily_sweep: Option<String>,
+    /// Submitter balance (in native token) below which it is topped up from market earnings
+    pub gas_top_up_threshold: Option<String>,
+    /// Submitter balance (in native token) restored by a top up
+    pub gas_top_up_target: Option<String>,
+    /// Optional max amount (in native token) withdrawn for gas per day
+    pub max_daily_gas_top_up: Option<String>,
+}
+
+impl Default for TreasuryConf {
+    fn default() -> Self {
+        Self {
+            interval_secs: defaults::treasury_interval_secs(),
+            stake_target: None,
+            stake_deposit_permit: defaults::stake_deposit_permit(),
+            max_daily_stake_deposit: None,
+            sweep_address: None,
+            sweep_threshold: None,
+            sweep_reserve: None,
+            max_daily_sweep: None,
+            gas_top_up_threshold: None,
+            gas_top_up_target: None,
+            max_daily_gas_top_up: None,
+        }
+    }
+}
+
 /// Top level config for the broker service
 #[derive(Deserialize, Serialize, Default, Debug)]
 pub struct Config {
@@ -428,6 +833,16 @@ pub struct Config {
     pub prover: ProverConf,
     /// Aggregation batch configs
     pub batcher: BatcherConfig,
+    /// Chains served in addition to the chain of the `--rpc-url`
+    ///
+    /// Only read at startup.
+    #[serde(default)]
+    pub chains: Vec<ChainConf>,
+    /// Automatic stake and balance management of the chain of the `--rpc-url`
+    ///
+    /// Additional chains set their own in their `chains` entry.
+    #[serde(default)]
+    pub treasury: TreasuryConf,
 }
 
 impl Config {
@@ -477,6 +892,11 @@ impl ConfigLock {
         Self { config }
     }
 
+    /// Create a lock over a fixed config, which is not watched for changes
+    pub fn from_config(config: Config) -> Self {
+        Self::new(Arc::new(RwLock::new(config)))
+    }
+
     pub fn lock_all(&self) -> Result<std::sync::RwLockReadGuard<Config>, ConfigErr> {
         self.config.read().map_err(|_| ConfigErr::LockFailed)
     }
@@ -629,8 +1049,26 @@ max_fetch_retries = 10
 allow_client_addresses = ["0x0000000000000000000000000000000000000000"]
 deny_requestor_addresses = ["0x0000000000000000000000000000000000000000"]
 lockin_priority_gas = 100
+lock_bid_max_profit_bps = 2500
+txn_bump_blocks = 3
 max_mcycle_limit = 10
 
+[market.stake_price_oracle]
+type = "chainlink"
+aggregator = "0x0000000000000000000000000000000000000000"
+
+[[market.rules]]
+name = "trusted"
+requestors = ["0x0000000000000000000000000000000000000001"]
+input_type = "inline"
+action = "always_accept"
+
+[[market.rules]]
+image_url_hosts = ["example.com"]
+max_lock_stake = "5"
+action = "mcycle_price"
+mcycle_price = "0.2"
+
 [prover]
 status_poll_retry_count = 2
 status_poll_ms = 1000
@@ -639,6 +1077,14 @@ req_retry_sleep_ms = 200
 proof_retry_count = 1
 proof_retry_sleep_ms = 500
 
+[[prover.backends]]
+name = "large"
+api_url = "http://localhost:8081"
+peak_prove_khz = 500
+
+[[prover.backends]]
+name = "small"
+api_url = "http://localhost:8082"
 
 [batcher]
 batch_max_time = 300
@@ -647,7 +1093,29 @@ block_deadline_buffer_secs = 120
 txn_timeout = 45
 batch_poll_time_ms = 1200
 single_txn_fulfill = true
-withdraw = true"#;
+withdraw = true
+
+[treasury]
+interval_secs = 300
+stake_target = "10"
+sweep_address = "0x0000000000000000000000000000000000000003"
+sweep_threshold = "1"
+max_daily_sweep = "5"
+
+[[chains]]
+rpc_url = "http://localhost:8546"
+boundless_market_address = "0x0000000000000000000000000000000000000001"
+set_verifier_address = "0x0000000000000000000000000000000000000002"
+
+[[chains]]
+rpc_url = "http://localhost:8547"
+
+[chains.stake_price_oracle]
+type = "fixed"
+price = "0.001"
+
+[chains.treasury]
+stake_target = "20""#;
 
     const BAD_CONFIG: &str = r#"
 [market]
@@ -674,6 +1142,14 @@ error = ?"#;
         assert_eq!(config.market.max_stake, "0.1");
         assert_eq!(config.market.max_file_size, 50_000_000);
         assert_eq!(config.market.lockin_priority_gas, None);
+        assert_eq!(config.market.lock_bid_max_profit_bps, None);
+        assert_eq!(config.market.txn_bump_blocks, None);
+        assert_eq!(config.market.txn_fee_bump_bps, defaults::txn_fee_bump_bps());
+        assert_eq!(config.market.stake_price_oracle, None);
+        assert!(config.market.rules.is_empty());
+        assert_eq!(config.market.max_cache_size, 10_000_000_000);
+        assert_eq!(config.market.ipfs_gateways, vec!["https://ipfs.io".to_string()]);
+        assert_eq!(config.market.ipfs_api_url, None);
 
         assert_eq!(config.prover.status_poll_ms, 1000);
         assert_eq!(config.prover.status_poll_retry_count, 3);
@@ -684,6 +1160,9 @@ error = ?"#;
         assert_eq!(config.prover.proof_retry_sleep_ms, 500);
         assert_eq!(config.prover.set_builder_guest_path, None);
         assert_eq!(config.prover.assessor_set_guest_path, None);
+        assert!(config.prover.backends.is_empty());
+        assert!(config.chains.is_empty());
+        assert_eq!(config.treasury, TreasuryConf::default());
 
         assert_eq!(config.batcher.batch_max_time, Some(300));
         assert_eq!(config.batcher.min_batch_size, Some(2));
@@ -737,8 +1216,31 @@ error = ?"#;
                 Some([Address::ZERO].into_iter().collect())
             );
             assert_eq!(config.market.lockin_priority_gas, Some(100));
+            assert_eq!(config.market.lock_bid_max_profit_bps, Some(2500));
+            assert_eq!(config.market.lock_bid_outbid_bps, defaults::lock_bid_outbid_bps());
+            assert_eq!(config.market.txn_bump_blocks, Some(3));
+            assert_eq!(config.market.txn_fee_bump_bps, defaults::txn_fee_bump_bps());
             assert_eq!(config.market.max_fetch_retries, Some(10));
             assert_eq!(config.market.max_mcycle_limit, Some(10));
+            assert_eq!(
+                config.market.stake_price_oracle,
+                Some(StakePriceOracleConf::Chainlink {
+                    aggregator: Address::ZERO,
+                    max_age_secs: defaults::stake_price_max_age_secs(),
+                })
+            );
+            assert_eq!(config.market.rules.len(), 2);
+            assert_eq!(config.market.rules[0].name.as_deref(), Some("trusted"));
+            assert_eq!(config.market.rules[0].input_type, Some(RuleInputType::Inline));
+            assert_eq!(config.market.rules[0].action, RuleAction::AlwaysAccept);
+            assert_eq!(config.market.rules[1].max_lock_stake.as_deref(), Some("5"));
+            assert_eq!(
+                config.market.rules[1].action,
+                RuleAction::McyclePrice {
+                    mcycle_price: "0.2".into(),
+                    mcycle_price_stake_token: None
+                }
+            );
             assert_eq!(config.prover.status_poll_ms, 1000);
             assert_eq!(config.prover.status_poll_retry_count, 2);
             assert_eq!(config.prover.req_retry_count, 1);
@@ -746,11 +1248,62 @@ error = ?"#;
             assert_eq!(config.prover.proof_retry_count, 1);
             assert_eq!(config.prover.proof_retry_sleep_ms, 500);
             assert!(config.prover.bonsai_r0_zkvm_ver.is_none());
+            assert_eq!(
+                config.prover.backends,
+                vec![
+                    ProverBackendConf {
+                        name: "large".into(),
+                        api_url: "http://localhost:8081".into(),
+                        api_key: None,
+                        peak_prove_khz: Some(500),
+                    },
+                    ProverBackendConf {
+                        name: "small".into(),
+                        api_url: "http://localhost:8082".into(),
+                        api_key: None,
+                        peak_prove_khz: None,
+                    },
+                ]
+            );
+            assert_eq!(config.prover.backend_retry_secs, defaults::backend_retry_secs());
             assert_eq!(config.batcher.txn_timeout, Some(45));
             assert_eq!(config.batcher.batch_poll_time_ms, Some(1200));
             assert_eq!(config.batcher.min_batch_size, Some(3));
             assert!(config.batcher.single_txn_fulfill);
             assert!(config.batcher.withdraw);
+            assert_eq!(config.treasury.interval_secs, 300);
+            assert_eq!(config.treasury.stake_target, Some("10".into()));
+            assert!(config.treasury.stake_deposit_permit);
+            assert_eq!(config.treasury.sweep_address, Some(Address::with_last_byte(3)));
+            assert_eq!(config.treasury.sweep_threshold, Some("1".into()));
+            assert_eq!(config.treasury.max_daily_sweep, Some("5".into()));
+            assert_eq!(config.treasury.gas_top_up_threshold, None);
+            assert_eq!(
+                config.chains,
+                vec![
+                    ChainConf {
+                        rpc_url: "http://localhost:8546".into(),
+                        boundless_market_address: Some(Address::with_last_byte(1)),
+                        set_verifier_address: Some(Address::with_last_byte(2)),
+                        order_stream_url: None,
+                        stake_price_oracle: None,
+                        treasury: None,
+                    },
+                    ChainConf {
+                        rpc_url: "http://localhost:8547".into(),
+                        boundless_market_address: None,
+                        set_verifier_address: None,
+                        order_stream_url: None,
+                        stake_price_oracle: Some(StakePriceOracleConf::Fixed {
+                            price: "0.001".into()
+                        }),
+                        treasury: Some(TreasuryConf {
+                            stake_target: Some("20".into()),
+                            ..Default::default()
+                        }),
+                    },
+                ]
+            );
         }
         tracing::debug!("closing...");
     }
@@ -20,17 +20,19 @@ use proptest_derive::Arbitrary;
 use rand::Rng;
 use risc0_aggregation::GuestState;
 use risc0_zkvm::sha::Digest;
-use sqlx::{sqlite::SqliteConnectOptions, SqlitePool};
+use sqlx::{migrate::MigrateDatabase, sqlite::SqliteConnectOptions, Postgres, SqlitePool};
 use std::str::FromStr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tempfile::NamedTempFile;
-use tokio::runtime::Builder;
+use tokio::runtime::{Builder, Runtime};
+use url::Url;
+use uuid::Uuid;
 
 use crate::FulfillmentType;
 use crate::{db::AggregationOrder, AggregationState, Order, OrderStatus};
 
-use super::{BrokerDb, SqliteDb};
+use super::{BrokerDb, PostgresDb, SqliteDb};
 
 use boundless_market::contracts::{
     Offer, Predicate, PredicateType, ProofRequest, RequestId, RequestInput, RequestInputType,
@@ -108,6 +110,7 @@ fn generate_test_order(request_id: u32) -> Order {
         ),
         image_id: None,
         input_id: None,
+        assumption_ids: vec![],
         proof_id: Some(format!("proof_{}", request_id)),
         compressed_proof_id: Some(format!("compressed_proof_{}", request_id)),
         expire_timestamp: Some(1000),
@@ -115,6 +118,7 @@ fn generate_test_order(request_id: u32) -> Order {
         lock_price: Some(U256::from(10)),
         fulfillment_type: FulfillmentType::LockAndFulfill,
         error_msg: None,
+        skip_reason: None,
         boundless_market_address: Address::ZERO,
         chain_id: 1,
         total_cycles: None,
@@ -122,19 +126,179 @@ fn generate_test_order(request_id: u32) -> Order {
     }
 }
 
+/// Apply the operations concurrently from several tasks, panicking on any DB error
+async fn run_operations(db: Arc<dyn BrokerDb + Send + Sync>, operations: Vec<DbOperation>) {
+    // Create state tracking structure
+    let state = TestState {
+        added_orders: Arc::new(FrozenVec::new()),
+        completed_batch: Arc::new(AtomicBool::new(false)),
+    };
+
+    // Spawn multiple tasks to execute operations concurrently
+    let mut handles = vec![];
+
+    for ops in operations.chunks(12) {
+        let db = db.clone();
+        let ops = ops.to_vec();
+        let state = TestState {
+            added_orders: state.added_orders.clone(),
+            completed_batch: state.completed_batch.clone(),
+        };
+
+        handles.push(tokio::spawn(async move {
+            for op in ops {
+                match op {
+                    DbOperation::AddOrder(request_id) => {
+                        let order = generate_test_order(request_id);
+                        let id = order.id();
+                        db.add_order(&order).await.unwrap();
+                        state.added_orders.push(id);
+                    }
+                    DbOperation::OperateOnExistingOrder(operation) => {
+                        // Skip if no orders have been added yet
+                        if state.added_orders.len() == 0 {
+                            continue;
+                        }
+
+                        // Randomly select an existing order by index
+                        let len = state.added_orders.len();
+                        let random_index: usize = rand::rng().random_range(0..len);
+                        let id = state.added_orders.get(random_index).unwrap();
+
+                        match operation {
+                            ExistingOrderOperation::GetOrder => {
+                                db.get_order(id).await.unwrap();
+                            }
+                            ExistingOrderOperation::SetOrderComplete => {
+                                db.set_order_complete(id).await.unwrap();
+                            }
+                            ExistingOrderOperation::SetOrderFailure => {
+                                db.set_order_failure(id, "test").await.unwrap();
+                            }
+                            ExistingOrderOperation::SetOrderProofId { proof_id } => {
+                                db.set_order_proof_id(id, &proof_id).await.unwrap();
+                            }
+                            ExistingOrderOperation::SetAggregationStatus => {
+                                db.set_aggregation_status(id, OrderStatus::PendingAgg)
+                                    .await
+                                    .unwrap();
+                            }
+                            ExistingOrderOperation::GetSubmissionOrder => {
+                                let order = db.get_order(id).await.unwrap();
+                                if let Some(order) = order {
+                                    if order.proof_id.is_some() && order.lock_price.is_some() {
+                                        db.get_submission_order(id).await.unwrap();
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    DbOperation::BatchOperation(operation) => {
+                        match operation {
+                            BatchOperation::GetCurrentBatch => {
+                                db.get_current_batch(1).await.unwrap();
+                            }
+                            BatchOperation::CompleteBatch { g16_proof_id } => {
+                                let batch_id = db.get_current_batch(1).await.unwrap();
+                                let batch = db.get_batch(batch_id).await.unwrap();
+                                if batch.aggregation_state.is_some() {
+                                    db.complete_batch(batch_id, &g16_proof_id).await.unwrap();
+                                    state.completed_batch.store(true, Ordering::SeqCst);
+                                }
+                            }
+                            BatchOperation::GetCompleteBatch => {
+                                db.get_complete_batch(1).await.unwrap();
+                            }
+                            BatchOperation::SetBatchSubmitted => {
+                                if state.completed_batch.load(Ordering::SeqCst) {
+                                    let batch_id = db.get_current_batch(1).await.unwrap();
+                                    db.set_batch_submitted(batch_id).await.unwrap();
+                                }
+                            }
+                            BatchOperation::SetBatchFailure { error } => {
+                                if state.completed_batch.load(Ordering::SeqCst) {
+                                    let batch_id = db.get_current_batch(1).await.unwrap();
+                                    db.set_batch_failure(batch_id, error).await.unwrap();
+                                }
+                            }
+                            BatchOperation::UpdateBatch { proof_id, order_count } => {
+                                if state.added_orders.len() > 0 {
+                                    let batch_id = db.get_current_batch(1).await.unwrap();
+                                    // Select up to order_count random orders
+                                    let count = std::cmp::min(
+                                        order_count as usize,
+                                        state.added_orders.len(),
+                                    );
+                                    let mut orders = Vec::with_capacity(count);
+
+                                    for _ in 0..count {
+                                        let len = state.added_orders.len();
+                                        let random_index: usize = rand::rng().random_range(0..len);
+                                        let id = state.added_orders.get(random_index).unwrap();
+
+                                        orders.push(AggregationOrder {
+                                            order_id: id.to_string(),
+                                            proof_id: format!("proof_{}", id),
+                                            expiration: 1000,
+                                            fee: U256::from(10),
+                                        });
+                                    }
+
+                                    let agg_state = AggregationState {
+                                        guest_state: GuestState::initial([1u32; 8]),
+                                        claim_digests: vec![],
+                                        groth16_proof_id: None,
+                                        proof_id,
+                                    };
+
+                                    db.update_batch(
+                                        batch_id,
+                                        &agg_state,
+                                        &orders,
+                                        Some("proof_id".to_string()),
+                                    )
+                                    .await
+                                    .unwrap();
+                                }
+                            }
+                        }
+                    }
+
+                    DbOperation::GetProvingOrder => {
+                        db.get_proving_order().await.unwrap();
+                    }
+                    DbOperation::GetActiveProofs => {
+                        db.get_active_proofs().await.unwrap();
+                    }
+
+                    DbOperation::GetAggregationProofs => {
+                        db.get_aggregation_proofs(1).await.unwrap();
+                    }
+                    DbOperation::GetBatch(batch_id) => {
+                        let current_batch = db.get_current_batch(1).await.unwrap();
+                        let _ = db.get_batch(batch_id as usize % current_batch).await;
+                    }
+                }
+            }
+        }));
+    }
+
+    // Wait for all operations to complete
+    for handle in handles {
+        handle.await.unwrap();
+    }
+}
+
+fn runtime() -> Runtime {
+    // Create a multi-threaded runtime with 4 worker threads
+    Builder::new_multi_thread().worker_threads(4).enable_time().enable_all().build().unwrap()
+}
+
 // Main fuzz test function
 proptest! {
     #[test]
     fn fuzz_db_operations(operations in prop::collection::vec(any::<DbOperation>(), 1..1000)) {
-        // Create a multi-threaded runtime with 4 worker threads
-        let rt = Builder::new_multi_thread()
-            .worker_threads(4)
-            .enable_time()
-            .enable_all()
-            .build()
-            .unwrap();
-
-        rt.block_on(async {
+        runtime().block_on(async {
             // Create temporary file for SQLite database
             let temp_db = NamedTempFile::new().unwrap();
             // SQLite URL requires 3 forward slashes after sqlite:
@@ -157,159 +321,33 @@ proptest! {
                 SqliteDb::new(&db_path).await.unwrap()
             );
 
-            // Create state tracking structure
-            let state = TestState {
-                added_orders: Arc::new(FrozenVec::new()),
-                completed_batch: Arc::new(AtomicBool::new(false)),
-            };
-
-            // Spawn multiple tasks to execute operations concurrently
-            let mut handles = vec![];
-
-            for ops in operations.chunks(12) {
-                let db = db.clone();
-                let ops = ops.to_vec();
-                let state = TestState {
-                    added_orders: state.added_orders.clone(),
-                    completed_batch: state.completed_batch.clone(),
-                };
-
-                handles.push(tokio::spawn(async move {
-                    for op in ops {
-                        match op {
-                            DbOperation::AddOrder(request_id) => {
-                                let order = generate_test_order(request_id);
-                                let id = order.id();
-                                db.add_order(&order).await.unwrap();
-                                state.added_orders.push(id);
-                            },
-                            DbOperation::OperateOnExistingOrder(operation) => {
-                                // Skip if no orders have been added yet
-                                if state.added_orders.len() == 0 {
-                                    continue;
-                                }
+            run_operations(db, operations).await;
+        });
+    }
+}
 
-                                // Randomly select an existing order by index
-                                let len = state.added_orders.len();
-                                let random_index: usize = rand::rng().random_range(0..len);
-                                let id = state.added_orders.get(random_index).unwrap();
-
-                                match operation {
-                                    ExistingOrderOperation::GetOrder => {
-                                        db.get_order(id).await.unwrap();
-                                    },
-                                    ExistingOrderOperation::SetOrderComplete => {
-                                        db.set_order_complete(id).await.unwrap();
-                                    },
-                                    ExistingOrderOperation::SetOrderFailure => {
-                                        db.set_order_failure(id, "test").await.unwrap();
-                                    },
-                                    ExistingOrderOperation::SetOrderProofId { proof_id } => {
-                                        db.set_order_proof_id(id, &proof_id).await.unwrap();
-                                    },
-                                    ExistingOrderOperation::SetAggregationStatus => {
-                                        db.set_aggregation_status(id, OrderStatus::PendingAgg).await.unwrap();
-                                    },
-                                    ExistingOrderOperation::GetSubmissionOrder => {
-                                        let order = db.get_order(id).await.unwrap();
-                                        if let Some(order) = order {
-                                            if order.proof_id.is_some() && order.lock_price.is_some() {
-                                                db.get_submission_order(id).await.unwrap();
-                                            }
-                                        }
-                                    },
+// Each case creates a fresh database on the server, so run fewer cases than for SQLite
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+    #[test]
+    fn fuzz_db_operations_postgres(operations in prop::collection::vec(any::<DbOperation>(), 1..1000)) {
+        // Only run against a Postgres server when one is configured
+        let Ok(database_url) = std::env::var("DATABASE_URL") else {
+            return Ok(());
+        };
+        runtime().block_on(async {
+            // Create a uniquely named database on the server from DATABASE_URL
+            let mut db_url = Url::parse(&database_url).unwrap();
+            db_url.set_path(&format!("broker_fuzz_{}", Uuid::new_v4().simple()));
+            Postgres::create_database(db_url.as_str()).await.unwrap();
 
-                                }
-                            },
-                            DbOperation::BatchOperation(operation) => {
-                                match operation {
-                                    BatchOperation::GetCurrentBatch => {
-                                        db.get_current_batch().await.unwrap();
-                                    },
-                                    BatchOperation::CompleteBatch { g16_proof_id } => {
-                                        let batch_id = db.get_current_batch().await.unwrap();
-                                        let batch = db.get_batch(batch_id).await.unwrap();
-                                        if batch.aggregation_state.is_some() {
-                                            db.complete_batch(batch_id, &g16_proof_id).await.unwrap();
-                                            state.completed_batch.store(true, Ordering::SeqCst);
-                                        }
-                                    },
-                                    BatchOperation::GetCompleteBatch => {
-                                        db.get_complete_batch().await.unwrap();
-                                    },
-                                    BatchOperation::SetBatchSubmitted => {
-                                        if state.completed_batch.load(Ordering::SeqCst) {
-                                            let batch_id = db.get_current_batch().await.unwrap();
-                                            db.set_batch_submitted(batch_id).await.unwrap();
-                                        }
-                                    },
-                                    BatchOperation::SetBatchFailure { error } => {
-                                        if state.completed_batch.load(Ordering::SeqCst) {
-                                            let batch_id = db.get_current_batch().await.unwrap();
-                                            db.set_batch_failure(batch_id, error).await.unwrap();
-                                        }
-                                    },
-                                    BatchOperation::UpdateBatch { proof_id, order_count } => {
-                                        if state.added_orders.len() > 0 {
-                                            let batch_id = db.get_current_batch().await.unwrap();
-                                            // Select up to order_count random orders
-                                            let count = std::cmp::min(order_count as usize, state.added_orders.len());
-                                            let mut orders = Vec::with_capacity(count);
-
-                                            for _ in 0..count {
-                                                let len = state.added_orders.len();
-                                                let random_index: usize = rand::rng().random_range(0..len);
-                                                let id = state.added_orders.get(random_index).unwrap();
-
-                                                orders.push(AggregationOrder {
-                                                    order_id: id.to_string(),
-                                                    proof_id: format!("proof_{}", id),
-                                                    expiration: 1000,
-                                                    fee: U256::from(10),
-                                                });
-                                            }
-
-                                            let agg_state = AggregationState {
-                                                guest_state: GuestState::initial([1u32; 8]),
-                                                claim_digests: vec![],
-                                                groth16_proof_id: None,
-                                                proof_id,
-                                            };
-
-                                            db.update_batch(
-                                                batch_id,
-                                                &agg_state,
-                                                &orders,
-                                                Some("proof_id".to_string()),
-                                            ).await.unwrap();
-                                        }
-                                    },
-                                }
-                            },
-
-                            DbOperation::GetProvingOrder => {
-                                db.get_proving_order().await.unwrap();
-                            },
-                            DbOperation::GetActiveProofs => {
-                                db.get_active_proofs().await.unwrap();
-                            },
-
-                            DbOperation::GetAggregationProofs => {
-                                db.get_aggregation_proofs().await.unwrap();
-                            },
-                            DbOperation::GetBatch(batch_id) => {
-                                let current_batch = db.get_current_batch().await.unwrap();
-                                let _ = db.get_batch(batch_id as usize % current_batch).await;
-                            },
-                        }
-                    }
-                }));
-            }
+            let db: Arc<dyn BrokerDb + Send + Sync> = Arc::new(
+                PostgresDb::new(db_url.as_str()).await.unwrap()
+            );
 
-            // Wait for all operations to complete
-            for handle in handles {
-                handle.await.unwrap();
-            }
+            run_operations(db, operations).await;
+
+            Postgres::force_drop_database(db_url.as_str()).await.unwrap();
         });
     }
 }
@@ -12,11 +12,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{default::Default, str::FromStr, sync::Arc};
+use std::{collections::BTreeMap, default::Default, str::FromStr, sync::Arc};
 
 use alloy::primitives::{ruint::ParseError as RuintParseErr, Bytes, B256, U256};
 use async_trait::async_trait;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use sqlx::{
     sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions},
     Row,
@@ -25,13 +25,22 @@ use thiserror::Error;
 
 use crate::{
     errors::{impl_coded_debug, CodedError},
+    ledger::{LedgerEntry, LedgerKind},
+    lock_bidding::LockBid,
+    preflight_cache::PreflightCacheEntry,
+    shadow::ShadowDecision,
+    throughput::ProvingSample,
+    treasury::TreasuryAction,
     AggregationState, Batch, BatchStatus, FulfillmentType, Order, OrderRequest, OrderStatus,
-    ProofRequest,
+    ProofRequest, SkipReason,
 };
 use tracing::instrument;
 
 #[cfg(test)]
 mod fuzz_db;
+mod postgres;
+
+pub use postgres::PostgresDb;
 
 #[derive(Error)]
 pub enum DbError {
@@ -124,7 +133,11 @@ pub struct AggregationOrder {
 
 #[async_trait]
 pub trait BrokerDb {
-    async fn insert_skipped_request(&self, order_request: &OrderRequest) -> Result<(), DbError>;
+    async fn insert_skipped_request(
+        &self,
+        order_request: &OrderRequest,
+        reason: SkipReason,
+    ) -> Result<(), DbError>;
     async fn insert_accepted_request(
         &self,
         order_request: &OrderRequest,
@@ -155,30 +168,119 @@ pub trait BrokerDb {
         proof_id: &str,
     ) -> Result<(), DbError>;
     async fn set_aggregation_status(&self, id: &str, status: OrderStatus) -> Result<(), DbError>;
-    async fn get_aggregation_proofs(&self) -> Result<Vec<AggregationOrder>, DbError>;
-    async fn get_groth16_proofs(&self) -> Result<Vec<AggregationOrder>, DbError>;
+    /// Take the orders of the given chain that are ready to be aggregated.
+    async fn get_aggregation_proofs(&self, chain_id: u64)
+        -> Result<Vec<AggregationOrder>, DbError>;
+    /// Take the orders of the given chain that require a Groth16 proof.
+    async fn get_groth16_proofs(&self, chain_id: u64) -> Result<Vec<AggregationOrder>, DbError>;
     async fn complete_batch(&self, batch_id: usize, g16_proof_id: &str) -> Result<(), DbError>;
-    async fn get_complete_batch(&self) -> Result<Option<(usize, Batch)>, DbError>;
+    /// Take a complete batch of the given chain, ready for submission.
+    async fn get_complete_batch(&self, chain_id: u64) -> Result<Option<(usize, Batch)>, DbError>;
     async fn set_batch_submitted(&self, batch_id: usize) -> Result<(), DbError>;
     async fn set_batch_failure(&self, batch_id: usize, err: String) -> Result<(), DbError>;
-    async fn get_current_batch(&self) -> Result<usize, DbError>;
+    /// Get the batch of the given chain currently being aggregated, creating one if needed.
+    async fn get_current_batch(&self, chain_id: u64) -> Result<usize, DbError>;
     async fn set_request_fulfilled(
         &self,
+        chain_id: u64,
         request_id: U256,
         block_number: u64,
     ) -> Result<(), DbError>;
-    // Checks the fulfillment table for the given request_id
-    async fn is_request_fulfilled(&self, request_id: U256) -> Result<bool, DbError>;
+    // Checks the fulfillment table for the given request_id on the given chain
+    async fn is_request_fulfilled(&self, chain_id: u64, request_id: U256) -> Result<bool, DbError>;
     async fn set_request_locked(
         &self,
+        chain_id: u64,
         request_id: U256,
         locker: &str,
         block_number: u64,
     ) -> Result<(), DbError>;
-    // Checks the locked table for the given request_id
-    async fn is_request_locked(&self, request_id: U256) -> Result<bool, DbError>;
-    // Checks the locked table for the given request_id
-    async fn get_request_locked(&self, request_id: U256) -> Result<Option<(String, u64)>, DbError>;
+    // Checks the locked table for the given request_id on the given chain
+    async fn is_request_locked(&self, chain_id: u64, request_id: U256) -> Result<bool, DbError>;
+    // Checks the locked table for the given request_id on the given chain
+    async fn get_request_locked(
+        &self,
+        chain_id: u64,
+        request_id: U256,
+    ) -> Result<Option<(String, u64)>, DbError>;
+    /// Append a decision to the shadow mode decision log.
+    async fn add_shadow_decision(&self, decision: &ShadowDecision) -> Result<(), DbError>;
+    /// Get the most recent decisions of the shadow mode log recorded at or after `from`, in the
+    /// order the decisions were recorded.
+    async fn get_shadow_decisions(
+        &self,
+        from: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<ShadowDecision>, DbError>;
+    /// Record the proving throughput of a completed proof.
+    async fn add_proving_sample(&self, sample: &ProvingSample) -> Result<(), DbError>;
+    /// Get the most recent proving samples, oldest first.
+    async fn get_proving_samples(&self, limit: usize) -> Result<Vec<ProvingSample>, DbError>;
+    /// Record the priority fee bid for a lock transaction, and its outcome.
+    async fn add_lock_bid(&self, bid: &LockBid) -> Result<(), DbError>;
+    /// Get the most recent lock bids, oldest first.
+    async fn get_lock_bids(&self, limit: usize) -> Result<Vec<LockBid>, DbError>;
+    /// Get the cached preflight outcome of an image and input.
+    async fn get_preflight_cache(
+        &self,
+        image_id: &str,
+        input_digest: &str,
+    ) -> Result<Option<PreflightCacheEntry>, DbError>;
+    /// Insert or replace the cached preflight outcome of an image and input.
+    async fn set_preflight_cache(&self, entry: &PreflightCacheEntry) -> Result<(), DbError>;
+    /// Delete the cached preflight outcomes created before `created_before`, and the oldest
+    /// outcomes beyond the newest `max_entries`. Returns the number of deleted outcomes.
+    async fn prune_preflight_cache(
+        &self,
+        created_before: DateTime<Utc>,
+        max_entries: u64,
+    ) -> Result<u64, DbError>;
+    /// Delete the shadow decisions, proving samples and lock bids recorded before
+    /// `created_before`, and the oldest beyond the newest `max_entries` of each. Returns the
+    /// number of deleted records.
+    async fn prune_history(
+        &self,
+        created_before: DateTime<Utc>,
+        max_entries: u64,
+    ) -> Result<u64, DbError>;
+    /// Count the orders skipped at or after `from` and before `to`, by reason.
+    async fn get_skip_counts(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<BTreeMap<SkipReason, u64>, DbError>;
+    /// Append entries to the profit and loss ledger.
+    async fn add_ledger_entries(&self, entries: &[LedgerEntry]) -> Result<(), DbError>;
+    /// Get the ledger entries recorded at or after `from` and before `to`, oldest first, on
+    /// `chain_id` only if set.
+    async fn get_ledger_entries(
+        &self,
+        chain_id: Option<u64>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<LedgerEntry>, DbError>;
+    /// Get the stake locked entries of the orders whose stake was neither returned nor slashed.
+    async fn get_open_stakes(&self) -> Result<Vec<LedgerEntry>, DbError>;
+    /// Record a treasury action before it is taken, returning its ID.
+    async fn add_treasury_action(&self, action: &TreasuryAction) -> Result<i64, DbError>;
+    /// Record the error of a treasury action that failed.
+    async fn set_treasury_action_error(&self, id: i64, error: &str) -> Result<(), DbError>;
+    /// Record the hash of the transaction broadcast for a treasury action, pending until the
+    /// action is confirmed or fails.
+    async fn set_treasury_action_tx_hash(&self, id: i64, tx_hash: B256) -> Result<(), DbError>;
+    /// Mark the transaction of a treasury action as landed.
+    async fn confirm_treasury_action(&self, id: i64) -> Result<(), DbError>;
+    /// Get the treasury actions of the given chain whose transaction is pending, with their IDs.
+    async fn get_pending_treasury_actions(
+        &self,
+        chain_id: u64,
+    ) -> Result<Vec<(i64, TreasuryAction)>, DbError>;
+    /// Get the treasury actions of the given chain recorded at or after `from`, oldest first.
+    async fn get_treasury_actions(
+        &self,
+        chain_id: u64,
+        from: DateTime<Utc>,
+    ) -> Result<Vec<TreasuryAction>, DbError>;
     /// Update a batch with the results of an aggregation step.
     ///
     /// Sets the aggreagtion state, and adds the given orders to the batch, updating the batch fees
@@ -190,6 +292,15 @@ pub trait BrokerDb {
         orders: &[AggregationOrder],
         assessor_proof_id: Option<String>,
     ) -> Result<(), DbError>;
+    /// Reset an aggregating batch to an empty batch, to rebuild it from a clean set builder state.
+    ///
+    /// Clears the orders, fees, deadline and aggregation state of the batch, and returns the given
+    /// orders to the given statuses so they are taken for aggregation again.
+    async fn reset_batch(
+        &self,
+        batch_id: usize,
+        requeued_orders: &[(String, OrderStatus)],
+    ) -> Result<(), DbError>;
     async fn get_batch(&self, batch_id: usize) -> Result<Batch, DbError>;
 
     #[cfg(test)]
@@ -202,6 +313,17 @@ pub trait BrokerDb {
 
 pub type DbObj = Arc<dyn BrokerDb + Send + Sync>;
 
+/// Connect to the broker database, selecting the backend from the scheme of the connection url.
+///
+/// `postgres://` and `postgresql://` urls use [PostgresDb], anything else is opened as SQLite.
+pub async fn connect(db_url: &str) -> Result<DbObj, DbError> {
+    if db_url.starts_with("postgres://") || db_url.starts_with("postgresql://") {
+        Ok(Arc::new(PostgresDb::new(db_url).await?))
+    } else {
+        Ok(Arc::new(SqliteDb::new(db_url).await?))
+    }
+}
+
 pub struct SqliteDb {
     pool: SqlitePool,
 }
@@ -236,8 +358,8 @@ impl SqliteDb {
         Ok(Self { pool })
     }
 
-    async fn new_batch(&self) -> Result<usize, DbError> {
-        let batch = Batch { start_time: Utc::now(), ..Default::default() };
+    async fn new_batch(&self, chain_id: u64) -> Result<usize, DbError> {
+        let batch = Batch { start_time: Utc::now(), chain_id, ..Default::default() };
 
         let res: i64 = sqlx::query_scalar("INSERT INTO batches (data) VALUES ($1) RETURNING id")
             .bind(sqlx::types::Json(&batch))
@@ -317,8 +439,12 @@ impl BrokerDb for SqliteDb {
     }
 
     #[instrument(level = "trace", skip_all, fields(id = %format!("{}", order_request.id())))]
-    async fn insert_skipped_request(&self, order_request: &OrderRequest) -> Result<(), DbError> {
-        self.insert_order_ignore_duplicates(&order_request.to_skipped_order()).await
+    async fn insert_skipped_request(
+        &self,
+        order_request: &OrderRequest,
+        reason: SkipReason,
+    ) -> Result<(), DbError> {
+        self.insert_order_ignore_duplicates(&order_request.to_skipped_order(reason)).await
     }
 
     #[instrument(level = "trace", skip_all, fields(id = %format!("{}", order_request.id())))]
@@ -599,7 +725,10 @@ impl BrokerDb for SqliteDb {
     }
 
     #[instrument(level = "trace", skip_all)]
-    async fn get_aggregation_proofs(&self) -> Result<Vec<AggregationOrder>, DbError> {
+    async fn get_aggregation_proofs(
+        &self,
+        chain_id: u64,
+    ) -> Result<Vec<AggregationOrder>, DbError> {
         let orders: Vec<DbOrder> = sqlx::query_as(
             r#"
             UPDATE orders
@@ -609,6 +738,7 @@ impl BrokerDb for SqliteDb {
                        '$.update_at', $2)
             WHERE
                 data->>'status' IN ($3, $4)
+                AND data->>'chain_id' = $5
             RETURNING *
             "#,
         )
@@ -616,6 +746,7 @@ impl BrokerDb for SqliteDb {
         .bind(Utc::now().timestamp())
         .bind(OrderStatus::PendingAgg)
         .bind(OrderStatus::Aggregating)
+        .bind(chain_id as i64)
         .fetch_all(&self.pool)
         .await?;
 
@@ -643,7 +774,7 @@ impl BrokerDb for SqliteDb {
     }
 
     #[instrument(level = "trace", skip_all)]
-    async fn get_groth16_proofs(&self) -> Result<Vec<AggregationOrder>, DbError> {
+    async fn get_groth16_proofs(&self, chain_id: u64) -> Result<Vec<AggregationOrder>, DbError> {
         let orders: Vec<DbOrder> = sqlx::query_as(
             r#"
             UPDATE orders
@@ -653,12 +784,14 @@ impl BrokerDb for SqliteDb {
                        '$.update_at', $2)
             WHERE
                 data->>'status' == $3
+                AND data->>'chain_id' = $4
             RETURNING *
             "#,
         )
         .bind(OrderStatus::SkipAggregation)
         .bind(Utc::now().timestamp())
         .bind(OrderStatus::SkipAggregation)
+        .bind(chain_id as i64)
         .fetch_all(&self.pool)
         .await?;
 
@@ -713,7 +846,7 @@ impl BrokerDb for SqliteDb {
     }
 
     #[instrument(level = "trace", skip_all)]
-    async fn get_complete_batch(&self) -> Result<Option<(usize, Batch)>, DbError> {
+    async fn get_complete_batch(&self, chain_id: u64) -> Result<Option<(usize, Batch)>, DbError> {
         let elm: Option<DbBatch> = sqlx::query_as(
             r#"
             UPDATE batches
@@ -723,12 +856,14 @@ impl BrokerDb for SqliteDb {
                 (SELECT id
                 FROM batches
                 WHERE data->>'status' = $2
+                    AND data->>'chain_id' = $3
                 LIMIT 1)
             RETURNING *
             "#,
         )
         .bind(BatchStatus::PendingSubmission)
         .bind(BatchStatus::Complete)
+        .bind(chain_id as i64)
         .fetch_optional(&self.pool)
         .await?;
 
@@ -788,25 +923,24 @@ impl BrokerDb for SqliteDb {
     }
 
     #[instrument(level = "trace", skip_all)]
-    async fn get_current_batch(&self) -> Result<usize, DbError> {
-        let batch_count: i64 =
-            sqlx::query_scalar("SELECT COUNT(*) FROM batches").fetch_one(&self.pool).await?;
+    async fn get_current_batch(&self, chain_id: u64) -> Result<usize, DbError> {
+        let cur_batch: Option<DbBatch> = sqlx::query_as(
+            r#"
+            SELECT * FROM batches
+            WHERE data->>'status' IN ($1, $2)
+                AND data->>'chain_id' = $3
+            LIMIT 1"#,
+        )
+        .bind(BatchStatus::Aggregating)
+        .bind(BatchStatus::PendingCompression)
+        .bind(chain_id as i64)
+        .fetch_optional(&self.pool)
+        .await?;
 
-        if batch_count == 0 {
-            self.new_batch().await
+        if let Some(batch) = cur_batch {
+            Ok(batch.id as usize)
         } else {
-            let cur_batch: Option<DbBatch> =
-                sqlx::query_as("SELECT * FROM batches WHERE data->>'status' IN ($1, $2) LIMIT 1")
-                    .bind(BatchStatus::Aggregating)
-                    .bind(BatchStatus::PendingCompression)
-                    .fetch_optional(&self.pool)
-                    .await?;
-
-            if let Some(batch) = cur_batch {
-                Ok(batch.id as usize)
-            } else {
-                self.new_batch().await
-            }
+            self.new_batch(chain_id).await
         }
     }
 
@@ -935,6 +1069,63 @@ impl BrokerDb for SqliteDb {
         Ok(())
     }
 
+    #[instrument(level = "trace", skip(self, requeued_orders))]
+    async fn reset_batch(
+        &self,
+        batch_id: usize,
+        requeued_orders: &[(String, OrderStatus)],
+    ) -> Result<(), DbError> {
+        let mut txn = self.pool.begin().await?;
+
+        let res = sqlx::query(
+            r#"
+            UPDATE batches
+            SET
+                data = json_set(
+                       json_set(
+                       json_remove(data,
+                       '$.deadline', '$.aggregation_state', '$.assessor_proof_id'),
+                       '$.orders', json('[]')),
+                       '$.fees', $1)
+            WHERE
+                id = $2"#,
+        )
+        .bind(format!("0x{:x}", U256::ZERO))
+        .bind(batch_id as i64)
+        .execute(&mut *txn)
+        .await?;
+
+        if res.rows_affected() == 0 {
+            return Err(DbError::BatchNotFound(batch_id));
+        }
+
+        for (order_id, status) in requeued_orders {
+            let res = sqlx::query(
+                r#"
+                UPDATE orders
+                SET data = json_set(
+                           json_set(data,
+                           '$.status', $1),
+                           '$.updated_at', $2)
+                WHERE
+                    id = $3"#,
+            )
+            .bind(status)
+            .bind(Utc::now().timestamp())
+            .bind(order_id)
+            .execute(&mut *txn)
+            .await?;
+
+            if res.rows_affected() == 0 {
+                return Err(DbError::OrderNotFound(order_id.clone()));
+            }
+        }
+
+        txn.commit().await?;
+
+        Ok(())
+    }
+
     #[instrument(level = "trace", skip(self))]
     async fn get_batch(&self, batch_id: usize) -> Result<Batch, DbError> {
         let batch: Option<DbBatch> = sqlx::query_as("SELECT * FROM batches WHERE id = $1")
@@ -952,13 +1143,15 @@ impl BrokerDb for SqliteDb {
     #[instrument(level = "trace", skip(self))]
     async fn set_request_fulfilled(
         &self,
+        chain_id: u64,
         request_id: U256,
         block_number: u64,
     ) -> Result<(), DbError> {
         sqlx::query(
             r#"
-            INSERT INTO fulfilled_requests (id, block_number) VALUES ($1, $2)"#,
+            INSERT INTO fulfilled_requests (chain_id, id, block_number) VALUES ($1, $2, $3)"#,
         )
+        .bind(chain_id as i64)
         .bind(format!("0x{:x}", request_id))
         .bind(block_number as i64)
         .execute(&self.pool)
@@ -968,11 +1161,13 @@ impl BrokerDb for SqliteDb {
     }
 
     #[instrument(level = "trace", skip(self))]
-    async fn is_request_fulfilled(&self, request_id: U256) -> Result<bool, DbError> {
-        let res = sqlx::query(r#"SELECT * FROM fulfilled_requests WHERE id = $1"#)
-            .bind(format!("0x{:x}", request_id))
-            .fetch_optional(&self.pool)
-            .await?;
+    async fn is_request_fulfilled(&self, chain_id: u64, request_id: U256) -> Result<bool, DbError> {
+        let res =
+            sqlx::query(r#"SELECT * FROM fulfilled_requests WHERE chain_id = $1 AND id = $2"#)
+                .bind(chain_id as i64)
+                .bind(format!("0x{:x}", request_id))
+                .fetch_optional(&self.pool)
+                .await?;
 
         Ok(res.is_some())
     }
@@ -980,13 +1175,17 @@ impl BrokerDb for SqliteDb {
     #[instrument(level = "trace", skip(self))]
     async fn set_request_locked(
         &self,
+        chain_id: u64,
         request_id: U256,
         locker: &str,
         block_number: u64,
     ) -> Result<(), DbError> {
         sqlx::query(
-            r#"INSERT INTO locked_requests (id, locker, block_number) VALUES ($1, $2, $3)"#,
+            r#"
+            INSERT INTO locked_requests (chain_id, id, locker, block_number)
+            VALUES ($1, $2, $3, $4)"#,
         )
+        .bind(chain_id as i64)
         .bind(format!("0x{:x}", request_id))
         .bind(locker)
         .bind(block_number as i64)
@@ -997,8 +1196,9 @@ impl BrokerDb for SqliteDb {
     }
 
     #[instrument(level = "trace", skip(self))]
-    async fn is_request_locked(&self, request_id: U256) -> Result<bool, DbError> {
-        let res = sqlx::query(r#"SELECT * FROM locked_requests WHERE id = $1"#)
+    async fn is_request_locked(&self, chain_id: u64, request_id: U256) -> Result<bool, DbError> {
+        let res = sqlx::query(r#"SELECT * FROM locked_requests WHERE chain_id = $1 AND id = $2"#)
+            .bind(chain_id as i64)
             .bind(format!("0x{:x}", request_id))
             .fetch_optional(&self.pool)
             .await?;
@@ -1007,16 +1207,378 @@ impl BrokerDb for SqliteDb {
     }
 
     #[instrument(level = "trace", skip(self))]
-    async fn get_request_locked(&self, request_id: U256) -> Result<Option<(String, u64)>, DbError> {
-        let res: Option<DbLockedRequest> =
-            sqlx::query_as(r#"SELECT * FROM locked_requests WHERE id = $1"#)
-                .bind(format!("0x{:x}", request_id))
-                .fetch_optional(&self.pool)
-                .await?;
+    async fn get_request_locked(
+        &self,
+        chain_id: u64,
+        request_id: U256,
+    ) -> Result<Option<(String, u64)>, DbError> {
+        let res: Option<DbLockedRequest> = sqlx::query_as(
+            r#"
+            SELECT id, locker, block_number FROM locked_requests
+            WHERE chain_id = $1 AND id = $2"#,
+        )
+        .bind(chain_id as i64)
+        .bind(format!("0x{:x}", request_id))
+        .fetch_optional(&self.pool)
+        .await?;
 
         Ok(res.map(|r| (r.locker, r.block_number)))
     }
 
+    #[instrument(level = "trace", skip_all, fields(id = %format!("{}", decision.order_id)))]
+    async fn add_shadow_decision(&self, decision: &ShadowDecision) -> Result<(), DbError> {
+        sqlx::query(
+            "INSERT INTO shadow_decisions (order_id, data, created_at) VALUES ($1, $2, $3)",
+        )
+        .bind(&decision.order_id)
+        .bind(sqlx::types::Json(decision))
+        .bind(decision.created_at.timestamp())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_shadow_decisions(
+        &self,
+        from: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<ShadowDecision>, DbError> {
+        let decisions: Vec<sqlx::types::Json<ShadowDecision>> = sqlx::query_scalar(
+            "SELECT data FROM shadow_decisions WHERE created_at >= $1 ORDER BY id DESC LIMIT $2",
+        )
+        .bind(from.timestamp())
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(decisions.into_iter().rev().map(|decision| decision.0).collect())
+    }
+
+    #[instrument(level = "trace", skip_all, fields(id = %format!("{}", sample.order_id)))]
+    async fn add_proving_sample(&self, sample: &ProvingSample) -> Result<(), DbError> {
+        sqlx::query("INSERT INTO proving_samples (order_id, data, created_at) VALUES ($1, $2, $3)")
+            .bind(&sample.order_id)
+            .bind(sqlx::types::Json(sample))
+            .bind(sample.created_at.timestamp())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_proving_samples(&self, limit: usize) -> Result<Vec<ProvingSample>, DbError> {
+        let samples: Vec<sqlx::types::Json<ProvingSample>> =
+            sqlx::query_scalar("SELECT data FROM proving_samples ORDER BY id DESC LIMIT $1")
+                .bind(limit as i64)
+                .fetch_all(&self.pool)
+                .await?;
+
+        Ok(samples.into_iter().rev().map(|sample| sample.0).collect())
+    }
+
+    #[instrument(level = "trace", skip_all, fields(id = %format!("{}", bid.order_id)))]
+    async fn add_lock_bid(&self, bid: &LockBid) -> Result<(), DbError> {
+        sqlx::query("INSERT INTO lock_bids (order_id, data, created_at) VALUES ($1, $2, $3)")
+            .bind(&bid.order_id)
+            .bind(sqlx::types::Json(bid))
+            .bind(bid.created_at.timestamp())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_lock_bids(&self, limit: usize) -> Result<Vec<LockBid>, DbError> {
+        let bids: Vec<sqlx::types::Json<LockBid>> =
+            sqlx::query_scalar("SELECT data FROM lock_bids ORDER BY id DESC LIMIT $1")
+                .bind(limit as i64)
+                .fetch_all(&self.pool)
+                .await?;
+
+        Ok(bids.into_iter().rev().map(|bid| bid.0).collect())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_preflight_cache(
+        &self,
+        image_id: &str,
+        input_digest: &str,
+    ) -> Result<Option<PreflightCacheEntry>, DbError> {
+        let entry: Option<sqlx::types::Json<PreflightCacheEntry>> = sqlx::query_scalar(
+            "SELECT data FROM preflight_cache WHERE image_id = $1 AND input_digest = $2",
+        )
+        .bind(image_id)
+        .bind(input_digest)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(entry.map(|entry| entry.0))
+    }
+
+    #[instrument(level = "trace", skip_all, fields(image_id = %entry.image_id))]
+    async fn set_preflight_cache(&self, entry: &PreflightCacheEntry) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            INSERT INTO preflight_cache (image_id, input_digest, created_at, data)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (image_id, input_digest)
+            DO UPDATE SET created_at = excluded.created_at, data = excluded.data
+            "#,
+        )
+        .bind(&entry.image_id)
+        .bind(&entry.input_digest)
+        .bind(entry.created_at.timestamp())
+        .bind(sqlx::types::Json(entry))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn prune_preflight_cache(
+        &self,
+        created_before: DateTime<Utc>,
+        max_entries: u64,
+    ) -> Result<u64, DbError> {
+        let expired = sqlx::query("DELETE FROM preflight_cache WHERE created_at < $1")
+            .bind(created_before.timestamp())
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+
+        let evicted = sqlx::query(
+            r#"
+            DELETE FROM preflight_cache
+            WHERE (image_id, input_digest) NOT IN (
+                SELECT image_id, input_digest FROM preflight_cache
+                ORDER BY created_at DESC
+                LIMIT $1)
+            "#,
+        )
+        .bind(i64::try_from(max_entries).unwrap_or(i64::MAX))
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(expired + evicted)
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn prune_history(
+        &self,
+        created_before: DateTime<Utc>,
+        max_entries: u64,
+    ) -> Result<u64, DbError> {
+        let mut pruned = 0;
+        for table in ["shadow_decisions", "proving_samples", "lock_bids"] {
+            pruned += sqlx::query(&format!("DELETE FROM {table} WHERE created_at < $1"))
+                .bind(created_before.timestamp())
+                .execute(&self.pool)
+                .await?
+                .rows_affected();
+
+            pruned += sqlx::query(&format!(
+                r#"
+                DELETE FROM {table}
+                WHERE id NOT IN (SELECT id FROM {table} ORDER BY id DESC LIMIT $1)
+                "#
+            ))
+            .bind(i64::try_from(max_entries).unwrap_or(i64::MAX))
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        }
+
+        Ok(pruned)
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_skip_counts(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<BTreeMap<SkipReason, u64>, DbError> {
+        let counts: Vec<(sqlx::types::Json<SkipReason>, i64)> = sqlx::query_as(
+            r#"
+            SELECT data->'skip_reason', COUNT(*) FROM orders
+                WHERE data->>'status' = $1
+                AND data->>'skip_reason' IS NOT NULL
+                AND data->>'updated_at' >= $2 AND data->>'updated_at' < $3
+                GROUP BY data->'skip_reason'"#,
+        )
+        .bind(OrderStatus::Skipped)
+        .bind(from.timestamp())
+        .bind(to.timestamp())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(counts.into_iter().map(|(reason, count)| (reason.0, count as u64)).collect())
+    }
+
+    #[instrument(level = "trace", skip_all, fields(count = entries.len()))]
+    async fn add_ledger_entries(&self, entries: &[LedgerEntry]) -> Result<(), DbError> {
+        let mut txn = self.pool.begin().await?;
+        for entry in entries {
+            sqlx::query(
+                r#"
+                INSERT INTO ledger (order_id, chain_id, kind, created_at, data)
+                    VALUES ($1, $2, $3, $4, $5)"#,
+            )
+            .bind(&entry.order_id)
+            .bind(entry.chain_id as i64)
+            .bind(entry.kind.as_str())
+            .bind(entry.created_at.timestamp())
+            .bind(sqlx::types::Json(entry))
+            .execute(&mut *txn)
+            .await?;
+        }
+        txn.commit().await?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_ledger_entries(
+        &self,
+        chain_id: Option<u64>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<LedgerEntry>, DbError> {
+        let entries: Vec<sqlx::types::Json<LedgerEntry>> = sqlx::query_scalar(
+            r#"
+            SELECT data FROM ledger
+                WHERE ($1 IS NULL OR chain_id = $1)
+                AND created_at >= $2 AND created_at < $3
+                ORDER BY id"#,
+        )
+        .bind(chain_id.map(|chain_id| chain_id as i64))
+        .bind(from.timestamp())
+        .bind(to.timestamp())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(entries.into_iter().map(|entry| entry.0).collect())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_open_stakes(&self) -> Result<Vec<LedgerEntry>, DbError> {
+        let entries: Vec<sqlx::types::Json<LedgerEntry>> = sqlx::query_scalar(
+            r#"
+            SELECT data FROM ledger AS locked
+                WHERE locked.kind = $1
+                AND NOT EXISTS (
+                    SELECT 1 FROM ledger AS settled
+                        WHERE settled.order_id = locked.order_id
+                        AND settled.kind IN ($2, $3))
+                ORDER BY locked.id"#,
+        )
+        .bind(LedgerKind::StakeLocked.as_str())
+        .bind(LedgerKind::StakeReturned.as_str())
+        .bind(LedgerKind::StakeSlashed.as_str())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(entries.into_iter().map(|entry| entry.0).collect())
+    }
+
+    #[instrument(level = "trace", skip_all, fields(kind = ?action.kind))]
+    async fn add_treasury_action(&self, action: &TreasuryAction) -> Result<i64, DbError> {
+        let id: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO treasury_actions (chain_id, kind, created_at, pending, data)
+                VALUES ($1, $2, $3, $4, $5)
+                RETURNING id"#,
+        )
+        .bind(action.chain_id as i64)
+        .bind(action.kind.as_str())
+        .bind(action.created_at.timestamp())
+        .bind(action.tx_hash.is_some())
+        .bind(sqlx::types::Json(action))
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(id)
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn set_treasury_action_error(&self, id: i64, error: &str) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            UPDATE treasury_actions
+            SET data = json_set(data, '$.error', $1), pending = FALSE
+            WHERE id = $2"#,
+        )
+        .bind(error)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn set_treasury_action_tx_hash(&self, id: i64, tx_hash: B256) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            UPDATE treasury_actions
+            SET data = json_set(data, '$.tx_hash', $1), pending = TRUE
+            WHERE id = $2"#,
+        )
+        .bind(tx_hash.to_string())
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn confirm_treasury_action(&self, id: i64) -> Result<(), DbError> {
+        sqlx::query("UPDATE treasury_actions SET pending = FALSE WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_pending_treasury_actions(
+        &self,
+        chain_id: u64,
+    ) -> Result<Vec<(i64, TreasuryAction)>, DbError> {
+        let actions: Vec<(i64, sqlx::types::Json<TreasuryAction>)> = sqlx::query_as(
+            "SELECT id, data FROM treasury_actions WHERE chain_id = $1 AND pending ORDER BY id",
+        )
+        .bind(chain_id as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(actions.into_iter().map(|(id, action)| (id, action.0)).collect())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_treasury_actions(
+        &self,
+        chain_id: u64,
+        from: DateTime<Utc>,
+    ) -> Result<Vec<TreasuryAction>, DbError> {
+        let actions: Vec<sqlx::types::Json<TreasuryAction>> = sqlx::query_scalar(
+            "SELECT data FROM treasury_actions WHERE chain_id = $1 AND created_at >= $2 ORDER BY id",
+        )
+        .bind(chain_id as i64)
+        .bind(from.timestamp())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(actions.into_iter().map(|action| action.0).collect())
+    }
+
     #[cfg(test)]
     async fn add_batch(&self, batch_id: usize, batch: Batch) -> Result<(), DbError> {
         let res = sqlx::query("INSERT INTO batches (id, data) VALUES ($1, $2)")
@@ -1059,15 +1621,44 @@ impl BrokerDb for SqliteDb {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::ProofRequest;
-    use alloy::primitives::{Address, Bytes, U256};
+    use crate::{
+        lock_bidding::LockBidOutcome, preflight_cache::PreflightOutcome, shadow::ShadowAction,
+        treasury::TreasuryActionKind, ProofRequest,
+    };
+    use alloy::primitives::{Address, Bytes, I256, U256};
     use boundless_market::contracts::{
         Offer, Predicate, PredicateType, RequestId, RequestInput, RequestInputType, Requirements,
     };
     use risc0_aggregation::GuestState;
     use risc0_zkvm::sha::Digest;
+    use sqlx::postgres::PgPool;
     use tracing_test::traced_test;
 
+    /// Defines a test that is run against both the SQLite and the Postgres backends.
+    ///
+    /// The Postgres variant requires `DATABASE_URL` to point to a running Postgres server.
+    macro_rules! db_test {
+        ($(#[$attr:meta])* async fn $name:ident($db:ident) $body:block) => {
+            mod $name {
+                use super::*;
+
+                #[sqlx::test]
+                $(#[$attr])*
+                async fn sqlite(pool: SqlitePool) {
+                    let $db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
+                    $body
+                }
+
+                #[sqlx::test(migrations = "./migrations/postgres")]
+                $(#[$attr])*
+                async fn postgres(pool: PgPool) {
+                    let $db: DbObj = Arc::new(PostgresDb::from(pool).await.unwrap());
+                    $body
+                }
+            }
+        };
+    }
+
     fn create_order_request() -> OrderRequest {
         OrderRequest::new(
             ProofRequest::new(
@@ -1102,587 +1693,1012 @@ mod tests {
         create_order_request().to_proving_order(Default::default())
     }
 
-    #[sqlx::test]
-    async fn add_order(pool: SqlitePool) {
-        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
-        let order = create_order_request();
-        db.insert_accepted_request(&order, U256::ZERO).await.unwrap();
+    db_test! {
+        async fn add_order(db) {
+            let order = create_order_request();
+            db.insert_accepted_request(&order, U256::ZERO).await.unwrap();
+        }
+    }
+
+    db_test! {
+        async fn get_order(db) {
+            let order = create_order();
+            db.add_order(&order).await.unwrap();
+
+            let db_order = db.get_order(&order.id()).await.unwrap().unwrap();
+
+            assert_eq!(order.request, db_order.request);
+        }
+    }
+
+    db_test! {
+        async fn get_orders(db) {
+            let mut order1 = create_order();
+            order1.request.id = U256::from(1);
+            let mut order2 = create_order();
+            order2.request.id = U256::from(2);
+            let mut order3 = create_order();
+            order3.request.id = U256::from(3);
+            db.add_order(&order1).await.unwrap();
+            db.add_order(&order2).await.unwrap();
+            db.add_order(&order3).await.unwrap();
+
+            let ids = [order1.id(), order2.id(), order3.id()];
+            let id_refs: Vec<&str> = ids.iter().map(|s| s.as_str()).collect();
+            let orders = db.get_orders(&id_refs).await.unwrap();
+            assert_eq!(orders.len(), 3);
+            let returned_ids: Vec<String> = orders.iter().map(|o| o.id()).collect();
+            assert!(returned_ids.contains(&order1.id()));
+            assert!(returned_ids.contains(&order2.id()));
+            assert!(returned_ids.contains(&order3.id()));
+
+            // Test empty input returns empty vec
+            let empty: Vec<&str> = vec![];
+            let orders = db.get_orders(&empty).await.unwrap();
+            assert!(orders.is_empty());
+        }
+    }
+
+    db_test! {
+        async fn get_submission_order(db) {
+            let mut order = create_order();
+            order.proof_id = Some("test".to_string());
+            order.lock_price = Some(U256::from(10));
+            db.add_order(&order).await.unwrap();
+
+            let submit_order: (ProofRequest, Bytes, String, B256, U256, FulfillmentType) =
+                db.get_submission_order(&order.id()).await.unwrap();
+            assert_eq!(submit_order.0, order.request);
+            assert_eq!(submit_order.1, order.client_sig);
+            assert_eq!(submit_order.2, order.proof_id.unwrap());
+            assert_eq!(submit_order.3, order.request.requirements.imageId);
+            assert_eq!(submit_order.4, order.lock_price.unwrap());
+            assert_eq!(submit_order.5, order.fulfillment_type);
+        }
     }
 
-    #[sqlx::test]
-    async fn get_order(pool: SqlitePool) {
-        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
-        let order = create_order();
-        db.add_order(&order).await.unwrap();
+    db_test! {
+        async fn set_order_failure(db) {
+            let order = create_order();
+            db.add_order(&order).await.unwrap();
 
-        let db_order = db.get_order(&order.id()).await.unwrap().unwrap();
+            let failure_str = "TEST_FAIL";
+            db.set_order_failure(&order.id(), failure_str).await.unwrap();
 
-        assert_eq!(order.request, db_order.request);
+            let db_order = db.get_order(&order.id()).await.unwrap().unwrap();
+            assert_eq!(db_order.status, OrderStatus::Failed);
+            assert_eq!(db_order.error_msg, Some(failure_str.into()));
+        }
     }
 
-    #[sqlx::test]
-    async fn get_orders(pool: SqlitePool) {
-        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
-        let mut order1 = create_order();
-        order1.request.id = U256::from(1);
-        let mut order2 = create_order();
-        order2.request.id = U256::from(2);
-        let mut order3 = create_order();
-        order3.request.id = U256::from(3);
-        db.add_order(&order1).await.unwrap();
-        db.add_order(&order2).await.unwrap();
-        db.add_order(&order3).await.unwrap();
+    db_test! {
+        async fn set_order_complete(db) {
+            let order = create_order();
+            db.add_order(&order).await.unwrap();
 
-        let ids = [order1.id(), order2.id(), order3.id()];
-        let id_refs: Vec<&str> = ids.iter().map(|s| s.as_str()).collect();
-        let orders = db.get_orders(&id_refs).await.unwrap();
-        assert_eq!(orders.len(), 3);
-        let returned_ids: Vec<String> = orders.iter().map(|o| o.id()).collect();
-        assert!(returned_ids.contains(&order1.id()));
-        assert!(returned_ids.contains(&order2.id()));
-        assert!(returned_ids.contains(&order3.id()));
+            db.set_order_complete(&order.id()).await.unwrap();
 
-        // Test empty input returns empty vec
-        let empty: Vec<&str> = vec![];
-        let orders = db.get_orders(&empty).await.unwrap();
-        assert!(orders.is_empty());
+            let db_order = db.get_order(&order.id()).await.unwrap().unwrap();
+            assert_eq!(db_order.status, OrderStatus::Done);
+        }
     }
 
-    #[sqlx::test]
-    async fn get_submission_order(pool: SqlitePool) {
-        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
-        let mut order = create_order();
-        order.proof_id = Some("test".to_string());
-        order.lock_price = Some(U256::from(10));
-        db.add_order(&order).await.unwrap();
+    db_test! {
+        async fn skip_order(db) {
+            let order = create_order_request();
 
-        let submit_order: (ProofRequest, Bytes, String, B256, U256, FulfillmentType) =
-            db.get_submission_order(&order.id()).await.unwrap();
-        assert_eq!(submit_order.0, order.request);
-        assert_eq!(submit_order.1, order.client_sig);
-        assert_eq!(submit_order.2, order.proof_id.unwrap());
-        assert_eq!(submit_order.3, order.request.requirements.imageId);
-        assert_eq!(submit_order.4, order.lock_price.unwrap());
-        assert_eq!(submit_order.5, order.fulfillment_type);
+            db.insert_skipped_request(&order, SkipReason::UnderPriced).await.unwrap();
+            let db_order = db.get_order(&order.id()).await.unwrap().unwrap();
+            assert_eq!(db_order.status, OrderStatus::Skipped);
+            assert_eq!(db_order.skip_reason, Some(SkipReason::UnderPriced));
+        }
     }
 
-    #[sqlx::test]
-    async fn set_order_failure(pool: SqlitePool) {
-        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
-        let order = create_order();
-        db.add_order(&order).await.unwrap();
+    db_test! {
+        async fn skip_counts(db) {
+            let now = Utc::now();
+            let mut order = create_order_request();
+            for (idx, reason) in
+                [SkipReason::UnderPriced, SkipReason::Expired, SkipReason::UnderPriced]
+                    .into_iter()
+                    .enumerate()
+            {
+                order.request.id = U256::from(idx);
+                db.insert_skipped_request(&order, reason).await.unwrap();
+            }
+            // Accepted orders are not counted
+            order.request.id = U256::from(10);
+            db.insert_accepted_request(&order, U256::ZERO).await.unwrap();
+
+            let window_start = now - chrono::Duration::seconds(60);
+            let window_end = now + chrono::Duration::seconds(60);
+            let counts = db.get_skip_counts(window_start, window_end).await.unwrap();
+            assert_eq!(counts.len(), 2);
+            assert_eq!(counts[&SkipReason::UnderPriced], 2);
+            assert_eq!(counts[&SkipReason::Expired], 1);
+
+            // Orders skipped outside of the window are not counted
+            let counts = db.get_skip_counts(window_end, window_end + chrono::Duration::seconds(60)).await.unwrap();
+            assert!(counts.is_empty());
+        }
+    }
 
-        let failure_str = "TEST_FAIL";
-        db.set_order_failure(&order.id(), failure_str).await.unwrap();
+    db_test! {
+        async fn get_proving_order(db) {
+            let id = U256::ZERO;
+            let mut order = create_order();
+            order.status = OrderStatus::PendingProving;
+            order.request.id = id;
+            db.add_order(&order).await.unwrap();
 
-        let db_order = db.get_order(&order.id()).await.unwrap().unwrap();
-        assert_eq!(db_order.status, OrderStatus::Failed);
-        assert_eq!(db_order.error_msg, Some(failure_str.into()));
+            let db_order = db.get_proving_order().await.unwrap();
+            let db_order = db_order.unwrap();
+            assert_eq!(db_order.id(), order.id());
+            assert_eq!(db_order.status, OrderStatus::Proving);
+        }
     }
 
-    #[sqlx::test]
-    async fn set_order_complete(pool: SqlitePool) {
-        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
-        let order = create_order();
-        db.add_order(&order).await.unwrap();
+    db_test! {
+        async fn set_order_proof_id(db) {
+            let id = U256::ZERO;
+            let mut order = create_order();
+            order.request.id = id;
+            db.add_order(&order).await.unwrap();
 
-        db.set_order_complete(&order.id()).await.unwrap();
+            let proof_id = "test";
+            db.set_order_proof_id(&order.id(), proof_id).await.unwrap();
 
-        let db_order = db.get_order(&order.id()).await.unwrap().unwrap();
-        assert_eq!(db_order.status, OrderStatus::Done);
+            let db_order = db.get_order(&order.id()).await.unwrap().unwrap();
+            assert_eq!(db_order.proof_id, Some(proof_id.into()));
+        }
     }
-
-    #[sqlx::test]
-    async fn skip_order(pool: SqlitePool) {
-        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
-        let order = create_order_request();
-
-        db.insert_skipped_request(&order).await.unwrap();
-        let db_order = db.get_order(&order.id()).await.unwrap().unwrap();
-        assert_eq!(db_order.status, OrderStatus::Skipped);
-    }
-
-    #[sqlx::test]
-    async fn get_proving_order(pool: SqlitePool) {
-        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
-
-        let id = U256::ZERO;
-        let mut order = create_order();
-        order.status = OrderStatus::PendingProving;
-        order.request.id = id;
-        db.add_order(&order).await.unwrap();
-
-        let db_order = db.get_proving_order().await.unwrap();
-        let db_order = db_order.unwrap();
-        assert_eq!(db_order.id(), order.id());
-        assert_eq!(db_order.status, OrderStatus::Proving);
-    }
-
-    #[sqlx::test]
-    async fn set_order_proof_id(pool: SqlitePool) {
-        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
-
-        let id = U256::ZERO;
-        let mut order = create_order();
-        order.request.id = id;
-        db.add_order(&order).await.unwrap();
-
-        let proof_id = "test";
-        db.set_order_proof_id(&order.id(), proof_id).await.unwrap();
-
-        let db_order = db.get_order(&order.id()).await.unwrap().unwrap();
-        assert_eq!(db_order.proof_id, Some(proof_id.into()));
-    }
-
-    #[sqlx::test]
-    async fn get_active_proofs(pool: SqlitePool) {
-        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
-
-        let id = U256::ZERO;
-        let mut order = create_order();
-        order.status = OrderStatus::Done;
-        order.request.id = id;
-        db.add_order(&order).await.unwrap();
-
-        let id_2 = U256::from(1);
-        let mut order = create_order();
-        order.status = OrderStatus::Proving;
-        order.request.id = id_2;
-        db.add_order(&order).await.unwrap();
-
-        let proving_orders = db.get_active_proofs().await.unwrap();
-        assert_eq!(proving_orders.len(), 1);
-        assert_eq!(proving_orders[0].id(), order.id());
+
+    db_test! {
+        async fn get_active_proofs(db) {
+            let id = U256::ZERO;
+            let mut order = create_order();
+            order.status = OrderStatus::Done;
+            order.request.id = id;
+            db.add_order(&order).await.unwrap();
+
+            let id_2 = U256::from(1);
+            let mut order = create_order();
+            order.status = OrderStatus::Proving;
+            order.request.id = id_2;
+            db.add_order(&order).await.unwrap();
+
+            let proving_orders = db.get_active_proofs().await.unwrap();
+            assert_eq!(proving_orders.len(), 1);
+            assert_eq!(proving_orders[0].id(), order.id());
+        }
+    }
+
+    db_test! {
+        async fn set_aggregation_status(db) {
+            let id = U256::ZERO;
+            let mut order = create_order();
+            order.request.id = id;
+            db.add_order(&order).await.unwrap();
+
+            db.set_aggregation_status(&order.id(), OrderStatus::PendingAgg).await.unwrap();
+
+            let db_order = db.get_order(&order.id()).await.unwrap().unwrap();
+
+            assert_eq!(db_order.status, OrderStatus::PendingAgg);
+        }
+    }
+
+    db_test! {
+        async fn get_aggregation_proofs(db) {
+            let mut orders = [
+                Order {
+                    status: OrderStatus::PendingProving,
+                    proof_id: Some("test_id3".to_string()),
+                    expire_timestamp: Some(10),
+                    lock_price: Some(U256::from(10u64)),
+                    ..create_order()
+                },
+                Order {
+                    status: OrderStatus::PendingAgg,
+                    proof_id: Some("test_id1".to_string()),
+                    expire_timestamp: Some(10),
+                    lock_price: Some(U256::from(10u64)),
+                    ..create_order()
+                },
+                Order {
+                    status: OrderStatus::Aggregating,
+                    proof_id: Some("test_id2".to_string()),
+                    expire_timestamp: Some(10),
+                    lock_price: Some(U256::from(10u64)),
+                    ..create_order()
+                },
+                Order {
+                    status: OrderStatus::PendingSubmission,
+                    proof_id: Some("test_id4".to_string()),
+                    expire_timestamp: Some(10),
+                    lock_price: Some(U256::from(10u64)),
+                    ..create_order()
+                },
+            ];
+            for (i, order) in orders.iter_mut().enumerate() {
+                order.request.id = U256::from(i);
+                db.add_order(order).await.unwrap();
+            }
+
+            let agg_proofs = db.get_aggregation_proofs(1).await.unwrap();
+
+            assert_eq!(agg_proofs.len(), 2);
+
+            let agg_proof = &agg_proofs[0];
+            assert_eq!(agg_proof.order_id, orders[1].id());
+            assert_eq!(agg_proof.proof_id, "test_id1");
+            assert_eq!(agg_proof.expiration, 10);
+            assert_eq!(agg_proof.fee, U256::from(10u64));
+
+            let agg_proof = &agg_proofs[1];
+            assert_eq!(agg_proof.order_id, orders[2].id());
+            assert_eq!(agg_proof.proof_id, "test_id2");
+            assert_eq!(agg_proof.expiration, 10);
+            assert_eq!(agg_proof.fee, U256::from(10u64));
+
+            let db_order = db.get_order(&agg_proofs[0].order_id).await.unwrap().unwrap();
+            assert_eq!(db_order.status, OrderStatus::Aggregating);
+            let db_order = db.get_order(&agg_proofs[1].order_id).await.unwrap().unwrap();
+            assert_eq!(db_order.status, OrderStatus::Aggregating);
+        }
     }
 
-    #[sqlx::test]
-    async fn set_aggregation_status(pool: SqlitePool) {
-        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
+    db_test! {
+        async fn get_current_batch(db) {
+            let batch_id = db.get_current_batch(1).await.unwrap();
+            assert_eq!(batch_id, 1);
 
-        let id = U256::ZERO;
-        let mut order = create_order();
-        order.request.id = id;
-        db.add_order(&order).await.unwrap();
+            let batch = db.get_batch(batch_id).await.unwrap();
+            assert_eq!(batch.status, BatchStatus::Aggregating);
 
-        db.set_aggregation_status(&order.id(), OrderStatus::PendingAgg).await.unwrap();
+            let batch_id = db.get_current_batch(1).await.unwrap();
+            assert_eq!(batch_id, 1);
 
-        let db_order = db.get_order(&order.id()).await.unwrap().unwrap();
+            db.set_batch_status(1, BatchStatus::PendingCompression).await.unwrap();
 
-        assert_eq!(db_order.status, OrderStatus::PendingAgg);
+            let batch = db.get_batch(batch_id).await.unwrap();
+            assert_eq!(batch.status, BatchStatus::PendingCompression);
+
+            let batch_id = db.get_current_batch(1).await.unwrap();
+            assert_eq!(batch_id, 1);
+        }
+    }
+
+    db_test! {
+        async fn batches_by_chain(db) {
+            assert_eq!(db.get_current_batch(1).await.unwrap(), 1);
+            assert_eq!(db.get_current_batch(2).await.unwrap(), 2);
+            assert_eq!(db.get_current_batch(1).await.unwrap(), 1);
+            assert_eq!(db.get_batch(2).await.unwrap().chain_id, 2);
+
+            let mut orders = [
+                Order {
+                    status: OrderStatus::PendingAgg,
+                    proof_id: Some("chain_1".to_string()),
+                    expire_timestamp: Some(10),
+                    lock_price: Some(U256::from(10u64)),
+                    ..create_order()
+                },
+                Order {
+                    status: OrderStatus::PendingAgg,
+                    proof_id: Some("chain_2".to_string()),
+                    expire_timestamp: Some(10),
+                    lock_price: Some(U256::from(10u64)),
+                    chain_id: 2,
+                    ..create_order()
+                },
+            ];
+            for (i, order) in orders.iter_mut().enumerate() {
+                order.request.id = U256::from(i);
+                db.add_order(order).await.unwrap();
+            }
+            let agg_proofs = db.get_aggregation_proofs(2).await.unwrap();
+            assert_eq!(agg_proofs.len(), 1);
+            assert_eq!(agg_proofs[0].proof_id, "chain_2");
+
+            let batch = Batch {
+                start_time: Utc::now(),
+                status: BatchStatus::Complete,
+                chain_id: 2,
+                ..Default::default()
+            };
+            db.add_batch(3, batch).await.unwrap();
+            assert!(db.get_complete_batch(2).await.unwrap().is_some());
+            assert!(db.get_complete_batch(1).await.unwrap().is_none());
+        }
     }
 
-    #[sqlx::test]
-    async fn get_aggregation_proofs(pool: SqlitePool) {
-        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
+    db_test! {
+        async fn add_batch(db) {
+            let batch_id = 1;
+            let batch = Batch { start_time: Utc::now(), ..Default::default() };
+            db.add_batch(batch_id, batch.clone()).await.unwrap();
 
-        let mut orders = [
-            Order {
-                status: OrderStatus::PendingProving,
-                proof_id: Some("test_id3".to_string()),
-                expire_timestamp: Some(10),
-                lock_price: Some(U256::from(10u64)),
-                ..create_order()
-            },
-            Order {
-                status: OrderStatus::PendingAgg,
-                proof_id: Some("test_id1".to_string()),
-                expire_timestamp: Some(10),
-                lock_price: Some(U256::from(10u64)),
-                ..create_order()
-            },
-            Order {
-                status: OrderStatus::Aggregating,
-                proof_id: Some("test_id2".to_string()),
-                expire_timestamp: Some(10),
-                lock_price: Some(U256::from(10u64)),
-                ..create_order()
-            },
-            Order {
-                status: OrderStatus::PendingSubmission,
-                proof_id: Some("test_id4".to_string()),
-                expire_timestamp: Some(10),
-                lock_price: Some(U256::from(10u64)),
-                ..create_order()
-            },
-        ];
-        for (i, order) in orders.iter_mut().enumerate() {
-            order.request.id = U256::from(i);
-            db.add_order(order).await.unwrap();
+            let batch = db.get_batch(batch_id).await.unwrap();
+            assert_eq!(batch.status, BatchStatus::Aggregating);
         }
+    }
 
-        let agg_proofs = db.get_aggregation_proofs().await.unwrap();
+    db_test! {
+        async fn complete_batch(db) {
+            let batch_id = 1;
+            let batch = Batch {
+                aggregation_state: Some(AggregationState {
+                    guest_state: GuestState::initial([1u32; 8]),
+                    claim_digests: vec![],
+                    groth16_proof_id: None,
+                    proof_id: "a".to_string(),
+                }),
+                ..Default::default()
+            };
+            db.add_batch(batch_id, batch).await.unwrap();
+
+            let g16_proof_id = "Testg16";
+            db.complete_batch(batch_id, g16_proof_id).await.unwrap();
+
+            let db_batch = db.get_batch(batch_id).await.unwrap();
+            assert_eq!(db_batch.status, BatchStatus::Complete);
+            assert_eq!(db_batch.aggregation_state.unwrap().groth16_proof_id.unwrap(), g16_proof_id);
+        }
+    }
 
-        assert_eq!(agg_proofs.len(), 2);
+    db_test! {
+        async fn get_complete_batch(db) {
+            let batch_id = 1;
+            let batch = Batch {
+                start_time: Utc::now(),
+                status: BatchStatus::Complete,
+                chain_id: 1,
+                ..Default::default()
+            };
 
-        let agg_proof = &agg_proofs[0];
-        assert_eq!(agg_proof.order_id, orders[1].id());
-        assert_eq!(agg_proof.proof_id, "test_id1");
-        assert_eq!(agg_proof.expiration, 10);
-        assert_eq!(agg_proof.fee, U256::from(10u64));
+            db.add_batch(batch_id, batch.clone()).await.unwrap();
 
-        let agg_proof = &agg_proofs[1];
-        assert_eq!(agg_proof.order_id, orders[2].id());
-        assert_eq!(agg_proof.proof_id, "test_id2");
-        assert_eq!(agg_proof.expiration, 10);
-        assert_eq!(agg_proof.fee, U256::from(10u64));
+            let (db_batch_id, db_batch) = db.get_complete_batch(1).await.unwrap().unwrap();
+            assert_eq!(db_batch_id, batch_id);
+            assert_eq!(db_batch.status, BatchStatus::PendingSubmission);
+        }
+    }
 
-        let db_order = db.get_order(&agg_proofs[0].order_id).await.unwrap().unwrap();
-        assert_eq!(db_order.status, OrderStatus::Aggregating);
-        let db_order = db.get_order(&agg_proofs[1].order_id).await.unwrap().unwrap();
-        assert_eq!(db_order.status, OrderStatus::Aggregating);
-    }
+    db_test! {
+        async fn set_batch_submitted(db) {
+            let batch_id = db.get_current_batch(1).await.unwrap();
+            db.set_batch_submitted(batch_id).await.unwrap();
 
-    #[sqlx::test]
-    async fn get_current_batch(pool: SqlitePool) {
-        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
-
-        let batch_id = db.get_current_batch().await.unwrap();
-        assert_eq!(batch_id, 1);
-
-        let batch = db.get_batch(batch_id).await.unwrap();
-        assert_eq!(batch.status, BatchStatus::Aggregating);
-
-        let batch_id = db.get_current_batch().await.unwrap();
-        assert_eq!(batch_id, 1);
-
-        db.set_batch_status(1, BatchStatus::PendingCompression).await.unwrap();
-
-        let batch = db.get_batch(batch_id).await.unwrap();
-        assert_eq!(batch.status, BatchStatus::PendingCompression);
+            let db_batch = db.get_batch(batch_id).await.unwrap();
+            assert_eq!(db_batch.status, BatchStatus::Submitted);
+        }
+    }
+
+    db_test! {
+        async fn set_batch_failure(db) {
+            let batch_id = db.get_current_batch(1).await.unwrap();
+            let err_msg = "test_err";
+            db.set_batch_failure(batch_id, err_msg.into()).await.unwrap();
 
-        let batch_id = db.get_current_batch().await.unwrap();
-        assert_eq!(batch_id, 1);
+            let db_batch = db.get_batch(batch_id).await.unwrap();
+            assert_eq!(db_batch.status, BatchStatus::Failed);
+            assert_eq!(db_batch.error_msg, Some(err_msg.into()));
+        }
     }
 
-    #[sqlx::test]
-    async fn add_batch(pool: SqlitePool) {
-        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
-
-        let batch_id = 1;
-        let batch = Batch { start_time: Utc::now(), ..Default::default() };
-        db.add_batch(batch_id, batch.clone()).await.unwrap();
-
-        let batch = db.get_batch(batch_id).await.unwrap();
-        assert_eq!(batch.status, BatchStatus::Aggregating);
-    }
-
-    #[sqlx::test]
-    async fn complete_batch(pool: SqlitePool) {
-        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
-
-        let batch_id = 1;
-        let batch = Batch {
-            aggregation_state: Some(AggregationState {
-                guest_state: GuestState::initial([1u32; 8]),
+    db_test! {
+        async fn update_batch(db) {
+            // Create a persistent DB for manual testing:
+            //
+            // let db_url = "sqlite:///tmp/test.db";
+            // if !Sqlite::database_exists(db_url).await.unwrap() {
+            //     Sqlite::create_database(db_url).await.unwrap()
+            // }
+            // let tmp_pool = SqlitePool::connect("sqlite:///tmp/test.db").await.unwrap();
+            // sqlx::migrate!("./migrations").run(&tmp_pool).await.unwrap();
+
+            let mut order1 = create_order();
+            order1.request.id = U256::from(11);
+            db.add_order(&order1).await.unwrap();
+            let mut order2 = create_order();
+            order2.request.id = U256::from(12);
+            db.add_order(&order2).await.unwrap();
+
+            let batch_id = 1;
+            let agg_proofs = [
+                AggregationOrder {
+                    proof_id: "a".to_string(),
+                    order_id: order1.id(),
+                    expiration: 20,
+                    fee: U256::from(5),
+                },
+                AggregationOrder {
+                    proof_id: "b".to_string(),
+                    order_id: order2.id(),
+                    expiration: 25,
+                    fee: U256::from(10),
+                },
+            ];
+            let claim_digests = vec![[1u32; 8].into(), [2u32; 8].into()];
+            let mut guest_state = GuestState::initial([3u32; 8]);
+            guest_state.mmr.extend(&claim_digests);
+            let agg_state = AggregationState {
+                guest_state,
+                proof_id: "c".to_string(),
+                claim_digests: claim_digests.clone(),
+                groth16_proof_id: None,
+            };
+
+            let base_fees = U256::from(10);
+            let batch = Batch {
+                start_time: Utc::now(),
+                deadline: Some(100),
+                fees: base_fees,
+                ..Default::default()
+            };
+
+            db.add_batch(batch_id, batch.clone()).await.unwrap();
+            db.update_batch(batch_id, &agg_state, &agg_proofs, Some("proof_id".to_string()))
+                .await
+                .unwrap();
+
+            let db_batch = db.get_batch(batch_id).await.unwrap();
+            assert_eq!(db_batch.status, BatchStatus::PendingCompression);
+            assert_eq!(db_batch.orders, vec![order1.id(), order2.id()]);
+            assert_eq!(db_batch.deadline, Some(20));
+            assert_eq!(db_batch.fees, U256::from(25));
+            assert!(db_batch.aggregation_state.is_some());
+            let agg_state = db_batch.aggregation_state.unwrap();
+            assert_eq!(agg_state.groth16_proof_id.as_ref(), None);
+            assert!(!agg_state.guest_state.is_initial());
+            assert_eq!(&agg_state.proof_id, "c");
+            assert_eq!(&agg_state.claim_digests, &claim_digests);
+        }
+    }
+
+    db_test! {
+        async fn reset_batch(db) {
+            let mut order1 = create_order();
+            order1.request.id = U256::from(11);
+            db.add_order(&order1).await.unwrap();
+            let mut order2 = create_order();
+            order2.request.id = U256::from(12);
+            db.add_order(&order2).await.unwrap();
+
+            let batch_id = 1;
+            let agg_proofs = [
+                AggregationOrder {
+                    proof_id: "a".to_string(),
+                    order_id: order1.id(),
+                    expiration: 20,
+                    fee: U256::from(5),
+                },
+                AggregationOrder {
+                    proof_id: "b".to_string(),
+                    order_id: order2.id(),
+                    expiration: 25,
+                    fee: U256::from(10),
+                },
+            ];
+            let agg_state = AggregationState {
+                guest_state: GuestState::initial([3u32; 8]),
+                proof_id: "c".to_string(),
                 claim_digests: vec![],
                 groth16_proof_id: None,
-                proof_id: "a".to_string(),
-            }),
-            ..Default::default()
-        };
-        db.add_batch(batch_id, batch).await.unwrap();
-
-        let g16_proof_id = "Testg16";
-        db.complete_batch(batch_id, g16_proof_id).await.unwrap();
-
-        let db_batch = db.get_batch(batch_id).await.unwrap();
-        assert_eq!(db_batch.status, BatchStatus::Complete);
-        assert_eq!(db_batch.aggregation_state.unwrap().groth16_proof_id.unwrap(), g16_proof_id);
-    }
-
-    #[sqlx::test]
-    async fn get_complete_batch(pool: SqlitePool) {
-        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
-
-        let batch_id = 1;
-        let batch =
-            Batch { start_time: Utc::now(), status: BatchStatus::Complete, ..Default::default() };
-
-        db.add_batch(batch_id, batch.clone()).await.unwrap();
-
-        let (db_batch_id, db_batch) = db.get_complete_batch().await.unwrap().unwrap();
-        assert_eq!(db_batch_id, batch_id);
-        assert_eq!(db_batch.status, BatchStatus::PendingSubmission);
-    }
-
-    #[sqlx::test]
-    async fn set_batch_submitted(pool: SqlitePool) {
-        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
-
-        let batch_id = db.get_current_batch().await.unwrap();
-        db.set_batch_submitted(batch_id).await.unwrap();
-
-        let db_batch = db.get_batch(batch_id).await.unwrap();
-        assert_eq!(db_batch.status, BatchStatus::Submitted);
-    }
-
-    #[sqlx::test]
-    async fn set_batch_failure(pool: SqlitePool) {
-        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
-
-        let batch_id = db.get_current_batch().await.unwrap();
-        let err_msg = "test_err";
-        db.set_batch_failure(batch_id, err_msg.into()).await.unwrap();
-
-        let db_batch = db.get_batch(batch_id).await.unwrap();
-        assert_eq!(db_batch.status, BatchStatus::Failed);
-        assert_eq!(db_batch.error_msg, Some(err_msg.into()));
-    }
-
-    #[sqlx::test]
-    async fn update_batch(pool: SqlitePool) {
-        // Create a persistent DB for manual testing:
-        //
-        // let db_url = "sqlite:///tmp/test.db";
-        // if !Sqlite::database_exists(db_url).await.unwrap() {
-        //     Sqlite::create_database(db_url).await.unwrap()
-        // }
-        // let tmp_pool = SqlitePool::connect("sqlite:///tmp/test.db").await.unwrap();
-        // sqlx::migrate!("./migrations").run(&tmp_pool).await.unwrap();
-
-        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
-        let mut order1 = create_order();
-        order1.request.id = U256::from(11);
-        db.add_order(&order1).await.unwrap();
-        let mut order2 = create_order();
-        order2.request.id = U256::from(12);
-        db.add_order(&order2).await.unwrap();
-
-        let batch_id = 1;
-        let agg_proofs = [
-            AggregationOrder {
-                proof_id: "a".to_string(),
-                order_id: order1.id(),
-                expiration: 20,
-                fee: U256::from(5),
-            },
-            AggregationOrder {
-                proof_id: "b".to_string(),
-                order_id: order2.id(),
-                expiration: 25,
-                fee: U256::from(10),
-            },
-        ];
-        let claim_digests = vec![[1u32; 8].into(), [2u32; 8].into()];
-        let mut guest_state = GuestState::initial([3u32; 8]);
-        guest_state.mmr.extend(&claim_digests);
-        let agg_state = AggregationState {
-            guest_state,
-            proof_id: "c".to_string(),
-            claim_digests: claim_digests.clone(),
-            groth16_proof_id: None,
-        };
+            };
+            let start_time = Utc::now();
+            db.add_batch(batch_id, Batch { start_time, ..Default::default() }).await.unwrap();
+            db.update_batch(batch_id, &agg_state, &agg_proofs, None).await.unwrap();
+
+            db.reset_batch(batch_id, &[(order2.id(), OrderStatus::PendingAgg)]).await.unwrap();
+
+            let db_batch = db.get_batch(batch_id).await.unwrap();
+            assert_eq!(db_batch.status, BatchStatus::Aggregating);
+            assert!(db_batch.orders.is_empty());
+            assert_eq!(db_batch.deadline, None);
+            assert_eq!(db_batch.fees, U256::ZERO);
+            assert!(db_batch.aggregation_state.is_none());
+            assert_eq!(db_batch.start_time.timestamp(), start_time.timestamp());
+
+            let db_order = db.get_order(&order1.id()).await.unwrap().unwrap();
+            assert_eq!(db_order.status, OrderStatus::PendingSubmission);
+            let db_order = db.get_order(&order2.id()).await.unwrap().unwrap();
+            assert_eq!(db_order.status, OrderStatus::PendingAgg);
+        }
+    }
 
-        let base_fees = U256::from(10);
-        let batch = Batch {
-            start_time: Utc::now(),
-            deadline: Some(100),
-            fees: base_fees,
-            ..Default::default()
-        };
+    db_test! {
+        async fn set_and_check_request_fulfilled(db) {
+            let request_id = U256::from(123);
+            let block_number = 42;
+
+            // Initially should not be fulfilled
+            assert!(!db.is_request_fulfilled(1, request_id).await.unwrap());
+
+            // Set as fulfilled
+            db.set_request_fulfilled(1, request_id, block_number).await.unwrap();
 
-        db.add_batch(batch_id, batch.clone()).await.unwrap();
-        db.update_batch(batch_id, &agg_state, &agg_proofs, Some("proof_id".to_string()))
-            .await
-            .unwrap();
-
-        let db_batch = db.get_batch(batch_id).await.unwrap();
-        assert_eq!(db_batch.status, BatchStatus::PendingCompression);
-        assert_eq!(db_batch.orders, vec![order1.id(), order2.id()]);
-        assert_eq!(db_batch.deadline, Some(20));
-        assert_eq!(db_batch.fees, U256::from(25));
-        assert!(db_batch.aggregation_state.is_some());
-        let agg_state = db_batch.aggregation_state.unwrap();
-        assert_eq!(agg_state.groth16_proof_id.as_ref(), None);
-        assert!(!agg_state.guest_state.is_initial());
-        assert_eq!(&agg_state.proof_id, "c");
-        assert_eq!(&agg_state.claim_digests, &claim_digests);
-    }
-
-    #[sqlx::test]
-    async fn set_and_check_request_fulfilled(pool: SqlitePool) {
-        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
-
-        let request_id = U256::from(123);
-        let block_number = 42;
-
-        // Initially should not be fulfilled
-        assert!(!db.is_request_fulfilled(request_id).await.unwrap());
-
-        // Set as fulfilled
-        db.set_request_fulfilled(request_id, block_number).await.unwrap();
-
-        // Should now be fulfilled
-        assert!(db.is_request_fulfilled(request_id).await.unwrap());
-
-        // Different request should still not be fulfilled
-        assert!(!db.is_request_fulfilled(U256::from(413)).await.unwrap());
-    }
-
-    #[sqlx::test]
-    async fn set_and_check_request_locked(pool: SqlitePool) {
-        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
-
-        let request_id = U256::from(123);
-        let locker = "test_locker";
-        let block_number = 42;
-        // Initially should not be locked
-        assert!(!db.is_request_locked(request_id).await.unwrap());
-
-        // Set as locked
-        db.set_request_locked(request_id, locker, block_number).await.unwrap();
-
-        // Should now be locked
-        assert!(db.is_request_locked(request_id).await.unwrap());
-
-        // Different request should still not be locked
-        assert!(!db.is_request_locked(U256::from(413)).await.unwrap());
-    }
-
-    #[sqlx::test]
-    async fn get_expired_committed_orders(pool: SqlitePool) {
-        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
-
-        let current_time = Utc::now().timestamp() as u64;
-        let past_time = current_time - 100;
-        let future_time = current_time + 100;
-
-        let mut orders = [
-            // Expired orders (should be returned)
-            Order {
-                status: OrderStatus::PendingProving,
-                expire_timestamp: Some(past_time),
-                ..create_order()
-            },
-            Order {
-                status: OrderStatus::Proving,
-                expire_timestamp: Some(past_time),
-                ..create_order()
-            },
-            Order {
-                status: OrderStatus::PendingAgg,
-                expire_timestamp: Some(past_time),
-                ..create_order()
-            },
-            Order {
-                status: OrderStatus::SkipAggregation,
-                expire_timestamp: Some(past_time),
-                ..create_order()
-            },
-            Order {
-                status: OrderStatus::PendingSubmission,
-                expire_timestamp: Some(past_time),
-                ..create_order()
-            },
-            // Non-expired orders (should NOT be returned)
-            Order {
-                status: OrderStatus::Aggregating,
-                expire_timestamp: Some(past_time),
-                ..create_order()
-            },
-            Order {
-                status: OrderStatus::PendingProving,
-                expire_timestamp: Some(future_time),
-                ..create_order()
-            },
-            Order {
-                status: OrderStatus::Proving,
-                expire_timestamp: Some(future_time),
-                ..create_order()
-            },
-            // Orders without expiration timestamp (should NOT be possible, but shouldn't error)
-            Order { status: OrderStatus::PendingProving, expire_timestamp: None, ..create_order() },
-            // Orders with non-committed status (should NOT be returned even if expired)
-            Order {
-                status: OrderStatus::Done,
-                expire_timestamp: Some(past_time),
-                ..create_order()
-            },
-            Order {
-                status: OrderStatus::Failed,
-                expire_timestamp: Some(past_time),
-                ..create_order()
-            },
-            Order {
-                status: OrderStatus::Skipped,
-                expire_timestamp: Some(past_time),
-                ..create_order()
-            },
-        ];
-
-        for (i, order) in orders.iter_mut().enumerate() {
-            order.request.id = U256::from(i);
-            db.add_order(order).await.unwrap();
-        }
-
-        let expired_orders = db.get_expired_committed_orders(0).await.unwrap();
-
-        assert_eq!(expired_orders.len(), 5);
-
-        for order in &expired_orders {
-            assert!(order.expire_timestamp.is_some());
-            assert!(order.expire_timestamp.unwrap() < current_time);
-            assert!(matches!(
-                order.status,
-                OrderStatus::PendingProving
-                    | OrderStatus::Proving
-                    | OrderStatus::PendingAgg
-                    | OrderStatus::SkipAggregation
-                    | OrderStatus::PendingSubmission
-            ));
-        }
-
-        let mut expected_ids: Vec<U256> = (0..5).map(|i| U256::from(i)).collect();
-        let mut returned_ids: Vec<U256> = expired_orders.iter().map(|o| o.request.id).collect();
-        returned_ids.sort();
-        expected_ids.sort();
-        assert_eq!(returned_ids, expected_ids);
-    }
-
-    #[sqlx::test]
-    #[traced_test]
-    async fn insert_duplicate_orders_conflict_handling(pool: SqlitePool) {
-        let db: DbObj = Arc::new(SqliteDb::from(pool).await.unwrap());
-
-        // Skipped request ignores duplicates
-        let order_request = create_order_request();
-        db.insert_skipped_request(&order_request).await.unwrap();
-
-        let stored_order = db.get_order(&order_request.id()).await.unwrap().unwrap();
-        assert_eq!(stored_order.status, OrderStatus::Skipped);
-
-        // Try to insert the same skipped request again - should be ignored
-        db.insert_skipped_request(&order_request).await.unwrap();
-        assert!(logs_contain("already exists"));
-
-        // Accepted request can overwrite skipped order
-        let accepted_order =
-            db.insert_accepted_request(&order_request, U256::from(100)).await.unwrap();
-        assert_eq!(accepted_order.status, OrderStatus::PendingProving);
-        assert_eq!(accepted_order.lock_price, Some(U256::from(100)));
-
-        let stored_order = db.get_order(&order_request.id()).await.unwrap().unwrap();
-        assert_eq!(stored_order.status, OrderStatus::PendingProving);
-        assert_eq!(stored_order.lock_price, Some(U256::from(100)));
-
-        // Accepted request errors on non-skipped duplicate
-        assert!(db.insert_accepted_request(&order_request, U256::from(200)).await.is_err());
-
-        // Verify the stored order still has the original lock price (wasn't updated)
-        let stored_order = db.get_order(&order_request.id()).await.unwrap().unwrap();
-        assert_eq!(
-            stored_order.lock_price,
-            Some(U256::from(100)),
-            "Lock price should not be updated"
-        );
-
-        // New order (different ID) should work normally
-        let mut different_request = create_order_request();
-        different_request.request.id = U256::from(999);
-
-        let new_order =
-            db.insert_accepted_request(&different_request, U256::from(300)).await.unwrap();
-        assert_eq!(new_order.status, OrderStatus::PendingProving);
-        assert_eq!(new_order.lock_price, Some(U256::from(300)));
+            // Should now be fulfilled
+            assert!(db.is_request_fulfilled(1, request_id).await.unwrap());
+
+            // Different request should still not be fulfilled
+            assert!(!db.is_request_fulfilled(1, U256::from(413)).await.unwrap());
+
+            // Same request on another chain should still not be fulfilled
+            assert!(!db.is_request_fulfilled(2, request_id).await.unwrap());
+            db.set_request_fulfilled(2, request_id, block_number).await.unwrap();
+            assert!(db.is_request_fulfilled(2, request_id).await.unwrap());
+        }
+    }
+
+    db_test! {
+        async fn set_and_check_request_locked(db) {
+            let request_id = U256::from(123);
+            let locker = "test_locker";
+            let block_number = 42;
+            // Initially should not be locked
+            assert!(!db.is_request_locked(1, request_id).await.unwrap());
+
+            // Set as locked
+            db.set_request_locked(1, request_id, locker, block_number).await.unwrap();
+
+            // Should now be locked
+            assert!(db.is_request_locked(1, request_id).await.unwrap());
+            assert_eq!(
+                db.get_request_locked(1, request_id).await.unwrap(),
+                Some((locker.to_string(), block_number))
+            );
+
+            // Different request should still not be locked
+            assert!(!db.is_request_locked(1, U256::from(413)).await.unwrap());
+
+            // The same request ID on another chain is tracked separately
+            assert!(!db.is_request_locked(2, request_id).await.unwrap());
+            assert_eq!(db.get_request_locked(2, request_id).await.unwrap(), None);
+            db.set_request_locked(2, request_id, "other_locker", 7).await.unwrap();
+            assert_eq!(
+                db.get_request_locked(2, request_id).await.unwrap(),
+                Some(("other_locker".to_string(), 7))
+            );
+            assert_eq!(
+                db.get_request_locked(1, request_id).await.unwrap(),
+                Some((locker.to_string(), block_number))
+            );
+        }
+    }
+
+    db_test! {
+        async fn get_expired_committed_orders(db) {
+            let current_time = Utc::now().timestamp() as u64;
+            let past_time = current_time - 100;
+            let future_time = current_time + 100;
+
+            let mut orders = [
+                // Expired orders (should be returned)
+                Order {
+                    status: OrderStatus::PendingProving,
+                    expire_timestamp: Some(past_time),
+                    ..create_order()
+                },
+                Order {
+                    status: OrderStatus::Proving,
+                    expire_timestamp: Some(past_time),
+                    ..create_order()
+                },
+                Order {
+                    status: OrderStatus::PendingAgg,
+                    expire_timestamp: Some(past_time),
+                    ..create_order()
+                },
+                Order {
+                    status: OrderStatus::SkipAggregation,
+                    expire_timestamp: Some(past_time),
+                    ..create_order()
+                },
+                Order {
+                    status: OrderStatus::PendingSubmission,
+                    expire_timestamp: Some(past_time),
+                    ..create_order()
+                },
+                // Non-expired orders (should NOT be returned)
+                Order {
+                    status: OrderStatus::Aggregating,
+                    expire_timestamp: Some(past_time),
+                    ..create_order()
+                },
+                Order {
+                    status: OrderStatus::PendingProving,
+                    expire_timestamp: Some(future_time),
+                    ..create_order()
+                },
+                Order {
+                    status: OrderStatus::Proving,
+                    expire_timestamp: Some(future_time),
+                    ..create_order()
+                },
+                // Orders without expiration timestamp (should NOT be possible, but shouldn't error)
+                Order { status: OrderStatus::PendingProving, expire_timestamp: None, ..create_order() },
+                // Orders with non-committed status (should NOT be returned even if expired)
+                Order {
+                    status: OrderStatus::Done,
+                    expire_timestamp: Some(past_time),
+                    ..create_order()
+                },
+                Order {
+                    status: OrderStatus::Failed,
+                    expire_timestamp: Some(past_time),
+                    ..create_order()
+                },
+                Order {
+                    status: OrderStatus::Skipped,
+                    expire_timestamp: Some(past_time),
+                    ..create_order()
+                },
+            ];
+
+            for (i, order) in orders.iter_mut().enumerate() {
+                order.request.id = U256::from(i);
+                db.add_order(order).await.unwrap();
+            }
+
+            let expired_orders = db.get_expired_committed_orders(0).await.unwrap();
+
+            assert_eq!(expired_orders.len(), 5);
+
+            for order in &expired_orders {
+                assert!(order.expire_timestamp.is_some());
+                assert!(order.expire_timestamp.unwrap() < current_time);
+                assert!(matches!(
+                    order.status,
+                    OrderStatus::PendingProving
+                        | OrderStatus::Proving
+                        | OrderStatus::PendingAgg
+                        | OrderStatus::SkipAggregation
+                        | OrderStatus::PendingSubmission
+                ));
+            }
+
+            let mut expected_ids: Vec<U256> = (0..5).map(|i| U256::from(i)).collect();
+            let mut returned_ids: Vec<U256> = expired_orders.iter().map(|o| o.request.id).collect();
+            returned_ids.sort();
+            expected_ids.sort();
+            assert_eq!(returned_ids, expected_ids);
+        }
+    }
+
+    db_test! {
+        #[traced_test]
+        async fn insert_duplicate_orders_conflict_handling(db) {
+            // Skipped request ignores duplicates
+            let order_request = create_order_request();
+            db.insert_skipped_request(&order_request, SkipReason::Expired).await.unwrap();
+
+            let stored_order = db.get_order(&order_request.id()).await.unwrap().unwrap();
+            assert_eq!(stored_order.status, OrderStatus::Skipped);
+
+            // Try to insert the same skipped request again - should be ignored
+            db.insert_skipped_request(&order_request, SkipReason::Expired).await.unwrap();
+            assert!(logs_contain("already exists"));
+
+            // Accepted request can overwrite skipped order
+            let accepted_order =
+                db.insert_accepted_request(&order_request, U256::from(100)).await.unwrap();
+            assert_eq!(accepted_order.status, OrderStatus::PendingProving);
+            assert_eq!(accepted_order.lock_price, Some(U256::from(100)));
+
+            let stored_order = db.get_order(&order_request.id()).await.unwrap().unwrap();
+            assert_eq!(stored_order.status, OrderStatus::PendingProving);
+            assert_eq!(stored_order.lock_price, Some(U256::from(100)));
+
+            // Accepted request errors on non-skipped duplicate
+            assert!(db.insert_accepted_request(&order_request, U256::from(200)).await.is_err());
+
+            // Verify the stored order still has the original lock price (wasn't updated)
+            let stored_order = db.get_order(&order_request.id()).await.unwrap().unwrap();
+            assert_eq!(
+                stored_order.lock_price,
+                Some(U256::from(100)),
+                "Lock price should not be updated"
+            );
+
+            // New order (different ID) should work normally
+            let mut different_request = create_order_request();
+            different_request.request.id = U256::from(999);
+
+            let new_order =
+                db.insert_accepted_request(&different_request, U256::from(300)).await.unwrap();
+            assert_eq!(new_order.status, OrderStatus::PendingProving);
+            assert_eq!(new_order.lock_price, Some(U256::from(300)));
+        }
+    }
+
+    db_test! {
+        async fn shadow_decisions(db) {
+            let order_request = create_order_request();
+            assert!(db.get_shadow_decisions(DateTime::UNIX_EPOCH, 10).await.unwrap().is_empty());
+
+            let priced = ShadowDecision::new(&order_request, ShadowAction::Lock)
+                .with_expected_profit(I256::try_from(100).unwrap());
+            let skipped = ShadowDecision::skip(&order_request, SkipReason::LockFailed);
+            db.add_shadow_decision(&priced).await.unwrap();
+            db.add_shadow_decision(&skipped).await.unwrap();
+
+            let decisions = db.get_shadow_decisions(DateTime::UNIX_EPOCH, 10).await.unwrap();
+            assert_eq!(decisions.len(), 2);
+            assert_eq!(decisions[0].action, ShadowAction::Lock);
+            assert_eq!(decisions[0].expected_profit, priced.expected_profit);
+            assert_eq!(decisions[1].action, ShadowAction::Skip);
+            assert_eq!(decisions[1].reason, Some(SkipReason::LockFailed));
+
+            // Only the most recent decisions are returned, oldest first
+            let decisions = db.get_shadow_decisions(DateTime::UNIX_EPOCH, 1).await.unwrap();
+            assert_eq!(decisions.len(), 1);
+            assert_eq!(decisions[0].action, ShadowAction::Skip);
+
+            // Decisions recorded before the start of the window are not returned
+            let from = skipped.created_at + chrono::Duration::seconds(1);
+            assert!(db.get_shadow_decisions(from, 10).await.unwrap().is_empty());
+        }
+    }
+
+    db_test! {
+        async fn proving_samples(db) {
+            assert!(db.get_proving_samples(2).await.unwrap().is_empty());
+
+            for (idx, total_cycles) in [1_000_000, 2_000_000, 3_000_000].into_iter().enumerate() {
+                let sample = ProvingSample {
+                    order_id: format!("order-{idx}"),
+                    image_id: "image".into(),
+                    backend: None,
+                    total_cycles,
+                    elapsed_secs: 1.0,
+                    created_at: Utc::now(),
+                };
+                db.add_proving_sample(&sample).await.unwrap();
+            }
+
+            // Only the most recent samples are returned, oldest first
+            let samples = db.get_proving_samples(2).await.unwrap();
+            assert_eq!(samples.len(), 2);
+            assert_eq!(samples[0].order_id, "order-1");
+            assert_eq!(samples[1].order_id, "order-2");
+        }
+    }
+
+    db_test! {
+        async fn lock_bids(db) {
+            assert!(db.get_lock_bids(2).await.unwrap().is_empty());
+
+            let outcomes = [LockBidOutcome::Outbid, LockBidOutcome::Locked, LockBidOutcome::Failed];
+            for (idx, outcome) in outcomes.into_iter().enumerate() {
+                let bid = LockBid {
+                    order_id: format!("order-{idx}"),
+                    request_id: U256::from(idx),
+                    chain_id: 1,
+                    priority_fee: 100,
+                    suggested_priority_fee: 10,
+                    competing_priority_fee: Some(90),
+                    max_priority_fee: Some(1_000),
+                    base_fee: Some(1_000_000_000),
+                    expected_profit: Some(I256::try_from(1_000_000).unwrap()),
+                    outcome: Some(outcome),
+                    created_at: Utc::now(),
+                };
+                db.add_lock_bid(&bid).await.unwrap();
+            }
+
+            // Only the most recent bids are returned, oldest first
+            let bids = db.get_lock_bids(2).await.unwrap();
+            assert_eq!(bids.len(), 2);
+            assert_eq!(bids[0].order_id, "order-1");
+            assert_eq!(bids[0].outcome, Some(LockBidOutcome::Locked));
+            assert_eq!(bids[1].order_id, "order-2");
+            assert_eq!(bids[1].competing_priority_fee, Some(90));
+        }
+    }
+
+    db_test! {
+        async fn ledger(db) {
+            let order = create_order();
+            let entry = |kind, amount: u64| {
+                let amount = U256::from(amount);
+                LedgerEntry::new(order.id(), order.chain_id, &order.request, kind, amount)
+            };
+
+            let mut lock_gas = entry(LedgerKind::LockGas, 1);
+            lock_gas.created_at -= chrono::Duration::days(2);
+            db.add_ledger_entries(&[lock_gas, entry(LedgerKind::StakeLocked, 10)]).await.unwrap();
+            let open_stakes = db.get_open_stakes().await.unwrap();
+            assert_eq!(open_stakes.len(), 1);
+            assert_eq!(open_stakes[0].kind, LedgerKind::StakeLocked);
+            assert_eq!(open_stakes[0].amount, U256::from(10));
+
+            db.add_ledger_entries(&[entry(LedgerKind::StakeReturned, 10)]).await.unwrap();
+            assert!(db.get_open_stakes().await.unwrap().is_empty());
+
+            // Entries recorded outside of the window are not returned
+            let from = Utc::now() - chrono::Duration::days(1);
+            let to = Utc::now() + chrono::Duration::seconds(1);
+            let entries = db.get_ledger_entries(None, from, to).await.unwrap();
+            let kinds: Vec<_> = entries.iter().map(|entry| entry.kind).collect();
+            assert_eq!(kinds, vec![LedgerKind::StakeLocked, LedgerKind::StakeReturned]);
+            assert_eq!(entries[0].requestor, order.request.client_address());
+            assert_eq!(entries[0].chain_id, order.chain_id);
+
+            // Entries of other chains are filtered out
+            let other_chain =
+                LedgerEntry { chain_id: order.chain_id + 1, ..entry(LedgerKind::Payment, 5) };
+            db.add_ledger_entries(&[other_chain]).await.unwrap();
+            assert_eq!(db.get_ledger_entries(None, from, to).await.unwrap().len(), 3);
+            let entries = db.get_ledger_entries(Some(order.chain_id), from, to).await.unwrap();
+            assert_eq!(entries.len(), 2);
+            assert!(entries.iter().all(|entry| entry.chain_id == order.chain_id));
+        }
+    }
+
+    db_test! {
+        async fn treasury_actions(db) {
+            let action = |chain_id, kind| TreasuryAction {
+                chain_id,
+                kind,
+                amount: U256::from(10),
+                to: None,
+                error: None,
+                tx_hash: None,
+                shadow: false,
+                created_at: Utc::now(),
+            };
+
+            let mut old = action(1, TreasuryActionKind::Sweep);
+            old.created_at -= chrono::Duration::days(2);
+            db.add_treasury_action(&old).await.unwrap();
+            let deposit =
+                db.add_treasury_action(&action(1, TreasuryActionKind::StakeDeposit)).await.unwrap();
+            db.add_treasury_action(&action(1, TreasuryActionKind::GasTopUp)).await.unwrap();
+            db.add_treasury_action(&action(2, TreasuryActionKind::GasTopUp)).await.unwrap();
+            db.set_treasury_action_error(deposit, "failed").await.unwrap();
+
+            let from = Utc::now() - chrono::Duration::days(1);
+            let actions = db.get_treasury_actions(1, from).await.unwrap();
+            let kinds: Vec<_> = actions.iter().map(|action| action.kind).collect();
+            assert_eq!(
+                kinds,
+                vec![TreasuryActionKind::StakeDeposit, TreasuryActionKind::GasTopUp]
+            );
+            assert_eq!(actions[0].error.as_deref(), Some("failed"));
+            assert_eq!(actions[0].amount, U256::from(10));
+            assert_eq!(actions[1].error, None);
+
+            // Actions are pending from when their transaction is broadcast until settled
+            let sweep = action(1, TreasuryActionKind::Sweep);
+            let sweep = db.add_treasury_action(&sweep).await.unwrap();
+            let withdraw = TreasuryAction {
+                tx_hash: Some(B256::repeat_byte(2)),
+                ..action(1, TreasuryActionKind::SweepWithdraw)
+            };
+            let withdraw = db.add_treasury_action(&withdraw).await.unwrap();
+            db.set_treasury_action_tx_hash(sweep, B256::repeat_byte(1)).await.unwrap();
+            let pending = db.get_pending_treasury_actions(1).await.unwrap();
+            assert_eq!(pending.len(), 2);
+            assert_eq!(pending[0].0, sweep);
+            assert_eq!(pending[0].1.tx_hash, Some(B256::repeat_byte(1)));
+            assert_eq!(pending[1].0, withdraw);
+
+            db.confirm_treasury_action(sweep).await.unwrap();
+            db.set_treasury_action_error(withdraw, "reverted").await.unwrap();
+            assert!(db.get_pending_treasury_actions(1).await.unwrap().is_empty());
+        }
+    }
+
+    db_test! {
+        async fn preflight_cache(db) {
+            assert!(db.get_preflight_cache("image", "input").await.unwrap().is_none());
+
+            let mut entry = PreflightCacheEntry {
+                image_id: "image".into(),
+                input_digest: "input".into(),
+                outcome: PreflightOutcome::SessionLimit { exec_limit_cycles: 1_000 },
+                created_at: Utc::now(),
+            };
+            db.set_preflight_cache(&entry).await.unwrap();
+            assert_eq!(
+                db.get_preflight_cache("image", "input").await.unwrap().unwrap().outcome,
+                entry.outcome
+            );
+            assert!(db.get_preflight_cache("image", "other").await.unwrap().is_none());
+
+            // Setting the cache of the same image and input replaces the entry
+            entry.outcome = PreflightOutcome::Completed {
+                segments: 2,
+                user_cycles: 1_500,
+                total_cycles: 2_048,
+                journal: Bytes::from(vec![1, 2, 3]),
+            };
+            db.set_preflight_cache(&entry).await.unwrap();
+            assert_eq!(
+                db.get_preflight_cache("image", "input").await.unwrap().unwrap().outcome,
+                entry.outcome
+            );
+        }
+    }
+
+    db_test! {
+        async fn prune_preflight_cache(db) {
+            let now = Utc::now();
+            for (input_digest, age_secs) in [("expired", 86_400), ("old", 60), ("new", 0)] {
+                let entry = PreflightCacheEntry {
+                    image_id: "image".into(),
+                    input_digest: input_digest.into(),
+                    outcome: PreflightOutcome::SessionLimit { exec_limit_cycles: 1_000 },
+                    created_at: now - chrono::Duration::seconds(age_secs),
+                };
+                db.set_preflight_cache(&entry).await.unwrap();
+            }
+
+            let created_before = now - chrono::Duration::seconds(3_600);
+            assert_eq!(db.prune_preflight_cache(created_before, 10).await.unwrap(), 1);
+            assert!(db.get_preflight_cache("image", "expired").await.unwrap().is_none());
+            assert!(db.get_preflight_cache("image", "old").await.unwrap().is_some());
+
+            // Beyond the max number of entries, the oldest are deleted
+            assert_eq!(db.prune_preflight_cache(created_before, 1).await.unwrap(), 1);
+            assert!(db.get_preflight_cache("image", "old").await.unwrap().is_none());
+            assert!(db.get_preflight_cache("image", "new").await.unwrap().is_some());
+        }
+    }
+
+    db_test! {
+        async fn prune_history(db) {
+            let now = Utc::now();
+            let order_request = create_order_request();
+            for age_secs in [86_400, 60, 0] {
+                let created_at = now - chrono::Duration::seconds(age_secs);
+                let decision = ShadowDecision {
+                    created_at,
+                    ..ShadowDecision::new(&order_request, ShadowAction::Lock)
+                };
+                db.add_shadow_decision(&decision).await.unwrap();
+                let sample = ProvingSample {
+                    order_id: format!("order-{age_secs}"),
+                    image_id: "image".into(),
+                    backend: None,
+                    total_cycles: 1_000_000,
+                    elapsed_secs: 1.0,
+                    created_at,
+                };
+                db.add_proving_sample(&sample).await.unwrap();
+                let bid = LockBid {
+                    order_id: format!("order-{age_secs}"),
+                    request_id: U256::ZERO,
+                    chain_id: 1,
+                    priority_fee: 100,
+                    suggested_priority_fee: 10,
+                    competing_priority_fee: None,
+                    max_priority_fee: None,
+                    base_fee: None,
+                    expected_profit: None,
+                    outcome: Some(LockBidOutcome::Locked),
+                    created_at,
+                };
+                db.add_lock_bid(&bid).await.unwrap();
+            }
+
+            let created_before = now - chrono::Duration::seconds(3_600);
+            assert_eq!(db.prune_history(created_before, 10).await.unwrap(), 3);
+            let from = DateTime::UNIX_EPOCH;
+            assert_eq!(db.get_shadow_decisions(from, 10).await.unwrap().len(), 2);
+            let samples = db.get_proving_samples(10).await.unwrap();
+            assert_eq!(samples[0].order_id, "order-60");
+            assert_eq!(samples.len(), 2);
+
+            // Beyond the max number of entries, the oldest are deleted
+            assert_eq!(db.prune_history(created_before, 1).await.unwrap(), 3);
+            assert_eq!(db.get_shadow_decisions(from, 10).await.unwrap().len(), 1);
+            assert_eq!(db.get_proving_samples(10).await.unwrap()[0].order_id, "order-0");
+            let bids = db.get_lock_bids(10).await.unwrap();
+            assert_eq!(bids.len(), 1);
+            assert_eq!(bids[0].order_id, "order-0");
+        }
     }
 }
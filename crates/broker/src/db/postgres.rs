@@ -0,0 +1,1272 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{collections::BTreeMap, str::FromStr};
+
+use alloy::primitives::{Bytes, B256, U256};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{
+    postgres::{PgExecutor, PgPool, PgPoolOptions},
+    types::Json,
+    Row,
+};
+use tracing::instrument;
+
+use crate::{
+    ledger::{LedgerEntry, LedgerKind},
+    lock_bidding::LockBid,
+    preflight_cache::PreflightCacheEntry,
+    shadow::ShadowDecision,
+    throughput::ProvingSample,
+    treasury::TreasuryAction,
+    AggregationState, Batch, BatchStatus, FulfillmentType, Order, OrderRequest, OrderStatus,
+    ProofRequest, SkipReason,
+};
+
+use super::{AggregationOrder, BrokerDb, DbBatch, DbError, DbOrder};
+
+/// Env var used to override the size of the connection pool
+const MAX_CONNECTIONS_ENV: &str = "DB_MAX_CONNECTIONS";
+const DEFAULT_MAX_CONNECTIONS: u32 = 5;
+
+/// Advisory lock key held while selecting or creating the current batch, so that brokers sharing
+/// the database do not open more than one aggregating batch.
+const CURRENT_BATCH_LOCK_KEY: i64 = 0x6272_6f6b_6572;
+
+/// [BrokerDb] backed by a PostgreSQL database, allowing several brokers to share durable state.
+pub struct PostgresDb {
+    pool: PgPool,
+}
+
+impl PostgresDb {
+    pub async fn new(conn_str: &str) -> Result<Self, DbError> {
+        let max_connections = match std::env::var(MAX_CONNECTIONS_ENV) {
+            Ok(value) => value.parse()?,
+            Err(_) => DEFAULT_MAX_CONNECTIONS,
+        };
+
+        let pool = PgPoolOptions::new().max_connections(max_connections).connect(conn_str).await?;
+
+        sqlx::migrate!("./migrations/postgres").run(&pool).await?;
+
+        Ok(Self { pool })
+    }
+
+    #[cfg(test)]
+    pub async fn from(pool: PgPool) -> Result<Self, DbError> {
+        Ok(Self { pool })
+    }
+
+    async fn new_batch<'c>(executor: impl PgExecutor<'c>, chain_id: u64) -> Result<usize, DbError> {
+        let batch = Batch { start_time: Utc::now(), chain_id, ..Default::default() };
+
+        let res: i64 = sqlx::query_scalar("INSERT INTO batches (data) VALUES ($1) RETURNING id")
+            .bind(Json(&batch))
+            .fetch_one(executor)
+            .await?;
+
+        Ok(res as usize)
+    }
+
+    /// Insert an order into the database using ON CONFLICT to handle duplicates safely.
+    /// Always ignores duplicates - used for skipped requests.
+    async fn insert_order_ignore_duplicates(&self, order: &Order) -> Result<(), DbError> {
+        let result =
+            sqlx::query("INSERT INTO orders (id, data) VALUES ($1, $2) ON CONFLICT(id) DO NOTHING")
+                .bind(order.id())
+                .bind(Json(&order))
+                .execute(&self.pool)
+                .await?;
+
+        if result.rows_affected() == 0 {
+            tracing::debug!("Order {} already exists in the database", order.id());
+        }
+
+        Ok(())
+    }
+
+    /// Insert an accepted order, overwriting only if the existing order is skipped.
+    async fn insert_accepted_order(&self, order: &Order) -> Result<(), DbError> {
+        let result = sqlx::query(
+            r#"INSERT INTO orders (id, data) VALUES ($1, $2)
+               ON CONFLICT(id) DO UPDATE SET
+                   data = excluded.data
+               WHERE orders.data->>'status' = 'Skipped'"#,
+        )
+        .bind(order.id())
+        .bind(Json(&order))
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(DbError::DuplicateOrderId(order.id()));
+        }
+
+        Ok(())
+    }
+
+    /// Set the status and updated_at fields of an order
+    async fn set_order_status(&self, id: &str, status: OrderStatus) -> Result<(), DbError> {
+        let res = sqlx::query(
+            r#"
+            UPDATE orders
+            SET data = data || jsonb_build_object(
+                       'status', $1::jsonb,
+                       'updated_at', $2::bigint)
+            WHERE
+                id = $3"#,
+        )
+        .bind(Json(status))
+        .bind(Utc::now().timestamp())
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        if res.rows_affected() == 0 {
+            return Err(DbError::OrderNotFound(id.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Move all orders in one of the `from` statuses to `to`, returning them as aggregation orders
+    async fn take_aggregation_orders(
+        &self,
+        chain_id: u64,
+        from: &[OrderStatus],
+        to: OrderStatus,
+    ) -> Result<Vec<AggregationOrder>, DbError> {
+        let from: Vec<_> = from.iter().map(|status| Json(*status)).collect();
+        let orders: Vec<DbOrder> = sqlx::query_as(
+            r#"
+            UPDATE orders
+            SET data = data || jsonb_build_object(
+                       'status', $1::jsonb,
+                       'updated_at', $2::bigint)
+            WHERE
+                data->'status' = ANY($3)
+                AND data->'chain_id' = $4
+            RETURNING *
+            "#,
+        )
+        .bind(Json(to))
+        .bind(Utc::now().timestamp())
+        .bind(from)
+        .bind(Json(chain_id))
+        .fetch_all(&self.pool)
+        .await?;
+
+        orders
+            .into_iter()
+            .map(|order| {
+                Ok(AggregationOrder {
+                    proof_id: order
+                        .data
+                        .proof_id
+                        .ok_or(DbError::InvalidOrder(order.id.clone(), "proof_id"))?,
+                    expiration: order
+                        .data
+                        .expire_timestamp
+                        .ok_or(DbError::InvalidOrder(order.id.clone(), "expire_timestamp"))?,
+                    fee: order
+                        .data
+                        .lock_price
+                        .ok_or(DbError::InvalidOrder(order.id.clone(), "lock_price"))?,
+                    order_id: order.id,
+                })
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl BrokerDb for PostgresDb {
+    #[cfg(test)]
+    #[instrument(level = "trace", skip_all, fields(id = %format!("{}", order.id())))]
+    async fn add_order(&self, order: &Order) -> Result<(), DbError> {
+        self.insert_order_ignore_duplicates(order).await
+    }
+
+    #[instrument(level = "trace", skip_all, fields(id = %format!("{}", order_request.id())))]
+    async fn insert_skipped_request(
+        &self,
+        order_request: &OrderRequest,
+        reason: SkipReason,
+    ) -> Result<(), DbError> {
+        self.insert_order_ignore_duplicates(&order_request.to_skipped_order(reason)).await
+    }
+
+    #[instrument(level = "trace", skip_all, fields(id = %format!("{}", order_request.id())))]
+    async fn insert_accepted_request(
+        &self,
+        order_request: &OrderRequest,
+        lock_price: U256,
+    ) -> Result<Order, DbError> {
+        let order = order_request.to_proving_order(lock_price);
+        self.insert_accepted_order(&order).await?;
+        Ok(order)
+    }
+
+    #[instrument(level = "trace", skip_all, fields(id = %format!("{id}")))]
+    async fn get_order(&self, id: &str) -> Result<Option<Order>, DbError> {
+        let order: Option<DbOrder> = sqlx::query_as("SELECT * FROM orders WHERE id = $1 LIMIT 1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(order.map(|x| x.data))
+    }
+
+    async fn get_orders(&self, ids: &[&str]) -> Result<Vec<Order>, DbError> {
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+        let orders: Vec<DbOrder> = sqlx::query_as("SELECT * FROM orders WHERE id = ANY($1)")
+            .bind(ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(orders.into_iter().map(|x| x.data).collect())
+    }
+
+    #[instrument(level = "trace", skip_all, fields(id = %format!("{id}")))]
+    async fn get_submission_order(
+        &self,
+        id: &str,
+    ) -> Result<(ProofRequest, Bytes, String, B256, U256, FulfillmentType), DbError> {
+        let order = self.get_order(id).await?;
+        if let Some(order) = order {
+            Ok((
+                order.request.clone(),
+                order.client_sig.clone(),
+                order.proof_id.ok_or(DbError::MissingElm("proof_id"))?,
+                order.request.requirements.imageId,
+                order.lock_price.ok_or(DbError::MissingElm("lock_price"))?,
+                order.fulfillment_type,
+            ))
+        } else {
+            Err(DbError::OrderNotFound(id.to_string()))
+        }
+    }
+
+    #[instrument(level = "trace", skip_all, fields(id = %format!("{id}")))]
+    async fn get_order_compressed_proof_id(&self, id: &str) -> Result<String, DbError> {
+        let order = self.get_order(id).await?;
+        if let Some(order) = order {
+            Ok(order.compressed_proof_id.ok_or(DbError::MissingElm("compressed_proof_id"))?)
+        } else {
+            Err(DbError::OrderNotFound(id.to_string()))
+        }
+    }
+
+    #[instrument(level = "trace", skip_all, fields(id = %format!("{id}")))]
+    async fn set_order_failure(&self, id: &str, failure_str: &'static str) -> Result<(), DbError> {
+        let res = sqlx::query(
+            r#"
+            UPDATE orders
+            SET data = data || jsonb_build_object(
+                       'status', $1::jsonb,
+                       'updated_at', $2::bigint,
+                       'error_msg', $3::text)
+            WHERE
+                id = $4"#,
+        )
+        .bind(Json(OrderStatus::Failed))
+        .bind(Utc::now().timestamp())
+        .bind(failure_str)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        if res.rows_affected() == 0 {
+            return Err(DbError::OrderNotFound(id.to_string()));
+        }
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip_all, fields(id = %format!("{id}")))]
+    async fn set_order_complete(&self, id: &str) -> Result<(), DbError> {
+        self.set_order_status(id, OrderStatus::Done).await
+    }
+
+    #[instrument(level = "trace", skip_all)]
+    async fn get_committed_orders(&self) -> Result<Vec<Order>, DbError> {
+        let orders: Vec<DbOrder> =
+            sqlx::query_as("SELECT * FROM orders WHERE data->'status' IN ($1, $2, $3, $4, $5, $6)")
+                .bind(Json(OrderStatus::PendingProving))
+                .bind(Json(OrderStatus::Proving))
+                .bind(Json(OrderStatus::PendingAgg))
+                .bind(Json(OrderStatus::Aggregating))
+                .bind(Json(OrderStatus::SkipAggregation))
+                .bind(Json(OrderStatus::PendingSubmission))
+                .fetch_all(&self.pool)
+                .await?;
+
+        Ok(orders.into_iter().map(|elm| elm.data).collect())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_expired_committed_orders(
+        &self,
+        grace_period_secs: i64,
+    ) -> Result<Vec<Order>, DbError> {
+        let orders: Vec<DbOrder> = sqlx::query_as(
+            r#"
+            SELECT * FROM orders
+                WHERE data->'status' IN ($1, $2, $3, $4, $5)
+                AND data->>'expire_timestamp' IS NOT NULL
+                AND (data->>'expire_timestamp')::bigint < $6"#,
+        )
+        .bind(Json(OrderStatus::PendingProving))
+        .bind(Json(OrderStatus::Proving))
+        .bind(Json(OrderStatus::PendingAgg))
+        .bind(Json(OrderStatus::SkipAggregation))
+        .bind(Json(OrderStatus::PendingSubmission))
+        .bind(Utc::now().timestamp().saturating_sub(grace_period_secs))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(orders.into_iter().map(|db_order| db_order.data).collect())
+    }
+
+    #[instrument(level = "trace", skip_all)]
+    async fn get_proving_order(&self) -> Result<Option<Order>, DbError> {
+        // SKIP LOCKED lets brokers sharing the database each claim a different order
+        let elm: Option<DbOrder> = sqlx::query_as(
+            r#"
+            UPDATE orders
+            SET data = data || jsonb_build_object(
+                       'status', $1::jsonb,
+                       'updated_at', $2::bigint)
+            WHERE id =
+                (SELECT id
+                FROM orders
+                WHERE data->'status' = $3
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED)
+            RETURNING *
+            "#,
+        )
+        .bind(Json(OrderStatus::Proving))
+        .bind(Utc::now().timestamp())
+        .bind(Json(OrderStatus::PendingProving))
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(elm.map(|order| order.data))
+    }
+
+    #[instrument(level = "trace", skip_all)]
+    async fn get_active_proofs(&self) -> Result<Vec<Order>, DbError> {
+        let orders: Vec<DbOrder> = sqlx::query_as("SELECT * FROM orders WHERE data->'status' = $1")
+            .bind(Json(OrderStatus::Proving))
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(orders.into_iter().map(|elm| elm.data).collect())
+    }
+
+    #[instrument(level = "trace", skip_all, fields(id = %format!("{id}")))]
+    async fn set_order_proof_id(&self, id: &str, proof_id: &str) -> Result<(), DbError> {
+        let res = sqlx::query(
+            r#"
+            UPDATE orders
+            SET data = data || jsonb_build_object(
+                       'proof_id', $1::text,
+                       'updated_at', $2::bigint)
+            WHERE
+                id = $3"#,
+        )
+        .bind(proof_id)
+        .bind(Utc::now().timestamp())
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        if res.rows_affected() == 0 {
+            return Err(DbError::OrderNotFound(id.to_string()));
+        }
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip_all, fields(id = %format!("{id}")))]
+    async fn set_order_compressed_proof_id(
+        &self,
+        id: &str,
+        compressed_proof_id: &str,
+    ) -> Result<(), DbError> {
+        let res = sqlx::query(
+            r#"
+            UPDATE orders
+            SET data = data || jsonb_build_object(
+                       'compressed_proof_id', $1::text,
+                       'updated_at', $2::bigint)
+            WHERE
+                id = $3"#,
+        )
+        .bind(compressed_proof_id)
+        .bind(Utc::now().timestamp())
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        if res.rows_affected() == 0 {
+            return Err(DbError::OrderNotFound(id.to_string()));
+        }
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip_all, fields(id = %format!("{id}")))]
+    async fn set_aggregation_status(&self, id: &str, status: OrderStatus) -> Result<(), DbError> {
+        self.set_order_status(id, status).await
+    }
+
+    #[instrument(level = "trace", skip_all)]
+    async fn get_aggregation_proofs(
+        &self,
+        chain_id: u64,
+    ) -> Result<Vec<AggregationOrder>, DbError> {
+        self.take_aggregation_orders(
+            chain_id,
+            &[OrderStatus::PendingAgg, OrderStatus::Aggregating],
+            OrderStatus::Aggregating,
+        )
+        .await
+    }
+
+    #[instrument(level = "trace", skip_all)]
+    async fn get_groth16_proofs(&self, chain_id: u64) -> Result<Vec<AggregationOrder>, DbError> {
+        self.take_aggregation_orders(
+            chain_id,
+            &[OrderStatus::SkipAggregation],
+            OrderStatus::SkipAggregation,
+        )
+        .await
+    }
+
+    #[instrument(level = "trace", skip_all)]
+    async fn complete_batch(&self, batch_id: usize, g16_proof_id: &str) -> Result<(), DbError> {
+        let batch = self.get_batch(batch_id).await?;
+        if batch.aggregation_state.is_none() {
+            return Err(DbError::BatchAggregationStateIsNone(batch_id));
+        }
+
+        let res = sqlx::query(
+            r#"
+            UPDATE batches
+            SET data = jsonb_set(
+                       jsonb_set(data,
+                       '{status}', $1),
+                       '{aggregation_state,groth16_proof_id}', to_jsonb($2::text))
+            WHERE
+                id = $3"#,
+        )
+        .bind(Json(BatchStatus::Complete))
+        .bind(g16_proof_id)
+        .bind(batch_id as i64)
+        .execute(&self.pool)
+        .await?;
+
+        if res.rows_affected() == 0 {
+            return Err(DbError::BatchNotFound(batch_id));
+        }
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip_all)]
+    async fn get_complete_batch(&self, chain_id: u64) -> Result<Option<(usize, Batch)>, DbError> {
+        let elm: Option<DbBatch> = sqlx::query_as(
+            r#"
+            UPDATE batches
+            SET
+                data = jsonb_set(data, '{status}', $1)
+            WHERE id =
+                (SELECT id
+                FROM batches
+                WHERE data->'status' = $2
+                    AND data->'chain_id' = $3
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED)
+            RETURNING *
+            "#,
+        )
+        .bind(Json(BatchStatus::PendingSubmission))
+        .bind(Json(BatchStatus::Complete))
+        .bind(Json(chain_id))
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(elm.map(|db_batch| (db_batch.id as usize, db_batch.data)))
+    }
+
+    #[instrument(level = "trace", skip_all)]
+    async fn set_batch_submitted(&self, batch_id: usize) -> Result<(), DbError> {
+        let res = sqlx::query(
+            r#"
+            UPDATE batches
+            SET
+                data = jsonb_set(data, '{status}', $1)
+            WHERE
+                id = $2"#,
+        )
+        .bind(Json(BatchStatus::Submitted))
+        .bind(batch_id as i64)
+        .execute(&self.pool)
+        .await?;
+
+        if res.rows_affected() == 0 {
+            return Err(DbError::BatchNotFound(batch_id));
+        }
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip_all)]
+    async fn set_batch_failure(&self, batch_id: usize, err: String) -> Result<(), DbError> {
+        let res = sqlx::query(
+            r#"
+            UPDATE batches
+            SET
+                data = data || jsonb_build_object(
+                       'status', $1::jsonb,
+                       'error_msg', $2::text)
+            WHERE
+                id = $3"#,
+        )
+        .bind(Json(BatchStatus::Failed))
+        .bind(err)
+        .bind(batch_id as i64)
+        .execute(&self.pool)
+        .await?;
+
+        if res.rows_affected() == 0 {
+            return Err(DbError::BatchNotFound(batch_id));
+        }
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip_all)]
+    async fn get_current_batch(&self, chain_id: u64) -> Result<usize, DbError> {
+        let mut txn = self.pool.begin().await?;
+
+        sqlx::query("SELECT pg_advisory_xact_lock($1)")
+            .bind(CURRENT_BATCH_LOCK_KEY)
+            .execute(&mut *txn)
+            .await?;
+
+        let cur_batch: Option<i64> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM batches
+            WHERE data->'status' IN ($1, $2)
+                AND data->'chain_id' = $3
+            ORDER BY id LIMIT 1"#,
+        )
+        .bind(Json(BatchStatus::Aggregating))
+        .bind(Json(BatchStatus::PendingCompression))
+        .bind(Json(chain_id))
+        .fetch_optional(&mut *txn)
+        .await?;
+
+        let batch_id = match cur_batch {
+            Some(batch_id) => batch_id as usize,
+            None => Self::new_batch(&mut *txn, chain_id).await?,
+        };
+
+        txn.commit().await?;
+
+        Ok(batch_id)
+    }
+
+    #[instrument(level = "trace", skip(self, aggreagtion_state, orders, assessor_proof_id))]
+    async fn update_batch(
+        &self,
+        batch_id: usize,
+        aggreagtion_state: &AggregationState,
+        orders: &[AggregationOrder],
+        assessor_proof_id: Option<String>,
+    ) -> Result<(), DbError> {
+        let mut txn = self.pool.begin().await?;
+
+        let rows = sqlx::query(
+            r#"
+            SELECT data->>'fees' AS fees, (data->>'deadline')::bigint AS deadline
+            FROM batches WHERE id = $1
+            FOR UPDATE"#,
+        )
+        .bind(batch_id as i64)
+        .fetch_optional(&mut *txn)
+        .await?;
+
+        let Some(rows) = rows else {
+            return Err(DbError::BatchNotFound(batch_id));
+        };
+
+        let db_fees: String = rows.try_get("fees")?;
+        let db_deadline: Option<i64> = rows.try_get("deadline")?;
+
+        let new_deadline = orders
+            .iter()
+            .fold(db_deadline, |min, order| {
+                Some(i64::min(min.unwrap_or(i64::MAX), order.expiration as i64))
+            })
+            .unwrap_or(i64::MAX);
+
+        let db_fees = U256::from_str(&db_fees)?;
+        let new_fees = orders.iter().fold(db_fees, |sum, order| sum + order.fee);
+
+        // Update the batch fees, deadline, and aggregation state.
+        let res = sqlx::query(
+            r#"
+            UPDATE batches
+            SET
+                data = data || jsonb_build_object(
+                       'deadline', $1::bigint,
+                       'fees', $2::text,
+                       'aggregation_state', $3::jsonb)
+            WHERE
+                id = $4"#,
+        )
+        .bind(new_deadline)
+        .bind(format!("0x{new_fees:x}"))
+        .bind(Json(aggreagtion_state))
+        .bind(batch_id as i64)
+        .execute(&mut *txn)
+        .await?;
+
+        if res.rows_affected() == 0 {
+            return Err(DbError::BatchNotFound(batch_id));
+        }
+
+        // Insert all the new orders.
+        for order in orders {
+            let res = sqlx::query(
+                r#"
+                UPDATE batches
+                SET
+                    data = jsonb_set(data, '{orders}', (data->'orders') || to_jsonb($1::text))
+                WHERE
+                    id = $2"#,
+            )
+            .bind(order.order_id.clone())
+            .bind(batch_id as i64)
+            .execute(&mut *txn)
+            .await?;
+
+            if res.rows_affected() == 0 {
+                return Err(DbError::BatchNotFound(batch_id));
+            }
+
+            let res = sqlx::query(
+                r#"
+                UPDATE orders
+                SET data = data || jsonb_build_object(
+                           'status', $1::jsonb,
+                           'updated_at', $2::bigint)
+                WHERE
+                    id = $3"#,
+            )
+            .bind(Json(OrderStatus::PendingSubmission))
+            .bind(Utc::now().timestamp())
+            .bind(order.order_id.clone())
+            .execute(&mut *txn)
+            .await?;
+
+            if res.rows_affected() == 0 {
+                return Err(DbError::OrderNotFound(order.order_id.clone()));
+            }
+        }
+
+        if let Some(assessor_proof_id) = assessor_proof_id {
+            let res = sqlx::query(
+                r#"
+                UPDATE batches
+                SET
+                    data = data || jsonb_build_object(
+                           'status', $1::jsonb,
+                           'assessor_proof_id', $2::text)
+                WHERE
+                    id = $3"#,
+            )
+            .bind(Json(BatchStatus::PendingCompression))
+            .bind(assessor_proof_id)
+            .bind(batch_id as i64)
+            .execute(&mut *txn)
+            .await?;
+
+            if res.rows_affected() == 0 {
+                return Err(DbError::BatchNotFound(batch_id));
+            }
+        }
+
+        txn.commit().await?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip(self, requeued_orders))]
+    async fn reset_batch(
+        &self,
+        batch_id: usize,
+        requeued_orders: &[(String, OrderStatus)],
+    ) -> Result<(), DbError> {
+        let mut txn = self.pool.begin().await?;
+
+        let res = sqlx::query(
+            r#"
+            UPDATE batches
+            SET
+                data = (data - 'deadline' - 'aggregation_state' - 'assessor_proof_id')
+                       || jsonb_build_object(
+                       'orders', '[]'::jsonb,
+                       'fees', $1::text)
+            WHERE
+                id = $2"#,
+        )
+        .bind(format!("0x{:x}", U256::ZERO))
+        .bind(batch_id as i64)
+        .execute(&mut *txn)
+        .await?;
+
+        if res.rows_affected() == 0 {
+            return Err(DbError::BatchNotFound(batch_id));
+        }
+
+        for (order_id, status) in requeued_orders {
+            let res = sqlx::query(
+                r#"
+                UPDATE orders
+                SET data = data || jsonb_build_object(
+                           'status', $1::jsonb,
+                           'updated_at', $2::bigint)
+                WHERE
+                    id = $3"#,
+            )
+            .bind(Json(status))
+            .bind(Utc::now().timestamp())
+            .bind(order_id)
+            .execute(&mut *txn)
+            .await?;
+
+            if res.rows_affected() == 0 {
+                return Err(DbError::OrderNotFound(order_id.clone()));
+            }
+        }
+
+        txn.commit().await?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_batch(&self, batch_id: usize) -> Result<Batch, DbError> {
+        let batch: Option<DbBatch> = sqlx::query_as("SELECT * FROM batches WHERE id = $1")
+            .bind(batch_id as i64)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        if let Some(batch) = batch {
+            Ok(batch.data)
+        } else {
+            Err(DbError::BatchNotFound(batch_id))
+        }
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn set_request_fulfilled(
+        &self,
+        chain_id: u64,
+        request_id: U256,
+        block_number: u64,
+    ) -> Result<(), DbError> {
+        sqlx::query(
+            r#"INSERT INTO fulfilled_requests (chain_id, id, block_number) VALUES ($1, $2, $3)"#,
+        )
+        .bind(chain_id as i64)
+        .bind(format!("0x{:x}", request_id))
+        .bind(block_number as i64)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn is_request_fulfilled(&self, chain_id: u64, request_id: U256) -> Result<bool, DbError> {
+        let res =
+            sqlx::query(r#"SELECT id FROM fulfilled_requests WHERE chain_id = $1 AND id = $2"#)
+                .bind(chain_id as i64)
+                .bind(format!("0x{:x}", request_id))
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(res.is_some())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn set_request_locked(
+        &self,
+        chain_id: u64,
+        request_id: U256,
+        locker: &str,
+        block_number: u64,
+    ) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            INSERT INTO locked_requests (chain_id, id, locker, block_number)
+            VALUES ($1, $2, $3, $4)"#,
+        )
+        .bind(chain_id as i64)
+        .bind(format!("0x{:x}", request_id))
+        .bind(locker)
+        .bind(block_number as i64)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn is_request_locked(&self, chain_id: u64, request_id: U256) -> Result<bool, DbError> {
+        let res = sqlx::query(r#"SELECT id FROM locked_requests WHERE chain_id = $1 AND id = $2"#)
+            .bind(chain_id as i64)
+            .bind(format!("0x{:x}", request_id))
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(res.is_some())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_request_locked(
+        &self,
+        chain_id: u64,
+        request_id: U256,
+    ) -> Result<Option<(String, u64)>, DbError> {
+        // Postgres has no unsigned integers, so the block number is decoded as an i64
+        let res: Option<(String, i64)> = sqlx::query_as(
+            r#"SELECT locker, block_number FROM locked_requests WHERE chain_id = $1 AND id = $2"#,
+        )
+        .bind(chain_id as i64)
+        .bind(format!("0x{:x}", request_id))
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(res.map(|(locker, block_number)| (locker, block_number as u64)))
+    }
+
+    #[instrument(level = "trace", skip_all, fields(id = %format!("{}", decision.order_id)))]
+    async fn add_shadow_decision(&self, decision: &ShadowDecision) -> Result<(), DbError> {
+        sqlx::query(
+            "INSERT INTO shadow_decisions (order_id, data, created_at) VALUES ($1, $2, $3)",
+        )
+        .bind(&decision.order_id)
+        .bind(Json(decision))
+        .bind(decision.created_at.timestamp())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_shadow_decisions(
+        &self,
+        from: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<ShadowDecision>, DbError> {
+        let decisions: Vec<Json<ShadowDecision>> = sqlx::query_scalar(
+            "SELECT data FROM shadow_decisions WHERE created_at >= $1 ORDER BY id DESC LIMIT $2",
+        )
+        .bind(from.timestamp())
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(decisions.into_iter().rev().map(|decision| decision.0).collect())
+    }
+
+    #[instrument(level = "trace", skip_all, fields(id = %format!("{}", sample.order_id)))]
+    async fn add_proving_sample(&self, sample: &ProvingSample) -> Result<(), DbError> {
+        sqlx::query("INSERT INTO proving_samples (order_id, data, created_at) VALUES ($1, $2, $3)")
+            .bind(&sample.order_id)
+            .bind(Json(sample))
+            .bind(sample.created_at.timestamp())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_proving_samples(&self, limit: usize) -> Result<Vec<ProvingSample>, DbError> {
+        let samples: Vec<Json<ProvingSample>> =
+            sqlx::query_scalar("SELECT data FROM proving_samples ORDER BY id DESC LIMIT $1")
+                .bind(limit as i64)
+                .fetch_all(&self.pool)
+                .await?;
+
+        Ok(samples.into_iter().rev().map(|sample| sample.0).collect())
+    }
+
+    #[instrument(level = "trace", skip_all, fields(id = %format!("{}", bid.order_id)))]
+    async fn add_lock_bid(&self, bid: &LockBid) -> Result<(), DbError> {
+        sqlx::query("INSERT INTO lock_bids (order_id, data, created_at) VALUES ($1, $2, $3)")
+            .bind(&bid.order_id)
+            .bind(Json(bid))
+            .bind(bid.created_at.timestamp())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_lock_bids(&self, limit: usize) -> Result<Vec<LockBid>, DbError> {
+        let bids: Vec<Json<LockBid>> =
+            sqlx::query_scalar("SELECT data FROM lock_bids ORDER BY id DESC LIMIT $1")
+                .bind(limit as i64)
+                .fetch_all(&self.pool)
+                .await?;
+
+        Ok(bids.into_iter().rev().map(|bid| bid.0).collect())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_preflight_cache(
+        &self,
+        image_id: &str,
+        input_digest: &str,
+    ) -> Result<Option<PreflightCacheEntry>, DbError> {
+        let entry: Option<Json<PreflightCacheEntry>> = sqlx::query_scalar(
+            "SELECT data FROM preflight_cache WHERE image_id = $1 AND input_digest = $2",
+        )
+        .bind(image_id)
+        .bind(input_digest)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(entry.map(|entry| entry.0))
+    }
+
+    #[instrument(level = "trace", skip_all, fields(image_id = %entry.image_id))]
+    async fn set_preflight_cache(&self, entry: &PreflightCacheEntry) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            INSERT INTO preflight_cache (image_id, input_digest, created_at, data)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (image_id, input_digest)
+            DO UPDATE SET created_at = excluded.created_at, data = excluded.data
+            "#,
+        )
+        .bind(&entry.image_id)
+        .bind(&entry.input_digest)
+        .bind(entry.created_at.timestamp())
+        .bind(Json(entry))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn prune_preflight_cache(
+        &self,
+        created_before: DateTime<Utc>,
+        max_entries: u64,
+    ) -> Result<u64, DbError> {
+        let expired = sqlx::query("DELETE FROM preflight_cache WHERE created_at < $1")
+            .bind(created_before.timestamp())
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+
+        let evicted = sqlx::query(
+            r#"
+            DELETE FROM preflight_cache
+            WHERE (image_id, input_digest) NOT IN (
+                SELECT image_id, input_digest FROM preflight_cache
+                ORDER BY created_at DESC
+                LIMIT $1)
+            "#,
+        )
+        .bind(i64::try_from(max_entries).unwrap_or(i64::MAX))
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(expired + evicted)
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn prune_history(
+        &self,
+        created_before: DateTime<Utc>,
+        max_entries: u64,
+    ) -> Result<u64, DbError> {
+        let mut pruned = 0;
+        for table in ["shadow_decisions", "proving_samples", "lock_bids"] {
+            pruned += sqlx::query(&format!("DELETE FROM {table} WHERE created_at < $1"))
+                .bind(created_before.timestamp())
+                .execute(&self.pool)
+                .await?
+                .rows_affected();
+
+            pruned += sqlx::query(&format!(
+                r#"
+                DELETE FROM {table}
+                WHERE id NOT IN (SELECT id FROM {table} ORDER BY id DESC LIMIT $1)
+                "#
+            ))
+            .bind(i64::try_from(max_entries).unwrap_or(i64::MAX))
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        }
+
+        Ok(pruned)
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_skip_counts(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<BTreeMap<SkipReason, u64>, DbError> {
+        let counts: Vec<(Json<SkipReason>, i64)> = sqlx::query_as(
+            r#"
+            SELECT data->'skip_reason', COUNT(*) FROM orders
+                WHERE data->'status' = $1
+                AND data->'skip_reason' IS NOT NULL
+                AND (data->>'updated_at')::bigint >= $2
+                AND (data->>'updated_at')::bigint < $3
+                GROUP BY data->'skip_reason'"#,
+        )
+        .bind(Json(OrderStatus::Skipped))
+        .bind(from.timestamp())
+        .bind(to.timestamp())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(counts.into_iter().map(|(reason, count)| (reason.0, count as u64)).collect())
+    }
+
+    #[instrument(level = "trace", skip_all, fields(count = entries.len()))]
+    async fn add_ledger_entries(&self, entries: &[LedgerEntry]) -> Result<(), DbError> {
+        let mut txn = self.pool.begin().await?;
+        for entry in entries {
+            sqlx::query(
+                r#"
+                INSERT INTO ledger (order_id, chain_id, kind, created_at, data)
+                    VALUES ($1, $2, $3, $4, $5)"#,
+            )
+            .bind(&entry.order_id)
+            .bind(entry.chain_id as i64)
+            .bind(entry.kind.as_str())
+            .bind(entry.created_at.timestamp())
+            .bind(Json(entry))
+            .execute(&mut *txn)
+            .await?;
+        }
+        txn.commit().await?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_ledger_entries(
+        &self,
+        chain_id: Option<u64>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<LedgerEntry>, DbError> {
+        let entries: Vec<Json<LedgerEntry>> = sqlx::query_scalar(
+            r#"
+            SELECT data FROM ledger
+                WHERE ($1::BIGINT IS NULL OR chain_id = $1)
+                AND created_at >= $2 AND created_at < $3
+                ORDER BY id"#,
+        )
+        .bind(chain_id.map(|chain_id| chain_id as i64))
+        .bind(from.timestamp())
+        .bind(to.timestamp())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(entries.into_iter().map(|entry| entry.0).collect())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_open_stakes(&self) -> Result<Vec<LedgerEntry>, DbError> {
+        let entries: Vec<Json<LedgerEntry>> = sqlx::query_scalar(
+            r#"
+            SELECT data FROM ledger AS locked
+                WHERE locked.kind = $1
+                AND NOT EXISTS (
+                    SELECT 1 FROM ledger AS settled
+                        WHERE settled.order_id = locked.order_id
+                        AND settled.kind IN ($2, $3))
+                ORDER BY locked.id"#,
+        )
+        .bind(LedgerKind::StakeLocked.as_str())
+        .bind(LedgerKind::StakeReturned.as_str())
+        .bind(LedgerKind::StakeSlashed.as_str())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(entries.into_iter().map(|entry| entry.0).collect())
+    }
+
+    #[instrument(level = "trace", skip_all, fields(kind = ?action.kind))]
+    async fn add_treasury_action(&self, action: &TreasuryAction) -> Result<i64, DbError> {
+        let id: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO treasury_actions (chain_id, kind, created_at, pending, data)
+                VALUES ($1, $2, $3, $4, $5)
+                RETURNING id"#,
+        )
+        .bind(action.chain_id as i64)
+        .bind(action.kind.as_str())
+        .bind(action.created_at.timestamp())
+        .bind(action.tx_hash.is_some())
+        .bind(Json(action))
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(id)
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn set_treasury_action_error(&self, id: i64, error: &str) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            UPDATE treasury_actions
+            SET data = data || jsonb_build_object('error', $1::text), pending = FALSE
+            WHERE id = $2"#,
+        )
+        .bind(error)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn set_treasury_action_tx_hash(&self, id: i64, tx_hash: B256) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            UPDATE treasury_actions
+            SET data = data || jsonb_build_object('tx_hash', $1::text), pending = TRUE
+            WHERE id = $2"#,
+        )
+        .bind(tx_hash.to_string())
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn confirm_treasury_action(&self, id: i64) -> Result<(), DbError> {
+        sqlx::query("UPDATE treasury_actions SET pending = FALSE WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_pending_treasury_actions(
+        &self,
+        chain_id: u64,
+    ) -> Result<Vec<(i64, TreasuryAction)>, DbError> {
+        let actions: Vec<(i64, Json<TreasuryAction>)> = sqlx::query_as(
+            "SELECT id, data FROM treasury_actions WHERE chain_id = $1 AND pending ORDER BY id",
+        )
+        .bind(chain_id as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(actions.into_iter().map(|(id, action)| (id, action.0)).collect())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_treasury_actions(
+        &self,
+        chain_id: u64,
+        from: DateTime<Utc>,
+    ) -> Result<Vec<TreasuryAction>, DbError> {
+        let actions: Vec<Json<TreasuryAction>> = sqlx::query_scalar(
+            "SELECT data FROM treasury_actions WHERE chain_id = $1 AND created_at >= $2 ORDER BY id",
+        )
+        .bind(chain_id as i64)
+        .bind(from.timestamp())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(actions.into_iter().map(|action| action.0).collect())
+    }
+
+    #[cfg(test)]
+    async fn add_batch(&self, batch_id: usize, batch: Batch) -> Result<(), DbError> {
+        let res = sqlx::query("INSERT INTO batches (id, data) VALUES ($1, $2)")
+            .bind(batch_id as i64)
+            .bind(Json(batch))
+            .execute(&self.pool)
+            .await?;
+
+        if res.rows_affected() == 0 {
+            return Err(DbError::BatchInsertFailure(batch_id));
+        }
+
+        // Explicit ids do not advance the sequence, so move it past the inserted batch
+        sqlx::query(
+            "SELECT setval(pg_get_serial_sequence('batches', 'id'), (SELECT MAX(id) FROM batches))",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[cfg(test)]
+    async fn set_batch_status(&self, batch_id: usize, status: BatchStatus) -> Result<(), DbError> {
+        let res = sqlx::query(
+            r#"
+                UPDATE batches
+                SET
+                    data = jsonb_set(data, '{status}', $1)
+                WHERE
+                    id = $2"#,
+        )
+        .bind(Json(status))
+        .bind(batch_id as i64)
+        .execute(&self.pool)
+        .await?;
+
+        if res.rows_affected() == 0 {
+            return Err(DbError::BatchNotFound(batch_id));
+        }
+
+        Ok(())
+    }
+}
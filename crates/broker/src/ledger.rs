@@ -0,0 +1,319 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Profit and loss ledger of the orders the broker committed to.
+//!
+//! Every amount earned or spent on an order is appended to the DB as a [LedgerEntry]: the gas of
+//! its lock transaction, its share of the gas of the fulfillment transaction, the payment
+//! received, and the stake locked, returned or slashed. The entries are summarized by day,
+//! chain, requestor or image ID in a [LedgerReport], printed by the `pnl` binary, so that pricing
+//! parameters can be tuned against the actual earnings of the broker.
+
+use std::collections::{BTreeMap, HashSet};
+
+use alloy::{
+    primitives::{Address, B256, I256, U256},
+    rpc::types::TransactionReceipt,
+};
+use anyhow::{Context, Result};
+use chrono::{serde::ts_seconds, DateTime, Utc};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    db::{self, DbObj},
+    ProofRequest,
+};
+
+/// Kind of amount recorded in the ledger
+///
+/// Gas and payments are denominated in wei of the native token, stake in the smallest unit of the
+/// stake token.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerKind {
+    /// Gas paid for the lock transaction
+    LockGas,
+    /// Share of the gas paid for the fulfillment transaction of the batch of the order
+    FulfillGas,
+    /// Payment received for fulfilling the request
+    Payment,
+    /// Stake locked with the request
+    StakeLocked,
+    /// Locked stake returned on fulfillment
+    StakeReturned,
+    /// Stake of another prover received for fulfilling the request after its lock expired
+    StakeReward,
+    /// Locked stake lost to the request not being fulfilled before its lock expired
+    StakeSlashed,
+}
+
+impl LedgerKind {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            LedgerKind::LockGas => "lock_gas",
+            LedgerKind::FulfillGas => "fulfill_gas",
+            LedgerKind::Payment => "payment",
+            LedgerKind::StakeLocked => "stake_locked",
+            LedgerKind::StakeReturned => "stake_returned",
+            LedgerKind::StakeReward => "stake_reward",
+            LedgerKind::StakeSlashed => "stake_slashed",
+        }
+    }
+}
+
+/// An amount earned or spent on an order
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub order_id: String,
+    /// Chain the order was committed to on
+    pub chain_id: u64,
+    pub kind: LedgerKind,
+    pub amount: U256,
+    pub requestor: Address,
+    pub image_id: B256,
+    #[serde(with = "ts_seconds")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl LedgerEntry {
+    pub fn new(
+        order_id: String,
+        chain_id: u64,
+        request: &ProofRequest,
+        kind: LedgerKind,
+        amount: U256,
+    ) -> Self {
+        Self {
+            order_id,
+            chain_id,
+            kind,
+            amount,
+            requestor: request.client_address(),
+            image_id: request.requirements.imageId,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// Gas paid for a transaction, in wei
+pub(crate) fn gas_cost(receipt: &TransactionReceipt) -> U256 {
+    U256::from(receipt.gas_used) * U256::from(receipt.effective_gas_price)
+}
+
+/// Append entries to the ledger, logging rather than failing on DB errors
+pub(crate) async fn record(db: &DbObj, entries: Vec<LedgerEntry>) {
+    for entry in entries.iter() {
+        tracing::debug!(
+            "Ledger entry for order {}: {:?} {}",
+            entry.order_id,
+            entry.kind,
+            entry.amount
+        );
+    }
+    if let Err(err) = db.add_ledger_entries(&entries).await {
+        tracing::error!("Failed to record {} ledger entries: {err:?}", entries.len());
+    }
+}
+
+/// Key the entries of a [LedgerReport] are grouped by
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBy {
+    /// UTC day the entries were recorded
+    Day,
+    /// Chain the orders were committed to on
+    Chain,
+    /// Address of the requestor
+    Requestor,
+    /// Image ID of the request
+    ImageId,
+}
+
+impl GroupBy {
+    fn key(&self, entry: &LedgerEntry) -> String {
+        match self {
+            GroupBy::Day => entry.created_at.format("%Y-%m-%d").to_string(),
+            GroupBy::Chain => entry.chain_id.to_string(),
+            GroupBy::Requestor => entry.requestor.to_string(),
+            GroupBy::ImageId => entry.image_id.to_string(),
+        }
+    }
+}
+
+/// Totals of a group of ledger entries
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LedgerSummary {
+    /// Number of orders with entries in the group
+    pub orders: usize,
+    /// Gas paid for lock and fulfillment transactions, in wei
+    pub gas: U256,
+    /// Payments received, in wei
+    pub payments: U256,
+    pub stake_locked: U256,
+    pub stake_returned: U256,
+    pub stake_rewards: U256,
+    pub stake_slashed: U256,
+}
+
+impl LedgerSummary {
+    fn add(&mut self, entry: &LedgerEntry) {
+        let total = match entry.kind {
+            LedgerKind::LockGas | LedgerKind::FulfillGas => &mut self.gas,
+            LedgerKind::Payment => &mut self.payments,
+            LedgerKind::StakeLocked => &mut self.stake_locked,
+            LedgerKind::StakeReturned => &mut self.stake_returned,
+            LedgerKind::StakeReward => &mut self.stake_rewards,
+            LedgerKind::StakeSlashed => &mut self.stake_slashed,
+        };
+        *total = total.saturating_add(entry.amount);
+    }
+
+    /// Payments received net of the gas paid, in wei
+    pub fn profit(&self) -> I256 {
+        I256::from_raw(self.payments).saturating_sub(I256::from_raw(self.gas))
+    }
+
+    /// Stake received net of the stake slashed, in the stake token
+    pub fn stake_profit(&self) -> I256 {
+        I256::from_raw(self.stake_rewards).saturating_sub(I256::from_raw(self.stake_slashed))
+    }
+}
+
+/// Summary of the ledger entries recorded in a window
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LedgerReport {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Chain the entries were filtered to, if any
+    pub chain_id: Option<u64>,
+    pub group_by: GroupBy,
+    pub total: LedgerSummary,
+    pub groups: BTreeMap<String, LedgerSummary>,
+}
+
+impl LedgerReport {
+    pub fn new(
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        chain_id: Option<u64>,
+        group_by: GroupBy,
+        entries: &[LedgerEntry],
+    ) -> Self {
+        let mut report = Self {
+            from,
+            to,
+            chain_id,
+            group_by,
+            total: LedgerSummary::default(),
+            groups: BTreeMap::new(),
+        };
+        let mut orders = HashSet::new();
+        let mut group_orders: BTreeMap<String, HashSet<&str>> = BTreeMap::new();
+        for entry in entries {
+            let key = group_by.key(entry);
+            report.total.add(entry);
+            report.groups.entry(key.clone()).or_default().add(entry);
+            orders.insert(entry.order_id.as_str());
+            group_orders.entry(key).or_default().insert(entry.order_id.as_str());
+        }
+
+        report.total.orders = orders.len();
+        for (key, orders) in group_orders {
+            if let Some(summary) = report.groups.get_mut(&key) {
+                summary.orders = orders.len();
+            }
+        }
+        report
+    }
+}
+
+/// Summarize the ledger entries recorded at or after `from` and before `to` in the broker DB,
+/// on `chain_id` only if set
+pub async fn load_report(
+    db_url: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    chain_id: Option<u64>,
+    group_by: GroupBy,
+) -> Result<LedgerReport> {
+    let db = db::connect(db_url).await.context("Failed to connect to DB")?;
+    let entries =
+        db.get_ledger_entries(chain_id, from, to).await.context("Failed to get ledger entries")?;
+    Ok(LedgerReport::new(from, to, chain_id, group_by, &entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(order_id: &str, requestor: Address, kind: LedgerKind, amount: u64) -> LedgerEntry {
+        LedgerEntry {
+            order_id: order_id.into(),
+            chain_id: 1,
+            kind,
+            amount: U256::from(amount),
+            requestor,
+            image_id: B256::ZERO,
+            created_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn report_groups_entries() {
+        let alice = Address::repeat_byte(1);
+        let bob = Address::repeat_byte(2);
+        let entries = vec![
+            entry("a", alice, LedgerKind::LockGas, 10),
+            entry("a", alice, LedgerKind::StakeLocked, 100),
+            entry("a", alice, LedgerKind::FulfillGas, 5),
+            entry("a", alice, LedgerKind::Payment, 50),
+            entry("a", alice, LedgerKind::StakeReturned, 100),
+            entry("b", alice, LedgerKind::LockGas, 10),
+            entry("b", alice, LedgerKind::StakeLocked, 100),
+            entry("b", alice, LedgerKind::StakeSlashed, 100),
+            entry("c", bob, LedgerKind::FulfillGas, 5),
+            entry("c", bob, LedgerKind::StakeReward, 20),
+            LedgerEntry { chain_id: 2, ..entry("d", bob, LedgerKind::Payment, 40) },
+        ];
+        let from = DateTime::from_timestamp(0, 0).unwrap();
+        let to = Utc::now();
+
+        let report = LedgerReport::new(from, to, None, GroupBy::Requestor, &entries);
+        assert_eq!(report.total.orders, 4);
+        assert_eq!(report.total.gas, U256::from(30));
+        assert_eq!(report.total.profit(), I256::try_from(60).unwrap());
+        assert_eq!(report.total.stake_profit(), I256::try_from(-80).unwrap());
+
+        let alice_summary = &report.groups[&alice.to_string()];
+        assert_eq!(alice_summary.orders, 2);
+        assert_eq!(alice_summary.profit(), I256::try_from(25).unwrap());
+        assert_eq!(alice_summary.stake_locked, U256::from(200));
+        assert_eq!(alice_summary.stake_returned, U256::from(100));
+        assert_eq!(alice_summary.stake_slashed, U256::from(100));
+        let bob_summary = &report.groups[&bob.to_string()];
+        assert_eq!(bob_summary.orders, 2);
+        assert_eq!(bob_summary.stake_rewards, U256::from(20));
+
+        let report = LedgerReport::new(from, to, None, GroupBy::Day, &entries);
+        assert_eq!(report.groups.len(), 1);
+        assert_eq!(report.groups["2023-11-14"].orders, 4);
+
+        let report = LedgerReport::new(from, to, None, GroupBy::Chain, &entries);
+        assert_eq!(report.groups.len(), 2);
+        assert_eq!(report.groups["1"].orders, 3);
+        assert_eq!(report.groups["1"].profit(), I256::try_from(20).unwrap());
+        assert_eq!(report.groups["2"].payments, U256::from(40));
+    }
+}
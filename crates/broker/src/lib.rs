@@ -12,18 +12,19 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{path::PathBuf, sync::Arc, time::SystemTime};
+use std::{net::SocketAddr, path::PathBuf, sync::Arc, time::SystemTime};
 
 use crate::storage::create_uri_handler;
 use alloy::{
     network::Ethereum,
-    primitives::{Address, Bytes, FixedBytes, U256},
+    primitives::{utils::parse_units, Address, Bytes, FixedBytes, I256, U256},
     providers::{Provider, WalletProvider},
     signers::local::PrivateKeySigner,
 };
 use anyhow::{Context, Result};
 use boundless_market::{
     contracts::{boundless_market::BoundlessMarketService, ProofRequest},
+    nonce_layer::ReservedNonces,
     order_stream_client::OrderStreamClient,
     selector::is_groth16_selector,
     Deployment,
@@ -31,13 +32,16 @@ use boundless_market::{
 use chrono::{serde::ts_seconds, DateTime, Utc};
 use clap::Parser;
 pub use config::Config;
-use config::ConfigWatcher;
-use db::{DbObj, SqliteDb};
+use config::{ChainConf, ConfigLock, ConfigWatcher, StakePriceOracleConf, TreasuryConf};
+use db::DbObj;
+use pricing::{PricingStrategies, PricingStrategyObj};
 use provers::ProverObj;
+pub use provers::{ExecutorResp, ProofResult};
 use risc0_ethereum_contracts::set_verifier::SetVerifierService;
 use risc0_zkvm::sha::Digest;
 pub use rpc_retry_policy::CustomRetryPolicy;
 use serde::{Deserialize, Serialize};
+use stake_price::StakePriceOracleObj;
 use task::{RetryPolicy, Supervisor};
 use tokio::sync::mpsc;
 use tokio::task::JoinSet;
@@ -47,30 +51,44 @@ use url::Url;
 const NEW_ORDER_CHANNEL_CAPACITY: usize = 1000;
 const PRICING_CHANNEL_CAPACITY: usize = 1000;
 
+pub(crate) mod admin;
 pub(crate) mod aggregator;
+pub mod backtest;
+pub(crate) mod blob_store;
 pub(crate) mod chain_monitor;
 pub mod config;
 pub(crate) mod db;
 pub(crate) mod errors;
 pub mod futures_retry;
+pub mod ledger;
+pub(crate) mod lock_bidding;
 pub(crate) mod market_monitor;
+pub(crate) mod metrics;
 pub(crate) mod offchain_market_monitor;
 pub(crate) mod order_monitor;
 pub(crate) mod order_picker;
+pub(crate) mod preflight_cache;
+pub mod pricing;
 pub(crate) mod prioritization;
 pub(crate) mod provers;
 pub(crate) mod proving;
 pub(crate) mod reaper;
 pub(crate) mod rpc_retry_policy;
+pub mod rules;
+pub(crate) mod shadow;
+pub mod stake_price;
 pub(crate) mod storage;
 pub(crate) mod submitter;
 pub(crate) mod task;
+pub(crate) mod throughput;
+pub(crate) mod treasury;
+pub(crate) mod tx_manager;
 pub(crate) mod utils;
 
 #[derive(Parser, Debug, Clone)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
-    /// sqlite database connection url
+    /// Database connection url, either sqlite or postgres
     #[clap(short = 's', long, env, default_value = "sqlite::memory:")]
     pub db_url: String,
 
@@ -135,6 +153,31 @@ pub struct Args {
     /// Log JSON
     #[clap(long, env, default_value_t = false)]
     pub log_json: bool,
+
+    /// Admin API bind address
+    ///
+    /// Enables the local admin HTTP API when set, eg: 127.0.0.1:8686
+    #[clap(long, env)]
+    pub admin_addr: Option<SocketAddr>,
+
+    /// Admin API bearer token
+    ///
+    /// Required to use the admin API write endpoints
+    #[clap(long, env)]
+    pub admin_token: Option<String>,
+
+    /// Metrics bind address
+    ///
+    /// Serves Prometheus metrics on /metrics when set, eg: 127.0.0.1:9090
+    #[clap(long, env)]
+    pub metrics_addr: Option<SocketAddr>,
+
+    /// Shadow mode
+    ///
+    /// Runs the full pipeline without sending lock or fulfillment transactions, recording each
+    /// decision to the DB instead. The report is served by the admin API.
+    #[clap(long, env, default_value_t = false)]
+    pub shadow: bool,
 }
 
 /// Status of a persistent order as it moves through the lifecycle in the database.
@@ -163,13 +206,150 @@ enum OrderStatus {
 }
 
 #[derive(Clone, Copy, sqlx::Type, Debug, PartialEq, Serialize, Deserialize)]
-enum FulfillmentType {
+pub enum FulfillmentType {
     LockAndFulfill,
     FulfillAfterLockExpire,
-    // Currently not supported
     FulfillWithoutLocking,
 }
 
+impl FulfillmentType {
+    /// Select how to fulfill a request that has not been locked.
+    ///
+    /// Requests with a lock stake at or below `market.fulfill_without_locking_max_stake` are
+    /// fulfilled without locking, all others are locked before proving.
+    pub(crate) fn for_unlocked_request(
+        request: &ProofRequest,
+        config: &ConfigLock,
+        stake_token_decimals: u8,
+    ) -> Result<Self> {
+        let max_stake = {
+            let config = config.lock_all().context("Failed to read config")?;
+            config.market.fulfill_without_locking_max_stake.clone()
+        };
+        let Some(max_stake) = max_stake else {
+            return Ok(Self::LockAndFulfill);
+        };
+        let max_stake: U256 = parse_units(&max_stake, stake_token_decimals)
+            .context("Failed to parse fulfill_without_locking_max_stake")?
+            .into();
+
+        if U256::from(request.offer.lockStake) <= max_stake {
+            Ok(Self::FulfillWithoutLocking)
+        } else {
+            Ok(Self::LockAndFulfill)
+        }
+    }
+}
+
+/// Reason an order was skipped
+///
+/// Recorded with skipped orders in the DB, in shadow decisions and in metrics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// Marked to be skipped by the operator
+    SkippedByOperator,
+    /// The request was locked, by another prover or before the broker could lock it
+    AlreadyLocked,
+    /// The request was fulfilled by another prover
+    AlreadyFulfilled,
+    /// The selector of the request is not supported
+    UnsupportedSelector,
+    /// An assumption of the request cannot be resolved to a receipt, e.g. an aggregated fulfillment
+    UnsupportedAssumption,
+    /// The requestor is denied by `deny_requestor_addresses` or a rule
+    Denylisted,
+    /// The requestor is not in `allow_client_addresses`
+    NotAllowlisted,
+    /// The order expired
+    Expired,
+    /// The order expires within `min_deadline`
+    MinDeadline,
+    /// The lock stake exceeds `max_stake`
+    StakeTooHigh,
+    /// The gas cost exceeds the price of the order
+    GasExceedsPrice,
+    /// Not enough gas tokens to lock and fulfill the order
+    InsufficientGas,
+    /// Not enough stake tokens to lock the order
+    InsufficientStake,
+    /// The executor limit derived from the price of the order is too low
+    ExecLimitTooLow,
+    /// No time left to prove the order before it expires
+    NoTimeToProve,
+    /// Preflight exceeded the executor limit
+    SessionLimit,
+    /// Preflight did not complete in time
+    PreflightTimeout,
+    /// The journal does not satisfy the predicate of the request
+    PredicateFailed,
+    /// The order exceeds `max_mcycle_limit`
+    McycleLimit,
+    /// The journal exceeds `max_journal_bytes`
+    JournalTooLarge,
+    /// The price of the order is below the min mcycle price
+    UnderPriced,
+    /// Not enough proving capacity to complete the order before it expires
+    InsufficientCapacity,
+    /// The lock of the order expired before the broker locked it
+    LockExpired,
+    /// The lock transaction failed
+    LockFailed,
+    /// Pricing the order failed
+    PricingError,
+    /// Skipped by a custom pricing strategy for another reason
+    Other,
+}
+
+impl SkipReason {
+    /// Name of the reason, as stored in the DB and used in metrics
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::SkippedByOperator => "skipped_by_operator",
+            Self::AlreadyLocked => "already_locked",
+            Self::AlreadyFulfilled => "already_fulfilled",
+            Self::UnsupportedSelector => "unsupported_selector",
+            Self::UnsupportedAssumption => "unsupported_assumption",
+            Self::Denylisted => "denylisted",
+            Self::NotAllowlisted => "not_allowlisted",
+            Self::Expired => "expired",
+            Self::MinDeadline => "min_deadline",
+            Self::StakeTooHigh => "stake_too_high",
+            Self::GasExceedsPrice => "gas_exceeds_price",
+            Self::InsufficientGas => "insufficient_gas",
+            Self::InsufficientStake => "insufficient_stake",
+            Self::ExecLimitTooLow => "exec_limit_too_low",
+            Self::NoTimeToProve => "no_time_to_prove",
+            Self::SessionLimit => "session_limit",
+            Self::PreflightTimeout => "preflight_timeout",
+            Self::PredicateFailed => "predicate_failed",
+            Self::McycleLimit => "mcycle_limit",
+            Self::JournalTooLarge => "journal_too_large",
+            Self::UnderPriced => "under_priced",
+            Self::InsufficientCapacity => "insufficient_capacity",
+            Self::LockExpired => "lock_expired",
+            Self::LockFailed => "lock_failed",
+            Self::PricingError => "pricing_error",
+            Self::Other => "other",
+        }
+    }
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for SkipReason {
+    type Err = serde::de::value::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use serde::de::IntoDeserializer;
+        Self::deserialize(s.into_deserializer())
+    }
+}
+
 /// Helper function to format an order ID consistently
 fn format_order_id(
     request_id: &U256,
@@ -183,17 +363,23 @@ fn format_order_id(
 ///
 /// This will turn into an [`Order`] once it is locked or skipped.
 #[derive(Serialize, Deserialize, Debug, Clone)]
-struct OrderRequest {
-    request: ProofRequest,
-    client_sig: Bytes,
-    fulfillment_type: FulfillmentType,
-    boundless_market_address: Address,
-    chain_id: u64,
-    image_id: Option<String>,
-    input_id: Option<String>,
-    total_cycles: Option<u64>,
-    target_timestamp: Option<u64>,
-    expire_timestamp: Option<u64>,
+pub struct OrderRequest {
+    pub request: ProofRequest,
+    pub client_sig: Bytes,
+    pub fulfillment_type: FulfillmentType,
+    pub boundless_market_address: Address,
+    pub chain_id: u64,
+    pub image_id: Option<String>,
+    pub input_id: Option<String>,
+    pub assumption_ids: Vec<String>,
+    pub total_cycles: Option<u64>,
+    pub target_timestamp: Option<u64>,
+    pub expire_timestamp: Option<u64>,
+    /// Expected profit, net of gas, in wei, when priced to be locked
+    pub expected_profit: Option<I256>,
+    /// Whether a rule in `market.rules` boosted the priority of the order
+    #[serde(default)]
+    pub priority_boost: bool,
 }
 
 impl OrderRequest {
@@ -212,9 +398,12 @@ impl OrderRequest {
             chain_id,
             image_id: None,
             input_id: None,
+            assumption_ids: Vec::new(),
             total_cycles: None,
             target_timestamp: None,
             expire_timestamp: None,
+            expected_profit: None,
+            priority_boost: false,
         }
     }
 
@@ -227,6 +416,19 @@ impl OrderRequest {
         format_order_id(&self.request.id, &signing_hash, &self.fulfillment_type)
     }
 
+    /// Timestamp after which the order can no longer be fulfilled for a reward.
+    ///
+    /// Payment for fulfilling a request is only made before its lock expires, unless the order
+    /// claims the stake of another prover whose lock expired.
+    pub fn expiration(&self) -> u64 {
+        match self.fulfillment_type {
+            FulfillmentType::LockAndFulfill | FulfillmentType::FulfillWithoutLocking => {
+                self.request.lock_expires_at()
+            }
+            FulfillmentType::FulfillAfterLockExpire => self.request.expires_at(),
+        }
+    }
+
     fn to_order(&self, status: OrderStatus) -> Order {
         Order {
             boundless_market_address: self.boundless_market_address,
@@ -238,6 +440,7 @@ impl OrderRequest {
             updated_at: Utc::now(),
             image_id: self.image_id.clone(),
             input_id: self.input_id.clone(),
+            assumption_ids: self.assumption_ids.clone(),
             total_cycles: self.total_cycles,
             target_timestamp: self.target_timestamp,
             expire_timestamp: self.expire_timestamp,
@@ -246,11 +449,14 @@ impl OrderRequest {
             compressed_proof_id: None,
             lock_price: None,
             error_msg: None,
+            skip_reason: None,
         }
     }
 
-    fn to_skipped_order(&self) -> Order {
-        self.to_order(OrderStatus::Skipped)
+    fn to_skipped_order(&self, reason: SkipReason) -> Order {
+        let mut order = self.to_order(OrderStatus::Skipped);
+        order.skip_reason = Some(reason);
+        order
     }
 
     fn to_proving_order(&self, lock_price: U256) -> Order {
@@ -315,6 +521,11 @@ struct Order {
     ///
     ///  Populated after preflight
     input_id: Option<String>,
+    /// Prover IDs of the receipts for the request's assumptions
+    ///
+    /// Populated after preflight
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    assumption_ids: Vec<String>,
     /// Proof Id
     ///
     /// Populated after proof completion
@@ -333,6 +544,9 @@ struct Order {
     lock_price: Option<U256>,
     /// Failure message
     error_msg: Option<String>,
+    /// Reason the order was skipped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    skip_reason: Option<SkipReason>,
 }
 
 impl Order {
@@ -403,15 +617,39 @@ struct Batch {
     pub deadline: Option<u64>,
     /// The total fees for the batch, which is the sum of fees from all orders.
     pub fees: U256,
+    /// Chain the batch is submitted to.
+    pub chain_id: u64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error_msg: Option<String>,
 }
 
+/// A chain and Boundless market deployment served by the broker
+struct Chain<P> {
+    chain_id: u64,
+    provider: Arc<P>,
+    deployment: Deployment,
+    stake_price_oracle: Option<StakePriceOracleConf>,
+    /// Treasury config of an additional chain, the chain of the `--rpc-url` follows `treasury`
+    treasury: Option<TreasuryConf>,
+    /// Nonces reserved by the transaction manager, skipped by the nonce source of the provider
+    reserved_nonces: ReservedNonces,
+}
+
+/// Handles to the services of a chain, used by the shared order picker
+struct ChainHandles<P> {
+    chain_monitor: Arc<chain_monitor::ChainMonitorService<P>>,
+    priced_orders_tx: mpsc::Sender<Box<OrderRequest>>,
+    stake_token_decimals: u8,
+    stake_price_oracle: Option<StakePriceOracleObj>,
+}
+
 pub struct Broker<P> {
     args: Args,
-    provider: Arc<P>,
+    // Chains served by the broker, starting with the chain of the `--rpc-url`
+    chains: Vec<Chain<P>>,
     db: DbObj,
     config_watcher: ConfigWatcher,
+    pricing_strategies: PricingStrategies,
 }
 
 impl<P> Broker<P>
@@ -422,8 +660,7 @@ where
         let config_watcher =
             ConfigWatcher::new(&args.config_file).await.context("Failed to load broker config")?;
 
-        let db: DbObj =
-            Arc::new(SqliteDb::new(&args.db_url).await.context("Failed to connect to sqlite DB")?);
+        let db: DbObj = db::connect(&args.db_url).await.context("Failed to connect to DB")?;
 
         let chain_id = provider.get_chain_id().await.context("Failed to get chain ID")?;
 
@@ -441,7 +678,106 @@ where
             tracing::info!("Using default deployment configuration for chain ID {chain_id}");
         }
 
-        Ok(Self { args, db, provider: Arc::new(provider), config_watcher })
+        let stake_price_oracle = config_watcher
+            .config
+            .lock_all()
+            .context("Failed to lock config")?
+            .market
+            .stake_price_oracle
+            .clone();
+        let chain = Chain {
+            chain_id,
+            provider: Arc::new(provider),
+            deployment: args.deployment.clone().unwrap(),
+            stake_price_oracle,
+            treasury: None,
+            reserved_nonces: ReservedNonces::default(),
+        };
+
+        Ok(Self {
+            args,
+            chains: vec![chain],
+            db,
+            config_watcher,
+            pricing_strategies: PricingStrategies::default(),
+        })
+    }
+
+    /// Serve an additional chain, connected to through `provider`.
+    ///
+    /// Contract addresses and the order stream URL not set in `conf` default to the Boundless
+    /// deployment of the chain.
+    pub async fn with_chain(mut self, provider: P, conf: &ChainConf) -> Result<Self> {
+        let chain_id = provider.get_chain_id().await.context("Failed to get chain ID")?;
+        if self.chains.iter().any(|chain| chain.chain_id == chain_id) {
+            anyhow::bail!("Chain ID {chain_id} is configured more than once");
+        }
+
+        let mut deployment = match Deployment::from_chain_id(chain_id) {
+            Some(deployment) => deployment,
+            None => {
+                let (Some(market_addr), Some(set_verifier_addr)) =
+                    (conf.boundless_market_address, conf.set_verifier_address)
+                else {
+                    anyhow::bail!("No default deployment found for chain ID {chain_id}. Please specify boundless_market_address and set_verifier_address of the chain.");
+                };
+                Deployment::builder()
+                    .chain_id(chain_id)
+                    .boundless_market_address(market_addr)
+                    .set_verifier_address(set_verifier_addr)
+                    .build()
+                    .context("Failed to build deployment")?
+            }
+        };
+        if let Some(market_addr) = conf.boundless_market_address {
+            deployment.boundless_market_address = market_addr;
+        }
+        if let Some(set_verifier_addr) = conf.set_verifier_address {
+            deployment.set_verifier_address = set_verifier_addr;
+        }
+        if let Some(order_stream_url) = &conf.order_stream_url {
+            deployment.order_stream_url = Some(order_stream_url.clone().into());
+        }
+        tracing::info!(
+            "Serving chain ID {chain_id} with market {}",
+            deployment.boundless_market_address
+        );
+
+        self.chains.push(Chain {
+            chain_id,
+            provider: Arc::new(provider),
+            deployment,
+            stake_price_oracle: conf.stake_price_oracle.clone(),
+            treasury: Some(conf.treasury.clone().unwrap_or_default()),
+            reserved_nonces: ReservedNonces::default(),
+        });
+        Ok(self)
+    }
+
+    /// Share the nonces reserved by the transaction manager of the last chain added with the
+    /// nonce source of its provider, e.g. [NonceProvider::reserved_nonces].
+    ///
+    /// Without it, transactions sent outside the transaction manager, such as stake deposits and
+    /// treasury transfers, may take the nonce of a pending managed transaction.
+    ///
+    /// [NonceProvider::reserved_nonces]: boundless_market::nonce_layer::NonceProvider::reserved_nonces
+    pub fn with_reserved_nonces(mut self, reserved_nonces: ReservedNonces) -> Self {
+        self.chains.last_mut().expect("Broker serves at least one chain").reserved_nonces =
+            reserved_nonces;
+        self
+    }
+
+    /// Register a custom [PricingStrategy](pricing::PricingStrategy) under the given name.
+    ///
+    /// The strategy is used by the order picker when `market.pricing_strategy` in the config is
+    /// set to `name`.
+    pub fn with_pricing_strategy(
+        mut self,
+        name: impl Into<String>,
+        strategy: PricingStrategyObj,
+    ) -> Self {
+        self.pricing_strategies.register(name, strategy);
+        self
     }
 
     pub fn deployment(&self) -> &Deployment {
@@ -516,10 +852,14 @@ where
         }
     }
 
-    async fn fetch_and_upload_set_builder_image(&self, prover: &ProverObj) -> Result<Digest> {
+    async fn fetch_and_upload_set_builder_image(
+        &self,
+        chain: &Chain<P>,
+        prover: &ProverObj,
+    ) -> Result<Digest> {
         let set_verifier_contract = SetVerifierService::new(
-            self.deployment().set_verifier_address,
-            self.provider.clone(),
+            chain.deployment.set_verifier_address,
+            chain.provider.clone(),
             Address::ZERO,
         );
 
@@ -540,10 +880,14 @@ where
         Ok(image_id)
     }
 
-    async fn fetch_and_upload_assessor_image(&self, prover: &ProverObj) -> Result<Digest> {
+    async fn fetch_and_upload_assessor_image(
+        &self,
+        chain: &Chain<P>,
+        prover: &ProverObj,
+    ) -> Result<Digest> {
         let boundless_market = BoundlessMarketService::new(
-            self.deployment().boundless_market_address,
-            self.provider.clone(),
+            chain.deployment.boundless_market_address,
+            chain.provider.clone(),
             Address::ZERO,
         );
         let (image_id, image_url_str) =
@@ -611,22 +955,337 @@ where
 
         let config = self.config_watcher.config.clone();
 
-        let loopback_blocks = {
+        let pricing_strategy = {
             let config = match config.lock_all() {
                 Ok(res) => res,
                 Err(err) => anyhow::bail!("Failed to lock config in watcher: {err:?}"),
             };
-            config.market.lookback_blocks
+            config.market.pricing_strategy.clone()
         };
 
-        // Create two cancellation tokens for graceful shutdown:
-        // 1. Non-critical tasks (order discovery, picking, monitoring) - cancelled immediately on shutdown signal
+        if self.pricing_strategies.get(&pricing_strategy).is_none() {
+            anyhow::bail!("Unknown pricing strategy configured: {pricing_strategy}");
+        }
+
+        // Create two cancellation tokens for graceful shutdown, started by a signal or a drain
+        // requested through the admin API:
+        // 1. Non-critical tasks (order discovery, picking, monitoring) - cancelled once no order is being locked
         // 2. Critical tasks (proving, aggregation, submission) - cancelled only after committed orders complete
         let non_critical_cancel_token = CancellationToken::new();
         let critical_cancel_token = CancellationToken::new();
 
+        if let Some(metrics_addr) = self.args.metrics_addr {
+            let handle = metrics::install_recorder()?;
+            let metrics_service = Arc::new(metrics::MetricsService::new(metrics_addr, handle));
+            let cloned_config = config.clone();
+            // Kept running until critical tasks complete, to allow monitoring during shutdown
+            let cancel_token = critical_cancel_token.clone();
+            supervisor_tasks.spawn(async move {
+                Supervisor::new(metrics_service, cloned_config, cancel_token)
+                    .spawn()
+                    .await
+                    .context("Failed to start metrics endpoint")?;
+                Ok(())
+            });
+        }
+
+        // Runtime controls shared with the admin API
+        let control = admin::BrokerControl::new(self.args.shadow);
+        if self.args.shadow {
+            tracing::warn!(
+                "Running in shadow mode, no lock or fulfillment transactions will be sent"
+            );
+        }
+
+        // Create a channel for new orders to be sent to the OrderPicker / from monitors
+        let (new_order_tx, new_order_rx) = mpsc::channel(NEW_ORDER_CHANNEL_CAPACITY);
+
+        // Create a broadcast channel for (chain ID, request ID) fulfillment notifications
+        let (fulfillment_tx, _) = tokio::sync::broadcast::channel(1000);
+
+        let throughput = throughput::ProvingThroughput::load(self.db.clone(), config.clone())
+            .await
+            .context("Failed to load proving throughput")?;
+
+        // Construct the prover object interface
+        let prover: provers::ProverObj = if risc0_zkvm::is_dev_mode() {
+            tracing::warn!("WARNING: Running the Broker in dev mode does not generate valid receipts. \
+            Receipts generated from this process are invalid and should never be used in production.");
+            Arc::new(provers::DefaultProver::new())
+        } else if !config.lock_all().context("Failed to lock config")?.prover.backends.is_empty() {
+            tracing::info!("Configured to run with multiple prover backends");
+            Arc::new(
+                provers::CompositeProver::from_config(config.clone())
+                    .context("Failed to construct prover backends")?
+                    .with_throughput(throughput.clone()),
+            )
+        } else if let (Some(bonsai_api_key), Some(bonsai_api_url)) =
+            (self.args.bonsai_api_key.as_ref(), self.args.bonsai_api_url.as_ref())
+        {
+            tracing::info!("Configured to run with Bonsai backend");
+            Arc::new(
+                provers::Bonsai::new(config.clone(), bonsai_api_url.as_ref(), bonsai_api_key)
+                    .context("Failed to construct Bonsai client")?,
+            )
+        } else if let Some(bento_api_url) = self.args.bento_api_url.as_ref() {
+            tracing::info!("Configured to run with Bento backend");
+
+            Arc::new(
+                provers::Bonsai::new(config.clone(), bento_api_url.as_ref(), "")
+                    .context("Failed to initialize Bento client")?,
+            )
+        } else {
+            Arc::new(provers::DefaultProver::new())
+        };
+
+        // Spin up the services of each chain, all feeding the shared order picker
+        let mut chain_handles = Vec::with_capacity(self.chains.len());
+        for chain in &self.chains {
+            let handles = self
+                .spawn_chain_services(
+                    chain,
+                    &mut supervisor_tasks,
+                    new_order_tx.clone(),
+                    fulfillment_tx.clone(),
+                    prover.clone(),
+                    control.clone(),
+                    throughput.clone(),
+                    non_critical_cancel_token.clone(),
+                    critical_cancel_token.clone(),
+                )
+                .await
+                .with_context(|| format!("Failed to start services of chain {}", chain.chain_id))?;
+            chain_handles.push(handles);
+        }
+
+        if let Some(admin_addr) = self.args.admin_addr {
+            let mut admin_service = admin::AdminService::new(
+                admin_addr,
+                self.args.admin_token.clone(),
+                self.db.clone(),
+                self.chains[0].provider.clone(),
+                self.chains[0].chain_id,
+                chain_handles[0].chain_monitor.clone(),
+                self.deployment().boundless_market_address,
+                control.clone(),
+            );
+            for (chain, handles) in self.chains.iter().zip(&chain_handles).skip(1) {
+                admin_service = admin_service.with_chain(
+                    chain.chain_id,
+                    chain.provider.clone(),
+                    handles.chain_monitor.clone(),
+                    chain.deployment.boundless_market_address,
+                );
+            }
+            let admin_service = Arc::new(admin_service);
+            let cloned_config = config.clone();
+            // Kept running until critical tasks complete, to allow monitoring during shutdown
+            let cancel_token = critical_cancel_token.clone();
+            supervisor_tasks.spawn(async move {
+                Supervisor::new(admin_service, cloned_config, cancel_token)
+                    .spawn()
+                    .await
+                    .context("Failed to start admin API")?;
+                Ok(())
+            });
+        }
+
+        // Spin up the order picker to pre-flight and find orders to lock
+        let mut chains = self.chains.iter().zip(chain_handles);
+        let (chain, handles) = chains.next().context("No chain configured")?;
+        let mut order_picker = order_picker::OrderPicker::new(
+            self.db.clone(),
+            config.clone(),
+            prover.clone(),
+            chain.chain_id,
+            chain.deployment.boundless_market_address,
+            chain.provider.clone(),
+            handles.chain_monitor,
+            new_order_rx,
+            handles.priced_orders_tx,
+            handles.stake_token_decimals,
+            handles.stake_price_oracle,
+            self.pricing_strategies.clone(),
+            control.clone(),
+            throughput.clone(),
+        );
+        for (chain, handles) in chains {
+            order_picker = order_picker.with_chain(
+                chain.chain_id,
+                chain.deployment.boundless_market_address,
+                chain.provider.clone(),
+                handles.chain_monitor,
+                handles.priced_orders_tx,
+                handles.stake_token_decimals,
+                handles.stake_price_oracle,
+            );
+        }
+        let order_picker = Arc::new(order_picker);
+        let cloned_config = config.clone();
+        let cancel_token = non_critical_cancel_token.clone();
+        supervisor_tasks.spawn(async move {
+            Supervisor::new(order_picker, cloned_config, cancel_token)
+                .spawn()
+                .await
+                .context("Failed to start order picker")?;
+            Ok(())
+        });
+
+        let proving_service = Arc::new(
+            proving::ProvingService::new(
+                self.db.clone(),
+                prover.clone(),
+                config.clone(),
+                fulfillment_tx.clone(),
+                throughput.clone(),
+            )
+            .await
+            .context("Failed to initialize proving service")?,
+        );
+
+        let cloned_config = config.clone();
+        let cancel_token = critical_cancel_token.clone();
+        supervisor_tasks.spawn(async move {
+            Supervisor::new(proving_service, cloned_config, cancel_token)
+                .spawn()
+                .await
+                .context("Failed to start proving service")?;
+            Ok(())
+        });
+
+        // Start the ReaperTask to check for expired committed orders
+        let reaper =
+            Arc::new(reaper::ReaperTask::new(self.db.clone(), config.clone(), prover.clone()));
+        let cloned_config = config.clone();
+        // Using critical cancel token to ensure no stuck expired jobs on shutdown
+        let cancel_token = critical_cancel_token.clone();
+        supervisor_tasks.spawn(async move {
+            Supervisor::new(reaper, cloned_config, cancel_token)
+                .spawn()
+                .await
+                .context("Failed to start reaper service")?;
+            Ok(())
+        });
+
+        // Monitor the different supervisor tasks and handle shutdown
+        let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler");
+        let mut sigint = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::interrupt())
+            .expect("Failed to install SIGINT handler");
+        loop {
+            tracing::info!("Waiting for supervisor tasks to complete...");
+            tokio::select! {
+                // Handle supervisor task results
+                Some(res) = supervisor_tasks.join_next() => {
+                    let status = match res {
+                        Err(join_err) if join_err.is_cancelled() => {
+                            tracing::info!("Tokio task exited with cancellation status: {join_err:?}");
+                            continue;
+                        }
+                        Err(join_err) => {
+                            tracing::error!("Tokio task exited with error status: {join_err:?}");
+                            anyhow::bail!("Task exited with error status: {join_err:?}")
+                        }
+                        Ok(status) => status,
+                    };
+                    match status {
+                        Err(err) => {
+                            tracing::error!("Task exited with error status: {err:?}");
+                            anyhow::bail!("Task exited with error status: {err:?}")
+                        }
+                        Ok(()) => {
+                            tracing::info!("Task exited with ok status");
+                        }
+                    }
+                }
+                // Handle shutdown signals
+                _ = tokio::signal::ctrl_c() => {
+                    tracing::info!("Received CTRL+C, starting graceful shutdown...");
+                    break;
+                }
+                _ = sigterm.recv() => {
+                    tracing::info!("Received SIGTERM, starting graceful shutdown...");
+                    break;
+                }
+                _ = sigint.recv() => {
+                    tracing::info!("Received SIGINT, starting graceful shutdown...");
+                    break;
+                }
+                _ = control.draining() => {
+                    tracing::info!("Drain requested, starting graceful shutdown...");
+                    break;
+                }
+            }
+        }
+
+        // Keep listening for signals while draining, so that a second signal forces an exit
+        let drain = async {
+            // Phase 1: Stop picking and locking new orders, waiting for in-flight locks to be
+            // recorded before cancelling the non-critical tasks
+            tracing::info!("Draining order picking and locking...");
+            control.start_drain();
+            while control.commits_in_flight() > 0 {
+                tracing::debug!(
+                    "Waiting for {} in-flight order locks",
+                    control.commits_in_flight()
+                );
+                tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+            }
+            tracing::info!(
+                "Cancelling non-critical tasks (order discovery, picking, monitoring)..."
+            );
+            non_critical_cancel_token.cancel();
+
+            // Phase 2: Wait for committed orders to complete, then cancel critical tasks
+            self.shutdown_and_cancel_critical_tasks(critical_cancel_token.clone()).await
+        };
+        tokio::select! {
+            res = drain => res?,
+            _ = tokio::signal::ctrl_c() => {
+                tracing::warn!("Received CTRL+C while draining, forcing shutdown");
+            }
+            _ = sigterm.recv() => {
+                tracing::warn!("Received SIGTERM while draining, forcing shutdown");
+            }
+            _ = sigint.recv() => {
+                tracing::warn!("Received SIGINT while draining, forcing shutdown");
+            }
+        }
+        non_critical_cancel_token.cancel();
+        critical_cancel_token.cancel();
+
+        Ok(())
+    }
+
+    /// Spawn the services of a single chain: chain monitor, market monitors, order monitor,
+    /// aggregator, submitter and treasury.
+    #[allow(clippy::too_many_arguments)]
+    async fn spawn_chain_services(
+        &self,
+        chain: &Chain<P>,
+        supervisor_tasks: &mut JoinSet<Result<()>>,
+        new_order_tx: mpsc::Sender<Box<OrderRequest>>,
+        fulfillment_tx: tokio::sync::broadcast::Sender<(u64, U256)>,
+        prover: ProverObj,
+        control: admin::BrokerControl,
+        throughput: throughput::ProvingThroughput,
+        non_critical_cancel_token: CancellationToken,
+        critical_cancel_token: CancellationToken,
+    ) -> Result<ChainHandles<P>> {
+        let config = self.config_watcher.config.clone();
+        let chain_id = chain.chain_id;
+        let market_addr = chain.deployment.boundless_market_address;
+
+        let loopback_blocks = {
+            let config = match config.lock_all() {
+                Ok(res) => res,
+                Err(err) => anyhow::bail!("Failed to lock config in watcher: {err:?}"),
+            };
+            config.market.lookback_blocks
+        };
+
         let chain_monitor = Arc::new(
-            chain_monitor::ChainMonitorService::new(self.provider.clone())
+            chain_monitor::ChainMonitorService::new(chain.provider.clone())
                 .await
                 .context("Failed to initialize chain monitor")?,
         );
@@ -643,44 +1302,61 @@ where
             Ok(())
         });
 
-        let chain_id = self.provider.get_chain_id().await.context("Failed to get chain ID")?;
-        let client = self
-            .deployment()
+        let client = chain
+            .deployment
             .order_stream_url
             .clone()
             .map(|url| -> Result<OrderStreamClient> {
                 let url = Url::parse(&url).context("Failed to parse order stream URL")?;
-                Ok(OrderStreamClient::new(
-                    url,
-                    self.deployment().boundless_market_address,
-                    chain_id,
-                ))
+                Ok(OrderStreamClient::new(url, market_addr, chain_id))
             })
             .transpose()?;
 
-        // Create a channel for new orders to be sent to the OrderPicker / from monitors
-        let (new_order_tx, new_order_rx) = mpsc::channel(NEW_ORDER_CHANNEL_CAPACITY);
-
-        // Create a broadcast channel for request fulfillment notifications
-        let (fulfillment_tx, _) = tokio::sync::broadcast::channel(1000);
+        let stake_token_decimals =
+            BoundlessMarketService::new(market_addr, chain.provider.clone(), Address::ZERO)
+                .stake_token_decimals()
+                .await
+                .context("Failed to get stake token decimals. Possible RPC error.")?;
+
+        let stake_price_oracle = match &chain.stake_price_oracle {
+            Some(conf) => {
+                let stake_token =
+                    BoundlessMarketService::new(market_addr, chain.provider.clone(), Address::ZERO)
+                        .stake_token_address()
+                        .await
+                        .context("Failed to get stake token address. Possible RPC error.")?;
+                let oracle = stake_price::from_config(
+                    conf,
+                    chain.provider.clone(),
+                    stake_token,
+                    stake_token_decimals,
+                )
+                .await
+                .context("Failed to initialize stake token price oracle")?;
+                Some(oracle)
+            }
+            None => None,
+        };
 
         // spin up a supervisor for the market monitor
         let market_monitor = Arc::new(market_monitor::MarketMonitor::new(
             loopback_blocks,
-            self.deployment().boundless_market_address,
-            self.provider.clone(),
+            market_addr,
+            chain.provider.clone(),
             self.db.clone(),
             chain_monitor.clone(),
             self.args.private_key.address(),
             client.clone(),
             new_order_tx.clone(),
-            fulfillment_tx.clone(),
+            fulfillment_tx,
+            config.clone(),
+            stake_token_decimals,
         ));
 
         let block_times =
             market_monitor.get_block_time().await.context("Failed to sample block times")?;
 
-        tracing::debug!("Estimated block time: {block_times}");
+        tracing::debug!("Estimated block time of chain {chain_id}: {block_times}");
 
         let cloned_config = config.clone();
         let cancel_token = non_critical_cancel_token.clone();
@@ -698,7 +1374,9 @@ where
                 Arc::new(offchain_market_monitor::OffchainMarketMonitor::new(
                     client_clone,
                     self.args.private_key.clone(),
-                    new_order_tx.clone(),
+                    new_order_tx,
+                    config.clone(),
+                    stake_token_decimals,
                 ));
             let cloned_config = config.clone();
             let cancel_token = non_critical_cancel_token.clone();
@@ -711,100 +1389,35 @@ where
             });
         }
 
-        // Construct the prover object interface
-        let prover: provers::ProverObj = if risc0_zkvm::is_dev_mode() {
-            tracing::warn!("WARNING: Running the Broker in dev mode does not generate valid receipts. \
-            Receipts generated from this process are invalid and should never be used in production.");
-            Arc::new(provers::DefaultProver::new())
-        } else if let (Some(bonsai_api_key), Some(bonsai_api_url)) =
-            (self.args.bonsai_api_key.as_ref(), self.args.bonsai_api_url.as_ref())
-        {
-            tracing::info!("Configured to run with Bonsai backend");
-            Arc::new(
-                provers::Bonsai::new(config.clone(), bonsai_api_url.as_ref(), bonsai_api_key)
-                    .context("Failed to construct Bonsai client")?,
-            )
-        } else if let Some(bento_api_url) = self.args.bento_api_url.as_ref() {
-            tracing::info!("Configured to run with Bento backend");
-
-            Arc::new(
-                provers::Bonsai::new(config.clone(), bento_api_url.as_ref(), "")
-                    .context("Failed to initialize Bento client")?,
-            )
-        } else {
-            Arc::new(provers::DefaultProver::new())
-        };
-
         let (pricing_tx, pricing_rx) = mpsc::channel(PRICING_CHANNEL_CAPACITY);
 
-        let stake_token_decimals = BoundlessMarketService::new(
-            self.deployment().boundless_market_address,
-            self.provider.clone(),
-            Address::ZERO,
-        )
-        .stake_token_decimals()
-        .await
-        .context("Failed to get stake token decimals. Possible RPC error.")?;
+        let prover_addr = self.args.private_key.address();
 
-        // Spin up the order picker to pre-flight and find orders to lock
-        let order_picker = Arc::new(order_picker::OrderPicker::new(
-            self.db.clone(),
+        // Shared by the order monitor and the submitter, which send from the same account.
+        let tx_manager = Arc::new(tx_manager::TxManager::new(
+            chain.provider.clone(),
             config.clone(),
-            prover.clone(),
-            self.deployment().boundless_market_address,
-            self.provider.clone(),
-            chain_monitor.clone(),
-            new_order_rx,
-            pricing_tx,
-            stake_token_decimals,
+            chain.reserved_nonces.clone(),
         ));
-        let cloned_config = config.clone();
-        let cancel_token = non_critical_cancel_token.clone();
-        supervisor_tasks.spawn(async move {
-            Supervisor::new(order_picker, cloned_config, cancel_token)
-                .spawn()
-                .await
-                .context("Failed to start order picker")?;
-            Ok(())
-        });
-
-        let proving_service = Arc::new(
-            proving::ProvingService::new(
-                self.db.clone(),
-                prover.clone(),
-                config.clone(),
-                fulfillment_tx.clone(),
-            )
-            .await
-            .context("Failed to initialize proving service")?,
-        );
-
-        let cloned_config = config.clone();
-        let cancel_token = critical_cancel_token.clone();
-        supervisor_tasks.spawn(async move {
-            Supervisor::new(proving_service, cloned_config, cancel_token)
-                .spawn()
-                .await
-                .context("Failed to start proving service")?;
-            Ok(())
-        });
-
-        let prover_addr = self.args.private_key.address();
 
         let order_monitor = Arc::new(order_monitor::OrderMonitor::new(
             self.db.clone(),
-            self.provider.clone(),
+            chain.provider.clone(),
+            chain_id,
             chain_monitor.clone(),
             config.clone(),
             block_times,
             prover_addr,
-            self.deployment().boundless_market_address,
+            market_addr,
             pricing_rx,
             stake_token_decimals,
             order_monitor::RpcRetryConfig {
                 retry_count: self.args.rpc_retry_max.into(),
                 retry_sleep_ms: self.args.rpc_retry_backoff,
             },
+            control.clone(),
+            throughput,
+            tx_manager.clone(),
         )?);
         let cloned_config = config.clone();
         let cancel_token = non_critical_cancel_token.clone();
@@ -816,8 +1429,8 @@ where
             Ok(())
         });
 
-        let set_builder_img_id = self.fetch_and_upload_set_builder_image(&prover).await?;
-        let assessor_img_id = self.fetch_and_upload_assessor_image(&prover).await?;
+        let set_builder_img_id = self.fetch_and_upload_set_builder_image(chain, &prover).await?;
+        let assessor_img_id = self.fetch_and_upload_assessor_image(chain, &prover).await?;
 
         let aggregator = Arc::new(
             aggregator::AggregatorService::new(
@@ -825,10 +1438,11 @@ where
                 chain_id,
                 set_builder_img_id,
                 assessor_img_id,
-                self.deployment().boundless_market_address,
+                market_addr,
                 prover_addr,
                 config.clone(),
                 prover.clone(),
+                control.clone(),
             )
             .await
             .context("Failed to initialize aggregator service")?,
@@ -845,31 +1459,21 @@ where
             Ok(())
         });
 
-        // Start the ReaperTask to check for expired committed orders
-        let reaper =
-            Arc::new(reaper::ReaperTask::new(self.db.clone(), config.clone(), prover.clone()));
-        let cloned_config = config.clone();
-        // Using critical cancel token to ensure no stuck expired jobs on shutdown
-        let cancel_token = critical_cancel_token.clone();
-        supervisor_tasks.spawn(async move {
-            Supervisor::new(reaper, cloned_config, cancel_token)
-                .spawn()
-                .await
-                .context("Failed to start reaper service")?;
-            Ok(())
-        });
-
         let submitter = Arc::new(submitter::Submitter::new(
             self.db.clone(),
             config.clone(),
-            prover.clone(),
-            self.provider.clone(),
-            self.deployment().set_verifier_address,
-            self.deployment().boundless_market_address,
+            prover,
+            chain.provider.clone(),
+            chain.deployment.set_verifier_address,
+            market_addr,
+            chain_id,
             set_builder_img_id,
+            assessor_img_id,
+            control.clone(),
+            tx_manager,
         )?);
         let cloned_config = config.clone();
-        let cancel_token = critical_cancel_token.clone();
+        let cancel_token = critical_cancel_token;
         supervisor_tasks.spawn(async move {
             Supervisor::new(submitter, cloned_config, cancel_token)
                 .with_retry_policy(RetryPolicy::CRITICAL_SERVICE)
@@ -879,61 +1483,36 @@ where
             Ok(())
         });
 
-        // Monitor the different supervisor tasks and handle shutdown
-        let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
-            .expect("Failed to install SIGTERM handler");
-        let mut sigint = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::interrupt())
-            .expect("Failed to install SIGINT handler");
-        loop {
-            tracing::info!("Waiting for supervisor tasks to complete...");
-            tokio::select! {
-                // Handle supervisor task results
-                Some(res) = supervisor_tasks.join_next() => {
-                    let status = match res {
-                        Err(join_err) if join_err.is_cancelled() => {
-                            tracing::info!("Tokio task exited with cancellation status: {join_err:?}");
-                            continue;
-                        }
-                        Err(join_err) => {
-                            tracing::error!("Tokio task exited with error status: {join_err:?}");
-                            anyhow::bail!("Task exited with error status: {join_err:?}")
-                        }
-                        Ok(status) => status,
-                    };
-                    match status {
-                        Err(err) => {
-                            tracing::error!("Task exited with error status: {err:?}");
-                            anyhow::bail!("Task exited with error status: {err:?}")
-                        }
-                        Ok(()) => {
-                            tracing::info!("Task exited with ok status");
-                        }
-                    }
-                }
-                // Handle shutdown signals
-                _ = tokio::signal::ctrl_c() => {
-                    tracing::info!("Received CTRL+C, starting graceful shutdown...");
-                    break;
-                }
-                _ = sigterm.recv() => {
-                    tracing::info!("Received SIGTERM, starting graceful shutdown...");
-                    break;
-                }
-                _ = sigint.recv() => {
-                    tracing::info!("Received SIGINT, starting graceful shutdown...");
-                    break;
-                }
-            }
+        let mut treasury = treasury::TreasuryTask::new(
+            self.db.clone(),
+            config.clone(),
+            chain.provider.clone(),
+            chain_id,
+            market_addr,
+            self.args.private_key.clone(),
+            stake_token_decimals,
+            control,
+        )?;
+        if let Some(conf) = &chain.treasury {
+            treasury = treasury.with_chain_conf(conf.clone());
         }
+        let treasury = Arc::new(treasury);
+        let cloned_config = config.clone();
+        let cancel_token = non_critical_cancel_token;
+        supervisor_tasks.spawn(async move {
+            Supervisor::new(treasury, cloned_config, cancel_token)
+                .spawn()
+                .await
+                .context("Failed to start treasury service")?;
+            Ok(())
+        });
 
-        // Phase 1: Cancel non-critical tasks immediately to stop taking new work
-        tracing::info!("Cancelling non-critical tasks (order discovery, picking, monitoring)...");
-        non_critical_cancel_token.cancel();
-
-        // Phase 2: Wait for committed orders to complete, then cancel critical tasks
-        self.shutdown_and_cancel_critical_tasks(critical_cancel_token).await?;
-
-        Ok(())
+        Ok(ChainHandles {
+            chain_monitor,
+            priced_orders_tx: pricing_tx,
+            stake_token_decimals,
+            stake_price_oracle,
+        })
     }
 
     async fn shutdown_and_cancel_critical_tasks(
@@ -1067,6 +1646,10 @@ pub mod test_utils {
                 rpc_retry_backoff: 200,
                 rpc_retry_cu: 1000,
                 log_json: false,
+                admin_addr: None,
+                admin_token: None,
+                metrics_addr: None,
+                shadow: false,
             };
             Self { args, provider: ctx.prover_provider.clone(), config_file }
         }
@@ -0,0 +1,361 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Priority fee bidding for lock transactions.
+//!
+//! When `market.lock_bid_max_profit_bps` is set, the [LockBidder] picks the priority fee of each
+//! lock transaction to outbid the lock transactions other provers sent in recent blocks, spending
+//! at most the configured share of the expected profit of the order. Otherwise the static
+//! `market.lockin_priority_gas` is used. Every bid and its outcome is recorded in the DB as a
+//! [LockBid], to allow tuning the bidding parameters.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use alloy::{
+    consensus::Transaction,
+    network::{Ethereum, TransactionResponse},
+    primitives::{Address, I256, U256},
+    providers::Provider,
+};
+use anyhow::{Context, Result};
+use boundless_market::contracts::IBoundlessMarket;
+use chrono::{serde::ts_seconds, DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{chain_monitor::ChainMonitorService, config::ConfigLock, db::DbObj, OrderRequest};
+
+/// Outcome of a lock transaction
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LockBidOutcome {
+    /// The lock transaction succeeded
+    Locked,
+    /// Another prover locked the request first
+    Outbid,
+    /// The lock transaction failed for another reason
+    Failed,
+}
+
+/// Priority fee bid for a lock transaction, and its outcome once sent
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LockBid {
+    pub order_id: String,
+    pub request_id: U256,
+    pub chain_id: u64,
+    /// Priority fee, in wei per gas, added to the priority fee suggested by the RPC node
+    pub priority_fee: u128,
+    /// Priority fee, in wei per gas, suggested by the RPC node
+    pub suggested_priority_fee: u128,
+    /// Highest priority fee, in wei per gas, of the competing lock transactions in recent blocks
+    pub competing_priority_fee: Option<u128>,
+    /// Max priority fee, in wei per gas, allowed by the expected profit of the order
+    pub max_priority_fee: Option<u128>,
+    /// Base fee, in wei per gas, of the latest observed block
+    pub base_fee: Option<u128>,
+    /// Expected profit of the order, net of gas, in wei
+    pub expected_profit: Option<I256>,
+    pub outcome: Option<LockBidOutcome>,
+    #[serde(with = "ts_seconds")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// Parameters of a single bid
+#[derive(Debug, Default)]
+struct BidInputs {
+    expected_profit: Option<I256>,
+    /// Base fees of recently observed blocks, oldest first
+    recent_base_fees: Vec<u128>,
+    suggested_priority_fee: u128,
+    competing_priority_fees: Vec<u128>,
+    lock_gas: u64,
+    max_profit_bps: u64,
+    outbid_bps: u64,
+    /// Priority fee to add at least, from `lockin_priority_gas`
+    min_priority_fee: u128,
+}
+
+/// Priority fee to add to the suggested priority fee, and the max allowed by the profit
+fn compute_bid(inputs: &BidInputs) -> (u128, Option<u128>) {
+    // Budget for the priority fee, after accounting for base fees rising back to their recent
+    // highs, as the expected profit is net of gas at the current gas price.
+    let max_priority_fee = inputs.expected_profit.map(|profit| {
+        if profit <= I256::ZERO || inputs.lock_gas == 0 {
+            return 0;
+        }
+        let budget = profit.into_raw() * U256::from(inputs.max_profit_bps) / U256::from(10_000);
+        let base_fee_rise =
+            match (inputs.recent_base_fees.iter().max(), inputs.recent_base_fees.last()) {
+                (Some(max), Some(latest)) => max.saturating_sub(*latest),
+                _ => 0,
+            };
+        let budget = budget.saturating_sub(U256::from(base_fee_rise) * U256::from(inputs.lock_gas));
+        u128::try_from(budget / U256::from(inputs.lock_gas)).unwrap_or(u128::MAX)
+    });
+
+    // Outbid the highest competing fee, which is paid in full, on top of the suggested fee.
+    let target = inputs
+        .competing_priority_fees
+        .iter()
+        .max()
+        .map(|fee| {
+            let outbid = fee.saturating_mul(10_000 + inputs.outbid_bps as u128) / 10_000;
+            outbid.saturating_add(1).saturating_sub(inputs.suggested_priority_fee)
+        })
+        .unwrap_or(0);
+
+    let priority_fee = match max_priority_fee {
+        Some(max) => target.min(max),
+        None => target,
+    };
+    (priority_fee.max(inputs.min_priority_fee), max_priority_fee)
+}
+
+/// Priority fees of the lock transactions sent by other provers, by block
+#[derive(Default)]
+struct CompetingLocks {
+    /// Last block scanned for lock transactions
+    scanned_to: Option<u64>,
+    priority_fees: BTreeMap<u64, Vec<u128>>,
+}
+
+/// Picks the priority fee of lock transactions
+#[derive(Clone)]
+pub(crate) struct LockBidder<P> {
+    db: DbObj,
+    config: ConfigLock,
+    provider: Arc<P>,
+    chain_monitor: Arc<ChainMonitorService<P>>,
+    chain_id: u64,
+    market_addr: Address,
+    prover_addr: Address,
+    competing: Arc<Mutex<CompetingLocks>>,
+}
+
+impl<P> LockBidder<P>
+where
+    P: Provider<Ethereum>,
+{
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        db: DbObj,
+        config: ConfigLock,
+        provider: Arc<P>,
+        chain_monitor: Arc<ChainMonitorService<P>>,
+        chain_id: u64,
+        market_addr: Address,
+        prover_addr: Address,
+    ) -> Self {
+        Self {
+            db,
+            config,
+            provider,
+            chain_monitor,
+            chain_id,
+            market_addr,
+            prover_addr,
+            competing: Arc::new(Mutex::new(CompetingLocks::default())),
+        }
+    }
+
+    /// Pick the priority fee of the lock transaction of the order
+    ///
+    /// Returns `None` when bidding is disabled, in which case `lockin_priority_gas` applies.
+    pub(crate) async fn bid(&self, order: &OrderRequest) -> Result<Option<LockBid>> {
+        let (max_profit_bps, outbid_bps, lookback_blocks, lock_gas, min_priority_fee) = {
+            let config = self.config.lock_all().context("Failed to read config")?;
+            (
+                config.market.lock_bid_max_profit_bps,
+                config.market.lock_bid_outbid_bps,
+                config.market.lock_bid_lookback_blocks,
+                config.market.lockin_gas_estimate,
+                config.market.lockin_priority_gas.unwrap_or_default(),
+            )
+        };
+        let Some(max_profit_bps) = max_profit_bps else {
+            return Ok(None);
+        };
+
+        let head = self.chain_monitor.current_block_number().await?;
+        let competing_priority_fees = self.competing_priority_fees(head, lookback_blocks).await?;
+        let suggested_priority_fee = self
+            .provider
+            .estimate_eip1559_fees()
+            .await
+            .context("Failed to estimate priority fee")?
+            .max_priority_fee_per_gas;
+        let recent_base_fees = self.chain_monitor.recent_base_fees();
+
+        let inputs = BidInputs {
+            expected_profit: order.expected_profit,
+            recent_base_fees,
+            suggested_priority_fee,
+            competing_priority_fees,
+            lock_gas,
+            max_profit_bps,
+            outbid_bps,
+            min_priority_fee: min_priority_fee.into(),
+        };
+        let (priority_fee, max_priority_fee) = compute_bid(&inputs);
+
+        let bid = LockBid {
+            order_id: order.id(),
+            request_id: order.request.id,
+            chain_id: self.chain_id,
+            priority_fee,
+            suggested_priority_fee,
+            competing_priority_fee: inputs.competing_priority_fees.iter().max().copied(),
+            max_priority_fee,
+            base_fee: inputs.recent_base_fees.last().copied(),
+            expected_profit: order.expected_profit,
+            outcome: None,
+            created_at: Utc::now(),
+        };
+        tracing::debug!(
+            "Bidding priority fee {} (suggested {}, competing {:?}, max {:?}) to lock order {}",
+            bid.priority_fee,
+            bid.suggested_priority_fee,
+            bid.competing_priority_fee,
+            bid.max_priority_fee,
+            bid.order_id
+        );
+        Ok(Some(bid))
+    }
+
+    /// Record the outcome of a bid, logging rather than failing on DB errors
+    pub(crate) async fn record(&self, bid: LockBid, outcome: LockBidOutcome) {
+        let bid = LockBid { outcome: Some(outcome), ..bid };
+        if let Err(err) = self.db.add_lock_bid(&bid).await {
+            tracing::error!("Failed to record lock bid for order {}: {err:?}", bid.order_id);
+        }
+    }
+
+    /// Priority fees of the lock transactions other provers sent in the recent blocks
+    async fn competing_priority_fees(&self, head: u64, lookback_blocks: u64) -> Result<Vec<u128>> {
+        let from_block = head.saturating_sub(lookback_blocks.saturating_sub(1));
+        let mut competing = self.competing.lock().await;
+
+        // Only scan the blocks not seen by a previous bid
+        let scan_from =
+            competing.scanned_to.map_or(from_block, |scanned| (scanned + 1).max(from_block));
+        if scan_from <= head {
+            let logs = IBoundlessMarket::new(self.market_addr, self.provider.clone())
+                .RequestLocked_filter()
+                .from_block(scan_from)
+                .to_block(head)
+                .query()
+                .await
+                .context("Failed to query lock events")?;
+
+            let mut base_fees = BTreeMap::new();
+            for (event, log) in logs {
+                if event.prover == self.prover_addr {
+                    continue;
+                }
+                let (Some(tx_hash), Some(block_number)) = (log.transaction_hash, log.block_number)
+                else {
+                    continue;
+                };
+                let Some(tx) = self
+                    .provider
+                    .get_transaction_by_hash(tx_hash)
+                    .await
+                    .context("Failed to get lock transaction")?
+                else {
+                    continue;
+                };
+                // Provers can lock through contracts, so only direct calls reveal the fee paid
+                if tx.to() != Some(self.market_addr) || tx.from() == self.prover_addr {
+                    continue;
+                }
+                let base_fee = match base_fees.get(&block_number) {
+                    Some(base_fee) => *base_fee,
+                    None => {
+                        let base_fee = self
+                            .provider
+                            .get_block_by_number(block_number.into())
+                            .await
+                            .context("Failed to get block of lock transaction")?
+                            .and_then(|block| block.header.base_fee_per_gas)
+                            .unwrap_or_default();
+                        base_fees.insert(block_number, base_fee);
+                        base_fee
+                    }
+                };
+                if let Some(priority_fee) = tx.effective_tip_per_gas(base_fee) {
+                    competing.priority_fees.entry(block_number).or_default().push(priority_fee);
+                }
+            }
+            competing.scanned_to = Some(head);
+        }
+
+        competing.priority_fees = competing.priority_fees.split_off(&from_block);
+        Ok(competing.priority_fees.values().flatten().copied().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inputs() -> BidInputs {
+        BidInputs {
+            expected_profit: Some(I256::try_from(1_000_000_000_000_000u64).unwrap()),
+            lock_gas: 200_000,
+            max_profit_bps: 1_000,
+            outbid_bps: 1_000,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn no_competition() {
+        let (priority_fee, max) = compute_bid(&inputs());
+        assert_eq!(priority_fee, 0);
+        // 10% of 0.001 ETH over 200k gas
+        assert_eq!(max, Some(500_000_000));
+
+        let (priority_fee, _) = compute_bid(&BidInputs { min_priority_fee: 100, ..inputs() });
+        assert_eq!(priority_fee, 100);
+    }
+
+    #[test]
+    fn outbids_competing_locks() {
+        let inputs = BidInputs {
+            competing_priority_fees: vec![10_000_000, 100_000_000, 50_000_000],
+            suggested_priority_fee: 1_000_000,
+            ..inputs()
+        };
+        let (priority_fee, _) = compute_bid(&inputs);
+        assert_eq!(priority_fee, 110_000_001 - 1_000_000);
+    }
+
+    #[test]
+    fn capped_by_profit() {
+        let inputs = BidInputs { competing_priority_fees: vec![1_000_000_000], ..inputs() };
+        let (priority_fee, max) = compute_bid(&inputs);
+        assert_eq!(Some(priority_fee), max);
+        assert_eq!(priority_fee, 500_000_000);
+
+        // Base fees rising back to their recent highs eat into the budget
+        let inputs = BidInputs { recent_base_fees: vec![300_000_000, 100_000_000], ..inputs };
+        let (priority_fee, _) = compute_bid(&inputs);
+        assert_eq!(priority_fee, 300_000_000);
+
+        // No bid above the configured minimum without a profit
+        let inputs =
+            BidInputs { expected_profit: Some(I256::ZERO), min_priority_fee: 100, ..inputs };
+        let (priority_fee, max) = compute_bid(&inputs);
+        assert_eq!((priority_fee, max), (100, Some(0)));
+    }
+}
@@ -36,6 +36,7 @@ use tokio_util::sync::CancellationToken;
 
 use crate::{
     chain_monitor::ChainMonitorService,
+    config::ConfigLock,
     db::{DbError, DbObj},
     errors::{impl_coded_debug, CodedError},
     task::{RetryRes, RetryTask, SupervisorErr},
@@ -82,7 +83,9 @@ pub struct MarketMonitor<P> {
     prover_addr: Address,
     order_stream: Option<OrderStreamClient>,
     new_order_tx: tokio::sync::mpsc::Sender<Box<OrderRequest>>,
-    fulfillment_tx: tokio::sync::broadcast::Sender<U256>,
+    fulfillment_tx: tokio::sync::broadcast::Sender<(u64, U256)>,
+    config: ConfigLock,
+    stake_token_decimals: u8,
 }
 
 sol! {
@@ -108,7 +111,9 @@ where
         prover_addr: Address,
         order_stream: Option<OrderStreamClient>,
         new_order_tx: tokio::sync::mpsc::Sender<Box<OrderRequest>>,
-        fulfillment_tx: tokio::sync::broadcast::Sender<U256>,
+        fulfillment_tx: tokio::sync::broadcast::Sender<(u64, U256)>,
+        config: ConfigLock,
+        stake_token_decimals: u8,
     ) -> Self {
         Self {
             lookback_blocks,
@@ -120,6 +125,8 @@ where
             order_stream,
             new_order_tx,
             fulfillment_tx,
+            config,
+            stake_token_decimals,
         }
     }
 
@@ -153,6 +160,8 @@ where
         provider: Arc<P>,
         chain_monitor: Arc<ChainMonitorService<P>>,
         new_order_tx: &tokio::sync::mpsc::Sender<Box<OrderRequest>>,
+        config: &ConfigLock,
+        stake_token_decimals: u8,
     ) -> Result<u64, MarketMonitorErr> {
         let current_block = chain_monitor.current_block_number().await?;
         let chain_id = provider.get_chain_id().await.context("Failed to get chain id")?;
@@ -210,10 +219,11 @@ where
                 continue;
             }
 
-            let fulfillment_type = match req_status {
-                RequestStatus::Locked => FulfillmentType::FulfillAfterLockExpire,
-                _ => FulfillmentType::LockAndFulfill,
-            };
+            let fulfillment_type = FulfillmentType::for_unlocked_request(
+                &event.request,
+                config,
+                stake_token_decimals,
+            )?;
 
             tracing::info!(
                 "Found open order: {request_id:x} with request status: {req_status:?}, preparing to process with fulfillment type: {fulfillment_type:?}",
@@ -243,6 +253,8 @@ where
         market_addr: Address,
         provider: Arc<P>,
         new_order_tx: mpsc::Sender<Box<OrderRequest>>,
+        config: ConfigLock,
+        stake_token_decimals: u8,
         cancel_token: CancellationToken,
     ) -> Result<(), MarketMonitorErr> {
         let chain_id = provider.get_chain_id().await.context("Failed to get chain id")?;
@@ -272,6 +284,8 @@ where
                                 market_addr,
                                 chain_id,
                                 &new_order_tx,
+                                &config,
+                                stake_token_decimals,
                             )
                             .await
                             {
@@ -330,6 +344,7 @@ where
                             );
                             if let Err(e) = db
                                 .set_request_locked(
+                                    chain_id,
                                     U256::from(event.requestId),
                                     &event.prover.to_string(),
                                     log.block_number.unwrap(),
@@ -406,9 +421,10 @@ where
         market_addr: Address,
         provider: Arc<P>,
         db: DbObj,
-        fulfillment_tx: tokio::sync::broadcast::Sender<U256>,
+        fulfillment_tx: tokio::sync::broadcast::Sender<(u64, U256)>,
         cancel_token: CancellationToken,
     ) -> Result<(), MarketMonitorErr> {
+        let chain_id = provider.get_chain_id().await.context("Failed to get chain id")?;
         let market = BoundlessMarketService::new(market_addr, provider.clone(), Address::ZERO);
         let event = market
             .instance()
@@ -427,6 +443,7 @@ where
                             tracing::debug!("Detected request fulfilled 0x{:x}", event.requestId);
                             if let Err(e) = db
                                 .set_request_fulfilled(
+                                    chain_id,
                                     U256::from(event.requestId),
                                     log.block_number.unwrap(),
                                 )
@@ -446,7 +463,7 @@ where
                             }
 
                             // Broadcast the fulfillment event to any listeners
-                            if let Err(e) = fulfillment_tx.send(U256::from(event.requestId)) {
+                            if let Err(e) = fulfillment_tx.send((chain_id, U256::from(event.requestId))) {
                                 tracing::trace!("No fulfillment listeners for request 0x{:x}: {}", event.requestId, e);
                             }
                         }
@@ -474,6 +491,8 @@ where
         market_addr: Address,
         chain_id: u64,
         new_order_tx: &tokio::sync::mpsc::Sender<Box<OrderRequest>>,
+        config: &ConfigLock,
+        stake_token_decimals: u8,
     ) -> Result<()> {
         tracing::info!("Detected new on-chain request 0x{:x}", event.requestId);
         // Check the request id flag to determine if the request is smart contract signed. If so we verify the
@@ -509,10 +528,12 @@ where
             return Ok(()); // Return early without propagating the error if signature verification fails.
         }
 
+        let fulfillment_type =
+            FulfillmentType::for_unlocked_request(&event.request, config, stake_token_decimals)?;
         let new_order = OrderRequest::new(
             event.request.clone(),
             event.clientSignature.clone(),
-            FulfillmentType::LockAndFulfill,
+            fulfillment_type,
             market_addr,
             chain_id,
         );
@@ -542,6 +563,8 @@ where
         let db = self.db.clone();
         let order_stream = self.order_stream.clone();
         let fulfillment_tx = self.fulfillment_tx.clone();
+        let config = self.config.clone();
+        let stake_token_decimals = self.stake_token_decimals;
 
         Box::pin(async move {
             tracing::info!("Starting up market monitor");
@@ -552,6 +575,8 @@ where
                 provider.clone(),
                 chain_monitor,
                 &new_order_tx,
+                &config,
+                stake_token_decimals,
             )
             .await
             .map_err(|err| {
@@ -564,6 +589,8 @@ where
                     market_addr,
                     provider.clone(),
                     new_order_tx.clone(),
+                    config,
+                    stake_token_decimals,
                     cancel_token.clone()
                 ),
                 Self::monitor_order_fulfillments(
@@ -678,16 +705,64 @@ mod tests {
         tokio::spawn(chain_monitor.spawn(Default::default()));
 
         let (order_tx, mut order_rx) = tokio::sync::mpsc::channel(16);
-        let orders =
-            MarketMonitor::find_open_orders(2, market_address, provider, chain_monitor, &order_tx)
-                .await
-                .unwrap();
+        let orders = MarketMonitor::find_open_orders(
+            2,
+            market_address,
+            provider,
+            chain_monitor,
+            &order_tx,
+            &ConfigLock::default(),
+            18,
+        )
+        .await
+        .unwrap();
         assert_eq!(orders, 1);
 
-        order_rx.try_recv().unwrap();
+        let order = order_rx.try_recv().unwrap();
+        assert_eq!(order.fulfillment_type, FulfillmentType::LockAndFulfill);
         assert!(order_rx.try_recv().is_err());
     }
 
+    #[test]
+    fn unlocked_request_fulfillment_type() {
+        let request = ProofRequest {
+            id: U256::from(1),
+            requirements: Requirements::new(
+                Digest::ZERO,
+                Predicate { predicateType: PredicateType::PrefixMatch, data: Default::default() },
+            ),
+            imageUrl: "test".to_string(),
+            input: RequestInput { inputType: RequestInputType::Url, data: Default::default() },
+            offer: Offer {
+                minPrice: U256::from(1),
+                maxPrice: U256::from(10),
+                biddingStart: now_timestamp(),
+                timeout: 1000,
+                lockTimeout: 1000,
+                rampUpPeriod: 1,
+                lockStake: U256::from(500_000),
+            },
+        };
+
+        let config = ConfigLock::default();
+        assert_eq!(
+            FulfillmentType::for_unlocked_request(&request, &config, 6).unwrap(),
+            FulfillmentType::LockAndFulfill
+        );
+
+        config.load_write().unwrap().market.fulfill_without_locking_max_stake = Some("0.5".into());
+        assert_eq!(
+            FulfillmentType::for_unlocked_request(&request, &config, 6).unwrap(),
+            FulfillmentType::FulfillWithoutLocking
+        );
+
+        config.load_write().unwrap().market.fulfill_without_locking_max_stake = Some("0.1".into());
+        assert_eq!(
+            FulfillmentType::for_unlocked_request(&request, &config, 6).unwrap(),
+            FulfillmentType::LockAndFulfill
+        );
+    }
+
     #[tokio::test]
     async fn block_times() {
         let anvil = Anvil::new().spawn();
@@ -717,6 +792,8 @@ mod tests {
             None,
             order_tx,
             fulfillment_tx,
+            ConfigLock::default(),
+            18,
         );
 
         let block_time = market_monitor.get_block_time().await.unwrap();
@@ -0,0 +1,242 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Prometheus metrics for the broker pipeline.
+//!
+//! The recording helpers are no-ops until a recorder is installed, which only happens when the
+//! broker is started with a metrics listen address.
+
+use std::{net::SocketAddr, time::Duration};
+
+use axum::{extract::State, routing::get, Router};
+use metrics_exporter_prometheus::{BuildError, Matcher, PrometheusBuilder, PrometheusHandle};
+use thiserror::Error;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    errors::{impl_coded_debug, CodedError},
+    task::{RetryRes, RetryTask, SupervisorErr},
+    FulfillmentType, SkipReason,
+};
+
+const METRICS_PATH: &str = "/metrics";
+
+/// Interval between drains of the histogram buffers, bounds memory use when not being scraped
+const UPKEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+const ORDERS_SEEN: &str = "broker_orders_seen_total";
+const ORDERS_SKIPPED: &str = "broker_orders_skipped_total";
+const PREFLIGHT_DURATION: &str = "broker_preflight_duration_seconds";
+const ORDER_LOCKS: &str = "broker_order_locks_total";
+const PROOF_SECONDS_PER_MCYCLE: &str = "broker_proof_seconds_per_mcycle";
+const BATCH_SIZE: &str = "broker_batch_size";
+const BATCH_TIME_TO_SUBMIT: &str = "broker_batch_time_to_submit_seconds";
+const SUBMISSION_GAS_USED: &str = "broker_submission_gas_used";
+const SUPERVISOR_RESTARTS: &str = "broker_supervisor_restarts_total";
+
+#[derive(Error)]
+pub enum MetricsErr {
+    #[error("{code} Failed to install metrics recorder: {0}", code = self.code())]
+    RecorderErr(#[from] BuildError),
+
+    #[error("{code} Failed to bind metrics endpoint to {0}: {1}", code = self.code())]
+    BindErr(SocketAddr, std::io::Error),
+
+    #[error("{code} Metrics server error: {0}", code = self.code())]
+    ServerErr(std::io::Error),
+}
+
+impl_coded_debug!(MetricsErr);
+
+impl CodedError for MetricsErr {
+    fn code(&self) -> &str {
+        match self {
+            MetricsErr::RecorderErr(_) => "[B-MET-001]",
+            MetricsErr::BindErr(..) => "[B-MET-002]",
+            MetricsErr::ServerErr(_) => "[B-MET-500]",
+        }
+    }
+}
+
+fn builder() -> PrometheusBuilder {
+    let buckets: [(&str, &[f64]); 5] = [
+        (PREFLIGHT_DURATION, &[0.1, 0.5, 1.0, 2.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0]),
+        (PROOF_SECONDS_PER_MCYCLE, &[0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.0, 5.0, 10.0]),
+        (BATCH_SIZE, &[1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0, 128.0]),
+        (BATCH_TIME_TO_SUBMIT, &[10.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1200.0, 1800.0, 3600.0]),
+        (
+            SUBMISSION_GAS_USED,
+            &[100_000.0, 250_000.0, 500_000.0, 1_000_000.0, 2_000_000.0, 5_000_000.0, 10_000_000.0],
+        ),
+    ];
+
+    buckets.into_iter().fold(PrometheusBuilder::new(), |builder, (name, values)| {
+        builder
+            .set_buckets_for_metric(Matcher::Full(name.into()), values)
+            .expect("histogram buckets must not be empty")
+    })
+}
+
+/// Install the global Prometheus recorder, returning the handle used to render the metrics
+pub(crate) fn install_recorder() -> Result<PrometheusHandle, MetricsErr> {
+    Ok(builder().install_recorder()?)
+}
+
+/// An order was received by the order picker
+pub(crate) fn record_order_seen(fulfillment_type: FulfillmentType) {
+    metrics::counter!(ORDERS_SEEN, "fulfillment_type" => format!("{fulfillment_type:?}"))
+        .increment(1);
+}
+
+/// An order was skipped, `stage` is the broker service that made the decision
+pub(crate) fn record_order_skipped(stage: &'static str, reason: SkipReason) {
+    metrics::counter!(ORDERS_SKIPPED, "stage" => stage, "reason" => reason.as_str()).increment(1);
+}
+
+pub(crate) fn record_preflight_duration(duration: Duration) {
+    metrics::histogram!(PREFLIGHT_DURATION).record(duration.as_secs_f64());
+}
+
+pub(crate) fn record_lock(success: bool) {
+    let result = if success { "success" } else { "failure" };
+    metrics::counter!(ORDER_LOCKS, "result" => result).increment(1);
+}
+
+pub(crate) fn record_proof_duration(elapsed_secs: f64, total_cycles: u64) {
+    if total_cycles == 0 {
+        return;
+    }
+    let mcycles = total_cycles as f64 / 1_000_000.0;
+    metrics::histogram!(PROOF_SECONDS_PER_MCYCLE).record(elapsed_secs / mcycles);
+}
+
+/// A batch was submitted on chain
+pub(crate) fn record_batch_submitted(size: usize, time_to_submit: Duration, gas_used: u64) {
+    metrics::histogram!(BATCH_SIZE).record(size as f64);
+    metrics::histogram!(BATCH_TIME_TO_SUBMIT).record(time_to_submit.as_secs_f64());
+    metrics::histogram!(SUBMISSION_GAS_USED).record(gas_used as f64);
+}
+
+pub(crate) fn record_supervisor_restart<T>() {
+    metrics::counter!(SUPERVISOR_RESTARTS, "task" => task_name::<T>()).increment(1);
+}
+
+/// Short name of a task type, without its module path or generic parameters
+fn task_name<T>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    let name = name.split('<').next().unwrap_or(name);
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+#[derive(Clone)]
+pub struct MetricsService {
+    addr: SocketAddr,
+    handle: PrometheusHandle,
+}
+
+impl MetricsService {
+    pub(crate) fn new(addr: SocketAddr, handle: PrometheusHandle) -> Self {
+        Self { addr, handle }
+    }
+
+    fn app(&self) -> Router {
+        Router::new().route(METRICS_PATH, get(render)).with_state(self.handle.clone())
+    }
+}
+
+impl RetryTask for MetricsService {
+    type Error = MetricsErr;
+    fn spawn(&self, cancel_token: CancellationToken) -> RetryRes<Self::Error> {
+        let service = self.clone();
+
+        Box::pin(async move {
+            let listener = tokio::net::TcpListener::bind(service.addr)
+                .await
+                .map_err(|err| SupervisorErr::Fault(MetricsErr::BindErr(service.addr, err)))?;
+            tracing::info!("Metrics endpoint listening on: {}{METRICS_PATH}", service.addr);
+
+            let handle = service.handle.clone();
+            let upkeep_cancel = cancel_token.clone();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(UPKEEP_INTERVAL);
+                loop {
+                    tokio::select! {
+                        _ = interval.tick() => handle.run_upkeep(),
+                        _ = upkeep_cancel.cancelled() => break,
+                    }
+                }
+            });
+
+            axum::serve(listener, service.app())
+                .with_graceful_shutdown(async move { cancel_token.cancelled().await })
+                .await
+                .map_err(|err| SupervisorErr::Recover(MetricsErr::ServerErr(err)))?;
+
+            tracing::debug!("Metrics endpoint shut down");
+            Ok(())
+        })
+    }
+}
+
+async fn render(State(handle): State<PrometheusHandle>) -> String {
+    handle.render()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct ExampleTask<T>(T);
+
+    #[test]
+    fn task_names() {
+        assert_eq!(task_name::<MetricsService>(), "MetricsService");
+        assert_eq!(task_name::<ExampleTask<Vec<u8>>>(), "ExampleTask");
+    }
+
+    #[tokio::test]
+    async fn metrics_endpoint() {
+        let recorder = builder().build_recorder();
+        let handle = recorder.handle();
+        metrics::with_local_recorder(&recorder, || {
+            record_order_seen(FulfillmentType::LockAndFulfill);
+            record_order_skipped("order_picker", SkipReason::Expired);
+            record_lock(false);
+            record_proof_duration(4.0, 2_000_000);
+            record_batch_submitted(3, Duration::from_secs(90), 400_000);
+            record_supervisor_restart::<ExampleTask<u8>>();
+        });
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = MetricsService::new(addr, handle).app();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let body = reqwest::get(format!("http://{addr}{METRICS_PATH}"))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+
+        assert!(body.contains(r#"broker_orders_seen_total{fulfillment_type="LockAndFulfill"} 1"#));
+        assert!(body
+            .contains(r#"broker_orders_skipped_total{stage="order_picker",reason="expired"} 1"#));
+        assert!(body.contains(r#"broker_order_locks_total{result="failure"} 1"#));
+        assert!(body.contains(r#"broker_proof_seconds_per_mcycle_bucket{le="2"} 1"#));
+        assert!(body.contains(r#"broker_batch_size_sum 3"#));
+        assert!(body.contains(r#"broker_submission_gas_used_bucket{le="500000"} 1"#));
+        assert!(body.contains(r#"broker_supervisor_restarts_total{task="ExampleTask"} 1"#));
+    }
+}
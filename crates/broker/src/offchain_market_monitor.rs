@@ -18,6 +18,7 @@ use boundless_market::order_stream_client::{order_stream, OrderStreamClient};
 use futures_util::StreamExt;
 
 use crate::{
+    config::ConfigLock,
     errors::CodedError,
     impl_coded_debug,
     task::{RetryRes, RetryTask, SupervisorErr},
@@ -54,6 +55,8 @@ pub struct OffchainMarketMonitor {
     client: OrderStreamClient,
     signer: PrivateKeySigner,
     new_order_tx: tokio::sync::mpsc::Sender<Box<OrderRequest>>,
+    config: ConfigLock,
+    stake_token_decimals: u8,
 }
 
 impl OffchainMarketMonitor {
@@ -61,14 +64,18 @@ impl OffchainMarketMonitor {
         client: OrderStreamClient,
         signer: PrivateKeySigner,
         new_order_tx: tokio::sync::mpsc::Sender<Box<OrderRequest>>,
+        config: ConfigLock,
+        stake_token_decimals: u8,
     ) -> Self {
-        Self { client, signer, new_order_tx }
+        Self { client, signer, new_order_tx, config, stake_token_decimals }
     }
 
     async fn monitor_orders(
         client: OrderStreamClient,
         signer: &impl Signer,
         new_order_tx: tokio::sync::mpsc::Sender<Box<OrderRequest>>,
+        config: &ConfigLock,
+        stake_token_decimals: u8,
         cancel_token: CancellationToken,
     ) -> Result<(), OffchainMarketMonitorErr> {
         tracing::debug!("Connecting to off-chain market: {}", client.base_url);
@@ -89,10 +96,15 @@ impl OffchainMarketMonitor {
                                 order_data.order.request.id
                             );
 
+                            let fulfillment_type = FulfillmentType::for_unlocked_request(
+                                &order_data.order.request,
+                                config,
+                                stake_token_decimals,
+                            )?;
                             let new_order = OrderRequest::new(
                                 order_data.order.request,
                                 order_data.order.signature.as_bytes().into(),
-                                FulfillmentType::LockAndFulfill,
+                                fulfillment_type,
                                 client.boundless_market_address,
                                 client.chain_id,
                             );
@@ -129,12 +141,21 @@ impl RetryTask for OffchainMarketMonitor {
         let client = self.client.clone();
         let signer = self.signer.clone();
         let new_order_tx = self.new_order_tx.clone();
+        let config = self.config.clone();
+        let stake_token_decimals = self.stake_token_decimals;
 
         Box::pin(async move {
             tracing::info!("Starting up offchain market monitor");
-            Self::monitor_orders(client, &signer, new_order_tx, cancel_token)
-                .await
-                .map_err(SupervisorErr::Recover)?;
+            Self::monitor_orders(
+                client,
+                &signer,
+                new_order_tx,
+                &config,
+                stake_token_decimals,
+                cancel_token,
+            )
+            .await
+            .map_err(SupervisorErr::Recover)?;
             Ok(())
         })
     }
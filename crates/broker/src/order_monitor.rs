@@ -15,13 +15,21 @@
 use crate::chain_monitor::ChainHead;
 use crate::OrderRequest;
 use crate::{
+    admin::BrokerControl,
     chain_monitor::ChainMonitorService,
     config::{ConfigLock, OrderCommitmentPriority},
     db::DbObj,
     errors::CodedError,
-    impl_coded_debug, now_timestamp,
+    impl_coded_debug,
+    ledger::{self, LedgerEntry, LedgerKind},
+    lock_bidding::{LockBidOutcome, LockBidder},
+    metrics, now_timestamp,
+    prioritization::sort_orders_by_priority_and_mode,
+    shadow::{self, ShadowAction, ShadowDecision},
     task::{RetryRes, RetryTask, SupervisorErr},
-    utils, FulfillmentType, Order,
+    throughput::ProvingThroughput,
+    tx_manager::{TxManager, TxPolicy},
+    utils, FulfillmentType, Order, SkipReason,
 };
 use alloy::{
     network::Ethereum,
@@ -30,6 +38,7 @@ use alloy::{
         Address, U256,
     },
     providers::{Provider, WalletProvider},
+    rpc::types::TransactionReceipt,
 };
 use anyhow::{Context, Result};
 use boundless_market::contracts::{
@@ -39,6 +48,7 @@ use boundless_market::contracts::{
 };
 use boundless_market::selector::SupportedSelectors;
 use moka::{future::Cache, Expiry};
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use thiserror::Error;
@@ -48,6 +58,9 @@ use tokio_util::sync::CancellationToken;
 /// Hard limit on the number of orders to concurrently kick off proving work for.
 const MAX_PROVING_BATCH_SIZE: u32 = 10;
 
+/// Stage label recorded with the metrics of orders skipped by the order monitor
+const SKIP_STAGE: &str = "order_monitor";
+
 #[derive(Error)]
 pub enum OrderMonitorErr {
     #[error("{code} Failed to lock order: {0}", code = self.code())]
@@ -113,6 +126,102 @@ impl Capacity {
     }
 }
 
+/// How long the candidate orders of a chain are ranked against those of other chains after its
+/// order monitor last selected orders, so a stalled chain does not hold up the others
+const CANDIDATE_TTL: Duration = Duration::from_secs(60);
+
+/// Proving capacity shared by the order monitors of all chains.
+///
+/// Each monitor checks the capacity and reserves it for the orders it selects while holding the
+/// lock, then releases the lock before locking them, so that slow lock transactions on one chain
+/// do not hold up the monitors of other chains. Orders are selected by ranking them against the
+/// candidates of all chains, so that prioritization compares orders across chains.
+#[derive(Clone, Default)]
+pub(crate) struct SharedCapacity {
+    lock: Arc<Mutex<()>>,
+    /// Orders selected to be committed to, until they are recorded in the DB
+    reserved: Arc<std::sync::Mutex<HashMap<String, Arc<OrderRequest>>>>,
+    /// Orders ready to be committed to on each chain but not selected, by chain ID
+    candidates: Arc<std::sync::Mutex<HashMap<u64, (Instant, Vec<Arc<OrderRequest>>)>>>,
+}
+
+/// Capacity reserved for an order, released when dropped
+struct CapacityReservation {
+    reserved: Arc<std::sync::Mutex<HashMap<String, Arc<OrderRequest>>>>,
+    order_id: String,
+}
+
+impl Drop for CapacityReservation {
+    fn drop(&mut self) {
+        self.reserved.lock().unwrap().remove(&self.order_id);
+    }
+}
+
+impl SharedCapacity {
+    fn reserve(&self, orders: &[Arc<OrderRequest>]) -> Vec<CapacityReservation> {
+        let mut reserved = self.reserved.lock().unwrap();
+        orders
+            .iter()
+            .map(|order| {
+                reserved.insert(order.id(), order.clone());
+                CapacityReservation { reserved: self.reserved.clone(), order_id: order.id() }
+            })
+            .collect()
+    }
+
+    /// Orders reserved by any chain that are not yet in the committed orders
+    fn reserved(&self, committed_orders: &[Order]) -> Vec<Arc<OrderRequest>> {
+        let reserved = self.reserved.lock().unwrap();
+        reserved
+            .iter()
+            .filter(|(id, _)| !committed_orders.iter().any(|order| order.id() == **id))
+            .map(|(_, order)| order.clone())
+            .collect()
+    }
+
+    fn set_candidates(&self, chain_id: u64, orders: Vec<Arc<OrderRequest>>) {
+        self.candidates.lock().unwrap().insert(chain_id, (Instant::now(), orders));
+    }
+
+    /// Rank the orders of a chain against the candidates of the other chains, returning them in
+    /// priority order along with how many of them are granted capacity.
+    ///
+    /// Candidates of other chains ranked ahead of an order take up capacity before it.
+    fn select(
+        &self,
+        chain_id: u64,
+        orders: Vec<Arc<OrderRequest>>,
+        capacity: &Capacity,
+        priority_mode: OrderCommitmentPriority,
+        priority_addresses: Option<&[Address]>,
+    ) -> (Vec<Arc<OrderRequest>>, usize) {
+        let num_orders: u32 =
+            orders.len().try_into().expect("Failed to convert order count to u32");
+        let mut ranked: Vec<Arc<OrderRequest>> = self
+            .candidates
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(id, (selected_at, _))| {
+                **id != chain_id && selected_at.elapsed() < CANDIDATE_TTL
+            })
+            .flat_map(|(_, (_, candidates))| candidates.iter().cloned())
+            .collect();
+        if ranked.is_empty() || *capacity == Capacity::Unlimited {
+            return (orders, capacity.request_capacity(num_orders) as usize);
+        }
+
+        ranked.extend(orders);
+        sort_orders_by_priority_and_mode(&mut ranked, priority_addresses, priority_mode.into());
+        let slots = capacity.request_capacity(
+            ranked.len().try_into().expect("Failed to convert order count to u32"),
+        ) as usize;
+        let granted = ranked.iter().take(slots).filter(|order| order.chain_id == chain_id).count();
+        ranked.retain(|order| order.chain_id == chain_id);
+        (ranked, granted)
+    }
+}
+
 struct OrderExpiry;
 
 impl<K: std::hash::Hash + Eq, V: std::borrow::Borrow<OrderRequest>> Expiry<K, V> for OrderExpiry {
@@ -150,12 +259,17 @@ pub struct OrderMonitor<P> {
     config: ConfigLock,
     market: BoundlessMarketService<Arc<P>>,
     provider: Arc<P>,
+    chain_id: u64,
     prover_addr: Address,
     priced_order_rx: Arc<Mutex<mpsc::Receiver<Box<OrderRequest>>>>,
     lock_and_prove_cache: Arc<Cache<String, Arc<OrderRequest>>>,
     prove_cache: Arc<Cache<String, Arc<OrderRequest>>>,
     supported_selectors: SupportedSelectors,
     rpc_retry_config: RpcRetryConfig,
+    control: BrokerControl,
+    throughput: ProvingThroughput,
+    lock_bidder: LockBidder<P>,
+    tx_manager: Arc<TxManager<P>>,
 }
 
 impl<P> OrderMonitor<P>
@@ -166,6 +280,7 @@ where
     pub fn new(
         db: DbObj,
         provider: Arc<P>,
+        chain_id: u64,
         chain_monitor: Arc<ChainMonitorService<P>>,
         config: ConfigLock,
         block_time: u64,
@@ -174,6 +289,9 @@ where
         priced_orders_rx: mpsc::Receiver<Box<OrderRequest>>,
         stake_token_decimals: u8,
         rpc_retry_config: RpcRetryConfig,
+        control: BrokerControl,
+        throughput: ProvingThroughput,
+        tx_manager: Arc<TxManager<P>>,
     ) -> Result<Self> {
         let txn_timeout_opt = {
             let config = config.lock_all().context("Failed to read config")?;
@@ -204,6 +322,15 @@ where
                     .map(|s| parse_units(s, stake_token_decimals).unwrap().into()),
             );
         }
+        let lock_bidder = LockBidder::new(
+            db.clone(),
+            config.clone(),
+            provider.clone(),
+            chain_monitor.clone(),
+            chain_id,
+            market_addr,
+            prover_addr,
+        );
         let monitor = Self {
             db,
             chain_monitor,
@@ -211,12 +338,17 @@ where
             config,
             market,
             provider,
+            chain_id,
             prover_addr,
             priced_order_rx: Arc::new(Mutex::new(priced_orders_rx)),
             lock_and_prove_cache: Arc::new(Cache::builder().expire_after(OrderExpiry).build()),
             prove_cache: Arc::new(Cache::builder().expire_after(OrderExpiry).build()),
             supported_selectors: SupportedSelectors::default(),
             rpc_retry_config,
+            control,
+            throughput,
+            lock_bidder,
+            tx_manager,
         };
         Ok(monitor)
     }
@@ -238,7 +370,7 @@ where
 
         let is_locked = self
             .db
-            .is_request_locked(U256::from(order.request.id))
+            .is_request_locked(order.chain_id, U256::from(order.request.id))
             .await
             .context("Failed to check if request is locked")?;
         if is_locked {
@@ -246,9 +378,39 @@ where
             return Err(OrderMonitorErr::AlreadyLocked);
         }
 
-        let conf_priority_gas = {
-            let conf = self.config.lock_all().context("Failed to lock config")?;
-            conf.market.lockin_priority_gas
+        if self.control.is_shadow() {
+            // Price the lock at the current chain head, as if the lock tx landed in the next block.
+            let ChainHead { block_timestamp, .. } = self.chain_monitor.current_chain_head().await?;
+            tracing::info!(
+                "Shadow mode: would lock request: 0x{:x} for stake: {}",
+                request_id,
+                order.request.offer.lockStake
+            );
+            shadow::record(&self.db, ShadowDecision::new(order, ShadowAction::Locked)).await;
+            let lock_price = order
+                .request
+                .offer
+                .price_at(block_timestamp)
+                .context("Failed to calculate lock price")?;
+            return Ok(lock_price);
+        }
+
+        let bid = match self.lock_bidder.bid(order).await {
+            Ok(bid) => bid,
+            Err(err) => {
+                tracing::warn!(
+                    "Failed to bid priority fee for request 0x{:x}, using lockin_priority_gas: {err:?}",
+                    request_id
+                );
+                None
+            }
+        };
+        let priority_gas = match &bid {
+            Some(bid) => Some(u64::try_from(bid.priority_fee).unwrap_or(u64::MAX)),
+            None => {
+                let conf = self.config.lock_all().context("Failed to lock config")?;
+                conf.market.lockin_priority_gas
+            }
         };
 
         tracing::info!(
@@ -256,11 +418,8 @@ where
             request_id,
             order.request.offer.lockStake
         );
-        let lock_block = self
-            .market
-            .lock_request(&order.request, order.client_sig.clone(), conf_priority_gas)
-            .await
-            .map_err(|e| -> OrderMonitorErr {
+        let lock_res =
+            self.send_lock_request(order, priority_gas).await.map_err(|e| -> OrderMonitorErr {
                 match e {
                     MarketError::TxnError(txn_err) => match txn_err {
                         TxnErr::BoundlessMarketErr(IBoundlessMarketErrors::RequestIsLocked(_)) => {
@@ -311,7 +470,44 @@ where
                         }
                     }
                 }
-            })?;
+            });
+        if let Some(bid) = bid {
+            let outcome = match &lock_res {
+                Ok(_) => LockBidOutcome::Locked,
+                Err(OrderMonitorErr::AlreadyLocked) => LockBidOutcome::Outbid,
+                // A reverted lock is most often due to another prover locking first
+                Err(OrderMonitorErr::LockTxFailed(_))
+                    if self.market.is_locked(request_id).await.unwrap_or(false) =>
+                {
+                    LockBidOutcome::Outbid
+                }
+                Err(_) => LockBidOutcome::Failed,
+            };
+            self.lock_bidder.record(bid, outcome).await;
+        }
+        let lock_receipt = lock_res?;
+        let lock_block =
+            lock_receipt.block_number.context("Lock transaction receipt missing block number")?;
+        ledger::record(
+            &self.db,
+            vec![
+                LedgerEntry::new(
+                    order.id(),
+                    order.chain_id,
+                    &order.request,
+                    LedgerKind::LockGas,
+                    ledger::gas_cost(&lock_receipt),
+                ),
+                LedgerEntry::new(
+                    order.id(),
+                    order.chain_id,
+                    &order.request,
+                    LedgerKind::StakeLocked,
+                    order.request.offer.lockStake,
+                ),
+            ],
+        )
+        .await;
 
         // Fetch the block to retrieve the lock timestamp. This has been observed to return
         // inconsistent state between the receipt being available but the block not yet.
@@ -342,6 +538,49 @@ where
         Ok(lock_price)
     }
 
+    /// Lock the request, through the transaction manager when enabled
+    async fn send_lock_request(
+        &self,
+        order: &OrderRequest,
+        priority_gas: Option<u64>,
+    ) -> Result<TransactionReceipt, MarketError> {
+        if !self.tx_manager.enabled()? {
+            return self
+                .market
+                .lock_request_with_receipt(&order.request, order.client_sig.clone(), priority_gas)
+                .await;
+        }
+
+        let request_id = order.request.id;
+        if self.market.is_locked(request_id).await? {
+            return Err(MarketError::RequestAlreadyLocked(request_id));
+        }
+
+        // Fee bumps are capped by the price the order pays if locked now.
+        let lock_price = order
+            .request
+            .offer
+            .price_at(now_timestamp())
+            .context("Failed to calculate lock price")?;
+        let policy = TxPolicy {
+            max_cost: lock_price,
+            deadline: Some(order.request.lock_expires_at()),
+            priority_fee: priority_gas.unwrap_or_default().into(),
+        };
+        let tx = self.market.lock_request_transaction(&order.request, order.client_sig.clone());
+        let receipt = self.tx_manager.send(tx, policy).await?;
+        if !receipt.status() {
+            return Err(MarketError::LockRevert(receipt.transaction_hash));
+        }
+
+        tracing::info!(
+            "Locked request {:x}, transaction hash: {}",
+            request_id,
+            receipt.transaction_hash
+        );
+        Ok(receipt)
+    }
+
     async fn get_proving_order_capacity(
         &self,
         max_concurrent_proofs: Option<u32>,
@@ -358,10 +597,13 @@ where
             .await
             .map_err(|e| OrderMonitorErr::UnexpectedError(e.into()))?;
         let committed_orders_count: u32 = committed_orders.len().try_into().unwrap();
+        // Orders being locked by the monitors of other chains take up capacity as well
+        let reserved_count: u32 =
+            self.control.capacity().reserved(&committed_orders).len().try_into().unwrap();
 
         Self::log_capacity(prev_orders_by_status, committed_orders, max).await;
 
-        let available_slots = max.saturating_sub(committed_orders_count);
+        let available_slots = max.saturating_sub(committed_orders_count + reserved_count);
         Ok(Capacity::Available(available_slots))
     }
 
@@ -392,8 +634,12 @@ where
     }
 
     /// Helper method to skip an order in the database and invalidate the appropriate cache
-    async fn skip_order(&self, order: &OrderRequest, reason: &str) {
-        if let Err(e) = self.db.insert_skipped_request(order).await {
+    async fn skip_order(&self, order: &OrderRequest, reason: SkipReason) {
+        metrics::record_order_skipped(SKIP_STAGE, reason);
+        if self.control.is_shadow() {
+            shadow::record(&self.db, ShadowDecision::skip(order, reason)).await;
+        }
+        if let Err(e) = self.db.insert_skipped_request(order, reason).await {
             tracing::error!("Failed to skip order ({}): {} - {e:?}", reason, order.id());
         }
 
@@ -416,14 +662,15 @@ where
 
         fn is_within_deadline(
             order: &OrderRequest,
+            expiration: u64,
             current_block_timestamp: u64,
             min_deadline: u64,
         ) -> bool {
-            if order.request.expires_at() < current_block_timestamp {
+            if expiration < current_block_timestamp {
                 tracing::debug!("Request {:x} has now expired. Skipping.", order.request.id);
                 false
-            } else if order.request.expires_at().saturating_sub(now_timestamp()) < min_deadline {
-                tracing::debug!("Request {:x} deadline at {} is less than the minimum deadline {} seconds required to prove an order. Skipping.", order.request.id, order.request.expires_at(), min_deadline);
+            } else if expiration.saturating_sub(now_timestamp()) < min_deadline {
+                tracing::debug!("Request {:x} deadline at {} is less than the minimum deadline {} seconds required to prove an order. Skipping.", order.request.id, expiration, min_deadline);
                 false
             } else {
                 true
@@ -456,32 +703,65 @@ where
         }
 
         for (_, order) in self.prove_cache.iter() {
+            if self.control.take_skipped_order(&order.id()) {
+                tracing::info!("Order {} was marked to be skipped by the operator", order.id());
+                self.skip_order(&order, SkipReason::SkippedByOperator).await;
+                continue;
+            }
             let is_fulfilled = self
                 .db
-                .is_request_fulfilled(U256::from(order.request.id))
+                .is_request_fulfilled(order.chain_id, U256::from(order.request.id))
                 .await
                 .context("Failed to check if request is fulfilled")?;
+            let without_locking = order.fulfillment_type == FulfillmentType::FulfillWithoutLocking;
             if is_fulfilled {
                 tracing::debug!(
-                    "Request 0x{:x} was locked by another prover and was fulfilled. Skipping.",
+                    "Request 0x{:x} was fulfilled by another prover. Skipping.",
+                    order.request.id
+                );
+                self.skip_order(&order, SkipReason::AlreadyFulfilled).await;
+            } else if without_locking
+                && self
+                    .db
+                    .is_request_locked(order.chain_id, U256::from(order.request.id))
+                    .await
+                    .context("Failed to check if request is locked")?
+            {
+                // Once locked, only the locking prover is paid until the lock expires.
+                tracing::debug!(
+                    "Request 0x{:x} was scheduled to be fulfilled without locking, but was locked by another prover. Skipping.",
                     order.request.id
                 );
-                self.skip_order(&order, "was fulfilled by other").await;
-            } else if !is_within_deadline(&order, current_block_timestamp, min_deadline) {
-                self.skip_order(&order, "expired").await;
+                self.skip_order(&order, SkipReason::AlreadyLocked).await;
+            } else if !is_within_deadline(
+                &order,
+                order.expiration(),
+                current_block_timestamp,
+                min_deadline,
+            ) {
+                self.skip_order(&order, SkipReason::Expired).await;
             } else if is_target_time_reached(&order, current_block_timestamp) {
-                tracing::info!("Request 0x{:x} was locked by another prover but expired unfulfilled, setting status to pending proving", order.request.id);
+                if without_locking {
+                    tracing::info!("Request 0x{:x} will be fulfilled without locking, setting status to pending proving", order.request.id);
+                } else {
+                    tracing::info!("Request 0x{:x} was locked by another prover but expired unfulfilled, setting status to pending proving", order.request.id);
+                }
                 candidate_orders.push(order);
             }
         }
 
         for (_, order) in self.lock_and_prove_cache.iter() {
+            if self.control.take_skipped_order(&order.id()) {
+                tracing::info!("Order {} was marked to be skipped by the operator", order.id());
+                self.skip_order(&order, SkipReason::SkippedByOperator).await;
+                continue;
+            }
             let is_lock_expired = order.request.lock_expires_at() < current_block_timestamp;
             if is_lock_expired {
                 tracing::debug!("Request {:x} was scheduled to be locked by us, but its lock has now expired. Skipping.", order.request.id);
-                self.skip_order(&order, "lock expired before we locked").await;
+                self.skip_order(&order, SkipReason::LockExpired).await;
             } else if let Some((locker, _)) =
-                self.db.get_request_locked(U256::from(order.request.id)).await?
+                self.db.get_request_locked(order.chain_id, U256::from(order.request.id)).await?
             {
                 let our_address = self.provider.default_signer_address().to_string().to_lowercase();
                 let locker_address = locker.to_lowercase();
@@ -491,14 +771,19 @@ where
 
                 if locker_address_normalized != our_address_normalized {
                     tracing::debug!("Request 0x{:x} was scheduled to be locked by us ({}), but is already locked by another prover ({}). Skipping.", order.request.id, our_address, locker_address);
-                    self.skip_order(&order, "locked by another prover").await;
+                    self.skip_order(&order, SkipReason::AlreadyLocked).await;
                 } else {
                     // Edge case where we locked the order, but due to some reason was not moved to proving state. Should not happen.
                     tracing::debug!("Request 0x{:x} was scheduled to be locked by us, but is already locked by us. Proceeding to prove.", order.request.id);
                     candidate_orders.push(order);
                 }
-            } else if !is_within_deadline(&order, current_block_timestamp, min_deadline) {
-                self.skip_order(&order, "insufficient deadline").await;
+            } else if !is_within_deadline(
+                &order,
+                order.request.expires_at(),
+                current_block_timestamp,
+                min_deadline,
+            ) {
+                self.skip_order(&order, SkipReason::MinDeadline).await;
             } else if is_target_time_reached(&order, current_block_timestamp) {
                 candidate_orders.push(order);
             }
@@ -530,6 +815,7 @@ where
                     match self.lock_order(order).await {
                         Ok(lock_price) => {
                             tracing::info!("Locked request: 0x{:x}", request_id);
+                            metrics::record_lock(true);
                             if let Err(err) = self.db.insert_accepted_request(order, lock_price).await {
                                 tracing::error!(
                                     "FATAL STAKE AT RISK: {} failed to move from locking -> proving status {}",
@@ -539,6 +825,12 @@ where
                             }
                         }
                         Err(ref err) => {
+                            metrics::record_lock(false);
+                            metrics::record_order_skipped(SKIP_STAGE, SkipReason::LockFailed);
+                            if self.control.is_shadow() {
+                                let decision = ShadowDecision::skip(order, SkipReason::LockFailed);
+                                shadow::record(&self.db, decision).await;
+                            }
                             match err {
                                 OrderMonitorErr::UnexpectedError(inner) => {
                                     tracing::error!(
@@ -553,7 +845,9 @@ where
                                     );
                                 }
                             }
-                            if let Err(err) = self.db.insert_skipped_request(order).await {
+                            if let Err(err) =
+                                self.db.insert_skipped_request(order, SkipReason::LockFailed).await
+                            {
                                 tracing::error!(
                                     "Failed to set DB failure state for order: {order_id} - {err:?}"
                                 );
@@ -612,6 +906,37 @@ where
         Ok(order_cost_wei)
     }
 
+    /// Lock and prove the orders within the proving capacity, returning the number of orders.
+    ///
+    /// The capacity is shared with the order monitors of other chains, so it is reserved for the
+    /// selected orders until they are committed to, and the orders left are kept as candidates to
+    /// rank the orders of other chains against.
+    async fn commit_orders(
+        &self,
+        orders: Vec<Arc<OrderRequest>>,
+        config: &OrderMonitorConfig,
+        prev_orders_by_status: &mut String,
+    ) -> Result<usize> {
+        let capacity = self.control.capacity();
+        let (final_orders, _reservations) = {
+            let _lock = capacity.lock.lock().await;
+            let candidates = orders.clone();
+            let final_orders =
+                self.apply_capacity_limits(orders, config, prev_orders_by_status).await?;
+            let candidates = candidates
+                .into_iter()
+                .filter(|order| !final_orders.iter().any(|selected| selected.id() == order.id()))
+                .collect();
+            capacity.set_candidates(self.chain_id, candidates);
+            let reservations = capacity.reserve(&final_orders);
+            (final_orders, reservations)
+        };
+        if !final_orders.is_empty() {
+            self.lock_and_prove_orders(&final_orders).await?;
+        }
+        Ok(final_orders.len())
+    }
+
     async fn apply_capacity_limits(
         &self,
         orders: Vec<Arc<OrderRequest>>,
@@ -623,9 +948,13 @@ where
         let capacity = self
             .get_proving_order_capacity(config.max_concurrent_proofs, prev_orders_by_status)
             .await?;
-        let capacity_granted: usize = capacity
-            .request_capacity(num_orders.try_into().expect("Failed to convert order count to u32"))
-            as usize;
+        let (orders, capacity_granted) = self.control.capacity().select(
+            self.chain_id,
+            orders,
+            &capacity,
+            config.order_commitment_priority,
+            config.priority_addresses.as_deref(),
+        );
 
         tracing::info!(
             "Num orders ready for locking and/or proving: {}. Total capacity available: {capacity:?}, Capacity granted: {capacity_granted:?}",
@@ -643,10 +972,13 @@ where
             .await
             .map_err(|err| OrderMonitorErr::RpcErr(err.into()))?;
 
-        // Calculate gas units required for committed orders
+        // Calculate gas units required for committed orders. Only orders of this chain are
+        // fulfilled from the balance on this chain.
         let committed_orders = self.db.get_committed_orders().await?;
+        let chain_committed_orders: Vec<_> =
+            committed_orders.iter().filter(|order| order.chain_id == self.chain_id).collect();
         let committed_gas_units =
-            futures::future::try_join_all(committed_orders.iter().map(|order| {
+            futures::future::try_join_all(chain_committed_orders.iter().map(|order| {
                 utils::estimate_gas_to_fulfill(
                     &self.config,
                     &self.supported_selectors,
@@ -661,10 +993,10 @@ where
         let committed_cost_wei = U256::from(gas_price) * U256::from(committed_gas_units);
 
         // Log committed order gas requirements
-        if !committed_orders.is_empty() {
+        if !chain_committed_orders.is_empty() {
             tracing::debug!(
                 "Cost for {} committed orders: {} ether",
-                committed_orders.len(),
+                chain_committed_orders.len(),
                 format_ether(committed_cost_wei),
             );
         }
@@ -686,10 +1018,19 @@ where
         let num_commited_orders = committed_orders.len();
         if config.peak_prove_khz.is_some() && !orders.is_empty() {
             let peak_prove_khz = config.peak_prove_khz.unwrap();
+            let reserved_cycles = self
+                .control
+                .capacity()
+                .reserved(&committed_orders)
+                .iter()
+                .filter_map(|order| order.total_cycles)
+                .map(|cycles| cycles + config.additional_proof_cycles)
+                .sum::<u64>();
             let total_commited_cycles = committed_orders
                 .iter()
                 .map(|order| order.total_cycles.unwrap() + config.additional_proof_cycles)
-                .sum::<u64>();
+                .sum::<u64>()
+                + reserved_cycles;
 
             let now = now_timestamp();
             // Estimate the time the prover will be available given our current committed orders.
@@ -732,7 +1073,7 @@ where
                         format_ether(order_cost_wei),
                         format_ether(remaining_balance_wei)
                     );
-                    self.skip_order(&order, "insufficient balance").await;
+                    self.skip_order(&order, SkipReason::InsufficientGas).await;
                     continue;
                 }
 
@@ -748,11 +1089,7 @@ where
 
                 let proof_time_seconds = total_cycles.div_ceil(1_000).div_ceil(peak_prove_khz);
                 let completion_time = prover_available_at + proof_time_seconds;
-                let expiration = match order.fulfillment_type {
-                    FulfillmentType::LockAndFulfill => order.request.lock_expires_at(),
-                    FulfillmentType::FulfillAfterLockExpire => order.request.expires_at(),
-                    _ => panic!("Unsupported fulfillment type: {:?}", order.fulfillment_type),
-                };
+                let expiration = order.expiration();
 
                 if completion_time + config.batch_buffer_time_secs > expiration {
                     // If the order cannot be completed before its expiration, skip it permanently.
@@ -767,7 +1104,7 @@ where
                         );
                         // If the order cannot be completed regardless of other orders, skip it
                         // permanently. Otherwise, will retry including the order.
-                        self.skip_order(&order, "cannot be completed before expiration").await;
+                        self.skip_order(&order, SkipReason::InsufficientCapacity).await;
                     } else {
                         tracing::debug!("Given current commited orders and capacity, order 0x{:x} cannot be completed before its expiration. Not skipping as capacity may free up before it expires.", order.request.id);
                     }
@@ -796,7 +1133,7 @@ where
                         format_ether(order_cost_wei),
                         format_ether(remaining_balance_wei)
                     );
-                    self.skip_order(&order, "insufficient balance").await;
+                    self.skip_order(&order, SkipReason::InsufficientGas).await;
                     continue;
                 }
 
@@ -855,6 +1192,12 @@ where
                     let ChainHead { block_number, block_timestamp } =
                         self.chain_monitor.current_chain_head().await?;
                     if block_number != last_block {
+                        // Held until the orders of this block are locked and recorded, so a drain
+                        // does not shut down the broker in between.
+                        let Some(_commit) = self.control.start_commit() else {
+                            tracing::trace!("Broker is draining, not locking or proving new orders");
+                            continue;
+                        };
                         last_block = block_number;
                         if first_block == 0 {
                             first_block = block_number;
@@ -863,11 +1206,12 @@ where
                             "Order monitor processing block {block_number} at timestamp {block_timestamp}"
                         );
 
+                        let peak_prove_khz = self.throughput.capacity_khz();
                         let monitor_config = {
                             let config = self.config.lock_all().context("Failed to read config")?;
                             OrderMonitorConfig {
                                 min_deadline: config.market.min_deadline,
-                                peak_prove_khz: config.market.peak_prove_khz,
+                                peak_prove_khz,
                                 max_concurrent_proofs: config.market.max_concurrent_proofs,
                                 additional_proof_cycles: config.market.additional_proof_cycles,
                                 batch_buffer_time_secs: config.batcher.block_deadline_buffer_secs,
@@ -884,31 +1228,27 @@ where
                                 "No orders to lock and/or prove as of block timestamp {}",
                                 block_timestamp
                             );
+                            self.control.capacity().set_candidates(self.chain_id, Vec::new());
                             continue;
                         }
 
                         // Prioritize the orders that intend to fulfill based on configured commitment priority.
                         valid_orders = self.prioritize_orders(valid_orders, monitor_config.order_commitment_priority, monitor_config.priority_addresses.as_deref());
 
-                        // Filter down the orders given our max concurrent proofs, peak khz limits, and gas limitations.
-                        let final_orders = self
-                            .apply_capacity_limits(
+                        // Filter down the orders given our max concurrent proofs, peak khz limits, and gas limitations, then lock and prove them.
+                        let num_committed = self
+                            .commit_orders(
                                 valid_orders,
                                 &monitor_config,
                                 &mut prev_orders_by_status,
                             )
                             .await?;
 
-                        tracing::trace!("After processing block {}[timestamp {}], we will now start locking and/or proving {} orders.",
+                        tracing::trace!("After processing block {}[timestamp {}], started locking and/or proving {} orders.",
                             block_number,
                             block_timestamp,
-                            final_orders.len(),
+                            num_committed,
                         );
-
-                        if !final_orders.is_empty() {
-                            // Lock and prove filtered orders.
-                            self.lock_and_prove_orders(&final_orders).await?;
-                        }
                     }
                 }
                 _ = cancel_token.cancelled() => {
@@ -965,7 +1305,7 @@ pub(crate) mod tests {
     use alloy::{
         network::EthereumWallet,
         node_bindings::Anvil,
-        primitives::{Address, U256},
+        primitives::{Address, I256, U256},
         providers::{
             fillers::{
                 BlobGasFiller, ChainIdFiller, FillProvider, GasFiller, JoinFill, NonceFiller,
@@ -982,6 +1322,7 @@ pub(crate) mod tests {
     use boundless_market_test_utils::{
         deploy_boundless_market, deploy_hit_points, ASSESSOR_GUEST_ID, ASSESSOR_GUEST_PATH,
     };
+    use chrono::{DateTime, Utc};
 
     use risc0_zkvm::Digest;
     use std::{future::Future, sync::Arc};
@@ -1058,12 +1399,15 @@ pub(crate) mod tests {
                 request,
                 image_id: None,
                 input_id: None,
+                assumption_ids: vec![],
                 expire_timestamp: None,
                 client_sig,
                 fulfillment_type,
                 boundless_market_address: self.market_address,
                 chain_id: self.anvil.chain_id(),
                 total_cycles: None,
+                expected_profit: None,
+                priority_boost: false,
             })
         }
     }
@@ -1125,9 +1469,11 @@ pub(crate) mod tests {
         // Create required channels for tests
         let (priced_order_tx, priced_order_rx) = mpsc::channel(16);
 
+        let throughput = ProvingThroughput::load(db.clone(), config.clone()).await.unwrap();
         let monitor = OrderMonitor::new(
             db.clone(),
             provider.clone(),
+            anvil.chain_id(),
             chain_monitor.clone(),
             config.clone(),
             block_time,
@@ -1136,6 +1482,9 @@ pub(crate) mod tests {
             priced_order_rx,
             stake_token_decimals,
             RpcRetryConfig { retry_count: 2, retry_sleep_ms: 500 },
+            BrokerControl::default(),
+            throughput,
+            Arc::new(TxManager::new(provider.clone(), config.clone(), Default::default())),
         )
         .unwrap();
 
@@ -1204,6 +1553,57 @@ pub(crate) mod tests {
         .await;
     }
 
+    #[tokio::test]
+    #[traced_test]
+    async fn lock_bid_recorded() {
+        let mut ctx = setup_om_test_context().await;
+        ctx.config.load_write().unwrap().market.lock_bid_max_profit_bps = Some(1_000);
+        ctx.config.load_write().unwrap().market.lockin_priority_gas = Some(10);
+
+        let mut order =
+            ctx.create_test_order(FulfillmentType::LockAndFulfill, now_timestamp(), 100, 200).await;
+        order.expected_profit = Some(I256::try_from(1_000_000_000_000_000u64).unwrap());
+        ctx.market_service.submit_request(&order.request, &ctx.signer).await.unwrap();
+
+        ctx.monitor.lock_order(&order).await.unwrap();
+
+        let bids = ctx.db.get_lock_bids(10).await.unwrap();
+        assert_eq!(bids.len(), 1);
+        assert_eq!(bids[0].order_id, order.id());
+        assert_eq!(bids[0].outcome, Some(LockBidOutcome::Locked));
+        // Without competing locks, the configured minimum is bid
+        assert_eq!(bids[0].competing_priority_fee, None);
+        assert_eq!(bids[0].priority_fee, 10);
+        assert!(bids[0].max_priority_fee.unwrap() > 10);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn lock_recorded_in_ledger() {
+        let ctx = setup_om_test_context().await;
+        let order =
+            ctx.create_test_order(FulfillmentType::LockAndFulfill, now_timestamp(), 100, 200).await;
+        ctx.market_service.submit_request(&order.request, &ctx.signer).await.unwrap();
+
+        ctx.monitor.lock_order(&order).await.unwrap();
+
+        let stakes = ctx.db.get_open_stakes().await.unwrap();
+        assert_eq!(stakes.len(), 1);
+        assert_eq!(stakes[0].order_id, order.id());
+        assert_eq!(stakes[0].amount, order.request.offer.lockStake);
+        let entries = ctx
+            .db
+            .get_ledger_entries(
+                None,
+                DateTime::UNIX_EPOCH,
+                Utc::now() + chrono::Duration::seconds(1),
+            )
+            .await
+            .unwrap();
+        let lock_gas = entries.iter().find(|entry| entry.kind == LedgerKind::LockGas).unwrap();
+        assert!(lock_gas.amount > U256::ZERO);
+    }
+
     // Capacity tests
     #[test]
     fn test_capacity_unlimited() {
@@ -1288,6 +1688,7 @@ pub(crate) mod tests {
         let order_id = order.id();
         ctx.db
             .set_request_locked(
+                order.chain_id,
                 U256::from(order.request.id),
                 &Address::ZERO.to_string(),
                 current_timestamp,
@@ -1305,6 +1706,57 @@ pub(crate) mod tests {
         assert_eq!(order.status, OrderStatus::Skipped);
     }
 
+    #[tokio::test]
+    #[traced_test]
+    async fn test_filter_fulfill_without_locking_orders() {
+        let mut ctx = setup_om_test_context().await;
+        let current_timestamp = now_timestamp();
+
+        // Order locked by another prover after we decided to fulfill it without locking
+        let locked_order = ctx
+            .create_test_order(FulfillmentType::FulfillWithoutLocking, current_timestamp, 100, 200)
+            .await;
+        let locked_order_id = locked_order.id();
+        ctx.db
+            .set_request_locked(
+                locked_order.chain_id,
+                U256::from(locked_order.request.id),
+                &Address::ZERO.to_string(),
+                current_timestamp,
+            )
+            .await
+            .unwrap();
+        ctx.monitor.prove_cache.insert(locked_order.id(), Arc::from(locked_order)).await;
+
+        // Order whose lock expires before the min deadline, so it can no longer be paid for
+        let short_order = ctx
+            .create_test_order(FulfillmentType::FulfillWithoutLocking, current_timestamp, 10, 200)
+            .await;
+        let short_order_id = short_order.id();
+        ctx.monitor.prove_cache.insert(short_order.id(), Arc::from(short_order)).await;
+
+        let valid_order = ctx
+            .create_test_order(FulfillmentType::FulfillWithoutLocking, current_timestamp, 100, 200)
+            .await;
+        let valid_order_id = valid_order.id();
+        ctx.monitor.prove_cache.insert(valid_order.id(), Arc::from(valid_order)).await;
+
+        let result = ctx.monitor.get_valid_orders(current_timestamp, 30).await.unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id(), valid_order_id);
+        assert!(logs_contain("will be fulfilled without locking"));
+
+        let order = ctx.db.get_order(&locked_order_id).await.unwrap().unwrap();
+        assert_eq!(order.status, OrderStatus::Skipped);
+        let order = ctx.db.get_order(&short_order_id).await.unwrap().unwrap();
+        assert_eq!(order.status, OrderStatus::Skipped);
+
+        ctx.monitor.lock_and_prove_orders(&result).await.unwrap();
+        let order = ctx.db.get_order(&valid_order_id).await.unwrap().unwrap();
+        assert_eq!(order.status, OrderStatus::PendingProving);
+    }
+
     // Processing tests
     #[tokio::test]
     #[traced_test]
@@ -1436,6 +1888,113 @@ pub(crate) mod tests {
         );
     }
 
+    #[tokio::test]
+    #[traced_test]
+    async fn test_commit_orders_shares_capacity() {
+        let mut ctx = setup_om_test_context().await;
+        let current_timestamp = now_timestamp();
+
+        let order_1 = ctx
+            .create_test_order(FulfillmentType::FulfillAfterLockExpire, current_timestamp, 100, 200)
+            .await;
+        let order_2 = ctx
+            .create_test_order(FulfillmentType::FulfillAfterLockExpire, current_timestamp, 100, 200)
+            .await;
+
+        // Monitors of two chains share the DB and broker controls
+        let config = OrderMonitorConfig { max_concurrent_proofs: Some(1), ..Default::default() };
+        let other_monitor = ctx.monitor.clone();
+        let (committed_1, committed_2) = tokio::join!(
+            ctx.monitor.commit_orders(vec![Arc::from(order_1)], &config, &mut String::new()),
+            other_monitor.commit_orders(vec![Arc::from(order_2)], &config, &mut String::new()),
+        );
+
+        assert_eq!(committed_1.unwrap() + committed_2.unwrap(), 1);
+        assert_eq!(ctx.db.get_committed_orders().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_apply_capacity_limits_reserved_by_other_chain() {
+        let mut ctx = setup_om_test_context().await;
+        let current_timestamp = now_timestamp();
+
+        let mut other_order = ctx
+            .create_test_order(FulfillmentType::FulfillAfterLockExpire, current_timestamp, 100, 200)
+            .await;
+        other_order.chain_id += 1;
+        let order = ctx
+            .create_test_order(FulfillmentType::FulfillAfterLockExpire, current_timestamp, 100, 200)
+            .await;
+        let order: Arc<OrderRequest> = Arc::from(order);
+
+        // The order of the other chain is being locked
+        let config = OrderMonitorConfig { max_concurrent_proofs: Some(1), ..Default::default() };
+        let reservations = ctx.monitor.control.capacity().reserve(&[Arc::from(other_order)]);
+        let selected = ctx
+            .monitor
+            .apply_capacity_limits(vec![order.clone()], &config, &mut String::new())
+            .await
+            .unwrap();
+        assert!(selected.is_empty());
+
+        // Once it is committed to or skipped, the capacity is free again
+        drop(reservations);
+        let selected = ctx
+            .monitor
+            .apply_capacity_limits(vec![order], &config, &mut String::new())
+            .await
+            .unwrap();
+        assert_eq!(selected.len(), 1);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_apply_capacity_limits_prioritizes_across_chains() {
+        let mut ctx = setup_om_test_context().await;
+        let current_timestamp = now_timestamp();
+
+        let mut other_order = ctx
+            .create_test_order(FulfillmentType::FulfillAfterLockExpire, current_timestamp, 100, 200)
+            .await;
+        other_order.chain_id += 1;
+        let order = ctx
+            .create_test_order(FulfillmentType::FulfillAfterLockExpire, current_timestamp, 100, 300)
+            .await;
+        let order: Arc<OrderRequest> = Arc::from(order);
+
+        let config = OrderMonitorConfig {
+            max_concurrent_proofs: Some(1),
+            order_commitment_priority: OrderCommitmentPriority::ShortestExpiry,
+            ..Default::default()
+        };
+        let other_chain_id = other_order.chain_id;
+
+        // The order of the other chain expires first, so it takes the only slot
+        let capacity = ctx.monitor.control.capacity();
+        capacity.set_candidates(other_chain_id, vec![Arc::from(other_order)]);
+        let selected = ctx
+            .monitor
+            .apply_capacity_limits(vec![order.clone()], &config, &mut String::new())
+            .await
+            .unwrap();
+        assert!(selected.is_empty());
+
+        // An order of the other chain expiring later is ranked behind it
+        let mut later_order = ctx
+            .create_test_order(FulfillmentType::FulfillAfterLockExpire, current_timestamp, 100, 400)
+            .await;
+        later_order.chain_id = other_chain_id;
+        capacity.set_candidates(other_chain_id, vec![Arc::from(later_order)]);
+        let selected = ctx
+            .monitor
+            .apply_capacity_limits(vec![order.clone()], &config, &mut String::new())
+            .await
+            .unwrap();
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].id(), order.id());
+    }
+
     #[tokio::test]
     #[traced_test]
     async fn test_apply_capacity_limits_committed_work_too_large() {
@@ -1662,6 +2221,29 @@ pub(crate) mod tests {
             .unwrap();
         assert_eq!(filtered_orders.len(), 1);
 
+        // Orders committed on other chains are fulfilled from the balance on those chains
+        for _ in 0..3 {
+            let committed_order = ctx
+                .create_test_order(FulfillmentType::LockAndFulfill, now_timestamp(), 100, 200)
+                .await;
+
+            let mut committed_order_obj = committed_order.to_proving_order(Default::default());
+            committed_order_obj.status = OrderStatus::Proving;
+            committed_order_obj.proving_started_at = Some(now_timestamp());
+            committed_order_obj.chain_id += 1;
+            ctx.db.add_order(&committed_order_obj).await.unwrap();
+        }
+        let filtered_orders = ctx
+            .monitor
+            .apply_capacity_limits(
+                orders.clone(),
+                &OrderMonitorConfig::default(),
+                &mut String::new(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(filtered_orders.len(), 1);
+
         for _ in 0..3 {
             let committed_order = ctx
                 .create_test_order(FulfillmentType::LockAndFulfill, now_timestamp(), 100, 200)
@@ -1718,6 +2300,7 @@ pub(crate) mod tests {
         // Simulate that this order was locked by another prover but the lock has now expired
         ctx.db
             .set_request_locked(
+                fulfill_after_expire_order.chain_id,
                 U256::from(fulfill_after_expire_order.request.id),
                 &Address::ZERO.to_string(),
                 current_timestamp - 50,
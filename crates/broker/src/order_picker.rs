@@ -14,27 +14,37 @@
 
 use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use crate::now_timestamp;
 use crate::{
+    admin::BrokerControl,
     chain_monitor::ChainMonitorService,
     config::ConfigLock,
     db::DbObj,
     errors::CodedError,
-    provers::{ProverError, ProverObj},
-    storage::{upload_image_uri, upload_input_uri},
+    metrics,
+    preflight_cache::{self, PreflightOutcome},
+    pricing::{
+        DefaultPricingStrategy, PreflightDecision, PricingContext, PricingDecision,
+        PricingStrategies, PricingStrategyObj,
+    },
+    provers::{ExecutorResp, ProofResult, ProverError, ProverObj},
+    rules::{self, RuleOutcome},
+    shadow::{self, ShadowAction, ShadowDecision},
+    stake_price::StakePriceOracleObj,
+    storage::{upload_assumptions, upload_image_uri, upload_input_uri},
     task::{RetryRes, RetryTask, SupervisorErr},
-    utils, FulfillmentType, OrderRequest,
+    throughput::ProvingThroughput,
+    utils, FulfillmentType, OrderRequest, SkipReason,
 };
-use crate::{now_timestamp, provers::ProofResult};
 use alloy::{
     network::Ethereum,
     primitives::{
-        utils::{format_ether, format_units, parse_ether, parse_units},
-        Address, U256,
+        utils::{format_ether, format_units},
+        Address, I256, U256,
     },
     providers::{Provider, WalletProvider},
-    uint,
 };
 use anyhow::{Context, Result};
 use boundless_market::{
@@ -47,15 +57,19 @@ use tokio::sync::{mpsc, Mutex};
 use tokio::task::JoinSet;
 use tokio_util::sync::CancellationToken;
 
-use OrderPricingOutcome::{Lock, ProveAfterLockExpire, Skip};
+use OrderPricingOutcome::{Defer, Lock, ProveAfterLockExpire, ProveWithoutLock, Skip};
 
-const MIN_CAPACITY_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// Stage label recorded with the metrics of orders skipped by the picker
+const SKIP_STAGE: &str = "order_picker";
 
-const ONE_MILLION: U256 = uint!(1_000_000_U256);
+const MIN_CAPACITY_CHECK_INTERVAL: Duration = Duration::from_secs(5);
 
 /// Maximum number of orders to cache for deduplication
 const ORDER_DEDUP_CACHE_SIZE: u64 = 5000;
 
+/// Maximum number of orders deferred by the pricing strategy at a time
+const MAX_DEFERRED_ORDERS: usize = 5000;
+
 /// In-memory LRU cache for order deduplication by ID (prevents duplicate order processing)
 type OrderCache = Arc<Cache<String, ()>>;
 
@@ -68,6 +82,9 @@ pub enum OrderPickerErr {
     #[error("{code} failed to fetch / push image: {0}", code = self.code())]
     FetchImageErr(#[source] anyhow::Error),
 
+    #[error("{code} failed to fetch / push assumption: {0}", code = self.code())]
+    FetchAssumptionErr(#[source] anyhow::Error),
+
     #[error("{code} guest panicked: {0}", code = self.code())]
     GuestPanic(String),
 
@@ -77,6 +94,9 @@ pub enum OrderPickerErr {
     #[error("{code} RPC error: {0:?}", code = self.code())]
     RpcErr(anyhow::Error),
 
+    #[error("{code} unknown pricing strategy: {0}", code = self.code())]
+    UnknownPricingStrategy(String),
+
     #[error("{code} Unexpected error: {0:?}", code = self.code())]
     UnexpectedErr(#[from] anyhow::Error),
 }
@@ -89,26 +109,43 @@ impl CodedError for OrderPickerErr {
             OrderPickerErr::GuestPanic(_) => "[B-OP-003]",
             OrderPickerErr::RequestError(_) => "[B-OP-004]",
             OrderPickerErr::RpcErr(_) => "[B-OP-005]",
+            OrderPickerErr::UnknownPricingStrategy(_) => "[B-OP-006]",
+            OrderPickerErr::FetchAssumptionErr(_) => "[B-OP-007]",
             OrderPickerErr::UnexpectedErr(_) => "[B-OP-500]",
         }
     }
 }
 
+/// Chain specific state of the [OrderPicker]
+struct PickerChain<P> {
+    provider: Arc<P>,
+    chain_monitor: Arc<ChainMonitorService<P>>,
+    market: BoundlessMarketService<Arc<P>>,
+    // Sender to the order monitor of the chain
+    priced_orders_tx: mpsc::Sender<Box<OrderRequest>>,
+    stake_token_decimals: u8,
+    stake_price_oracle: Option<StakePriceOracleObj>,
+}
+
 #[derive(Clone)]
 pub struct OrderPicker<P> {
     db: DbObj,
     config: ConfigLock,
     prover: ProverObj,
-    provider: Arc<P>,
-    chain_monitor: Arc<ChainMonitorService<P>>,
-    market: BoundlessMarketService<Arc<P>>,
+    // Chains orders are priced for, keyed by chain ID. Orders of all chains are prioritized
+    // against each other, and share the preflight capacity.
+    chains: HashMap<u64, Arc<PickerChain<P>>>,
     supported_selectors: SupportedSelectors,
     // TODO ideal not to wrap in mutex, but otherwise would require supervisor refactor, try to find alternative
     new_order_rx: Arc<Mutex<mpsc::Receiver<Box<OrderRequest>>>>,
-    priced_orders_tx: mpsc::Sender<Box<OrderRequest>>,
-    stake_token_decimals: u8,
+    pricing_strategies: PricingStrategies,
     order_cache: OrderCache,
     active_tasks: Arc<Mutex<HashMap<String, Box<OrderRequest>>>>,
+    // Orders the pricing strategy asked to re-evaluate later, keyed by order ID, with the
+    // timestamp to retry at
+    deferred_orders: Arc<Mutex<HashMap<String, (u64, Box<OrderRequest>)>>>,
+    control: BrokerControl,
+    throughput: ProvingThroughput,
 }
 
 #[derive(Debug)]
@@ -120,15 +157,33 @@ enum OrderPricingOutcome {
         target_timestamp_secs: u64,
         // TODO handle checking what time the lock should occur before, when estimating proving time.
         expiry_secs: u64,
+        // Price at the target timestamp, net of gas, in native token
+        expected_profit: I256,
     },
     // Do not lock the order, but consider proving and fulfilling it after the lock expires
     ProveAfterLockExpire {
         total_cycles: u64,
         lock_expire_timestamp_secs: u64,
         expiry_secs: u64,
+        // Stake reward, net of gas if the stake token price is known, in stake token
+        expected_profit: I256,
+    },
+    // Do not lock the order, but prove it and fulfill it before the lock expires
+    ProveWithoutLock {
+        total_cycles: u64,
+        target_timestamp_secs: u64,
+        expiry_secs: u64,
+        // Price at the target timestamp, net of gas, in native token
+        expected_profit: I256,
     },
     // Do not accept engage order
-    Skip,
+    Skip {
+        reason: SkipReason,
+    },
+    // Re-price the order once the timestamp has been reached
+    Defer {
+        retry_at_secs: u64,
+    },
 }
 
 impl<P> OrderPicker<P>
@@ -140,30 +195,26 @@ where
         db: DbObj,
         config: ConfigLock,
         prover: ProverObj,
+        chain_id: u64,
         market_addr: Address,
         provider: Arc<P>,
         chain_monitor: Arc<ChainMonitorService<P>>,
         new_order_rx: mpsc::Receiver<Box<OrderRequest>>,
         order_result_tx: mpsc::Sender<Box<OrderRequest>>,
         stake_token_decimals: u8,
+        stake_price_oracle: Option<StakePriceOracleObj>,
+        pricing_strategies: PricingStrategies,
+        control: BrokerControl,
+        throughput: ProvingThroughput,
     ) -> Self {
-        let market = BoundlessMarketService::new(
-            market_addr,
-            provider.clone(),
-            provider.default_signer_address(),
-        );
-
-        Self {
+        let picker = Self {
             db,
             config,
             prover,
-            provider,
-            chain_monitor,
-            market,
+            chains: HashMap::new(),
             supported_selectors: SupportedSelectors::default(),
             new_order_rx: Arc::new(Mutex::new(new_order_rx)),
-            priced_orders_tx: order_result_tx,
-            stake_token_decimals,
+            pricing_strategies,
             order_cache: Arc::new(
                 Cache::builder()
                     .max_capacity(ORDER_DEDUP_CACHE_SIZE)
@@ -171,7 +222,57 @@ where
                     .build(),
             ),
             active_tasks: Arc::new(Mutex::new(HashMap::new())),
-        }
+            deferred_orders: Arc::new(Mutex::new(HashMap::new())),
+            control,
+            throughput,
+        };
+        picker.with_chain(
+            chain_id,
+            market_addr,
+            provider,
+            chain_monitor,
+            order_result_tx,
+            stake_token_decimals,
+            stake_price_oracle,
+        )
+    }
+
+    /// Price the orders of an additional chain, sending the orders to lock or prove to
+    /// `order_result_tx`
+    #[allow(clippy::too_many_arguments)]
+    pub fn with_chain(
+        mut self,
+        chain_id: u64,
+        market_addr: Address,
+        provider: Arc<P>,
+        chain_monitor: Arc<ChainMonitorService<P>>,
+        order_result_tx: mpsc::Sender<Box<OrderRequest>>,
+        stake_token_decimals: u8,
+        stake_price_oracle: Option<StakePriceOracleObj>,
+    ) -> Self {
+        let market = BoundlessMarketService::new(
+            market_addr,
+            provider.clone(),
+            provider.default_signer_address(),
+        );
+        let chain = PickerChain {
+            provider,
+            chain_monitor,
+            market,
+            priced_orders_tx: order_result_tx,
+            stake_token_decimals,
+            stake_price_oracle,
+        };
+        self.chains.insert(chain_id, Arc::new(chain));
+        self
+    }
+
+    fn chain(&self, chain_id: u64) -> Result<&PickerChain<P>, OrderPickerErr> {
+        Ok(self
+            .chains
+            .get(&chain_id)
+            .map(Arc::as_ref)
+            .with_context(|| format!("No chain configured with chain ID {chain_id}"))?)
     }
 
     async fn price_order_and_update_state(
@@ -188,12 +289,14 @@ where
                     return Ok(false);
                 }
             };
+            self.record_shadow_decision(&order, &pricing_result).await;
 
             match pricing_result {
-                Ok(Lock { total_cycles, target_timestamp_secs, expiry_secs }) => {
+                Ok(Lock { total_cycles, target_timestamp_secs, expiry_secs, expected_profit }) => {
                     order.total_cycles = Some(total_cycles);
                     order.target_timestamp = Some(target_timestamp_secs);
                     order.expire_timestamp = Some(expiry_secs);
+                    order.expected_profit = Some(expected_profit);
 
                     tracing::info!(
                         "Order {order_id} scheduled for lock attempt in {}s (timestamp: {}), when price threshold met",
@@ -201,7 +304,8 @@ where
                         target_timestamp_secs,
                     );
 
-                    self.priced_orders_tx
+                    self.chain(order.chain_id)?
+                        .priced_orders_tx
                         .send(order)
                         .await
                         .context("Failed to send to order_result_tx")?;
@@ -212,33 +316,90 @@ where
                     total_cycles,
                     lock_expire_timestamp_secs,
                     expiry_secs,
+                    ..
                 }) => {
                     tracing::info!("Setting order {order_id} to prove after lock expiry at {lock_expire_timestamp_secs}");
                     order.total_cycles = Some(total_cycles);
                     order.target_timestamp = Some(lock_expire_timestamp_secs);
                     order.expire_timestamp = Some(expiry_secs);
 
-                    self.priced_orders_tx
+                    self.chain(order.chain_id)?
+                        .priced_orders_tx
+                        .send(order)
+                        .await
+                        .context("Failed to send to order_result_tx")?;
+
+                    Ok(true)
+                }
+                Ok(ProveWithoutLock {
+                    total_cycles, target_timestamp_secs, expiry_secs, ..
+                }) => {
+                    tracing::info!(
+                        "Setting order {order_id} to prove without locking at {target_timestamp_secs}"
+                    );
+                    order.total_cycles = Some(total_cycles);
+                    order.target_timestamp = Some(target_timestamp_secs);
+                    order.expire_timestamp = Some(expiry_secs);
+
+                    self.chain(order.chain_id)?
+                        .priced_orders_tx
                         .send(order)
                         .await
                         .context("Failed to send to order_result_tx")?;
 
                     Ok(true)
                 }
-                Ok(Skip) => {
-                    tracing::info!("Skipping order {order_id}");
+                Ok(Defer { retry_at_secs }) => {
+                    let mut deferred = self.deferred_orders.lock().await;
+                    let skip_reason = if retry_at_secs >= order.expiration() {
+                        tracing::info!("Skipping order {order_id}, deferred past its expiration");
+                        Some(SkipReason::Expired)
+                    } else if deferred.len() >= MAX_DEFERRED_ORDERS
+                        && !deferred.contains_key(&order_id)
+                    {
+                        tracing::warn!(
+                            "Skipping order {order_id}, {MAX_DEFERRED_ORDERS} orders are already deferred"
+                        );
+                        Some(SkipReason::Other)
+                    } else {
+                        None
+                    };
+
+                    let Some(reason) = skip_reason else {
+                        tracing::debug!(
+                            "Deferring pricing of order {order_id} until {retry_at_secs}"
+                        );
+                        deferred.insert(order_id.clone(), (retry_at_secs, order));
+                        return Ok(false);
+                    };
+                    drop(deferred);
+
+                    metrics::record_order_skipped(SKIP_STAGE, reason);
+                    if self.control.is_shadow() {
+                        shadow::record(&self.db, ShadowDecision::skip(&order, reason)).await;
+                    }
+                    self.db
+                        .insert_skipped_request(&order, reason)
+                        .await
+                        .context("Failed to add skipped order to database")?;
+                    Ok(false)
+                }
+                Ok(Skip { reason }) => {
+                    tracing::info!("Skipping order {order_id} ({reason})");
+                    metrics::record_order_skipped(SKIP_STAGE, reason);
 
                     // Add the skipped order to the database
                     self.db
-                        .insert_skipped_request(&order)
+                        .insert_skipped_request(&order, reason)
                         .await
                         .context("Failed to add skipped order to database")?;
                     Ok(false)
                 }
                 Err(err) => {
                     tracing::warn!("Failed to price order {order_id}: {err}");
+                    metrics::record_order_skipped(SKIP_STAGE, SkipReason::PricingError);
                     self.db
-                        .insert_skipped_request(&order)
+                        .insert_skipped_request(&order, SkipReason::PricingError)
                         .await
                         .context("Failed to skip failed priced order")?;
                     Ok(false)
@@ -263,55 +424,36 @@ where
         let order_id = order.id();
         tracing::debug!("Pricing order {order_id}");
 
+        if self.control.take_skipped_order(&order_id) {
+            tracing::info!("Order {order_id} was marked to be skipped by the operator, skipping");
+            return Ok(Skip { reason: SkipReason::SkippedByOperator });
+        }
+
         // Short circuit if the order has been locked.
-        if order.fulfillment_type == FulfillmentType::LockAndFulfill
-            && self
-                .db
-                .is_request_locked(U256::from(order.request.id))
-                .await
-                .context("Failed to check if request is locked before pricing")?
+        if matches!(
+            order.fulfillment_type,
+            FulfillmentType::LockAndFulfill | FulfillmentType::FulfillWithoutLocking
+        ) && self
+            .db
+            .is_request_locked(order.chain_id, U256::from(order.request.id))
+            .await
+            .context("Failed to check if request is locked before pricing")?
         {
             tracing::debug!("Order {order_id} is already locked, skipping");
-            return Ok(Skip);
+            return Ok(Skip { reason: SkipReason::AlreadyLocked });
         }
 
-        if order.fulfillment_type == FulfillmentType::FulfillAfterLockExpire
-            && self
-                .db
-                .is_request_fulfilled(U256::from(order.request.id))
-                .await
-                .context("Failed to check if request is fulfilled before pricing")?
+        if matches!(
+            order.fulfillment_type,
+            FulfillmentType::FulfillAfterLockExpire | FulfillmentType::FulfillWithoutLocking
+        ) && self
+            .db
+            .is_request_fulfilled(order.chain_id, U256::from(order.request.id))
+            .await
+            .context("Failed to check if request is fulfilled before pricing")?
         {
             tracing::debug!("Order {order_id} is already fulfilled, skipping");
-            return Ok(Skip);
-        }
-
-        let (min_deadline, allowed_addresses_opt, denied_addresses_opt) = {
-            let config = self.config.lock_all().context("Failed to read config")?;
-            (
-                config.market.min_deadline,
-                config.market.allow_client_addresses.clone(),
-                config.market.deny_requestor_addresses.clone(),
-            )
-        };
-
-        // Initial sanity checks:
-        if let Some(allow_addresses) = allowed_addresses_opt {
-            let client_addr = order.request.client_address();
-            if !allow_addresses.contains(&client_addr) {
-                tracing::info!("Removing order {order_id} from {client_addr} because it is not in allowed addrs");
-                return Ok(Skip);
-            }
-        }
-
-        if let Some(deny_addresses) = denied_addresses_opt {
-            let client_addr = order.request.client_address();
-            if deny_addresses.contains(&client_addr) {
-                tracing::info!(
-                    "Removing order {order_id} from {client_addr} because it is in denied addrs"
-                );
-                return Ok(Skip);
-            }
+            return Ok(Skip { reason: SkipReason::AlreadyFulfilled });
         }
 
         if !self.supported_selectors.is_supported(order.request.requirements.selector) {
@@ -319,60 +461,51 @@ where
                 "Removing order {order_id} because it has an unsupported selector requirement"
             );
 
-            return Ok(Skip);
+            return Ok(Skip { reason: SkipReason::UnsupportedSelector });
         };
 
-        // Lock expiration is the timestamp before which the order must be filled in order to avoid slashing
-        let lock_expiration =
-            order.request.offer.biddingStart + order.request.offer.lockTimeout as u64;
-        // order expiration is the timestamp after which the order can no longer be filled by anyone.
-        let order_expiration =
-            order.request.offer.biddingStart + order.request.offer.timeout as u64;
-
+        let chain = self.chain(order.chain_id)?;
         let now = now_timestamp();
 
+        let rules = {
+            let config = self.config.lock_all().context("Failed to read config")?;
+            rules::evaluate(&config.market.rules, order, chain.stake_token_decimals)?
+        };
+        let rule_overrides = match rules {
+            RuleOutcome::Deny { rule } => {
+                tracing::info!("Removing order {order_id} because it is denied by rule {rule}");
+                return Ok(Skip { reason: SkipReason::Denylisted });
+            }
+            RuleOutcome::Price(overrides) => overrides,
+        };
+        order.priority_boost = rule_overrides.priority_boost;
+
         // If order_expiration > lock_expiration the period in-between is when order can be filled
         // by anyone without staking to partially claim the slashed stake
         let lock_expired = order.fulfillment_type == FulfillmentType::FulfillAfterLockExpire;
+        // Orders fulfilled without locking are priced and fulfilled in a single transaction
+        let without_locking = order.fulfillment_type == FulfillmentType::FulfillWithoutLocking;
 
-        let (expiration, lockin_stake) = if lock_expired {
-            (order_expiration, U256::ZERO)
-        } else {
-            (lock_expiration, U256::from(order.request.offer.lockStake))
-        };
+        // For lock expired orders, this is the timestamp after which the order can no longer be
+        // filled by anyone. Otherwise, it is the lock expiration, before which the order must be
+        // filled in order to avoid slashing, or to be paid when fulfilling without locking.
+        let expiration = order.expiration();
 
         if expiration <= now {
             tracing::info!("Removing order {order_id} because it has expired");
-            return Ok(Skip);
+            return Ok(Skip { reason: SkipReason::Expired });
         };
 
-        // Does the order expire within the min deadline
-        let seconds_left = expiration.saturating_sub(now);
-        if seconds_left <= min_deadline {
-            tracing::info!("Removing order {order_id} because it expires within min_deadline: {seconds_left}, min_deadline: {min_deadline}");
-            return Ok(Skip);
-        }
-
-        // Check if the stake is sane and if we can afford it
-        // For lock expired orders, we don't check the max stake because we can't lock those orders.
-        let max_stake = {
-            let config = self.config.lock_all().context("Failed to read config")?;
-            parse_ether(&config.market.max_stake).context("Failed to parse max_stake")?
-        };
-
-        if !lock_expired && lockin_stake > max_stake {
-            tracing::info!("Removing high stake order {order_id}, lock stake: {lockin_stake}, max stake: {max_stake}");
-            return Ok(Skip);
-        }
+        let strategy = self.pricing_strategy()?;
 
         // Check that we have both enough staking tokens to stake, and enough gas tokens to lock and fulfil
         // NOTE: We use the current gas price and a rough heuristic on gas costs. Its possible that
         // gas prices may go up (or down) by the time its time to fulfill. This does not aim to be
         // a tight estimate, although improving this estimate will allow for a more profit.
         let gas_price =
-            self.chain_monitor.current_gas_price().await.context("Failed to get gas price")?;
-        let order_gas = if lock_expired {
-            // No need to include lock gas if its a lock expired order
+            chain.chain_monitor.current_gas_price().await.context("Failed to get gas price")?;
+        let order_gas = if lock_expired || without_locking {
+            // No need to include lock gas if the order will not be locked
             U256::from(
                 utils::estimate_gas_to_fulfill(
                     &self.config,
@@ -393,41 +526,47 @@ where
             )
         };
         let order_gas_cost = U256::from(gas_price) * order_gas;
-        let available_gas = self.available_gas_balance().await?;
-        let available_stake = self.available_stake_balance().await?;
+        let available_gas = self.available_gas_balance(order.chain_id, chain).await?;
+        let available_stake = self.available_stake_balance(chain).await?;
         tracing::debug!(
             "Estimated {order_gas} gas to {} order {order_id}; {} ether @ {} gwei",
-            if lock_expired { "fulfill" } else { "lock and fulfill" },
+            if lock_expired || without_locking { "fulfill" } else { "lock and fulfill" },
             format_ether(order_gas_cost),
             format_units(gas_price, "gwei").unwrap()
         );
 
-        if order_gas_cost > order.request.offer.maxPrice && !lock_expired {
-            // Cannot check the gas cost for lock expired orders where the reward is a fraction of the stake
-            // TODO: This can be added once we have a price feed for the stake token in gas tokens
-            tracing::info!(
-                "Estimated gas cost to lock and fulfill order {order_id}: {} exceeds max price; max price {}",
-                format_ether(order_gas_cost),
-                format_ether(order.request.offer.maxPrice)
-            );
-            return Ok(Skip);
-        }
-
-        if order_gas_cost > available_gas {
-            tracing::warn!("Estimated there will be insufficient gas for order {order_id} after locking and fulfilling pending orders; available_gas {} ether", format_ether(available_gas));
-            return Ok(Skip);
-        }
+        // Lock expired orders are paid in stake tokens, priced in native token to cover gas
+        let stake_token_price = match &chain.stake_price_oracle {
+            Some(oracle) if lock_expired => {
+                Some(oracle.price().await.context("Failed to get stake token price")?)
+            }
+            _ => None,
+        };
 
-        if !lock_expired && lockin_stake > available_stake {
-            tracing::warn!(
-                "Insufficient available stake to lock order {order_id}. Requires {lockin_stake}, has {available_stake}"
-            );
-            return Ok(Skip);
-        }
+        let pricing_ctx = PricingContext {
+            config: self.config.clone(),
+            now,
+            gas_price,
+            order_gas_cost,
+            available_gas,
+            available_stake,
+            stake_token_decimals: chain.stake_token_decimals,
+            stake_token_price,
+            prove_khz: self
+                .throughput
+                .prove_khz(Some(&order.request.requirements.imageId.to_string())),
+            rules: rule_overrides,
+        };
 
-        let (max_mcycle_limit, peak_prove_khz) = {
-            let config = self.config.lock_all().context("Failed to read config")?;
-            (config.market.max_mcycle_limit, config.market.peak_prove_khz)
+        let exec_limit_cycles = match strategy.preflight_limit(order, &pricing_ctx).await? {
+            PreflightDecision::Preflight { exec_limit_cycles } => exec_limit_cycles,
+            PreflightDecision::Skip { reason } => {
+                tracing::debug!(
+                    "Pricing strategy skipped order {order_id} before preflight: {reason}"
+                );
+                return Ok(Skip { reason });
+            }
+            PreflightDecision::Defer { retry_at } => return Ok(Defer { retry_at_secs: retry_at }),
         };
 
         // TODO: Move URI handling like this into the prover impls
@@ -435,124 +574,221 @@ where
             .await
             .map_err(OrderPickerErr::FetchImageErr)?;
 
-        let input_id = upload_input_uri(&self.prover, &order.request, &self.config)
-            .await
-            .map_err(OrderPickerErr::FetchInputErr)?;
+        let (input_id, input_digest, assumptions) =
+            upload_input_uri(&self.prover, &order.request, &self.config)
+                .await
+                .map_err(OrderPickerErr::FetchInputErr)?;
+        let input_digest = input_digest.to_string();
+
+        let Some(assumption_ids) = upload_assumptions(
+            &self.prover,
+            &chain.market,
+            &order.request,
+            &assumptions,
+            &self.config,
+        )
+        .await
+        .map_err(OrderPickerErr::FetchAssumptionErr)?
+        else {
+            tracing::info!(
+                "Removing order {order_id} because it has an assumption that cannot be resolved"
+            );
+            return Ok(Skip { reason: SkipReason::UnsupportedAssumption });
+        };
+        if !assumption_ids.is_empty() {
+            tracing::debug!(
+                "Uploaded {} assumption receipts for order {order_id}",
+                assumption_ids.len()
+            );
+        }
 
         order.image_id = Some(image_id.clone());
         order.input_id = Some(input_id.clone());
+        order.assumption_ids = assumption_ids.clone();
 
-        // Create a executor limit based on the max price of the order
-        let mut exec_limit_cycles: u64 = if lock_expired {
-            let min_mcycle_price_stake_token = {
-                let config = self.config.lock_all().context("Failed to read config")?;
-                parse_units(&config.market.mcycle_price_stake_token, self.stake_token_decimals)
-                    .context("Failed to parse mcycle_price")?
-                    .into()
-            };
+        // Fetching the image and input takes time, after which the order may expire within
+        // min_deadline, leaving no time to preflight or prove it.
+        let Some(preflight_timeout) = self.preflight_timeout(expiration)? else {
+            tracing::info!(
+                "Removing order {order_id} because it expires within min_deadline before preflight"
+            );
+            return Ok(Skip { reason: SkipReason::MinDeadline });
+        };
 
-            if min_mcycle_price_stake_token == U256::ZERO {
-                tracing::warn!("min_mcycle_price_stake_token is 0, setting unlimited exec limit");
-                u64::MAX
-            } else {
-                // Note this does not account for gas cost unlike a normal order
-                // TODO: Update to account for gas once the stake token to gas token exchange rate is known
-                let price = order.request.offer.stake_reward_if_locked_and_not_fulfilled();
-                // (stake price * 1_000_000) / stake mcycle price = max cycles
-                (price.saturating_mul(ONE_MILLION).div_ceil(min_mcycle_price_stake_token))
-                    .try_into()
-                    .context("Failed to convert U256 exec limit to u64")?
+        let cached =
+            preflight_cache::lookup(&self.db, &image_id, &input_digest, exec_limit_cycles).await;
+        let preflight = match cached {
+            Some(outcome) => {
+                tracing::debug!(
+                    "Reusing cached preflight of image {image_id} and input {input_digest} for order {order_id}"
+                );
+                outcome
+            }
+            None => {
+                let outcome = match self
+                    .preflight(
+                        &order_id,
+                        &image_id,
+                        &input_id,
+                        assumption_ids,
+                        exec_limit_cycles,
+                        preflight_timeout,
+                    )
+                    .await?
+                {
+                    Some(outcome) => outcome,
+                    None => return Ok(Skip { reason: SkipReason::PreflightTimeout }),
+                };
+                preflight_cache::store(&self.db, &image_id, &input_digest, outcome.clone()).await;
+                outcome
             }
-        } else {
-            let min_mcycle_price = {
-                let config = self.config.lock_all().context("Failed to read config")?;
-                parse_ether(&config.market.mcycle_price).context("Failed to parse mcycle_price")?
-            };
-            // ((max_price - gas_cost) * 1_000_000) / mcycle_price = max cycles
-            (U256::from(order.request.offer.maxPrice)
-                .saturating_sub(order_gas_cost)
-                .saturating_mul(ONE_MILLION)
-                / min_mcycle_price)
-                .try_into()
-                .context("Failed to convert U256 exec limit to u64")?
         };
 
-        if exec_limit_cycles < 2 {
-            // Exec limit is based on user cycles, and 2 is the minimum number of user cycles for a
-            // provable execution.
-            // TODO when/if total cycle limit is allowed in future, update this to be total cycle min
-            tracing::info!("Removing order {order_id} because its exec limit is too low");
+        let (proof_res, journal) = match preflight {
+            PreflightOutcome::Completed { segments, user_cycles, total_cycles, journal } => {
+                let stats =
+                    ExecutorResp { segments, user_cycles, total_cycles, assumption_count: 0 };
+                (ProofResult { id: String::new(), stats, elapsed_time: 0.0 }, journal.to_vec())
+            }
+            PreflightOutcome::SessionLimit { .. } => {
+                tracing::debug!("Skipping order {order_id} due to session limit exceeded");
+                return Ok(Skip { reason: SkipReason::SessionLimit });
+            }
+            PreflightOutcome::GuestPanic { message } => {
+                return Err(OrderPickerErr::GuestPanic(message));
+            }
+        };
 
-            return Ok(Skip);
-        } else {
-            tracing::trace!("exec limit cycles for order {order_id}: {}", exec_limit_cycles);
+        // Validate the predicates:
+        if !order.request.requirements.predicate.eval(journal.clone()) {
+            tracing::info!("Order {order_id} predicate check failed, skipping");
+            return Ok(Skip { reason: SkipReason::PredicateFailed });
         }
 
-        let priority_requestor_addresses = {
-            let config = self.config.lock_all().context("Failed to read config")?;
-            config.market.priority_requestor_addresses.clone()
+        let decision = strategy.price(order, &proof_res, &journal, &pricing_ctx).await?;
+        let target_timestamp_secs = match decision {
+            PricingDecision::Accept { target_timestamp_secs } => target_timestamp_secs,
+            PricingDecision::Skip { reason } => {
+                tracing::debug!("Pricing strategy skipped order {order_id}: {reason}");
+                return Ok(Skip { reason });
+            }
+            PricingDecision::Defer { retry_at } => return Ok(Defer { retry_at_secs: retry_at }),
         };
 
-        let mut skip_mcycle_limit = false;
-        let client_addr = order.request.client_address();
-        if let Some(allow_addresses) = priority_requestor_addresses {
-            if allow_addresses.contains(&client_addr) {
-                skip_mcycle_limit = true;
+        let total_cycles = proof_res.stats.total_cycles;
+        if lock_expired {
+            let expected_profit =
+                I256::from_raw(DefaultPricingStrategy::lock_expired_reward(order, &pricing_ctx));
+            Ok(ProveAfterLockExpire {
+                total_cycles,
+                lock_expire_timestamp_secs: target_timestamp_secs,
+                expiry_secs: order.request.expires_at(),
+                expected_profit,
+            })
+        } else {
+            let price = order
+                .request
+                .offer
+                .price_at(target_timestamp_secs.max(now))
+                .context("Failed to get order price at target timestamp")?;
+            let expected_profit =
+                I256::from_raw(price).saturating_sub(I256::from_raw(order_gas_cost));
+            if without_locking {
+                Ok(ProveWithoutLock {
+                    total_cycles,
+                    target_timestamp_secs,
+                    expiry_secs: order.request.lock_expires_at(),
+                    expected_profit,
+                })
+            } else {
+                Ok(Lock {
+                    total_cycles,
+                    target_timestamp_secs,
+                    expiry_secs: order.request.lock_expires_at(),
+                    expected_profit,
+                })
             }
         }
+    }
 
-        // If the order is from a priority requestor address, skip the mcycle limit
-        // If a max_mcycle_limit is configured, override the exec limit if the order is over that limit
-        if skip_mcycle_limit {
-            exec_limit_cycles = u64::MAX;
-            tracing::debug!("Order {order_id} exec limit skipped due to client {} being part of priority_requestor_addresses.", client_addr);
-        } else if let Some(config_mcycle_limit) = max_mcycle_limit {
-            let config_cycle_limit = config_mcycle_limit.saturating_mul(1_000_000);
-            if exec_limit_cycles >= config_cycle_limit {
-                tracing::debug!("Order {order_id} exec limit computed from max price {} exceeds config max_mcycle_limit {}, setting exec limit to max_mcycle_limit", exec_limit_cycles / 1_000_000, config_mcycle_limit);
-                exec_limit_cycles = config_cycle_limit;
-            }
+    /// Record the pricing outcome of an order when running in shadow mode
+    async fn record_shadow_decision(
+        &self,
+        order: &OrderRequest,
+        outcome: &Result<OrderPricingOutcome, OrderPickerErr>,
+    ) {
+        if !self.control.is_shadow() {
+            return;
         }
-
-        // Cap the exec limit based on the peak prove khz and the time until expiration.
-        if let Some(peak_prove_khz) = peak_prove_khz {
-            let time_until_expiration = expiration.saturating_sub(now);
-            let deadline_cycle_limit =
-                calculate_max_cycles_for_time(peak_prove_khz, time_until_expiration);
-
-            if exec_limit_cycles > deadline_cycle_limit {
-                tracing::debug!(
-                    "Order {order_id} preflight cycle limit adjusted to {} cycles (capped by {:.1}s fulfillment deadline at {} peak_prove_khz config)",
-                    deadline_cycle_limit,
-                    time_until_expiration,
-                    peak_prove_khz
-                );
-                exec_limit_cycles = deadline_cycle_limit;
+        let decision = match outcome {
+            Ok(Lock { expected_profit, .. }) => ShadowDecision::new(order, ShadowAction::Lock)
+                .with_expected_profit(*expected_profit),
+            Ok(ProveAfterLockExpire { expected_profit, .. }) => {
+                ShadowDecision::new(order, ShadowAction::ProveAfterLockExpire)
+                    .with_expected_profit(*expected_profit)
             }
-        }
+            Ok(ProveWithoutLock { expected_profit, .. }) => {
+                ShadowDecision::new(order, ShadowAction::ProveWithoutLock)
+                    .with_expected_profit(*expected_profit)
+            }
+            Ok(Skip { reason }) => ShadowDecision::skip(order, *reason),
+            Ok(Defer { .. }) => return,
+            Err(_) => ShadowDecision::skip(order, SkipReason::PricingError),
+        };
+        shadow::record(&self.db, decision).await;
+    }
 
-        if exec_limit_cycles == 0 {
-            tracing::debug!("Order {order_id} has no time left to prove within deadline, skipping");
-            return Ok(Skip);
-        }
+    /// Lookup the pricing strategy currently selected in the config.
+    fn pricing_strategy(&self) -> Result<PricingStrategyObj, OrderPickerErr> {
+        let name = {
+            let config = self.config.lock_all().context("Failed to read config")?;
+            config.market.pricing_strategy.clone()
+        };
+        self.pricing_strategies.get(&name).ok_or(OrderPickerErr::UnknownPricingStrategy(name))
+    }
 
+    /// Run the preflight execution of an order with the given cycle limit
+    ///
+    /// Returns `None` if the preflight did not complete within the timeout, which depends on the
+    /// load of the prover and is therefore not a cacheable outcome.
+    async fn preflight(
+        &self,
+        order_id: &str,
+        image_id: &str,
+        input_id: &str,
+        assumption_ids: Vec<String>,
+        exec_limit_cycles: u64,
+        preflight_timeout: Duration,
+    ) -> Result<Option<PreflightOutcome>, OrderPickerErr> {
         tracing::debug!(
             "Starting preflight execution of {order_id} with limit of {} cycles (~{} mcycles)",
             exec_limit_cycles,
             exec_limit_cycles / 1_000_000
         );
-        // TODO add a future timeout here to put a upper bound on how long to preflight for
-        let proof_res = match self
-            .prover
-            .preflight(
-                &image_id,
-                &input_id,
-                vec![],
-                /* TODO assumptions */ Some(exec_limit_cycles),
-                &order_id,
-            )
-            .await
+        let preflight_start = Instant::now();
+        let preflight_res = match tokio::time::timeout(
+            preflight_timeout,
+            self.prover.preflight(
+                image_id,
+                input_id,
+                assumption_ids,
+                Some(exec_limit_cycles),
+                order_id,
+            ),
+        )
+        .await
         {
+            Ok(res) => res,
+            Err(_) => {
+                if let Err(err) = self.prover.cancel_preflight(order_id).await {
+                    tracing::warn!("Failed to cancel preflight of order {order_id}: {err:?}");
+                }
+                Err(ProverError::PreflightTimeout(preflight_timeout))
+            }
+        };
+        metrics::record_preflight_duration(preflight_start.elapsed());
+
+        let proof_res = match preflight_res {
             Ok(res) => {
                 tracing::debug!(
                     "Preflight execution of {order_id} with session id {} and {} mcycles completed in {} seconds",
@@ -567,27 +803,23 @@ where
                     if err_msg.contains("Session limit exceeded") =>
                 {
                     tracing::debug!(
-                        "Skipping order {order_id} due to session limit exceeded: {}",
-                        err_msg
+                        "Preflight of order {order_id} exceeded the session limit: {err_msg}"
+                    );
+                    return Ok(Some(PreflightOutcome::SessionLimit { exec_limit_cycles }));
+                }
+                ProverError::PreflightTimeout(timeout) => {
+                    tracing::info!(
+                        "Skipping order {order_id} because preflight did not complete within {timeout:?}"
                     );
-                    return Ok(Skip);
+                    return Ok(None);
                 }
                 ProverError::ProvingFailed(ref err_msg) if err_msg.contains("GuestPanic") => {
-                    return Err(OrderPickerErr::GuestPanic(err_msg.clone()));
+                    return Ok(Some(PreflightOutcome::GuestPanic { message: err_msg.clone() }));
                 }
                 _ => return Err(OrderPickerErr::UnexpectedErr(err.into())),
             },
         };
 
-        // If a max_mcycle_limit is configured check if the order is over that limit
-        if let Some(mcycle_limit) = max_mcycle_limit {
-            let mcycles = proof_res.stats.total_cycles / 1_000_000;
-            if !skip_mcycle_limit && mcycles >= mcycle_limit {
-                tracing::info!("Order {order_id} max_mcycle_limit check failed req: {mcycles} | config: {mcycle_limit}");
-                return Ok(Skip);
-            }
-        }
-
         let journal = self
             .prover
             .get_preflight_journal(&proof_res.id)
@@ -595,160 +827,37 @@ where
             .context("Failed to fetch preflight journal")?
             .context("Failed to find preflight journal")?;
 
-        // ensure the journal is a size we are willing to submit on-chain
-        let max_journal_bytes =
-            self.config.lock_all().context("Failed to read config")?.market.max_journal_bytes;
-        if journal.len() > max_journal_bytes {
-            tracing::info!(
-                "Order {order_id} journal larger than set limit ({} > {}), skipping",
-                journal.len(),
-                max_journal_bytes
-            );
-            return Ok(Skip);
-        }
-
-        // Validate the predicates:
-        if !order.request.requirements.predicate.eval(journal.clone()) {
-            tracing::info!("Order {order_id} predicate check failed, skipping");
-            return Ok(Skip);
-        }
-
-        self.evaluate_order(order, &proof_res, order_gas_cost, lock_expired).await
-    }
-
-    async fn evaluate_order(
-        &self,
-        order: &OrderRequest,
-        proof_res: &ProofResult,
-        order_gas_cost: U256,
-        lock_expired: bool,
-    ) -> Result<OrderPricingOutcome, OrderPickerErr> {
-        if lock_expired {
-            return self.evaluate_lock_expired_order(order, proof_res).await;
-        } else {
-            self.evaluate_lockable_order(order, proof_res, order_gas_cost).await
-        }
-    }
-
-    /// Evaluate if a regular lockable order is worth picking based on the price and the configured min mcycle price
-    async fn evaluate_lockable_order(
-        &self,
-        order: &OrderRequest,
-        proof_res: &ProofResult,
-        order_gas_cost: U256,
-    ) -> Result<OrderPricingOutcome, OrderPickerErr> {
-        let config_min_mcycle_price = {
-            let config = self.config.lock_all().context("Failed to read config")?;
-            parse_ether(&config.market.mcycle_price).context("Failed to parse mcycle_price")?
-        };
-
-        let order_id = order.id();
-        let one_mill = U256::from(1_000_000);
-
-        let mcycle_price_min = U256::from(order.request.offer.minPrice)
-            .saturating_sub(order_gas_cost)
-            .saturating_mul(one_mill)
-            / U256::from(proof_res.stats.total_cycles);
-        let mcycle_price_max = U256::from(order.request.offer.maxPrice)
-            .saturating_sub(order_gas_cost)
-            .saturating_mul(one_mill)
-            / U256::from(proof_res.stats.total_cycles);
-
-        tracing::debug!(
-            "Order {order_id} price: {}-{} ETH, {}-{} ETH per mcycle, {} stake required, {} ETH gas cost",
-            format_ether(U256::from(order.request.offer.minPrice)),
-            format_ether(U256::from(order.request.offer.maxPrice)),
-            format_ether(mcycle_price_min),
-            format_ether(mcycle_price_max),
-            format_units(U256::from(order.request.offer.lockStake), self.stake_token_decimals).unwrap_or_default(),
-            format_ether(order_gas_cost),
-        );
-
-        // Skip the order if it will never be worth it
-        if mcycle_price_max < config_min_mcycle_price {
-            tracing::debug!("Removing under priced order {order_id}");
-            return Ok(Skip);
-        }
-
-        let target_timestamp_secs = if mcycle_price_min >= config_min_mcycle_price {
-            tracing::info!(
-                "Selecting order {order_id} at price {} - ASAP",
-                format_ether(U256::from(order.request.offer.minPrice))
-            );
-            0 // Schedule the lock ASAP
-        } else {
-            let target_min_price = config_min_mcycle_price
-                .saturating_mul(U256::from(proof_res.stats.total_cycles))
-                .div_ceil(ONE_MILLION)
-                + order_gas_cost;
-            tracing::debug!(
-                "Order {order_id} minimum profitable price: {} ETH",
-                format_ether(target_min_price)
-            );
-
-            order
-                .request
-                .offer
-                .time_at_price(target_min_price)
-                .context("Failed to get target price timestamp")?
-        };
-
-        let expiry_secs = order.request.offer.biddingStart + order.request.offer.lockTimeout as u64;
-
-        Ok(Lock { total_cycles: proof_res.stats.total_cycles, target_timestamp_secs, expiry_secs })
+        Ok(Some(PreflightOutcome::Completed {
+            segments: proof_res.stats.segments,
+            user_cycles: proof_res.stats.user_cycles,
+            total_cycles: proof_res.stats.total_cycles,
+            journal: journal.into(),
+        }))
     }
 
-    /// Evaluate if a lock expired order is worth picking based on how much of the slashed stake token we can recover
-    /// and the configured min mcycle price in stake tokens
-    async fn evaluate_lock_expired_order(
-        &self,
-        order: &OrderRequest,
-        proof_res: &ProofResult,
-    ) -> Result<OrderPricingOutcome, OrderPickerErr> {
-        let config_min_mcycle_price_stake_tokens: U256 = {
+    /// Wall-clock bound on the preflight of an order expiring at `expiration`, or `None` if the
+    /// order expires within `min_deadline`
+    ///
+    /// Preflight must complete with at least `min_deadline` seconds left to prove the order, and
+    /// never runs for longer than `max_preflight_secs`.
+    fn preflight_timeout(&self, expiration: u64) -> Result<Option<Duration>, OrderPickerErr> {
+        let (max_preflight_secs, min_deadline) = {
             let config = self.config.lock_all().context("Failed to read config")?;
-            parse_units(&config.market.mcycle_price_stake_token, self.stake_token_decimals)
-                .context("Failed to parse mcycle_price")?
-                .into()
+            (config.market.max_preflight_secs, config.market.min_deadline)
         };
-
-        let total_cycles = U256::from(proof_res.stats.total_cycles);
-
-        // Reward for the order is a fraction of the stake once the lock has expired
-        let price = order.request.offer.stake_reward_if_locked_and_not_fulfilled();
-        let mcycle_price_in_stake_tokens = price.saturating_mul(ONE_MILLION) / total_cycles;
-
-        tracing::info!(
-            "Order price: {} (stake tokens) - cycles: {} - mcycle price: {} (stake tokens), config_min_mcycle_price_stake_tokens: {} (stake tokens)",
-            format_ether(price),
-            proof_res.stats.total_cycles,
-            format_ether(mcycle_price_in_stake_tokens),
-            format_ether(config_min_mcycle_price_stake_tokens),
-        );
-
-        // Skip the order if it will never be worth it
-        if mcycle_price_in_stake_tokens < config_min_mcycle_price_stake_tokens {
-            tracing::info!(
-                "Removing under priced order (slashed stake reward too low) {} (stake price {} < config min stake price {})",
-                order.id(),
-                format_ether(mcycle_price_in_stake_tokens),
-                format_ether(config_min_mcycle_price_stake_tokens)
-            );
-            return Ok(Skip);
+        let remaining_secs =
+            expiration.saturating_sub(now_timestamp()).saturating_sub(min_deadline);
+        if remaining_secs == 0 {
+            return Ok(None);
         }
-
-        Ok(ProveAfterLockExpire {
-            total_cycles: proof_res.stats.total_cycles,
-            lock_expire_timestamp_secs: order.request.offer.biddingStart
-                + order.request.offer.lockTimeout as u64,
-            expiry_secs: order.request.offer.biddingStart + order.request.offer.timeout as u64,
-        })
+        Ok(Some(Duration::from_secs(max_preflight_secs.min(remaining_secs))))
     }
 
-    /// Estimate of gas for fulfilling any orders either pending lock or locked
-    async fn estimate_gas_to_fulfill_pending(&self) -> Result<u64> {
+    /// Estimate of gas for fulfilling any orders of the chain either pending lock or locked
+    async fn estimate_gas_to_fulfill_pending(&self, chain_id: u64) -> Result<u64> {
         let mut gas = 0;
-        for order in self.db.get_committed_orders().await? {
+        let committed_orders = self.db.get_committed_orders().await?;
+        for order in committed_orders.iter().filter(|order| order.chain_id == chain_id) {
             let gas_estimate = utils::estimate_gas_to_fulfill(
                 &self.config,
                 &self.supported_selectors,
@@ -761,25 +870,29 @@ where
         Ok(gas)
     }
 
-    /// Estimate the total gas tokens reserved to lock and fulfill all pending orders
-    async fn gas_balance_reserved(&self) -> Result<U256> {
+    /// Estimate the total gas tokens reserved to lock and fulfill all pending orders of the chain
+    async fn gas_balance_reserved(&self, chain_id: u64, chain: &PickerChain<P>) -> Result<U256> {
         let gas_price =
-            self.chain_monitor.current_gas_price().await.context("Failed to get gas price")?;
-        let fulfill_pending_gas = self.estimate_gas_to_fulfill_pending().await?;
+            chain.chain_monitor.current_gas_price().await.context("Failed to get gas price")?;
+        let fulfill_pending_gas = self.estimate_gas_to_fulfill_pending(chain_id).await?;
         Ok(U256::from(gas_price) * U256::from(fulfill_pending_gas))
     }
 
     /// Return available gas balance.
     ///
     /// This is defined as the balance of the signer account.
-    async fn available_gas_balance(&self) -> Result<U256, OrderPickerErr> {
-        let balance = self
+    async fn available_gas_balance(
+        &self,
+        chain_id: u64,
+        chain: &PickerChain<P>,
+    ) -> Result<U256, OrderPickerErr> {
+        let balance = chain
             .provider
-            .get_balance(self.provider.default_signer_address())
+            .get_balance(chain.provider.default_signer_address())
             .await
             .map_err(|err| OrderPickerErr::RpcErr(err.into()))?;
 
-        let gas_balance_reserved = self.gas_balance_reserved().await?;
+        let gas_balance_reserved = self.gas_balance_reserved(chain_id, chain).await?;
 
         let available = balance.saturating_sub(gas_balance_reserved);
         tracing::debug!(
@@ -795,8 +908,9 @@ where
     /// Return available stake balance.
     ///
     /// This is defined as the balance in staking tokens of the signer account minus any pending locked stake.
-    async fn available_stake_balance(&self) -> Result<U256> {
-        let balance = self.market.balance_of_stake(self.provider.default_signer_address()).await?;
+    async fn available_stake_balance(&self, chain: &PickerChain<P>) -> Result<U256> {
+        let balance =
+            chain.market.balance_of_stake(chain.provider.default_signer_address()).await?;
         Ok(balance)
     }
 }
@@ -835,6 +949,7 @@ where
                     // This channel is cancellation safe, so it's fine to use in the select!
                     Some(order) = rx.recv() => {
                         let order_id = order.id();
+                        metrics::record_order_seen(order.fulfillment_type);
                         pending_orders.push(order);
                         tracing::debug!(
                             "Queued order {} to be priced. Currently {} queued pricing tasks: {}",
@@ -865,6 +980,32 @@ where
                             tracing::debug!("Priority requestor addresses changed");
                             priority_addresses = new_priority_addresses;
                         }
+
+                        // Re-queue deferred orders that are due to be priced again, dropping
+                        // those that expired in the meantime
+                        let now = now_timestamp();
+                        let due: Vec<_> = {
+                            let mut deferred = picker.deferred_orders.lock().await;
+                            deferred.retain(|order_id, (_, order)| {
+                                let expired = order.expiration() <= now;
+                                if expired {
+                                    tracing::debug!("Dropping deferred order {order_id}, expired");
+                                }
+                                !expired
+                            });
+                            let due_ids: Vec<_> = deferred
+                                .iter()
+                                .filter(|(_, (retry_at, _))| *retry_at <= now)
+                                .map(|(order_id, _)| order_id.clone())
+                                .collect();
+                            due_ids.iter().filter_map(|order_id| deferred.remove(order_id)).collect()
+                        };
+                        for (_, order) in due {
+                            let order_id = order.id();
+                            tracing::debug!("Re-queueing deferred order {order_id} for pricing");
+                            picker.order_cache.invalidate(&order_id).await;
+                            pending_orders.push(order);
+                        }
                     }
 
                     _ = cancel_token.cancelled() => {
@@ -876,8 +1017,15 @@ where
                     }
                 }
 
-                // Process pending orders if we have capacity
-                if !pending_orders.is_empty() && tasks.len() < current_capacity {
+                // Process pending orders if we have capacity, and picking has not been paused
+                if picker.control.is_picking_paused() || picker.control.is_draining() {
+                    if !pending_orders.is_empty() {
+                        tracing::trace!(
+                            "Order picking paused, {} orders queued",
+                            pending_orders.len()
+                        );
+                    }
+                } else if !pending_orders.is_empty() && tasks.len() < current_capacity {
                     let available_capacity = current_capacity - tasks.len();
                     let selected_orders = picker.select_pricing_orders(
                         &mut pending_orders,
@@ -948,25 +1096,29 @@ where
     }
 }
 
-/// Returns the maximum cycles that can be proven within a given time period
-/// based on the proving rate provided, in khz.
-fn calculate_max_cycles_for_time(prove_khz: u64, time_seconds: u64) -> u64 {
-    (prove_khz.saturating_mul(1_000)).saturating_mul(time_seconds)
-}
-
 #[cfg(test)]
 pub(crate) mod tests {
     use std::time::Duration;
 
     use super::*;
     use crate::{
-        chain_monitor::ChainMonitorService, db::SqliteDb, provers::DefaultProver, FulfillmentType,
-        OrderStatus,
+        chain_monitor::ChainMonitorService,
+        config::{OrderRule, RuleAction},
+        db::SqliteDb,
+        pricing::PricingStrategy,
+        provers::{DefaultProver, ProofResult},
+        stake_price::FixedStakePrice,
+        FulfillmentType, OrderStatus,
     };
     use alloy::{
         network::EthereumWallet,
         node_bindings::{Anvil, AnvilInstance},
-        primitives::{address, aliases::U96, utils::parse_units, Address, Bytes, FixedBytes, B256},
+        primitives::{
+            address,
+            aliases::U96,
+            utils::{parse_ether, parse_units},
+            Address, Bytes, FixedBytes, B256,
+        },
         providers::{ext::AnvilApi, ProviderBuilder},
         signers::local::PrivateKeySigner,
     };
@@ -977,7 +1129,7 @@ pub(crate) mod tests {
     use boundless_market::storage::{MockStorageProvider, StorageProvider};
     use boundless_market_test_utils::{
         deploy_boundless_market, deploy_hit_points, ASSESSOR_GUEST_ID, ASSESSOR_GUEST_PATH,
-        ECHO_ELF, ECHO_ID,
+        ECHO_ELF, ECHO_ID, LOOP_ELF, LOOP_ID,
     };
     use risc0_ethereum_contracts::selector::Selector;
     use risc0_zkvm::sha::Digest;
@@ -1064,12 +1216,15 @@ pub(crate) mod tests {
                 target_timestamp: None,
                 image_id: None,
                 input_id: None,
+                assumption_ids: vec![],
                 expire_timestamp: None,
                 client_sig: Bytes::new(),
                 fulfillment_type: params.fulfillment_type,
                 boundless_market_address: *boundless_market_address,
                 chain_id,
                 total_cycles: None,
+                expected_profit: None,
+                priority_boost: false,
             })
         }
     }
@@ -1080,6 +1235,8 @@ pub(crate) mod tests {
         initial_hp: Option<U256>,
         config: Option<ConfigLock>,
         stake_token_decimals: Option<u8>,
+        stake_price_oracle: Option<StakePriceOracleObj>,
+        pricing_strategies: Option<PricingStrategies>,
     }
 
     impl PickerTestCtxBuilder {
@@ -1096,6 +1253,18 @@ pub(crate) mod tests {
         pub(crate) fn with_stake_token_decimals(self, decimals: u8) -> Self {
             Self { stake_token_decimals: Some(decimals), ..self }
         }
+        pub(crate) fn with_stake_price_oracle(self, oracle: StakePriceOracleObj) -> Self {
+            Self { stake_price_oracle: Some(oracle), ..self }
+        }
+        pub(crate) fn with_pricing_strategy(
+            self,
+            name: &str,
+            strategy: PricingStrategyObj,
+        ) -> Self {
+            let mut strategies = self.pricing_strategies.unwrap_or_default();
+            strategies.register(name, strategy);
+            Self { pricing_strategies: Some(strategies), ..self }
+        }
         pub(crate) async fn build(
             self,
         ) -> PickerTestCtx<impl Provider + WalletProvider + Clone + 'static> {
@@ -1156,16 +1325,23 @@ pub(crate) mod tests {
             let (_new_order_tx, new_order_rx) = mpsc::channel(TEST_CHANNEL_CAPACITY);
             let (priced_orders_tx, priced_orders_rx) = mpsc::channel(TEST_CHANNEL_CAPACITY);
 
+            let chain_id = provider.get_chain_id().await.unwrap();
+            let throughput = ProvingThroughput::load(db.clone(), config.clone()).await.unwrap();
             let picker = OrderPicker::new(
                 db.clone(),
                 config,
                 prover,
+                chain_id,
                 market_address,
                 provider.clone(),
                 chain_monitor,
                 new_order_rx,
                 priced_orders_tx,
                 self.stake_token_decimals.unwrap_or(6),
+                self.stake_price_oracle,
+                self.pricing_strategies.unwrap_or_default(),
+                BrokerControl::default(),
+                throughput,
             );
 
             PickerTestCtx {
@@ -1202,6 +1378,35 @@ pub(crate) mod tests {
         assert_eq!(priced_order.target_timestamp, Some(0));
     }
 
+    #[tokio::test]
+    #[traced_test]
+    async fn price_order_reuses_cached_preflight() {
+        let config = ConfigLock::default();
+        {
+            config.load_write().unwrap().market.mcycle_price = "0.0000001".into();
+        }
+        let mut ctx = PickerTestCtxBuilder::default().with_config(config).build().await;
+
+        // Same image and input, under a different request ID
+        let order1 = ctx.generate_next_order(Default::default()).await;
+        let order2 =
+            ctx.generate_next_order(OrderParams { order_index: 2, ..Default::default() }).await;
+
+        for order in [order1, order2] {
+            let _request_id =
+                ctx.boundless_market.submit_request(&order.request, &ctx.signer(0)).await.unwrap();
+            let locked =
+                ctx.picker.price_order_and_update_state(order, CancellationToken::new()).await;
+            assert!(locked);
+        }
+
+        let priced_order1 = ctx.priced_orders_rx.try_recv().unwrap();
+        let priced_order2 = ctx.priced_orders_rx.try_recv().unwrap();
+        assert!(priced_order1.total_cycles.is_some());
+        assert_eq!(priced_order2.total_cycles, priced_order1.total_cycles);
+        assert!(logs_contain("Reusing cached preflight of image"));
+    }
+
     #[tokio::test]
     #[traced_test]
     async fn skip_bad_predicate() {
@@ -1229,6 +1434,64 @@ pub(crate) mod tests {
         assert!(logs_contain("predicate check failed, skipping"));
     }
 
+    #[tokio::test]
+    #[traced_test]
+    async fn skip_preflight_timeout() {
+        let config = ConfigLock::default();
+        {
+            let mut cfg = config.load_write().unwrap();
+            cfg.market.mcycle_price = "0.0000001".into();
+            cfg.market.max_preflight_secs = 1;
+            // Bound the abandoned execution after the timeout
+            cfg.market.max_mcycle_limit = Some(256);
+        }
+        let ctx = PickerTestCtxBuilder::default().with_config(config).build().await;
+
+        // Loop guest that runs until it hits the executor limit
+        let mut order = ctx.generate_next_order(Default::default()).await;
+        order.request.imageUrl =
+            ctx.storage_provider.upload_program(LOOP_ELF).await.unwrap().to_string();
+        order.request.requirements =
+            order.request.requirements.clone().with_image_id(Digest::from(LOOP_ID));
+        order.request.input =
+            RequestInput::builder().write(&(u64::MAX, 0u64)).unwrap().build_inline().unwrap();
+
+        let order_id = order.id();
+        let _request_id =
+            ctx.boundless_market.submit_request(&order.request, &ctx.signer(0)).await.unwrap();
+
+        let locked = ctx.picker.price_order_and_update_state(order, CancellationToken::new()).await;
+        assert!(!locked);
+
+        let db_order = ctx.db.get_order(&order_id).await.unwrap().unwrap();
+        assert_eq!(db_order.status, OrderStatus::Skipped);
+
+        assert!(logs_contain("because preflight did not complete within 1s"));
+        assert!(logs_contain("Cancelling preflight"));
+    }
+
+    #[tokio::test]
+    async fn preflight_timeout_within_min_deadline() {
+        let config = ConfigLock::default();
+        {
+            let mut cfg = config.load_write().unwrap();
+            cfg.market.min_deadline = 100;
+            cfg.market.max_preflight_secs = 30;
+        }
+        let ctx = PickerTestCtxBuilder::default().with_config(config).build().await;
+
+        let now = now_timestamp();
+        assert_eq!(
+            ctx.picker.preflight_timeout(now + 1_000).unwrap(),
+            Some(Duration::from_secs(30))
+        );
+        let timeout = ctx.picker.preflight_timeout(now + 110).unwrap().unwrap();
+        assert!(timeout <= Duration::from_secs(10));
+        // No time is left to preflight, rather than preflight with a zero timeout
+        assert_eq!(ctx.picker.preflight_timeout(now + 100).unwrap(), None);
+        assert_eq!(ctx.picker.preflight_timeout(now.saturating_sub(1)).unwrap(), None);
+    }
+
     #[tokio::test]
     #[traced_test]
     async fn skip_unsupported_selector() {
@@ -1483,20 +1746,129 @@ pub(crate) mod tests {
         let db_order = ctx.db.get_order(&order_id).await.unwrap().unwrap();
         assert_eq!(db_order.status, OrderStatus::Skipped);
 
-        assert!(logs_contain("because it is not in allowed addrs"));
+        assert!(logs_contain("because it is not in allowed addrs"));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn skip_denied_addr() {
+        let config = ConfigLock::default();
+        let ctx = PickerTestCtxBuilder::default().with_config(config.clone()).build().await;
+        let deny_address = ctx.provider.default_signer_address();
+
+        {
+            let mut cfg = config.load_write().unwrap();
+            cfg.market.mcycle_price = "0.0000001".into();
+            cfg.market.deny_requestor_addresses = Some([deny_address].into_iter().collect());
+        }
+
+        let order = ctx.generate_next_order(Default::default()).await;
+
+        let _request_id =
+            ctx.boundless_market.submit_request(&order.request, &ctx.signer(0)).await.unwrap();
+
+        let order_id = order.id();
+        let locked = ctx.picker.price_order_and_update_state(order, CancellationToken::new()).await;
+        assert!(!locked);
+
+        let db_order = ctx.db.get_order(&order_id).await.unwrap().unwrap();
+        assert_eq!(db_order.status, OrderStatus::Skipped);
+
+        assert!(logs_contain("because it is in denied addrs"));
+    }
+
+    fn order_rule(requestor: Address, action: RuleAction) -> OrderRule {
+        OrderRule {
+            name: Some("test".into()),
+            requestors: Some(vec![requestor]),
+            image_ids: None,
+            image_url_hosts: None,
+            input_type: None,
+            selectors: None,
+            callback: None,
+            min_lock_stake: None,
+            max_lock_stake: None,
+            action,
+        }
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn skip_denied_by_rule() {
+        let config = ConfigLock::default();
+        let ctx = PickerTestCtxBuilder::default().with_config(config.clone()).build().await;
+        let requestor = ctx.provider.default_signer_address();
+
+        {
+            let mut cfg = config.load_write().unwrap();
+            cfg.market.mcycle_price = "0.0000001".into();
+            cfg.market.rules = vec![order_rule(requestor, RuleAction::Deny)];
+        }
+
+        let order = ctx.generate_next_order(Default::default()).await;
+
+        let _request_id =
+            ctx.boundless_market.submit_request(&order.request, &ctx.signer(0)).await.unwrap();
+
+        let order_id = order.id();
+        let locked = ctx.picker.price_order_and_update_state(order, CancellationToken::new()).await;
+        assert!(!locked);
+
+        let db_order = ctx.db.get_order(&order_id).await.unwrap().unwrap();
+        assert_eq!(db_order.status, OrderStatus::Skipped);
+
+        assert!(logs_contain("because it is denied by rule test"));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn price_order_with_rule_mcycle_price() {
+        let config = ConfigLock::default();
+        let mut ctx = PickerTestCtxBuilder::default().with_config(config.clone()).build().await;
+        let requestor = ctx.provider.default_signer_address();
+
+        {
+            // Under priced at the configured mcycle price
+            let mut cfg = config.load_write().unwrap();
+            cfg.market.mcycle_price = "0.1".into();
+            cfg.market.rules = vec![order_rule(
+                requestor,
+                RuleAction::McyclePrice {
+                    mcycle_price: "0.0000001".into(),
+                    mcycle_price_stake_token: None,
+                },
+            )];
+        }
+
+        let order = ctx.generate_next_order(Default::default()).await;
+
+        let _request_id =
+            ctx.boundless_market.submit_request(&order.request, &ctx.signer(0)).await.unwrap();
+
+        let locked = ctx.picker.price_order_and_update_state(order, CancellationToken::new()).await;
+        assert!(locked);
+
+        let priced_order = ctx.priced_orders_rx.try_recv().unwrap();
+        assert_eq!(priced_order.target_timestamp, Some(0));
     }
 
     #[tokio::test]
     #[traced_test]
-    async fn skip_denied_addr() {
+    async fn price_order_always_accepted_by_rule() {
         let config = ConfigLock::default();
-        let ctx = PickerTestCtxBuilder::default().with_config(config.clone()).build().await;
-        let deny_address = ctx.provider.default_signer_address();
+        let mut ctx = PickerTestCtxBuilder::default().with_config(config.clone()).build().await;
+        let requestor = ctx.provider.default_signer_address();
 
         {
+            // Neither allowed nor priced high enough, but always accepted by the rule
             let mut cfg = config.load_write().unwrap();
-            cfg.market.mcycle_price = "0.0000001".into();
-            cfg.market.deny_requestor_addresses = Some([deny_address].into_iter().collect());
+            cfg.market.mcycle_price = "0.1".into();
+            cfg.market.allow_client_addresses = Some(vec![Address::ZERO]);
+            cfg.market.rules = vec![
+                order_rule(requestor, RuleAction::AlwaysAccept),
+                order_rule(requestor, RuleAction::Deny),
+                order_rule(requestor, RuleAction::PriorityBoost),
+            ];
         }
 
         let order = ctx.generate_next_order(Default::default()).await;
@@ -1504,14 +1876,12 @@ pub(crate) mod tests {
         let _request_id =
             ctx.boundless_market.submit_request(&order.request, &ctx.signer(0)).await.unwrap();
 
-        let order_id = order.id();
         let locked = ctx.picker.price_order_and_update_state(order, CancellationToken::new()).await;
-        assert!(!locked);
-
-        let db_order = ctx.db.get_order(&order_id).await.unwrap().unwrap();
-        assert_eq!(db_order.status, OrderStatus::Skipped);
+        assert!(locked);
 
-        assert!(logs_contain("because it is in denied addrs"));
+        let priced_order = ctx.priced_orders_rx.try_recv().unwrap();
+        assert_eq!(priced_order.target_timestamp, Some(0));
+        assert!(priced_order.priority_boost);
     }
 
     #[tokio::test]
@@ -1635,7 +2005,10 @@ pub(crate) mod tests {
         let order = ctx.priced_orders_rx.try_recv().unwrap();
         ctx.db.insert_accepted_request(&order, order.request.offer.minPrice).await.unwrap();
 
-        assert_eq!(ctx.picker.estimate_gas_to_fulfill_pending().await.unwrap(), fulfill_gas);
+        assert_eq!(
+            ctx.picker.estimate_gas_to_fulfill_pending(order.chain_id).await.unwrap(),
+            fulfill_gas
+        );
 
         // add another order
         let order =
@@ -1646,7 +2019,50 @@ pub(crate) mod tests {
         ctx.db.insert_accepted_request(&order, order.request.offer.minPrice).await.unwrap();
 
         // gas estimate stacks (until estimates factor in bundling)
-        assert_eq!(ctx.picker.estimate_gas_to_fulfill_pending().await.unwrap(), 2 * fulfill_gas);
+        assert_eq!(
+            ctx.picker.estimate_gas_to_fulfill_pending(order.chain_id).await.unwrap(),
+            2 * fulfill_gas
+        );
+        // Orders of other chains do not reserve gas on this chain
+        assert_eq!(
+            ctx.picker.estimate_gas_to_fulfill_pending(order.chain_id + 1).await.unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn price_order_for_chain() {
+        let mut ctx = PickerTestCtxBuilder::default().build().await;
+        let chain_monitor = Arc::new(ChainMonitorService::new(ctx.provider.clone()).await.unwrap());
+        tokio::spawn(chain_monitor.spawn(Default::default()));
+
+        let chain_id = 1234;
+        let (priced_orders_tx, mut priced_orders_rx) = mpsc::channel(1);
+        ctx.picker = ctx.picker.with_chain(
+            chain_id,
+            *ctx.boundless_market.instance().address(),
+            ctx.provider.clone(),
+            chain_monitor,
+            priced_orders_tx,
+            6,
+            None,
+        );
+
+        // Priced orders are sent to the order monitor of their chain
+        let mut order = ctx.generate_next_order(Default::default()).await;
+        order.chain_id = chain_id;
+        let locked = ctx.picker.price_order_and_update_state(order, CancellationToken::new()).await;
+        assert!(locked);
+        assert_eq!(priced_orders_rx.try_recv().unwrap().chain_id, chain_id);
+        assert!(ctx.priced_orders_rx.try_recv().is_err());
+
+        // Orders of chains the broker does not serve are skipped
+        let mut order =
+            ctx.generate_next_order(OrderParams { order_index: 2, ..Default::default() }).await;
+        order.chain_id = chain_id + 1;
+        let locked = ctx.picker.price_order_and_update_state(order, CancellationToken::new()).await;
+        assert!(!locked);
     }
 
     #[tokio::test]
@@ -1721,6 +2137,133 @@ pub(crate) mod tests {
         assert_eq!(priced.expire_timestamp, Some(expected_expire_timestamp));
     }
 
+    #[tokio::test]
+    #[traced_test]
+    async fn price_without_locking() {
+        let config = ConfigLock::default();
+        {
+            let mut cfg = config.load_write().unwrap();
+            cfg.market.mcycle_price = "0.0000001".into();
+            cfg.market.max_stake = "0.1".into();
+        }
+        let mut ctx = PickerTestCtxBuilder::default().with_config(config).build().await;
+
+        // Stake above max_stake is irrelevant, since the order is never locked
+        let order = ctx
+            .generate_next_order(OrderParams {
+                fulfillment_type: FulfillmentType::FulfillWithoutLocking,
+                lock_stake: parse_ether("10").unwrap(),
+                ..Default::default()
+            })
+            .await;
+        let order_id = order.id();
+        let expected_expire_timestamp = order.request.lock_expires_at();
+
+        assert!(ctx.picker.price_order_and_update_state(order, CancellationToken::new()).await);
+
+        assert!(logs_contain(&format!("gas to fulfill order {order_id}")));
+        assert!(logs_contain(&format!("Setting order {order_id} to prove without locking at 0")));
+
+        let priced = ctx.priced_orders_rx.try_recv().unwrap();
+        assert_eq!(priced.fulfillment_type, FulfillmentType::FulfillWithoutLocking);
+        assert_eq!(priced.target_timestamp, Some(0));
+        assert_eq!(priced.expire_timestamp, Some(expected_expire_timestamp));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn skip_without_locking_under_premium() {
+        let config = ConfigLock::default();
+        {
+            let mut cfg = config.load_write().unwrap();
+            // Max price of 0.04 ETH, less gas, covers 2 cycles at this price, but not at double
+            cfg.market.mcycle_price = "15000".into();
+            cfg.market.fulfill_without_locking_premium_bps = 10_000;
+        }
+        let ctx = PickerTestCtxBuilder::default().with_config(config).build().await;
+
+        let order = ctx
+            .generate_next_order(OrderParams {
+                fulfillment_type: FulfillmentType::FulfillWithoutLocking,
+                ..Default::default()
+            })
+            .await;
+        let order_id = order.id();
+
+        assert!(!ctx.picker.price_order_and_update_state(order, CancellationToken::new()).await);
+
+        let db_order = ctx.db.get_order(&order_id).await.unwrap().unwrap();
+        assert_eq!(db_order.status, OrderStatus::Skipped);
+        assert!(logs_contain(&format!(
+            "Removing order {order_id} because its exec limit is too low"
+        )));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn price_locked_by_other_with_stake_price() {
+        let config = ConfigLock::default();
+        {
+            config.load_write().unwrap().market.mcycle_price_stake_token = "0.0000001".into();
+        }
+        // Stake reward is worth far more than the gas to fulfill
+        let oracle = Arc::new(FixedStakePrice::new("1000", 6).unwrap());
+        let mut ctx = PickerTestCtxBuilder::default()
+            .with_config(config)
+            .with_stake_price_oracle(oracle)
+            .build()
+            .await;
+
+        let order = ctx
+            .generate_next_order(OrderParams {
+                fulfillment_type: FulfillmentType::FulfillAfterLockExpire,
+                bidding_start: now_timestamp(),
+                lock_timeout: 1000,
+                timeout: 10000,
+                lock_stake: parse_units("0.1", 6).unwrap().into(),
+                ..Default::default()
+            })
+            .await;
+
+        assert!(ctx.picker.price_order_and_update_state(order, CancellationToken::new()).await);
+        assert!(ctx.priced_orders_rx.try_recv().is_ok());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn skip_locked_by_other_gas_exceeds_stake_reward() {
+        let config = ConfigLock::default();
+        {
+            config.load_write().unwrap().market.mcycle_price_stake_token = "0.0000001".into();
+        }
+        // Stake reward is worth far less than the gas to fulfill
+        let oracle = Arc::new(FixedStakePrice::new("0.000000001", 6).unwrap());
+        let ctx = PickerTestCtxBuilder::default()
+            .with_config(config)
+            .with_stake_price_oracle(oracle)
+            .build()
+            .await;
+
+        let order = ctx
+            .generate_next_order(OrderParams {
+                fulfillment_type: FulfillmentType::FulfillAfterLockExpire,
+                bidding_start: now_timestamp(),
+                lock_timeout: 1000,
+                timeout: 10000,
+                lock_stake: parse_units("0.1", 6).unwrap().into(),
+                ..Default::default()
+            })
+            .await;
+        let order_id = order.id();
+
+        assert!(!ctx.picker.price_order_and_update_state(order, CancellationToken::new()).await);
+        assert!(logs_contain(&format!("Estimated gas cost to fulfill order {order_id}")));
+        assert!(logs_contain("exceeds stake reward"));
+
+        let db_order = ctx.db.get_order(&order_id).await.unwrap().unwrap();
+        assert_eq!(db_order.status, OrderStatus::Skipped);
+    }
+
     #[tokio::test]
     #[traced_test]
     async fn price_locked_by_other_unprofitable() {
@@ -1918,7 +2461,7 @@ pub(crate) mod tests {
         assert_eq!(stake_reward, U256::from(1));
 
         let locked = ctx.picker.price_order(&mut order).await;
-        assert!(matches!(locked, Ok(OrderPricingOutcome::Skip)));
+        assert!(matches!(locked, Ok(OrderPricingOutcome::Skip { .. })));
 
         assert!(logs_contain(&format!(
             "Removing order {order_id} because its exec limit is too low"
@@ -1941,7 +2484,7 @@ pub(crate) mod tests {
         assert_eq!(stake_reward2, U256::from(10));
 
         let locked = ctx.picker.price_order(&mut order2).await;
-        assert!(matches!(locked, Ok(OrderPricingOutcome::Skip)));
+        assert!(matches!(locked, Ok(OrderPricingOutcome::Skip { .. })));
 
         // Stake token denom offsets the mcycle multiplier, so for 1stake/mcycle, this will be 10
         assert!(logs_contain(&format!("exec limit cycles for order {order2_id}: 10")));
@@ -1958,22 +2501,52 @@ pub(crate) mod tests {
 
         ctx.db
             .set_request_locked(
+                order.chain_id,
                 U256::from(order.request.id),
                 &ctx.provider.default_signer_address().to_string(),
                 1000,
             )
             .await?;
 
-        assert!(ctx.db.is_request_locked(U256::from(order.request.id)).await?);
+        assert!(ctx.db.is_request_locked(order.chain_id, U256::from(order.request.id)).await?);
 
         let pricing_outcome = ctx.picker.price_order(&mut order).await?;
-        assert!(matches!(pricing_outcome, OrderPricingOutcome::Skip));
+        assert!(matches!(pricing_outcome, OrderPricingOutcome::Skip { .. }));
 
         assert!(logs_contain(&format!("Order {order_id} is already locked, skipping")));
 
         Ok(())
     }
 
+    #[tokio::test]
+    #[traced_test]
+    async fn test_order_locked_on_other_chain() -> Result<()> {
+        let config = ConfigLock::default();
+        {
+            config.load_write().unwrap().market.mcycle_price = "0.0000001".into();
+        }
+        let ctx = PickerTestCtxBuilder::default().with_config(config).build().await;
+
+        let mut order = ctx.generate_next_order(Default::default()).await;
+        let order_id = order.id();
+
+        // The same request ID locked on another chain's market
+        ctx.db
+            .set_request_locked(
+                order.chain_id + 1,
+                U256::from(order.request.id),
+                &Address::ZERO.to_string(),
+                1000,
+            )
+            .await?;
+
+        let pricing_outcome = ctx.picker.price_order(&mut order).await?;
+        assert!(matches!(pricing_outcome, OrderPricingOutcome::Lock { .. }));
+        assert!(!logs_contain(&format!("Order {order_id} is already locked, skipping")));
+
+        Ok(())
+    }
+
     #[tokio::test]
     #[traced_test]
     async fn test_duplicate_order_cache() -> Result<()> {
@@ -1991,9 +2564,11 @@ pub(crate) mod tests {
             chain_id: order1.chain_id,
             image_id: order1.image_id.clone(),
             input_id: order1.input_id.clone(),
+            assumption_ids: order1.assumption_ids.clone(),
             total_cycles: order1.total_cycles,
             target_timestamp: order1.target_timestamp,
             expire_timestamp: order1.expire_timestamp,
+            expected_profit: order1.expected_profit,
         });
 
         assert_eq!(order1.id(), order2.id(), "Both orders should have the same ID");
@@ -2035,12 +2610,12 @@ pub(crate) mod tests {
             .await;
         let order_id = order.id();
 
-        ctx.db.set_request_fulfilled(U256::from(order.request.id), 1000).await?;
+        ctx.db.set_request_fulfilled(order.chain_id, U256::from(order.request.id), 1000).await?;
 
-        assert!(ctx.db.is_request_fulfilled(U256::from(order.request.id)).await?);
+        assert!(ctx.db.is_request_fulfilled(order.chain_id, U256::from(order.request.id)).await?);
 
         let pricing_outcome = ctx.picker.price_order(&mut order).await?;
-        assert!(matches!(pricing_outcome, OrderPricingOutcome::Skip));
+        assert!(matches!(pricing_outcome, OrderPricingOutcome::Skip { .. }));
 
         assert!(logs_contain(&format!("Order {order_id} is already fulfilled, skipping")));
 
@@ -2087,4 +2662,161 @@ pub(crate) mod tests {
 
         picker_task.abort();
     }
+    /// Strategy returning a fixed decision after preflight, accepting all orders before preflight.
+    struct FixedPricingStrategy(PricingDecision);
+
+    #[async_trait::async_trait]
+    impl PricingStrategy for FixedPricingStrategy {
+        async fn preflight_limit(
+            &self,
+            _order: &OrderRequest,
+            _ctx: &PricingContext,
+        ) -> Result<PreflightDecision> {
+            Ok(PreflightDecision::Preflight { exec_limit_cycles: u64::MAX })
+        }
+
+        async fn price(
+            &self,
+            _order: &OrderRequest,
+            _proof_res: &ProofResult,
+            _journal: &[u8],
+            _ctx: &PricingContext,
+        ) -> Result<PricingDecision> {
+            Ok(self.0.clone())
+        }
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn custom_pricing_strategy() -> Result<()> {
+        let config = ConfigLock::default();
+        {
+            let mut config = config.load_write().unwrap();
+            // The default strategy would skip the order at this price.
+            config.market.mcycle_price = "1".into();
+            config.market.pricing_strategy = "fixed".into();
+        }
+        let ctx = PickerTestCtxBuilder::default()
+            .with_config(config)
+            .with_pricing_strategy(
+                "fixed",
+                Arc::new(FixedPricingStrategy(PricingDecision::Accept {
+                    target_timestamp_secs: 0,
+                })),
+            )
+            .build()
+            .await;
+
+        let mut order = ctx.generate_next_order(Default::default()).await;
+        let pricing_outcome = ctx.picker.price_order(&mut order).await?;
+        assert!(matches!(
+            pricing_outcome,
+            OrderPricingOutcome::Lock { target_timestamp_secs: 0, .. }
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn unknown_pricing_strategy() {
+        let config = ConfigLock::default();
+        {
+            config.load_write().unwrap().market.pricing_strategy = "missing".into();
+        }
+        let ctx = PickerTestCtxBuilder::default().with_config(config).build().await;
+
+        let mut order = ctx.generate_next_order(Default::default()).await;
+        let pricing_outcome = ctx.picker.price_order(&mut order).await;
+        assert!(matches!(pricing_outcome, Err(OrderPickerErr::UnknownPricingStrategy(_))));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn defer_pricing_strategy() {
+        let config = ConfigLock::default();
+        {
+            config.load_write().unwrap().market.pricing_strategy = "defer".into();
+        }
+        let retry_at = now_timestamp() + 60;
+        let ctx = PickerTestCtxBuilder::default()
+            .with_config(config)
+            .with_pricing_strategy(
+                "defer",
+                Arc::new(FixedPricingStrategy(PricingDecision::Defer { retry_at })),
+            )
+            .build()
+            .await;
+
+        let order = ctx.generate_next_order(Default::default()).await;
+        let order_id = order.id();
+        let locked = ctx.picker.price_order_and_update_state(order, CancellationToken::new()).await;
+        assert!(!locked);
+
+        assert!(logs_contain(&format!("Deferring pricing of order {order_id} until {retry_at}")));
+        {
+            let deferred = ctx.picker.deferred_orders.lock().await;
+            assert_eq!(deferred.len(), 1);
+            assert_eq!(deferred[&order_id].0, retry_at);
+        }
+
+        // Deferred orders are not recorded as skipped
+        assert!(ctx.db.get_order(&order_id).await.unwrap().is_none());
+
+        // Deferring the same order again replaces its entry
+        let order = ctx.picker.deferred_orders.lock().await[&order_id].1.clone();
+        let locked = ctx.picker.price_order_and_update_state(order, CancellationToken::new()).await;
+        assert!(!locked);
+        assert_eq!(ctx.picker.deferred_orders.lock().await.len(), 1);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn defer_past_expiration() {
+        let config = ConfigLock::default();
+        {
+            config.load_write().unwrap().market.pricing_strategy = "defer".into();
+        }
+        let order_params = OrderParams::default();
+        let retry_at = order_params.bidding_start + order_params.lock_timeout as u64;
+        let ctx = PickerTestCtxBuilder::default()
+            .with_config(config)
+            .with_pricing_strategy(
+                "defer",
+                Arc::new(FixedPricingStrategy(PricingDecision::Defer { retry_at })),
+            )
+            .build()
+            .await;
+
+        let order = ctx.generate_next_order(order_params).await;
+        let order_id = order.id();
+        let locked = ctx.picker.price_order_and_update_state(order, CancellationToken::new()).await;
+        assert!(!locked);
+
+        assert!(ctx.picker.deferred_orders.lock().await.is_empty());
+        let db_order = ctx.db.get_order(&order_id).await.unwrap().unwrap();
+        assert_eq!(db_order.status, OrderStatus::Skipped);
+        assert!(logs_contain("deferred past its expiration"));
+    }
+    #[tokio::test]
+    #[traced_test]
+    async fn skip_order_marked_by_operator() -> Result<()> {
+        let config = ConfigLock::default();
+        {
+            config.load_write().unwrap().market.mcycle_price = "0.0000001".into();
+        }
+        let ctx = PickerTestCtxBuilder::default().with_config(config).build().await;
+
+        let mut order = ctx.generate_next_order(Default::default()).await;
+        let order_id = order.id();
+        ctx.picker.control.skip_order(&order_id);
+
+        let pricing_outcome = ctx.picker.price_order(&mut order).await?;
+        assert!(matches!(pricing_outcome, OrderPricingOutcome::Skip { .. }));
+        assert!(logs_contain(&format!(
+            "Order {order_id} was marked to be skipped by the operator"
+        )));
+
+        Ok(())
+    }
 }
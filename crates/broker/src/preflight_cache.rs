@@ -0,0 +1,162 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Cache of preflight results.
+//!
+//! Requestors frequently resubmit the same image and input under new request IDs. The outcome of
+//! every preflight is stored in the DB as a [PreflightCacheEntry], keyed by the image ID and the
+//! SHA-256 digest of the encoded input, and the order picker reuses it in place of executing the
+//! request again.
+
+use alloy::primitives::Bytes;
+use chrono::{serde::ts_seconds, DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::db::DbObj;
+
+/// Outcome of the preflight execution of an image and input
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum PreflightOutcome {
+    /// Execution completed
+    Completed { segments: u64, user_cycles: u64, total_cycles: u64, journal: Bytes },
+    /// Execution did not complete within the given cycle limit
+    SessionLimit { exec_limit_cycles: u64 },
+    /// The guest panicked
+    GuestPanic { message: String },
+}
+
+impl PreflightOutcome {
+    /// Check if the outcome holds for a preflight with the given cycle limit
+    pub fn applies_to(&self, exec_limit_cycles: u64) -> bool {
+        match self {
+            Self::Completed { total_cycles, .. } => *total_cycles <= exec_limit_cycles,
+            Self::SessionLimit { exec_limit_cycles: limit } => exec_limit_cycles <= *limit,
+            Self::GuestPanic { .. } => true,
+        }
+    }
+
+    /// Check if the outcome is more informative than a previously cached outcome
+    ///
+    /// Exceeding the session limit only tells us the execution takes more cycles than the limit,
+    /// so it never replaces a completed execution, nor exceeding a higher limit.
+    fn supersedes(&self, prev: &Self) -> bool {
+        match (self, prev) {
+            (
+                Self::SessionLimit { exec_limit_cycles },
+                Self::SessionLimit { exec_limit_cycles: prev },
+            ) => exec_limit_cycles > prev,
+            (Self::SessionLimit { .. }, _) => false,
+            _ => true,
+        }
+    }
+}
+
+/// Cached preflight outcome of an image and input
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PreflightCacheEntry {
+    /// Image ID, hex encoded
+    pub image_id: String,
+    /// SHA-256 digest of the encoded input, hex encoded
+    pub input_digest: String,
+    pub outcome: PreflightOutcome,
+    #[serde(with = "ts_seconds")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// Look up the cached preflight outcome of an image and input that holds for the given cycle
+/// limit, treating DB errors as a cache miss
+pub(crate) async fn lookup(
+    db: &DbObj,
+    image_id: &str,
+    input_digest: &str,
+    exec_limit_cycles: u64,
+) -> Option<PreflightOutcome> {
+    match db.get_preflight_cache(image_id, input_digest).await {
+        Ok(entry) => {
+            entry.map(|entry| entry.outcome).filter(|outcome| outcome.applies_to(exec_limit_cycles))
+        }
+        Err(err) => {
+            tracing::warn!("Failed to read preflight cache of image {image_id}: {err:?}");
+            None
+        }
+    }
+}
+
+/// Store the preflight outcome of an image and input, unless a more informative outcome is
+/// already cached. DB errors are logged rather than returned.
+pub(crate) async fn store(
+    db: &DbObj,
+    image_id: &str,
+    input_digest: &str,
+    outcome: PreflightOutcome,
+) {
+    match db.get_preflight_cache(image_id, input_digest).await {
+        Ok(Some(prev)) if !outcome.supersedes(&prev.outcome) => return,
+        Ok(_) => {}
+        Err(err) => {
+            tracing::warn!("Failed to read preflight cache of image {image_id}: {err:?}");
+            return;
+        }
+    }
+
+    let entry = PreflightCacheEntry {
+        image_id: image_id.to_string(),
+        input_digest: input_digest.to_string(),
+        outcome,
+        created_at: Utc::now(),
+    };
+    if let Err(err) = db.set_preflight_cache(&entry).await {
+        tracing::error!("Failed to write preflight cache of image {image_id}: {err:?}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn completed(total_cycles: u64) -> PreflightOutcome {
+        PreflightOutcome::Completed {
+            segments: 1,
+            user_cycles: total_cycles,
+            total_cycles,
+            journal: Bytes::new(),
+        }
+    }
+
+    #[test]
+    fn applies_to() {
+        assert!(completed(100).applies_to(100));
+        assert!(!completed(100).applies_to(99));
+
+        let session_limit = PreflightOutcome::SessionLimit { exec_limit_cycles: 100 };
+        assert!(session_limit.applies_to(100));
+        assert!(!session_limit.applies_to(101));
+
+        let panic = PreflightOutcome::GuestPanic { message: "panicked".into() };
+        assert!(panic.applies_to(0));
+        assert!(panic.applies_to(u64::MAX));
+    }
+
+    #[test]
+    fn supersedes() {
+        let low = PreflightOutcome::SessionLimit { exec_limit_cycles: 10 };
+        let high = PreflightOutcome::SessionLimit { exec_limit_cycles: 100 };
+
+        assert!(high.supersedes(&low));
+        assert!(!low.supersedes(&high));
+        assert!(!high.supersedes(&completed(1_000)));
+        assert!(completed(1_000).supersedes(&high));
+    }
+}
@@ -0,0 +1,572 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pluggable pricing for the [OrderPicker](crate::order_picker::OrderPicker).
+//!
+//! The order picker handles the mechanics of pricing an order: fetching the image and input,
+//! running preflight, and checking the predicate. The decision of whether an order is worth
+//! proving, and when to lock it, is delegated to a [PricingStrategy]. Strategies are registered
+//! by name on the [Broker](crate::Broker) and selected with `market.pricing_strategy` in
+//! `broker.toml`.
+
+use std::{collections::HashMap, sync::Arc};
+
+use alloy::{
+    primitives::{
+        utils::{format_ether, format_units, parse_ether, parse_units},
+        U256,
+    },
+    uint,
+};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+
+use crate::{
+    config::ConfigLock, provers::ProofResult, rules::RuleOverrides, stake_price::StakeTokenPrice,
+    FulfillmentType, OrderRequest, SkipReason,
+};
+
+/// Name of the built-in pricing strategy.
+pub const DEFAULT_PRICING_STRATEGY: &str = "default";
+
+const ONE_MILLION: U256 = uint!(1_000_000_U256);
+const BPS_DENOMINATOR: U256 = uint!(10_000_U256);
+
+/// Chain and balance context used to price a single order.
+///
+/// Collected by the order picker before calling into the [PricingStrategy].
+#[derive(Clone, Debug)]
+pub struct PricingContext {
+    /// Current broker configuration.
+    pub config: ConfigLock,
+    /// Current UNIX timestamp, in seconds.
+    pub now: u64,
+    /// Current gas price, in wei.
+    pub gas_price: u128,
+    /// Estimated gas cost to lock (if applicable) and fulfill the order, in wei.
+    pub order_gas_cost: U256,
+    /// Gas token balance available after accounting for committed orders, in wei.
+    pub available_gas: U256,
+    /// Stake token balance available to lock orders.
+    pub available_stake: U256,
+    /// Decimals of the stake token.
+    pub stake_token_decimals: u8,
+    /// Price of the stake token in native token, if a stake price oracle is configured.
+    ///
+    /// Only fetched for orders with an expired lock, which are paid in stake tokens.
+    pub stake_token_price: Option<StakeTokenPrice>,
+    /// Estimated proving throughput for the order, in kHz.
+    ///
+    /// Either `market.peak_prove_khz`, or measured from completed proofs when
+    /// `market.calibrate_prove_khz` is set.
+    pub prove_khz: Option<u64>,
+    /// Overrides applied to the order by the rules in `market.rules`.
+    pub rules: RuleOverrides,
+}
+
+/// Outcome of checking an order before preflight.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PreflightDecision {
+    /// Run preflight with the given executor limit, in user cycles.
+    Preflight { exec_limit_cycles: u64 },
+    /// Do not consider the order.
+    Skip { reason: SkipReason },
+    /// Re-evaluate the order at the given UNIX timestamp.
+    Defer { retry_at: u64 },
+}
+
+/// Outcome of pricing an order after preflight.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PricingDecision {
+    /// Commit to the order.
+    ///
+    /// For lock-and-fulfill orders, the order will be locked once `target_timestamp_secs` is
+    /// reached, with 0 meaning as soon as possible. For orders with an expired lock, or orders
+    /// fulfilled without locking, this is the time after which proving may begin.
+    Accept { target_timestamp_secs: u64 },
+    /// Do not commit to the order.
+    Skip { reason: SkipReason },
+    /// Re-evaluate the order at the given UNIX timestamp.
+    Defer { retry_at: u64 },
+}
+
+/// Decides which orders the broker bids on.
+#[async_trait]
+pub trait PricingStrategy {
+    /// Called before preflight to filter the order and derive the executor limit.
+    async fn preflight_limit(
+        &self,
+        order: &OrderRequest,
+        ctx: &PricingContext,
+    ) -> Result<PreflightDecision>;
+
+    /// Called after a successful preflight to decide whether, and when, to commit to the order.
+    async fn price(
+        &self,
+        order: &OrderRequest,
+        proof_res: &ProofResult,
+        journal: &[u8],
+        ctx: &PricingContext,
+    ) -> Result<PricingDecision>;
+}
+
+pub type PricingStrategyObj = Arc<dyn PricingStrategy + Send + Sync>;
+
+/// Registry of named pricing strategies.
+///
+/// Always contains the [DefaultPricingStrategy] under [DEFAULT_PRICING_STRATEGY].
+#[derive(Clone)]
+pub struct PricingStrategies {
+    strategies: HashMap<String, PricingStrategyObj>,
+}
+
+impl Default for PricingStrategies {
+    fn default() -> Self {
+        let mut strategies: HashMap<String, PricingStrategyObj> = HashMap::new();
+        strategies.insert(DEFAULT_PRICING_STRATEGY.to_string(), Arc::new(DefaultPricingStrategy));
+        Self { strategies }
+    }
+}
+
+impl PricingStrategies {
+    /// Register a strategy, replacing any existing strategy with the same name.
+    pub fn register(&mut self, name: impl Into<String>, strategy: PricingStrategyObj) {
+        self.strategies.insert(name.into(), strategy);
+    }
+
+    /// Lookup a strategy by name.
+    pub fn get(&self, name: &str) -> Option<PricingStrategyObj> {
+        self.strategies.get(name).cloned()
+    }
+}
+
+/// Returns the maximum cycles that can be proven within a given time period
+/// based on the proving rate provided, in khz.
+pub(crate) fn calculate_max_cycles_for_time(prove_khz: u64, time_seconds: u64) -> u64 {
+    (prove_khz.saturating_mul(1_000)).saturating_mul(time_seconds)
+}
+
+/// The broker's built-in pricing rules.
+///
+/// Applies the allow / deny lists, stake caps, gas cost checks and `mcycle_price` based executor
+/// limits before preflight, then the cycle and journal limits and the minimum price per mcycle
+/// after preflight. The mcycle prices and limits set by `market.rules` take precedence over the
+/// configured ones, and orders always accepted by a rule skip the price checks.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultPricingStrategy;
+
+impl DefaultPricingStrategy {
+    fn is_priority_requestor(order: &OrderRequest, ctx: &PricingContext) -> Result<bool> {
+        let config = ctx.config.lock_all().context("Failed to read config")?;
+        Ok(config
+            .market
+            .priority_requestor_addresses
+            .as_ref()
+            .is_some_and(|addrs| addrs.contains(&order.request.client_address())))
+    }
+
+    /// Max mcycles of an order, if limited
+    ///
+    /// A limit set by a rule applies to every order, while `max_mcycle_limit` does not apply to
+    /// orders from priority requestors.
+    fn max_mcycle_limit(order: &OrderRequest, ctx: &PricingContext) -> Result<Option<u64>> {
+        if let Some(mcycle_limit) = ctx.rules.max_mcycle_limit {
+            return Ok(Some(mcycle_limit));
+        }
+        if Self::is_priority_requestor(order, ctx)? {
+            return Ok(None);
+        }
+        let config = ctx.config.lock_all().context("Failed to read config")?;
+        Ok(config.market.max_mcycle_limit)
+    }
+
+    /// Minimum price per mcycle, in native token, to accept an order paid in native token
+    ///
+    /// Orders fulfilled without locking must beat `mcycle_price`, or the price set by a rule, by
+    /// the configured premium, to account for the risk of another prover locking or fulfilling
+    /// the request first.
+    fn min_mcycle_price(order: &OrderRequest, ctx: &PricingContext) -> Result<U256> {
+        let config = ctx.config.lock_all().context("Failed to read config")?;
+        let mcycle_price =
+            parse_ether(ctx.rules.mcycle_price.as_deref().unwrap_or(&config.market.mcycle_price))
+                .context("Failed to parse mcycle_price")?;
+        if order.fulfillment_type != FulfillmentType::FulfillWithoutLocking {
+            return Ok(mcycle_price);
+        }
+        let premium_bps = U256::from(config.market.fulfill_without_locking_premium_bps);
+        Ok(mcycle_price.saturating_mul(BPS_DENOMINATOR.saturating_add(premium_bps))
+            / BPS_DENOMINATOR)
+    }
+
+    /// Minimum price per mcycle, in stake token, to accept an order with an expired lock
+    fn min_mcycle_price_stake_token(ctx: &PricingContext) -> Result<U256> {
+        let config = ctx.config.lock_all().context("Failed to read config")?;
+        let mcycle_price_stake_token = ctx
+            .rules
+            .mcycle_price_stake_token
+            .as_deref()
+            .unwrap_or(&config.market.mcycle_price_stake_token);
+        Ok(parse_units(mcycle_price_stake_token, ctx.stake_token_decimals)
+            .context("Failed to parse mcycle_price")?
+            .into())
+    }
+
+    /// Evaluate if an order paid in native token is worth picking based on the price and the
+    /// configured min mcycle price
+    ///
+    /// Used both for orders we lock and orders fulfilled without locking.
+    fn price_lockable_order(
+        &self,
+        order: &OrderRequest,
+        proof_res: &ProofResult,
+        ctx: &PricingContext,
+    ) -> Result<PricingDecision> {
+        let config_min_mcycle_price = Self::min_mcycle_price(order, ctx)?;
+
+        let order_id = order.id();
+        let order_gas_cost = ctx.order_gas_cost;
+
+        let mcycle_price_min = U256::from(order.request.offer.minPrice)
+            .saturating_sub(order_gas_cost)
+            .saturating_mul(ONE_MILLION)
+            / U256::from(proof_res.stats.total_cycles);
+        let mcycle_price_max = U256::from(order.request.offer.maxPrice)
+            .saturating_sub(order_gas_cost)
+            .saturating_mul(ONE_MILLION)
+            / U256::from(proof_res.stats.total_cycles);
+
+        tracing::debug!(
+            "Order {order_id} price: {}-{} ETH, {}-{} ETH per mcycle, {} stake required, {} ETH gas cost",
+            format_ether(U256::from(order.request.offer.minPrice)),
+            format_ether(U256::from(order.request.offer.maxPrice)),
+            format_ether(mcycle_price_min),
+            format_ether(mcycle_price_max),
+            format_units(U256::from(order.request.offer.lockStake), ctx.stake_token_decimals).unwrap_or_default(),
+            format_ether(order_gas_cost),
+        );
+
+        // Skip the order if it will never be worth it
+        if mcycle_price_max < config_min_mcycle_price {
+            tracing::debug!("Removing under priced order {order_id}");
+            return Ok(PricingDecision::Skip { reason: SkipReason::UnderPriced });
+        }
+
+        let target_timestamp_secs = if mcycle_price_min >= config_min_mcycle_price {
+            tracing::info!(
+                "Selecting order {order_id} at price {} - ASAP",
+                format_ether(U256::from(order.request.offer.minPrice))
+            );
+            0 // Schedule the lock (or proving, if fulfilling without locking) ASAP
+        } else {
+            let target_min_price = config_min_mcycle_price
+                .saturating_mul(U256::from(proof_res.stats.total_cycles))
+                .div_ceil(ONE_MILLION)
+                + order_gas_cost;
+            tracing::debug!(
+                "Order {order_id} minimum profitable price: {} ETH",
+                format_ether(target_min_price)
+            );
+
+            order
+                .request
+                .offer
+                .time_at_price(target_min_price)
+                .context("Failed to get target price timestamp")?
+        };
+
+        Ok(PricingDecision::Accept { target_timestamp_secs })
+    }
+
+    /// Reward for fulfilling a lock expired order, in stake tokens
+    ///
+    /// This is the fraction of the slashed stake paid out to the prover, less the gas cost to
+    /// fulfill the order when the stake token price is known.
+    pub(crate) fn lock_expired_reward(order: &OrderRequest, ctx: &PricingContext) -> U256 {
+        let reward = order.request.offer.stake_reward_if_locked_and_not_fulfilled();
+        match ctx.stake_token_price {
+            Some(stake_price) => reward.saturating_sub(stake_price.to_stake(ctx.order_gas_cost)),
+            None => reward,
+        }
+    }
+
+    /// Evaluate if a lock expired order is worth picking based on how much of the slashed stake token we can recover
+    /// and the configured min mcycle price in stake tokens
+    fn price_lock_expired_order(
+        &self,
+        order: &OrderRequest,
+        proof_res: &ProofResult,
+        ctx: &PricingContext,
+    ) -> Result<PricingDecision> {
+        let config_min_mcycle_price_stake_tokens = Self::min_mcycle_price_stake_token(ctx)?;
+
+        let total_cycles = U256::from(proof_res.stats.total_cycles);
+
+        // Reward for the order is a fraction of the stake once the lock has expired
+        let price = Self::lock_expired_reward(order, ctx);
+        let mcycle_price_in_stake_tokens = price.saturating_mul(ONE_MILLION) / total_cycles;
+
+        tracing::info!(
+            "Order price: {} (stake tokens) - cycles: {} - mcycle price: {} (stake tokens), config_min_mcycle_price_stake_tokens: {} (stake tokens)",
+            format_ether(price),
+            proof_res.stats.total_cycles,
+            format_ether(mcycle_price_in_stake_tokens),
+            format_ether(config_min_mcycle_price_stake_tokens),
+        );
+
+        // Skip the order if it will never be worth it
+        if mcycle_price_in_stake_tokens < config_min_mcycle_price_stake_tokens {
+            tracing::info!(
+                "Removing under priced order (slashed stake reward too low) {} (stake price {} < config min stake price {})",
+                order.id(),
+                format_ether(mcycle_price_in_stake_tokens),
+                format_ether(config_min_mcycle_price_stake_tokens)
+            );
+            return Ok(PricingDecision::Skip { reason: SkipReason::UnderPriced });
+        }
+
+        Ok(PricingDecision::Accept { target_timestamp_secs: order.request.lock_expires_at() })
+    }
+}
+
+#[async_trait]
+impl PricingStrategy for DefaultPricingStrategy {
+    async fn preflight_limit(
+        &self,
+        order: &OrderRequest,
+        ctx: &PricingContext,
+    ) -> Result<PreflightDecision> {
+        let order_id = order.id();
+        let client_addr = order.request.client_address();
+        let lock_expired = order.fulfillment_type == FulfillmentType::FulfillAfterLockExpire;
+        // Only orders we lock require stake
+        let requires_stake = order.fulfillment_type == FulfillmentType::LockAndFulfill;
+
+        // Orders always accepted by a rule bypass the allow / deny lists and price checks
+        let always_accept = ctx.rules.always_accept;
+
+        let (min_deadline, allowed_addresses_opt, denied_addresses_opt, max_stake) = {
+            let config = ctx.config.lock_all().context("Failed to read config")?;
+            (
+                config.market.min_deadline,
+                config.market.allow_client_addresses.clone().filter(|_| !always_accept),
+                config.market.deny_requestor_addresses.clone().filter(|_| !always_accept),
+                parse_ether(&config.market.max_stake).context("Failed to parse max_stake")?,
+            )
+        };
+
+        if let Some(allow_addresses) = allowed_addresses_opt {
+            if !allow_addresses.contains(&client_addr) {
+                tracing::info!("Removing order {order_id} from {client_addr} because it is not in allowed addrs");
+                return Ok(PreflightDecision::Skip { reason: SkipReason::NotAllowlisted });
+            }
+        }
+
+        if let Some(deny_addresses) = denied_addresses_opt {
+            if deny_addresses.contains(&client_addr) {
+                tracing::info!(
+                    "Removing order {order_id} from {client_addr} because it is in denied addrs"
+                );
+                return Ok(PreflightDecision::Skip { reason: SkipReason::Denylisted });
+            }
+        }
+
+        // If order_expiration > lock_expiration the period in-between is when order can be filled
+        // by anyone without staking to partially claim the slashed stake
+        let expiration = order.expiration();
+        let lockin_stake =
+            if requires_stake { U256::from(order.request.offer.lockStake) } else { U256::ZERO };
+
+        // Does the order expire within the min deadline
+        let seconds_left = expiration.saturating_sub(ctx.now);
+        if seconds_left <= min_deadline {
+            tracing::info!("Removing order {order_id} because it expires within min_deadline: {seconds_left}, min_deadline: {min_deadline}");
+            return Ok(PreflightDecision::Skip { reason: SkipReason::MinDeadline });
+        }
+
+        // Check if the stake is sane and if we can afford it
+        // For lock expired orders and orders fulfilled without locking, we don't check the max
+        // stake because we don't lock those orders.
+        if requires_stake && !always_accept && lockin_stake > max_stake {
+            tracing::info!("Removing high stake order {order_id}, lock stake: {lockin_stake}, max stake: {max_stake}");
+            return Ok(PreflightDecision::Skip { reason: SkipReason::StakeTooHigh });
+        }
+
+        let order_gas_cost = ctx.order_gas_cost;
+        if always_accept {
+            tracing::debug!("Order {order_id} is always accepted by a rule, skipping price checks");
+        } else if lock_expired {
+            // The reward for lock expired orders is a fraction of the stake, which can only be
+            // compared to the gas cost given the price of the stake token.
+            if let Some(stake_price) = ctx.stake_token_price {
+                let stake_reward = order.request.offer.stake_reward_if_locked_and_not_fulfilled();
+                let stake_reward_value = stake_price.to_native(stake_reward);
+                if order_gas_cost > stake_reward_value {
+                    tracing::info!(
+                        "Estimated gas cost to fulfill order {order_id}: {} exceeds stake reward {} worth {}",
+                        format_ether(order_gas_cost),
+                        format_units(stake_reward, ctx.stake_token_decimals).unwrap_or_default(),
+                        format_ether(stake_reward_value)
+                    );
+                    return Ok(PreflightDecision::Skip { reason: SkipReason::GasExceedsPrice });
+                }
+            }
+        } else if order_gas_cost > order.request.offer.maxPrice {
+            tracing::info!(
+                "Estimated gas cost to lock and fulfill order {order_id}: {} exceeds max price; max price {}",
+                format_ether(order_gas_cost),
+                format_ether(order.request.offer.maxPrice)
+            );
+            return Ok(PreflightDecision::Skip { reason: SkipReason::GasExceedsPrice });
+        }
+
+        if order_gas_cost > ctx.available_gas {
+            tracing::warn!("Estimated there will be insufficient gas for order {order_id} after locking and fulfilling pending orders; available_gas {} ether", format_ether(ctx.available_gas));
+            return Ok(PreflightDecision::Skip { reason: SkipReason::InsufficientGas });
+        }
+
+        if requires_stake && lockin_stake > ctx.available_stake {
+            tracing::warn!(
+                "Insufficient available stake to lock order {order_id}. Requires {lockin_stake}, has {}",
+                ctx.available_stake
+            );
+            return Ok(PreflightDecision::Skip { reason: SkipReason::InsufficientStake });
+        }
+
+        // Create a executor limit based on the max price of the order
+        let mut exec_limit_cycles: u64 = if always_accept {
+            u64::MAX
+        } else if lock_expired {
+            let min_mcycle_price_stake_token = Self::min_mcycle_price_stake_token(ctx)?;
+
+            if min_mcycle_price_stake_token == U256::ZERO {
+                tracing::warn!("min_mcycle_price_stake_token is 0, setting unlimited exec limit");
+                u64::MAX
+            } else {
+                // Accounts for gas cost only if the stake token price is known
+                let price = Self::lock_expired_reward(order, ctx);
+                // (stake price * 1_000_000) / stake mcycle price = max cycles
+                (price.saturating_mul(ONE_MILLION).div_ceil(min_mcycle_price_stake_token))
+                    .try_into()
+                    .context("Failed to convert U256 exec limit to u64")?
+            }
+        } else {
+            let min_mcycle_price = Self::min_mcycle_price(order, ctx)?;
+            // ((max_price - gas_cost) * 1_000_000) / mcycle_price = max cycles
+            (U256::from(order.request.offer.maxPrice)
+                .saturating_sub(order_gas_cost)
+                .saturating_mul(ONE_MILLION)
+                / min_mcycle_price)
+                .try_into()
+                .context("Failed to convert U256 exec limit to u64")?
+        };
+
+        if exec_limit_cycles < 2 {
+            // Exec limit is based on user cycles, and 2 is the minimum number of user cycles for a
+            // provable execution.
+            // TODO when/if total cycle limit is allowed in future, update this to be total cycle min
+            tracing::info!("Removing order {order_id} because its exec limit is too low");
+
+            return Ok(PreflightDecision::Skip { reason: SkipReason::ExecLimitTooLow });
+        } else {
+            tracing::trace!("exec limit cycles for order {order_id}: {}", exec_limit_cycles);
+        }
+
+        // If the order is from a priority requestor address, skip the mcycle limit
+        // If a max_mcycle_limit is configured, override the exec limit if the order is over that limit
+        if ctx.rules.max_mcycle_limit.is_none() && Self::is_priority_requestor(order, ctx)? {
+            exec_limit_cycles = u64::MAX;
+            tracing::debug!("Order {order_id} exec limit skipped due to client {} being part of priority_requestor_addresses.", client_addr);
+        } else if let Some(config_mcycle_limit) = Self::max_mcycle_limit(order, ctx)? {
+            let config_cycle_limit = config_mcycle_limit.saturating_mul(1_000_000);
+            if exec_limit_cycles >= config_cycle_limit {
+                tracing::debug!("Order {order_id} exec limit computed from max price {} exceeds config max_mcycle_limit {}, setting exec limit to max_mcycle_limit", exec_limit_cycles / 1_000_000, config_mcycle_limit);
+                exec_limit_cycles = config_cycle_limit;
+            }
+        }
+
+        // Cap the exec limit based on the proving throughput and the time until expiration.
+        if let Some(prove_khz) = ctx.prove_khz {
+            let time_until_expiration = expiration.saturating_sub(ctx.now);
+            let deadline_cycle_limit =
+                calculate_max_cycles_for_time(prove_khz, time_until_expiration);
+
+            if exec_limit_cycles > deadline_cycle_limit {
+                tracing::debug!(
+                    "Order {order_id} preflight cycle limit adjusted to {} cycles (capped by {:.1}s fulfillment deadline at {} kHz from peak_prove_khz config or proving history)",
+                    deadline_cycle_limit,
+                    time_until_expiration,
+                    prove_khz
+                );
+                exec_limit_cycles = deadline_cycle_limit;
+            }
+        }
+
+        if exec_limit_cycles == 0 {
+            tracing::debug!("Order {order_id} has no time left to prove within deadline, skipping");
+            return Ok(PreflightDecision::Skip { reason: SkipReason::NoTimeToProve });
+        }
+
+        Ok(PreflightDecision::Preflight { exec_limit_cycles })
+    }
+
+    async fn price(
+        &self,
+        order: &OrderRequest,
+        proof_res: &ProofResult,
+        journal: &[u8],
+        ctx: &PricingContext,
+    ) -> Result<PricingDecision> {
+        let order_id = order.id();
+        let max_journal_bytes = {
+            let config = ctx.config.lock_all().context("Failed to read config")?;
+            config.market.max_journal_bytes
+        };
+
+        // If a max_mcycle_limit is configured check if the order is over that limit
+        if let Some(mcycle_limit) = Self::max_mcycle_limit(order, ctx)? {
+            let mcycles = proof_res.stats.total_cycles / 1_000_000;
+            if mcycles >= mcycle_limit {
+                tracing::info!("Order {order_id} max_mcycle_limit check failed req: {mcycles} | config: {mcycle_limit}");
+                return Ok(PricingDecision::Skip { reason: SkipReason::McycleLimit });
+            }
+        }
+
+        // ensure the journal is a size we are willing to submit on-chain
+        if journal.len() > max_journal_bytes {
+            tracing::info!(
+                "Order {order_id} journal larger than set limit ({} > {}), skipping",
+                journal.len(),
+                max_journal_bytes
+            );
+            return Ok(PricingDecision::Skip { reason: SkipReason::JournalTooLarge });
+        }
+
+        if ctx.rules.always_accept {
+            tracing::info!("Selecting order {order_id}, always accepted by a rule");
+            let target_timestamp_secs =
+                if order.fulfillment_type == FulfillmentType::FulfillAfterLockExpire {
+                    order.request.lock_expires_at()
+                } else {
+                    0
+                };
+            return Ok(PricingDecision::Accept { target_timestamp_secs });
+        }
+
+        if order.fulfillment_type == FulfillmentType::FulfillAfterLockExpire {
+            self.price_lock_expired_order(order, proof_res, ctx)
+        } else {
+            self.price_lockable_order(order, proof_res, ctx)
+        }
+    }
+}
@@ -16,7 +16,7 @@ use crate::{
     config::{OrderCommitmentPriority, OrderPricingPriority},
     order_monitor::OrderMonitor,
     order_picker::OrderPicker,
-    FulfillmentType, OrderRequest,
+    OrderRequest,
 };
 
 use rand::seq::SliceRandom;
@@ -24,7 +24,7 @@ use std::sync::Arc;
 
 /// Unified priority mode for both pricing and commitment
 #[derive(Debug, Clone, Copy)]
-enum UnifiedPriorityMode {
+pub(crate) enum UnifiedPriorityMode {
     Random,
     TimeOrdered,
     ShortestExpiry,
@@ -49,21 +49,22 @@ impl From<OrderCommitmentPriority> for UnifiedPriorityMode {
     }
 }
 
-fn sort_orders_by_priority_and_mode<T>(
+pub(crate) fn sort_orders_by_priority_and_mode<T>(
     orders: &mut Vec<T>,
     priority_addresses: Option<&[alloy::primitives::Address]>,
     mode: UnifiedPriorityMode,
 ) where
     T: AsRef<OrderRequest>,
 {
-    let Some(addresses) = priority_addresses else {
-        sort_by_mode(orders, mode);
-        return;
+    // Orders from priority requestors, or boosted by a rule, come first
+    let is_priority = |order: &OrderRequest| {
+        order.priority_boost
+            || priority_addresses
+                .is_some_and(|addresses| addresses.contains(&order.request.client_address()))
     };
 
-    let (mut priority_orders, mut regular_orders): (Vec<T>, Vec<T>) = orders
-        .drain(..)
-        .partition(|order| addresses.contains(&order.as_ref().request.client_address()));
+    let (mut priority_orders, mut regular_orders): (Vec<T>, Vec<T>) =
+        orders.drain(..).partition(|order| is_priority(order.as_ref()));
 
     sort_by_mode(&mut priority_orders, mode);
     sort_by_mode(&mut regular_orders, mode);
@@ -82,13 +83,7 @@ where
             // Already in observation time order, no sorting needed
         }
         UnifiedPriorityMode::ShortestExpiry => {
-            orders.sort_by_key(|order| {
-                let order_ref = order.as_ref();
-                match order_ref.fulfillment_type {
-                    FulfillmentType::LockAndFulfill => order_ref.request.lock_expires_at(),
-                    _ => order_ref.request.expires_at(),
-                }
-            });
+            orders.sort_by_key(|order| order.as_ref().expiration());
         }
     }
 }
@@ -139,9 +134,9 @@ mod tests {
     use std::collections::HashSet;
 
     use super::*;
-    use crate::now_timestamp;
     use crate::order_monitor::tests::setup_om_test_context;
     use crate::order_picker::tests::{OrderParams, PickerTestCtxBuilder};
+    use crate::{now_timestamp, FulfillmentType};
     use tracing_test::traced_test;
 
     #[tokio::test]
@@ -388,6 +383,31 @@ mod tests {
         assert!(orders[3].id() == order_2_id);
     }
 
+    #[tokio::test]
+    async fn test_prioritize_boosted_orders() {
+        let mut ctx = setup_om_test_context().await;
+        let current_timestamp = now_timestamp();
+
+        let order1 = ctx
+            .create_test_order(FulfillmentType::LockAndFulfill, current_timestamp, 50, 200)
+            .await;
+        let order_1_id = order1.id();
+
+        // Boosted by a rule, so committed to first despite the later expiry
+        let mut order2 = ctx
+            .create_test_order(FulfillmentType::LockAndFulfill, current_timestamp, 100, 200)
+            .await;
+        order2.priority_boost = true;
+        let order_2_id = order2.id();
+
+        let orders = vec![Arc::from(order1), Arc::from(order2)];
+        let orders =
+            ctx.monitor.prioritize_orders(orders, OrderCommitmentPriority::ShortestExpiry, None);
+
+        assert!(orders[0].id() == order_2_id);
+        assert!(orders[1].id() == order_1_id);
+    }
+
     #[tokio::test]
     #[traced_test]
     async fn test_expired_order_fulfillment_priority_random() {
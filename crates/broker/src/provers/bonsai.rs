@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::future::Future;
+use std::{collections::HashMap, future::Future, sync::Mutex};
 
 use async_trait::async_trait;
 use bonsai_sdk::{
@@ -42,6 +42,8 @@ pub struct Bonsai {
     status_poll_ms: u64,
     status_poll_retry_count: u64,
     prover_type: ProverType,
+    /// In-flight preflight sessions, keyed by order id
+    preflights: Mutex<HashMap<String, String>>,
 }
 
 impl Bonsai {
@@ -72,6 +74,7 @@ impl Bonsai {
             status_poll_ms,
             status_poll_retry_count,
             prover_type,
+            preflights: Default::default(),
         })
     }
 
@@ -285,6 +288,14 @@ impl Prover for Bonsai {
             .await
     }
 
+    async fn upload_receipt(&self, receipt: Vec<u8>) -> Result<String, ProverError> {
+        self.retry(
+            || async { Ok(self.client.upload_receipt(receipt.clone()).await?) },
+            "upload receipt",
+        )
+        .await
+    }
+
     async fn upload_image(&self, image_id: &str, image: Vec<u8>) -> Result<(), ProverError> {
         self.retry(
             || async { Ok(self.client.upload_img(image_id, image.clone()).await.map(|_| ())?) },
@@ -324,11 +335,14 @@ impl Prover for Bonsai {
                 tracing::debug!(
                     "Created session for preflight: {preflight_id:?} for order id {order_id:?} with image id {image_id} and input id {input_id}"
                 );
+                self.preflights.lock().unwrap().insert(order_id.to_string(), preflight_id.uuid.clone());
                 let poller = StatusPoller {
                     poll_sleep_ms: self.status_poll_ms,
                     retry_counts: self.status_poll_retry_count,
                 };
-                poller.poll_with_retries_session_id(&preflight_id, &self.client).await
+                let res = poller.poll_with_retries_session_id(&preflight_id, &self.client).await;
+                self.preflights.lock().unwrap().remove(order_id);
+                res
             },
             "preflight",
             |err| matches!(err, ProverError::ProverInternalError(_)),
@@ -378,6 +392,15 @@ impl Prover for Bonsai {
         poller.poll_with_retries_session_id(&proof_id, &self.client).await
     }
 
+    async fn cancel_preflight(&self, order_id: &str) -> Result<(), ProverError> {
+        let Some(preflight_id) = self.preflights.lock().unwrap().remove(order_id) else {
+            return Ok(());
+        };
+
+        tracing::debug!("Cancelling preflight {preflight_id} for order {order_id}");
+        self.cancel_stark(&preflight_id).await
+    }
+
     async fn cancel_stark(&self, proof_id: &str) -> Result<(), ProverError> {
         // TODO this is a temporary workaround to cancel a job in Bento. This should be implemented
         // and migrated to use just the Bonsai API in future versions.
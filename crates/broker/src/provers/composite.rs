@@ -0,0 +1,667 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Prover that routes work across multiple prover backends.
+//!
+//! Inputs, images and receipts are uploaded to every available backend, so that preflight and
+//! proving can run on whichever backend is least loaded relative to its throughput. Backends that
+//! fail a request are skipped for a while, and the request is retried on the next backend.
+//!
+//! IDs returned by the [CompositeProver] record the backend that holds each object, as a list of
+//! `name:id` pairs, e.g. `bento-large:<uuid>,bento-small:<uuid>`. As these IDs are stored in the
+//! broker DB, proofs in flight keep being tracked on their backend across restarts.
+
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use futures::future::join_all;
+use risc0_zkvm::Receipt;
+
+use super::{Bonsai, ProofResult, Prover, ProverError, ProverObj};
+use crate::{
+    config::{ConfigErr, ConfigLock},
+    throughput::ProvingThroughput,
+};
+
+const ID_SEPARATOR: char = ',';
+const NAME_SEPARATOR: char = ':';
+
+/// Name of the backend holding a proof, if the proof ID was issued by a [CompositeProver]
+pub(crate) fn backend_name(proof_id: &str) -> Option<&str> {
+    if proof_id.contains(ID_SEPARATOR) {
+        return None;
+    }
+    proof_id.split_once(NAME_SEPARATOR).map(|(name, _)| name)
+}
+
+/// A prover backend routed to by the [CompositeProver]
+pub struct ProverBackend {
+    /// Unique name of the backend
+    pub name: String,
+    pub prover: ProverObj,
+    /// Estimated peak proving performance of the backend, in kHz
+    pub peak_prove_khz: u64,
+}
+
+struct BackendState {
+    backend: ProverBackend,
+    /// Proofs and compressions in flight on the backend
+    in_flight: Mutex<HashSet<String>>,
+    /// Set when the backend failed a request, until it can be retried
+    unhealthy_until: Mutex<Option<Instant>>,
+}
+
+impl BackendState {
+    fn is_healthy(&self) -> bool {
+        self.unhealthy_until.lock().unwrap().is_none_or(|until| Instant::now() >= until)
+    }
+}
+
+pub struct CompositeProver {
+    backends: Vec<BackendState>,
+    retry_delay: Duration,
+    /// In-flight preflights, keyed by order id, with the index of the backend running them
+    preflights: Mutex<HashMap<String, usize>>,
+    /// Measured throughput of the backends, used in place of their configured peak
+    throughput: Option<ProvingThroughput>,
+}
+
+impl CompositeProver {
+    pub fn new(backends: Vec<ProverBackend>, retry_delay: Duration) -> Result<Self, ProverError> {
+        if backends.is_empty() {
+            return Err(ConfigErr::InvalidConfig.into());
+        }
+        let mut names = HashSet::new();
+        for backend in backends.iter() {
+            if backend.name.is_empty()
+                || backend.name.contains([ID_SEPARATOR, NAME_SEPARATOR])
+                || !names.insert(backend.name.as_str())
+            {
+                return Err(
+                    anyhow!("Invalid or duplicate prover backend name {:?}", backend.name).into()
+                );
+            }
+        }
+
+        Ok(Self {
+            backends: backends
+                .into_iter()
+                .map(|backend| BackendState {
+                    backend,
+                    in_flight: Default::default(),
+                    unhealthy_until: Default::default(),
+                })
+                .collect(),
+            retry_delay,
+            preflights: Default::default(),
+            throughput: None,
+        })
+    }
+
+    /// Score backends by the throughput measured from their completed proofs
+    pub(crate) fn with_throughput(self, throughput: ProvingThroughput) -> Self {
+        Self { throughput: Some(throughput), ..self }
+    }
+
+    /// Construct a [CompositeProver] of the Bento or Bonsai backends listed in the config
+    pub fn from_config(config: ConfigLock) -> Result<Self, ProverError> {
+        let (backend_confs, backend_retry_secs) = {
+            let config = config.lock_all()?;
+            (config.prover.backends.clone(), config.prover.backend_retry_secs)
+        };
+
+        let mut backends = Vec::with_capacity(backend_confs.len());
+        for conf in backend_confs {
+            let api_key = conf.api_key.as_deref().unwrap_or_default();
+            let prover = Bonsai::new(config.clone(), &conf.api_url, api_key)?;
+            backends.push(ProverBackend {
+                name: conf.name,
+                prover: Arc::new(prover),
+                peak_prove_khz: conf.peak_prove_khz.unwrap_or(1),
+            });
+        }
+
+        Self::new(backends, Duration::from_secs(backend_retry_secs))
+    }
+
+    fn name(&self, idx: usize) -> &str {
+        &self.backends[idx].backend.name
+    }
+
+    fn prover(&self, idx: usize) -> &ProverObj {
+        &self.backends[idx].backend.prover
+    }
+
+    fn load(&self, idx: usize) -> usize {
+        let preflights =
+            self.preflights.lock().unwrap().values().filter(|&&backend| backend == idx).count();
+        self.backends[idx].in_flight.lock().unwrap().len() + preflights
+    }
+
+    /// Load of a backend relative to its throughput, lower is better
+    fn score(&self, idx: usize) -> f64 {
+        let backend = &self.backends[idx].backend;
+        let prove_khz = match &self.throughput {
+            Some(throughput) => throughput
+                .backend_khz(&backend.name, Some(backend.peak_prove_khz))
+                .unwrap_or(backend.peak_prove_khz),
+            None => backend.peak_prove_khz,
+        };
+        (self.load(idx) + 1) as f64 / prove_khz.max(1) as f64
+    }
+
+    /// Order the given backends by preference: healthy backends first, then by score
+    fn candidates(&self, backends: impl IntoIterator<Item = usize>) -> Vec<usize> {
+        let mut candidates: Vec<_> = backends
+            .into_iter()
+            .map(|idx| (!self.backends[idx].is_healthy(), self.score(idx), idx))
+            .collect();
+        candidates.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+        candidates.into_iter().map(|(_, _, idx)| idx).collect()
+    }
+
+    /// Backends to upload to: all healthy backends, or all backends if none are healthy
+    fn upload_targets(&self) -> Vec<usize> {
+        let healthy: Vec<_> =
+            (0..self.backends.len()).filter(|&idx| self.backends[idx].is_healthy()).collect();
+        if healthy.is_empty() {
+            (0..self.backends.len()).collect()
+        } else {
+            healthy
+        }
+    }
+
+    /// Record the outcome of a request to a backend, marking it unhealthy on backend failures
+    fn record<T>(&self, idx: usize, op: &str, res: &Result<T, ProverError>) {
+        let mut unhealthy_until = self.backends[idx].unhealthy_until.lock().unwrap();
+        match res {
+            Ok(_) => *unhealthy_until = None,
+            Err(err) if is_backend_failure(err) => {
+                tracing::warn!(
+                    "Prover backend {} failed to {op}, skipping it for {:?}: {err:?}",
+                    self.name(idx),
+                    self.retry_delay
+                );
+                *unhealthy_until = Some(Instant::now() + self.retry_delay);
+            }
+            Err(_) => {}
+        }
+    }
+
+    /// Run a request on the first of the candidate backends that does not fail
+    async fn route<T, F, Fut>(
+        &self,
+        candidates: Vec<usize>,
+        op: &str,
+        f: F,
+    ) -> Result<T, ProverError>
+    where
+        F: Fn(usize) -> Fut,
+        Fut: Future<Output = Result<T, ProverError>>,
+    {
+        let mut last_err = None;
+        for idx in candidates {
+            let res = f(idx).await;
+            self.record(idx, op, &res);
+            match res {
+                Err(err) if is_backend_failure(&err) => last_err = Some(err),
+                res => return res,
+            }
+        }
+        Err(last_err.unwrap_or_else(|| ProverError::NotFound(format!("prover backend to {op}"))))
+    }
+
+    /// Run a request on the backends holding the given ID, failing over between them
+    async fn on_holders<T, F, Fut>(&self, id: &str, op: &str, f: F) -> Result<T, ProverError>
+    where
+        F: Fn(ProverObj, String) -> Fut,
+        Fut: Future<Output = Result<T, ProverError>>,
+    {
+        let holders: HashMap<_, _> = self.decode_id(id)?.into_iter().collect();
+        let candidates = self.candidates(holders.keys().copied());
+        self.route(candidates, op, |idx| f(self.prover(idx).clone(), holders[&idx].clone())).await
+    }
+
+    /// Upload to all available backends, returning the ID of the uploaded object
+    async fn upload<F, Fut>(&self, op: &str, f: F) -> Result<String, ProverError>
+    where
+        F: Fn(ProverObj) -> Fut,
+        Fut: Future<Output = Result<String, ProverError>>,
+    {
+        let targets = self.upload_targets();
+        let results = join_all(targets.iter().map(|&idx| f(self.prover(idx).clone()))).await;
+
+        let mut parts = Vec::new();
+        let mut last_err = None;
+        for (idx, res) in targets.into_iter().zip(results) {
+            self.record(idx, op, &res);
+            match res {
+                Ok(id) => parts.push((idx, id)),
+                Err(err) => last_err = Some(err),
+            }
+        }
+
+        match last_err {
+            Some(err) if parts.is_empty() => Err(err),
+            _ => Ok(self.encode_id(&parts)),
+        }
+    }
+
+    fn encode_id(&self, parts: &[(usize, String)]) -> String {
+        parts
+            .iter()
+            .map(|(idx, id)| format!("{}{NAME_SEPARATOR}{id}", self.name(*idx)))
+            .collect::<Vec<_>>()
+            .join(&ID_SEPARATOR.to_string())
+    }
+
+    fn decode_id(&self, id: &str) -> Result<Vec<(usize, String)>, ProverError> {
+        id.split(ID_SEPARATOR)
+            .map(|part| {
+                let (name, inner) = part
+                    .split_once(NAME_SEPARATOR)
+                    .ok_or_else(|| ProverError::NotFound(format!("prover backend of {id}")))?;
+                let idx = self
+                    .backends
+                    .iter()
+                    .position(|state| state.backend.name == name)
+                    .ok_or_else(|| ProverError::NotFound(format!("prover backend {name}")))?;
+                Ok((idx, inner.to_string()))
+            })
+            .collect()
+    }
+
+    /// ID of the object on the single backend holding it, e.g. a proof
+    fn decode_single_id(&self, id: &str) -> Result<(usize, String), ProverError> {
+        let mut parts = self.decode_id(id)?;
+        match parts.len() {
+            1 => Ok(parts.remove(0)),
+            _ => Err(ProverError::NotFound(format!("single prover backend of {id}"))),
+        }
+    }
+
+    /// Resolve assumption IDs on a backend, copying over receipts it does not hold
+    async fn resolve_assumptions(
+        &self,
+        idx: usize,
+        assumptions: &[String],
+    ) -> Result<Vec<String>, ProverError> {
+        let mut resolved = Vec::with_capacity(assumptions.len());
+        for assumption in assumptions {
+            if let Some((_, id)) =
+                self.decode_id(assumption)?.into_iter().find(|(holder, _)| *holder == idx)
+            {
+                resolved.push(id);
+                continue;
+            }
+
+            let receipt = self
+                .get_receipt(assumption)
+                .await?
+                .ok_or_else(|| ProverError::NotFound(format!("receipt {assumption}")))?;
+            let id = self.prover(idx).upload_receipt(bincode::serialize(&receipt)?).await?;
+            tracing::debug!("Copied receipt {assumption} to prover backend {}", self.name(idx));
+            resolved.push(id);
+        }
+        Ok(resolved)
+    }
+}
+
+/// Tracks a preflight in flight on a backend for as long as it is held
+struct PreflightGuard<'a> {
+    preflights: &'a Mutex<HashMap<String, usize>>,
+    order_id: &'a str,
+}
+
+impl<'a> PreflightGuard<'a> {
+    fn new(preflights: &'a Mutex<HashMap<String, usize>>, order_id: &'a str, idx: usize) -> Self {
+        preflights.lock().unwrap().insert(order_id.to_string(), idx);
+        Self { preflights, order_id }
+    }
+}
+
+impl Drop for PreflightGuard<'_> {
+    fn drop(&mut self) {
+        self.preflights.lock().unwrap().remove(self.order_id);
+    }
+}
+
+/// Whether an error is caused by the backend, rather than the request, and can be failed over
+fn is_backend_failure(err: &ProverError) -> bool {
+    matches!(
+        err,
+        ProverError::BonsaiErr(_)
+            | ProverError::StatusFailure
+            | ProverError::ProverInternalError(_)
+            | ProverError::UnexpectedError(_)
+    )
+}
+
+#[async_trait]
+impl Prover for CompositeProver {
+    /// Whether the backends that work is routed to hold the image, ignoring unhealthy backends
+    async fn has_image(&self, image_id: &str) -> Result<bool, ProverError> {
+        let targets = self.upload_targets();
+        let results =
+            join_all(targets.iter().map(|&idx| self.prover(idx).has_image(image_id))).await;
+
+        let mut has_image = true;
+        for (idx, res) in targets.into_iter().zip(results) {
+            self.record(idx, "check image", &res);
+            has_image &= res.unwrap_or(false);
+        }
+        Ok(has_image)
+    }
+
+    async fn upload_input(&self, input: Vec<u8>) -> Result<String, ProverError> {
+        self.upload("upload input", |prover| {
+            let input = input.clone();
+            async move { prover.upload_input(input).await }
+        })
+        .await
+    }
+
+    async fn upload_receipt(&self, receipt: Vec<u8>) -> Result<String, ProverError> {
+        self.upload("upload receipt", |prover| {
+            let receipt = receipt.clone();
+            async move { prover.upload_receipt(receipt).await }
+        })
+        .await
+    }
+
+    async fn upload_image(&self, image_id: &str, image: Vec<u8>) -> Result<(), ProverError> {
+        let results = join_all(
+            self.backends
+                .iter()
+                .map(|state| state.backend.prover.upload_image(image_id, image.clone())),
+        )
+        .await;
+
+        let mut uploaded = false;
+        let mut last_err = None;
+        for (idx, res) in results.into_iter().enumerate() {
+            self.record(idx, "upload image", &res);
+            match res {
+                Ok(()) => uploaded = true,
+                Err(err) => last_err = Some(err),
+            }
+        }
+
+        match last_err {
+            Some(err) if !uploaded => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    async fn preflight(
+        &self,
+        image_id: &str,
+        input_id: &str,
+        assumptions: Vec<String>,
+        executor_limit: Option<u64>,
+        order_id: &str,
+    ) -> Result<ProofResult, ProverError> {
+        let inputs: HashMap<_, _> = self.decode_id(input_id)?.into_iter().collect();
+        let candidates = self.candidates(inputs.keys().copied());
+
+        self.route(candidates, "preflight", |idx| {
+            let assumptions = &assumptions;
+            let inputs = &inputs;
+            async move {
+                let assumptions = self.resolve_assumptions(idx, assumptions).await?;
+                tracing::debug!(
+                    "Preflighting order {order_id} on prover backend {}",
+                    self.name(idx)
+                );
+                let _guard = PreflightGuard::new(&self.preflights, order_id, idx);
+                let mut res = self
+                    .prover(idx)
+                    .preflight(image_id, &inputs[&idx], assumptions, executor_limit, order_id)
+                    .await?;
+                res.id = self.encode_id(&[(idx, res.id)]);
+                Ok(res)
+            }
+        })
+        .await
+    }
+
+    async fn cancel_preflight(&self, order_id: &str) -> Result<(), ProverError> {
+        // The preflight may already have been dropped, e.g. on a timeout, so cancel it on every
+        // backend. Backends without a preflight of the order ignore the request.
+        let results = join_all(
+            self.backends.iter().map(|state| state.backend.prover.cancel_preflight(order_id)),
+        )
+        .await;
+        results.into_iter().collect()
+    }
+
+    async fn prove_stark(
+        &self,
+        image_id: &str,
+        input_id: &str,
+        assumptions: Vec<String>,
+    ) -> Result<String, ProverError> {
+        let inputs: HashMap<_, _> = self.decode_id(input_id)?.into_iter().collect();
+        let candidates = self.candidates(inputs.keys().copied());
+
+        self.route(candidates, "prove stark", |idx| {
+            let assumptions = &assumptions;
+            let inputs = &inputs;
+            async move {
+                let assumptions = self.resolve_assumptions(idx, assumptions).await?;
+                let proof_id =
+                    self.prover(idx).prove_stark(image_id, &inputs[&idx], assumptions).await?;
+                tracing::debug!("Proving {proof_id} on prover backend {}", self.name(idx));
+                self.backends[idx].in_flight.lock().unwrap().insert(proof_id.clone());
+                Ok(self.encode_id(&[(idx, proof_id)]))
+            }
+        })
+        .await
+    }
+
+    async fn wait_for_stark(&self, proof_id: &str) -> Result<ProofResult, ProverError> {
+        let (idx, id) = self.decode_single_id(proof_id)?;
+        let res = self.prover(idx).wait_for_stark(&id).await;
+        self.backends[idx].in_flight.lock().unwrap().remove(&id);
+        self.record(idx, "wait for stark", &res);
+
+        let mut res = res?;
+        res.id = proof_id.to_string();
+        Ok(res)
+    }
+
+    async fn cancel_stark(&self, proof_id: &str) -> Result<(), ProverError> {
+        let (idx, id) = self.decode_single_id(proof_id)?;
+        let res = self.prover(idx).cancel_stark(&id).await;
+        self.backends[idx].in_flight.lock().unwrap().remove(&id);
+        res
+    }
+
+    async fn get_receipt(&self, proof_id: &str) -> Result<Option<Receipt>, ProverError> {
+        self.on_holders(proof_id, "get receipt", |prover, id| async move {
+            prover.get_receipt(&id).await
+        })
+        .await
+    }
+
+    async fn get_preflight_journal(&self, proof_id: &str) -> Result<Option<Vec<u8>>, ProverError> {
+        self.on_holders(proof_id, "get preflight journal", |prover, id| async move {
+            prover.get_preflight_journal(&id).await
+        })
+        .await
+    }
+
+    async fn get_journal(&self, proof_id: &str) -> Result<Option<Vec<u8>>, ProverError> {
+        self.on_holders(proof_id, "get journal", |prover, id| async move {
+            prover.get_journal(&id).await
+        })
+        .await
+    }
+
+    async fn compress(&self, proof_id: &str) -> Result<String, ProverError> {
+        let (idx, id) = self.decode_single_id(proof_id)?;
+        self.backends[idx].in_flight.lock().unwrap().insert(id.clone());
+        let res = self.prover(idx).compress(&id).await;
+        self.backends[idx].in_flight.lock().unwrap().remove(&id);
+        self.record(idx, "compress", &res);
+
+        Ok(self.encode_id(&[(idx, res?)]))
+    }
+
+    async fn get_compressed_receipt(&self, proof_id: &str) -> Result<Option<Vec<u8>>, ProverError> {
+        self.on_holders(proof_id, "get compressed receipt", |prover, id| async move {
+            prover.get_compressed_receipt(&id).await
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provers::{encode_input, DefaultProver};
+    use boundless_market_test_utils::{ECHO_ELF, ECHO_ID};
+    use risc0_zkvm::sha::Digest;
+    use tokio::test;
+
+    fn composite(backends: &[(&str, u64)]) -> CompositeProver {
+        CompositeProver::new(
+            backends
+                .iter()
+                .map(|(name, peak_prove_khz)| ProverBackend {
+                    name: name.to_string(),
+                    prover: Arc::new(DefaultProver::new()),
+                    peak_prove_khz: *peak_prove_khz,
+                })
+                .collect(),
+            Duration::from_secs(60),
+        )
+        .unwrap()
+    }
+
+    fn mark_unhealthy(prover: &CompositeProver, idx: usize) {
+        *prover.backends[idx].unhealthy_until.lock().unwrap() =
+            Some(Instant::now() + Duration::from_secs(60));
+    }
+
+    #[test]
+    async fn test_invalid_backend_names() {
+        assert!(CompositeProver::new(vec![], Duration::from_secs(1)).is_err());
+        for names in [vec!["a", "a"], vec!["a:b"], vec!["a,b"], vec![""]] {
+            let backends = names
+                .into_iter()
+                .map(|name| ProverBackend {
+                    name: name.to_string(),
+                    prover: Arc::new(DefaultProver::new()),
+                    peak_prove_khz: 1,
+                })
+                .collect();
+            assert!(CompositeProver::new(backends, Duration::from_secs(1)).is_err());
+        }
+    }
+
+    #[test]
+    async fn test_backend_name() {
+        let prover = composite(&[("small", 1), ("large", 4)]);
+        let id = prover.encode_id(&[(1, "proof".into())]);
+        assert_eq!(backend_name(&id), Some("large"));
+        assert_eq!(backend_name(&prover.encode_id(&[(0, "a".into()), (1, "b".into())])), None);
+        assert_eq!(backend_name("proof"), None);
+    }
+
+    #[test]
+    async fn test_routing() {
+        let prover = composite(&[("small", 1), ("large", 4)]);
+        assert_eq!(prover.candidates(0..2), vec![1, 0]);
+
+        // Load the large backend past the small one
+        prover.backends[1].in_flight.lock().unwrap().extend((0..4).map(|i| format!("stark_{i}")));
+        assert_eq!(prover.candidates(0..2), vec![0, 1]);
+
+        // Unhealthy backends are only used as a last resort
+        mark_unhealthy(&prover, 0);
+        assert_eq!(prover.candidates(0..2), vec![1, 0]);
+        assert_eq!(prover.upload_targets(), vec![1]);
+    }
+
+    #[test]
+    async fn test_prove_across_backends() {
+        let prover = composite(&[("a", 1), ("b", 1)]);
+        let input_data = encode_input(&vec![0x41, 0x41, 0x41, 0x41]).unwrap();
+        let input_id = prover.upload_input(input_data).await.unwrap();
+        assert_eq!(prover.decode_id(&input_id).unwrap().len(), 2);
+
+        let image_id = Digest::from(ECHO_ID).to_string();
+        assert!(!prover.has_image(&image_id).await.unwrap());
+        prover.upload_image(&image_id, ECHO_ELF.to_vec()).await.unwrap();
+        assert!(prover.has_image(&image_id).await.unwrap());
+
+        let preflight =
+            prover.preflight(&image_id, &input_id, vec![], None, "test_order_id").await.unwrap();
+        let journal = prover.get_preflight_journal(&preflight.id).await.unwrap().unwrap();
+        assert_eq!(journal, vec![0x41, 0x41, 0x41, 0x41]);
+
+        // The proof is held by a single backend
+        let proof = prover.prove_and_monitor_stark(&image_id, &input_id, vec![]).await.unwrap();
+        let (idx, _) = prover.decode_single_id(&proof.id).unwrap();
+        assert!(prover.backends[idx].in_flight.lock().unwrap().is_empty());
+        assert!(prover.get_receipt(&proof.id).await.unwrap().is_some());
+
+        // Proofs on a backend that is no longer configured can't be found
+        let other = composite(&[("c", 1)]);
+        assert!(matches!(other.get_receipt(&proof.id).await, Err(ProverError::NotFound(_))));
+    }
+
+    #[test]
+    async fn test_has_image_on_healthy_backends() {
+        let prover = composite(&[("a", 1), ("b", 1)]);
+        let image_id = Digest::from(ECHO_ID).to_string();
+        prover.prover(0).upload_image(&image_id, ECHO_ELF.to_vec()).await.unwrap();
+        assert!(!prover.has_image(&image_id).await.unwrap());
+
+        // The image is not uploaded again while the backend missing it is unhealthy
+        mark_unhealthy(&prover, 1);
+        assert!(prover.has_image(&image_id).await.unwrap());
+    }
+
+    #[test]
+    async fn test_assumption_copied_between_backends() {
+        let prover = composite(&[("a", 1), ("b", 1)]);
+        let image_id = Digest::from(ECHO_ID).to_string();
+        prover.upload_image(&image_id, ECHO_ELF.to_vec()).await.unwrap();
+        let input_id = prover
+            .upload_input(encode_input(&vec![0x41, 0x41, 0x41, 0x41]).unwrap())
+            .await
+            .unwrap();
+
+        mark_unhealthy(&prover, 1);
+        let assumption =
+            prover.prove_and_monitor_stark(&image_id, &input_id, vec![]).await.unwrap();
+        assert!(assumption.id.starts_with("a:"));
+
+        *prover.backends[1].unhealthy_until.lock().unwrap() = None;
+        mark_unhealthy(&prover, 0);
+        let proof = prover
+            .prove_and_monitor_stark(&image_id, &input_id, vec![assumption.id.clone()])
+            .await
+            .unwrap();
+        assert!(proof.id.starts_with("b:"));
+    }
+}
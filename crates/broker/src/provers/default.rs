@@ -35,6 +35,8 @@ struct ProverState {
     inputs: RwLock<HashMap<String, Vec<u8>>>,
     images: RwLock<HashMap<String, Vec<u8>>>,
     proofs: RwLock<HashMap<String, ProofData>>,
+    /// In-flight preflights, keyed by order id
+    preflights: RwLock<HashMap<String, String>>,
 }
 
 #[derive(Debug, Default)]
@@ -146,6 +148,19 @@ impl Prover for DefaultProver {
         Ok(input_id)
     }
 
+    async fn upload_receipt(&self, receipt: Vec<u8>) -> Result<String, ProverError> {
+        let receipt: Receipt = bincode::deserialize(&receipt)?;
+        let receipt_id = format!("receipt_{}", Uuid::new_v4());
+
+        let mut proofs = self.state.proofs.write().await;
+        proofs.insert(
+            receipt_id.clone(),
+            ProofData { status: Status::Succeeded, receipt: Some(receipt), ..Default::default() },
+        );
+
+        Ok(receipt_id)
+    }
+
     async fn upload_image(&self, image_id: &str, image: Vec<u8>) -> Result<(), ProverError> {
         let mut images = self.state.images.write().await;
         images.insert(image_id.to_string(), image);
@@ -159,7 +174,7 @@ impl Prover for DefaultProver {
         input_id: &str,
         assumptions: Vec<String>,
         executor_limit: Option<u64>,
-        _order_id: &str,
+        order_id: &str,
     ) -> Result<ProofResult, ProverError> {
         let image = self
             .get_image(image_id)
@@ -176,12 +191,17 @@ impl Prover for DefaultProver {
 
         let proof_id = format!("execute_{}", Uuid::new_v4());
         self.state.proofs.write().await.insert(proof_id.clone(), ProofData::default());
+        self.state.preflights.write().await.insert(order_id.to_string(), proof_id.clone());
 
         let execute_result =
             DefaultProver::execute(image, input, assumption_receipts, executor_limit).await;
 
+        self.state.preflights.write().await.remove(order_id);
         let mut proofs = self.state.proofs.write().await;
         let proof = proofs.get_mut(&proof_id).unwrap();
+        if matches!(proof.status, Status::Failed) {
+            return Err(ProverError::ProvingFailed(proof.error_msg.clone()));
+        }
         match execute_result {
             Ok(info) => {
                 let stats = ExecutorResp {
@@ -320,6 +340,17 @@ impl Prover for DefaultProver {
         Ok(())
     }
 
+    async fn cancel_preflight(&self, order_id: &str) -> Result<(), ProverError> {
+        let Some(proof_id) = self.state.preflights.write().await.remove(order_id) else {
+            return Ok(());
+        };
+
+        // The local executor can not be interrupted, the execution is abandoned and runs on in the
+        // background until it completes or reaches the executor limit.
+        tracing::debug!("Cancelling preflight {proof_id} for order {order_id}");
+        self.cancel_stark(&proof_id).await
+    }
+
     async fn get_receipt(&self, proof_id: &str) -> Result<Option<Receipt>, ProverError> {
         let proofs = self.state.proofs.read().await;
         let proof_data = proofs
@@ -400,7 +431,8 @@ impl Prover for DefaultProver {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use boundless_market_test_utils::{ECHO_ELF, ECHO_ID};
+    use crate::provers::encode_input;
+    use boundless_market_test_utils::{ECHO_ELF, ECHO_ID, LOOP_ELF, LOOP_ID};
     use risc0_zkvm::sha::Digest;
     use tokio::test;
 
@@ -446,6 +478,33 @@ mod tests {
         assert_eq!(journal, input_data);
     }
 
+    #[test]
+    async fn test_cancel_preflight() {
+        let prover = DefaultProver::new();
+
+        // Upload a guest that loops until it hits the executor limit
+        let input_id = prover.upload_input(encode_input(&(u64::MAX, 0u64)).unwrap()).await.unwrap();
+        let image_id = Digest::from(LOOP_ID).to_string();
+        prover.upload_image(&image_id, LOOP_ELF.to_vec()).await.unwrap();
+
+        let order_id = "test_order_id";
+        let preflight = prover.preflight(&image_id, &input_id, vec![], Some(1 << 26), order_id);
+        tokio::time::timeout(std::time::Duration::from_millis(100), preflight).await.unwrap_err();
+
+        let proof_id = prover.state.preflights.read().await.get(order_id).cloned().unwrap();
+        prover.cancel_preflight(order_id).await.unwrap();
+
+        assert!(prover.state.preflights.read().await.is_empty());
+        let proofs = prover.state.proofs.read().await;
+        let proof = proofs.get(&proof_id).unwrap();
+        assert!(matches!(proof.status, Status::Failed));
+        assert_eq!(proof.error_msg, "Cancelled");
+        drop(proofs);
+
+        // Cancelling an order without a preflight in flight is a no-op
+        prover.cancel_preflight(order_id).await.unwrap();
+    }
+
     #[test]
     async fn test_prove_stark() {
         let prover = DefaultProver::new();
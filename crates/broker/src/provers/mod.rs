@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use async_trait::async_trait;
 use bonsai_sdk::SdkErr;
@@ -22,9 +22,12 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 mod bonsai;
+mod composite;
 mod default;
 
 pub use bonsai::Bonsai;
+pub(crate) use composite::backend_name;
+pub use composite::CompositeProver;
 pub use default::DefaultProver;
 
 /// Executor output
@@ -71,6 +74,9 @@ pub enum ProverError {
     #[error("{code} Prover internal error: {0}", code = self.code())]
     ProverInternalError(String),
 
+    #[error("{code} Preflight timed out after {0:?}", code = self.code())]
+    PreflightTimeout(Duration),
+
     #[error("{code} {0:?}", code = self.code())]
     UnexpectedError(#[from] anyhow::Error),
 }
@@ -88,6 +94,7 @@ impl CodedError for ProverError {
             ProverError::BincodeErr(_) => "[B-BON-006]",
             ProverError::StatusFailure => "[B-BON-007]",
             ProverError::ProverInternalError(_) => "[B-BON-008]",
+            ProverError::PreflightTimeout(_) => "[B-BON-009]",
             ProverError::UnexpectedError(_) => "[B-BON-500]",
         }
     }
@@ -109,6 +116,8 @@ pub fn encode_input(input: &impl serde::Serialize) -> Result<Vec<u8>, anyhow::Er
 pub trait Prover {
     async fn has_image(&self, image_id: &str) -> Result<bool, ProverError>;
     async fn upload_input(&self, input: Vec<u8>) -> Result<String, ProverError>;
+    /// Upload a bincode encoded receipt, returning an ID that can be passed as an assumption
+    async fn upload_receipt(&self, receipt: Vec<u8>) -> Result<String, ProverError>;
     async fn upload_image(&self, image_id: &str, image: Vec<u8>) -> Result<(), ProverError>;
     async fn preflight(
         &self,
@@ -118,6 +127,8 @@ pub trait Prover {
         executor_limit: Option<u64>,
         order_id: &str,
     ) -> Result<ProofResult, ProverError>;
+    /// Cancel the in-flight preflight of an order, if there is one
+    async fn cancel_preflight(&self, order_id: &str) -> Result<(), ProverError>;
     async fn prove_stark(
         &self,
         image_id: &str,
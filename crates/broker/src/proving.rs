@@ -20,9 +20,10 @@ use crate::{
     db::DbObj,
     errors::CodedError,
     futures_retry::retry,
-    impl_coded_debug,
-    provers::ProverObj,
+    impl_coded_debug, metrics,
+    provers::{self, ProverObj},
     task::{RetryRes, RetryTask, SupervisorErr},
+    throughput::{ProvingSample, ProvingThroughput},
     utils::cancel_proof_and_fail_order,
     Order, OrderStatus,
 };
@@ -64,7 +65,8 @@ pub struct ProvingService {
     db: DbObj,
     prover: ProverObj,
     config: ConfigLock,
-    fulfillment_tx: tokio::sync::broadcast::Sender<U256>,
+    fulfillment_tx: tokio::sync::broadcast::Sender<(u64, U256)>,
+    throughput: ProvingThroughput,
 }
 
 impl ProvingService {
@@ -72,9 +74,10 @@ impl ProvingService {
         db: DbObj,
         prover: ProverObj,
         config: ConfigLock,
-        fulfillment_tx: tokio::sync::broadcast::Sender<U256>,
+        fulfillment_tx: tokio::sync::broadcast::Sender<(u64, U256)>,
+        throughput: ProvingThroughput,
     ) -> Result<Self> {
-        Ok(Self { db, prover, config, fulfillment_tx })
+        Ok(Self { db, prover, config, fulfillment_tx, throughput })
     }
 
     async fn cancel_stark_session(&self, proof_id: &str, order_id: &str, reason: &str) {
@@ -92,6 +95,7 @@ impl ProvingService {
     async fn monitor_proof_internal(
         &self,
         order_id: &str,
+        image_id: String,
         stark_proof_id: &str,
         is_groth16: bool,
         snark_proof_id: Option<String>,
@@ -125,6 +129,17 @@ impl ProvingService {
             proof_res.stats.total_cycles,
             proof_res.elapsed_time,
         );
+        metrics::record_proof_duration(proof_res.elapsed_time, proof_res.stats.total_cycles);
+        self.throughput
+            .record(ProvingSample {
+                order_id: order_id.to_string(),
+                image_id,
+                backend: provers::backend_name(stark_proof_id).map(Into::into),
+                total_cycles: proof_res.stats.total_cycles,
+                elapsed_secs: proof_res.elapsed_time,
+                created_at: chrono::Utc::now(),
+            })
+            .await;
 
         Ok(status)
     }
@@ -156,15 +171,25 @@ impl ProvingService {
                 let input_id = match order.input_id.as_ref() {
                     Some(val) => val.clone(),
                     None => {
-                        crate::storage::upload_input_uri(&self.prover, &order.request, &self.config)
-                            .await
-                            .context("Failed to upload input")?
+                        let (input_id, _, assumptions) = crate::storage::upload_input_uri(
+                            &self.prover,
+                            &order.request,
+                            &self.config,
+                        )
+                        .await
+                        .context("Failed to upload input")?;
+                        // Resolving assumptions requires chain access, only done in preflight
+                        anyhow::ensure!(
+                            assumptions.is_empty(),
+                            "Order {order_id} has assumptions that were not resolved in preflight"
+                        );
+                        input_id
                     }
                 };
 
                 let proof_id = self
                     .prover
-                    .prove_stark(&image_id, &input_id, /* TODO assumptions */ vec![])
+                    .prove_stark(&image_id, &input_id, order.assumption_ids.clone())
                     .await
                     .context("Failed to prove customer proof STARK order")?;
 
@@ -194,15 +219,17 @@ impl ProvingService {
             let now = crate::now_timestamp();
             Duration::from_secs(expiry_timestamp_secs.saturating_sub(now))
         };
-        // Only subscribe to fulfillment events for FulfillAfterLockExpire orders
+        // Only subscribe to fulfillment events for orders we have not locked, as any prover may
+        // fulfill those first
         let mut fulfillment_rx = if matches!(
             order.fulfillment_type,
             crate::FulfillmentType::FulfillAfterLockExpire
+                | crate::FulfillmentType::FulfillWithoutLocking
         ) {
             let rx = self.fulfillment_tx.subscribe();
 
             // Check if the order has already been fulfilled before starting proof
-            match self.db.is_request_fulfilled(request_id).await {
+            match self.db.is_request_fulfilled(order.chain_id, request_id).await {
                 Ok(true) => {
                     tracing::debug!(
                         "Order {} (request {}) was already fulfilled, skipping proof",
@@ -227,6 +254,7 @@ impl ProvingService {
 
         let monitor_task = self.monitor_proof_internal(
             &order_id,
+            order.request.requirements.imageId.to_string(),
             proof_id,
             order.is_groth16(),
             order.compressed_proof_id,
@@ -258,15 +286,17 @@ impl ProvingService {
                     self.cancel_stark_session(proof_id, &order_id, "timed out").await;
                     return Err(ProvingErr::ProvingTimedOut);
                 }
-                // External fulfillment notification (only active for FulfillAfterLockExpire orders)
+                // External fulfillment notification (only active for orders we have not locked)
                 Some(recv_res) = async {
                     match &mut fulfillment_rx {
                         Some(rx) => Some(rx.recv().await),
-                        None => pending::<Option<Result<U256, tokio::sync::broadcast::error::RecvError>>>().await,
+                        None => pending::<Option<Result<(u64, U256), tokio::sync::broadcast::error::RecvError>>>().await,
                     }
                 } => {
                     match recv_res {
-                        Ok(fulfilled_request_id) if fulfilled_request_id == request_id => {
+                        Ok((chain_id, fulfilled_request_id))
+                            if chain_id == order.chain_id && fulfilled_request_id == request_id =>
+                        {
                             tracing::debug!(
                                 "Order {} (request {}) was fulfilled by another prover, cancelling proof {}",
                                 order_id,
@@ -277,7 +307,7 @@ impl ProvingService {
                             return Err(ProvingErr::ExternallyFulfilled);
                         }
                         Ok(_) => {
-                            // Fulfillment for a different request, continue monitoring
+                            // Fulfillment for a different request or chain, continue monitoring
                         }
                         Err(_) => {
                             // Channel closed or lagged, continue monitoring
@@ -500,6 +530,7 @@ mod tests {
             },
             image_id: Some(image_id),
             input_id: Some(input_id),
+            assumption_ids: vec![],
             proof_id,
             compressed_proof_id: None,
             expire_timestamp: Some(now_timestamp() + 3600), // 1 hour from now
@@ -507,6 +538,7 @@ mod tests {
             lock_price: None,
             fulfillment_type,
             error_msg: None,
+            skip_reason: None,
             boundless_market_address: Address::ZERO,
             chain_id: 1,
             total_cycles: None,
@@ -515,13 +547,14 @@ mod tests {
     }
 
     async fn send_fulfillment_event(
-        fulfillment_tx: tokio::sync::broadcast::Sender<U256>,
+        fulfillment_tx: tokio::sync::broadcast::Sender<(u64, U256)>,
+        chain_id: u64,
         request_id: U256,
     ) {
         for _ in 0..50 {
             // Try for up to 5 seconds
             tokio::time::sleep(Duration::from_millis(100)).await;
-            if fulfillment_tx.send(request_id).is_ok() {
+            if fulfillment_tx.send((chain_id, request_id)).is_ok() {
                 return;
             }
         }
@@ -543,10 +576,15 @@ mod tests {
             .unwrap();
 
         let (fulfillment_tx, _) = tokio::sync::broadcast::channel(100);
-        let proving_service =
-            ProvingService::new(db.clone(), prover.clone(), config.clone(), fulfillment_tx)
-                .await
-                .unwrap();
+        let proving_service = ProvingService::new(
+            db.clone(),
+            prover.clone(),
+            config.clone(),
+            fulfillment_tx,
+            ProvingThroughput::load(db.clone(), config.clone()).await.unwrap(),
+        )
+        .await
+        .unwrap();
 
         let order = create_test_order(
             U256::ZERO,
@@ -566,10 +604,15 @@ mod tests {
 
         // Test that LockAndFulfill orders ignore fulfillment events
         let (fulfillment_tx, _) = tokio::sync::broadcast::channel(100);
-        let proving_service_with_fulfillment =
-            ProvingService::new(db.clone(), prover.clone(), config.clone(), fulfillment_tx.clone())
-                .await
-                .unwrap();
+        let proving_service_with_fulfillment = ProvingService::new(
+            db.clone(),
+            prover.clone(),
+            config.clone(),
+            fulfillment_tx.clone(),
+            ProvingThroughput::load(db.clone(), config.clone()).await.unwrap(),
+        )
+        .await
+        .unwrap();
 
         let lock_and_fulfill_order = create_test_order(
             U256::from(999),
@@ -584,7 +627,12 @@ mod tests {
 
         // Spawn fulfillment event that should be ignored
         tokio::spawn(async move {
-            send_fulfillment_event(fulfillment_tx, lock_and_fulfill_order.request.id).await
+            send_fulfillment_event(
+                fulfillment_tx,
+                lock_and_fulfill_order.chain_id,
+                lock_and_fulfill_order.request.id,
+            )
+            .await
         });
 
         proving_service_with_fulfillment.prove_and_update_db(lock_and_fulfill_order.clone()).await;
@@ -612,8 +660,15 @@ mod tests {
         let proof_id = prover.prove_stark(&image_id, &input_id, vec![]).await.unwrap();
 
         let (fulfillment_tx, _) = tokio::sync::broadcast::channel(100);
-        let proving_service =
-            ProvingService::new(db.clone(), prover, config.clone(), fulfillment_tx).await.unwrap();
+        let proving_service = ProvingService::new(
+            db.clone(),
+            prover,
+            config.clone(),
+            fulfillment_tx,
+            ProvingThroughput::load(db.clone(), config.clone()).await.unwrap(),
+        )
+        .await
+        .unwrap();
 
         let order_id = U256::ZERO;
         let min_price = 2;
@@ -649,6 +704,7 @@ mod tests {
             },
             image_id: Some(image_id),
             input_id: Some(input_id),
+            assumption_ids: vec![],
             proof_id: Some(proof_id.clone()),
             compressed_proof_id: None,
             expire_timestamp: Some(now_timestamp() + 3600), // 1 hour from now
@@ -656,6 +712,7 @@ mod tests {
             lock_price: None,
             fulfillment_type: FulfillmentType::LockAndFulfill,
             error_msg: None,
+            skip_reason: None,
             boundless_market_address: Address::ZERO,
             chain_id: 1,
             total_cycles: None,
@@ -696,10 +753,15 @@ mod tests {
             .unwrap();
 
         let (fulfillment_tx, _) = tokio::sync::broadcast::channel(100);
-        let proving_service =
-            ProvingService::new(db.clone(), prover.clone(), config.clone(), fulfillment_tx.clone())
-                .await
-                .unwrap();
+        let proving_service = ProvingService::new(
+            db.clone(),
+            prover.clone(),
+            config.clone(),
+            fulfillment_tx.clone(),
+            ProvingThroughput::load(db.clone(), config.clone()).await.unwrap(),
+        )
+        .await
+        .unwrap();
 
         let request_id = U256::from(123);
         let proof_id = prover.prove_stark(&image_id, &input_id, vec![]).await.unwrap();
@@ -723,7 +785,7 @@ mod tests {
         });
 
         // Send fulfillment event for the same request - should cancel proof
-        send_fulfillment_event(fulfillment_tx.clone(), request_id).await;
+        send_fulfillment_event(fulfillment_tx.clone(), order.chain_id, request_id).await;
 
         let result = monitor_task.await.unwrap();
         assert!(result.is_err());
@@ -736,8 +798,8 @@ mod tests {
 
         let order_2 = create_test_order(
             request_id_2,
-            image_id,
-            input_id,
+            image_id.clone(),
+            input_id.clone(),
             Some(proof_id_2.clone()),
             FulfillmentType::FulfillAfterLockExpire,
             OrderStatus::Proving,
@@ -752,12 +814,42 @@ mod tests {
         });
 
         // Send fulfillment event for different request ID - should be ignored
-        send_fulfillment_event(fulfillment_tx, different_fulfillment_id).await;
+        send_fulfillment_event(fulfillment_tx.clone(), order_2.chain_id, different_fulfillment_id)
+            .await;
 
         let result_2 = monitor_task_2.await.unwrap();
         assert!(result_2.is_ok());
         assert_eq!(result_2.unwrap(), OrderStatus::PendingAgg);
 
+        // Test 3: FulfillAfterLockExpire order ignores the same request ID on another chain
+        let request_id_3 = U256::from(789);
+        let proof_id_3 = prover.prove_stark(&image_id, &input_id, vec![]).await.unwrap();
+
+        let order_3 = create_test_order(
+            request_id_3,
+            image_id,
+            input_id,
+            Some(proof_id_3.clone()),
+            FulfillmentType::FulfillAfterLockExpire,
+            OrderStatus::Proving,
+        );
+
+        db.add_order(&order_3).await.unwrap();
+        db.set_request_fulfilled(order_3.chain_id + 1, request_id_3, 1).await.unwrap();
+
+        let proving_service_clone_3 = proving_service.clone();
+        let order_clone_3 = order_3.clone();
+        let monitor_task_3 = tokio::spawn(async move {
+            proving_service_clone_3.monitor_proof_with_timeout(order_clone_3).await
+        });
+
+        // Send fulfillment event for the same request ID on another chain - should be ignored
+        send_fulfillment_event(fulfillment_tx, order_3.chain_id + 1, request_id_3).await;
+
+        let result_3 = monitor_task_3.await.unwrap();
+        assert!(result_3.is_ok());
+        assert_eq!(result_3.unwrap(), OrderStatus::PendingAgg);
+
         assert!(logs_contain("was fulfilled by another prover"));
     }
 }
@@ -15,6 +15,7 @@
 use std::time::Duration;
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use thiserror::Error;
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, warn};
@@ -23,9 +24,12 @@ use crate::{
     config::{ConfigErr, ConfigLock},
     db::{DbError, DbObj},
     errors::CodedError,
+    ledger::{self, LedgerEntry, LedgerKind},
+    now_timestamp,
     provers::ProverObj,
     task::{RetryRes, RetryTask, SupervisorErr},
     utils::cancel_proof_and_fail_order,
+    OrderStatus,
 };
 
 #[derive(Error, Debug)]
@@ -99,6 +103,78 @@ impl ReaperTask {
         Ok(())
     }
 
+    /// Record the stake of the orders whose lock expired before they were fulfilled as slashed
+    ///
+    /// Orders being submitted, or already submitted, are settled by the submitter, which knows
+    /// whether the fulfillment landed before the lock expired.
+    async fn settle_slashed_stakes(&self) -> Result<(), ReaperError> {
+        let now = now_timestamp();
+        let mut slashed = vec![];
+        for stake in self.db.get_open_stakes().await? {
+            let Some(order) = self.db.get_order(&stake.order_id).await? else {
+                continue;
+            };
+            if matches!(order.status, OrderStatus::PendingSubmission | OrderStatus::Done) {
+                continue;
+            }
+            if order.request.lock_expires_at() >= now {
+                continue;
+            }
+            warn!("Lock of order {} expired before it was fulfilled, stake slashed", order.id());
+            slashed.push(LedgerEntry::new(
+                stake.order_id,
+                stake.chain_id,
+                &order.request,
+                LedgerKind::StakeSlashed,
+                stake.amount,
+            ));
+        }
+
+        if !slashed.is_empty() {
+            ledger::record(&self.db, slashed).await;
+        }
+        Ok(())
+    }
+
+    /// Delete the cached preflight outcomes which are too old, or beyond the max cache size
+    async fn prune_preflight_cache(&self) -> Result<(), ReaperError> {
+        let (max_age_secs, max_entries) = {
+            let config = self.config.lock_all()?;
+            (config.prover.preflight_cache_max_age_secs, config.prover.preflight_cache_max_entries)
+        };
+
+        let created_before = i64::try_from(max_age_secs)
+            .ok()
+            .and_then(chrono::Duration::try_seconds)
+            .and_then(|max_age| Utc::now().checked_sub_signed(max_age))
+            .unwrap_or(DateTime::UNIX_EPOCH);
+        let pruned = self.db.prune_preflight_cache(created_before, max_entries).await?;
+        if pruned > 0 {
+            debug!("Pruned {pruned} cached preflight outcomes");
+        }
+        Ok(())
+    }
+
+    /// Delete the shadow decisions, proving samples and lock bids which are too old, or beyond
+    /// the max number kept
+    async fn prune_history(&self) -> Result<(), ReaperError> {
+        let (max_age_secs, max_entries) = {
+            let config = self.config.lock_all()?;
+            (config.prover.history_max_age_secs, config.prover.history_max_entries)
+        };
+
+        let created_before = i64::try_from(max_age_secs)
+            .ok()
+            .and_then(chrono::Duration::try_seconds)
+            .and_then(|max_age| Utc::now().checked_sub_signed(max_age))
+            .unwrap_or(DateTime::UNIX_EPOCH);
+        let pruned = self.db.prune_history(created_before, max_entries).await?;
+        if pruned > 0 {
+            debug!("Pruned {pruned} shadow decisions, proving samples and lock bids");
+        }
+        Ok(())
+    }
+
     async fn run_reaper_loop(&self, cancel_token: CancellationToken) -> Result<(), ReaperError> {
         let interval = {
             let config = self.config.lock_all()?;
@@ -118,6 +194,15 @@ impl ReaperTask {
             if let Err(err) = self.check_expired_orders().await {
                 warn!("Error checking expired orders: {}", err);
             }
+            if let Err(err) = self.settle_slashed_stakes().await {
+                warn!("Error settling slashed stakes: {}", err);
+            }
+            if let Err(err) = self.prune_preflight_cache().await {
+                warn!("Error pruning preflight cache: {}", err);
+            }
+            if let Err(err) = self.prune_history().await {
+                warn!("Error pruning history: {}", err);
+            }
         }
     }
 }
@@ -146,7 +231,6 @@ mod tests {
         Offer, Predicate, PredicateType, ProofRequest, RequestId, RequestInput, RequestInputType,
         Requirements,
     };
-    use chrono::Utc;
     use risc0_zkvm::sha::Digest;
     use std::sync::Arc;
     use tracing_test::traced_test;
@@ -183,6 +267,7 @@ mod tests {
             ),
             image_id: None,
             input_id: None,
+            assumption_ids: vec![],
             proof_id: None,
             compressed_proof_id: None,
             expire_timestamp,
@@ -190,6 +275,7 @@ mod tests {
             lock_price: Some(U256::from(1)),
             fulfillment_type: FulfillmentType::LockAndFulfill,
             error_msg: None,
+            skip_reason: None,
             boundless_market_address: Address::ZERO,
             chain_id: 1,
             total_cycles: None,
@@ -232,6 +318,62 @@ mod tests {
         assert!(stored_order2.error_msg.is_none());
     }
 
+    #[tokio::test]
+    #[traced_test]
+    async fn test_settle_slashed_stakes() {
+        let db: DbObj = Arc::new(SqliteDb::new("sqlite::memory:").await.unwrap());
+        let config = ConfigLock::default();
+        let prover: ProverObj = Arc::new(DefaultProver::new());
+        let reaper = ReaperTask::new(db.clone(), config, prover);
+
+        // Locked at the epoch, so the lock has long expired
+        let expired = create_order_with_status_and_expiration(1, OrderStatus::Failed, None);
+        let mut active = create_order_with_status_and_expiration(2, OrderStatus::Proving, None);
+        active.request.offer.biddingStart = now_timestamp();
+        // Its fulfillment may have landed before the lock expired, so the submitter settles it
+        let submitting =
+            create_order_with_status_and_expiration(3, OrderStatus::PendingSubmission, None);
+        db.add_order(&expired).await.unwrap();
+        db.add_order(&active).await.unwrap();
+        db.add_order(&submitting).await.unwrap();
+        let stakes: Vec<_> = [&expired, &active, &submitting]
+            .into_iter()
+            .map(|order| {
+                LedgerEntry::new(
+                    order.id(),
+                    order.chain_id,
+                    &order.request,
+                    LedgerKind::StakeLocked,
+                    U256::from(10),
+                )
+            })
+            .collect();
+        db.add_ledger_entries(&stakes).await.unwrap();
+
+        reaper.settle_slashed_stakes().await.unwrap();
+
+        let open_stakes = db.get_open_stakes().await.unwrap();
+        assert_eq!(open_stakes.len(), 2);
+        assert_eq!(open_stakes[0].order_id, active.id());
+        assert_eq!(open_stakes[1].order_id, submitting.id());
+
+        // Settled stakes are only recorded once
+        reaper.settle_slashed_stakes().await.unwrap();
+        let entries = db
+            .get_ledger_entries(
+                None,
+                DateTime::UNIX_EPOCH,
+                Utc::now() + chrono::Duration::seconds(1),
+            )
+            .await
+            .unwrap();
+        let slashed: Vec<_> =
+            entries.iter().filter(|entry| entry.kind == LedgerKind::StakeSlashed).collect();
+        assert_eq!(slashed.len(), 1);
+        assert_eq!(slashed[0].order_id, expired.id());
+        assert_eq!(slashed[0].amount, U256::from(10));
+    }
+
     #[tokio::test]
     #[traced_test]
     async fn test_expired_orders() {
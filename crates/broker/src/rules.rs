@@ -0,0 +1,298 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Evaluation of the order rules configured in `market.rules`.
+//!
+//! Rules are evaluated by the [OrderPicker](crate::order_picker::OrderPicker) before preflight.
+//! Orders denied by a rule are skipped, while the remaining actions of the matching rules are
+//! collected into [RuleOverrides] and passed to the [PricingStrategy](crate::pricing::PricingStrategy)
+//! in the [PricingContext](crate::pricing::PricingContext).
+
+use alloy::primitives::{utils::parse_units, U256};
+use anyhow::{Context, Result};
+use boundless_market::contracts::RequestInputType;
+
+use crate::{
+    config::{OrderRule, RuleAction, RuleInputType},
+    OrderRequest,
+};
+
+/// Overrides applied to an order by the rules matching it
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RuleOverrides {
+    /// Accept the order regardless of its price, provided there is enough gas and stake
+    pub always_accept: bool,
+    /// Mega-cycle price, in native token, to use in place of `market.mcycle_price`
+    pub mcycle_price: Option<String>,
+    /// Mega-cycle price, in stake token, to use in place of `market.mcycle_price_stake_token`
+    pub mcycle_price_stake_token: Option<String>,
+    /// Max mcycles to use in place of `market.max_mcycle_limit`
+    pub max_mcycle_limit: Option<u64>,
+    /// Commit to the order ahead of other orders
+    pub priority_boost: bool,
+}
+
+/// Outcome of evaluating the rules against an order
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RuleOutcome {
+    /// The order was denied by the named rule
+    Deny { rule: String },
+    /// The order may be priced, with the given overrides
+    Price(RuleOverrides),
+}
+
+/// Evaluate the rules, in order, against an order
+///
+/// The first matching rule to set an override takes effect. An order matching a deny rule is
+/// denied, unless an earlier matching rule always accepts it.
+pub fn evaluate(
+    rules: &[OrderRule],
+    order: &OrderRequest,
+    stake_token_decimals: u8,
+) -> Result<RuleOutcome> {
+    let mut overrides = RuleOverrides::default();
+    for (idx, rule) in rules.iter().enumerate() {
+        if !matches(rule, order, stake_token_decimals)? {
+            continue;
+        }
+        match &rule.action {
+            RuleAction::Deny if !overrides.always_accept => {
+                let rule = rule.name.clone().unwrap_or_else(|| format!("#{idx}"));
+                return Ok(RuleOutcome::Deny { rule });
+            }
+            RuleAction::Deny => {}
+            RuleAction::AlwaysAccept => overrides.always_accept = true,
+            RuleAction::McyclePrice { mcycle_price, mcycle_price_stake_token } => {
+                if overrides.mcycle_price.is_none() {
+                    overrides.mcycle_price = Some(mcycle_price.clone());
+                }
+                if overrides.mcycle_price_stake_token.is_none() {
+                    overrides.mcycle_price_stake_token = mcycle_price_stake_token.clone();
+                }
+            }
+            RuleAction::MaxMcycleLimit { max_mcycle_limit } => {
+                if overrides.max_mcycle_limit.is_none() {
+                    overrides.max_mcycle_limit = Some(*max_mcycle_limit);
+                }
+            }
+            RuleAction::PriorityBoost => overrides.priority_boost = true,
+        }
+    }
+    Ok(RuleOutcome::Price(overrides))
+}
+
+/// Check if an order matches every field set on the rule
+fn matches(rule: &OrderRule, order: &OrderRequest, stake_token_decimals: u8) -> Result<bool> {
+    let request = &order.request;
+
+    if let Some(requestors) = &rule.requestors {
+        if !requestors.contains(&request.client_address()) {
+            return Ok(false);
+        }
+    }
+
+    if let Some(image_ids) = &rule.image_ids {
+        if !image_ids.contains(&request.requirements.imageId) {
+            return Ok(false);
+        }
+    }
+
+    if let Some(hosts) = &rule.image_url_hosts {
+        let host = url::Url::parse(&request.imageUrl)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string));
+        if !host.is_some_and(|host| hosts.iter().any(|h| h.eq_ignore_ascii_case(&host))) {
+            return Ok(false);
+        }
+    }
+
+    if let Some(input_type) = rule.input_type {
+        let matched = match input_type {
+            RuleInputType::Inline => request.input.inputType == RequestInputType::Inline,
+            RuleInputType::Url => request.input.inputType == RequestInputType::Url,
+        };
+        if !matched {
+            return Ok(false);
+        }
+    }
+
+    if let Some(selectors) = &rule.selectors {
+        if !selectors.contains(&request.requirements.selector) {
+            return Ok(false);
+        }
+    }
+
+    if let Some(callback) = rule.callback {
+        if request.requirements.callback.as_option().is_some() != callback {
+            return Ok(false);
+        }
+    }
+
+    let lock_stake = U256::from(request.offer.lockStake);
+    if let Some(min_lock_stake) = &rule.min_lock_stake {
+        let min_lock_stake: U256 = parse_units(min_lock_stake, stake_token_decimals)
+            .context("Failed to parse rule min_lock_stake")?
+            .into();
+        if lock_stake < min_lock_stake {
+            return Ok(false);
+        }
+    }
+    if let Some(max_lock_stake) = &rule.max_lock_stake {
+        let max_lock_stake: U256 = parse_units(max_lock_stake, stake_token_decimals)
+            .context("Failed to parse rule max_lock_stake")?
+            .into();
+        if lock_stake > max_lock_stake {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FulfillmentType;
+    use alloy::primitives::{Address, Bytes, FixedBytes, B256};
+    use boundless_market::contracts::{
+        Offer, Predicate, PredicateType, ProofRequest, RequestId, RequestInput, Requirements,
+    };
+    use risc0_zkvm::Digest;
+
+    fn order(client: Address, lock_stake: U256) -> OrderRequest {
+        let request = ProofRequest::new(
+            RequestId::new(client, 1),
+            Requirements::new(
+                Digest::ZERO,
+                Predicate { predicateType: PredicateType::PrefixMatch, data: Default::default() },
+            ),
+            "https://images.example.com/guest",
+            RequestInput { inputType: RequestInputType::Url, data: Default::default() },
+            Offer {
+                minPrice: U256::from(1),
+                maxPrice: U256::from(2),
+                biddingStart: 0,
+                rampUpPeriod: 1,
+                timeout: 100,
+                lockTimeout: 100,
+                lockStake: lock_stake,
+            },
+        );
+        OrderRequest::new(request, Bytes::new(), FulfillmentType::LockAndFulfill, Address::ZERO, 1)
+    }
+
+    fn rule(action: RuleAction) -> OrderRule {
+        OrderRule {
+            name: None,
+            requestors: None,
+            image_ids: None,
+            image_url_hosts: None,
+            input_type: None,
+            selectors: None,
+            callback: None,
+            min_lock_stake: None,
+            max_lock_stake: None,
+            action,
+        }
+    }
+
+    #[test]
+    fn matching() {
+        let client = Address::repeat_byte(1);
+        let order = order(client, parse_units("5", 18).unwrap().into());
+
+        assert!(matches(&rule(RuleAction::Deny), &order, 18).unwrap());
+
+        let rule_with = |f: fn(&mut OrderRule)| {
+            let mut rule = rule(RuleAction::Deny);
+            f(&mut rule);
+            matches(&rule, &order, 18).unwrap()
+        };
+        assert!(rule_with(|r| r.requestors = Some(vec![Address::ZERO, Address::repeat_byte(1)])));
+        assert!(!rule_with(|r| r.requestors = Some(vec![Address::ZERO])));
+        assert!(rule_with(|r| r.image_ids = Some(vec![B256::ZERO])));
+        assert!(rule_with(|r| r.image_url_hosts = Some(vec!["IMAGES.example.com".into()])));
+        assert!(!rule_with(|r| r.image_url_hosts = Some(vec!["example.com".into()])));
+        assert!(rule_with(|r| r.input_type = Some(RuleInputType::Url)));
+        assert!(!rule_with(|r| r.input_type = Some(RuleInputType::Inline)));
+        assert!(!rule_with(|r| r.selectors = Some(vec![FixedBytes::repeat_byte(1)])));
+        assert!(rule_with(|r| r.callback = Some(false)));
+        assert!(!rule_with(|r| r.callback = Some(true)));
+        assert!(rule_with(|r| r.min_lock_stake = Some("5".into())));
+        assert!(!rule_with(|r| r.min_lock_stake = Some("5.1".into())));
+        assert!(rule_with(|r| r.max_lock_stake = Some("5".into())));
+        assert!(!rule_with(|r| r.max_lock_stake = Some("4.9".into())));
+        // All fields set must match
+        let mut both = rule(RuleAction::Deny);
+        both.requestors = Some(vec![client]);
+        both.input_type = Some(RuleInputType::Inline);
+        assert!(!matches(&both, &order, 18).unwrap());
+    }
+
+    #[test]
+    fn first_override_wins() {
+        let order = order(Address::ZERO, U256::ZERO);
+        let rules = vec![
+            rule(RuleAction::McyclePrice {
+                mcycle_price: "1".into(),
+                mcycle_price_stake_token: None,
+            }),
+            rule(RuleAction::McyclePrice {
+                mcycle_price: "2".into(),
+                mcycle_price_stake_token: Some("3".into()),
+            }),
+            rule(RuleAction::MaxMcycleLimit { max_mcycle_limit: 10 }),
+            rule(RuleAction::PriorityBoost),
+        ];
+
+        assert_eq!(
+            evaluate(&rules, &order, 18).unwrap(),
+            RuleOutcome::Price(RuleOverrides {
+                always_accept: false,
+                mcycle_price: Some("1".into()),
+                mcycle_price_stake_token: Some("3".into()),
+                max_mcycle_limit: Some(10),
+                priority_boost: true,
+            })
+        );
+    }
+
+    #[test]
+    fn deny() {
+        let order = order(Address::ZERO, U256::ZERO);
+        let mut deny = rule(RuleAction::Deny);
+        deny.name = Some("no-zero".into());
+
+        assert_eq!(
+            evaluate(&[rule(RuleAction::PriorityBoost), deny.clone()], &order, 18).unwrap(),
+            RuleOutcome::Deny { rule: "no-zero".into() }
+        );
+        assert_eq!(
+            evaluate(&[rule(RuleAction::Deny)], &order, 18).unwrap(),
+            RuleOutcome::Deny { rule: "#0".into() }
+        );
+
+        // An earlier always accept rule takes precedence over deny rules
+        let RuleOutcome::Price(overrides) =
+            evaluate(&[rule(RuleAction::AlwaysAccept), deny.clone()], &order, 18).unwrap()
+        else {
+            panic!("order was denied");
+        };
+        assert!(overrides.always_accept);
+        assert!(matches!(
+            evaluate(&[deny, rule(RuleAction::AlwaysAccept)], &order, 18).unwrap(),
+            RuleOutcome::Deny { .. }
+        ));
+    }
+}
@@ -0,0 +1,229 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Shadow mode decision log.
+//!
+//! When the broker runs with `--shadow`, the full pipeline runs but no lock or fulfillment
+//! transactions are sent. Instead, every decision made about an order is appended to the DB as a
+//! [ShadowDecision], and summarized in a [ShadowReport] served by the admin API. This allows a
+//! candidate `broker.toml` to be compared against a live broker on the same RPC.
+
+use std::collections::{BTreeMap, HashMap};
+
+use alloy::primitives::{I256, U256};
+use chrono::{serde::ts_seconds, DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{db::DbObj, FulfillmentType, OrderRequest, SkipReason};
+
+/// Action the broker would have taken for an order
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShadowAction {
+    /// Priced to be locked once the target timestamp is reached
+    Lock,
+    /// Priced to be proven once the lock held by another prover expires
+    ProveAfterLockExpire,
+    /// Priced to be proven and fulfilled without locking
+    ProveWithoutLock,
+    /// Would have sent the lock transaction
+    Locked,
+    /// Would have sent the fulfillment transaction
+    Fulfilled,
+    /// Skipped, with the reason recorded in the decision
+    Skip,
+}
+
+/// A decision made about an order while running in shadow mode
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ShadowDecision {
+    pub order_id: String,
+    pub request_id: U256,
+    pub fulfillment_type: FulfillmentType,
+    pub action: ShadowAction,
+    /// Reason the order was skipped
+    pub reason: Option<SkipReason>,
+    /// Expected profit of the order when accepted by pricing
+    ///
+    /// Denominated in wei of the native token, or for orders with an expired lock, in the
+    /// smallest unit of the stake token.
+    pub expected_profit: Option<I256>,
+    #[serde(with = "ts_seconds")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl ShadowDecision {
+    pub fn new(order: &OrderRequest, action: ShadowAction) -> Self {
+        Self::for_request(order.id(), order.request.id, order.fulfillment_type, action)
+    }
+
+    pub fn for_request(
+        order_id: String,
+        request_id: U256,
+        fulfillment_type: FulfillmentType,
+        action: ShadowAction,
+    ) -> Self {
+        Self {
+            order_id,
+            request_id,
+            fulfillment_type,
+            action,
+            reason: None,
+            expected_profit: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn skip(order: &OrderRequest, reason: SkipReason) -> Self {
+        Self { reason: Some(reason), ..Self::new(order, ShadowAction::Skip) }
+    }
+
+    pub fn with_expected_profit(self, expected_profit: I256) -> Self {
+        Self { expected_profit: Some(expected_profit), ..self }
+    }
+}
+
+/// Append a decision to the shadow log, logging rather than failing on DB errors
+pub(crate) async fn record(db: &DbObj, decision: ShadowDecision) {
+    tracing::debug!(
+        "Shadow decision for order {}: {:?}{}",
+        decision.order_id,
+        decision.action,
+        decision.reason.map(|reason| format!(" ({reason})")).unwrap_or_default()
+    );
+    if let Err(err) = db.add_shadow_decision(&decision).await {
+        tracing::error!(
+            "Failed to record shadow decision for order {}: {err:?}",
+            decision.order_id
+        );
+    }
+}
+
+/// Summary of the decisions made in shadow mode
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ShadowReport {
+    /// Number of orders a decision was made for
+    pub orders: usize,
+    /// Number of orders by their latest action
+    pub actions: BTreeMap<String, usize>,
+    /// Number of skipped orders by reason
+    pub skip_reasons: BTreeMap<SkipReason, usize>,
+    /// Total expected profit, in wei, of orders paid in native token that were not skipped
+    pub expected_profit: I256,
+    /// Total expected profit, in stake token, of orders with an expired lock that were not skipped
+    pub expected_stake_profit: I256,
+    /// Latest decision for each order
+    pub decisions: Vec<ShadowDecision>,
+}
+
+impl ShadowReport {
+    /// Build a report from the decision log, in the order the decisions were recorded
+    pub fn new(log: Vec<ShadowDecision>) -> Self {
+        let mut expected_profits = HashMap::new();
+        let mut latest: Vec<ShadowDecision> = Vec::new();
+        let mut positions = HashMap::new();
+        for decision in log {
+            if let Some(expected_profit) = decision.expected_profit {
+                expected_profits.insert(decision.order_id.clone(), expected_profit);
+            }
+            match positions.get(&decision.order_id) {
+                Some(&pos) => latest[pos] = decision,
+                None => {
+                    positions.insert(decision.order_id.clone(), latest.len());
+                    latest.push(decision);
+                }
+            }
+        }
+
+        let mut report = Self {
+            orders: latest.len(),
+            actions: BTreeMap::new(),
+            skip_reasons: BTreeMap::new(),
+            expected_profit: I256::ZERO,
+            expected_stake_profit: I256::ZERO,
+            decisions: Vec::new(),
+        };
+        for decision in latest.iter() {
+            *report.actions.entry(format!("{:?}", decision.action)).or_default() += 1;
+            if decision.action == ShadowAction::Skip {
+                let reason = decision.reason.unwrap_or(SkipReason::Other);
+                *report.skip_reasons.entry(reason).or_default() += 1;
+                continue;
+            }
+
+            let expected_profit =
+                expected_profits.get(&decision.order_id).copied().unwrap_or(I256::ZERO);
+            if decision.fulfillment_type == FulfillmentType::FulfillAfterLockExpire {
+                report.expected_stake_profit += expected_profit;
+            } else {
+                report.expected_profit += expected_profit;
+            }
+        }
+        report.decisions = latest;
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decision(
+        order_id: &str,
+        fulfillment_type: FulfillmentType,
+        action: ShadowAction,
+    ) -> ShadowDecision {
+        ShadowDecision::for_request(order_id.into(), U256::ZERO, fulfillment_type, action)
+    }
+
+    #[test]
+    fn report_uses_latest_decision() {
+        let profit = I256::try_from(100).unwrap();
+        let log = vec![
+            decision("a", FulfillmentType::LockAndFulfill, ShadowAction::Lock)
+                .with_expected_profit(profit),
+            decision("b", FulfillmentType::LockAndFulfill, ShadowAction::Lock)
+                .with_expected_profit(profit),
+            decision(
+                "c",
+                FulfillmentType::FulfillAfterLockExpire,
+                ShadowAction::ProveAfterLockExpire,
+            )
+            .with_expected_profit(profit),
+            ShadowDecision {
+                reason: Some(SkipReason::Expired),
+                ..decision("d", FulfillmentType::LockAndFulfill, ShadowAction::Skip)
+            },
+            decision("a", FulfillmentType::LockAndFulfill, ShadowAction::Locked),
+            ShadowDecision {
+                reason: Some(SkipReason::LockFailed),
+                ..decision("b", FulfillmentType::LockAndFulfill, ShadowAction::Skip)
+            },
+        ];
+
+        let report = ShadowReport::new(log);
+        assert_eq!(report.orders, 4);
+        assert_eq!(report.actions["Locked"], 1);
+        assert_eq!(report.actions["ProveAfterLockExpire"], 1);
+        assert_eq!(report.actions["Skip"], 2);
+        assert_eq!(report.skip_reasons[&SkipReason::Expired], 1);
+        assert_eq!(report.skip_reasons[&SkipReason::LockFailed], 1);
+        // Only the profit of orders that were not skipped is counted
+        assert_eq!(report.expected_profit, profit);
+        assert_eq!(report.expected_stake_profit, profit);
+        assert_eq!(
+            report.decisions.iter().map(|d| d.order_id.as_str()).collect::<Vec<_>>(),
+            vec!["a", "b", "c", "d"]
+        );
+    }
+}
@@ -0,0 +1,392 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Price of the stake token in native token.
+//!
+//! Orders with an expired lock pay out a fraction of the slashed stake, while the gas to fulfill
+//! them is paid in native token. A [StakePriceOracle] converts between the two so that gas costs
+//! can be accounted for when pricing those orders. The oracle is selected with
+//! `market.stake_price_oracle` in `broker.toml`.
+
+use std::sync::Arc;
+
+use alloy::{
+    network::Ethereum,
+    primitives::{utils::parse_ether, Address, U256},
+    providers::Provider,
+    sol, uint,
+};
+use anyhow::{bail, ensure, Context, Result};
+use async_trait::async_trait;
+
+use crate::{config::StakePriceOracleConf, now_timestamp};
+
+const WEI_PER_ETHER: U256 = uint!(1_000_000_000_000_000_000_U256);
+
+sol! {
+    #[sol(rpc)]
+    interface IUniswapV2Pair {
+        function token0() external view returns (address);
+        function token1() external view returns (address);
+        function getReserves() external view returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast);
+    }
+
+    #[sol(rpc)]
+    interface AggregatorV3Interface {
+        function decimals() external view returns (uint8);
+        function latestRoundData() external view returns (uint80 roundId, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound);
+    }
+}
+
+/// Exchange rate between the stake token and the native token.
+///
+/// Expressed as the ratio of an amount of native token, in wei, to an equivalent amount of stake
+/// token, in its smallest unit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StakeTokenPrice {
+    native: U256,
+    stake: U256,
+}
+
+impl StakeTokenPrice {
+    /// Create a price where `native` wei is worth `stake` units of the stake token.
+    pub fn new(native: U256, stake: U256) -> Result<Self> {
+        ensure!(!native.is_zero() && !stake.is_zero(), "Stake token price must be non-zero");
+        Ok(Self { native, stake })
+    }
+
+    /// Convert an amount of stake token to native token, rounding down.
+    pub fn to_native(&self, stake_amount: U256) -> U256 {
+        stake_amount.saturating_mul(self.native) / self.stake
+    }
+
+    /// Convert an amount of native token to stake token, rounding up.
+    pub fn to_stake(&self, native_amount: U256) -> U256 {
+        native_amount.saturating_mul(self.stake).div_ceil(self.native)
+    }
+}
+
+/// Source of the price of the stake token in native token.
+#[async_trait]
+pub trait StakePriceOracle {
+    /// Fetch the current price of the stake token.
+    async fn price(&self) -> Result<StakeTokenPrice>;
+}
+
+pub type StakePriceOracleObj = Arc<dyn StakePriceOracle + Send + Sync>;
+
+/// Construct the oracle described by the broker config.
+pub async fn from_config<P>(
+    conf: &StakePriceOracleConf,
+    provider: Arc<P>,
+    stake_token: Address,
+    stake_token_decimals: u8,
+) -> Result<StakePriceOracleObj>
+where
+    P: Provider<Ethereum> + 'static + Clone,
+{
+    Ok(match conf {
+        StakePriceOracleConf::Fixed { price } => {
+            Arc::new(FixedStakePrice::new(price, stake_token_decimals)?)
+        }
+        StakePriceOracleConf::UniswapV2 { pair } => {
+            Arc::new(UniswapV2StakePrice::new(*pair, provider, stake_token).await?)
+        }
+        StakePriceOracleConf::Chainlink { aggregator, max_age_secs } => Arc::new(
+            ChainlinkStakePrice::new(*aggregator, provider, stake_token_decimals, *max_age_secs)
+                .await?,
+        ),
+    })
+}
+
+/// A fixed, configured price.
+#[derive(Clone, Debug)]
+pub struct FixedStakePrice {
+    price: StakeTokenPrice,
+}
+
+impl FixedStakePrice {
+    /// Create a fixed price from the amount of native token, e.g. "0.0003", paid for one whole
+    /// stake token.
+    pub fn new(price: &str, stake_token_decimals: u8) -> Result<Self> {
+        let native = parse_ether(price).context("Failed to parse fixed stake token price")?;
+        let stake = U256::from(10).pow(U256::from(stake_token_decimals));
+        Ok(Self { price: StakeTokenPrice::new(native, stake)? })
+    }
+}
+
+#[async_trait]
+impl StakePriceOracle for FixedStakePrice {
+    async fn price(&self) -> Result<StakeTokenPrice> {
+        Ok(self.price)
+    }
+}
+
+/// Spot price from the reserves of a Uniswap V2 style pool pairing the stake token with the
+/// wrapped native token.
+pub struct UniswapV2StakePrice<P> {
+    pair: IUniswapV2Pair::IUniswapV2PairInstance<Arc<P>>,
+    stake_is_token0: bool,
+}
+
+impl<P> UniswapV2StakePrice<P>
+where
+    P: Provider<Ethereum> + 'static + Clone,
+{
+    pub async fn new(pair: Address, provider: Arc<P>, stake_token: Address) -> Result<Self> {
+        let pair = IUniswapV2Pair::new(pair, provider);
+        let token0 = pair.token0().call().await.context("Failed to query pool token0")?;
+        let token1 = pair.token1().call().await.context("Failed to query pool token1")?;
+        let stake_is_token0 = if token0 == stake_token {
+            true
+        } else if token1 == stake_token {
+            false
+        } else {
+            bail!("Pool {} does not contain the stake token {stake_token}", pair.address());
+        };
+        Ok(Self { pair, stake_is_token0 })
+    }
+}
+
+#[async_trait]
+impl<P> StakePriceOracle for UniswapV2StakePrice<P>
+where
+    P: Provider<Ethereum> + 'static + Clone,
+{
+    async fn price(&self) -> Result<StakeTokenPrice> {
+        let reserves =
+            self.pair.getReserves().call().await.context("Failed to query pool reserves")?;
+        let (reserve0, reserve1) = (U256::from(reserves.reserve0), U256::from(reserves.reserve1));
+        let (stake, native) =
+            if self.stake_is_token0 { (reserve0, reserve1) } else { (reserve1, reserve0) };
+        StakeTokenPrice::new(native, stake).context("Pool has no liquidity")
+    }
+}
+
+/// Price reported by a Chainlink style aggregator of the stake token price in native token.
+pub struct ChainlinkStakePrice<P> {
+    aggregator: AggregatorV3Interface::AggregatorV3InterfaceInstance<Arc<P>>,
+    // 10^(aggregator decimals + stake token decimals)
+    scale: U256,
+    max_age_secs: u64,
+}
+
+impl<P> ChainlinkStakePrice<P>
+where
+    P: Provider<Ethereum> + 'static + Clone,
+{
+    pub async fn new(
+        aggregator: Address,
+        provider: Arc<P>,
+        stake_token_decimals: u8,
+        max_age_secs: u64,
+    ) -> Result<Self> {
+        let aggregator = AggregatorV3Interface::new(aggregator, provider);
+        let decimals =
+            aggregator.decimals().call().await.context("Failed to query aggregator decimals")?;
+        let scale = U256::from(10).pow(U256::from(decimals) + U256::from(stake_token_decimals));
+        Ok(Self { aggregator, scale, max_age_secs })
+    }
+}
+
+#[async_trait]
+impl<P> StakePriceOracle for ChainlinkStakePrice<P>
+where
+    P: Provider<Ethereum> + 'static + Clone,
+{
+    async fn price(&self) -> Result<StakeTokenPrice> {
+        let round = self
+            .aggregator
+            .latestRoundData()
+            .call()
+            .await
+            .context("Failed to query aggregator round data")?;
+
+        let updated_at: u64 = round.updatedAt.try_into().unwrap_or(u64::MAX);
+        let age = now_timestamp().saturating_sub(updated_at);
+        ensure!(age <= self.max_age_secs, "Aggregator price is stale, last updated {age}s ago");
+
+        if round.answer.is_negative() || round.answer.is_zero() {
+            bail!("Aggregator reported an invalid price: {}", round.answer);
+        }
+
+        StakeTokenPrice::new(round.answer.into_raw().saturating_mul(WEI_PER_ETHER), self.scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{
+        primitives::{
+            aliases::{U112, U80},
+            utils::parse_units,
+            Bytes, I256,
+        },
+        providers::ProviderBuilder,
+        sol_types::SolCall,
+        transports::mock::Asserter,
+    };
+
+    const STAKE_TOKEN: Address = Address::repeat_byte(0x5a);
+    const WETH: Address = Address::repeat_byte(0xee);
+
+    fn mock_provider() -> (Asserter, Arc<impl Provider<Ethereum> + Clone + 'static>) {
+        let asserter = Asserter::new();
+        let provider = ProviderBuilder::new().connect_mocked_client(asserter.clone());
+        (asserter, Arc::new(provider))
+    }
+
+    /// Queue the response of an eth_call to the mocked provider
+    fn push_return<C: SolCall>(asserter: &Asserter, ret: &C::Return) {
+        asserter.push_success(&Bytes::from(C::abi_encode_returns(ret)));
+    }
+
+    fn push_reserves(asserter: &Asserter, reserve0: U256, reserve1: U256) {
+        push_return::<IUniswapV2Pair::getReservesCall>(
+            asserter,
+            &IUniswapV2Pair::getReservesReturn {
+                reserve0: U112::from(reserve0),
+                reserve1: U112::from(reserve1),
+                blockTimestampLast: 0,
+            },
+        );
+    }
+
+    fn push_round(asserter: &Asserter, answer: I256, updated_at: u64) {
+        push_return::<AggregatorV3Interface::latestRoundDataCall>(
+            asserter,
+            &AggregatorV3Interface::latestRoundDataReturn {
+                roundId: U80::from(1),
+                answer,
+                startedAt: U256::from(updated_at),
+                updatedAt: U256::from(updated_at),
+                answeredInRound: U80::from(1),
+            },
+        );
+    }
+
+    #[test]
+    fn stake_token_price_conversion() {
+        // 1 ETH for 2000 USDC
+        let price =
+            StakeTokenPrice::new(parse_ether("1").unwrap(), parse_units("2000", 6).unwrap().into())
+                .unwrap();
+
+        assert_eq!(
+            price.to_native(parse_units("1000", 6).unwrap().into()),
+            parse_ether("0.5").unwrap()
+        );
+        assert_eq!(price.to_stake(parse_ether("0.5").unwrap()), parse_units("1000", 6).unwrap());
+        // Native to stake conversions round up
+        assert_eq!(price.to_stake(U256::from(1)), U256::from(1));
+        assert_eq!(price.to_native(U256::from(1)), U256::from(500_000_000));
+
+        assert!(StakeTokenPrice::new(U256::ZERO, U256::from(1)).is_err());
+    }
+
+    #[tokio::test]
+    async fn fixed_stake_price() {
+        let oracle = FixedStakePrice::new("0.0005", 6).unwrap();
+        let price = oracle.price().await.unwrap();
+        assert_eq!(
+            price.to_native(parse_units("10", 6).unwrap().into()),
+            parse_ether("0.005").unwrap()
+        );
+
+        assert!(FixedStakePrice::new("0", 6).is_err());
+        assert!(FixedStakePrice::new("not a price", 6).is_err());
+    }
+
+    #[tokio::test]
+    async fn uniswap_v2_stake_price() {
+        let usdc = |amount: &str| -> U256 { parse_units(amount, 6).unwrap().into() };
+
+        // The stake token is token1, priced against WETH as token0
+        let (asserter, provider) = mock_provider();
+        push_return::<IUniswapV2Pair::token0Call>(&asserter, &WETH);
+        push_return::<IUniswapV2Pair::token1Call>(&asserter, &STAKE_TOKEN);
+        let oracle = UniswapV2StakePrice::new(Address::ZERO, provider, STAKE_TOKEN).await.unwrap();
+
+        // 10 WETH for 20000 USDC
+        push_reserves(&asserter, parse_ether("10").unwrap(), usdc("20000"));
+        let price = oracle.price().await.unwrap();
+        assert_eq!(price.to_native(usdc("1000")), parse_ether("0.5").unwrap());
+        assert_eq!(price.to_stake(parse_ether("0.5").unwrap()), usdc("1000"));
+
+        // A pool without liquidity has no price
+        push_reserves(&asserter, U256::ZERO, U256::ZERO);
+        assert!(oracle.price().await.is_err());
+
+        // The stake token is token0, so the reserves are read in the opposite order
+        let (asserter, provider) = mock_provider();
+        push_return::<IUniswapV2Pair::token0Call>(&asserter, &STAKE_TOKEN);
+        push_return::<IUniswapV2Pair::token1Call>(&asserter, &WETH);
+        let oracle = UniswapV2StakePrice::new(Address::ZERO, provider, STAKE_TOKEN).await.unwrap();
+
+        push_reserves(&asserter, usdc("20000"), parse_ether("10").unwrap());
+        let price = oracle.price().await.unwrap();
+        assert_eq!(price.to_native(usdc("1000")), parse_ether("0.5").unwrap());
+    }
+
+    #[tokio::test]
+    async fn uniswap_v2_pool_without_stake_token() {
+        let (asserter, provider) = mock_provider();
+        push_return::<IUniswapV2Pair::token0Call>(&asserter, &WETH);
+        push_return::<IUniswapV2Pair::token1Call>(&asserter, &Address::repeat_byte(0x01));
+        assert!(UniswapV2StakePrice::new(Address::ZERO, provider, STAKE_TOKEN).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn chainlink_stake_price() {
+        // Aggregators report the price of one whole stake token in native token, at their own
+        // decimals, independent of the decimals of the stake token.
+        for (aggregator_decimals, stake_token_decimals) in [(18, 6), (8, 6), (18, 18)] {
+            let (asserter, provider) = mock_provider();
+            push_return::<AggregatorV3Interface::decimalsCall>(&asserter, &aggregator_decimals);
+            let oracle =
+                ChainlinkStakePrice::new(Address::ZERO, provider, stake_token_decimals, 3600)
+                    .await
+                    .unwrap();
+
+            // 0.0005 ETH per stake token
+            let answer: U256 = parse_units("0.0005", aggregator_decimals).unwrap().into();
+            push_round(&asserter, I256::from_raw(answer), now_timestamp());
+            let price = oracle.price().await.unwrap();
+            let stake: U256 = parse_units("10", stake_token_decimals).unwrap().into();
+            assert_eq!(
+                price.to_native(stake),
+                parse_ether("0.005").unwrap(),
+                "aggregator decimals {aggregator_decimals}, stake decimals {stake_token_decimals}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn chainlink_invalid_price() {
+        let (asserter, provider) = mock_provider();
+        push_return::<AggregatorV3Interface::decimalsCall>(&asserter, &18);
+        let oracle = ChainlinkStakePrice::new(Address::ZERO, provider, 6, 3600).await.unwrap();
+
+        // Stale price
+        push_round(&asserter, I256::from_raw(U256::from(1_000)), now_timestamp() - 3601);
+        assert!(oracle.price().await.is_err());
+
+        // Non-positive prices
+        push_round(&asserter, I256::ZERO, now_timestamp());
+        assert!(oracle.price().await.is_err());
+        push_round(&asserter, I256::MINUS_ONE, now_timestamp());
+        assert!(oracle.price().await.is_err());
+    }
+}
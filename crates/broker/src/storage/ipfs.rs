@@ -0,0 +1,590 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Fetching of `ipfs://` URLs.
+//!
+//! Content is fetched as raw blocks, from a Kubo HTTP RPC API or from trustless gateways, and
+//! every block is verified against its CID before it is used, so untrusted public gateways can
+//! serve the content. Files are reassembled from the UnixFS DAG below their root block. Only CIDs
+//! with SHA-256 multihashes are supported, which covers the CIDs produced by Kubo and Pinata.
+//! Blocks are limited to [MAX_BLOCK_SIZE] and files to [MAX_BLOCKS] blocks, so that a source
+//! cannot exhaust the memory of the broker or keep it fetching indefinitely.
+
+use std::fmt::{Display, Formatter};
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+use reqwest_retry::{policies::ExponentialBackoff, RetryTransientMiddleware};
+use risc0_zkvm::sha::{Impl as ShaImpl, Sha256};
+
+use super::{Handler, StorageErr};
+
+/// Multicodec of raw binary blocks
+const RAW_CODEC: u64 = 0x55;
+/// Multicodec of MerkleDAG protobuf blocks
+const DAG_PB_CODEC: u64 = 0x70;
+/// Multihash code of SHA-256
+const SHA2_256: u64 = 0x12;
+
+/// UnixFS data types that hold file contents
+const UNIXFS_RAW: u64 = 0;
+const UNIXFS_FILE: u64 = 2;
+
+/// Largest block read from a source, the limit on blocks exchanged by IPFS nodes
+const MAX_BLOCK_SIZE: usize = 2 * 1024 * 1024;
+/// Most blocks fetched for a single file
+const MAX_BLOCKS: usize = 16 * 1024;
+
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// Content identifier of an IPFS block
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Cid {
+    /// CIDv0 are base58 encoded SHA-256 multihashes of dag-pb blocks
+    v0: bool,
+    codec: u64,
+    digest: [u8; 32],
+}
+
+impl Cid {
+    /// Parse a CID from its binary encoding, as found in the links of dag-pb blocks
+    fn from_bytes(bytes: &[u8]) -> Result<Self, StorageErr> {
+        if bytes.len() == 34 && bytes[0] == SHA2_256 as u8 && bytes[1] == 32 {
+            return Ok(Self {
+                v0: true,
+                codec: DAG_PB_CODEC,
+                digest: bytes[2..].try_into().unwrap(),
+            });
+        }
+
+        let mut reader = Reader(bytes);
+        if reader.varint()? != 1 {
+            return Err(ipfs_err("unsupported CID version"));
+        }
+        let codec = reader.varint()?;
+        if reader.varint()? != SHA2_256 {
+            return Err(ipfs_err("unsupported CID multihash, only sha2-256 is supported"));
+        }
+        let digest = reader.bytes()?;
+        let digest = digest.try_into().map_err(|_| ipfs_err("invalid sha2-256 digest length"))?;
+        if !reader.0.is_empty() {
+            return Err(ipfs_err("trailing bytes in CID"));
+        }
+        Ok(Self { v0: false, codec, digest })
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(38);
+        if !self.v0 {
+            bytes.push(1);
+            write_varint(&mut bytes, self.codec);
+        }
+        write_varint(&mut bytes, SHA2_256);
+        bytes.push(32);
+        bytes.extend_from_slice(&self.digest);
+        bytes
+    }
+
+    /// Verify that a block matches the CID
+    fn verify(&self, block: &[u8]) -> bool {
+        ShaImpl::hash_bytes(block).as_bytes() == self.digest
+    }
+}
+
+impl std::str::FromStr for Cid {
+    type Err = StorageErr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = if s.len() == 46 && s.starts_with("Qm") {
+            bs58::decode(s).into_vec().map_err(|_| ipfs_err("invalid base58 CID"))?
+        } else if let Some(s) = s.strip_prefix('b') {
+            base32_decode(s).ok_or_else(|| ipfs_err("invalid base32 CID"))?
+        } else if let Some(s) = s.strip_prefix('z') {
+            bs58::decode(s).into_vec().map_err(|_| ipfs_err("invalid base58 CID"))?
+        } else {
+            return Err(ipfs_err("unsupported CID encoding"));
+        };
+        Self::from_bytes(&bytes)
+    }
+}
+
+impl Display for Cid {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.v0 {
+            f.write_str(&bs58::encode(self.to_bytes()).into_string())
+        } else {
+            write!(f, "b{}", base32_encode(&self.to_bytes()))
+        }
+    }
+}
+
+/// Handles fetching the contents of `ipfs://` URLs.
+///
+/// Blocks are requested from the Kubo RPC API first, when configured, and then from each
+/// gateway in order, until one returns a block matching the CID.
+pub(crate) struct IpfsHandler {
+    cid: Cid,
+    api_url: Option<url::Url>,
+    gateways: Vec<url::Url>,
+    client: ClientWithMiddleware,
+    max_size: usize,
+}
+
+impl IpfsHandler {
+    pub(crate) fn new(
+        url: url::Url,
+        api_url: Option<&str>,
+        gateways: &[String],
+        max_size: usize,
+        max_retries: Option<u8>,
+    ) -> Result<Self, StorageErr> {
+        if url.scheme() != "ipfs" {
+            return Err(StorageErr::InvalidURL("invalid IPFS scheme"));
+        }
+        let cid = url.host_str().ok_or(StorageErr::InvalidURL("missing CID"))?.parse()?;
+        if !matches!(url.path(), "" | "/") {
+            return Err(StorageErr::InvalidURL("IPFS paths are not supported"));
+        }
+
+        let api_url = api_url.map(parse_base_url).transpose()?;
+        let gateways = gateways
+            .iter()
+            .map(|gateway| parse_base_url(gateway))
+            .collect::<Result<Vec<_>, _>>()?;
+        if api_url.is_none() && gateways.is_empty() {
+            return Err(StorageErr::InvalidURL("no IPFS API or gateway configured"));
+        }
+
+        let mut builder = ClientBuilder::new(reqwest::Client::new());
+        if let Some(max_retries) = max_retries {
+            let retry_policy =
+                ExponentialBackoff::builder().build_with_max_retries(max_retries as u32);
+            builder = builder.with(RetryTransientMiddleware::new_with_policy(retry_policy));
+        }
+
+        Ok(Self { cid, api_url, gateways, client: builder.build(), max_size })
+    }
+
+    /// Fetch a block from the first source returning a block that matches the CID
+    async fn fetch_block(&self, cid: &Cid) -> Result<Vec<u8>, StorageErr> {
+        let mut requests = Vec::new();
+        if let Some(api_url) = &self.api_url {
+            // https://docs.ipfs.tech/reference/kubo/rpc/#api-v0-block-get
+            let url = api_url.join(&format!("api/v0/block/get?arg={cid}"))?;
+            requests.push(self.client.post(url));
+        }
+        for gateway in &self.gateways {
+            // https://specs.ipfs.tech/http-gateways/trustless-gateway/
+            let url = gateway.join(&format!("ipfs/{cid}?format=raw"))?;
+            requests.push(self.client.get(url).header("Accept", "application/vnd.ipld.raw"));
+        }
+
+        for request in requests {
+            let response = match request.send().await.and_then(|response| {
+                response.error_for_status().map_err(reqwest_middleware::Error::from)
+            }) {
+                Ok(response) => response,
+                Err(err) => {
+                    tracing::debug!("Failed to fetch IPFS block {cid}: {err}");
+                    continue;
+                }
+            };
+            let source = response.url().clone();
+            let block = match read_block(response).await {
+                Ok(block) => block,
+                Err(err) => {
+                    tracing::debug!("Failed to read IPFS block {cid} from {source}: {err}");
+                    continue;
+                }
+            };
+            if !cid.verify(&block) {
+                tracing::warn!("IPFS block {cid} from {source} does not match its CID");
+                continue;
+            }
+            return Ok(block);
+        }
+
+        Err(ipfs_err(format!("failed to fetch block {cid} from any IPFS source")))
+    }
+}
+
+impl Display for IpfsHandler {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ipfs://{}", self.cid)
+    }
+}
+
+#[async_trait]
+impl Handler for IpfsHandler {
+    async fn fetch(&self) -> Result<Vec<u8>, StorageErr> {
+        let mut buffer = Vec::new();
+        // Depth first traversal of the DAG, in link order
+        let mut pending = vec![self.cid.clone()];
+        let mut fetched = 0;
+        while let Some(cid) = pending.pop() {
+            fetched += 1;
+            if fetched + pending.len() > MAX_BLOCKS {
+                return Err(ipfs_err(format!("file exceeds the limit of {MAX_BLOCKS} blocks")));
+            }
+            let block = self.fetch_block(&cid).await?;
+            match cid.codec {
+                RAW_CODEC => buffer.extend_from_slice(&block),
+                DAG_PB_CODEC => {
+                    let (data, links) = decode_dag_pb(&block)?;
+                    buffer.extend_from_slice(data);
+                    pending.extend(links.into_iter().rev());
+                }
+                codec => return Err(ipfs_err(format!("unsupported CID codec {codec:#x}"))),
+            }
+            if buffer.len() > self.max_size {
+                return Err(StorageErr::SizeLimitExceeded(buffer.len()));
+            }
+        }
+
+        Ok(buffer)
+    }
+}
+
+/// Parse the URL of an IPFS API or gateway, which request paths are joined onto
+///
+/// The path of the URL is treated as a directory, so that a gateway served under a path prefix
+/// keeps it when joined.
+fn parse_base_url(url: &str) -> Result<url::Url, StorageErr> {
+    let mut url = url::Url::parse(url)?;
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+    Ok(url)
+}
+
+fn ipfs_err(msg: impl Into<String>) -> StorageErr {
+    StorageErr::Ipfs(msg.into())
+}
+
+/// Read a block from a response, failing as soon as it exceeds [MAX_BLOCK_SIZE]
+async fn read_block(response: reqwest::Response) -> Result<Vec<u8>, StorageErr> {
+    let size = response.content_length().unwrap_or_default() as usize;
+    if size > MAX_BLOCK_SIZE {
+        return Err(StorageErr::SizeLimitExceeded(size));
+    }
+
+    let mut block = Vec::with_capacity(size);
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|err| StorageErr::Http(err.into()))?;
+        block.extend_from_slice(&chunk);
+        if block.len() > MAX_BLOCK_SIZE {
+            return Err(StorageErr::SizeLimitExceeded(block.len()));
+        }
+    }
+    Ok(block)
+}
+
+/// Decode a dag-pb block holding a UnixFS file, returning the file data of the block and the
+/// links to the blocks holding the rest of the file
+fn decode_dag_pb(block: &[u8]) -> Result<(&[u8], Vec<Cid>), StorageErr> {
+    // https://ipld.io/specs/codecs/dag-pb/spec/#serial-format
+    let mut node_data: &[u8] = &[];
+    let mut links = Vec::new();
+    let mut reader = Reader(block);
+    while let Some((field, value)) = reader.field()? {
+        match (field, value) {
+            (1, Value::Bytes(data)) => node_data = data,
+            (2, Value::Bytes(link)) => {
+                let mut reader = Reader(link);
+                while let Some((field, value)) = reader.field()? {
+                    if let (1, Value::Bytes(hash)) = (field, value) {
+                        links.push(Cid::from_bytes(hash)?);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // https://github.com/ipfs/specs/blob/main/UNIXFS.md#data-format
+    let mut data_type = None;
+    let mut data: &[u8] = &[];
+    let mut reader = Reader(node_data);
+    while let Some((field, value)) = reader.field()? {
+        match (field, value) {
+            (1, Value::Varint(value)) => data_type = Some(value),
+            (2, Value::Bytes(value)) => data = value,
+            _ => {}
+        }
+    }
+    match data_type {
+        Some(UNIXFS_RAW | UNIXFS_FILE) => Ok((data, links)),
+        Some(data_type) => Err(ipfs_err(format!("unsupported UnixFS data type {data_type}"))),
+        None => Err(ipfs_err("missing UnixFS data type")),
+    }
+}
+
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+/// Reader of the protobuf wire format
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn varint(&mut self) -> Result<u64, StorageErr> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = self.0.split_first().ok_or_else(|| ipfs_err("truncated varint"))?;
+            self.0 = rest;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(ipfs_err("varint overflow"))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], StorageErr> {
+        let len = self.varint()? as usize;
+        self.take(len)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], StorageErr> {
+        if self.0.len() < len {
+            return Err(ipfs_err("truncated block"));
+        }
+        let (value, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(value)
+    }
+
+    fn field(&mut self) -> Result<Option<(u64, Value<'a>)>, StorageErr> {
+        if self.0.is_empty() {
+            return Ok(None);
+        }
+        let key = self.varint()?;
+        let value = match key & 0x7 {
+            0 => Value::Varint(self.varint()?),
+            1 => self.take(8).map(|_| Value::Fixed)?,
+            2 => Value::Bytes(self.bytes()?),
+            5 => self.take(4).map(|_| Value::Fixed)?,
+            wire_type => return Err(ipfs_err(format!("unsupported wire type {wire_type}"))),
+        };
+        Ok(Some((key >> 3, value)))
+    }
+}
+
+fn write_varint(buffer: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buffer.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+/// Encode as RFC 4648 base32, lowercase and unpadded, as used by multibase
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let (mut acc, mut bits) = (0u32, 0);
+    for &byte in bytes {
+        acc = (acc << 8) | u32::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[(acc >> bits) as usize & 0x1f] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[(acc << (5 - bits)) as usize & 0x1f] as char);
+    }
+    out
+}
+
+fn base32_decode(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len() * 5 / 8);
+    let (mut acc, mut bits) = (0u32, 0);
+    for c in s.bytes() {
+        let value = BASE32_ALPHABET.iter().position(|&a| a == c.to_ascii_lowercase())? as u32;
+        acc = (acc << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::prelude::*;
+
+    const EMPTY_DIR_V0: &str = "QmUNLLsPACCz1vLxQVkXqqLX5R1X345qqfHbsf67hvA3Nn";
+    const EMPTY_DIR_V1: &str = "bafybeiczsscdsbs7ffqz55asqdf3smv6klcw3gofszvwlyarci47bgf354";
+    const HELLO_WORLD_RAW: &str = "bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e";
+
+    fn raw_cid(data: &[u8]) -> Cid {
+        Cid {
+            v0: false,
+            codec: RAW_CODEC,
+            digest: ShaImpl::hash_bytes(data).as_bytes().try_into().unwrap(),
+        }
+    }
+
+    fn bytes_field(buffer: &mut Vec<u8>, field: u64, value: &[u8]) {
+        write_varint(buffer, (field << 3) | 2);
+        write_varint(buffer, value.len() as u64);
+        buffer.extend_from_slice(value);
+    }
+
+    /// dag-pb block of a UnixFS file split across the linked blocks
+    fn file_node(links: &[Cid]) -> Vec<u8> {
+        let mut block = Vec::new();
+        for link in links {
+            let mut pb_link = Vec::new();
+            bytes_field(&mut pb_link, 1, &link.to_bytes());
+            bytes_field(&mut block, 2, &pb_link);
+        }
+        let mut unixfs = Vec::new();
+        write_varint(&mut unixfs, 1 << 3);
+        write_varint(&mut unixfs, UNIXFS_FILE);
+        bytes_field(&mut block, 1, &unixfs);
+        block
+    }
+
+    #[test]
+    fn parse_cid() {
+        let v0: Cid = EMPTY_DIR_V0.parse().unwrap();
+        let v1: Cid = EMPTY_DIR_V1.parse().unwrap();
+        assert_eq!((v0.codec, v1.codec), (DAG_PB_CODEC, DAG_PB_CODEC));
+        assert_eq!(v0.digest, v1.digest);
+        assert!(v0.verify(&[0x0a, 0x02, 0x08, 0x01]));
+        assert_eq!(v0.to_string(), EMPTY_DIR_V0);
+        assert_eq!(v1.to_string(), EMPTY_DIR_V1);
+
+        let raw: Cid = HELLO_WORLD_RAW.parse().unwrap();
+        assert_eq!(raw, raw_cid(b"hello world"));
+        assert_eq!(raw.to_string(), HELLO_WORLD_RAW);
+
+        assert!("Qmfoo".parse::<Cid>().is_err());
+        assert!("bafy".parse::<Cid>().is_err());
+    }
+
+    #[tokio::test]
+    async fn fetch_verified_blocks() {
+        let first = b"hello ".to_vec();
+        let second = b"world".to_vec();
+        let root_block = file_node(&[raw_cid(&first), raw_cid(&second)]);
+        let root = Cid { v0: true, codec: DAG_PB_CODEC, ..raw_cid(&root_block) };
+
+        // The first gateway serves corrupted blocks, which are fetched again from the second
+        let bad_gateway = MockServer::start();
+        let bad_mock = bad_gateway.mock(|when, then| {
+            when.method(GET).path_contains("/ipfs/");
+            then.status(200).body("corrupted");
+        });
+        let gateway = MockServer::start();
+        for (cid, block) in
+            [(root.clone(), root_block), (raw_cid(&first), first), (raw_cid(&second), second)]
+        {
+            gateway.mock(|when, then| {
+                when.method(GET).path(format!("/ipfs/{cid}")).query_param("format", "raw");
+                then.status(200).body(&block);
+            });
+        }
+
+        let url = url::Url::parse(&format!("ipfs://{root}")).unwrap();
+        let gateways = [bad_gateway.base_url(), gateway.base_url()];
+        let handler = IpfsHandler::new(url.clone(), None, &gateways, 1024, None).unwrap();
+        assert_eq!(handler.to_string(), url.to_string());
+        assert_eq!(handler.fetch().await.unwrap(), b"hello world");
+        bad_mock.assert_hits(3);
+
+        let handler = IpfsHandler::new(url.clone(), None, &gateways, 8, None).unwrap();
+        assert!(matches!(handler.fetch().await, Err(StorageErr::SizeLimitExceeded(_))));
+
+        let handler = IpfsHandler::new(url, None, &gateways[..1], 1024, None).unwrap();
+        assert!(matches!(handler.fetch().await, Err(StorageErr::Ipfs(_))));
+    }
+
+    #[tokio::test]
+    async fn fetch_bounded_blocks() {
+        // The first gateway serves a block above the size limit, which is fetched again from the
+        // second
+        let oversized_gateway = MockServer::start();
+        let oversized_mock = oversized_gateway.mock(|when, then| {
+            when.method(GET).path_contains("/ipfs/");
+            then.status(200).body(vec![0u8; MAX_BLOCK_SIZE + 1]);
+        });
+        let gateway = MockServer::start();
+        gateway.mock(|when, then| {
+            when.method(GET).path(format!("/ipfs/{HELLO_WORLD_RAW}"));
+            then.status(200).body("hello world");
+        });
+        let url = url::Url::parse(&format!("ipfs://{HELLO_WORLD_RAW}")).unwrap();
+        let gateways = [oversized_gateway.base_url(), gateway.base_url()];
+        let handler = IpfsHandler::new(url, None, &gateways, usize::MAX, None).unwrap();
+        assert_eq!(handler.fetch().await.unwrap(), b"hello world");
+        oversized_mock.assert();
+
+        // Files linking to more blocks than the limit are rejected before fetching them
+        let links = vec![raw_cid(b""); MAX_BLOCKS];
+        let root_block = file_node(&links);
+        let root = Cid { v0: true, codec: DAG_PB_CODEC, ..raw_cid(&root_block) };
+        let root_mock = gateway.mock(|when, then| {
+            when.method(GET).path(format!("/ipfs/{root}"));
+            then.status(200).body(&root_block);
+        });
+        let leaf_mock = gateway.mock(|when, then| {
+            when.method(GET).path(format!("/ipfs/{}", raw_cid(b"")));
+            then.status(200).body("");
+        });
+        let url = url::Url::parse(&format!("ipfs://{root}")).unwrap();
+        let handler = IpfsHandler::new(url, None, &[gateway.base_url()], usize::MAX, None).unwrap();
+        assert!(matches!(handler.fetch().await, Err(StorageErr::Ipfs(_))));
+        root_mock.assert();
+        leaf_mock.assert_hits(0);
+    }
+
+    #[tokio::test]
+    async fn fetch_from_kubo_api() {
+        let api = MockServer::start();
+        let api_mock = api.mock(|when, then| {
+            when.method(POST).path("/api/v0/block/get").query_param("arg", HELLO_WORLD_RAW);
+            then.status(200).body("hello world");
+        });
+
+        let url = url::Url::parse(&format!("ipfs://{HELLO_WORLD_RAW}")).unwrap();
+        let handler = IpfsHandler::new(url, Some(&api.base_url()), &[], 1024, None).unwrap();
+        assert_eq!(handler.fetch().await.unwrap(), b"hello world");
+        api_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn fetch_from_gateway_path() {
+        let gateway = MockServer::start();
+        let gateway_mock = gateway.mock(|when, then| {
+            when.method(GET).path(format!("/prefix/ipfs/{HELLO_WORLD_RAW}"));
+            then.status(200).body("hello world");
+        });
+
+        // The gateway path is kept without a trailing slash
+        let url = url::Url::parse(&format!("ipfs://{HELLO_WORLD_RAW}")).unwrap();
+        let gateways = [gateway.url("/prefix")];
+        let handler = IpfsHandler::new(url, None, &gateways, 1024, None).unwrap();
+        assert_eq!(handler.fetch().await.unwrap(), b"hello world");
+        gateway_mock.assert();
+    }
+}
@@ -12,8 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{config::ConfigLock, errors::CodedError};
-use alloy::primitives::bytes::Buf;
+use crate::{
+    blob_store::{self, BlobStore},
+    config::ConfigLock,
+    errors::CodedError,
+};
+use alloy::{primitives::bytes::Buf, providers::Provider};
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use aws_config::retry::RetryConfig;
@@ -22,11 +26,18 @@ use aws_sdk_s3::{
     error::ProvideErrorMetadata,
     Client as S3Client,
 };
+use boundless_market::{
+    input::{AssumptionSource, GuestEnv},
+    BoundlessMarketService,
+};
 use futures::StreamExt;
-use http_cache_reqwest::{CACacheManager, Cache, CacheMode, HttpCache, HttpCacheOptions};
 use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
 use reqwest_retry::{policies::ExponentialBackoff, RetryTransientMiddleware};
-use risc0_zkvm::Digest;
+use risc0_ethereum_contracts::receipt::{decode_seal, Receipt as ContractReceipt};
+use risc0_zkvm::{
+    sha::{Impl as ShaImpl, Sha256},
+    Digest,
+};
 use std::{
     env,
     error::Error as StdError,
@@ -35,6 +46,10 @@ use std::{
     sync::Arc,
 };
 
+mod ipfs;
+
+use ipfs::IpfsHandler;
+
 const ENV_VAR_ROLE_ARN: &str = "AWS_ROLE_ARN";
 
 #[derive(thiserror::Error, Debug)]
@@ -60,12 +75,16 @@ pub enum StorageErr {
 
     #[error("{code} AWS S3 error", code = self.code())]
     S3(#[source] Box<dyn StdError + Send + Sync + 'static>),
+
+    #[error("{code} IPFS error: {0}", code = self.code())]
+    Ipfs(String),
 }
 
 impl CodedError for StorageErr {
     fn code(&self) -> &str {
         match self {
             StorageErr::Http(_) => "[B-STR-002]",
+            StorageErr::Ipfs(_) => "[B-STR-003]",
             _ => "[B-STR-500]",
         }
     }
@@ -77,44 +96,40 @@ pub(crate) async fn create_uri_handler(
     skip_max_size_check: bool,
 ) -> Result<Arc<dyn Handler>, StorageErr> {
     let uri = url::Url::parse(uri_str)?;
-
-    match uri.scheme() {
+    // The contents of other URLs may change, so only content-addressed URLs are cached by URL.
+    // Programs are cached by image ID once verified, see [upload_image_uri].
+    let content_addressed = uri.scheme() == "ipfs";
+    let (max_size, max_retries, ipfs_api_url, ipfs_gateways) = {
+        let config = &config.lock_all().expect("lock failed").market;
+        let size = if skip_max_size_check { usize::MAX } else { config.max_file_size };
+        (size, config.max_fetch_retries, config.ipfs_api_url.clone(), config.ipfs_gateways.clone())
+    };
+
+    let handler: Arc<dyn Handler> = match uri.scheme() {
         "file" => {
             if !risc0_zkvm::is_dev_mode() {
                 return Err(StorageErr::UnsupportedScheme("file".to_string()));
             }
-            let max_size = if skip_max_size_check {
-                usize::MAX
-            } else {
-                config.lock_all().expect("lock failed").market.max_file_size
-            };
-
-            let handler = FileHandler { path: uri.path().into(), max_size };
-
-            Ok(Arc::new(handler))
+            Arc::new(FileHandler { path: uri.path().into(), max_size })
         }
-        "http" | "https" => {
-            let (max_size, max_retries, cache_dir) = {
-                let config = &config.lock_all().expect("lock failed").market;
-                let size = if skip_max_size_check { usize::MAX } else { config.max_file_size };
-                (size, config.max_fetch_retries, config.cache_dir.clone())
-            };
-            let handler = HttpHandler::new(uri, max_size, cache_dir, max_retries).await?;
-
-            Ok(Arc::new(handler))
-        }
-        "s3" => {
-            let (max_size, max_retries) = {
-                let config = &config.lock_all().expect("lock failed").market;
-                let size = if skip_max_size_check { usize::MAX } else { config.max_file_size };
-                (size, config.max_fetch_retries)
-            };
-            let handler = S3Handler::new(uri, max_size, max_retries).await?;
-
-            Ok(Arc::new(handler))
+        "http" | "https" => Arc::new(HttpHandler::new(uri, max_size, max_retries).await?),
+        "s3" => Arc::new(S3Handler::new(uri, max_size, max_retries).await?),
+        "ipfs" => Arc::new(IpfsHandler::new(
+            uri,
+            ipfs_api_url.as_deref(),
+            &ipfs_gateways,
+            max_size,
+            max_retries,
+        )?),
+        scheme => return Err(StorageErr::UnsupportedScheme(scheme.to_string())),
+    };
+
+    Ok(match BlobStore::from_config(config) {
+        Some(store) if content_addressed => {
+            Arc::new(CachedHandler { uri: uri_str.to_string(), inner: handler, store, max_size })
         }
-        scheme => Err(StorageErr::UnsupportedScheme(scheme.to_string())),
-    }
+        _ => handler,
+    })
 }
 
 #[async_trait]
@@ -122,6 +137,39 @@ pub(crate) trait Handler: Display + Send + Sync {
     async fn fetch(&self) -> Result<Vec<u8>, StorageErr>;
 }
 
+/// Serves the contents of a content-addressed URL from the [BlobStore] when present, storing them
+/// when fetched
+struct CachedHandler {
+    uri: String,
+    inner: Arc<dyn Handler>,
+    store: BlobStore,
+    max_size: usize,
+}
+
+impl Display for CachedHandler {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+#[async_trait]
+impl Handler for CachedHandler {
+    async fn fetch(&self) -> Result<Vec<u8>, StorageErr> {
+        let key = blob_store::uri_key(&self.uri);
+        if let Some(data) = self.store.get(&key).await {
+            if data.len() > self.max_size {
+                return Err(StorageErr::SizeLimitExceeded(data.len()));
+            }
+            tracing::trace!("Serving {} from cache", self.inner);
+            return Ok(data);
+        }
+
+        let data = self.inner.fetch().await?;
+        self.store.put(&key, &data).await;
+        Ok(data)
+    }
+}
+
 struct FileHandler {
     path: PathBuf,
     max_size: usize,
@@ -156,7 +204,6 @@ impl HttpHandler {
     async fn new(
         url: url::Url,
         max_size: usize,
-        cache_dir: Option<PathBuf>,
         max_retries: Option<u8>,
     ) -> Result<Self, StorageErr> {
         if !matches!(url.scheme(), "http" | "https") {
@@ -168,17 +215,6 @@ impl HttpHandler {
 
         let mut builder = ClientBuilder::new(reqwest::Client::new());
 
-        if let Some(cache_dir) = cache_dir {
-            tokio::fs::create_dir_all(&cache_dir).await?;
-            let manager = CACacheManager { path: cache_dir };
-            let cache_middleware = Cache(HttpCache {
-                mode: CacheMode::ForceCache,
-                manager,
-                options: HttpCacheOptions::default(),
-            });
-
-            builder = builder.with(cache_middleware)
-        }
         if let Some(max_retries) = max_retries {
             let retry_policy =
                 ExponentialBackoff::builder().build_with_max_retries(max_retries as u32);
@@ -357,28 +393,53 @@ pub async fn upload_image_uri(
         return Ok(image_id_str);
     }
 
-    tracing::debug!(
-        "Fetching program for request {:x} with image ID {image_id_str} from URI {}",
-        request.id,
-        request.imageUrl
-    );
-    let uri = create_uri_handler(&request.imageUrl, config, false)
-        .await
-        .context("URL handling failed")?;
+    let store = BlobStore::from_config(config);
+    let image_key = blob_store::image_key(&image_id_str);
+    let cached = match &store {
+        Some(store) => store.get(&image_key).await,
+        None => None,
+    };
+    let image_data = match cached {
+        Some(image_data) => {
+            tracing::debug!(
+                "Restoring program for request {:x} with image ID {image_id_str} from cache",
+                request.id
+            );
+            image_data
+        }
+        None => {
+            tracing::debug!(
+                "Fetching program for request {:x} with image ID {image_id_str} from URI {}",
+                request.id,
+                request.imageUrl
+            );
+            let uri = create_uri_handler(&request.imageUrl, config, false)
+                .await
+                .context("URL handling failed")?;
 
-    let image_data = uri
-        .fetch()
-        .await
-        .with_context(|| format!("Failed to fetch image URI: {}", request.imageUrl))?;
+            uri.fetch()
+                .await
+                .with_context(|| format!("Failed to fetch image URI: {}", request.imageUrl))?
+        }
+    };
     let image_id = risc0_zkvm::compute_image_id(&image_data)
         .context(format!("Failed to compute image ID for request {:x}", request.id))?;
 
-    anyhow::ensure!(
-        image_id == required_image_id,
-        "image ID does not match requirements; expect {}, got {}",
-        required_image_id,
-        image_id
-    );
+    if image_id != required_image_id {
+        // Make sure the mismatching program is not served from the cache again
+        if let Some(store) = &store {
+            store.remove(&image_key).await;
+            store.remove(&blob_store::uri_key(&request.imageUrl)).await;
+        }
+        anyhow::bail!(
+            "image ID does not match requirements; expect {}, got {}",
+            required_image_id,
+            image_id
+        );
+    }
+    if let Some(store) = &store {
+        store.put(&image_key, &image_data).await;
+    }
 
     tracing::debug!(
         "Uploading program for request {:x} with image ID {image_id_str} to prover",
@@ -392,20 +453,15 @@ pub async fn upload_image_uri(
     Ok(image_id_str)
 }
 
+/// Upload the input of a request to the prover, returning the input ID and the digest of the
+/// encoded input, along with the sources of any assumptions the request references
 pub async fn upload_input_uri(
     prover: &crate::provers::ProverObj,
     request: &crate::ProofRequest,
     config: &crate::config::ConfigLock,
-) -> Result<String> {
-    Ok(match request.input.inputType {
-        boundless_market::contracts::RequestInputType::Inline => prover
-            .upload_input(
-                boundless_market::input::GuestEnv::decode(&request.input.data)
-                    .with_context(|| "Failed to decode input")?
-                    .stdin,
-            )
-            .await
-            .context("Failed to upload input data")?,
+) -> Result<(String, Digest, Vec<AssumptionSource>)> {
+    let input_data = match request.input.inputType {
+        boundless_market::contracts::RequestInputType::Inline => request.input.data.to_vec(),
 
         boundless_market::contracts::RequestInputType::Url => {
             let input_uri_str =
@@ -426,20 +482,79 @@ pub async fn upload_input_uri(
                 .await
                 .context("URL handling failed")?;
 
-            let input_data = boundless_market::input::GuestEnv::decode(
-                &input_uri
-                    .fetch()
-                    .await
-                    .with_context(|| format!("Failed to fetch input URI: {input_uri_str}"))?,
-            )
-            .with_context(|| format!("Failed to decode input from URI: {input_uri_str}"))?
-            .stdin;
-
-            prover.upload_input(input_data).await.context("Failed to upload input")?
+            input_uri
+                .fetch()
+                .await
+                .with_context(|| format!("Failed to fetch input URI: {input_uri_str}"))?
         }
         //???
         _ => anyhow::bail!("Invalid input type: {:?}", request.input.inputType),
-    })
+    };
+
+    let input_digest = *ShaImpl::hash_bytes(&input_data);
+    let env = GuestEnv::decode(&input_data).with_context(|| "Failed to decode input")?;
+
+    let input_id = prover.upload_input(env.stdin).await.context("Failed to upload input")?;
+    Ok((input_id, input_digest, env.assumptions))
+}
+
+/// Fetch the receipts for the assumptions of a request and upload them to the prover, returning
+/// the IDs to pass as assumptions to preflight and proving
+///
+/// Returns `None` if an assumption references a fulfillment with an aggregated proof, as the zkVM
+/// cannot resolve assumptions with set inclusion receipts.
+pub async fn upload_assumptions<P: Provider>(
+    prover: &crate::provers::ProverObj,
+    market: &BoundlessMarketService<P>,
+    request: &crate::ProofRequest,
+    assumptions: &[AssumptionSource],
+    config: &crate::config::ConfigLock,
+) -> Result<Option<Vec<String>>> {
+    let mut assumption_ids = Vec::with_capacity(assumptions.len());
+    for assumption in assumptions {
+        let receipt_data = match assumption {
+            AssumptionSource::Fulfillment { request_id, image_id } => {
+                tracing::debug!(
+                    "Fetching fulfillment of request {request_id:x} as assumption for request {:x}",
+                    request.id
+                );
+                let (journal, seal) =
+                    market.get_request_fulfillment(*request_id).await.with_context(|| {
+                        format!("Failed to get fulfillment of request {request_id:x}")
+                    })?;
+                let receipt = decode_seal(seal, Digest::from(image_id.0), journal)
+                    .with_context(|| format!("Failed to decode seal of request {request_id:x}"))?;
+                match receipt {
+                    ContractReceipt::Base(receipt) => bincode::serialize(&receipt)
+                        .context("Failed to serialize assumption receipt")?,
+                    ContractReceipt::SetInclusion(_) => {
+                        tracing::debug!(
+                            "Fulfillment of request {request_id:x} is aggregated and cannot be used as an assumption"
+                        );
+                        return Ok(None);
+                    }
+                }
+            }
+            AssumptionSource::Url(url) => {
+                tracing::debug!("Fetching assumption for request {:x} from URI {url}", request.id);
+                let handler =
+                    create_uri_handler(url, config, false).await.context("URL handling failed")?;
+                handler
+                    .fetch()
+                    .await
+                    .with_context(|| format!("Failed to fetch assumption URI: {url}"))?
+            }
+            _ => anyhow::bail!("Unsupported assumption source: {assumption:?}"),
+        };
+
+        let assumption_id = prover
+            .upload_receipt(receipt_data)
+            .await
+            .context("Failed to upload assumption receipt")?;
+        assumption_ids.push(assumption_id);
+    }
+
+    Ok(Some(assumption_ids))
 }
 
 #[cfg(test)]
@@ -463,7 +578,7 @@ mod tests {
         });
 
         let url = url::Url::parse(&server.url("/image")).unwrap();
-        let handler = HttpHandler::new(url, 1024, None, None).await.unwrap();
+        let handler = HttpHandler::new(url, 1024, None).await.unwrap();
 
         let data = handler.fetch().await.unwrap();
         assert_eq!(data, resp_data);
@@ -493,7 +608,7 @@ mod tests {
         });
 
         let url = url::Url::parse(&server.url("/image")).unwrap();
-        let handler = HttpHandler::new(url, 1024, None, Some(RETRIES)).await.unwrap();
+        let handler = HttpHandler::new(url, 1024, Some(RETRIES)).await.unwrap();
 
         handler.fetch().await.unwrap();
         success_mock.assert();
@@ -510,13 +625,62 @@ mod tests {
         });
 
         let url = url::Url::parse(&server.url("/image")).unwrap();
-        let handler = HttpHandler::new(url, 1, None, None).await.unwrap();
+        let handler = HttpHandler::new(url, 1, None).await.unwrap();
 
         let result = handler.fetch().await;
         get_mock.assert();
         assert!(matches!(result, Err(StorageErr::SizeLimitExceeded(_))));
     }
 
+    #[tokio::test]
+    #[traced_test]
+    async fn cached_fetch() {
+        let server = MockServer::start();
+        let resp_data = vec![0x41, 0x41, 0x41, 0x41];
+        let get_mock = server.mock(|when, then| {
+            when.method(GET).path("/input");
+            then.status(200).body(&resp_data);
+        });
+
+        let cache_dir = tempfile::tempdir().unwrap();
+        let url = url::Url::parse(&server.url("/input")).unwrap();
+        let handler = CachedHandler {
+            uri: url.to_string(),
+            inner: Arc::new(HttpHandler::new(url, 1024, None).await.unwrap()),
+            store: BlobStore::new(cache_dir.path(), 1024),
+            max_size: 1024,
+        };
+
+        // The second fetch is served from the cache
+        assert_eq!(handler.fetch().await.unwrap(), resp_data);
+        assert_eq!(handler.fetch().await.unwrap(), resp_data);
+        get_mock.assert_hits(1);
+
+        // The size limit applies to cached contents
+        let handler = CachedHandler { max_size: 1, ..handler };
+        assert!(matches!(handler.fetch().await, Err(StorageErr::SizeLimitExceeded(_))));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn http_not_cached() {
+        let server = MockServer::start();
+        let get_mock = server.mock(|when, then| {
+            when.method(GET).path("/input");
+            then.status(200).body([0x41, 0x41, 0x41, 0x41]);
+        });
+
+        let cache_dir = tempfile::tempdir().unwrap();
+        let config = ConfigLock::default();
+        config.load_write().unwrap().market.cache_dir = Some(cache_dir.path().into());
+
+        // The contents of HTTP URLs may change, so each fetch goes to the server
+        let handler = create_uri_handler(&server.url("/input"), &config, false).await.unwrap();
+        handler.fetch().await.unwrap();
+        handler.fetch().await.unwrap();
+        get_mock.assert_hits(2);
+    }
+
     // NOTE: These are dummy values, they don't need to be real AWS keys but their presence allows
     // the default provider chain to "succeed" initially.
     const DUMMY_AWS_CREDENTIALS: [(&str, Option<&str>); 6] = [
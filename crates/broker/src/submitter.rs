@@ -12,22 +12,30 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
 
 use alloy::{
     network::Ethereum,
     primitives::{utils::format_ether, Address, B256, U256},
     providers::{Provider, WalletProvider},
-    sol_types::{SolStruct, SolValue},
+    rpc::types::TransactionReceipt,
+    sol_types::{SolEvent, SolInterface, SolStruct, SolValue},
 };
 use anyhow::{anyhow, Context, Result};
 use boundless_market::{
     contracts::{
         boundless_market::{BoundlessMarketService, FulfillmentTx, MarketError, UnlockedRequest},
         encode_seal, AssessorJournal, AssessorReceipt, Fulfillment,
+        IBoundlessMarket::{self, IBoundlessMarketErrors},
+        TxnErr,
     },
     selector::is_groth16_selector,
 };
+use chrono::Utc;
 use risc0_aggregation::{SetInclusionReceipt, SetInclusionReceiptVerifierParameters};
 use risc0_ethereum_contracts::set_verifier::SetVerifierService;
 use risc0_zkvm::{
@@ -36,12 +44,18 @@ use risc0_zkvm::{
 };
 
 use crate::{
+    admin::BrokerControl,
+    aggregator,
     config::ConfigLock,
     db::DbObj,
-    impl_coded_debug, now_timestamp,
+    impl_coded_debug,
+    ledger::{self, LedgerEntry, LedgerKind},
+    metrics, now_timestamp,
     provers::ProverObj,
+    shadow::{self, ShadowAction, ShadowDecision},
     task::{RetryRes, RetryTask, SupervisorErr},
-    Batch, FulfillmentType, Order,
+    tx_manager::{TxManager, TxPolicy},
+    Batch, FulfillmentType, Order, ProofRequest,
 };
 use thiserror::Error;
 
@@ -69,6 +83,9 @@ pub enum SubmitterErr {
     #[error("{code} Market error: {0}", code = self.code())]
     MarketError(#[from] MarketError),
 
+    #[error("{code} All orders of the batch were dropped: {0:?}", code = self.code())]
+    AllOrdersDropped(Vec<String>),
+
     #[error("{code} Unexpected error: {0:?}", code = self.code())]
     UnexpectedErr(#[from] anyhow::Error),
 }
@@ -85,6 +102,7 @@ impl CodedError for SubmitterErr {
             SubmitterErr::BatchSubmissionFailed(_) => "[B-SUB-004]",
             SubmitterErr::BatchSubmissionFailedTimeouts(_) => "[B-SUB-003]",
             SubmitterErr::TxnConfirmationError(_) => "[B-SUB-006]",
+            SubmitterErr::AllOrdersDropped(_) => "[B-SUB-008]",
         }
     }
 }
@@ -97,8 +115,12 @@ pub struct Submitter<P> {
     set_verifier: SetVerifierService<Arc<P>>,
     set_verifier_addr: Address,
     set_builder_img_id: Digest,
+    assessor_img_id: Digest,
     prover_address: Address,
+    chain_id: u64,
     config: ConfigLock,
+    control: BrokerControl,
+    tx_manager: Arc<TxManager<P>>,
 }
 
 impl<P> Submitter<P>
@@ -113,7 +135,11 @@ where
         provider: Arc<P>,
         set_verifier_addr: Address,
         market_addr: Address,
+        chain_id: u64,
         set_builder_img_id: Digest,
+        assessor_img_id: Digest,
+        control: BrokerControl,
+        tx_manager: Arc<TxManager<P>>,
     ) -> Result<Self> {
         let txn_timeout_opt = {
             let config = config.lock_all().context("Failed to read config")?;
@@ -149,8 +175,12 @@ where
             set_verifier,
             set_verifier_addr,
             set_builder_img_id,
+            assessor_img_id,
             prover_address,
+            chain_id,
             config,
+            control,
+            tx_manager,
         })
     }
 
@@ -254,6 +284,9 @@ where
         }
         let mut order_prices: HashMap<&str, OrderPrice> = HashMap::new();
         let mut fulfillment_to_order_id: HashMap<U256, &str> = HashMap::new();
+        let mut fulfillment_types: HashMap<&str, FulfillmentType> = HashMap::new();
+        let mut order_requests: HashMap<&str, ProofRequest> = HashMap::new();
+        let mut priceable_requests: HashMap<U256, UnlockedRequest> = HashMap::new();
 
         for order_id in batch.orders.iter() {
             tracing::info!("Submitting order {order_id}");
@@ -271,14 +304,35 @@ where
                         "Failed to get order from DB for submission, order NOT finalized",
                     )?;
 
+                let mut price = lock_price;
                 let mut stake_reward = U256::ZERO;
-                if fulfillment_type == FulfillmentType::FulfillAfterLockExpire {
-                    requests_to_price
-                        .push(UnlockedRequest::new(order_request.clone(), client_sig.clone()));
-                    stake_reward = order_request.offer.stake_reward_if_locked_and_not_fulfilled();
+                match fulfillment_type {
+                    FulfillmentType::LockAndFulfill => {}
+                    FulfillmentType::FulfillAfterLockExpire => {
+                        requests_to_price
+                            .push(UnlockedRequest::new(order_request.clone(), client_sig.clone()));
+                        stake_reward =
+                            order_request.offer.stake_reward_if_locked_and_not_fulfilled();
+                    }
+                    FulfillmentType::FulfillWithoutLocking => {
+                        // The request is priced in the fulfillment transaction, at no less than
+                        // its current price.
+                        requests_to_price
+                            .push(UnlockedRequest::new(order_request.clone(), client_sig.clone()));
+                        price = order_request
+                            .offer
+                            .price_at(now)
+                            .context("Failed to get current price of unlocked request")?;
+                    }
                 }
 
-                order_prices.insert(order_id, OrderPrice { price: lock_price, stake_reward });
+                order_prices.insert(order_id, OrderPrice { price, stake_reward });
+                fulfillment_types.insert(order_id, fulfillment_type);
+                order_requests.insert(order_id, order_request.clone());
+                priceable_requests.insert(
+                    order_request.id,
+                    UnlockedRequest::new(order_request.clone(), client_sig.clone()),
+                );
 
                 let order_journal = self
                     .prover
@@ -379,6 +433,33 @@ where
             callbacks: assessor_journal.callbacks,
         };
 
+        if self.control.is_shadow() {
+            // Everything up to the transactions has been built, so mark the orders as complete
+            // without submitting the merkle root or the fulfillment.
+            tracing::info!(
+                "Shadow mode: would submit batch {batch_id} with {} fulfillments",
+                fulfillments.len()
+            );
+            for fulfillment in fulfillments.iter() {
+                let order_id = fulfillment_to_order_id.get(&fulfillment.id).unwrap();
+                if let Err(db_err) = self.db.set_order_complete(order_id).await {
+                    tracing::error!(
+                        "Failed to set order complete during shadow submission: {:x} {db_err:?}",
+                        fulfillment.id
+                    );
+                    continue;
+                }
+                let decision = ShadowDecision::for_request(
+                    order_id.to_string(),
+                    fulfillment.id,
+                    fulfillment_types[order_id],
+                    ShadowAction::Fulfilled,
+                );
+                shadow::record(&self.db, decision).await;
+            }
+            return Ok(());
+        }
+
         let (single_txn_fulfill, withdraw) = {
             let config = self.config.lock_all().context("Failed to read config")?;
             (config.batcher.single_txn_fulfill, config.batcher.withdraw)
@@ -433,13 +514,52 @@ where
             }
         };
 
-        if let Err(err) = self.market.fulfill(fulfillment_tx).await {
-            let order_ids: Vec<&str> =
-                fulfillments.iter().map(|f| *fulfillment_to_order_id.get(&f.id).unwrap()).collect();
-            tracing::warn!("Failed to fulfill batch for orders: {order_ids:?}");
-            self.handle_fulfillment_error(err, batch_id, &fulfillments, &order_ids).await?;
+        let reverted_orders = self
+            .simulate_fulfillment(&fulfillment_tx, &fulfillment_to_order_id, &priceable_requests)
+            .await;
+        if !reverted_orders.is_empty() {
+            fulfillment_tx = self
+                .drop_orders(batch_id, fulfillment_tx, &fulfillment_to_order_id, &reverted_orders)
+                .await?;
+            fulfillments = fulfillment_tx.fulfillments.clone();
         }
 
+        let (fulfill_gas, unpaid_requests, fulfilled_at) = match self
+            .fulfill(batch, fulfillment_tx)
+            .await
+        {
+            Ok(receipt) => {
+                let time_to_submit = (Utc::now() - batch.start_time).to_std().unwrap_or_default();
+                metrics::record_batch_submitted(
+                    fulfillments.len(),
+                    time_to_submit,
+                    receipt.gas_used,
+                );
+                // The gas of the fulfillment is apportioned evenly between the orders of the batch.
+                let fulfill_gas = ledger::gas_cost(&receipt)
+                    .checked_div(U256::from(fulfillments.len()))
+                    .unwrap_or_default();
+                // Whether a lock expired is decided by the market at the time of the block.
+                let fulfilled_at = match self.block_timestamp(&receipt).await {
+                    Ok(timestamp) => timestamp,
+                    Err(err) => {
+                        tracing::warn!("Failed to get the time of the fulfillment block: {err:?}");
+                        now_timestamp()
+                    }
+                };
+                (fulfill_gas, unpaid_requests(&receipt), fulfilled_at)
+            }
+            Err(err) => {
+                let order_ids: Vec<&str> = fulfillments
+                    .iter()
+                    .map(|f| *fulfillment_to_order_id.get(&f.id).unwrap())
+                    .collect();
+                tracing::warn!("Failed to fulfill batch for orders: {order_ids:?}");
+                self.handle_fulfillment_error(err, batch_id, &fulfillments, &order_ids).await?;
+                (U256::ZERO, HashSet::new(), now_timestamp())
+            }
+        };
+
         for fulfillment in fulfillments.iter() {
             let order_id = fulfillment_to_order_id.get(&fulfillment.id).unwrap();
             if let Err(db_err) = self.db.set_order_complete(order_id).await {
@@ -458,11 +578,218 @@ where
                 format_ether(order_price.price),
                 format_ether(order_price.stake_reward)
             );
+
+            let Some(request) = order_requests.get(order_id) else {
+                continue;
+            };
+            let entry = |kind, amount| {
+                LedgerEntry::new(order_id.to_string(), self.chain_id, request, kind, amount)
+            };
+            let mut entries = vec![entry(LedgerKind::FulfillGas, fulfill_gas)];
+            let lock_expired = request.lock_expires_at() < fulfilled_at;
+            let unpaid = unpaid_requests.contains(&fulfillment.id);
+            match fulfillment_types[order_id] {
+                FulfillmentType::LockAndFulfill if unpaid => {
+                    // The lock is not released without payment, so the stake is slashed once the
+                    // lock expires.
+                    tracing::warn!("Payment requirements failed for order {order_id}, not paid");
+                    entries.push(entry(LedgerKind::StakeSlashed, request.offer.lockStake));
+                }
+                _ if unpaid => {
+                    tracing::warn!("Payment requirements failed for order {order_id}, not paid");
+                }
+                FulfillmentType::LockAndFulfill if lock_expired => {
+                    // The stake is slashed, and the request was not priced in the fulfillment.
+                    tracing::warn!("Order {order_id} fulfilled after its lock expired, not paid");
+                    entries.push(entry(LedgerKind::StakeSlashed, request.offer.lockStake));
+                }
+                FulfillmentType::LockAndFulfill => {
+                    entries.push(entry(LedgerKind::Payment, order_price.price));
+                    entries.push(entry(LedgerKind::StakeReturned, request.offer.lockStake));
+                }
+                FulfillmentType::FulfillAfterLockExpire => {
+                    entries.push(entry(LedgerKind::Payment, order_price.price));
+                    entries.push(entry(LedgerKind::StakeReward, order_price.stake_reward));
+                }
+                FulfillmentType::FulfillWithoutLocking => {
+                    entries.push(entry(LedgerKind::Payment, order_price.price));
+                }
+            }
+            ledger::record(&self.db, entries).await;
         }
 
         Ok(())
     }
 
+    /// Send the fulfillment, through the transaction manager when enabled
+    async fn fulfill(
+        &self,
+        batch: &Batch,
+        fulfillment_tx: FulfillmentTx,
+    ) -> Result<TransactionReceipt, MarketError> {
+        if !self.tx_manager.enabled()? {
+            return self.market.fulfill_with_receipt(fulfillment_tx).await;
+        }
+
+        // The fees the batch earns cap the fee bumps, and once the earliest order of the batch
+        // expires the batch no longer earns them, so the stuck fulfillment is cancelled.
+        let policy = TxPolicy { max_cost: batch.fees, deadline: batch.deadline, priority_fee: 0 };
+        let tx = self.market.fulfillment_transaction(fulfillment_tx);
+        let receipt = self.tx_manager.send(tx, policy).await?;
+        if !receipt.status() {
+            return Err(MarketError::Error(anyhow!(
+                "Fulfillment transaction reverted: {}",
+                receipt.transaction_hash
+            )));
+        }
+
+        tracing::info!("Submitted fulfillment for batch: {}", receipt.transaction_hash);
+        Ok(receipt)
+    }
+
+    /// Timestamp of the block a transaction was included in
+    async fn block_timestamp(&self, receipt: &TransactionReceipt) -> Result<u64> {
+        let block_number =
+            receipt.block_number.context("Transaction receipt missing block number")?;
+        let block = self
+            .market
+            .instance()
+            .provider()
+            .get_block_by_number(block_number.into())
+            .await
+            .context("Failed to get block of transaction")?
+            .with_context(|| format!("Block {block_number} not found"))?;
+        Ok(block.header.timestamp)
+    }
+
+    /// Simulate the fulfillment with `eth_call`, returning the orders whose fulfillment reverts
+    /// along with the reasons
+    ///
+    /// The market reverts on the first reverting fulfillment, and the assessor receipt commits to
+    /// all of them, so a reverting fulfillment cannot be left out of the next simulation. It is
+    /// priced instead, which lets it through without payment, so that the simulation reaches the
+    /// fulfillments after it.
+    async fn simulate_fulfillment(
+        &self,
+        fulfillment_tx: &FulfillmentTx,
+        fulfillment_to_order_id: &HashMap<U256, &str>,
+        priceable_requests: &HashMap<U256, UnlockedRequest>,
+    ) -> Vec<(String, &'static str)> {
+        let mut simulated_tx = fulfillment_tx.clone();
+        let mut reverted_orders = vec![];
+        loop {
+            let err = match self.market.simulate_fulfill(simulated_tx.clone()).await {
+                Ok(()) => break,
+                Err(err) => err,
+            };
+
+            let reverted = match &err {
+                MarketError::TxnError(TxnErr::BoundlessMarketErr(market_err)) => {
+                    reverted_request(market_err).and_then(|(request_id, reason)| {
+                        let order_id = fulfillment_to_order_id.get(&request_id)?;
+                        Some((request_id, order_id.to_string(), reason))
+                    })
+                }
+                _ => None,
+            };
+            let Some((request_id, order_id, reason)) = reverted else {
+                // Reverts not attributed to a single request are left to the fulfillment itself.
+                tracing::warn!("Fulfillment simulation failed, trying to submit anyway: {err:?}");
+                break;
+            };
+            if reverted_orders.iter().any(|(id, _)| *id == order_id) {
+                break;
+            }
+            reverted_orders.push((order_id, reason));
+
+            // A request that reverts even though it is priced hides the fulfillments after it.
+            if simulated_tx.unlocked_requests.iter().any(|req| req.request.id == request_id) {
+                break;
+            }
+            let Some(request) = priceable_requests.get(&request_id) else {
+                break;
+            };
+            simulated_tx.unlocked_requests.push(request.clone());
+        }
+        reverted_orders
+    }
+
+    /// Fail the orders whose fulfillment reverts, and rebuild the fulfillment without them
+    ///
+    /// The assessor receipt commits to every fulfillment in the batch, so the assessor is proven
+    /// again over the remaining ones. The new assessor claim is not in the merkle root of the
+    /// batch, so its seal is submitted directly rather than through set inclusion. The claims of
+    /// the remaining orders are still in the root, so their inclusion paths are kept.
+    async fn drop_orders(
+        &self,
+        batch_id: usize,
+        mut fulfillment_tx: FulfillmentTx,
+        fulfillment_to_order_id: &HashMap<U256, &str>,
+        dropped_orders: &[(String, &'static str)],
+    ) -> Result<FulfillmentTx, SubmitterErr> {
+        for (order_id, reason) in dropped_orders {
+            tracing::warn!("[B-SUB-007] Order {order_id} dropped from batch {batch_id}: {reason}");
+            if let Err(db_err) = self.db.set_order_failure(order_id, reason).await {
+                tracing::error!(
+                    "Failed to set order failure during proof submission: {order_id} {db_err:?}"
+                );
+            }
+        }
+
+        let is_dropped = |request_id: &U256| {
+            let order_id = fulfillment_to_order_id.get(request_id);
+            dropped_orders.iter().any(|(id, _)| order_id == Some(&id.as_str()))
+        };
+        fulfillment_tx.fulfillments.retain(|fulfillment| !is_dropped(&fulfillment.id));
+        fulfillment_tx.unlocked_requests.retain(|unlocked| !is_dropped(&unlocked.request.id));
+        if fulfillment_tx.fulfillments.is_empty() {
+            return Err(SubmitterErr::AllOrdersDropped(
+                dropped_orders.iter().map(|(order_id, _)| order_id.clone()).collect(),
+            ));
+        }
+
+        let order_ids: Vec<String> = fulfillment_tx
+            .fulfillments
+            .iter()
+            .map(|fulfillment| fulfillment_to_order_id[&fulfillment.id].to_string())
+            .collect();
+        tracing::info!("Proving the assessor of batch {batch_id} again for orders {order_ids:?}");
+        let assessor_proof_id = aggregator::prove_assessor(
+            &self.db,
+            &self.prover,
+            self.assessor_img_id,
+            *self.market.instance().address(),
+            self.chain_id,
+            self.prover_address,
+            &order_ids,
+        )
+        .await?;
+        let assessor_journal = self
+            .prover
+            .get_journal(&assessor_proof_id)
+            .await
+            .context("Failed to get assessor journal")?
+            .context("Assessor journal missing")?;
+        let assessor_journal =
+            AssessorJournal::abi_decode(&assessor_journal).with_context(|| {
+                format!("Failed to decode assessor journal for {assessor_proof_id}")
+            })?;
+        let assessor_g16_proof_id = self
+            .prover
+            .compress(&assessor_proof_id)
+            .await
+            .context("Failed to compress assessor proof")?;
+        let assessor_seal = self.fetch_encode_g16(&assessor_g16_proof_id).await?;
+
+        fulfillment_tx.assessor_receipt = AssessorReceipt {
+            seal: assessor_seal.into(),
+            selectors: assessor_journal.selectors,
+            prover: self.prover_address,
+            callbacks: assessor_journal.callbacks,
+        };
+        Ok(fulfillment_tx)
+    }
+
     async fn handle_expired_requests_error(
         &self,
         batch_id: usize,
@@ -510,8 +837,11 @@ where
     }
 
     pub async fn process_next_batch(&self) -> Result<(), SubmitterErr> {
-        let batch_res =
-            self.db.get_complete_batch().await.context("Failed to get complete batch")?;
+        let batch_res = self
+            .db
+            .get_complete_batch(self.chain_id)
+            .await
+            .context("Failed to get complete batch")?;
 
         let Some((batch_id, batch)) = batch_res else {
             return Ok(());
@@ -538,6 +868,15 @@ where
                     );
                     return Ok(());
                 }
+                Err(err @ SubmitterErr::AllOrdersDropped(_)) => {
+                    // Retrying cannot submit any order of the batch.
+                    tracing::warn!("Batch {batch_id} not submitted: {err}");
+                    self.db
+                        .set_batch_failure(batch_id, format!("{err:?}"))
+                        .await
+                        .context("Failed to set batch failure")?;
+                    return Ok(());
+                }
                 Err(err) => {
                     tracing::warn!(
                         "Batch submission attempt {}/{} failed. Error: {err:?}",
@@ -562,6 +901,53 @@ where
     }
 }
 
+/// Get the requests of a fulfillment transaction whose payment requirements failed, for example as
+/// they were already fulfilled by another prover
+fn unpaid_requests(receipt: &TransactionReceipt) -> HashSet<U256> {
+    receipt
+        .inner
+        .logs()
+        .iter()
+        .filter(|log| {
+            log.topic0() == Some(&IBoundlessMarket::PaymentRequirementsFailed::SIGNATURE_HASH)
+        })
+        .filter_map(|log| log.log_decode::<IBoundlessMarket::PaymentRequirementsFailed>().ok())
+        .filter_map(|log| IBoundlessMarketErrors::abi_decode(&log.inner.data.error).ok())
+        .filter_map(|err| reverted_request(&err).map(|(request_id, _)| request_id))
+        .collect()
+}
+
+/// Get the request a market error was raised for, and the reason to record for it
+fn reverted_request(err: &IBoundlessMarketErrors) -> Option<(U256, &'static str)> {
+    match err {
+        IBoundlessMarketErrors::RequestIsNotLockedOrPriced(err) => {
+            Some((err.requestId, "Fulfillment reverted: request is not locked or priced"))
+        }
+        IBoundlessMarketErrors::RequestIsLocked(err) => {
+            Some((err.requestId, "Fulfillment reverted: request is locked"))
+        }
+        IBoundlessMarketErrors::RequestIsNotLocked(err) => {
+            Some((err.requestId, "Fulfillment reverted: request is not locked"))
+        }
+        IBoundlessMarketErrors::RequestIsFulfilled(err) => {
+            Some((err.requestId, "Fulfillment reverted: request is fulfilled"))
+        }
+        IBoundlessMarketErrors::RequestIsSlashed(err) => {
+            Some((err.requestId, "Fulfillment reverted: request is slashed"))
+        }
+        IBoundlessMarketErrors::RequestLockIsExpired(err) => {
+            Some((err.requestId, "Fulfillment reverted: request lock is expired"))
+        }
+        IBoundlessMarketErrors::RequestIsExpired(err) => {
+            Some((err.requestId, "Fulfillment reverted: request is expired"))
+        }
+        IBoundlessMarketErrors::RequestIsNotExpired(err) => {
+            Some((err.requestId, "Fulfillment reverted: request is not expired"))
+        }
+        _ => None,
+    }
+}
+
 impl<P> RetryTask for Submitter<P>
 where
     P: Provider<Ethereum> + WalletProvider + 'static + Clone,
@@ -614,7 +1000,7 @@ mod tests {
     use alloy::{
         network::EthereumWallet,
         node_bindings::{Anvil, AnvilInstance},
-        primitives::U256,
+        primitives::{utils::parse_ether, U256},
         providers::ProviderBuilder,
         signers::local::PrivateKeySigner,
     };
@@ -627,17 +1013,29 @@ mod tests {
         input::GuestEnv,
     };
     use boundless_market_test_utils::{
-        deploy_boundless_market, deploy_hit_points, deploy_mock_verifier, deploy_set_verifier,
-        ASSESSOR_GUEST_ELF, ASSESSOR_GUEST_ID, ASSESSOR_GUEST_PATH, ECHO_ELF, ECHO_ID,
-        SET_BUILDER_ELF, SET_BUILDER_ID, SET_BUILDER_PATH,
+        add_verifier_route, deploy_boundless_market, deploy_hit_points, deploy_mock_verifier,
+        deploy_set_verifier, deploy_verifier_router, ASSESSOR_GUEST_ELF, ASSESSOR_GUEST_ID,
+        ASSESSOR_GUEST_PATH, ECHO_ELF, ECHO_ID, SET_BUILDER_ELF, SET_BUILDER_ID, SET_BUILDER_PATH,
     };
-    use chrono::Utc;
+    use chrono::DateTime;
     use risc0_aggregation::GuestState;
     use risc0_zkvm::sha::Digest;
     use tracing_test::traced_test;
 
     async fn build_submitter_and_batch(
         config: ConfigLock,
+        fulfillment_type: FulfillmentType,
+    ) -> (AnvilInstance, Submitter<impl Provider + WalletProvider + Clone + 'static>, DbObj, usize)
+    {
+        let lock = fulfillment_type == FulfillmentType::LockAndFulfill;
+        build_submitter_and_batch_with_locks(config, fulfillment_type, &[lock]).await
+    }
+
+    /// Build a batch with an order for each entry of `locks`, locking the order on chain if set
+    async fn build_submitter_and_batch_with_locks(
+        config: ConfigLock,
+        fulfillment_type: FulfillmentType,
+        locks: &[bool],
     ) -> (AnvilInstance, Submitter<impl Provider + WalletProvider + Clone + 'static>, DbObj, usize)
     {
         let anvil = Anvil::new().spawn();
@@ -663,7 +1061,11 @@ mod tests {
                 .unwrap(),
         );
 
+        // Seals are routed by their selector, as assessor seals are submitted directly when the
+        // assessor is proven again.
+        let verifier_router = deploy_verifier_router(provider.clone(), prover_addr).await.unwrap();
         let verifier = deploy_mock_verifier(provider.clone()).await.unwrap();
+        add_verifier_route(provider.clone(), verifier_router, [0xFFu8; 4], verifier).await.unwrap();
         let set_verifier = deploy_set_verifier(
             provider.clone(),
             verifier,
@@ -672,11 +1074,22 @@ mod tests {
         )
         .await
         .unwrap();
+        let set_verifier_selector =
+            SetInclusionReceiptVerifierParameters { image_id: Digest::from(SET_BUILDER_ID) }
+                .digest();
+        add_verifier_route(
+            provider.clone(),
+            verifier_router,
+            set_verifier_selector.as_bytes()[..4].try_into().unwrap(),
+            set_verifier,
+        )
+        .await
+        .unwrap();
         let hit_points = deploy_hit_points(prover_addr, provider.clone()).await.unwrap();
         let market_address = deploy_boundless_market(
             prover_addr,
             provider.clone(),
-            set_verifier,
+            verifier_router,
             hit_points,
             Digest::from(ASSESSOR_GUEST_ID),
             format!("file://{ASSESSOR_GUEST_PATH}"),
@@ -698,10 +1111,6 @@ mod tests {
         let echo_id = Digest::from(ECHO_ID);
         let echo_id_str = echo_id.to_string();
         prover.upload_image(&echo_id_str, ECHO_ELF.to_vec()).await.unwrap();
-        let input_id = prover
-            .upload_input(encode_input(&vec![0x41, 0x41, 0x41, 0x41]).unwrap())
-            .await
-            .unwrap();
 
         let set_builder_id = Digest::from(SET_BUILDER_ID);
         let set_builder_id_str = set_builder_id.to_string();
@@ -711,79 +1120,120 @@ mod tests {
         let assessor_id_str = assessor_id.to_string();
         prover.upload_image(&assessor_id_str, ASSESSOR_GUEST_ELF.to_vec()).await.unwrap();
 
-        let echo_proof =
-            prover.prove_and_monitor_stark(&echo_id_str, &input_id, vec![]).await.unwrap();
-        let echo_receipt = prover.get_receipt(&echo_proof.id).await.unwrap().unwrap();
-
-        let order_request = ProofRequest::new(
-            RequestId::new(customer_addr, market_customer.index_from_nonce().await.unwrap()),
-            Requirements::new(
-                echo_id,
-                Predicate { predicateType: PredicateType::PrefixMatch, data: Default::default() },
-            ),
-            "http://risczero.com/image",
-            RequestInput { inputType: RequestInputType::Inline, data: Default::default() },
-            Offer {
-                minPrice: U256::from(2),
-                maxPrice: U256::from(4),
-                biddingStart: now_timestamp(),
-                timeout: 100,
-                lockTimeout: 100,
-                rampUpPeriod: 1,
-                lockStake: U256::from(10),
-            },
-        );
-
         let chain_id = provider.get_chain_id().await.unwrap();
-        let client_sig = order_request
-            .sign_request(&customer_signer, market_address, chain_id)
-            .await
-            .unwrap()
-            .as_bytes();
+        let mut orders = vec![];
+        let mut echo_receipts = vec![];
+        for index in 0..locks.len() {
+            // Distinct inputs, so that each order has its own claim in the set
+            let input_id = prover
+                .upload_input(encode_input(&vec![0x41 + index as u8; 4]).unwrap())
+                .await
+                .unwrap();
+            let echo_proof =
+                prover.prove_and_monitor_stark(&echo_id_str, &input_id, vec![]).await.unwrap();
+            echo_receipts.push(prover.get_receipt(&echo_proof.id).await.unwrap().unwrap());
+
+            let order_request = ProofRequest::new(
+                RequestId::new(
+                    customer_addr,
+                    market_customer.index_from_nonce().await.unwrap() + index as u32,
+                ),
+                Requirements::new(
+                    echo_id,
+                    Predicate {
+                        predicateType: PredicateType::PrefixMatch,
+                        data: Default::default(),
+                    },
+                ),
+                "http://risczero.com/image",
+                RequestInput { inputType: RequestInputType::Inline, data: Default::default() },
+                Offer {
+                    minPrice: U256::from(2),
+                    maxPrice: U256::from(4),
+                    biddingStart: now_timestamp(),
+                    timeout: 100,
+                    lockTimeout: 100,
+                    rampUpPeriod: 1,
+                    lockStake: U256::from(10),
+                },
+            );
+            let client_sig = order_request
+                .sign_request(&customer_signer, market_address, chain_id)
+                .await
+                .unwrap()
+                .as_bytes();
+
+            orders.push(Order {
+                status: OrderStatus::PendingSubmission,
+                updated_at: Utc::now(),
+                target_timestamp: Some(0),
+                request: order_request,
+                image_id: Some(echo_id_str.clone()),
+                input_id: Some(input_id),
+                assumption_ids: vec![],
+                proof_id: Some(echo_proof.id),
+                compressed_proof_id: None,
+                expire_timestamp: Some(now_timestamp() + 100),
+                client_sig: client_sig.into(),
+                lock_price: Some(U256::ZERO),
+                fulfillment_type,
+                error_msg: None,
+                skip_reason: None,
+                boundless_market_address: market_address,
+                chain_id,
+                total_cycles: None,
+                proving_started_at: None,
+            });
+        }
 
         let assessor_input = AssessorInput {
             domain: boundless_market::contracts::eip712_domain(market_address, chain_id),
-            fills: vec![Fulfillment {
-                request: order_request.clone(),
-                signature: client_sig.into(),
-                journal: echo_receipt.journal.bytes.clone(),
-            }],
+            fills: orders
+                .iter()
+                .zip(echo_receipts.iter())
+                .map(|(order, receipt)| Fulfillment {
+                    request: order.request.clone(),
+                    signature: order.client_sig.to_vec(),
+                    journal: receipt.journal.bytes.clone(),
+                })
+                .collect(),
             prover_address: prover_addr,
         };
         let assessor_stdin = GuestEnv::builder().write_frame(&assessor_input.encode()).stdin;
 
         let assessor_input = prover.upload_input(assessor_stdin).await.unwrap();
 
+        let echo_proof_ids: Vec<String> =
+            orders.iter().map(|order| order.proof_id.clone().unwrap()).collect();
         let assessor_proof = prover
-            .prove_and_monitor_stark(&assessor_id_str, &assessor_input, vec![echo_proof.id.clone()])
+            .prove_and_monitor_stark(&assessor_id_str, &assessor_input, echo_proof_ids.clone())
             .await
             .unwrap();
         let assessor_receipt = prover.get_receipt(&assessor_proof.id).await.unwrap().unwrap();
 
+        let mut claims: Vec<ReceiptClaim> =
+            echo_receipts.iter().map(|receipt| receipt.claim().unwrap().value().unwrap()).collect();
+        claims.push(assessor_receipt.claim().unwrap().value().unwrap());
+        let claim_digests: Vec<Digest> = claims.iter().map(|claim| claim.digest()).collect();
+
         // Build and finalize the aggregation in one execution.
         let set_builder_input = prover
             .upload_input(
                 encode_input(
-                    &GuestState::initial(set_builder_id)
-                        .into_input(
-                            vec![
-                                echo_receipt.claim().unwrap().value().unwrap(),
-                                assessor_receipt.claim().unwrap().value().unwrap(),
-                            ],
-                            true,
-                        )
-                        .unwrap(),
+                    &GuestState::initial(set_builder_id).into_input(claims, true).unwrap(),
                 )
                 .unwrap(),
             )
             .await
             .unwrap();
 
+        let mut set_builder_assumptions = echo_proof_ids;
+        set_builder_assumptions.push(assessor_proof.id.clone());
         let aggregation_proof = prover
             .prove_and_monitor_stark(
                 &set_builder_id_str,
                 &set_builder_input,
-                vec![echo_proof.id.clone(), assessor_proof.id.clone()],
+                set_builder_assumptions,
             )
             .await
             .unwrap();
@@ -794,57 +1244,43 @@ mod tests {
         assert!(batch_guest_state.mmr.is_finalized());
         assert_eq!(
             batch_guest_state.mmr.clone().finalized_root().unwrap(),
-            risc0_aggregation::merkle_root(&[
-                echo_receipt.claim().unwrap().digest(),
-                assessor_receipt.claim().unwrap().digest(),
-            ])
+            risc0_aggregation::merkle_root(&claim_digests)
         );
 
-        let order = Order {
-            status: OrderStatus::PendingSubmission,
-            updated_at: Utc::now(),
-            target_timestamp: Some(0),
-            request: order_request,
-            image_id: Some(echo_id_str.clone()),
-            input_id: Some(input_id.clone()),
-            proof_id: Some(echo_proof.id.clone()),
-            compressed_proof_id: None,
-            expire_timestamp: Some(now_timestamp() + 100),
-            client_sig: client_sig.into(),
-            lock_price: Some(U256::ZERO),
-            fulfillment_type: FulfillmentType::LockAndFulfill,
-            error_msg: None,
-            boundless_market_address: market_address,
-            chain_id,
-            total_cycles: None,
-            proving_started_at: None,
-        };
-        let order_id = order.id();
-        db.add_order(&order).await.unwrap();
+        for order in orders.iter() {
+            db.add_order(order).await.unwrap();
+        }
 
         let batch_id = 0;
         let batch = Batch {
             status: BatchStatus::Complete,
             assessor_proof_id: Some(assessor_proof.id),
-            orders: vec![order_id],
-            fees: U256::ZERO,
+            orders: orders.iter().map(|order| order.id()).collect(),
+            // Covers the gas of the fulfillment when sent through the transaction manager
+            fees: parse_ether("0.1").unwrap(),
             start_time: Utc::now(),
-            deadline: Some(order.request.offer.biddingStart + order.request.offer.timeout as u64),
+            deadline: Some(
+                orders[0].request.offer.biddingStart + orders[0].request.offer.timeout as u64,
+            ),
+            chain_id,
             error_msg: None,
             aggregation_state: Some(AggregationState {
                 guest_state: batch_guest_state,
                 proof_id: aggregation_proof.id,
                 groth16_proof_id: Some(batch_g16),
-                claim_digests: vec![
-                    echo_receipt.claim().unwrap().digest(),
-                    assessor_receipt.claim().unwrap().digest(),
-                ],
+                claim_digests,
             }),
         };
         db.add_batch(batch_id, batch).await.unwrap();
 
-        market.lock_request(&order.request, client_sig.to_vec(), None).await.unwrap();
+        for (order, lock) in orders.iter().zip(locks) {
+            if *lock {
+                market.lock_request(&order.request, order.client_sig.to_vec(), None).await.unwrap();
+            }
+        }
 
+        let tx_manager =
+            Arc::new(TxManager::new(provider.clone(), config.clone(), Default::default()));
         let submitter = Submitter::new(
             db.clone(),
             config,
@@ -852,7 +1288,11 @@ mod tests {
             provider.clone(),
             set_verifier,
             market_address,
+            chain_id,
             set_builder_id,
+            assessor_id,
+            BrokerControl::default(),
+            tx_manager,
         )
         .unwrap();
 
@@ -872,7 +1312,8 @@ mod tests {
     #[traced_test]
     async fn submit_batch() {
         let config = ConfigLock::default();
-        let (_anvil, submitter, db, batch_id) = build_submitter_and_batch(config).await;
+        let (_anvil, submitter, db, batch_id) =
+            build_submitter_and_batch(config, FulfillmentType::LockAndFulfill).await;
         process_next_batch(submitter, db, batch_id).await;
     }
 
@@ -881,15 +1322,99 @@ mod tests {
     async fn submit_batch_merged_txn() {
         let config = ConfigLock::default();
         config.load_write().as_mut().unwrap().batcher.single_txn_fulfill = true;
-        let (_anvil, submitter, db, batch_id) = build_submitter_and_batch(config).await;
+        let (_anvil, submitter, db, batch_id) =
+            build_submitter_and_batch(config, FulfillmentType::LockAndFulfill).await;
         process_next_batch(submitter, db, batch_id).await;
     }
 
+    #[tokio::test]
+    #[traced_test]
+    async fn submit_batch_tx_manager() {
+        let config = ConfigLock::default();
+        config.load_write().as_mut().unwrap().market.txn_bump_blocks = Some(1);
+        let (_anvil, submitter, db, batch_id) =
+            build_submitter_and_batch(config, FulfillmentType::LockAndFulfill).await;
+        let market = submitter.market.clone();
+        let batch = db.get_batch(batch_id).await.unwrap();
+        let order = db.get_order(&batch.orders[0]).await.unwrap().unwrap();
+
+        process_next_batch(submitter, db, batch_id).await;
+
+        assert!(market.is_fulfilled(order.request.id).await.unwrap());
+        assert!(logs_contain("Submitted fulfillment for batch"));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn submit_batch_recorded_in_ledger() {
+        let config = ConfigLock::default();
+        let (_anvil, submitter, db, batch_id) =
+            build_submitter_and_batch(config, FulfillmentType::LockAndFulfill).await;
+        let batch = db.get_batch(batch_id).await.unwrap();
+        let order = db.get_order(&batch.orders[0]).await.unwrap().unwrap();
+
+        process_next_batch(submitter, db.clone(), batch_id).await;
+
+        let to = Utc::now() + chrono::Duration::seconds(1);
+        let entries = db.get_ledger_entries(None, DateTime::UNIX_EPOCH, to).await.unwrap();
+        let kinds: Vec<_> = entries.iter().map(|entry| entry.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![LedgerKind::FulfillGas, LedgerKind::Payment, LedgerKind::StakeReturned]
+        );
+        assert!(entries.iter().all(|entry| entry.order_id == order.id()));
+        assert!(entries[0].amount > U256::ZERO);
+        assert_eq!(entries[1].amount, order.lock_price.unwrap());
+        assert_eq!(entries[2].amount, order.request.offer.lockStake);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn submit_batch_without_locking() {
+        let config = ConfigLock::default();
+        let (_anvil, submitter, db, batch_id) =
+            build_submitter_and_batch(config, FulfillmentType::FulfillWithoutLocking).await;
+        let market = submitter.market.clone();
+        let batch = db.get_batch(batch_id).await.unwrap();
+        let order = db.get_order(&batch.orders[0]).await.unwrap().unwrap();
+
+        process_next_batch(submitter, db.clone(), batch_id).await;
+
+        assert!(market.is_fulfilled(order.request.id).await.unwrap());
+        assert!(!market.is_locked(order.request.id).await.unwrap());
+        let order = db.get_order(&order.id()).await.unwrap().unwrap();
+        assert_eq!(order.status, OrderStatus::Done);
+        assert!(logs_contain("Completed order"));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn submit_batch_shadow() {
+        let config = ConfigLock::default();
+        let (_anvil, submitter, db, batch_id) =
+            build_submitter_and_batch(config, FulfillmentType::LockAndFulfill).await;
+        let submitter = Submitter { control: BrokerControl::new(true), ..submitter };
+        let market = submitter.market.clone();
+        let batch = db.get_batch(batch_id).await.unwrap();
+        let order = db.get_order(&batch.orders[0]).await.unwrap().unwrap();
+
+        process_next_batch(submitter, db.clone(), batch_id).await;
+
+        assert!(!market.is_fulfilled(order.request.id).await.unwrap());
+        let order = db.get_order(&order.id()).await.unwrap().unwrap();
+        assert_eq!(order.status, OrderStatus::Done);
+        let decisions = db.get_shadow_decisions(DateTime::UNIX_EPOCH, 10).await.unwrap();
+        assert_eq!(decisions.len(), 1);
+        assert_eq!(decisions[0].order_id, order.id());
+        assert_eq!(decisions[0].action, ShadowAction::Fulfilled);
+    }
+
     #[tokio::test]
     #[traced_test]
     async fn submit_batch_retry_max_attempts() {
         let config = ConfigLock::default();
-        let (anvil, submitter, _db, _batch_id) = build_submitter_and_batch(config).await;
+        let (anvil, submitter, _db, _batch_id) =
+            build_submitter_and_batch(config, FulfillmentType::LockAndFulfill).await;
 
         drop(anvil); // drop anvil to simluate an RPC fault
 
@@ -898,4 +1423,64 @@ mod tests {
         assert!(logs_contain("reached max submission attempts"));
         assert!(matches!(res, Err(SubmitterErr::BatchSubmissionFailed(_))));
     }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn submit_batch_drops_reverting_fulfillment() {
+        let config = ConfigLock::default();
+        // The order is recorded as locked but was never locked on chain, so its fulfillment
+        // reverts.
+        let (_anvil, submitter, db, batch_id) =
+            build_submitter_and_batch_with_locks(config, FulfillmentType::LockAndFulfill, &[false])
+                .await;
+        let market = submitter.market.clone();
+        let batch = db.get_batch(batch_id).await.unwrap();
+        let order = db.get_order(&batch.orders[0]).await.unwrap().unwrap();
+
+        submitter.process_next_batch().await.unwrap();
+
+        assert!(!market.is_fulfilled(order.request.id).await.unwrap());
+        let order = db.get_order(&order.id()).await.unwrap().unwrap();
+        assert_eq!(order.status, OrderStatus::Failed);
+        assert_eq!(
+            order.error_msg.as_deref(),
+            Some("Fulfillment reverted: request is not locked or priced")
+        );
+        let batch = db.get_batch(batch_id).await.unwrap();
+        assert_eq!(batch.status, BatchStatus::Failed);
+        assert!(logs_contain("[B-SUB-007]"));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn submit_batch_drops_one_reverting_fulfillment() {
+        let config = ConfigLock::default();
+        // Only the first order was locked on chain, so the fulfillment of the second reverts.
+        let (_anvil, submitter, db, batch_id) = build_submitter_and_batch_with_locks(
+            config,
+            FulfillmentType::LockAndFulfill,
+            &[true, false],
+        )
+        .await;
+        let market = submitter.market.clone();
+        let batch = db.get_batch(batch_id).await.unwrap();
+        let locked = db.get_order(&batch.orders[0]).await.unwrap().unwrap();
+        let unlocked = db.get_order(&batch.orders[1]).await.unwrap().unwrap();
+
+        process_next_batch(submitter, db.clone(), batch_id).await;
+
+        // The locked order is submitted with the assessor proven again, without aggregating again
+        assert!(market.is_fulfilled(locked.request.id).await.unwrap());
+        let locked = db.get_order(&locked.id()).await.unwrap().unwrap();
+        assert_eq!(locked.status, OrderStatus::Done);
+        assert!(logs_contain("Proving the assessor of batch"));
+
+        assert!(!market.is_fulfilled(unlocked.request.id).await.unwrap());
+        let unlocked = db.get_order(&unlocked.id()).await.unwrap().unwrap();
+        assert_eq!(unlocked.status, OrderStatus::Failed);
+        assert_eq!(
+            unlocked.error_msg.as_deref(),
+            Some("Fulfillment reverted: request is not locked or priced")
+        );
+    }
 }
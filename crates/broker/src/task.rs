@@ -19,7 +19,7 @@ use thiserror::Error;
 use tokio::task::JoinSet;
 use tokio_util::sync::CancellationToken;
 
-use crate::{config::ConfigLock, errors::CodedError};
+use crate::{config::ConfigLock, errors::CodedError, metrics};
 
 #[derive(Error, Debug)]
 pub enum SupervisorErr<E: CodedError> {
@@ -175,6 +175,7 @@ where
                                 retry_count + 1,
                             );
                             tracing::debug!("Waiting {:?} before retry", current_delay);
+                            metrics::record_supervisor_restart::<T>();
 
                             // Instead of sleeping here, wrap the task spawn with a delay
                             let task_clone = self.task.clone();
@@ -17,7 +17,7 @@ use std::{future::Future, path::PathBuf};
 use crate::{config::Config, now_timestamp, Args, Broker};
 use alloy::{
     node_bindings::Anvil,
-    primitives::{aliases::U96, utils, utils::parse_ether, Address, FixedBytes, U256},
+    primitives::{aliases::U96, utils, utils::parse_ether, Address, FixedBytes, B256, U256},
     providers::{Provider, WalletProvider},
     signers::local::PrivateKeySigner,
 };
@@ -26,14 +26,16 @@ use boundless_market::{
         hit_points::default_allowance, Callback, Offer, Predicate, PredicateType, ProofRequest,
         RequestId, RequestInput, Requirements,
     },
+    input::{AssumptionSource, GuestEnv},
     selector::{is_groth16_selector, ProofType},
     storage::{MockStorageProvider, StorageProvider},
     Deployment,
 };
 use boundless_market_test_utils::{
-    create_test_ctx, deploy_mock_callback, get_mock_callback_count, ASSESSOR_GUEST_PATH, ECHO_ELF,
-    ECHO_ID, SET_BUILDER_PATH,
+    create_test_ctx, deploy_mock_callback, get_mock_callback_count, ASSESSOR_GUEST_PATH,
+    COMPOSE_ELF, COMPOSE_ID, ECHO_ELF, ECHO_ID, SET_BUILDER_PATH,
 };
+use risc0_ethereum_contracts::receipt::{decode_seal, Receipt as ContractReceipt};
 use risc0_zkvm::{is_dev_mode, sha::Digest};
 use tempfile::NamedTempFile;
 use tokio::{task::JoinSet, time::Duration};
@@ -128,6 +130,10 @@ fn broker_args(
         rpc_retry_backoff: 200,
         rpc_retry_cu: 1000,
         log_json: false,
+        admin_addr: None,
+        admin_token: None,
+        metrics_addr: None,
+        shadow: false,
     }
 }
 
@@ -420,6 +426,138 @@ async fn e2e_with_selector() {
     .await;
 }
 
+#[tokio::test]
+#[traced_test]
+async fn e2e_with_assumptions() {
+    // Setup anvil
+    let anvil = Anvil::new().spawn();
+
+    // Setup signers / providers
+    let ctx = create_test_ctx(&anvil).await.unwrap();
+
+    // Deposit prover / customer balances
+    ctx.prover_market
+        .deposit_stake_with_permit(default_allowance(), &ctx.prover_signer)
+        .await
+        .unwrap();
+    ctx.customer_market.deposit(utils::parse_ether("0.5").unwrap()).await.unwrap();
+
+    // Start broker
+    let config = new_config(1).await;
+    let args = broker_args(
+        config.path().to_path_buf(),
+        ctx.deployment.clone(),
+        anvil.endpoint_url(),
+        ctx.prover_signer,
+    );
+    let broker = Broker::new(args, ctx.prover_provider).await.unwrap();
+
+    // Provide URLs for ECHO and COMPOSE programs
+    let storage = MockStorageProvider::start();
+    let image_url = storage.upload_program(ECHO_ELF).await.unwrap();
+    let compose_url = storage.upload_program(COMPOSE_ELF).await.unwrap();
+
+    // The first request requires an unaggregated proof, so its receipt can be used as an assumption
+    let first_request = generate_request(
+        ctx.customer_market.index_from_nonce().await.unwrap(),
+        &ctx.customer_signer.address(),
+        ProofType::Groth16,
+        image_url.clone(),
+        None,
+        None,
+    );
+
+    run_with_broker(broker, async move {
+        ctx.customer_market.submit_request(&first_request, &ctx.customer_signer).await.unwrap();
+        let (journal, seal) = ctx
+            .customer_market
+            .wait_for_request_fulfillment(
+                U256::from(first_request.id),
+                Duration::from_secs(1),
+                first_request.expires_at(),
+            )
+            .await
+            .unwrap();
+
+        // Reference the first fulfillment both onchain and as a receipt hosted at a URL
+        let ContractReceipt::Base(receipt) = decode_seal(seal, ECHO_ID, journal.clone()).unwrap()
+        else {
+            panic!("expected an unaggregated receipt");
+        };
+        let receipt_url =
+            storage.upload_input(&bincode::serialize(&receipt).unwrap()).await.unwrap();
+
+        // The COMPOSE program verifies the ECHO journal, which requires the assumption to be
+        // resolved to the receipt of the first request
+        let mut request = generate_request(
+            ctx.customer_market.index_from_nonce().await.unwrap(),
+            &ctx.customer_signer.address(),
+            ProofType::Any,
+            compose_url.clone(),
+            None,
+            None,
+        );
+        request.requirements.imageId = <[u8; 32]>::from(Digest::from(COMPOSE_ID)).into();
+        request.input = GuestEnv::builder()
+            .write(&(Digest::from(ECHO_ID), journal.to_vec()))
+            .unwrap()
+            .with_assumption(AssumptionSource::Fulfillment {
+                request_id: U256::from(first_request.id),
+                image_id: B256::from(<[u8; 32]>::from(Digest::from(ECHO_ID))),
+            })
+            .with_assumption(AssumptionSource::Url(receipt_url.to_string()))
+            .build_inline()
+            .unwrap();
+
+        ctx.customer_market.submit_request(&request, &ctx.customer_signer).await.unwrap();
+        ctx.customer_market
+            .wait_for_request_fulfillment(
+                U256::from(request.id),
+                Duration::from_secs(1),
+                request.expires_at(),
+            )
+            .await
+            .unwrap();
+
+        assert!(logs_contain("Uploaded 2 assumption receipts for order"));
+
+        // The composed request was fulfilled with an aggregated proof, which cannot be used as an
+        // assumption, so a request relying on it is skipped
+        let mut aggregated_request = generate_request(
+            ctx.customer_market.index_from_nonce().await.unwrap(),
+            &ctx.customer_signer.address(),
+            ProofType::Any,
+            compose_url,
+            None,
+            None,
+        );
+        aggregated_request.requirements.imageId = <[u8; 32]>::from(Digest::from(COMPOSE_ID)).into();
+        aggregated_request.input = GuestEnv::builder()
+            .write(&(Digest::from(COMPOSE_ID), journal.to_vec()))
+            .unwrap()
+            .with_assumption(AssumptionSource::Fulfillment {
+                request_id: U256::from(request.id),
+                image_id: B256::from(<[u8; 32]>::from(Digest::from(COMPOSE_ID))),
+            })
+            .build_inline()
+            .unwrap();
+
+        ctx.customer_market
+            .submit_request(&aggregated_request, &ctx.customer_signer)
+            .await
+            .unwrap();
+        let skipped = "because it has an assumption that cannot be resolved";
+        for _ in 0..30 {
+            if logs_contain(skipped) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+        assert!(logs_contain(skipped));
+    })
+    .await;
+}
+
 #[tokio::test]
 #[traced_test]
 #[ignore = "runs a proof; requires BONSAI if RISC0_DEV_MODE=FALSE"]
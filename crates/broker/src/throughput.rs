@@ -0,0 +1,309 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Proving throughput calibration.
+//!
+//! The throughput of every completed proof is recorded in the DB as a [ProvingSample]. When
+//! `market.calibrate_prove_khz` is set, [ProvingThroughput] measures the throughput over the most
+//! recent samples, which the order picker and order monitor use in place of the static
+//! `market.peak_prove_khz`. The order picker estimates the duration of a single proof from the
+//! throughput of each proof, while the order monitor plans its commitments with the capacity of
+//! the prover, measured over the wall-clock time the proofs ran, so that concurrent proofs add up.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{Context, Result};
+use chrono::{serde::ts_seconds, DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{config::ConfigLock, db::DbObj};
+
+/// Throughput achieved by a completed proof
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProvingSample {
+    pub order_id: String,
+    /// Image ID of the request, hex encoded
+    pub image_id: String,
+    /// Name of the prover backend the proof ran on, when routing across multiple backends
+    pub backend: Option<String>,
+    pub total_cycles: u64,
+    pub elapsed_secs: f64,
+    #[serde(with = "ts_seconds")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// Rolling estimate of the proving throughput
+#[derive(Clone)]
+pub struct ProvingThroughput {
+    db: DbObj,
+    config: ConfigLock,
+    /// Most recent samples, oldest first
+    samples: Arc<Mutex<VecDeque<ProvingSample>>>,
+}
+
+impl ProvingThroughput {
+    /// Load the most recent samples from the DB
+    pub async fn load(db: DbObj, config: ConfigLock) -> Result<Self> {
+        let window = config.lock_all().context("Failed to read config")?.market.prove_khz_window;
+        let samples =
+            db.get_proving_samples(window).await.context("Failed to load proving samples")?;
+        Ok(Self { db, config, samples: Arc::new(Mutex::new(samples.into())) })
+    }
+
+    /// Record the throughput of a completed proof
+    ///
+    /// Proofs without a usable duration are ignored, and DB errors are logged rather than
+    /// returned, as failing to record a sample must not fail the proof.
+    pub async fn record(&self, sample: ProvingSample) {
+        if sample.total_cycles == 0
+            || !sample.elapsed_secs.is_finite()
+            || sample.elapsed_secs <= 0.0
+        {
+            tracing::debug!("Not recording proving throughput of order {}", sample.order_id);
+            return;
+        }
+        if let Err(err) = self.db.add_proving_sample(&sample).await {
+            tracing::error!(
+                "Failed to record proving throughput of order {}: {err:?}",
+                sample.order_id
+            );
+        }
+
+        let window = match self.config.lock_all() {
+            Ok(config) => config.market.prove_khz_window,
+            Err(err) => {
+                tracing::error!("Failed to read config: {err:?}");
+                return;
+            }
+        };
+        let mut samples = self.samples.lock().unwrap();
+        samples.push_back(sample);
+        while samples.len() > window {
+            samples.pop_front();
+        }
+    }
+
+    /// Throughput measured over the matching samples, in kHz
+    ///
+    /// Returns `None` when fewer than `min_samples` samples match.
+    fn measure(&self, min_samples: usize, filter: impl Fn(&ProvingSample) -> bool) -> Option<u64> {
+        let samples = self.samples.lock().unwrap();
+        let (count, cycles, secs) = samples.iter().filter(|sample| filter(sample)).fold(
+            (0, 0u64, 0f64),
+            |(count, cycles, secs), sample| {
+                (count + 1, cycles.saturating_add(sample.total_cycles), secs + sample.elapsed_secs)
+            },
+        );
+        if count == 0 || count < min_samples {
+            return None;
+        }
+        Some(((cycles as f64 / secs / 1_000.0) as u64).max(1))
+    }
+
+    /// Capacity measured over the wall-clock time any sample was proving, in kHz
+    ///
+    /// Unlike [Self::measure], the cycles of proofs running at the same time add up. Returns
+    /// `None` when fewer than `min_samples` samples were recorded.
+    fn measure_wall_clock(&self, min_samples: usize) -> Option<u64> {
+        let samples = self.samples.lock().unwrap();
+        if samples.is_empty() || samples.len() < min_samples {
+            return None;
+        }
+
+        let mut intervals: Vec<(f64, f64)> = samples
+            .iter()
+            .map(|sample| {
+                let end = sample.created_at.timestamp_millis() as f64 / 1_000.0;
+                (end - sample.elapsed_secs, end)
+            })
+            .collect();
+        intervals.sort_by(|a, b| a.0.total_cmp(&b.0));
+        // Length of the union of the intervals, so that idle time does not count
+        let mut busy_secs = 0.0;
+        let mut current: Option<(f64, f64)> = None;
+        for (start, end) in intervals {
+            current = match current {
+                Some((current_start, current_end)) if start <= current_end => {
+                    Some((current_start, current_end.max(end)))
+                }
+                Some((current_start, current_end)) => {
+                    busy_secs += current_end - current_start;
+                    Some((start, end))
+                }
+                None => Some((start, end)),
+            };
+        }
+        if let Some((start, end)) = current {
+            busy_secs += end - start;
+        }
+
+        let cycles =
+            samples.iter().fold(0u64, |cycles, sample| cycles.saturating_add(sample.total_cycles));
+        Some(((cycles as f64 / busy_secs / 1_000.0) as u64).max(1))
+    }
+
+    /// Combine a measured throughput with the configured peak, which caps it, and is used until
+    /// enough proofs have completed
+    fn calibrate(
+        &self,
+        configured_khz: Option<u64>,
+        measure: impl Fn(usize) -> Option<u64>,
+    ) -> Option<u64> {
+        let (calibrate, min_samples) = match self.config.lock_all() {
+            Ok(config) => (config.market.calibrate_prove_khz, config.market.prove_khz_min_samples),
+            Err(err) => {
+                tracing::error!("Failed to read config: {err:?}");
+                return configured_khz;
+            }
+        };
+        if !calibrate {
+            return configured_khz;
+        }
+
+        let measured = measure(min_samples);
+        match (measured, configured_khz) {
+            (Some(measured), Some(configured)) => Some(measured.min(configured)),
+            (measured, configured) => measured.or(configured),
+        }
+    }
+
+    fn configured_khz(&self) -> Option<u64> {
+        match self.config.lock_all() {
+            Ok(config) => config.market.peak_prove_khz,
+            Err(err) => {
+                tracing::error!("Failed to read config: {err:?}");
+                None
+            }
+        }
+    }
+
+    /// Proving throughput of a single proof, in kHz, across all images, or of the given image
+    /// when enough of its proofs have completed
+    pub fn prove_khz(&self, image_id: Option<&str>) -> Option<u64> {
+        let by_image = |sample: &ProvingSample| Some(sample.image_id.as_str()) == image_id;
+        let all = |_: &ProvingSample| true;
+        self.calibrate(self.configured_khz(), |min_samples| match image_id {
+            Some(_) => {
+                self.measure(min_samples, &by_image).or_else(|| self.measure(min_samples, &all))
+            }
+            None => self.measure(min_samples, &all),
+        })
+    }
+
+    /// Total proving capacity of the broker, in kHz, across the proofs it runs concurrently
+    pub fn capacity_khz(&self) -> Option<u64> {
+        self.calibrate(self.configured_khz(), |min_samples| self.measure_wall_clock(min_samples))
+    }
+
+    /// Proving throughput, in kHz, of a prover backend
+    pub fn backend_khz(&self, backend: &str, configured_khz: Option<u64>) -> Option<u64> {
+        let by_backend = |sample: &ProvingSample| sample.backend.as_deref() == Some(backend);
+        self.calibrate(configured_khz, |min_samples| self.measure(min_samples, &by_backend))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::SqliteDb;
+
+    async fn throughput(calibrate: bool) -> ProvingThroughput {
+        let db: DbObj = Arc::new(SqliteDb::new("sqlite::memory:").await.unwrap());
+        let config = ConfigLock::default();
+        {
+            let mut config = config.load_write().unwrap();
+            config.market.peak_prove_khz = Some(500);
+            config.market.calibrate_prove_khz = calibrate;
+            config.market.prove_khz_window = 4;
+            config.market.prove_khz_min_samples = 2;
+        }
+        ProvingThroughput::load(db, config).await.unwrap()
+    }
+
+    fn sample(image_id: &str, backend: Option<&str>, khz: u64) -> ProvingSample {
+        ProvingSample {
+            order_id: "order".into(),
+            image_id: image_id.into(),
+            backend: backend.map(Into::into),
+            total_cycles: khz * 1_000 * 10,
+            elapsed_secs: 10.0,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn calibrated_prove_khz() {
+        let throughput = throughput(true).await;
+        // Bootstrapped from the config until enough proofs have completed
+        throughput.record(sample("a", Some("large"), 100)).await;
+        assert_eq!(throughput.prove_khz(None), Some(500));
+
+        throughput.record(sample("a", Some("large"), 100)).await;
+        throughput.record(sample("b", Some("small"), 400)).await;
+        throughput.record(sample("b", Some("small"), 400)).await;
+        assert_eq!(throughput.prove_khz(None), Some(250));
+        assert_eq!(throughput.prove_khz(Some("a")), Some(100));
+        assert_eq!(throughput.prove_khz(Some("b")), Some(400));
+        // Too few samples of image c, so the overall throughput is used
+        assert_eq!(throughput.prove_khz(Some("c")), Some(250));
+        assert_eq!(throughput.backend_khz("small", Some(300)), Some(300));
+        assert_eq!(throughput.backend_khz("other", None), None);
+
+        // Only the most recent samples are measured, capped by the configured peak
+        for _ in 0..4 {
+            throughput.record(sample("a", None, 1_000)).await;
+        }
+        assert_eq!(throughput.prove_khz(Some("b")), Some(500));
+
+        // Samples are persisted across restarts
+        let reloaded = ProvingThroughput::load(throughput.db.clone(), throughput.config.clone())
+            .await
+            .unwrap();
+        assert_eq!(reloaded.samples.lock().unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn calibrated_capacity_khz() {
+        let throughput = throughput(true).await;
+        let at = |secs: i64| DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap();
+
+        // Two proofs at 100 kHz each, running side by side
+        throughput.record(ProvingSample { created_at: at(10), ..sample("a", None, 100) }).await;
+        throughput.record(ProvingSample { created_at: at(10), ..sample("b", None, 100) }).await;
+        assert_eq!(throughput.prove_khz(None), Some(100));
+        assert_eq!(throughput.capacity_khz(), Some(200));
+
+        // Idle time between proofs does not lower the capacity
+        throughput.record(ProvingSample { created_at: at(100), ..sample("a", None, 200) }).await;
+        throughput.record(ProvingSample { created_at: at(110), ..sample("a", None, 200) }).await;
+        assert_eq!(throughput.capacity_khz(), Some(200));
+
+        // Capped by the configured peak
+        throughput.record(ProvingSample { created_at: at(120), ..sample("a", None, 1_000) }).await;
+        throughput.record(ProvingSample { created_at: at(120), ..sample("b", None, 1_000) }).await;
+        assert_eq!(throughput.capacity_khz(), Some(500));
+    }
+
+    #[tokio::test]
+    async fn uncalibrated_prove_khz() {
+        let throughput = throughput(false).await;
+        for _ in 0..4 {
+            throughput.record(sample("a", None, 100)).await;
+        }
+        assert_eq!(throughput.prove_khz(Some("a")), Some(500));
+    }
+}
@@ -0,0 +1,1069 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Automatic management of the stake and balances of the broker.
+//!
+//! The [TreasuryTask] of each chain periodically keeps `treasury.stake_target` stake deposited in
+//! the market, tops up the gas balance of the broker wallet from its market earnings, and sweeps
+//! the earnings above `treasury.sweep_threshold` to a cold address. Each action is recorded in the
+//! DB as it is taken, and the amount moved by each kind of action is capped per rolling day.
+//! In shadow mode, the actions are only recorded.
+//!
+//! The chain of the `--rpc-url` follows the `treasury` section of the config, and each additional
+//! chain the `treasury` of its `chains` entry.
+
+use std::{future::Future, sync::Arc, time::Duration};
+
+use alloy::{
+    network::{Ethereum, TransactionBuilder},
+    primitives::{
+        utils::{parse_ether, parse_units},
+        Address, TxHash, U256,
+    },
+    providers::{PendingTransactionBuilder, Provider, WalletProvider},
+    rpc::types::{TransactionReceipt, TransactionRequest},
+    signers::local::PrivateKeySigner,
+};
+use anyhow::{anyhow, bail, ensure, Context};
+use boundless_market::contracts::boundless_market::{BoundlessMarketService, MarketError};
+use chrono::{serde::ts_seconds, DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    admin::BrokerControl,
+    config::{ConfigErr, ConfigLock, TreasuryConf},
+    db::{DbError, DbObj},
+    errors::CodedError,
+    impl_coded_debug,
+    task::{RetryRes, RetryTask, SupervisorErr},
+};
+
+/// Timeout for transaction confirmations when `batcher.txn_timeout` is not set
+const DEFAULT_TXN_TIMEOUT: Duration = Duration::from_secs(45);
+
+#[derive(Error)]
+pub enum TreasuryErr {
+    #[error("{code} DB error: {0}", code = self.code())]
+    DbError(#[from] DbError),
+
+    #[error("{code} Config error {0}", code = self.code())]
+    ConfigReadErr(#[from] ConfigErr),
+
+    #[error("{code} Invalid treasury config: {0:?}", code = self.code())]
+    InvalidConfig(anyhow::Error),
+
+    #[error("{code} Treasury {kind:?} failed: {err:?}", code = self.code())]
+    ActionFailed { kind: TreasuryActionKind, err: anyhow::Error },
+
+    #[error("{code} RPC error: {0:?}", code = self.code())]
+    RpcErr(anyhow::Error),
+
+    #[error("{code} Market error: {0:?}", code = self.code())]
+    MarketErr(#[from] MarketError),
+}
+
+impl_coded_debug!(TreasuryErr);
+
+impl CodedError for TreasuryErr {
+    fn code(&self) -> &str {
+        match self {
+            TreasuryErr::DbError(_) => "[B-TRS-001]",
+            TreasuryErr::ConfigReadErr(_) => "[B-TRS-002]",
+            TreasuryErr::InvalidConfig(_) => "[B-TRS-003]",
+            TreasuryErr::ActionFailed { .. } => "[B-TRS-004]",
+            TreasuryErr::RpcErr(_) => "[B-TRS-400]",
+            TreasuryErr::MarketErr(_) => "[B-TRS-401]",
+        }
+    }
+}
+
+/// Kind of action taken by the treasury
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TreasuryActionKind {
+    /// Stake tokens of the wallet deposited in the market, in the smallest unit of the stake token
+    StakeDeposit,
+    /// Market earnings withdrawn to the wallet to pay for gas, in wei
+    GasTopUp,
+    /// Market earnings withdrawn to the wallet, to be sent to the sweep address, in wei
+    SweepWithdraw,
+    /// Withdrawn earnings sent from the wallet to the sweep address, in wei
+    Sweep,
+}
+
+impl TreasuryActionKind {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            TreasuryActionKind::StakeDeposit => "stake_deposit",
+            TreasuryActionKind::GasTopUp => "gas_top_up",
+            TreasuryActionKind::SweepWithdraw => "sweep_withdraw",
+            TreasuryActionKind::Sweep => "sweep",
+        }
+    }
+}
+
+/// An action taken by the treasury
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TreasuryAction {
+    pub chain_id: u64,
+    pub kind: TreasuryActionKind,
+    pub amount: U256,
+    /// Recipient of the earnings withdrawn or sent for a sweep
+    pub to: Option<Address>,
+    /// Error of the action, if it failed
+    ///
+    /// Failed actions do not count against the daily limits.
+    pub error: Option<String>,
+    /// Hash of the transaction of a sweep, recorded once broadcast
+    #[serde(default)]
+    pub tx_hash: Option<TxHash>,
+    /// Whether the action was only recorded in shadow mode, without being taken
+    ///
+    /// Shadow actions do not count against the daily limits.
+    #[serde(default)]
+    pub shadow: bool,
+    #[serde(with = "ts_seconds")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// Sweep of market earnings to a cold address
+#[derive(Debug, PartialEq)]
+struct SweepParams {
+    address: Address,
+    threshold: U256,
+    reserve: U256,
+}
+
+/// Top up of the wallet balance from market earnings
+#[derive(Debug, PartialEq)]
+struct GasTopUpParams {
+    threshold: U256,
+    target: U256,
+}
+
+/// Treasury config parsed to wei and units of the stake token
+#[derive(Debug, PartialEq)]
+struct TreasuryParams {
+    stake_target: Option<U256>,
+    stake_deposit_permit: bool,
+    max_daily_stake_deposit: Option<U256>,
+    sweep: Option<SweepParams>,
+    max_daily_sweep: Option<U256>,
+    gas_top_up: Option<GasTopUpParams>,
+    max_daily_gas_top_up: Option<U256>,
+}
+
+impl TreasuryParams {
+    fn from_config(conf: &TreasuryConf, stake_token_decimals: u8) -> anyhow::Result<Self> {
+        fn ether(value: &Option<String>, name: &str) -> anyhow::Result<Option<U256>> {
+            value
+                .as_deref()
+                .map(parse_ether)
+                .transpose()
+                .with_context(|| format!("Failed to parse treasury.{name}"))
+        }
+        let stake = |value: &Option<String>, name: &str| -> anyhow::Result<Option<U256>> {
+            value
+                .as_deref()
+                .map(|value| parse_units(value, stake_token_decimals).map(Into::into))
+                .transpose()
+                .with_context(|| format!("Failed to parse treasury.{name}"))
+        };
+
+        let sweep = match (conf.sweep_address, ether(&conf.sweep_threshold, "sweep_threshold")?) {
+            (Some(address), Some(threshold)) => {
+                let reserve = ether(&conf.sweep_reserve, "sweep_reserve")?.unwrap_or_default();
+                ensure!(
+                    reserve <= threshold,
+                    "treasury.sweep_reserve must not exceed treasury.sweep_threshold"
+                );
+                Some(SweepParams { address, threshold, reserve })
+            }
+            (None, None) => None,
+            _ => bail!("treasury.sweep_address and treasury.sweep_threshold must be set together"),
+        };
+
+        let gas_top_up = match (
+            ether(&conf.gas_top_up_threshold, "gas_top_up_threshold")?,
+            ether(&conf.gas_top_up_target, "gas_top_up_target")?,
+        ) {
+            (Some(threshold), Some(target)) => {
+                ensure!(
+                    threshold <= target,
+                    "treasury.gas_top_up_threshold must not exceed treasury.gas_top_up_target"
+                );
+                Some(GasTopUpParams { threshold, target })
+            }
+            (None, None) => None,
+            _ => bail!(
+                "treasury.gas_top_up_threshold and treasury.gas_top_up_target must be set together"
+            ),
+        };
+
+        Ok(Self {
+            stake_target: stake(&conf.stake_target, "stake_target")?,
+            stake_deposit_permit: conf.stake_deposit_permit,
+            max_daily_stake_deposit: stake(
+                &conf.max_daily_stake_deposit,
+                "max_daily_stake_deposit",
+            )?,
+            sweep,
+            max_daily_sweep: ether(&conf.max_daily_sweep, "max_daily_sweep")?,
+            gas_top_up,
+            max_daily_gas_top_up: ether(&conf.max_daily_gas_top_up, "max_daily_gas_top_up")?,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.stake_target.is_none() && self.sweep.is_none() && self.gas_top_up.is_none()
+    }
+}
+
+/// Amount moved by the actions of a kind that were taken and did not fail
+fn moved(actions: &[TreasuryAction], kind: TreasuryActionKind) -> U256 {
+    actions
+        .iter()
+        .filter(|action| action.kind == kind && action.error.is_none() && !action.shadow)
+        .fold(U256::ZERO, |total, action| total.saturating_add(action.amount))
+}
+
+/// Amount that can still be moved under a daily limit
+fn remaining(
+    limit: Option<U256>,
+    actions: &[TreasuryAction],
+    kind: TreasuryActionKind,
+) -> Option<U256> {
+    let used = moved(actions, kind);
+    limit.map(|limit| limit.saturating_sub(used))
+}
+
+/// Earnings withdrawn for a sweep but not sent to the sweep address yet
+fn unswept(actions: &[TreasuryAction]) -> U256 {
+    moved(actions, TreasuryActionKind::SweepWithdraw)
+        .saturating_sub(moved(actions, TreasuryActionKind::Sweep))
+}
+
+/// Cap an amount to what can still be moved under its daily limit
+fn cap(kind: TreasuryActionKind, amount: U256, remaining: Option<U256>) -> U256 {
+    match remaining {
+        Some(remaining) if remaining < amount => {
+            tracing::warn!(
+                "[B-TRS-100] Treasury {kind:?} of {amount} capped to {remaining} by its daily limit"
+            );
+            remaining
+        }
+        _ => amount,
+    }
+}
+
+#[derive(Clone)]
+pub struct TreasuryTask<P> {
+    db: DbObj,
+    config: ConfigLock,
+    provider: Arc<P>,
+    market: BoundlessMarketService<Arc<P>>,
+    chain_id: u64,
+    prover_addr: Address,
+    signer: PrivateKeySigner,
+    stake_token_decimals: u8,
+    txn_timeout: Duration,
+    /// Treasury config of an additional chain, read at startup
+    ///
+    /// The chain of the `--rpc-url` follows the `treasury` section of the config instead.
+    chain_conf: Option<TreasuryConf>,
+    control: BrokerControl,
+}
+
+impl<P> TreasuryTask<P>
+where
+    P: Provider<Ethereum> + WalletProvider,
+{
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        db: DbObj,
+        config: ConfigLock,
+        provider: Arc<P>,
+        chain_id: u64,
+        market_addr: Address,
+        signer: PrivateKeySigner,
+        stake_token_decimals: u8,
+        control: BrokerControl,
+    ) -> anyhow::Result<Self> {
+        let txn_timeout = {
+            let config = config.lock_all().context("Failed to read config")?;
+            config.batcher.txn_timeout.map(Duration::from_secs).unwrap_or(DEFAULT_TXN_TIMEOUT)
+        };
+        let prover_addr = provider.default_signer_address();
+        let market = BoundlessMarketService::new(market_addr, provider.clone(), prover_addr)
+            .with_timeout(txn_timeout);
+
+        Ok(Self {
+            db,
+            config,
+            provider,
+            market,
+            chain_id,
+            prover_addr,
+            signer,
+            stake_token_decimals,
+            txn_timeout,
+            chain_conf: None,
+            control,
+        })
+    }
+
+    /// Use the treasury config of an additional chain, rather than the `treasury` section of the
+    /// config
+    pub fn with_chain_conf(mut self, conf: TreasuryConf) -> Self {
+        self.chain_conf = Some(conf);
+        self
+    }
+
+    fn treasury_conf(&self) -> Result<TreasuryConf, TreasuryErr> {
+        match &self.chain_conf {
+            Some(conf) => Ok(conf.clone()),
+            None => Ok(self.config.lock_all()?.treasury.clone()),
+        }
+    }
+
+    /// Take the actions needed to bring the stake and balances back within the treasury config
+    async fn check_balances(&self) -> Result<(), TreasuryErr> {
+        let params = TreasuryParams::from_config(&self.treasury_conf()?, self.stake_token_decimals)
+            .map_err(TreasuryErr::InvalidConfig)?;
+        if params.is_empty() {
+            return Ok(());
+        }
+
+        let actions = self
+            .db
+            .get_treasury_actions(self.chain_id, Utc::now() - chrono::Duration::days(1))
+            .await?;
+
+        if let Some(target) = params.stake_target {
+            let left = remaining(
+                params.max_daily_stake_deposit,
+                &actions,
+                TreasuryActionKind::StakeDeposit,
+            );
+            self.deposit_stake(target, params.stake_deposit_permit, left).await?;
+        }
+        // Gas is topped up before sweeping, so the wallet is funded before earnings leave it.
+        if let Some(gas_top_up) = &params.gas_top_up {
+            let left =
+                remaining(params.max_daily_gas_top_up, &actions, TreasuryActionKind::GasTopUp);
+            self.top_up_gas(gas_top_up, left).await?;
+        }
+        if let Some(sweep) = &params.sweep {
+            let left = remaining(params.max_daily_sweep, &actions, TreasuryActionKind::Sweep);
+            self.sweep(sweep, left).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Deposit stake tokens of the wallet until the deposited stake reaches `target`
+    async fn deposit_stake(
+        &self,
+        target: U256,
+        permit: bool,
+        remaining: Option<U256>,
+    ) -> Result<(), TreasuryErr> {
+        let deposited = self.market.balance_of_stake(self.prover_addr).await?;
+        if deposited >= target {
+            return Ok(());
+        }
+
+        let available = self.market.stake_token_balance_of(self.prover_addr).await?;
+        if available.is_zero() {
+            tracing::warn!(
+                "Stake balance {deposited} below target {target}, but the wallet holds no stake tokens to deposit"
+            );
+            return Ok(());
+        }
+        let kind = TreasuryActionKind::StakeDeposit;
+        let amount = cap(kind, (target - deposited).min(available), remaining);
+        if amount.is_zero() {
+            return Ok(());
+        }
+
+        self.execute(kind, amount, None, async {
+            let res = if permit {
+                self.market.deposit_stake_with_permit(amount, &self.signer).await
+            } else {
+                self.market.deposit_stake(amount).await
+            };
+            res.context("Failed to deposit stake")
+        })
+        .await
+    }
+
+    /// Withdraw market earnings to the wallet while its balance is below the top up threshold
+    async fn top_up_gas(
+        &self,
+        params: &GasTopUpParams,
+        remaining: Option<U256>,
+    ) -> Result<(), TreasuryErr> {
+        let balance = self
+            .provider
+            .get_balance(self.prover_addr)
+            .await
+            .map_err(|err| TreasuryErr::RpcErr(err.into()))?;
+        if balance >= params.threshold {
+            return Ok(());
+        }
+
+        let earnings = self.market.balance_of(self.prover_addr).await?;
+        if earnings.is_zero() {
+            tracing::warn!(
+                "Wallet balance {balance} below the gas top up threshold, but there are no market earnings to withdraw"
+            );
+            return Ok(());
+        }
+        let kind = TreasuryActionKind::GasTopUp;
+        let amount = cap(kind, (params.target - balance).min(earnings), remaining);
+        if amount.is_zero() {
+            return Ok(());
+        }
+
+        self.execute(kind, amount, None, async {
+            self.market.withdraw(amount).await.context("Failed to withdraw earnings")
+        })
+        .await
+    }
+
+    /// Send the market earnings above the sweep threshold to the sweep address
+    ///
+    /// The earnings are withdrawn to the wallet, then sent from it, as two actions. When a
+    /// previous transfer failed, the earnings it left in the wallet are sent again instead of
+    /// withdrawing more. Nothing is sent while a previous transaction is pending.
+    async fn sweep(
+        &self,
+        params: &SweepParams,
+        remaining: Option<U256>,
+    ) -> Result<(), TreasuryErr> {
+        if self.check_pending().await? {
+            return Ok(());
+        }
+
+        let history = self.db.get_treasury_actions(self.chain_id, DateTime::UNIX_EPOCH).await?;
+        let withdrawn = unswept(&history);
+        if !withdrawn.is_zero() {
+            let amount = cap(TreasuryActionKind::Sweep, withdrawn, remaining);
+            if amount.is_zero() {
+                return Ok(());
+            }
+            tracing::info!(
+                "Retrying the transfer of {amount} withdrawn earnings to the sweep address"
+            );
+            return self.send_sweep(params.address, amount).await;
+        }
+
+        let earnings = self.market.balance_of(self.prover_addr).await?;
+        if earnings <= params.threshold {
+            return Ok(());
+        }
+
+        let amount = cap(TreasuryActionKind::Sweep, earnings - params.reserve, remaining);
+        if amount.is_zero() {
+            return Ok(());
+        }
+
+        if self.control.is_shadow() {
+            return self
+                .record_shadow(TreasuryActionKind::Sweep, amount, Some(params.address))
+                .await;
+        }
+
+        // Recorded once broadcast, so that earnings are only sent from the wallet after they are
+        // known to be withdrawn.
+        let kind = TreasuryActionKind::SweepWithdraw;
+        let tx = self.market.instance().withdraw(amount).into_transaction_request();
+        let pending = self
+            .provider
+            .send_transaction(tx)
+            .await
+            .context("Failed to withdraw earnings")
+            .map_err(|err| TreasuryErr::ActionFailed { kind, err })?;
+        let action = TreasuryAction {
+            tx_hash: Some(*pending.tx_hash()),
+            ..self.action(kind, amount, Some(params.address))
+        };
+        let id = self.db.add_treasury_action(&action).await?;
+        if !self.confirm(id, kind, pending).await? {
+            return Ok(());
+        }
+
+        self.send_sweep(params.address, amount).await
+    }
+
+    /// Send earnings withdrawn to the wallet to the sweep address
+    async fn send_sweep(&self, to: Address, amount: U256) -> Result<(), TreasuryErr> {
+        let kind = TreasuryActionKind::Sweep;
+        if self.control.is_shadow() {
+            return self.record_shadow(kind, amount, Some(to)).await;
+        }
+
+        let id = self.record(kind, amount, Some(to)).await?;
+        let tx = TransactionRequest::default().with_to(to).with_value(amount);
+        let pending = match self.provider.send_transaction(tx).await {
+            Ok(pending) => pending,
+            // Never broadcast, so the earnings are sent again on the next sweep
+            Err(err) => {
+                let err = anyhow::Error::new(err)
+                    .context("Earnings withdrawn to the wallet, but failed to send them");
+                return Err(self.fail(id, kind, err).await);
+            }
+        };
+        self.db.set_treasury_action_tx_hash(id, *pending.tx_hash()).await?;
+        if self.confirm(id, kind, pending).await? {
+            tracing::info!("Treasury {kind:?} of {amount} completed on chain {}", self.chain_id);
+        }
+        Ok(())
+    }
+
+    /// Wait for the transaction of an action to land, returning whether it did
+    ///
+    /// A transaction not confirmed within the timeout may still land, so its action is left
+    /// pending, to be checked before the next sweep rather than sent again.
+    async fn confirm(
+        &self,
+        id: i64,
+        kind: TreasuryActionKind,
+        pending: PendingTransactionBuilder<Ethereum>,
+    ) -> Result<bool, TreasuryErr> {
+        let tx_hash = *pending.tx_hash();
+        match pending.with_timeout(Some(self.txn_timeout)).get_receipt().await {
+            Ok(receipt) => self.settle(id, kind, &receipt).await.map(|()| true),
+            Err(err) => {
+                tracing::warn!(
+                    "[B-TRS-101] Treasury {kind:?} transaction {tx_hash} not confirmed yet, checking it before the next sweep: {err:?}"
+                );
+                Ok(false)
+            }
+        }
+    }
+
+    /// Settle the pending action of a transaction from its receipt
+    async fn settle(
+        &self,
+        id: i64,
+        kind: TreasuryActionKind,
+        receipt: &TransactionReceipt,
+    ) -> Result<(), TreasuryErr> {
+        if !receipt.status() {
+            let err = anyhow!("Transaction {} reverted", receipt.transaction_hash);
+            return Err(self.fail(id, kind, err).await);
+        }
+        self.db.confirm_treasury_action(id).await?;
+        Ok(())
+    }
+
+    /// Settle the actions whose transaction was not confirmed in time, returning whether any is
+    /// still pending
+    ///
+    /// Transactions that reverted, or are no longer known to the node, are recorded as failed, so
+    /// that the earnings they did not move are sent again.
+    async fn check_pending(&self) -> Result<bool, TreasuryErr> {
+        let mut still_pending = false;
+        for (id, action) in self.db.get_pending_treasury_actions(self.chain_id).await? {
+            let Some(tx_hash) = action.tx_hash else {
+                continue;
+            };
+            let receipt = self
+                .provider
+                .get_transaction_receipt(tx_hash)
+                .await
+                .map_err(|err| TreasuryErr::RpcErr(err.into()))?;
+            if let Some(receipt) = receipt {
+                if let Err(err) = self.settle(id, action.kind, &receipt).await {
+                    tracing::warn!("{err:?}");
+                }
+                continue;
+            }
+
+            let tx = self
+                .provider
+                .get_transaction_by_hash(tx_hash)
+                .await
+                .map_err(|err| TreasuryErr::RpcErr(err.into()))?;
+            if tx.is_some() {
+                tracing::info!("Waiting for treasury {:?} transaction {tx_hash}", action.kind);
+                still_pending = true;
+            } else {
+                let err = anyhow!("Transaction {tx_hash} was dropped");
+                tracing::warn!("{:?}", self.fail(id, action.kind, err).await);
+            }
+        }
+        Ok(still_pending)
+    }
+
+    /// Record an action, then take it, recording its error if it fails
+    ///
+    /// The action is recorded first so that it counts against its daily limit even if the broker
+    /// stops before it completes.
+    async fn execute(
+        &self,
+        kind: TreasuryActionKind,
+        amount: U256,
+        to: Option<Address>,
+        action: impl Future<Output = anyhow::Result<()>>,
+    ) -> Result<(), TreasuryErr> {
+        if self.control.is_shadow() {
+            return self.record_shadow(kind, amount, to).await;
+        }
+
+        let id = self.record(kind, amount, to).await?;
+
+        if let Err(err) = action.await {
+            return Err(self.fail(id, kind, err).await);
+        }
+
+        tracing::info!("Treasury {kind:?} of {amount} completed on chain {}", self.chain_id);
+        Ok(())
+    }
+
+    /// Record the error of a failed action, returning it
+    async fn fail(&self, id: i64, kind: TreasuryActionKind, err: anyhow::Error) -> TreasuryErr {
+        if let Err(db_err) = self.db.set_treasury_action_error(id, &format!("{err:?}")).await {
+            tracing::error!("Failed to record the error of treasury action {id}: {db_err:?}");
+        }
+        TreasuryErr::ActionFailed { kind, err }
+    }
+
+    fn action(
+        &self,
+        kind: TreasuryActionKind,
+        amount: U256,
+        to: Option<Address>,
+    ) -> TreasuryAction {
+        TreasuryAction {
+            chain_id: self.chain_id,
+            kind,
+            amount,
+            to,
+            error: None,
+            tx_hash: None,
+            shadow: false,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Record an action in the DB, returning its id
+    async fn record(
+        &self,
+        kind: TreasuryActionKind,
+        amount: U256,
+        to: Option<Address>,
+    ) -> Result<i64, TreasuryErr> {
+        Ok(self.db.add_treasury_action(&self.action(kind, amount, to)).await?)
+    }
+
+    /// Record an action that would be taken outside of shadow mode
+    async fn record_shadow(
+        &self,
+        kind: TreasuryActionKind,
+        amount: U256,
+        to: Option<Address>,
+    ) -> Result<(), TreasuryErr> {
+        tracing::info!(
+            "Shadow mode: would take treasury {kind:?} of {amount} on chain {}",
+            self.chain_id
+        );
+        let action = TreasuryAction { shadow: true, ..self.action(kind, amount, to) };
+        self.db.add_treasury_action(&action).await?;
+        Ok(())
+    }
+
+    async fn run_treasury_loop(&self, cancel_token: CancellationToken) -> Result<(), TreasuryErr> {
+        loop {
+            let interval = self.treasury_conf()?.interval_secs;
+
+            // A failed check is retried on the next interval rather than restarting the task.
+            if let Err(err) = self.check_balances().await {
+                tracing::error!("Treasury check on chain {} failed: {err:?}", self.chain_id);
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs(interval)) => {},
+                _ = cancel_token.cancelled() => {
+                    tracing::info!("Treasury task received cancellation, shutting down");
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+impl<P> RetryTask for TreasuryTask<P>
+where
+    P: Provider<Ethereum> + WalletProvider + 'static + Clone,
+{
+    type Error = TreasuryErr;
+
+    fn spawn(&self, cancel_token: CancellationToken) -> RetryRes<Self::Error> {
+        let this = self.clone();
+        Box::pin(async move {
+            this.run_treasury_loop(cancel_token).await.map_err(SupervisorErr::Recover)?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::SqliteDb;
+    use alloy::{
+        network::EthereumWallet,
+        node_bindings::{Anvil, AnvilInstance},
+        providers::{ext::AnvilApi, ProviderBuilder},
+    };
+    use boundless_market_test_utils::{
+        deploy_boundless_market, deploy_hit_points, deploy_mock_verifier, ASSESSOR_GUEST_ID,
+        ASSESSOR_GUEST_PATH,
+    };
+    use risc0_zkvm::sha::Digest;
+    use tracing_test::traced_test;
+
+    async fn setup(
+        config: ConfigLock,
+        control: BrokerControl,
+    ) -> (AnvilInstance, TreasuryTask<impl Provider + WalletProvider + Clone + 'static>, DbObj)
+    {
+        let anvil = Anvil::new().spawn();
+        let signer: PrivateKeySigner = anvil.keys()[0].clone().into();
+        let prover_addr = signer.address();
+        let provider = Arc::new(
+            ProviderBuilder::new()
+                .wallet(EthereumWallet::from(signer.clone()))
+                .connect(&anvil.endpoint())
+                .await
+                .unwrap(),
+        );
+
+        let verifier = deploy_mock_verifier(provider.clone()).await.unwrap();
+        let hit_points = deploy_hit_points(prover_addr, provider.clone()).await.unwrap();
+        // Mints the default allowance of stake tokens to the prover
+        let market_addr = deploy_boundless_market(
+            prover_addr,
+            provider.clone(),
+            verifier,
+            hit_points,
+            Digest::from(ASSESSOR_GUEST_ID),
+            format!("file://{ASSESSOR_GUEST_PATH}"),
+            Some(prover_addr),
+        )
+        .await
+        .unwrap();
+
+        let db: DbObj = Arc::new(SqliteDb::new("sqlite::memory:").await.unwrap());
+        let stake_token_decimals =
+            BoundlessMarketService::new(market_addr, provider.clone(), prover_addr)
+                .stake_token_decimals()
+                .await
+                .unwrap();
+        let treasury = TreasuryTask::new(
+            db.clone(),
+            config,
+            provider,
+            anvil.chain_id(),
+            market_addr,
+            signer,
+            stake_token_decimals,
+            control,
+        )
+        .unwrap();
+
+        (anvil, treasury, db)
+    }
+
+    #[test]
+    fn params_from_config() {
+        let mut conf = TreasuryConf {
+            stake_target: Some("10".into()),
+            sweep_address: Some(Address::repeat_byte(1)),
+            sweep_threshold: Some("1".into()),
+            ..Default::default()
+        };
+        let params = TreasuryParams::from_config(&conf, 6).unwrap();
+        assert_eq!(params.stake_target, Some(U256::from(10_000_000)));
+        assert_eq!(
+            params.sweep,
+            Some(SweepParams {
+                address: Address::repeat_byte(1),
+                threshold: parse_ether("1").unwrap(),
+                reserve: U256::ZERO,
+            })
+        );
+        assert_eq!(params.gas_top_up, None);
+        assert_eq!(params.max_daily_sweep, None);
+
+        conf.sweep_reserve = Some("2".into());
+        assert!(TreasuryParams::from_config(&conf, 6).is_err());
+
+        conf.sweep_reserve = None;
+        conf.gas_top_up_threshold = Some("0.1".into());
+        assert!(TreasuryParams::from_config(&conf, 6).is_err());
+
+        assert!(TreasuryParams::from_config(&TreasuryConf::default(), 6).unwrap().is_empty());
+    }
+
+    #[test]
+    fn daily_limits() {
+        let action = |kind, amount: u64, error: Option<&str>| TreasuryAction {
+            chain_id: 1,
+            kind,
+            amount: U256::from(amount),
+            to: None,
+            error: error.map(Into::into),
+            tx_hash: None,
+            shadow: false,
+            created_at: Utc::now(),
+        };
+        let actions = vec![
+            action(TreasuryActionKind::Sweep, 30, None),
+            action(TreasuryActionKind::Sweep, 50, Some("reverted")),
+            action(TreasuryActionKind::GasTopUp, 20, None),
+        ];
+
+        let limit = Some(U256::from(100));
+        let left = remaining(limit, &actions, TreasuryActionKind::Sweep);
+        assert_eq!(left, Some(U256::from(70)));
+        assert_eq!(remaining(None, &actions, TreasuryActionKind::Sweep), None);
+        assert_eq!(
+            remaining(Some(U256::from(10)), &actions, TreasuryActionKind::GasTopUp),
+            Some(U256::ZERO)
+        );
+
+        assert_eq!(cap(TreasuryActionKind::Sweep, U256::from(90), left), U256::from(70));
+        assert_eq!(cap(TreasuryActionKind::Sweep, U256::from(60), left), U256::from(60));
+        assert_eq!(cap(TreasuryActionKind::Sweep, U256::from(90), None), U256::from(90));
+
+        let mut actions = actions;
+        actions.push(action(TreasuryActionKind::SweepWithdraw, 50, None));
+        actions.push(action(TreasuryActionKind::SweepWithdraw, 40, Some("reverted")));
+        assert_eq!(unswept(&actions), U256::from(20));
+
+        // Actions recorded in shadow mode were not taken
+        actions.push(TreasuryAction {
+            shadow: true,
+            ..action(TreasuryActionKind::SweepWithdraw, 40, None)
+        });
+        assert_eq!(unswept(&actions), U256::from(20));
+        assert_eq!(remaining(limit, &actions, TreasuryActionKind::Sweep), left);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn manages_stake_and_balances() {
+        let config = ConfigLock::default();
+        let sweep_address = Address::repeat_byte(0x22);
+        {
+            let mut config = config.load_write().unwrap();
+            config.treasury.stake_target = Some("60".into());
+            config.treasury.max_daily_stake_deposit = Some("50".into());
+            // Above the balance of the anvil wallet, so that gas is topped up
+            config.treasury.gas_top_up_threshold = Some("1000000".into());
+            config.treasury.gas_top_up_target = Some("1000000".into());
+            config.treasury.max_daily_gas_top_up = Some("0.25".into());
+            config.treasury.sweep_address = Some(sweep_address);
+            config.treasury.sweep_threshold = Some("1".into());
+            config.treasury.sweep_reserve = Some("0.5".into());
+            config.treasury.max_daily_sweep = Some("1".into());
+        }
+        let (_anvil, treasury, db) = setup(config, BrokerControl::default()).await;
+        let prover_addr = treasury.prover_addr;
+        treasury.market.deposit(parse_ether("2").unwrap()).await.unwrap();
+
+        treasury.check_balances().await.unwrap();
+
+        let stake_unit = U256::from(10).pow(U256::from(treasury.stake_token_decimals));
+        let stake = treasury.market.balance_of_stake(prover_addr).await.unwrap();
+        assert_eq!(stake, U256::from(50) * stake_unit);
+        // 2 ETH of earnings, less 0.25 ETH topped up and 1 ETH swept
+        let earnings = treasury.market.balance_of(prover_addr).await.unwrap();
+        assert_eq!(earnings, parse_ether("0.75").unwrap());
+        let swept = treasury.provider.get_balance(sweep_address).await.unwrap();
+        assert_eq!(swept, parse_ether("1").unwrap());
+
+        let from = Utc::now() - chrono::Duration::days(1);
+        let actions = db.get_treasury_actions(treasury.chain_id, from).await.unwrap();
+        let summary: Vec<_> = actions.iter().map(|action| (action.kind, action.amount)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (TreasuryActionKind::StakeDeposit, U256::from(50) * stake_unit),
+                (TreasuryActionKind::GasTopUp, parse_ether("0.25").unwrap()),
+                (TreasuryActionKind::SweepWithdraw, parse_ether("1").unwrap()),
+                (TreasuryActionKind::Sweep, parse_ether("1").unwrap()),
+            ]
+        );
+        assert!(actions.iter().all(|action| action.error.is_none()));
+        assert_eq!(actions[3].to, Some(sweep_address));
+
+        // The daily limits are reached, and the earnings left are below the sweep threshold
+        treasury.check_balances().await.unwrap();
+        assert!(logs_contain("[B-TRS-100]"));
+        assert_eq!(db.get_treasury_actions(treasury.chain_id, from).await.unwrap().len(), 4);
+        let stake = treasury.market.balance_of_stake(prover_addr).await.unwrap();
+        assert_eq!(stake, U256::from(50) * stake_unit);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn retries_sweep_transfer() {
+        let config = ConfigLock::default();
+        let sweep_address = Address::repeat_byte(0x22);
+        {
+            let mut config = config.load_write().unwrap();
+            config.treasury.sweep_address = Some(sweep_address);
+            config.treasury.sweep_threshold = Some("1".into());
+        }
+        let (_anvil, treasury, db) = setup(config, BrokerControl::default()).await;
+        let prover_addr = treasury.prover_addr;
+        treasury.market.deposit(parse_ether("2").unwrap()).await.unwrap();
+
+        // Earnings withdrawn by a previous sweep whose transfer failed
+        treasury
+            .record(TreasuryActionKind::SweepWithdraw, parse_ether("0.5").unwrap(), None)
+            .await
+            .unwrap();
+
+        treasury.check_balances().await.unwrap();
+
+        // The withdrawn earnings are sent, without withdrawing more
+        let swept = treasury.provider.get_balance(sweep_address).await.unwrap();
+        assert_eq!(swept, parse_ether("0.5").unwrap());
+        let earnings = treasury.market.balance_of(prover_addr).await.unwrap();
+        assert_eq!(earnings, parse_ether("2").unwrap());
+
+        // Once sent, the earnings above the threshold are swept again
+        treasury.check_balances().await.unwrap();
+        let swept = treasury.provider.get_balance(sweep_address).await.unwrap();
+        assert_eq!(swept, parse_ether("2.5").unwrap());
+
+        let actions =
+            db.get_treasury_actions(treasury.chain_id, DateTime::UNIX_EPOCH).await.unwrap();
+        assert_eq!(unswept(&actions), U256::ZERO);
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn shadow_mode_records_actions() {
+        let config = ConfigLock::default();
+        let sweep_address = Address::repeat_byte(0x22);
+        {
+            let mut config = config.load_write().unwrap();
+            config.treasury.stake_target = Some("60".into());
+            config.treasury.sweep_address = Some(sweep_address);
+            config.treasury.sweep_threshold = Some("1".into());
+        }
+        let (_anvil, treasury, db) = setup(config, BrokerControl::new(true)).await;
+        let prover_addr = treasury.prover_addr;
+        treasury.market.deposit(parse_ether("2").unwrap()).await.unwrap();
+
+        treasury.check_balances().await.unwrap();
+
+        // Nothing is deposited, withdrawn or sent
+        assert_eq!(treasury.market.balance_of_stake(prover_addr).await.unwrap(), U256::ZERO);
+        assert_eq!(
+            treasury.market.balance_of(prover_addr).await.unwrap(),
+            parse_ether("2").unwrap()
+        );
+        assert_eq!(treasury.provider.get_balance(sweep_address).await.unwrap(), U256::ZERO);
+
+        let actions =
+            db.get_treasury_actions(treasury.chain_id, DateTime::UNIX_EPOCH).await.unwrap();
+        let summary: Vec<_> = actions.iter().map(|action| (action.kind, action.shadow)).collect();
+        assert_eq!(
+            summary,
+            vec![(TreasuryActionKind::StakeDeposit, true), (TreasuryActionKind::Sweep, true)]
+        );
+        assert_eq!(actions[1].amount, parse_ether("2").unwrap());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn waits_for_pending_sweep() {
+        let config = ConfigLock::default();
+        let sweep_address = Address::repeat_byte(0x22);
+        {
+            let mut config = config.load_write().unwrap();
+            config.treasury.sweep_address = Some(sweep_address);
+            config.treasury.sweep_threshold = Some("1".into());
+            config.batcher.txn_timeout = Some(1);
+        }
+        let (_anvil, treasury, db) = setup(config, BrokerControl::default()).await;
+        treasury.market.deposit(parse_ether("2").unwrap()).await.unwrap();
+        treasury
+            .record(TreasuryActionKind::SweepWithdraw, parse_ether("0.5").unwrap(), None)
+            .await
+            .unwrap();
+
+        // The transfer is not mined before its confirmation times out
+        treasury.provider.anvil_set_auto_mine(false).await.unwrap();
+        treasury.check_balances().await.unwrap();
+        assert!(logs_contain("[B-TRS-101]"));
+        let pending = db.get_pending_treasury_actions(treasury.chain_id).await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].1.kind, TreasuryActionKind::Sweep);
+        assert!(pending[0].1.tx_hash.is_some());
+
+        // Nothing is sent again while the transfer is pending
+        treasury.check_balances().await.unwrap();
+        let actions =
+            db.get_treasury_actions(treasury.chain_id, DateTime::UNIX_EPOCH).await.unwrap();
+        assert_eq!(actions.len(), 2);
+
+        // Once the transfer lands, the earnings above the threshold are swept
+        treasury.provider.anvil_set_auto_mine(true).await.unwrap();
+        treasury.provider.anvil_mine(Some(1), None).await.unwrap();
+        treasury.check_balances().await.unwrap();
+        assert!(db.get_pending_treasury_actions(treasury.chain_id).await.unwrap().is_empty());
+        let swept = treasury.provider.get_balance(sweep_address).await.unwrap();
+        assert_eq!(swept, parse_ether("2.5").unwrap());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn resends_dropped_sweep() {
+        let config = ConfigLock::default();
+        let sweep_address = Address::repeat_byte(0x22);
+        {
+            let mut config = config.load_write().unwrap();
+            config.treasury.sweep_address = Some(sweep_address);
+            config.treasury.sweep_threshold = Some("1".into());
+        }
+        let (_anvil, treasury, db) = setup(config, BrokerControl::default()).await;
+        let amount = parse_ether("0.5").unwrap();
+        treasury.record(TreasuryActionKind::SweepWithdraw, amount, None).await.unwrap();
+        // A transfer the node does not know of
+        let id =
+            treasury.record(TreasuryActionKind::Sweep, amount, Some(sweep_address)).await.unwrap();
+        db.set_treasury_action_tx_hash(id, TxHash::repeat_byte(1)).await.unwrap();
+
+        treasury.check_balances().await.unwrap();
+
+        // The dropped transfer is recorded as failed, and the earnings are sent again
+        let swept = treasury.provider.get_balance(sweep_address).await.unwrap();
+        assert_eq!(swept, amount);
+        let actions =
+            db.get_treasury_actions(treasury.chain_id, DateTime::UNIX_EPOCH).await.unwrap();
+        assert!(actions[1].error.as_deref().unwrap().contains("dropped"));
+        assert_eq!(actions[2].error, None);
+        assert!(db.get_pending_treasury_actions(treasury.chain_id).await.unwrap().is_empty());
+    }
+}
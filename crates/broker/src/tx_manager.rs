@@ -0,0 +1,624 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lifecycle management of lock and fulfillment transactions.
+//!
+//! When `market.txn_bump_blocks` is set, these transactions are sent through the [TxManager],
+//! which sends them with an explicit nonce and EIP-1559 fees, and tracks the nonces of the
+//! transactions it has pending. A transaction that is not included within `txn_bump_blocks`
+//! blocks is re-broadcast on the same nonce with its fees raised by `market.txn_fee_bump_bps`,
+//! as long as its total fee stays within the [TxPolicy] of the caller, e.g. the fee revenue of a
+//! batch. Once it no longer does, or its deadline passes, the nonce is freed by replacing the
+//! transaction with a zero-value transfer to self.
+//!
+//! Nonces are reserved in the [ReservedNonces] of the chain until their transaction is settled,
+//! so that the other transactions of the broker, sent through a [NonceProvider] sharing them,
+//! skip the nonces of pending managed transactions.
+//!
+//! [NonceProvider]: boundless_market::nonce_layer::NonceProvider
+
+use std::sync::Arc;
+
+use alloy::{
+    eips::eip1559::Eip1559Estimation,
+    network::{Ethereum, TransactionBuilder},
+    primitives::{Address, B256, U256},
+    providers::{Provider, WalletProvider},
+    rpc::types::{TransactionReceipt, TransactionRequest},
+};
+use anyhow::{anyhow, Context};
+use boundless_market::{contracts::boundless_market::MarketError, nonce_layer::ReservedNonces};
+use thiserror::Error;
+
+use crate::{config::ConfigLock, errors::CodedError, impl_coded_debug, now_timestamp};
+
+/// Minimum fee increase, in basis points, nodes accept for a replacement transaction
+const MIN_FEE_BUMP_BPS: u64 = 1_000;
+
+/// Padding, in basis points, added to gas estimates for state changes before inclusion
+const GAS_LIMIT_PADDING_BPS: u64 = 2_000;
+
+/// Gas used by a plain transfer, which cancels a stuck transaction
+const CANCEL_GAS_LIMIT: u64 = 21_000;
+
+/// Number of times to query the receipts of a nonce that was used on chain
+///
+/// Some providers report a transaction as confirmed before its receipt can be queried.
+const RECEIPT_RETRY_COUNT: usize = 3;
+
+#[derive(Error)]
+pub enum TxManagerErr {
+    #[error("{code} Transaction costs {cost} wei, over its limit of {max_cost} wei", code = self.code())]
+    Unprofitable { cost: U256, max_cost: U256 },
+
+    #[error("{code} Transaction with nonce {nonce} cancelled by {tx_hash}", code = self.code())]
+    Cancelled { nonce: u64, tx_hash: B256 },
+
+    #[error("{code} Nonce {0} used by a transaction not sent by the transaction manager", code = self.code())]
+    NonceConsumed(u64),
+
+    #[error("{code} RPC error: {0:?}", code = self.code())]
+    RpcErr(anyhow::Error),
+
+    #[error("{code} Unexpected error: {0:?}", code = self.code())]
+    UnexpectedErr(#[from] anyhow::Error),
+}
+
+impl_coded_debug!(TxManagerErr);
+
+impl CodedError for TxManagerErr {
+    fn code(&self) -> &str {
+        match self {
+            TxManagerErr::Unprofitable { .. } => "[B-TXM-001]",
+            TxManagerErr::Cancelled { .. } => "[B-TXM-002]",
+            TxManagerErr::NonceConsumed(_) => "[B-TXM-003]",
+            TxManagerErr::RpcErr(_) => "[B-TXM-400]",
+            TxManagerErr::UnexpectedErr(_) => "[B-TXM-500]",
+        }
+    }
+}
+
+impl From<TxManagerErr> for MarketError {
+    fn from(err: TxManagerErr) -> Self {
+        match err {
+            // The transaction was not included
+            TxManagerErr::Unprofitable { .. }
+            | TxManagerErr::Cancelled { .. }
+            | TxManagerErr::NonceConsumed(_) => MarketError::TxnConfirmationError(err.into()),
+            TxManagerErr::RpcErr(err) | TxManagerErr::UnexpectedErr(err) => MarketError::Error(err),
+        }
+    }
+}
+
+/// What a transaction is worth to the caller
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct TxPolicy {
+    /// Max total fee, in wei, worth paying for the transaction
+    pub max_cost: U256,
+    /// Timestamp after which the transaction is no longer worth including
+    pub deadline: Option<u64>,
+    /// Priority fee, in wei per gas, added to the fees suggested by the RPC node
+    pub priority_fee: u128,
+}
+
+/// Fee bumping parameters, read from the config once per transaction
+struct BumpConf {
+    blocks: u64,
+    bps: u64,
+}
+
+/// Reservation of a nonce, released when the transaction sent with it is settled
+struct NonceGuard<'a> {
+    nonces: &'a ReservedNonces,
+    sender: Address,
+    nonce: u64,
+}
+
+impl Drop for NonceGuard<'_> {
+    fn drop(&mut self) {
+        self.nonces.release(self.sender, self.nonce);
+    }
+}
+
+pub(crate) struct TxManager<P> {
+    provider: Arc<P>,
+    config: ConfigLock,
+    sender: Address,
+    /// Nonces reserved on the chain, including those of the transactions sent by the manager that
+    /// are not yet settled
+    reserved_nonces: ReservedNonces,
+}
+
+impl<P> TxManager<P>
+where
+    P: Provider<Ethereum> + WalletProvider,
+{
+    /// Create a manager reserving nonces in `reserved_nonces`, which must be shared with the
+    /// nonce source of the other transactions sent through `provider`.
+    pub fn new(provider: Arc<P>, config: ConfigLock, reserved_nonces: ReservedNonces) -> Self {
+        let sender = provider.default_signer_address();
+        Self { provider, config, sender, reserved_nonces }
+    }
+
+    /// Whether transactions should be sent through the manager, see `market.txn_bump_blocks`
+    pub fn enabled(&self) -> anyhow::Result<bool> {
+        let config = self.config.lock_all().context("Failed to read config")?;
+        Ok(config.market.txn_bump_blocks.is_some())
+    }
+
+    /// Send the transaction, replacing it while it is stuck, until it is included or cancelled
+    ///
+    /// Returns the receipt of the included transaction, which may have reverted.
+    pub async fn send(
+        &self,
+        tx: TransactionRequest,
+        policy: TxPolicy,
+    ) -> Result<TransactionReceipt, TxManagerErr> {
+        let bump = {
+            let config = self.config.lock_all().context("Failed to read config")?;
+            let blocks = config
+                .market
+                .txn_bump_blocks
+                .ok_or_else(|| anyhow!("market.txn_bump_blocks is not set"))?;
+            BumpConf { blocks, bps: config.market.txn_fee_bump_bps.max(MIN_FEE_BUMP_BPS) }
+        };
+
+        let tx = tx.with_from(self.sender);
+        let gas_limit = match tx.gas {
+            Some(gas_limit) => gas_limit,
+            None => {
+                let estimate = self
+                    .provider
+                    .estimate_gas(tx.clone())
+                    .await
+                    .context("Failed to estimate gas")
+                    .map_err(TxManagerErr::RpcErr)?;
+                estimate + estimate * GAS_LIMIT_PADDING_BPS / 10_000
+            }
+        };
+
+        let fees = self.suggested_fees(policy.priority_fee).await?;
+        let cost = tx_cost(gas_limit, fees.max_fee_per_gas);
+        if cost > policy.max_cost {
+            return Err(TxManagerErr::Unprofitable { cost, max_cost: policy.max_cost });
+        }
+
+        let reservation = self.reserve_nonce().await?;
+        let nonce = reservation.nonce;
+        let tx = tx.with_nonce(nonce).with_gas_limit(gas_limit);
+        self.send_with_nonce(tx, nonce, gas_limit, fees, &policy, &bump).await
+    }
+
+    async fn send_with_nonce(
+        &self,
+        tx: TransactionRequest,
+        nonce: u64,
+        gas_limit: u64,
+        mut fees: Eip1559Estimation,
+        policy: &TxPolicy,
+        bump: &BumpConf,
+    ) -> Result<TransactionReceipt, TxManagerErr> {
+        let mut hashes = Vec::new();
+        loop {
+            let sent_at = self.block_number().await?;
+            match self.broadcast(tx.clone(), &fees).await {
+                Ok(hash) => {
+                    tracing::debug!(
+                        "Broadcast tx {hash} with nonce {nonce}, max fee {} wei, priority fee {} wei",
+                        fees.max_fee_per_gas,
+                        fees.max_priority_fee_per_gas
+                    );
+                    hashes.push(hash);
+                }
+                // An earlier broadcast may still be included
+                Err(err) if !hashes.is_empty() => {
+                    tracing::warn!("Failed to re-broadcast tx with nonce {nonce}: {err:?}");
+                }
+                Err(err) => return Err(err),
+            }
+
+            if let Some(receipt) =
+                self.wait_for_inclusion(nonce, &hashes, sent_at + bump.blocks).await?
+            {
+                return Ok(receipt);
+            }
+
+            let bumped = self.bump_fees(&fees, bump.bps, policy.priority_fee).await?;
+            let cost = tx_cost(gas_limit, bumped.max_fee_per_gas);
+            let expired = policy.deadline.is_some_and(|deadline| now_timestamp() >= deadline);
+            if expired || cost > policy.max_cost {
+                tracing::warn!(
+                    "Tx with nonce {nonce} is no longer profitable, cancelling: cost {cost} wei, limit {} wei, expired: {expired}",
+                    policy.max_cost
+                );
+                return self.cancel(nonce, bumped, hashes, policy, bump).await;
+            }
+
+            tracing::info!(
+                "Tx with nonce {nonce} not included after {} blocks, re-broadcasting with max fee {} wei",
+                bump.blocks,
+                bumped.max_fee_per_gas
+            );
+            fees = bumped;
+        }
+    }
+
+    /// Free the nonce of a stuck transaction by replacing it with a zero-value transfer to self
+    ///
+    /// The cancellation is bumped until it is included, as long as it costs no more than the
+    /// policy allows for the transaction. Past that, the transactions already sent are waited on.
+    ///
+    /// Returns the receipt of the stuck transaction if it is included before the cancellation.
+    async fn cancel(
+        &self,
+        nonce: u64,
+        mut fees: Eip1559Estimation,
+        mut hashes: Vec<B256>,
+        policy: &TxPolicy,
+        bump: &BumpConf,
+    ) -> Result<TransactionReceipt, TxManagerErr> {
+        let tx = TransactionRequest::default()
+            .with_from(self.sender)
+            .with_to(self.sender)
+            .with_value(U256::ZERO)
+            .with_nonce(nonce)
+            .with_gas_limit(CANCEL_GAS_LIMIT);
+
+        let mut cancel_hashes = Vec::new();
+        loop {
+            let sent_at = self.block_number().await?;
+            let cost = tx_cost(CANCEL_GAS_LIMIT, fees.max_fee_per_gas);
+            let within_limit = cost <= policy.max_cost;
+            if !within_limit {
+                tracing::warn!(
+                    "Cancellation of tx with nonce {nonce} costs {cost} wei, over its limit of {} wei, waiting for the sent txs",
+                    policy.max_cost
+                );
+            } else {
+                match self.broadcast(tx.clone(), &fees).await {
+                    Ok(hash) => {
+                        tracing::info!("Broadcast cancellation {hash} of tx with nonce {nonce}");
+                        hashes.push(hash);
+                        cancel_hashes.push(hash);
+                    }
+                    Err(err) => {
+                        tracing::warn!("Failed to cancel tx with nonce {nonce}: {err:?}");
+                    }
+                }
+            }
+
+            if let Some(receipt) =
+                self.wait_for_inclusion(nonce, &hashes, sent_at + bump.blocks).await?
+            {
+                if cancel_hashes.contains(&receipt.transaction_hash) {
+                    return Err(TxManagerErr::Cancelled {
+                        nonce,
+                        tx_hash: receipt.transaction_hash,
+                    });
+                }
+                return Ok(receipt);
+            }
+
+            if within_limit {
+                fees = self.bump_fees(&fees, bump.bps, 0).await?;
+            }
+        }
+    }
+
+    /// Reserve the lowest nonce that is neither used on chain nor reserved by another sender
+    async fn reserve_nonce(&self) -> Result<NonceGuard<'_>, TxManagerErr> {
+        let pending_nonce = self
+            .provider
+            .get_transaction_count(self.sender)
+            .pending()
+            .await
+            .context("Failed to get pending nonce")
+            .map_err(TxManagerErr::RpcErr)?;
+
+        let nonce = self.reserved_nonces.reserve(self.sender, pending_nonce);
+        Ok(NonceGuard { nonces: &self.reserved_nonces, sender: self.sender, nonce })
+    }
+
+    /// Wait until the given block for one of the transactions sent with the nonce to be included
+    async fn wait_for_inclusion(
+        &self,
+        nonce: u64,
+        hashes: &[B256],
+        until_block: u64,
+    ) -> Result<Option<TransactionReceipt>, TxManagerErr> {
+        let poll_interval = self.provider.client().poll_interval();
+        loop {
+            if let Some(receipt) = self.find_receipt(hashes).await? {
+                return Ok(Some(receipt));
+            }
+
+            let confirmed_nonce = self
+                .provider
+                .get_transaction_count(self.sender)
+                .latest()
+                .await
+                .context("Failed to get confirmed nonce")
+                .map_err(TxManagerErr::RpcErr)?;
+            if confirmed_nonce > nonce {
+                for _ in 0..RECEIPT_RETRY_COUNT {
+                    tokio::time::sleep(poll_interval).await;
+                    if let Some(receipt) = self.find_receipt(hashes).await? {
+                        return Ok(Some(receipt));
+                    }
+                }
+                return Err(TxManagerErr::NonceConsumed(nonce));
+            }
+
+            if self.block_number().await? >= until_block {
+                return Ok(None);
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    async fn find_receipt(
+        &self,
+        hashes: &[B256],
+    ) -> Result<Option<TransactionReceipt>, TxManagerErr> {
+        for hash in hashes {
+            let receipt = self
+                .provider
+                .get_transaction_receipt(*hash)
+                .await
+                .with_context(|| format!("Failed to get receipt of tx {hash}"))
+                .map_err(TxManagerErr::RpcErr)?;
+            if receipt.is_some() {
+                return Ok(receipt);
+            }
+        }
+        Ok(None)
+    }
+
+    async fn broadcast(
+        &self,
+        tx: TransactionRequest,
+        fees: &Eip1559Estimation,
+    ) -> Result<B256, TxManagerErr> {
+        let tx = tx
+            .with_max_fee_per_gas(fees.max_fee_per_gas)
+            .with_max_priority_fee_per_gas(fees.max_priority_fee_per_gas);
+        let pending_tx = self
+            .provider
+            .send_transaction(tx)
+            .await
+            .context("Failed to broadcast tx")
+            .map_err(TxManagerErr::RpcErr)?;
+        Ok(*pending_tx.tx_hash())
+    }
+
+    async fn block_number(&self) -> Result<u64, TxManagerErr> {
+        self.provider
+            .get_block_number()
+            .await
+            .context("Failed to get block number")
+            .map_err(TxManagerErr::RpcErr)
+    }
+
+    /// Fees suggested by the RPC node, with the extra priority fee added
+    async fn suggested_fees(&self, priority_fee: u128) -> Result<Eip1559Estimation, TxManagerErr> {
+        let fees = self
+            .provider
+            .estimate_eip1559_fees()
+            .await
+            .context("Failed to estimate fees")
+            .map_err(TxManagerErr::RpcErr)?;
+        Ok(Eip1559Estimation {
+            max_fee_per_gas: fees.max_fee_per_gas.saturating_add(priority_fee),
+            max_priority_fee_per_gas: fees.max_priority_fee_per_gas.saturating_add(priority_fee),
+        })
+    }
+
+    /// Fees of a replacement, at least `bump_bps` over the given fees and the suggested fees
+    async fn bump_fees(
+        &self,
+        fees: &Eip1559Estimation,
+        bump_bps: u64,
+        priority_fee: u128,
+    ) -> Result<Eip1559Estimation, TxManagerErr> {
+        let suggested = self.suggested_fees(priority_fee).await?;
+        Ok(Eip1559Estimation {
+            max_fee_per_gas: bump_fee(fees.max_fee_per_gas, bump_bps)
+                .max(suggested.max_fee_per_gas),
+            max_priority_fee_per_gas: bump_fee(fees.max_priority_fee_per_gas, bump_bps)
+                .max(suggested.max_priority_fee_per_gas),
+        })
+    }
+}
+
+fn bump_fee(fee: u128, bump_bps: u64) -> u128 {
+    fee.saturating_add((fee.saturating_mul(bump_bps as u128) / 10_000).max(1))
+}
+
+fn tx_cost(gas_limit: u64, max_fee_per_gas: u128) -> U256 {
+    U256::from(gas_limit) * U256::from(max_fee_per_gas)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use alloy::{
+        consensus::Transaction,
+        network::EthereumWallet,
+        node_bindings::{Anvil, AnvilInstance},
+        providers::{
+            ext::AnvilApi,
+            fillers::{ChainIdFiller, GasFiller},
+            ProviderBuilder,
+        },
+        signers::local::PrivateKeySigner,
+    };
+    use boundless_market::nonce_layer::NonceProvider;
+
+    use super::*;
+
+    async fn setup() -> (AnvilInstance, Arc<impl Provider + WalletProvider + AnvilApi<Ethereum>>) {
+        // Blocks are only mined on request, to control when transactions are included.
+        let anvil = Anvil::new().arg("--no-mining").spawn();
+        let signer: PrivateKeySigner = anvil.keys()[0].clone().into();
+        let provider = Arc::new(
+            ProviderBuilder::new()
+                .wallet(EthereumWallet::from(signer))
+                .connect(&anvil.endpoint())
+                .await
+                .unwrap(),
+        );
+        (anvil, provider)
+    }
+
+    fn manager<P: Provider + WalletProvider>(provider: Arc<P>) -> Arc<TxManager<P>> {
+        let config = ConfigLock::default();
+        config.load_write().unwrap().market.txn_bump_blocks = Some(1);
+        Arc::new(TxManager::new(provider, config, Default::default()))
+    }
+
+    fn transfer(recipient: Address) -> TransactionRequest {
+        TransactionRequest::default()
+            .with_to(recipient)
+            .with_value(U256::from(1))
+            .with_gas_limit(CANCEL_GAS_LIMIT)
+    }
+
+    /// Wait for a transaction of the sender to enter the mempool, then mine a block
+    async fn mine_next_tx(provider: &(impl Provider + AnvilApi<Ethereum>), sender: Address) {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while provider.get_transaction_count(sender).pending().await.unwrap()
+                == provider.get_transaction_count(sender).latest().await.unwrap()
+            {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("No transaction broadcast");
+        provider.anvil_mine(Some(1), None).await.unwrap();
+    }
+
+    /// Drop the transactions in the mempool, as if they were stuck, and mine an empty block
+    async fn drop_next_tx(provider: &(impl Provider + AnvilApi<Ethereum>), sender: Address) {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while provider.get_transaction_count(sender).pending().await.unwrap() == 0 {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("No transaction broadcast");
+        provider.anvil_drop_all_transactions().await.unwrap();
+        provider.anvil_mine(Some(1), None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn replaces_stuck_transaction() {
+        let (_anvil, provider) = setup().await;
+        let sender = provider.default_signer_address();
+        let manager = manager(provider.clone());
+        let recipient = Address::repeat_byte(0x11);
+        let initial_fees = provider.estimate_eip1559_fees().await.unwrap();
+
+        let policy = TxPolicy { max_cost: U256::MAX, deadline: None, priority_fee: 0 };
+        let send = tokio::spawn({
+            let manager = manager.clone();
+            async move { manager.send(transfer(recipient), policy).await }
+        });
+
+        drop_next_tx(&provider, sender).await;
+        mine_next_tx(&provider, sender).await;
+
+        let receipt = send.await.unwrap().unwrap();
+        assert!(receipt.status());
+        let tx = provider.get_transaction_by_hash(receipt.transaction_hash).await.unwrap().unwrap();
+        assert_eq!(tx.nonce(), 0);
+        assert!(tx.max_fee_per_gas() > initial_fees.max_fee_per_gas);
+        assert_eq!(provider.get_balance(recipient).await.unwrap(), U256::from(1));
+        assert!(!manager.reserved_nonces.is_reserved(manager.sender));
+    }
+
+    #[tokio::test]
+    async fn cancels_unprofitable_transaction() {
+        let (_anvil, provider) = setup().await;
+        let sender = provider.default_signer_address();
+        let manager = manager(provider.clone());
+        let recipient = Address::repeat_byte(0x11);
+        let initial_fees = provider.estimate_eip1559_fees().await.unwrap();
+
+        // Only the initial fees are worth paying, so the first bump cancels the transaction. The
+        // cancellation uses less gas, so it fits within the same cost.
+        let gas_limit = 2 * CANCEL_GAS_LIMIT;
+        let max_cost = tx_cost(gas_limit, initial_fees.max_fee_per_gas);
+        let policy = TxPolicy { max_cost, deadline: None, priority_fee: 0 };
+        let send = tokio::spawn({
+            let manager = manager.clone();
+            async move { manager.send(transfer(recipient).with_gas_limit(gas_limit), policy).await }
+        });
+
+        drop_next_tx(&provider, sender).await;
+        mine_next_tx(&provider, sender).await;
+
+        let err = send.await.unwrap().unwrap_err();
+        let TxManagerErr::Cancelled { nonce, tx_hash } = err else {
+            panic!("Expected a cancelled transaction, got {err:?}");
+        };
+        assert_eq!(nonce, 0);
+        let tx = provider.get_transaction_by_hash(tx_hash).await.unwrap().unwrap();
+        assert_eq!(tx.to(), Some(sender));
+        assert_eq!(tx.value(), U256::ZERO);
+        assert_eq!(provider.get_transaction_count(sender).latest().await.unwrap(), 1);
+        assert_eq!(provider.get_balance(recipient).await.unwrap(), U256::ZERO);
+        assert!(!manager.reserved_nonces.is_reserved(manager.sender));
+    }
+
+    #[tokio::test]
+    async fn rejects_unprofitable_transaction() {
+        let (_anvil, provider) = setup().await;
+        let sender = provider.default_signer_address();
+        let manager = manager(provider.clone());
+
+        let policy = TxPolicy { max_cost: U256::from(1), deadline: None, priority_fee: 0 };
+        let err = manager.send(transfer(Address::repeat_byte(0x11)), policy).await.unwrap_err();
+        assert!(matches!(err, TxManagerErr::Unprofitable { .. }));
+        assert_eq!(provider.get_transaction_count(sender).pending().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn unmanaged_sends_skip_reserved_nonces() {
+        let anvil = Anvil::new().arg("--no-mining").spawn();
+        let signer: PrivateKeySigner = anvil.keys()[0].clone().into();
+        let base_provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .filler(ChainIdFiller::default())
+            .filler(GasFiller)
+            .connect(&anvil.endpoint())
+            .await
+            .unwrap();
+        let provider = Arc::new(NonceProvider::new(base_provider, EthereumWallet::from(signer)));
+        let sender = provider.default_signer_address();
+        let manager =
+            TxManager::new(provider.clone(), ConfigLock::default(), provider.reserved_nonces());
+
+        // Reserved by the manager, before its transaction is broadcast
+        let reservation = manager.reserve_nonce().await.unwrap();
+        assert_eq!(reservation.nonce, 0);
+
+        let pending = provider.send_transaction(transfer(sender)).await.unwrap();
+        let tx = provider.get_transaction_by_hash(*pending.tx_hash()).await.unwrap().unwrap();
+        assert_eq!(tx.nonce(), 1);
+
+        drop(reservation);
+        assert!(!manager.reserved_nonces.is_reserved(sender));
+    }
+}
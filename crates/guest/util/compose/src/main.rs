@@ -0,0 +1,31 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verify the claim of an image ID and journal through composition and commit to the journal.
+//!
+//! The receipt for the claim must be provided as an assumption.
+
+#![no_main]
+
+use risc0_zkvm::{guest::env, sha::Digest};
+
+risc0_zkvm::guest::entry!(main);
+
+pub fn main() {
+    let (image_id, journal): (Digest, Vec<u8>) = env::read();
+
+    env::verify(image_id, &journal).unwrap();
+
+    env::commit_slice(&journal);
+}
@@ -300,6 +300,12 @@ export const createProverAlarms = (
     threshold: 2,
   }, { period: 3600 });
 
+  // An order dropped from aggregation because its receipt or journal is missing or invalid.
+  // Also indicates a slashed order, and a fault with the prover when it happens repeatedly.
+  createErrorCodeAlarm('"[B-AGG-601]"', 'aggregator-order-proof-invalid', Severity.SEV2, {
+    threshold: 2,
+  }, { period: 3600 });
+
   //
   // Proving engine
   //
@@ -351,6 +357,12 @@ export const createProverAlarms = (
     threshold: 5,
   }, { period: 3600 });
 
+  // 3 orders dropped from batches within 1 hour because their fulfillments revert triggers a SEV2 alarm.
+  // Occasional drops are expected when another prover fulfills a request first.
+  createErrorCodeAlarm('"[B-SUB-007]"', 'submitter-order-dropped-from-batch', Severity.SEV2, {
+    threshold: 3,
+  }, { period: 3600 });
+
   // Any 1 unexpected error in the submitter triggers a SEV2 alarm.
   createErrorCodeAlarm('"[B-SUB-500]"', 'submitter-unexpected-error', Severity.SEV2);
 
@@ -359,6 +371,30 @@ export const createProverAlarms = (
     threshold: 3,
   }, { period: 300 });
 
+  //
+  // Transaction manager
+  //
+  // 3 transactions cancelled within 1 hour triggers a SEV2 alarm. Fee bumps exceeding the revenue
+  // of the order or batch may indicate a congested chain or underpriced orders.
+  createErrorCodeAlarm('"[B-TXM-002]"', 'tx-manager-txn-cancelled', Severity.SEV2, {
+    threshold: 3,
+  }, { period: 3600 });
+
+  // Any 1 nonce used by another sender triggers a SEV2 alarm. The prover key should only be used
+  // by one broker.
+  createErrorCodeAlarm('"[B-TXM-003]"', 'tx-manager-nonce-consumed', Severity.SEV2);
+
+  //
+  // Treasury
+  //
+  // Any 1 invalid treasury config triggers a SEV2 alarm. No stake is deposited or earnings swept
+  // until it is fixed.
+  createErrorCodeAlarm('"[B-TRS-003]"', 'treasury-invalid-config', Severity.SEV2);
+
+  // Any 1 failed stake deposit, gas top up or sweep triggers a SEV2 alarm. A failed sweep may leave
+  // the withdrawn earnings in the broker wallet.
+  createErrorCodeAlarm('"[B-TRS-004]"', 'treasury-action-failed', Severity.SEV2);
+
   //
   // Reaper
   //